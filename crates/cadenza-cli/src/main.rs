@@ -0,0 +1,133 @@
+use cadenza_core::{load_midi_script, Command, HeadlessRunner, ScoreSource, SilentSynth};
+use cadenza_infra_storage_fs::FsStorage;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+
+const USAGE: &str = "usage:
+  cadenza-cli convert <input.mid|.musicxml|.mxl|.cadenza> <output.mid|.cadenza>
+  cadenza-cli practice-sim <score> <script.json> [max-seconds]
+  cadenza-cli export-diagnostics <output-dir>";
+const DEFAULT_MAX_PRACTICE_SECS: u64 = 3600;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["convert", input, output] => convert(input, output),
+        ["practice-sim", score, script] => practice_sim(score, script, DEFAULT_MAX_PRACTICE_SECS),
+        ["practice-sim", score, script, max_secs] => match max_secs.parse() {
+            Ok(max_secs) => practice_sim(score, script, max_secs),
+            Err(_) => Err(format!("bad max-seconds {max_secs:?}")),
+        },
+        ["export-diagnostics", output] => export_diagnostics(output),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("cadenza-cli: {message}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Settings directory for one run, removed again when dropped.
+struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    fn new() -> Self {
+        Self(std::env::temp_dir().join(format!("cadenza-cli-{}", std::process::id())))
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A runner whose settings live in `scratch`, leaving the app's own alone.
+fn runner(scratch: &ScratchDir) -> Result<HeadlessRunner, String> {
+    HeadlessRunner::new(
+        Arc::new(SilentSynth),
+        Some(Box::new(FsStorage::new(scratch.0.clone()))),
+    )
+    .map_err(|e| e.to_string())
+}
+
+fn lowercase_extension(path: &str) -> Option<String> {
+    Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase)
+}
+
+fn score_source(path: &str) -> Result<ScoreSource, String> {
+    match lowercase_extension(path).as_deref() {
+        Some("mid" | "midi") => Ok(ScoreSource::MidiFile(path.to_string())),
+        Some("xml" | "musicxml" | "mxl") => Ok(ScoreSource::MusicXmlFile {
+            path: path.to_string(),
+            parts: Default::default(),
+        }),
+        Some("cadenza") => Ok(ScoreSource::CadenzaFile(path.to_string())),
+        _ => Err(format!("unknown score format: {path}")),
+    }
+}
+
+fn convert(input: &str, output: &str) -> Result<(), String> {
+    let scratch = ScratchDir::new();
+    let mut runner = runner(&scratch)?;
+    runner
+        .load_score(score_source(input)?)
+        .map_err(|e| e.to_string())?;
+    let path = output.to_string();
+    let command = match lowercase_extension(output).as_deref() {
+        Some("cadenza") => Command::SaveScoreFile { path },
+        Some("mid" | "midi") => Command::ExportMidiRange {
+            path,
+            start_tick: None,
+            end_tick: None,
+            tempo_multiplier: None,
+        },
+        _ => return Err(format!("unknown output format: {output}")),
+    };
+    runner
+        .core()
+        .handle_command(command)
+        .map_err(|e| e.to_string())
+}
+
+fn practice_sim(score: &str, script: &str, max_secs: u64) -> Result<(), String> {
+    let script = load_midi_script(Path::new(script)).map_err(|e| e.to_string())?;
+    let scratch = ScratchDir::new();
+    let mut runner = runner(&scratch)?;
+    runner
+        .load_score(score_source(score)?)
+        .map_err(|e| e.to_string())?;
+    let report = runner
+        .practice(&script, Duration::from_secs(max_secs))
+        .map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+    println!("{json}");
+    Ok(())
+}
+
+fn export_diagnostics(output: &str) -> Result<(), String> {
+    let scratch = ScratchDir::new();
+    let mut runner = runner(&scratch)?;
+    runner
+        .core()
+        .handle_command(Command::ExportDiagnostics {
+            path: output.to_string(),
+        })
+        .map_err(|e| e.to_string())
+}
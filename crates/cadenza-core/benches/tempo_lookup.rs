@@ -0,0 +1,68 @@
+use cadenza_core::{TempoCursor, Transport};
+use cadenza_domain_score::TempoPoint;
+use cadenza_ports::types::Tick;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+const PPQ: u16 = 480;
+const TEMPO_POINTS: usize = 500;
+const EVENTS: usize = 50_000;
+
+/// A score with a tempo change every 100 events, as rubato MIDI exports have.
+fn tempo_points() -> Vec<TempoPoint> {
+    let spacing = (EVENTS / TEMPO_POINTS) as Tick * 120;
+    (0..TEMPO_POINTS)
+        .map(|idx| TempoPoint {
+            tick: idx as Tick * spacing,
+            us_per_quarter: 450_000 + (idx as u32 % 7) * 25_000,
+        })
+        .collect()
+}
+
+/// The lookup as it was: a walk over every tempo change for each event.
+fn linear_tick_to_micros(points: &[TempoPoint], tick: Tick) -> i64 {
+    let ticks_to_us = |ticks: Tick, us_per_quarter: u32| {
+        (i128::from(ticks) * i128::from(us_per_quarter) / i128::from(PPQ)) as i64
+    };
+    let mut start_us = 0;
+    let mut current = points[0];
+    for pair in points.windows(2) {
+        if pair[1].tick > tick {
+            break;
+        }
+        start_us += ticks_to_us(pair[1].tick - pair[0].tick, pair[0].us_per_quarter);
+        current = pair[1];
+    }
+    start_us + ticks_to_us(tick - current.tick, current.us_per_quarter)
+}
+
+fn tempo_lookup(c: &mut Criterion) {
+    let points = tempo_points();
+    let transport = Transport::new(PPQ, 48_000, points.clone());
+    let ticks: Vec<Tick> = (0..EVENTS as Tick).map(|idx| idx * 120).collect();
+
+    c.bench_function("50k events, linear walk", |b| {
+        b.iter(|| {
+            for &tick in &ticks {
+                black_box(linear_tick_to_micros(&points, tick));
+            }
+        })
+    });
+    c.bench_function("50k events, binary search", |b| {
+        b.iter(|| {
+            for &tick in &ticks {
+                black_box(transport.tick_to_sample(tick));
+            }
+        })
+    });
+    c.bench_function("50k events, carried cursor", |b| {
+        b.iter(|| {
+            let mut cursor = TempoCursor::default();
+            for &tick in &ticks {
+                black_box(transport.tick_to_sample_from(tick, &mut cursor));
+            }
+        })
+    });
+}
+
+criterion_group!(benches, tempo_lookup);
+criterion_main!(benches);
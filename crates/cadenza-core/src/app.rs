@@ -1,33 +1,85 @@
-use crate::audio_graph::{AudioClock, AudioGraph};
+use crate::audio_graph::{
+    AudioCallbackStats, AudioClock, AudioGraph, LateEventCounts, LateEventStats, SynthLoad,
+};
+use crate::audio_meters::{AudioLevels, AudioMeters};
 use crate::audio_params::AudioParams;
-use crate::diagnostics::export_diagnostics;
+use crate::audio_recorder::{audio_recorder, AudioRecorder, RecorderError};
+use crate::audio_self_test::{AudioSelfTest, SelfTestStatus, SelfTestTone};
+use crate::clock::{Clock, SystemClock};
+use crate::clock_stats::ClockStats;
+use crate::demos::{build_demo_score, internal_demos};
+use crate::diag_log;
+use crate::diagnostics::{
+    export_diagnostics, DiagnosticsSnapshot, QueueDropCounts, SynthStatus, TransportSnapshot,
+};
+use crate::follow::FollowMode;
 use crate::ipc::{
-    Command, Event, PianoRollNoteDto, PianoRollPedalDto, PianoRollTargetDto, ScoreSource,
-    SessionState,
+    Command, Event, EventQueue, GridLineDto, GridLineKind, PianoRollNoteDto, PianoRollPedalDto,
+    PianoRollTargetDto, ScoreSource, SessionState, SetupStep, EVENT_QUEUE_CAPACITY,
 };
-use crate::scheduler::{Scheduler, SchedulerConfig};
-use crate::transport::Transport;
+use crate::midi_capture::{CapturedEvent, MidiCapture, MAX_MIDI_CAPTURE_SECS};
+use crate::null_audio::{null_audio_device, NullAudioOutputPort, NULL_AUDIO_DEVICE_ID};
+use crate::pdf_job::{default_export_dir, resolve_output_path, PdfJob, PdfJobRequest};
+use crate::quality_governor::QualityGovernor;
+use crate::scheduler::{AutopilotFeel, Scheduler, SchedulerConfig, DEFAULT_RESOUND_VELOCITY_SCALE};
+use crate::session_snapshot::{unix_ms_now, SessionSnapshot, SESSION_AUTOSAVE_INTERVAL};
+use crate::transport::{TimeSignatureMap, Transport};
+use crate::upcoming::UpcomingTargets;
 use cadenza_domain_eval::{
     AdvanceMode, ChordRollTicks, Grade, Judge, JudgeConfig, JudgeEvent, PlayerNoteOn,
-    TimingWindowTicks, WrongNotePolicy,
+    TimingWindowMs, TimingWindowTicks, WrongNotePolicy,
 };
 use cadenza_domain_score::{
-    export_midi_path, import_midi_path, import_musicxml_path, Score, TargetEvent,
+    analyze_score, export_midi_range, import_midi_path_with_options,
+    import_musicxml_path_with_report, load_scorefile_path, save_scorefile_path, set_notes_hand,
+    swap_hands, MidiImportOptions, MusicXmlImportOptions, NoteSelection, Score, ScoreFile,
+    ScoreSoundOverrides, TargetEvent,
 };
+use cadenza_domain_score::{key_signature_at, note_on_spelling, spell, KeySignaturePoint, NoteId};
 use cadenza_ports::audio::{AudioError, AudioOutputPort, AudioRenderCallback, AudioStreamHandle};
-use cadenza_ports::midi::{MidiError, MidiInputPort, MidiInputStream, MidiLikeEvent, PlayerEvent};
-use cadenza_ports::omr::{OmrOptions, OmrPort};
+use cadenza_ports::midi::{
+    MidiAction, MidiControl, MidiError, MidiInputPort, MidiInputStream, MidiLikeEvent, MidiMapping,
+    PlayerEvent, ALL_NOTES_OFF,
+};
+use cadenza_ports::omr::{OmrError, OmrPort};
 use cadenza_ports::playback::{LoopRange, ScheduledEvent};
 use cadenza_ports::storage::{SettingsDto, StorageError, StoragePort};
-use cadenza_ports::synth::{SynthError, SynthPort};
-use cadenza_ports::types::{AudioConfig, Bus, DeviceId, SampleTime, Tick};
+use cadenza_ports::synth::{
+    QualityHint, SoundFontInfo, SynthError, SynthPort, MAX_DECAY_SCALE, MIN_DECAY_SCALE,
+};
+use cadenza_ports::types::{
+    AudioConfig, AudioOutputDevice, Bus, DeviceId, MidiInputDevice, SampleTime, Tick, Volume01,
+};
 use parking_lot::Mutex;
 use rtrb::{Consumer, Producer, RingBuffer};
-use std::collections::{HashMap, VecDeque};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+/// Fraction of the previous meter reading kept at each ~15 Hz level update.
+const METER_DECAY: f32 = 0.7;
+/// Settings changes are written at most this often, so dragging a fader does not hammer
+/// the disk.
+const SETTINGS_SAVE_INTERVAL: Duration = Duration::from_millis(500);
+/// Time given to the master gain to ramp down before the output closes on shutdown.
+const SHUTDOWN_FADE: Duration = Duration::from_millis(50);
+/// Tempo multiplier change per `TempoUp`/`TempoDown` press of a mapped control.
+const MIDI_TEMPO_STEP: f32 = 0.05;
+/// Tries at opening an output that reports itself busy, and the longest wait between them.
+const AUDIO_OPEN_ATTEMPTS: u32 = 3;
+const AUDIO_OPEN_MAX_RETRY_WAIT: Duration = Duration::from_secs(2);
+/// How often the open MIDI input is looked for among the listed devices, and how often a
+/// lost one is tried again.
+const MIDI_INPUT_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+const MIDI_INPUT_RETRY_INTERVAL: Duration = Duration::from_secs(3);
+/// Synth load, in percent of the realtime budget, that warns once it has lasted this many
+/// once-a-second checks in a row.
+const SYNTH_LOAD_WARN_PERCENT: f32 = 80.0;
+const SYNTH_LOAD_WARN_SECS: u32 = 3;
+
 #[derive(thiserror::Error, Debug)]
 pub enum AppError {
     #[error("audio error: {0}")]
@@ -35,7 +87,7 @@ pub enum AppError {
     #[error("midi error: {0}")]
     Midi(#[from] MidiError),
     #[error("omr error: {0}")]
-    Omr(#[from] cadenza_ports::omr::OmrError),
+    Omr(#[from] OmrError),
     #[error("synth error: {0}")]
     Synth(#[from] SynthError),
     #[error("storage error: {0}")]
@@ -44,32 +96,137 @@ pub enum AppError {
     InvalidState(String),
     #[error("score load failed: {0}")]
     ScoreLoad(String),
+    #[error("score save failed: {0}")]
+    ScoreSave(String),
+    #[error("recording error: {0}")]
+    Recording(#[from] RecorderError),
+}
+
+impl AppError {
+    /// False when a backend itself failed; the user can fix anything else by retrying with
+    /// another file, device or value.
+    pub fn is_recoverable(&self) -> bool {
+        !matches!(
+            self,
+            AppError::Audio(AudioError::Backend(_))
+                | AppError::Midi(MidiError::Backend(_))
+                | AppError::Omr(OmrError::Backend(_))
+                | AppError::Synth(SynthError::Backend(_))
+        )
+    }
 }
 
 pub struct AppCore {
     audio_port: Box<dyn AudioOutputPort>,
     midi_port: Box<dyn MidiInputPort>,
     synth: Arc<dyn SynthPort>,
-    omr: Option<Box<dyn OmrPort>>,
+    omr: Option<Arc<dyn OmrPort>>,
+    pdf_job: Option<PdfJob>,
+    /// Where PDF conversions write when not told otherwise.
+    export_dir: PathBuf,
     storage: Option<Box<dyn StoragePort>>,
     settings: SettingsDto,
     session_state: SessionState,
     transport: Transport,
     scheduler: Scheduler,
     judge: Judge,
+    /// Transport loop wraps the judge has been moved back for.
+    judged_loop_wraps: u64,
+    /// Tempo multiplier the judge's windows were last converted at.
+    judge_windows_multiplier: f32,
+    /// Transport tick at `PausePractice`, cleared by anything that moves the transport.
+    paused_tick: Option<Tick>,
+    /// Set while hits steer the transport.
+    follow: Option<FollowMode>,
+    clock: Arc<dyn Clock>,
     score: Option<Score>,
+    /// Where `score` was loaded from, recorded in session snapshots.
+    score_source: Option<ScoreSource>,
+    /// Offered by `Event::RecoveredSession` until `ResumeRecoveredSession` takes it.
+    recovered_session: Option<SessionSnapshot>,
+    /// A snapshot of the running session was written and not yet cleared.
+    session_autosaved: bool,
+    last_session_autosave: Instant,
+    /// Tracks picked by `SelectScoreTracks`; empty for all of them.
+    score_track_ids: Vec<u32>,
+    /// Edit history of the loaded project, written back by `SaveScoreFile`.
+    score_edit_log: Vec<String>,
+    score_sound: ScoreSoundOverrides,
+    /// Edited since the last load or save.
+    score_dirty: bool,
+    score_end_tick: Tick,
     targets: HashMap<u64, TargetEvent>,
+    upcoming_targets: UpcomingTargets,
+    /// Spelled name of every note of the practice track, for judge feedback.
+    note_names: HashMap<NoteId, String>,
     audio_params: Arc<AudioParams>,
     audio_clock: Arc<AudioClock>,
+    audio_meters: Arc<AudioMeters>,
+    audio_levels: AudioLevels,
+    unreported_levels: AudioLevels,
+    audio_self_test: Option<AudioSelfTest>,
+    midi_capture: Option<MidiCapture>,
+    late_event_stats: Arc<LateEventStats>,
+    late_event_counts: LateEventCounts,
+    callback_stats: Arc<AudioCallbackStats>,
+    logged_overruns: u64,
+    /// Stats checks in a row the synth load has been over [`SYNTH_LOAD_WARN_PERCENT`].
+    synth_overload_checks: u32,
+    quality_governor: QualityGovernor,
+    audio_queue_drops: u64,
+    /// `audio_queue_drops` at the last release of the scheduler's notes.
+    audio_queue_drops_at_release: u64,
+    /// Set when the scheduler is rebuilt and forgets the notes the old one sent.
+    sent_notes_lost: bool,
+    /// Stamped on events sent to the audio thread; see [`AppCore::invalidate_queued_audio`].
+    event_generation: u32,
+    midi_queue_drops: Arc<AtomicU64>,
+    synth_status: SynthStatus,
+    audio_recorder: Option<AudioRecorder>,
     audio_stream: Option<Box<dyn AudioStreamHandle>>,
+    /// Device and config of `audio_stream`.
+    audio_output: Option<(DeviceId, AudioConfig)>,
     audio_queue_tx: Option<Producer<ScheduledEvent>>,
     midi_stream: Option<Box<dyn MidiInputStream>>,
     midi_queue_rx: Option<Consumer<PlayerEvent>>,
-    events: VecDeque<Event>,
+    /// Input that disappeared while open, reopened once it is listed again.
+    lost_midi_input: Option<DeviceId>,
+    /// Port name of the open or lost input. Backends may number ports by position, so the
+    /// id changes when another device comes or goes while the name stays.
+    midi_input_name: Option<String>,
+    /// Output that was busy when opened, tried again from `tick` once its wait is over.
+    pending_audio_open: Option<PendingAudioOpen>,
+    last_midi_input_check: Instant,
+    events: EventQueue,
     recent_inputs: VecDeque<MidiLikeEvent>,
     last_transport_emit: Instant,
     last_input_emit: Instant,
+    /// Latest input per key or controller since the last `MidiInputEvent`s went out, plus the
+    /// press before a pending release.
+    pending_input_events: Vec<MidiLikeEvent>,
+    last_input_event_emit: Instant,
+    last_levels_emit: Instant,
+    last_recording_emit: Instant,
     clock_anchor: Option<ClockAnchor>,
+    clock_stats: ClockStats,
+    last_stats_emit: Instant,
+    settings_dirty: bool,
+    last_settings_save: Instant,
+    shut_down: bool,
+    /// Action the next pressed MIDI control gets bound to.
+    midi_learn: Option<MidiAction>,
+    /// Loop turned off by a mapped `ToggleLoop`, restored by the next one.
+    toggled_off_loop: Option<LoopRange>,
+    /// First-run setup steps not yet completed.
+    setup_pending: Vec<SetupStep>,
+}
+
+/// A busy output's next open, with the arguments of the one that found it busy.
+struct PendingAudioOpen {
+    device_id: DeviceId,
+    config: Option<AudioConfig>,
+    attempt: u32,
+    retry_at: Instant,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -92,81 +249,164 @@ impl AppCore {
             SettingsDto::default()
         };
 
-        let mut bootstrap_events = VecDeque::new();
+        let mut bootstrap_events = EventQueue::new(EVENT_QUEUE_CAPACITY);
+        let recovered_session = storage
+            .as_ref()
+            .and_then(|storage| take_session_snapshot(storage.as_ref()));
+        if let Some(snapshot) = recovered_session.clone() {
+            bootstrap_events.push_back(Event::RecoveredSession { snapshot });
+        }
+        let mut synth_status = SynthStatus::default();
         if let Some(path) = settings.default_sf2_path.clone() {
-            match synth.load_soundfont_from_path(&path) {
-                Ok(info) => bootstrap_events.push_back(Event::SoundFontStatus {
-                    loaded: true,
-                    path: Some(path),
-                    name: Some(info.name),
-                    preset_count: Some(info.preset_count as u32),
-                    message: None,
-                }),
-                Err(err) => bootstrap_events.push_back(Event::SoundFontStatus {
-                    loaded: false,
-                    path: Some(path),
-                    name: None,
-                    preset_count: None,
-                    message: Some(err.to_string()),
-                }),
+            let result = synth.load_soundfont_from_path(&path);
+            synth_status = soundfont_status(&path, &result);
+            bootstrap_events.push_back(soundfont_status_event(&synth_status));
+            if let Err(err) = result {
+                bootstrap_events
+                    .push_back(command_failed_event("LoadSoundFont", &AppError::from(err)));
             }
         }
+        apply_bus_programs(synth.as_ref(), &settings.bus_programs);
+        synth.set_decay_scale(settings.decay_scale);
 
         let audio_params = Arc::new(AudioParams::new(&settings));
         let audio_clock = Arc::new(AudioClock::new());
+        let audio_meters = Arc::new(AudioMeters::new());
 
         let transport = Transport::new(480, 48_000, Vec::new());
-        let scheduler = Scheduler::new(48_000, SchedulerConfig { lookahead_ms: 30 });
+        let scheduler = Scheduler::new(
+            48_000,
+            SchedulerConfig {
+                lookahead_ms: 30,
+                buffer_frames: None,
+            },
+        );
         let judge = Judge::new(default_judge_config());
 
         Ok(Self {
             audio_port,
             midi_port,
             synth,
-            omr,
+            omr: omr.map(Arc::from),
+            pdf_job: None,
+            export_dir: default_export_dir(),
             storage,
             settings,
             session_state: SessionState::Idle,
             transport,
             scheduler,
             judge,
+            judged_loop_wraps: 0,
+            judge_windows_multiplier: 1.0,
+            paused_tick: None,
+            follow: None,
+            clock: Arc::new(SystemClock),
             score: None,
+            score_source: None,
+            recovered_session,
+            session_autosaved: false,
+            last_session_autosave: Instant::now(),
+            score_track_ids: Vec::new(),
+            score_edit_log: Vec::new(),
+            score_sound: ScoreSoundOverrides::default(),
+            score_dirty: false,
+            score_end_tick: 0,
             targets: HashMap::new(),
+            upcoming_targets: UpcomingTargets::default(),
+            note_names: HashMap::new(),
             audio_params,
             audio_clock,
+            audio_meters,
+            audio_levels: AudioLevels::default(),
+            unreported_levels: AudioLevels::default(),
+            audio_self_test: None,
+            midi_capture: None,
+            late_event_stats: Arc::new(LateEventStats::new()),
+            late_event_counts: LateEventCounts::default(),
+            callback_stats: Arc::new(AudioCallbackStats::new()),
+            logged_overruns: 0,
+            synth_overload_checks: 0,
+            quality_governor: QualityGovernor::new(),
+            audio_queue_drops: 0,
+            audio_queue_drops_at_release: 0,
+            sent_notes_lost: false,
+            event_generation: 0,
+            midi_queue_drops: Arc::new(AtomicU64::new(0)),
+            synth_status,
+            audio_recorder: None,
             audio_stream: None,
+            audio_output: None,
             audio_queue_tx: None,
             midi_stream: None,
             midi_queue_rx: None,
+            lost_midi_input: None,
+            midi_input_name: None,
+            pending_audio_open: None,
+            last_midi_input_check: Instant::now(),
             events: bootstrap_events,
             recent_inputs: VecDeque::with_capacity(32),
             last_transport_emit: Instant::now(),
             last_input_emit: Instant::now(),
+            pending_input_events: Vec::new(),
+            last_input_event_emit: Instant::now(),
+            last_levels_emit: Instant::now(),
+            last_recording_emit: Instant::now(),
             clock_anchor: None,
+            clock_stats: ClockStats::new(48_000),
+            last_stats_emit: Instant::now(),
+            settings_dirty: false,
+            last_settings_save: Instant::now(),
+            shut_down: false,
+            midi_learn: None,
+            toggled_off_loop: None,
+            setup_pending: Vec::new(),
         })
     }
 
     pub fn handle_command(&mut self, cmd: Command) -> Result<(), AppError> {
+        let command_kind = cmd.kind();
+        let result = self.dispatch_command(cmd);
+        if let Err(err) = &result {
+            diag_log!(Error, "command failed: {err}");
+            self.push_command_failed(command_kind, err);
+        }
+        result
+    }
+
+    fn push_command_failed(&mut self, command_kind: &str, err: &AppError) {
+        self.events
+            .push_back(command_failed_event(command_kind, err));
+    }
+
+    fn dispatch_command(&mut self, cmd: Command) -> Result<(), AppError> {
         match cmd {
             Command::GetSessionState => {
                 self.emit_session_state();
                 self.emit_transport(true);
             }
             Command::ListMidiInputs => {
-                let devices = self.midi_port.list_inputs()?;
-                self.events.push_back(Event::MidiInputsUpdated { devices });
+                self.emit_midi_inputs()?;
             }
             Command::SelectMidiInput { device_id } => {
                 self.open_midi_input(device_id)?;
             }
             Command::ListAudioOutputs => {
-                let devices = self.audio_port.list_outputs()?;
-                self.events
-                    .push_back(Event::AudioOutputsUpdated { devices });
+                self.emit_audio_outputs()?;
+            }
+            Command::ListInternalDemos => {
+                self.events.push_back(Event::InternalDemosListed {
+                    demos: internal_demos(),
+                });
             }
             Command::SelectAudioOutput { device_id, config } => {
                 self.open_audio_output(device_id, config)?;
             }
+            Command::RefreshDevices => {
+                self.refresh_devices()?;
+            }
+            Command::SetAudioBufferSize { frames } => {
+                self.set_audio_buffer_size(frames)?;
+            }
             Command::TestAudio => {
                 self.test_audio()?;
             }
@@ -192,31 +432,77 @@ impl AppCore {
                 self.emit_session_state();
                 self.save_settings();
             }
-            Command::LoadSoundFont { path } => match self.synth.load_soundfont_from_path(&path) {
-                Ok(info) => {
-                    self.settings.default_sf2_path = Some(path.clone());
-                    self.save_settings();
-                    self.events.push_back(Event::SoundFontStatus {
-                        loaded: true,
-                        path: Some(path),
-                        name: Some(info.name),
-                        preset_count: Some(info.preset_count as u32),
-                        message: None,
-                    });
-                }
-                Err(err) => {
-                    self.events.push_back(Event::SoundFontStatus {
-                        loaded: false,
-                        path: Some(path),
-                        name: None,
-                        preset_count: None,
-                        message: Some(err.to_string()),
-                    });
-                    return Err(err.into());
-                }
-            },
+            Command::SetVolumeCurve { curve } => {
+                self.settings.convert_volume_curve(curve);
+                self.audio_params.set_volume_curve(curve);
+                self.audio_params.set_master(self.settings.master_volume);
+                self.audio_params
+                    .set_bus(Bus::UserMonitor, self.settings.bus_user_volume);
+                self.audio_params
+                    .set_bus(Bus::Autopilot, self.settings.bus_autopilot_volume);
+                self.audio_params
+                    .set_bus(Bus::MetronomeFx, self.settings.bus_metronome_volume);
+                self.emit_session_state();
+                self.save_settings();
+            }
+            Command::SetLimiterParams { params } => {
+                let params = params.clamped();
+                self.settings.limiter = params;
+                self.audio_params.set_limiter(params);
+                self.emit_session_state();
+                self.save_settings();
+            }
+            Command::SetDuckingParams { params } => {
+                let params = params.clamped();
+                self.settings.ducking = params;
+                self.audio_params.set_ducking(params);
+                self.emit_session_state();
+                self.save_settings();
+            }
+            Command::StartAudioRecording { path } => {
+                let recorder = self
+                    .audio_recorder
+                    .as_mut()
+                    .ok_or_else(|| AppError::InvalidState("audio output not open".to_string()))?;
+                recorder.start(Path::new(&path))?;
+                diag_log!(Info, "recording output to {path}");
+                self.last_recording_emit = self.now();
+                self.emit_recording_state();
+            }
+            Command::RunAudioSelfTest => {
+                self.start_audio_self_test()?;
+            }
+            Command::StartMidiCapture { seconds } => {
+                self.start_midi_capture(seconds)?;
+            }
+            Command::StopAudioRecording => {
+                self.stop_audio_recording()?;
+            }
+            Command::LoadSoundFont { path } => {
+                self.load_soundfont(&path)?;
+                self.settings.default_sf2_path = Some(path);
+                self.save_settings();
+            }
             Command::SetProgram { bus, gm_program } => {
                 self.synth.set_program(bus, gm_program)?;
+                self.settings.bus_programs.insert(bus, gm_program);
+                self.save_settings();
+                self.emit_session_state();
+            }
+            Command::SetUpcomingTargetCount { count } => {
+                self.upcoming_targets.set_count(count as usize);
+            }
+            Command::SetDecayScale { scale } => {
+                let scale = scale.clamp(MIN_DECAY_SCALE, MAX_DECAY_SCALE);
+                self.synth.set_decay_scale(scale);
+                self.settings.decay_scale = scale;
+                self.save_settings();
+                self.emit_session_state();
+            }
+            Command::SetSynthQuality { quality } => {
+                if let Some(quality) = self.quality_governor.set_forced(quality) {
+                    self.apply_synth_quality(quality, false);
+                }
             }
             Command::LoadScore { source } => {
                 self.load_score(source)?;
@@ -229,6 +515,7 @@ impl AppCore {
                     start_tick,
                     end_tick,
                 }));
+                self.seek_to(start_tick);
             }
             Command::StartPractice => {
                 if self.session_state == SessionState::Running {
@@ -238,6 +525,17 @@ impl AppCore {
                     return Err(AppError::InvalidState("no score loaded".to_string()));
                 }
                 self.ensure_audio_output_open()?;
+                // Paused inside a target's window: resume from where it opens, so the pause
+                // cannot use up the time left to play it.
+                if let Some(paused_tick) = self.paused_tick.take() {
+                    if let Some(window_start) = self
+                        .judge
+                        .focus_window_start()
+                        .filter(|&window_start| window_start < paused_tick)
+                    {
+                        self.transport.seek(window_start);
+                    }
+                }
                 self.transport.align_to_sample_time(self.audio_clock.get());
                 self.scheduler.seek(self.transport.now_tick());
                 self.flush_audio_notes();
@@ -248,6 +546,9 @@ impl AppCore {
                 self.emit_session_state();
             }
             Command::PausePractice => {
+                if self.session_state == SessionState::Running {
+                    self.paused_tick = Some(self.transport.now_tick());
+                }
                 self.session_state = SessionState::Paused;
                 self.transport.pause();
                 self.audio_params.set_playback_enabled(false);
@@ -255,18 +556,25 @@ impl AppCore {
                 self.flush_audio_notes();
             }
             Command::StopPractice => {
+                self.paused_tick = None;
                 self.session_state = SessionState::Ready;
                 self.transport.stop();
                 self.scheduler.seek(self.transport.now_tick());
+                self.seek_judge(self.transport.now_tick());
                 self.audio_params.set_playback_enabled(false);
                 self.emit_session_state();
                 self.flush_audio_notes();
+                self.flush_settings();
+                self.clear_session_snapshot();
+            }
+            Command::ResumeRecoveredSession => {
+                self.resume_recovered_session()?;
+            }
+            Command::PanicAllNotes => {
+                self.panic_all_notes();
             }
             Command::Seek { tick } => {
-                self.transport.seek(tick);
-                self.scheduler.seek(tick);
-                self.flush_audio_notes();
-                self.emit_transport(true);
+                self.seek_to(tick);
             }
             Command::SetLoop {
                 enabled,
@@ -283,25 +591,79 @@ impl AppCore {
                 };
                 self.set_loop(range);
             }
-            Command::SetTempoMultiplier { x } => {
-                self.transport.set_tempo_multiplier(x);
+            Command::SetTempoMultiplier { x, ramp_ms } => {
+                // The ramp is stepped by the audio clock, which only drives the transport
+                // while practicing.
+                match ramp_ms {
+                    Some(over_ms) if self.session_state == SessionState::Running => {
+                        self.transport.ramp_tempo_multiplier(x, over_ms);
+                    }
+                    _ => self.transport.set_tempo_multiplier(x),
+                }
                 self.emit_transport(true);
             }
             Command::SetPlaybackMode { mode } => {
                 self.scheduler.set_mode(mode);
             }
+            Command::SetFollowMode {
+                enabled,
+                correction_rate,
+            } => {
+                self.follow =
+                    enabled.then(|| correction_rate.map(FollowMode::new).unwrap_or_default());
+                self.events.push_back(Event::FollowModeChanged {
+                    correction_rate: self.follow.map(|follow| follow.correction_rate()),
+                });
+            }
             Command::SetAccompanimentRoute {
                 play_left,
                 play_right,
+                suppress_pedal,
             } => {
                 self.scheduler
                     .set_accompaniment_route(play_left, play_right);
+                self.scheduler
+                    .set_accompaniment_pedal_suppressed(suppress_pedal);
+            }
+            Command::SetAutopilotFeel {
+                velocity_scale,
+                humanize_timing_ms,
+                humanize_velocity,
+                resound_velocity_scale,
+            } => {
+                self.scheduler.set_autopilot_feel(AutopilotFeel {
+                    velocity_scale: velocity_scale.max(0.0),
+                    humanize_timing_ms: humanize_timing_ms.max(0.0),
+                    humanize_velocity,
+                    resound_velocity_scale: resound_velocity_scale
+                        .unwrap_or(DEFAULT_RESOUND_VELOCITY_SCALE)
+                        .max(0.0),
+                });
             }
             Command::SetInputOffsetMs { ms } => {
                 self.settings.input_offset_ms = ms;
                 self.emit_session_state();
                 self.save_settings();
             }
+            Command::SetInputEventRate { hz } => {
+                self.settings.input_event_rate_hz = hz;
+                if hz == 0 {
+                    self.pending_input_events.clear();
+                }
+                self.emit_session_state();
+                self.save_settings();
+            }
+            Command::SetMidiMapping { mappings } => {
+                self.settings.midi_mappings = mappings;
+                self.emit_session_state();
+                self.save_settings();
+            }
+            Command::LearnMidiMapping { action } => {
+                self.midi_learn = Some(action);
+            }
+            Command::CompleteSetupStep { step } => {
+                self.complete_setup_step(step);
+            }
             Command::SetAudiverisPath { path } => {
                 self.settings.audiveris_path = Some(path);
                 self.save_settings();
@@ -310,21 +672,88 @@ impl AppCore {
                 pdf_path,
                 output_path,
                 audiveris_path,
+                split_by_hand,
+                omr_cleanup,
             } => {
-                self.convert_pdf_to_midi(&pdf_path, &output_path, audiveris_path)?;
+                self.convert_pdf_to_midi(
+                    &pdf_path,
+                    &output_path,
+                    audiveris_path,
+                    split_by_hand,
+                    omr_cleanup,
+                )?;
+            }
+            Command::CancelPdfToMidi => {
+                if let Some(job) = &self.pdf_job {
+                    job.cancel();
+                }
+            }
+            Command::ExportMidiRange {
+                path,
+                start_tick,
+                end_tick,
+                tempo_multiplier,
+            } => {
+                self.export_midi_range(&path, start_tick, end_tick, tempo_multiplier)?;
             }
-            Command::CancelPdfToMidi => {}
             Command::ExportDiagnostics { path } => {
-                let midi_inputs = self.midi_port.list_inputs()?;
-                let audio_outputs = self.audio_port.list_outputs()?;
-                export_diagnostics(
-                    Path::new(&path),
-                    &self.settings,
-                    midi_inputs,
-                    audio_outputs,
-                    self.recent_inputs.iter().copied().collect(),
+                let snapshot = self.diagnostics_snapshot()?;
+                let archive = export_diagnostics(Path::new(&path), &snapshot)?;
+                diag_log!(Info, "diagnostics exported to {}", archive.display());
+                self.events.push_back(Event::DiagnosticsExported {
+                    path: archive.display().to_string(),
+                });
+            }
+            Command::SaveScoreFile { path } => {
+                self.save_score_file(&path)?;
+            }
+            Command::SetNotesHand {
+                start_tick,
+                end_tick,
+                min_note,
+                max_note,
+                hand,
+            } => {
+                let selection = NoteSelection {
+                    ticks: start_tick..end_tick,
+                    pitches: min_note..=max_note,
+                };
+                self.edit_score(
+                    format!(
+                        "set hand {hand:?} for notes {min_note}..={max_note} in ticks {start_tick}..{end_tick}"
+                    ),
+                    |score| set_notes_hand(score, &selection, hand),
                 )?;
             }
+            Command::SwapHands {
+                start_tick,
+                end_tick,
+            } => {
+                self.edit_score(
+                    format!("swap hands in ticks {start_tick}..{end_tick}"),
+                    |score| swap_hands(score, start_tick..end_tick),
+                )?;
+            }
+            Command::SetScoreSoundOverrides { overrides } => {
+                self.change_score_sound(overrides)?;
+            }
+            Command::ClearScoreSoundOverrides => {
+                self.change_score_sound(ScoreSoundOverrides::default())?;
+            }
+            Command::SelectScoreTracks { ids } => {
+                let Some(score) = self.score.as_ref() else {
+                    return Err(AppError::InvalidState("no score loaded".to_string()));
+                };
+                if let Some(id) = ids
+                    .iter()
+                    .find(|id| !score.tracks.iter().any(|track| track.id == **id))
+                {
+                    return Err(AppError::InvalidState(format!("no score track {id}")));
+                }
+                diag_log!(Info, "score tracks selected: {ids:?}");
+                self.score_track_ids = ids;
+                self.refresh_score();
+            }
         }
         Ok(())
     }
@@ -349,77 +778,631 @@ impl AppCore {
 
         let note = 60u8;
         let velocity = 96u8;
-        let _ = producer.push(ScheduledEvent {
-            sample_time: start,
-            bus: Bus::UserMonitor,
-            event: MidiLikeEvent::NoteOn { note, velocity },
-        });
-        let _ = producer.push(ScheduledEvent {
-            sample_time: start.saturating_add(duration_frames),
-            bus: Bus::UserMonitor,
-            event: MidiLikeEvent::NoteOff { note },
-        });
+        for event in [
+            ScheduledEvent {
+                sample_time: start,
+                bus: Bus::UserMonitor,
+                event: MidiLikeEvent::NoteOn { note, velocity },
+                generation: self.event_generation,
+            },
+            ScheduledEvent {
+                sample_time: start.saturating_add(duration_frames),
+                bus: Bus::UserMonitor,
+                event: MidiLikeEvent::NoteOff { note },
+                generation: self.event_generation,
+            },
+        ] {
+            if producer.push(event).is_err() {
+                self.audio_queue_drops += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn start_audio_self_test(&mut self) -> Result<(), AppError> {
+        if self.session_state == SessionState::Running {
+            return Err(AppError::InvalidState(
+                "stop practice before running the audio self-test".to_string(),
+            ));
+        }
+        if self.audio_self_test.is_some() {
+            return Err(AppError::InvalidState(
+                "audio self-test already running".to_string(),
+            ));
+        }
+        diag_log!(Info, "audio self-test started");
+        if let Err(err) = self.ensure_audio_output_open() {
+            diag_log!(Error, "audio self-test: output failed to open: {err}");
+            let stages = AudioSelfTest::device_open_failed(err.to_string());
+            self.events.push_back(Event::AudioSelfTestResult {
+                passed: false,
+                stages,
+            });
+            return Ok(());
+        }
+        let effective_hz = self
+            .audio_stream
+            .as_ref()
+            .and_then(|stream| stream.effective_config())
+            .map(|config| config.sample_rate_hz);
+        if let Some(effective_hz) = effective_hz {
+            let rendered_hz = self.transport.sample_rate_hz();
+            if effective_hz != rendered_hz {
+                diag_log!(
+                    Error,
+                    "audio self-test: output runs at {effective_hz} Hz, synth at {rendered_hz} Hz"
+                );
+                let stages = AudioSelfTest::device_open_failed(format!(
+                    "the output runs at {effective_hz} Hz but notes are rendered for {rendered_hz} Hz"
+                ));
+                self.events.push_back(Event::AudioSelfTestResult {
+                    passed: false,
+                    stages,
+                });
+                return Ok(());
+            }
+        }
+        // Lets the autopilot and metronome buses through while the test plays its tones.
+        self.audio_params.set_playback_enabled(true);
+        self.audio_self_test = Some(AudioSelfTest::start(
+            self.transport.sample_rate_hz(),
+            self.audio_clock.get(),
+        ));
+        Ok(())
+    }
 
+    fn start_midi_capture(&mut self, seconds: f32) -> Result<(), AppError> {
+        if !(seconds > 0.0 && seconds <= MAX_MIDI_CAPTURE_SECS) {
+            return Err(AppError::InvalidState(format!(
+                "capture length must be between 0 and {MAX_MIDI_CAPTURE_SECS} seconds"
+            )));
+        }
+        if self.midi_capture.is_some() {
+            return Err(AppError::InvalidState(
+                "MIDI capture already running".to_string(),
+            ));
+        }
+        if self.midi_stream.is_none() {
+            return Err(AppError::InvalidState("no MIDI input selected".to_string()));
+        }
+        // Input is only mapped (and jitter only measurable) against a running audio clock.
+        self.ensure_audio_output_open()?;
+        diag_log!(Info, "midi capture started for {seconds} s");
+        let raw_from = self
+            .midi_port
+            .raw_messages()
+            .last()
+            .map_or(0, |message| message.index + 1);
+        self.midi_capture = Some(
+            MidiCapture::new(
+                self.now(),
+                Duration::from_secs_f32(seconds),
+                self.transport.sample_rate_hz(),
+                self.midi_queue_drops.load(Ordering::Relaxed),
+            )
+            .with_raw_messages_from(raw_from),
+        );
         Ok(())
     }
 
+    fn finish_midi_capture(&mut self) {
+        let now = self.now();
+        let Some(capture) = self
+            .midi_capture
+            .take_if(|capture| capture.is_finished(now))
+        else {
+            return;
+        };
+        let report = capture.report(self.midi_queue_drops.load(Ordering::Relaxed));
+        diag_log!(
+            Info,
+            "midi capture finished: {} events, {} duplicates, {} dropped",
+            report.captured,
+            report.duplicates,
+            report.queue_drops + report.overflowed
+        );
+
+        let mut save_error = None;
+        let path = self.storage.as_ref().and_then(|storage| {
+            let file = serde_json::json!({
+                "report": &report,
+                "events": capture.events(),
+                "raw_messages": capture.raw_messages(self.midi_port.raw_messages()),
+            });
+            let saved = serde_json::to_vec_pretty(&file)
+                .map_err(|e| StorageError::Serde(e.to_string()))
+                .and_then(|data| storage.save_diagnostics_file("midi_capture.json", &data));
+            match saved {
+                Ok(path) => Some(path.display().to_string()),
+                Err(err) => {
+                    diag_log!(Warn, "midi capture report not saved: {err}");
+                    save_error = Some(AppError::from(err));
+                    None
+                }
+            }
+        });
+        if let Some(err) = save_error {
+            self.push_command_failed("StartMidiCapture", &err);
+        }
+        self.events
+            .push_back(Event::MidiCaptureReport { report, path });
+    }
+
+    fn advance_audio_self_test(&mut self) {
+        let Some(test) = self.audio_self_test.as_mut() else {
+            return;
+        };
+        if let Some(tone) = test.advance(self.audio_clock.get(), &self.audio_params) {
+            self.schedule_self_test_tone(tone);
+        }
+        let Some(test) = self.audio_self_test.take_if(|test| test.is_done()) else {
+            return;
+        };
+
+        self.audio_params
+            .set_playback_enabled(self.session_state == SessionState::Running);
+        let stages = test.results().to_vec();
+        let passed = stages
+            .iter()
+            .all(|stage| stage.status == SelfTestStatus::Passed);
+        if let Some(failed) = stages
+            .iter()
+            .find(|stage| stage.status == SelfTestStatus::Failed)
+        {
+            diag_log!(
+                Warn,
+                "audio self-test failed at {:?} {:?}: {}",
+                failed.stage,
+                failed.bus,
+                failed.detail
+            );
+        } else {
+            diag_log!(Info, "audio self-test passed");
+        }
+        self.events
+            .push_back(Event::AudioSelfTestResult { passed, stages });
+    }
+
+    fn schedule_self_test_tone(&mut self, tone: SelfTestTone) {
+        let Some(producer) = self.audio_queue_tx.as_mut() else {
+            return;
+        };
+        let note = 72u8;
+        for event in [
+            ScheduledEvent {
+                sample_time: tone.start,
+                bus: tone.bus,
+                event: MidiLikeEvent::NoteOn {
+                    note,
+                    velocity: 100,
+                },
+                generation: self.event_generation,
+            },
+            ScheduledEvent {
+                sample_time: tone.end,
+                bus: tone.bus,
+                event: MidiLikeEvent::NoteOff { note },
+                generation: self.event_generation,
+            },
+        ] {
+            if producer.push(event).is_err() {
+                self.audio_queue_drops += 1;
+            }
+        }
+    }
+
     fn convert_pdf_to_midi(
         &mut self,
         pdf_path: &str,
         output_path: &str,
         audiveris_path: Option<String>,
+        split_by_hand: bool,
+        omr_cleanup: bool,
     ) -> Result<(), AppError> {
-        let Some(omr) = self.omr.as_ref() else {
+        if self.pdf_job.is_some() {
+            return Err(AppError::InvalidState(
+                "PDF conversion already running".to_string(),
+            ));
+        }
+        let Some(omr) = self.omr.clone() else {
             return Err(AppError::ScoreLoad("OMR engine not configured".to_string()));
         };
+        let output_path = resolve_output_path(pdf_path, output_path, &self.export_dir)
+            .map_err(|e| AppError::ScoreLoad(format!("cannot create MIDI output: {e}")))?;
+
+        self.events.push_back(Event::OmrProgress {
+            page: 0,
+            total: 0,
+            stage: "Starting".to_string(),
+        });
+        self.pdf_job = Some(PdfJob::start(
+            omr,
+            PdfJobRequest {
+                pdf_path: pdf_path.to_string(),
+                output_path,
+                engine_path: audiveris_path.or_else(|| self.settings.audiveris_path.clone()),
+                split_by_hand,
+                omr_cleanup,
+            },
+        ));
+        Ok(())
+    }
+
+    fn poll_pdf_job(&mut self) {
+        if let Some(job) = &self.pdf_job {
+            if job.poll(&mut self.events) {
+                self.pdf_job = None;
+            }
+        }
+    }
+
+    fn export_midi_range(
+        &self,
+        path: &str,
+        start_tick: Option<Tick>,
+        end_tick: Option<Tick>,
+        tempo_multiplier: Option<f32>,
+    ) -> Result<(), AppError> {
+        let Some(score) = self.score.as_ref() else {
+            return Err(AppError::InvalidState("no score loaded".to_string()));
+        };
+
+        let loop_range = self.scheduler.loop_range();
+        let start_tick = start_tick.or(loop_range.map(|r| r.start_tick)).unwrap_or(0);
+        let end_tick = end_tick
+            .or(loop_range.map(|r| r.end_tick))
+            .unwrap_or_else(|| score_end_tick(score));
+        let tempo_multiplier =
+            tempo_multiplier.unwrap_or_else(|| self.transport.tempo_multiplier());
+
+        let path = normalize_fs_path(path);
+        export_midi_range(score, &path, start_tick, end_tick, tempo_multiplier)
+            .map_err(|e| AppError::ScoreLoad(e.to_string()))
+    }
 
-        let options = OmrOptions {
-            enable_diagnostics: true,
-            engine_path: audiveris_path.or_else(|| self.settings.audiveris_path.clone()),
+    fn save_score_file(&mut self, path: &str) -> Result<(), AppError> {
+        let Some(score) = self.score.as_ref() else {
+            return Err(AppError::InvalidState("no score loaded".to_string()));
         };
+        let path = normalize_fs_path(path);
+        let mut file = ScoreFile::new(score.clone(), self.score_edit_log.clone());
+        file.sound_overrides = self.score_sound.clone();
+        save_scorefile_path(&file, &path)
+            .map_err(|e| AppError::ScoreSave(format!("{}: {e}", path.display())))?;
+        diag_log!(Info, "score file saved to {}", path.display());
+        self.events.push_back(Event::ScoreFileSaved {
+            path: path.display().to_string(),
+        });
+        self.set_score_dirty(false);
+        Ok(())
+    }
 
-        let result = omr.recognize_pdf(pdf_path, options)?;
-        let musicxml_path = result
-            .musicxml_path
-            .ok_or_else(|| AppError::ScoreLoad("OMR did not produce MusicXML".to_string()))?;
-        let score =
-            import_musicxml_path(&musicxml_path).map_err(|e| AppError::ScoreLoad(e.to_string()))?;
-        export_midi_path(&score, Path::new(output_path))
-            .map_err(|e| AppError::ScoreLoad(e.to_string()))?;
+    /// Applies `edit` to the loaded score in place, keeping the session and transport where
+    /// they are. An edit that changes no note is neither logged nor marks the score dirty.
+    fn edit_score(
+        &mut self,
+        description: String,
+        edit: impl FnOnce(&mut Score) -> usize,
+    ) -> Result<(), AppError> {
+        let Some(score) = self.score.as_mut() else {
+            return Err(AppError::InvalidState("no score loaded".to_string()));
+        };
+        let changed = edit(score);
+        if changed == 0 {
+            return Ok(());
+        }
+        diag_log!(Info, "score edit: {description} ({changed} notes)");
+        self.score_edit_log.push(description);
+        self.refresh_score();
+        self.set_score_dirty(true);
+        Ok(())
+    }
+
+    fn change_score_sound(&mut self, overrides: ScoreSoundOverrides) -> Result<(), AppError> {
+        if self.score.is_none() {
+            return Err(AppError::InvalidState("no score loaded".to_string()));
+        }
+        if overrides == self.score_sound {
+            return Ok(());
+        }
+        self.apply_score_sound(overrides);
+        self.set_score_dirty(true);
         Ok(())
     }
 
+    /// Switches to the sounds of the loaded score, putting back the global choice for
+    /// whatever the previous score had changed.
+    fn apply_score_sound(&mut self, overrides: ScoreSoundOverrides) {
+        let previous = std::mem::replace(&mut self.score_sound, overrides);
+        if previous == self.score_sound {
+            return;
+        }
+        if previous.soundfont_path != self.score_sound.soundfont_path {
+            let path = self
+                .score_sound
+                .soundfont_path
+                .clone()
+                .or_else(|| self.settings.default_sf2_path.clone());
+            if let Some(path) = path {
+                if let Err(err) = self.load_soundfont(&path) {
+                    self.events
+                        .push_back(command_failed_event("LoadSoundFont", &err));
+                }
+            }
+        }
+        let programs = self.bus_programs();
+        for &bus in previous.bus_programs.keys() {
+            if !programs.contains_key(&bus) {
+                // No global choice to return to: back to the GM default.
+                if let Err(err) = self.synth.set_program(bus, 0) {
+                    diag_log!(Warn, "program reset failed for {bus:?}: {err}");
+                }
+            }
+        }
+        apply_bus_programs(self.synth.as_ref(), &programs);
+        self.emit_session_state();
+    }
+
+    /// Programs per bus: the global choice with the loaded score's overrides on top.
+    fn bus_programs(&self) -> BTreeMap<Bus, u8> {
+        let mut programs = self.settings.bus_programs.clone();
+        programs.extend(&self.score_sound.bus_programs);
+        programs
+    }
+
+    fn load_soundfont(&mut self, path: &str) -> Result<(), AppError> {
+        let result = self.synth.load_soundfont_from_path(path);
+        self.synth_status = soundfont_status(path, &result);
+        self.events
+            .push_back(soundfont_status_event(&self.synth_status));
+        result?;
+        apply_bus_programs(self.synth.as_ref(), &self.bus_programs());
+        Ok(())
+    }
+
+    /// Reloads the scheduler and judge from the edited score at the current position.
+    fn refresh_score(&mut self) {
+        let Some(track) = self
+            .score
+            .as_ref()
+            .and_then(|score| practice_track(score, &self.score_track_ids))
+        else {
+            return;
+        };
+        let targets = track.targets.clone();
+        let playback_events = track.playback_events.clone();
+        let tick = self.transport.now_tick();
+        let note_names = self
+            .score
+            .as_ref()
+            .map(|score| derive_note_names(&score.key_signatures, &playback_events))
+            .unwrap_or_default();
+
+        self.targets = targets.iter().map(|t| (t.id, t.clone())).collect();
+        self.note_names = note_names;
+        let judge_events = self.judge.load_targets(targets);
+        self.retime_judge_windows();
+        for event in judge_events {
+            self.handle_judge_event(event);
+        }
+        self.seek_judge(tick);
+        self.scheduler.set_score(playback_events);
+        self.scheduler.seek(tick);
+        // Notes already sent keep the old routing; let them go rather than hang.
+        self.flush_audio_notes();
+        self.emit_score_view();
+    }
+
+    fn set_score_dirty(&mut self, dirty: bool) {
+        if self.score_dirty != dirty {
+            self.score_dirty = dirty;
+            self.events.push_back(Event::ScoreDirtyChanged { dirty });
+        }
+    }
+
     fn ensure_audio_output_open(&mut self) -> Result<(), AppError> {
-        if self.audio_stream.is_some() {
+        if self.audio_stream.is_some() || self.pending_audio_open.is_some() {
             return Ok(());
         }
 
-        let device_id = if let Some(id) = self.settings.selected_audio_out.clone() {
-            id
-        } else {
-            let devices = self.audio_port.list_outputs()?;
-            let first = devices.first().ok_or_else(|| {
-                AudioError::DeviceUnavailable("no audio outputs found".to_string())
-            })?;
-            first.id.clone()
+        let Err(err) = self
+            .default_audio_output()
+            .and_then(|device_id| self.open_audio_output(device_id, None))
+        else {
+            return Ok(());
         };
 
-        self.open_audio_output(device_id, None)?;
+        self.fall_back_to_silent_output(&err)
+    }
+
+    /// Practice silently rather than not at all, keeping the saved choice for next time.
+    fn fall_back_to_silent_output(&mut self, err: &AppError) -> Result<(), AppError> {
+        diag_log!(Warn, "no audio output ({err}), continuing without sound");
+        let selected = self.settings.selected_audio_out.clone();
+        self.open_audio_output(DeviceId(NULL_AUDIO_DEVICE_ID.to_string()), None)?;
+        self.settings.selected_audio_out = selected;
+        self.emit_session_state();
+        self.save_settings_now();
+        self.events.push_back(Event::SilentAudioFallback {
+            reason: err.to_string(),
+        });
         Ok(())
     }
 
+    fn default_audio_output(&self) -> Result<DeviceId, AppError> {
+        if let Some(id) = self.settings.selected_audio_out.clone() {
+            return Ok(id);
+        }
+        let devices = self.audio_port.list_outputs()?;
+        let first = devices
+            .first()
+            .ok_or_else(|| AudioError::DeviceUnavailable("no audio outputs found".to_string()))?;
+        Ok(first.id.clone())
+    }
+
+    fn output_port(&self, device_id: &DeviceId) -> &dyn AudioOutputPort {
+        if device_id.0 == NULL_AUDIO_DEVICE_ID {
+            &NullAudioOutputPort
+        } else {
+            self.audio_port.as_ref()
+        }
+    }
+
+    /// Starts the first-run setup when the settings are still the defaults: reports the
+    /// steps to walk, then loads the demo score and opens the default audio output so
+    /// there is something to play right away. Does nothing once setup was completed.
+    pub fn start_first_run_setup(&mut self) {
+        if self.settings.setup_completed || !self.setup_pending.is_empty() {
+            return;
+        }
+        // Settings from before the setup existed already have devices chosen.
+        if self.settings.selected_midi_in.is_some() || self.settings.selected_audio_out.is_some() {
+            return;
+        }
+        self.setup_pending = vec![
+            SetupStep::MidiInput,
+            SetupStep::AudioOutput,
+            SetupStep::Score,
+        ];
+        self.events.push_back(Event::SetupRequired {
+            missing: self.setup_pending.clone(),
+        });
+
+        if self.score.is_none() {
+            let _ = self.handle_command(Command::LoadScore {
+                source: ScoreSource::InternalDemo("scale".to_string()),
+            });
+        }
+        if self.audio_stream.is_none() {
+            if let Err(err) = self
+                .default_audio_output()
+                .and_then(|device_id| self.open_audio_output(device_id, None))
+            {
+                diag_log!(Warn, "setup: default audio output not opened: {err}");
+            }
+        }
+    }
+
+    fn complete_setup_step(&mut self, step: SetupStep) {
+        let Some(index) = self
+            .setup_pending
+            .iter()
+            .position(|pending| *pending == step)
+        else {
+            return;
+        };
+        self.setup_pending.remove(index);
+        self.events.push_back(Event::SetupStepCompleted {
+            step,
+            remaining: self.setup_pending.clone(),
+        });
+        if self.setup_pending.is_empty() {
+            self.settings.setup_completed = true;
+            self.save_settings_now();
+            self.events.push_back(Event::SetupCompleted);
+        }
+    }
+
     pub fn tick(&mut self) {
         self.update_clock_anchor();
         self.sync_transport();
         self.process_midi_inputs();
+        self.watch_midi_input();
+        self.retry_audio_output();
+        self.finish_midi_capture();
         self.advance_judge();
         self.schedule_autopilot();
         self.emit_transport(false);
+        self.emit_upcoming_targets();
+        self.emit_input_events();
         self.emit_recent_inputs();
+        self.emit_audio_levels();
+        self.emit_audio_stats();
+        self.advance_audio_self_test();
+        self.emit_late_events();
+        self.log_callback_overruns();
+        self.emit_recording_progress();
+        self.save_settings_if_due();
+        self.autosave_session_if_due();
+        self.poll_pdf_job();
+    }
+
+    /// Directory PDF conversions write to when the command gives no directory of its own.
+    pub fn set_export_dir(&mut self, dir: PathBuf) {
+        self.export_dir = dir;
+    }
+
+    /// Replaces the wall clock, for runs driven by a virtual one. Pacing starts over from
+    /// the new clock's present.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        let now = clock.now();
+        self.clock = clock;
+        self.last_transport_emit = now;
+        self.last_input_emit = now;
+        self.last_input_event_emit = now;
+        self.last_levels_emit = now;
+        self.last_recording_emit = now;
+        self.last_stats_emit = now;
+        self.last_settings_save = now;
+        self.last_session_autosave = now;
+        self.last_midi_input_check = now;
+    }
+
+    fn now(&self) -> Instant {
+        self.clock.now()
     }
 
     pub fn drain_events(&mut self) -> Vec<Event> {
-        self.events.drain(..).collect()
+        self.events.drain()
+    }
+
+    /// Stops practice, releases sounding notes, closes the MIDI input and then the audio
+    /// output after fading it out, and writes pending settings. Later calls do nothing;
+    /// dropping the core calls it too.
+    pub fn shutdown(&mut self) {
+        if self.shut_down {
+            return;
+        }
+        self.shut_down = true;
+
+        if let Some(job) = self.pdf_job.take() {
+            job.cancel();
+        }
+        self.session_state = SessionState::Ready;
+        self.transport.stop();
+        self.audio_params.set_playback_enabled(false);
+        self.flush_audio_notes();
+
+        if let Some(stream) = self.midi_stream.take() {
+            stream.close();
+        }
+        self.midi_queue_rx = None;
+        self.lost_midi_input = None;
+        self.pending_audio_open = None;
+
+        if self
+            .audio_recorder
+            .as_ref()
+            .is_some_and(AudioRecorder::is_recording)
+        {
+            if let Err(err) = self.stop_audio_recording() {
+                diag_log!(Warn, "recording not finished on shutdown: {err}");
+            }
+        }
+        self.audio_output = None;
+        if let Some(stream) = self.audio_stream.take() {
+            // Only the audio thread's copy is muted; the saved master volume is untouched.
+            self.audio_params.set_master(Volume01::new(0.0));
+            std::thread::sleep(SHUTDOWN_FADE);
+            stream.close();
+        }
+        self.audio_queue_tx = None;
+
+        self.flush_settings();
+        self.clear_session_snapshot();
+        diag_log!(Info, "core shut down");
+        self.events.push_back(Event::ShutDown);
     }
 
     fn open_audio_output(
@@ -427,85 +1410,379 @@ impl AppCore {
         device_id: DeviceId,
         config: Option<AudioConfig>,
     ) -> Result<(), AppError> {
+        self.open_audio_output_attempt(device_id, config, 1)
+    }
+
+    /// Opens the output, or when it is busy and tries are left, closes the current one and
+    /// leaves the next try to [`Self::retry_audio_output`].
+    fn open_audio_output_attempt(
+        &mut self,
+        device_id: DeviceId,
+        config: Option<AudioConfig>,
+        attempt: u32,
+    ) -> Result<(), AppError> {
+        self.pending_audio_open = None;
+        let requested = config;
+        if self
+            .audio_recorder
+            .as_ref()
+            .is_some_and(AudioRecorder::is_recording)
+        {
+            self.stop_audio_recording()?;
+        }
         if let Some(stream) = self.audio_stream.take() {
             stream.close();
         }
+        self.audio_output = None;
+
+        let device = self.output_device(&device_id);
+        let device_default = device.as_ref().map_or(
+            AudioConfig {
+                sample_rate_hz: 48_000,
+                channels: 2,
+                buffer_size_frames: None,
+            },
+            |device| device.default_config,
+        );
 
-        let fallback_config = AudioConfig {
-            sample_rate_hz: 48_000,
-            channels: 2,
-            buffer_size_frames: None,
+        // Buffer size precedence: a requested config (where `None` or 0 asks for the device
+        // default) is persisted and wins; otherwise the persisted size, if the device accepts
+        // it; otherwise the device default.
+        let requested_frames = match config {
+            Some(config) => {
+                let frames = config.buffer_size_frames.filter(|&frames| frames > 0);
+                self.settings.audio_buffer_size_frames = frames;
+                frames
+            }
+            None => self.settings.audio_buffer_size_frames.filter(|&frames| {
+                let supported = frames > 0 && buffer_size_supported(device.as_ref(), frames);
+                if !supported {
+                    diag_log!(
+                        Warn,
+                        "saved buffer size {frames} not supported by {device_id}, using default"
+                    );
+                }
+                supported
+            }),
         };
+        let mut config = config.unwrap_or(device_default);
+        config.buffer_size_frames = requested_frames.or(device_default.buffer_size_frames);
 
-        let requested_config = config;
-        let mut config = match config {
-            Some(config) => config,
-            None => match self.audio_port.list_outputs() {
-                Ok(devices) => devices
-                    .into_iter()
-                    .find(|d| d.id == device_id)
-                    .map(|d| d.default_config)
-                    .unwrap_or(fallback_config),
-                Err(_) => fallback_config,
-            },
+        let (mut stream, mut producer) = match self.open_stream(&device_id, config) {
+            Ok(opened) => opened,
+            Err(err) => return self.retry_audio_output_later(device_id, requested, attempt, err),
         };
-
-        if config.buffer_size_frames == Some(0) {
-            config.buffer_size_frames = None;
-        }
-
-        // Persist requested buffer size selection, but keep the existing setting if the caller
-        // didn't provide a config override.
-        if requested_config.is_some() {
-            self.settings.audio_buffer_size_frames = config.buffer_size_frames;
+        let mut effective = stream.effective_config().unwrap_or(config);
+        if effective.sample_rate_hz != config.sample_rate_hz {
+            // The graph, scheduler and synth were all built for the requested rate; notes
+            // would play off pitch, so everything is rebuilt at the rate the device runs at.
+            let requested_hz = config.sample_rate_hz;
+            diag_log!(
+                Warn,
+                "audio output {device_id} runs at {} Hz, not the requested {requested_hz} Hz; reopening",
+                effective.sample_rate_hz
+            );
+            stream.close();
+            config.sample_rate_hz = effective.sample_rate_hz;
+            (stream, producer) = match self.open_stream(&device_id, config) {
+                Ok(opened) => opened,
+                Err(err) => {
+                    return self.retry_audio_output_later(device_id, requested, attempt, err)
+                }
+            };
+            effective = stream.effective_config().unwrap_or(config);
+            self.events.push_back(Event::SampleRateCorrected {
+                device_id: device_id.clone(),
+                requested_hz,
+                effective_hz: effective.sample_rate_hz,
+                message: format!(
+                    "The output runs at {} Hz instead of {requested_hz} Hz; playback was adjusted to match.",
+                    effective.sample_rate_hz
+                ),
+            });
         }
+        diag_log!(
+            Info,
+            "audio output opened: {device_id} at {} Hz, buffer {:?} (requested {:?})",
+            effective.sample_rate_hz,
+            effective.buffer_size_frames,
+            config.buffer_size_frames
+        );
+        self.events.push_back(Event::AudioOutputConfigured {
+            device_id: device_id.clone(),
+            requested_buffer_size_frames: config.buffer_size_frames,
+            effective,
+        });
 
-        // Apply persisted buffer size for callers that didn't request a specific size.
-        if config.buffer_size_frames.is_none() {
-            if let Some(frames) = self.settings.audio_buffer_size_frames {
-                config.buffer_size_frames = Some(frames);
-            }
-        }
+        self.audio_stream = Some(stream);
+        self.audio_output = Some((device_id.clone(), config));
+        self.audio_queue_tx = Some(producer);
+        self.settings.selected_audio_out = Some(device_id);
+        self.audio_params
+            .set_playback_enabled(self.session_state == SessionState::Running);
+        self.emit_session_state();
+        self.save_settings_now();
+        Ok(())
+    }
 
+    /// Sets the transport, synth and scheduler to `config`'s rate and opens the output with
+    /// a graph built for it.
+    fn open_stream(
+        &mut self,
+        device_id: &DeviceId,
+        config: AudioConfig,
+    ) -> Result<(Box<dyn AudioStreamHandle>, Producer<ScheduledEvent>), AppError> {
         self.transport.set_sample_rate(config.sample_rate_hz);
         self.synth.set_sample_rate(config.sample_rate_hz);
-        self.scheduler =
-            Scheduler::new(config.sample_rate_hz, SchedulerConfig { lookahead_ms: 30 });
+        apply_bus_programs(self.synth.as_ref(), &self.bus_programs());
+        let feel = self.scheduler.autopilot_feel();
+        self.sent_notes_lost = true;
+        self.scheduler = Scheduler::new(
+            config.sample_rate_hz,
+            SchedulerConfig {
+                lookahead_ms: 30,
+                buffer_frames: config.buffer_size_frames,
+            },
+        );
+        self.scheduler.set_autopilot_feel(feel);
         if let Some(score) = self.score.as_ref() {
-            if let Some(track) = score.tracks.first() {
+            if let Some(track) = practice_track(score, &self.score_track_ids) {
                 self.scheduler.set_score(track.playback_events.clone());
             }
         }
 
-        let (producer, consumer) = RingBuffer::new(4096);
         let max_frames = config
             .buffer_size_frames
             .map(|f| f as usize)
             .unwrap_or(8192);
+        let (producer, consumer) = RingBuffer::new(4096);
+        let (recorder_tap, recorder) = audio_recorder(config.sample_rate_hz);
         let audio_graph = AudioGraph::new(
             self.synth.clone(),
             self.audio_params.clone(),
             consumer,
             self.audio_clock.clone(),
+            self.audio_meters.clone(),
+            config.sample_rate_hz,
             max_frames,
-        );
+        )
+        .with_recorder(recorder_tap)
+        .with_late_event_stats(self.late_event_stats.clone())
+        .with_callback_stats(self.callback_stats.clone());
+        self.audio_recorder = Some(recorder);
 
         self.audio_clock.set(0);
         self.transport.set_origin_sample(0);
+        self.clock_stats.reset(config.sample_rate_hz);
 
-        let stream = self.audio_port.open_output(
-            &device_id,
+        let stream = self.output_port(device_id).open_output(
+            device_id,
             config,
             Box::new(audio_graph) as Box<dyn AudioRenderCallback>,
         )?;
+        Ok((stream, producer))
+    }
 
-        self.audio_stream = Some(stream);
-        self.audio_queue_tx = Some(producer);
-        self.settings.selected_audio_out = Some(device_id);
-        self.audio_params
-            .set_playback_enabled(self.session_state == SessionState::Running);
+    /// A device held by another application often frees up within a second or two, so a
+    /// busy one is tried again a few times. The wait is left to `tick` rather than slept
+    /// through, which would hold up every other command meanwhile.
+    fn retry_audio_output_later(
+        &mut self,
+        device_id: DeviceId,
+        config: Option<AudioConfig>,
+        attempt: u32,
+        err: AppError,
+    ) -> Result<(), AppError> {
+        let retry_after = match &err {
+            AppError::Audio(audio) => audio.retry_after(),
+            _ => None,
+        };
+        let Some(wait) = retry_after.filter(|_| attempt < AUDIO_OPEN_ATTEMPTS) else {
+            return Err(err);
+        };
+        let wait = wait.min(AUDIO_OPEN_MAX_RETRY_WAIT);
+        diag_log!(
+            Warn,
+            "audio output {device_id} busy, retrying in {wait:?} ({attempt}/{AUDIO_OPEN_ATTEMPTS})"
+        );
+        self.pending_audio_open = Some(PendingAudioOpen {
+            device_id,
+            config,
+            attempt: attempt + 1,
+            retry_at: self.now() + wait,
+        });
+        Ok(())
+    }
+
+    /// Tries a busy output again once its wait is over. When the last try fails too, the
+    /// failure is reported, and a running session carries on without sound.
+    fn retry_audio_output(&mut self) {
+        if self
+            .pending_audio_open
+            .as_ref()
+            .is_none_or(|pending| self.now() < pending.retry_at)
+        {
+            return;
+        }
+        let Some(pending) = self.pending_audio_open.take() else {
+            return;
+        };
+        let Err(err) =
+            self.open_audio_output_attempt(pending.device_id, pending.config, pending.attempt)
+        else {
+            return;
+        };
+        diag_log!(Warn, "audio output not opened: {err}");
+        self.events
+            .push_back(command_failed_event("SelectAudioOutput", &err));
+        if self.session_state == SessionState::Running {
+            if let Err(err) = self.fall_back_to_silent_output(&err) {
+                diag_log!(Warn, "silent audio fallback failed: {err}");
+            }
+        }
+    }
+
+    fn output_device(&self, device_id: &DeviceId) -> Option<AudioOutputDevice> {
+        self.output_port(device_id)
+            .list_outputs()
+            .ok()?
+            .into_iter()
+            .find(|device| &device.id == device_id)
+    }
+
+    fn set_audio_buffer_size(&mut self, frames: Option<u32>) -> Result<(), AppError> {
+        let frames = frames.filter(|&frames| frames > 0);
+        let Some((device_id, current)) = self.audio_output.clone() else {
+            // Nothing to reopen; the size applies to the next output opened.
+            self.settings.audio_buffer_size_frames = frames;
+            self.emit_session_state();
+            self.save_settings_now();
+            return Ok(());
+        };
+        if let Some(frames) = frames {
+            if !buffer_size_supported(self.output_device(&device_id).as_ref(), frames) {
+                return Err(AudioError::UnsupportedConfig(format!(
+                    "buffer size of {frames} frames not supported by {device_id}"
+                ))
+                .into());
+            }
+        }
+
+        // On the silent fallback the saved choice stays the device that failed.
+        let selected = self.settings.selected_audio_out.clone();
+        let saved_frames = self.settings.audio_buffer_size_frames;
+        let result = self.open_audio_output(
+            device_id.clone(),
+            Some(AudioConfig {
+                buffer_size_frames: frames,
+                ..current
+            }),
+        );
+        if let Err(err) = result {
+            diag_log!(
+                Warn,
+                "buffer size change failed ({err}), restoring previous"
+            );
+            self.open_audio_output(device_id, Some(current))?;
+            self.settings.audio_buffer_size_frames = saved_frames;
+            self.settings.selected_audio_out = selected;
+            self.save_settings_now();
+            return Err(err);
+        }
+        self.settings.selected_audio_out = selected;
+        self.save_settings_now();
+        Ok(())
+    }
+
+    fn emit_midi_inputs(&mut self) -> Result<Vec<MidiInputDevice>, AppError> {
+        let devices = self.midi_port.list_inputs()?;
+        self.events.push_back(Event::MidiInputsUpdated {
+            devices: devices.clone(),
+        });
+        Ok(devices)
+    }
+
+    fn emit_audio_outputs(&mut self) -> Result<Vec<AudioOutputDevice>, AppError> {
+        let mut devices = self.audio_port.list_outputs()?;
+        if !devices
+            .iter()
+            .any(|device| device.id.0 == NULL_AUDIO_DEVICE_ID)
+        {
+            devices.push(null_audio_device());
+        }
+        self.events.push_back(Event::AudioOutputsUpdated {
+            devices: devices.clone(),
+        });
+        Ok(devices)
+    }
+
+    /// Reopens both devices from scratch; after sleep a stream often still looks open while
+    /// its callbacks have stopped. The saved choices are kept even when a stand-in opens.
+    fn refresh_devices(&mut self) -> Result<(), AppError> {
+        diag_log!(Info, "refreshing audio and midi devices");
+        self.sync_transport();
+        let tick = self.transport.now_tick();
+        let inputs = self.emit_midi_inputs()?;
+        let outputs = self.emit_audio_outputs()?;
+
+        let selected_in = self.settings.selected_midi_in.clone();
+        let selected_listed = selected_in
+            .as_ref()
+            .and_then(|id| self.listed_midi_input(&inputs, id));
+        let input = selected_listed.clone().or_else(|| {
+            inputs
+                .iter()
+                .find(|device| device.is_available)
+                .map(|device| device.id.clone())
+        });
+        if let Some(stream) = self.midi_stream.take() {
+            stream.close();
+        }
+        self.midi_queue_rx = None;
+        self.release_notes(&[Bus::UserMonitor]);
+        match input {
+            Some(device_id) => {
+                self.open_midi_input(device_id)?;
+                if selected_in.is_some() && selected_listed.is_none() {
+                    self.settings.selected_midi_in = selected_in;
+                }
+            }
+            // Reconnects on its own once the device is listed again.
+            None => self.lost_midi_input = selected_in,
+        }
+
+        let selected_out = self.settings.selected_audio_out.clone();
+        let output = selected_out
+            .clone()
+            .filter(|id| outputs.iter().any(|device| &device.id == id))
+            .or_else(|| outputs.first().map(|device| device.id.clone()));
+        if let Some(stream) = self.audio_stream.take() {
+            stream.close();
+        }
+        self.audio_output = None;
+        let opened = match output {
+            Some(device_id) => self.open_audio_output(device_id, None),
+            None => Err(AudioError::DeviceUnavailable("no audio outputs found".to_string()).into()),
+        };
+        if let Err(err) = opened {
+            diag_log!(Warn, "refresh: audio output not reopened: {err}");
+            self.ensure_audio_output_open()?;
+        }
+        if selected_out.is_some() {
+            self.settings.selected_audio_out = selected_out;
+        }
+
+        // The new stream counts from zero; tie `tick` to its start so a running transport
+        // carries on from there rather than from the top of the clock.
+        self.transport.seek(tick);
+        if self.session_state == SessionState::Running {
+            self.transport.align_to_sample_time(self.audio_clock.get());
+        }
+        self.scheduler.seek(tick);
+        self.flush_audio_notes();
         self.emit_session_state();
-        self.save_settings();
+        self.emit_transport(true);
+        self.save_settings_now();
         Ok(())
     }
 
@@ -516,41 +1793,91 @@ impl AppCore {
 
         let (producer, consumer) = RingBuffer::new(2048);
         let producer = Arc::new(Mutex::new(producer));
+        let drops = self.midi_queue_drops.clone();
         let cb = Arc::new(move |event: PlayerEvent| {
-            if let Some(mut guard) = producer.try_lock() {
-                let _ = guard.push(event);
+            let pushed = producer
+                .try_lock()
+                .is_some_and(|mut guard| guard.push(event).is_ok());
+            if !pushed {
+                drops.fetch_add(1, Ordering::Relaxed);
             }
         });
 
         let stream = self.midi_port.open_input(&device_id, cb)?;
+        diag_log!(Info, "midi input opened: {device_id}");
+        self.midi_input_name = self.midi_port.list_inputs().ok().and_then(|devices| {
+            devices
+                .into_iter()
+                .find(|device| device.id == device_id)
+                .map(|device| device.name)
+        });
         self.midi_stream = Some(stream);
         self.midi_queue_rx = Some(consumer);
+        self.lost_midi_input = None;
         self.settings.selected_midi_in = Some(device_id);
         self.emit_session_state();
-        self.save_settings();
+        self.save_settings_now();
         Ok(())
     }
 
     fn load_score(&mut self, source: ScoreSource) -> Result<(), AppError> {
-        let score = match source {
+        let loaded_from = source.clone();
+        let mut edit_log = Vec::new();
+        let mut sound_overrides = ScoreSoundOverrides::default();
+        let (score, warnings) = match source {
             ScoreSource::MidiFile(path) => {
                 let path = normalize_fs_path(&path);
                 let path = resolve_existing_path(path, &["mid", "midi"]);
-                import_midi_path(&path).map_err(|e| {
-                    AppError::ScoreLoad(format!("midi load failed for {}: {e}", path.display()))
-                })?
+                let import = import_midi_path_with_options(&path, MidiImportOptions::default())
+                    .map_err(|e| {
+                        AppError::ScoreLoad(format!("midi load failed for {}: {e}", path.display()))
+                    })?;
+                (import.score, import.warnings)
             }
-            ScoreSource::MusicXmlFile(path) => {
+            ScoreSource::MusicXmlFile { path, parts } => {
                 let path = normalize_fs_path(&path);
                 let path = resolve_existing_path(path, &["mxl", "xml"]);
-                import_musicxml_path(&path).map_err(|e| {
+                let options = MusicXmlImportOptions {
+                    parts,
+                    ..MusicXmlImportOptions::default()
+                };
+                let import = import_musicxml_path_with_report(&path, &options).map_err(|e| {
                     AppError::ScoreLoad(format!("musicxml load failed for {}: {e}", path.display()))
-                })?
+                })?;
+                (import.score, import.warnings)
+            }
+            ScoreSource::InternalDemo(id) => {
+                let score = build_demo_score(&id)
+                    .ok_or_else(|| AppError::ScoreLoad(format!("unknown demo: {id}")))?;
+                (score, Vec::new())
+            }
+            ScoreSource::CadenzaFile(path) => {
+                let path = normalize_fs_path(&path);
+                let path = resolve_existing_path(path, &["cadenza"]);
+                let file = load_scorefile_path(&path).map_err(|e| {
+                    AppError::ScoreLoad(format!(
+                        "score file load failed for {}: {e}",
+                        path.display()
+                    ))
+                })?;
+                edit_log = file.edit_log;
+                sound_overrides = file.sound_overrides;
+                (file.score, Vec::new())
             }
-            ScoreSource::InternalDemo(id) => build_demo_score(&id),
         };
 
         self.apply_score(score);
+        self.score_source = Some(loaded_from);
+        // The session that was running is over, whatever it had reached.
+        self.clear_session_snapshot();
+        self.score_edit_log = edit_log;
+        self.apply_score_sound(sound_overrides);
+        self.set_score_dirty(false);
+        if !warnings.is_empty() {
+            self.events.push_back(Event::ImportWarnings {
+                messages: warnings.iter().map(ToString::to_string).collect(),
+            });
+        }
         Ok(())
     }
 
@@ -565,27 +1892,42 @@ impl AppCore {
             .collect();
 
         self.transport.update_tempo_map(tempo_map);
+        self.transport
+            .update_time_signatures(score.time_signatures.clone(), score.pickup_ticks);
         self.transport.seek(0);
 
         let mut targets = Vec::new();
         let mut playback_events = Vec::new();
 
-        if let Some(track) = score.tracks.first() {
+        self.score_track_ids.clear();
+        if let Some(track) = score.merged_track() {
             targets = track.targets.clone();
             playback_events = track.playback_events.clone();
         }
 
         self.targets = targets.iter().map(|t| (t.id, t.clone())).collect();
+        self.note_names = derive_note_names(&score.key_signatures, &playback_events);
         let judge_events = self.judge.load_targets(targets);
+        self.retime_judge_windows();
         for event in judge_events {
             self.handle_judge_event(event);
         }
 
         self.scheduler.set_score(playback_events);
+        self.score_end_tick = score.end_tick();
+        let analysis = analyze_score(&score);
+        diag_log!(
+            Info,
+            "score analysis: {:.0} notes/s peak, start at {:.2}x",
+            analysis.peak_notes_per_second,
+            analysis.suggested_tempo_multiplier
+        );
         self.score = Some(score);
+        self.paused_tick = None;
         self.session_state = SessionState::Ready;
         self.audio_params.set_playback_enabled(false);
         self.emit_score_view();
+        self.events.push_back(Event::ScoreAnalysis { analysis });
         self.emit_session_state();
         self.emit_transport(true);
     }
@@ -599,7 +1941,9 @@ impl AppCore {
         };
         let scheduled = self.scheduler.schedule(&mut self.transport);
         for event in scheduled {
-            let _ = producer.push(event);
+            if producer.push(event).is_err() {
+                self.audio_queue_drops += 1;
+            }
         }
     }
 
@@ -607,25 +1951,203 @@ impl AppCore {
         let Some(mut consumer) = self.midi_queue_rx.take() else {
             return;
         };
-        let Some(mut producer) = self.audio_queue_tx.take() else {
-            self.midi_queue_rx = Some(consumer);
-            return;
-        };
+        // Without an output nothing can sound or be judged, but mapped controls still work.
+        let mut producer = self.audio_queue_tx.take();
 
         let mut pending = Vec::new();
         while let Ok(event) = consumer.pop() {
             pending.push(event);
         }
 
+        let mut actions = Vec::new();
         for event in pending {
             self.record_recent_input(event.event);
+            if let Some(anchor) = self.clock_anchor {
+                self.clock_stats.record_input(event.at, anchor.at);
+            }
             if let Some((tick, sample_time)) = self.map_player_event(&event) {
-                self.route_player_event(event.event, tick, sample_time, &mut producer);
+                if let Some(capture) = self.midi_capture.as_mut() {
+                    capture.record(CapturedEvent {
+                        at: event.at,
+                        sample_time,
+                        tick,
+                        event: event.event,
+                    });
+                }
+                if self.intercept_mapped_input(event.event, &mut actions) {
+                    continue;
+                }
+                if let Some(producer) = producer.as_mut() {
+                    self.route_player_event(event.event, tick, sample_time, producer);
+                }
             }
         }
 
-        self.audio_queue_tx = Some(producer);
+        self.audio_queue_tx = producer;
         self.midi_queue_rx = Some(consumer);
+
+        // Run once the queues are back, since the actions may flush or seek.
+        for action in actions {
+            self.run_midi_action(action);
+        }
+    }
+
+    /// Closes the MIDI input once its device is no longer listed, since a backend whose
+    /// device was unplugged just stops calling back, and reopens it when it comes back.
+    fn watch_midi_input(&mut self) {
+        let interval = if self.lost_midi_input.is_some() {
+            MIDI_INPUT_RETRY_INTERVAL
+        } else if self.midi_stream.is_some() {
+            MIDI_INPUT_CHECK_INTERVAL
+        } else {
+            return;
+        };
+        if self
+            .now()
+            .saturating_duration_since(self.last_midi_input_check)
+            < interval
+        {
+            return;
+        }
+        self.last_midi_input_check = self.now();
+
+        let devices = match self.midi_port.list_inputs() {
+            Ok(devices) => devices,
+            Err(err) => {
+                diag_log!(Warn, "listing midi inputs failed: {err}");
+                return;
+            }
+        };
+        if let Some(lost) = self.lost_midi_input.clone() {
+            let Some(device_id) = self.listed_midi_input(&devices, &lost) else {
+                return;
+            };
+            match self.open_midi_input(device_id.clone()) {
+                Ok(()) => self
+                    .events
+                    .push_back(Event::MidiInputRestored { device_id }),
+                Err(err) => diag_log!(Warn, "reopening midi input {device_id} failed: {err}"),
+            }
+            return;
+        }
+
+        let Some(device_id) = self.settings.selected_midi_in.clone() else {
+            return;
+        };
+        if let Some(listed) = self.listed_midi_input(&devices, &device_id) {
+            if listed != device_id {
+                // Still connected, only renumbered; the open stream carries on.
+                diag_log!(Info, "midi input {device_id} is now listed as {listed}");
+                self.settings.selected_midi_in = Some(listed);
+                self.save_settings_now();
+            }
+            return;
+        }
+        diag_log!(Warn, "midi input lost: {device_id}");
+        if let Some(stream) = self.midi_stream.take() {
+            stream.close();
+        }
+        self.midi_queue_rx = None;
+        // Whatever was held when the device went away will never be let go.
+        self.release_notes(&[Bus::UserMonitor]);
+        self.lost_midi_input = Some(device_id.clone());
+        self.events.push_back(Event::MidiInputLost { device_id });
+    }
+
+    /// Id under which the input `device_id` is listed now and available: the same id, or
+    /// else the port of the same name as the input last opened.
+    fn listed_midi_input(
+        &self,
+        devices: &[MidiInputDevice],
+        device_id: &DeviceId,
+    ) -> Option<DeviceId> {
+        let available = || devices.iter().filter(|device| device.is_available);
+        available()
+            .find(|device| &device.id == device_id)
+            .or_else(|| {
+                let name = self.midi_input_name.as_deref()?;
+                available().find(|device| device.name == name)
+            })
+            .map(|device| device.id.clone())
+    }
+
+    /// Handles learn mode and mapped controls, returning true when `event` must not be
+    /// judged or monitored. Presses of mapped controls queue their action.
+    fn intercept_mapped_input(
+        &mut self,
+        event: MidiLikeEvent,
+        actions: &mut Vec<MidiAction>,
+    ) -> bool {
+        let pressed = MidiControl::pressed_by(event);
+        if let (Some(action), Some(control)) = (self.midi_learn, pressed) {
+            self.midi_learn = None;
+            let mapping = MidiMapping {
+                controller_or_note: control,
+                action,
+            };
+            self.settings
+                .midi_mappings
+                .retain(|existing| existing.controller_or_note != control);
+            self.settings.midi_mappings.push(mapping);
+            self.events.push_back(Event::MidiMappingLearned { mapping });
+            self.emit_session_state();
+            self.save_settings();
+            return true;
+        }
+
+        let Some(mapping) = self
+            .settings
+            .midi_mappings
+            .iter()
+            .find(|mapping| mapping.controller_or_note.is_source_of(event))
+        else {
+            return false;
+        };
+        if pressed == Some(mapping.controller_or_note) {
+            actions.push(mapping.action);
+        }
+        true
+    }
+
+    fn run_midi_action(&mut self, action: MidiAction) {
+        let running = self.session_state == SessionState::Running;
+        let command = match action {
+            MidiAction::StartPractice => Command::StartPractice,
+            MidiAction::PausePractice => Command::PausePractice,
+            MidiAction::StopPractice => Command::StopPractice,
+            MidiAction::TogglePractice if running => Command::PausePractice,
+            MidiAction::TogglePractice => Command::StartPractice,
+            MidiAction::ToggleLoop => {
+                let (enabled, range) = match self.scheduler.loop_range() {
+                    Some(range) => {
+                        self.toggled_off_loop = Some(range);
+                        (false, range)
+                    }
+                    None => match self.toggled_off_loop.take() {
+                        Some(range) => (true, range),
+                        None => return,
+                    },
+                };
+                Command::SetLoop {
+                    enabled,
+                    start_tick: range.start_tick,
+                    end_tick: range.end_tick,
+                }
+            }
+            MidiAction::TempoUp | MidiAction::TempoDown => {
+                let step = if action == MidiAction::TempoUp {
+                    MIDI_TEMPO_STEP
+                } else {
+                    -MIDI_TEMPO_STEP
+                };
+                Command::SetTempoMultiplier {
+                    x: self.transport.tempo_multiplier() + step,
+                    ramp_ms: None,
+                }
+            }
+        };
+        // Failures are reported through `CommandFailed` like the frontend's commands.
+        let _ = self.handle_command(command);
     }
 
     fn route_player_event(
@@ -646,7 +2168,9 @@ impl AppCore {
                     self.handle_judge_event(event);
                 }
             }
-            MidiLikeEvent::NoteOff { .. } | MidiLikeEvent::Cc64 { .. } => {}
+            MidiLikeEvent::NoteOff { .. }
+            | MidiLikeEvent::Cc64 { .. }
+            | MidiLikeEvent::ControlChange { .. } => {}
         }
 
         if self.settings.monitor_enabled {
@@ -654,20 +2178,98 @@ impl AppCore {
                 sample_time,
                 bus: Bus::UserMonitor,
                 event,
+                generation: self.event_generation,
             };
-            let _ = producer.push(scheduled);
+            if producer.push(scheduled).is_err() {
+                self.audio_queue_drops += 1;
+            }
+        }
+    }
+
+    fn advance_judge(&mut self) {
+        if self.session_state != SessionState::Running {
+            return;
+        }
+        // Covers tempo ramps as well as direct changes.
+        if self.transport.tempo_multiplier() != self.judge_windows_multiplier {
+            self.retime_judge_windows();
+        }
+        let loop_wraps = self.transport.loop_wraps();
+        if loop_wraps != self.judged_loop_wraps {
+            // Targets left open at the loop end are missed before the next pass starts.
+            let judge_events = self.judge.advance_to(Tick::MAX);
+            for event in judge_events {
+                self.handle_judge_event(event);
+            }
+            if let Some(range) = self.scheduler.loop_range() {
+                self.seek_judge(range.start_tick);
+            }
+            self.judged_loop_wraps = loop_wraps;
+        }
+        let now_tick = self.transport.now_tick();
+        let judge_events = self.judge.advance_to(now_tick);
+        for event in judge_events {
+            self.handle_judge_event(event);
         }
     }
 
-    fn advance_judge(&mut self) {
+    /// Pulls the transport towards a hit `delta_tick` off its target, in follow mode.
+    fn follow_player(&mut self, delta_tick: Tick) {
+        let (Some(follow), Some(score)) = (self.follow, self.score.as_ref()) else {
+            return;
+        };
         if self.session_state != SessionState::Running {
             return;
         }
-        let now_tick = self.transport.now_tick();
-        let judge_events = self.judge.advance_to(now_tick);
+        let shift = follow.correction(
+            delta_tick,
+            score.ppq,
+            self.transport.now_tick(),
+            self.scheduler.loop_range(),
+        );
+        if shift == 0 {
+            return;
+        }
+        self.transport.shift_ticks(shift);
+        self.scheduler.retime(&self.transport);
+    }
+
+    /// Converts the judge's millisecond windows to ticks at the present tempo.
+    fn retime_judge_windows(&mut self) {
+        let transport = &self.transport;
+        self.judge
+            .retime_windows(|tick| transport.ticks_per_ms_at(tick));
+        self.judge_windows_multiplier = transport.tempo_multiplier();
+    }
+
+    fn seek_judge(&mut self, tick: Tick) {
+        let judge_events = self.judge.seek(tick);
         for event in judge_events {
             self.handle_judge_event(event);
         }
+        self.judged_loop_wraps = self.transport.loop_wraps();
+    }
+
+    /// Names for a target's notes in order: as spelled on the piano roll, or in the key at
+    /// the target for notes the playback events don't cover.
+    fn note_names_of(&self, target: &TargetEvent) -> Vec<String> {
+        let keys = self
+            .score
+            .as_ref()
+            .map_or(&[][..], |score| &score.key_signatures[..]);
+        target
+            .notes
+            .iter()
+            .enumerate()
+            .map(|(idx, &note)| {
+                target
+                    .note_ids
+                    .get(idx)
+                    .and_then(|id| self.note_names.get(id))
+                    .cloned()
+                    .unwrap_or_else(|| spell(note, key_signature_at(keys, target.tick)).to_string())
+            })
+            .collect()
     }
 
     fn handle_judge_event(&mut self, event: JudgeEvent) {
@@ -678,31 +2280,40 @@ impl AppCore {
                 delta_tick,
                 ..
             } => {
-                let expected_notes = self
+                let (expected_notes, expected_names, note_ids) = self
                     .targets
                     .get(&target_id)
-                    .map(|t| t.notes.clone())
+                    .map(|t| (t.notes.clone(), self.note_names_of(t), t.note_ids.clone()))
                     .unwrap_or_default();
                 self.events.push_back(Event::JudgeFeedback {
                     target_id,
                     grade,
                     delta_tick,
                     expected_notes,
+                    expected_names,
                     played_notes: Vec::new(),
+                    note_ids,
                 });
+                self.follow_player(delta_tick);
             }
-            JudgeEvent::Miss { target_id, .. } => {
-                let expected_notes = self
+            JudgeEvent::Miss {
+                target_id,
+                missed_note_ids,
+                ..
+            } => {
+                let (expected_notes, expected_names) = self
                     .targets
                     .get(&target_id)
-                    .map(|t| t.notes.clone())
+                    .map(|t| (t.notes.clone(), self.note_names_of(t)))
                     .unwrap_or_default();
                 self.events.push_back(Event::JudgeFeedback {
                     target_id,
                     grade: Grade::Miss,
                     delta_tick: 0,
                     expected_notes,
+                    expected_names,
                     played_notes: Vec::new(),
+                    note_ids: missed_note_ids,
                 });
             }
             JudgeEvent::Stats {
@@ -710,6 +2321,7 @@ impl AppCore {
                 score,
                 hit,
                 miss,
+                extra_notes,
                 ..
             } => {
                 let total = hit + miss;
@@ -722,9 +2334,16 @@ impl AppCore {
                     combo,
                     score,
                     accuracy,
+                    extra_notes,
                 });
             }
-            JudgeEvent::FocusChanged { .. } => {}
+            JudgeEvent::FocusChanged { target_id } => {
+                let tick = target_id
+                    .and_then(|id| self.targets.get(&id))
+                    .map(|target| target.tick);
+                self.events
+                    .push_back(Event::FocusChanged { target_id, tick });
+            }
         }
     }
 
@@ -733,15 +2352,17 @@ impl AppCore {
 
         // Input offset should affect judging (tick alignment), but should not introduce audible
         // latency for monitoring. We therefore compute tick with the offset, while scheduling
-        // monitor audio at the estimated physical sample_time.
-        let offset_ticks = self.transport.ms_to_ticks(self.settings.input_offset_ms);
-
+        // monitor audio at the estimated physical sample_time. The offset is a latency, so it
+        // shifts the time before converting to ticks and holds at any tempo.
+        let offset_ms = self.settings.input_offset_ms;
         let tick = if self.session_state == SessionState::Running {
-            self.transport
-                .sample_to_tick(sample_time)
-                .saturating_add(offset_ticks)
+            let offset_samples = offset_ms as i64 * self.transport.sample_rate_hz() as i64 / 1000;
+            let judged_sample = (sample_time as i64 + offset_samples).max(0) as SampleTime;
+            self.transport.sample_to_tick(judged_sample)
         } else {
-            self.transport.now_tick().saturating_add(offset_ticks)
+            self.transport
+                .now_tick()
+                .saturating_add(self.transport.ms_to_ticks(offset_ms))
         };
 
         Some((tick, sample_time))
@@ -764,16 +2385,66 @@ impl AppCore {
         }
     }
 
+    /// Like [`Self::estimate_sample_time`] but without rounding, and never before the
+    /// freshest audio clock reading.
+    fn estimate_sample_time_f64(&self, at: Instant) -> f64 {
+        let clock = self.audio_clock.get() as f64;
+        let Some(anchor) = self.clock_anchor else {
+            return clock;
+        };
+        let dt_s = at.saturating_duration_since(anchor.at).as_secs_f64();
+        let sample_rate_hz = self.transport.sample_rate_hz().max(1) as f64;
+        (anchor.sample_time as f64 + dt_s * sample_rate_hz).max(clock)
+    }
+
     fn record_recent_input(&mut self, event: MidiLikeEvent) {
         if self.recent_inputs.len() >= 20 {
             self.recent_inputs.pop_front();
         }
         self.recent_inputs.push_back(event);
-        self.events.push_back(Event::MidiInputEvent { event });
+        if self.settings.input_event_rate_hz == 0 {
+            return;
+        }
+        // A later event for the same key or controller replaces the pending one, except that
+        // a release never replaces a press: a tap shorter than the emit interval still shows.
+        let source = input_source(event);
+        let pending = &mut self.pending_input_events;
+        match event {
+            MidiLikeEvent::NoteOn { .. } => {
+                pending.retain(|pending| input_source(*pending) != source);
+                pending.push(event);
+            }
+            _ => match pending
+                .iter()
+                .rposition(|pending| input_source(*pending) == source)
+            {
+                Some(i) if !matches!(pending[i], MidiLikeEvent::NoteOn { .. }) => {
+                    pending[i] = event
+                }
+                _ => pending.push(event),
+            },
+        }
+    }
+
+    fn emit_input_events(&mut self) {
+        let rate_hz = self.settings.input_event_rate_hz;
+        if rate_hz == 0
+            || self.pending_input_events.is_empty()
+            || self
+                .now()
+                .saturating_duration_since(self.last_input_event_emit)
+                < Duration::from_secs(1) / rate_hz
+        {
+            return;
+        }
+        for event in self.pending_input_events.drain(..) {
+            self.events.push_back(Event::MidiInputEvent { event });
+        }
+        self.last_input_event_emit = self.now();
     }
 
     fn emit_recent_inputs(&mut self) {
-        if self.last_input_emit.elapsed() < Duration::from_millis(50) {
+        if self.now().saturating_duration_since(self.last_input_emit) < Duration::from_millis(50) {
             return;
         }
         if !self.recent_inputs.is_empty() {
@@ -781,13 +2452,233 @@ impl AppCore {
                 events: self.recent_inputs.iter().copied().collect(),
             });
         }
-        self.last_input_emit = Instant::now();
+        self.last_input_emit = self.now();
+    }
+
+    fn emit_audio_levels(&mut self) {
+        if self.audio_stream.is_none() {
+            return;
+        }
+        let due = self.now().saturating_duration_since(self.last_levels_emit)
+            >= Duration::from_millis(66);
+        if !due && self.audio_self_test.is_none() {
+            return;
+        }
+        // The self-test reads the meters every tick; hold the loudest reading for the UI.
+        let measured = self.audio_meters.take();
+        if let Some(test) = self.audio_self_test.as_mut() {
+            test.observe(&measured);
+        }
+        self.unreported_levels = measured.decayed_from(self.unreported_levels, 1.0);
+        if !due {
+            return;
+        }
+        let measured = std::mem::take(&mut self.unreported_levels);
+        self.audio_levels = measured.decayed_from(self.audio_levels, METER_DECAY);
+        self.events.push_back(Event::AudioLevels {
+            master: self.audio_levels.master,
+            user: self.audio_levels.user,
+            autopilot: self.audio_levels.autopilot,
+            metronome: self.audio_levels.metronome,
+            gain_reduction_db: self.audio_levels.gain_reduction_db,
+        });
+        self.last_levels_emit = self.now();
+    }
+
+    fn emit_late_events(&mut self) {
+        let late = self.late_event_stats.snapshot();
+        if late != self.late_event_counts {
+            if late.dropped_note_ons > self.late_event_counts.dropped_note_ons {
+                diag_log!(
+                    Warn,
+                    "dropped {} late note-ons (worst {:.1} ms late)",
+                    late.dropped_note_ons - self.late_event_counts.dropped_note_ons,
+                    late.max_lateness_ms
+                );
+            }
+            self.late_event_counts = late;
+            self.events.push_back(Event::LateAudioEvents {
+                late_events: late.late_events,
+                dropped_note_ons: late.dropped_note_ons,
+                max_lateness_ms: late.max_lateness_ms,
+            });
+        }
+    }
+
+    fn emit_audio_stats(&mut self) {
+        if self.audio_stream.is_none()
+            || self.now().saturating_duration_since(self.last_stats_emit) < Duration::from_secs(1)
+        {
+            return;
+        }
+        self.last_stats_emit = self.now();
+        let synth_load = self.callback_stats.synth_load();
+        self.check_synth_load(synth_load);
+        if let Some(quality) = self.quality_governor.check(&self.callback_stats.snapshot()) {
+            self.apply_synth_quality(quality, true);
+        }
+        let Some(stats) = self.clock_stats.snapshot() else {
+            return;
+        };
+        self.events.push_back(Event::AudioStats {
+            input_jitter_ms: stats.input_jitter_ms as f32,
+            clock_drift_ppm: stats.clock_drift_ppm as f32,
+            synth_load,
+        });
+        if stats.drift_warning {
+            let message = format!(
+                "audio clock drifts {:.0} ppm from its {} Hz rate; check the device sample rate",
+                stats.clock_drift_ppm,
+                self.transport.sample_rate_hz()
+            );
+            diag_log!(Warn, "{message}");
+            self.events.push_back(Event::ClockDriftWarning {
+                clock_drift_ppm: stats.clock_drift_ppm as f32,
+                message,
+            });
+        }
+    }
+
+    /// Warns once per stretch of sustained synth overload.
+    fn check_synth_load(&mut self, load: SynthLoad) {
+        if load.total_percent <= SYNTH_LOAD_WARN_PERCENT {
+            self.synth_overload_checks = 0;
+            return;
+        }
+        self.synth_overload_checks += 1;
+        if self.synth_overload_checks != SYNTH_LOAD_WARN_SECS {
+            return;
+        }
+        let message = format!(
+            "the synth needs {:.0}% of the audio time budget; choose a larger buffer size or \
+             the simple synth to avoid dropouts",
+            load.total_percent
+        );
+        diag_log!(Warn, "{message}");
+        self.events
+            .push_back(Event::SynthLoadWarning { load, message });
+    }
+
+    fn apply_synth_quality(&mut self, quality: QualityHint, automatic: bool) {
+        self.synth.set_quality(quality);
+        let event = match quality {
+            QualityHint::Reduced => {
+                let message = if automatic {
+                    "audio kept dropping out, so the synth switched to reduced quality; it \
+                     switches back once playback has been stable for a while"
+                        .to_string()
+                } else {
+                    "the synth runs at reduced quality".to_string()
+                };
+                diag_log!(Warn, "{message}");
+                Event::QualityReduced { automatic, message }
+            }
+            QualityHint::Full => {
+                diag_log!(Info, "synth back at full quality");
+                Event::QualityRestored { automatic }
+            }
+        };
+        self.events.push_back(event);
+    }
+
+    fn log_callback_overruns(&mut self) {
+        let overruns = self.callback_stats.snapshot().overruns;
+        if overruns > self.logged_overruns {
+            diag_log!(
+                Warn,
+                "{} audio callbacks overran their buffer",
+                overruns - self.logged_overruns
+            );
+            self.logged_overruns = overruns;
+        }
+    }
+
+    fn diagnostics_snapshot(&self) -> Result<DiagnosticsSnapshot, AppError> {
+        Ok(DiagnosticsSnapshot {
+            settings: self.settings.clone(),
+            midi_inputs: self.midi_port.list_inputs()?,
+            audio_outputs: self.audio_port.list_outputs()?,
+            recent_events: self.recent_inputs.iter().copied().collect(),
+            raw_midi_messages: self.midi_port.raw_messages(),
+            audio_callbacks: self
+                .audio_stream
+                .as_ref()
+                .map(|_| self.callback_stats.snapshot()),
+            late_events: self.late_event_stats.snapshot(),
+            queue_drops: QueueDropCounts {
+                audio_queue: self.audio_queue_drops,
+                midi_queue: self.midi_queue_drops.load(Ordering::Relaxed),
+                event_queue: self.events.dropped(),
+            },
+            synth: self.synth_status.clone(),
+            transport: TransportSnapshot {
+                state: format!("{:?}", self.transport.state()),
+                tick: self.transport.now_tick(),
+                sample: self.transport.now_sample(),
+                tempo_multiplier: self.transport.tempo_multiplier(),
+                sample_rate_hz: self.transport.sample_rate_hz(),
+                lookahead_ms: self.scheduler.effective_lookahead_ms(),
+            },
+            judge: self.judge.snapshot(),
+        })
+    }
+
+    fn stop_audio_recording(&mut self) -> Result<(), AppError> {
+        let recorder = self
+            .audio_recorder
+            .as_mut()
+            .ok_or_else(|| AppError::InvalidState("not recording".to_string()))?;
+        let summary = recorder.stop()?;
+        diag_log!(
+            Info,
+            "recording stopped: {:.1} s, {} dropped frames",
+            summary.duration_secs(),
+            summary.dropped_frames
+        );
+        self.events.push_back(Event::AudioRecordingUpdated {
+            recording: false,
+            path: Some(summary.path.display().to_string()),
+            duration_secs: summary.duration_secs(),
+            dropped_frames: summary.dropped_frames,
+        });
+        Ok(())
+    }
+
+    fn emit_recording_progress(&mut self) {
+        let recording = self
+            .audio_recorder
+            .as_ref()
+            .is_some_and(AudioRecorder::is_recording);
+        if !recording
+            || self
+                .now()
+                .saturating_duration_since(self.last_recording_emit)
+                < Duration::from_secs(1)
+        {
+            return;
+        }
+        self.emit_recording_state();
+        self.last_recording_emit = self.now();
+    }
+
+    fn emit_recording_state(&mut self) {
+        let Some(recorder) = self.audio_recorder.as_ref() else {
+            return;
+        };
+        self.events.push_back(Event::AudioRecordingUpdated {
+            recording: recorder.is_recording(),
+            path: recorder.path().map(|path| path.display().to_string()),
+            duration_secs: recorder.frames_written() as f64
+                / recorder.sample_rate_hz().max(1) as f64,
+            dropped_frames: recorder.dropped_frames(),
+        });
     }
 
     fn emit_session_state(&mut self) {
         self.events.push_back(Event::SessionStateUpdated {
             state: self.session_state,
             settings: self.settings.clone(),
+            score_sound_overrides: self.score_sound.clone(),
         });
     }
 
@@ -795,19 +2686,38 @@ impl AppCore {
         let Some(score) = self.score.as_ref() else {
             return;
         };
+        let grid = derive_grid_lines(
+            self.transport.time_signatures(),
+            score.ppq,
+            self.score_end_tick,
+        );
 
-        let Some(track) = score.tracks.first() else {
+        let from_omr = matches!(score.meta.source, cadenza_domain_score::ScoreSource::PdfOmr);
+        let origin_path = score
+            .meta
+            .origin_path
+            .as_ref()
+            .map(|path| path.display().to_string());
+
+        let Some(track) = practice_track(score, &self.score_track_ids) else {
+            self.upcoming_targets
+                .set_targets(Vec::new(), Tick::from(score.ppq));
             self.events.push_back(Event::ScoreViewUpdated {
                 title: score.meta.title.clone(),
+                composer: score.meta.composer.clone(),
+                from_omr,
+                origin_path,
                 ppq: score.ppq,
+                key_signatures: score.key_signatures.clone(),
                 notes: Vec::new(),
                 targets: Vec::new(),
                 pedal: Vec::new(),
+                grid,
             });
             return;
         };
 
-        let notes = derive_note_spans(score.ppq, &track.playback_events);
+        let notes = derive_note_spans(score.ppq, &score.key_signatures, &track.playback_events);
         let pedal = derive_pedal_spans(&track.playback_events);
         let mut targets: Vec<PianoRollTargetDto> = track
             .targets
@@ -816,27 +2726,54 @@ impl AppCore {
                 id: t.id,
                 tick: t.tick,
                 notes: t.notes.clone(),
+                hand: t.hand,
+                note_ids: t.note_ids.clone(),
             })
             .collect();
         targets.sort_by_key(|t| t.tick);
+        self.upcoming_targets
+            .set_targets(targets.clone(), Tick::from(score.ppq));
 
         self.events.push_back(Event::ScoreViewUpdated {
             title: score.meta.title.clone(),
+            composer: score.meta.composer.clone(),
+            from_omr,
+            origin_path,
             ppq: score.ppq,
+            key_signatures: score.key_signatures.clone(),
             notes,
             targets,
             pedal,
+            grid,
         });
     }
 
+    fn emit_upcoming_targets(&mut self) {
+        let now_tick = self.transport.now_tick();
+        if let Some(targets) = self
+            .upcoming_targets
+            .poll(now_tick, self.judge.current_focus())
+        {
+            self.events
+                .push_back(Event::UpcomingTargets { now_tick, targets });
+        }
+    }
+
     fn emit_transport(&mut self, force: bool) {
-        let now = Instant::now();
+        let now = self.now();
         if !force && now.duration_since(self.last_transport_emit) < Duration::from_millis(33) {
             return;
         }
+        let position = self.transport.now_bar_beat();
+        let sample_time = self.estimate_sample_time_f64(now);
         self.events.push_back(Event::TransportUpdated {
             tick: self.transport.now_tick(),
+            tick_precise: self.transport.now_tick_f64(sample_time),
             sample_time: self.transport.now_sample(),
+            bar: position.bar,
+            beat: position.beat,
+            position_ms: self.transport.tick_to_ms(self.transport.now_tick()),
+            duration_ms: self.transport.total_duration_ms(self.score_end_tick),
             playing: self.session_state == SessionState::Running,
             tempo_multiplier: self.transport.tempo_multiplier(),
             loop_range: self.scheduler.loop_range(),
@@ -845,8 +2782,34 @@ impl AppCore {
     }
 
     fn set_loop(&mut self, range: Option<LoopRange>) {
+        let requeue = self.session_state == SessionState::Running && self.scheduler.wrap_queued();
         self.scheduler.set_loop(range);
         self.transport.set_loop(range);
+        if requeue {
+            // The old range's next pass is already queued; send it again from here.
+            self.scheduler.seek(self.transport.now_tick());
+            self.flush_audio_notes();
+        }
+        let judge_events = self
+            .judge
+            .set_range(range.map(|range| range.start_tick..range.end_tick));
+        for event in judge_events {
+            self.handle_judge_event(event);
+        }
+        self.emit_transport(true);
+    }
+
+    /// Moves the transport, scheduler and judge to `tick`.
+    fn seek_to(&mut self, tick: Tick) {
+        self.paused_tick = None;
+        self.transport.seek(tick);
+        if self.session_state == SessionState::Running {
+            // Otherwise the next sync maps the clock back to the old position.
+            self.transport.align_to_sample_time(self.audio_clock.get());
+        }
+        self.scheduler.seek(tick);
+        self.seek_judge(tick);
+        self.flush_audio_notes();
         self.emit_transport(true);
     }
 
@@ -864,53 +2827,197 @@ impl AppCore {
             return;
         }
 
-        self.clock_anchor = Some(ClockAnchor {
-            at: Instant::now(),
+        let anchor = ClockAnchor {
+            at: self.now(),
             sample_time: self.audio_clock.get(),
-        });
+        };
+        self.clock_stats
+            .record_anchor(anchor.at, anchor.sample_time);
+        self.clock_anchor = Some(anchor);
     }
 
+    /// Drops everything still queued for audio and lets go of the playback and monitor notes.
     fn flush_audio_notes(&mut self) {
+        self.invalidate_queued_audio();
+        self.release_sent_notes();
+        self.release_notes(&[Bus::UserMonitor]);
+    }
+
+    /// Sends NoteOffs for just the notes the scheduler has sounding. The playback bus is
+    /// released whole instead when any of them, or an event sent since the last release,
+    /// did not fit in the queue, or when the scheduler was rebuilt since.
+    fn release_sent_notes(&mut self) {
+        let released = self.scheduler.release_sent(self.audio_clock.get());
+        let mut release_whole = self.audio_queue_drops != self.audio_queue_drops_at_release
+            || std::mem::take(&mut self.sent_notes_lost);
+        if let Some(producer) = self.audio_queue_tx.as_mut() {
+            for event in released {
+                if producer.push(event).is_err() {
+                    self.audio_queue_drops += 1;
+                    release_whole = true;
+                }
+            }
+        }
+        if release_whole {
+            self.release_notes(&[Bus::Autopilot]);
+        }
+        self.audio_queue_drops_at_release = self.audio_queue_drops;
+    }
+
+    /// Starts a new event generation: whatever the audio thread has not played yet is
+    /// dropped there, NoteOffs included, so callers release the buses they touched.
+    fn invalidate_queued_audio(&mut self) {
+        self.event_generation = self.event_generation.wrapping_add(1);
+        self.scheduler.set_generation(self.event_generation);
+        self.audio_params
+            .set_event_generation(self.event_generation);
+    }
+
+    /// Lets go of every note and the pedal on `buses`.
+    fn release_notes(&mut self, buses: &[Bus]) {
         let Some(producer) = self.audio_queue_tx.as_mut() else {
             return;
         };
         let now = self.audio_clock.get();
-        let mut events = Vec::new();
-        for note in 0..128u8 {
-            events.push(ScheduledEvent {
-                sample_time: now,
-                bus: Bus::Autopilot,
-                event: MidiLikeEvent::NoteOff { note },
-            });
-            events.push(ScheduledEvent {
-                sample_time: now,
-                bus: Bus::UserMonitor,
-                event: MidiLikeEvent::NoteOff { note },
-            });
+        for &bus in buses {
+            let _ = producer.push(all_notes_off(now, bus, self.event_generation));
         }
-        events.push(ScheduledEvent {
-            sample_time: now,
-            bus: Bus::Autopilot,
-            event: MidiLikeEvent::Cc64 { value: 0 },
-        });
-        events.push(ScheduledEvent {
-            sample_time: now,
-            bus: Bus::UserMonitor,
-            event: MidiLikeEvent::Cc64 { value: 0 },
-        });
+    }
+
+    /// Silences everything at once: the scheduler forgets what it has queued, the audio
+    /// thread drops what it has not played yet, and every bus is released.
+    fn panic_all_notes(&mut self) {
+        self.scheduler.clear_pending();
+        self.invalidate_queued_audio();
+        self.release_notes(&[Bus::UserMonitor, Bus::Autopilot, Bus::MetronomeFx]);
+        diag_log!(Info, "panic: all notes released");
+        self.events.push_back(Event::AllNotesReleased);
+    }
+
+    /// Writes pending settings changes now instead of at the next due [`AppCore::tick`].
+    pub fn flush_settings(&mut self) {
+        if !self.settings_dirty {
+            return;
+        }
+        self.settings_dirty = false;
+        self.last_settings_save = self.now();
+        if let Some(storage) = self.storage.as_ref() {
+            if let Err(err) = storage.save_settings(&self.settings) {
+                diag_log!(Warn, "settings not saved: {err}");
+            }
+        }
+    }
+
+    /// Marks the settings as changed; they are written by a later tick or flush.
+    fn save_settings(&mut self) {
+        self.settings_dirty = true;
+    }
+
+    fn save_settings_now(&mut self) {
+        self.save_settings();
+        self.flush_settings();
+    }
 
-        for event in events {
-            let _ = producer.push(event);
+    /// Writes a snapshot of the running session every [`SESSION_AUTOSAVE_INTERVAL`], so a
+    /// crash does not lose all of it.
+    fn autosave_session_if_due(&mut self) {
+        if self.session_state != SessionState::Running || self.shut_down {
+            return;
+        }
+        let now = self.now();
+        if now.saturating_duration_since(self.last_session_autosave) < SESSION_AUTOSAVE_INTERVAL {
+            return;
+        }
+        self.last_session_autosave = now;
+        let (Some(storage), Some(score_source)) =
+            (self.storage.as_ref(), self.score_source.clone())
+        else {
+            return;
+        };
+        let snapshot = SessionSnapshot {
+            score_source,
+            score_title: self
+                .score
+                .as_ref()
+                .and_then(|score| score.meta.title.clone()),
+            tick: self.transport.now_tick(),
+            stats: self.judge.snapshot(),
+            saved_at_unix_ms: unix_ms_now(),
+        };
+        let saved = snapshot
+            .to_json()
+            .map_err(|err| StorageError::Serde(err.to_string()))
+            .and_then(|data| storage.save_session_snapshot(&data));
+        match saved {
+            Ok(()) => self.session_autosaved = true,
+            Err(err) => diag_log!(Warn, "session snapshot not saved: {err}"),
         }
     }
 
-    fn save_settings(&self) {
+    fn clear_session_snapshot(&mut self) {
+        if !std::mem::take(&mut self.session_autosaved) {
+            return;
+        }
         if let Some(storage) = self.storage.as_ref() {
-            let _ = storage.save_settings(&self.settings);
+            if let Err(err) = storage.clear_session_snapshot() {
+                diag_log!(Warn, "session snapshot not removed: {err}");
+            }
+        }
+    }
+
+    fn resume_recovered_session(&mut self) -> Result<(), AppError> {
+        if self.session_state == SessionState::Running {
+            return Err(AppError::InvalidState(
+                "stop practice before resuming a recovered session".to_string(),
+            ));
         }
+        let Some(snapshot) = self.recovered_session.take() else {
+            return Err(AppError::InvalidState(
+                "no recovered session to resume".to_string(),
+            ));
+        };
+        if let Err(err) = self.load_score(snapshot.score_source.clone()) {
+            self.recovered_session = Some(snapshot);
+            return Err(err);
+        }
+        self.seek_to(snapshot.tick);
+        let judge_events = self.judge.restore_stats(&snapshot.stats);
+        for event in judge_events {
+            self.handle_judge_event(event);
+        }
+        Ok(())
+    }
+
+    fn save_settings_if_due(&mut self) {
+        if self.shut_down {
+            return;
+        }
+        if self.settings_dirty
+            && self
+                .now()
+                .saturating_duration_since(self.last_settings_save)
+                >= SETTINGS_SAVE_INTERVAL
+        {
+            self.flush_settings();
+        }
+    }
+}
+
+impl Drop for AppCore {
+    fn drop(&mut self) {
+        self.shutdown();
     }
 }
 
+fn score_end_tick(score: &Score) -> Tick {
+    score
+        .tracks
+        .iter()
+        .flat_map(|track| track.playback_events.iter().map(|e| e.tick))
+        .max()
+        .map_or(0, |tick| tick.saturating_add(1))
+}
+
 fn normalize_fs_path(raw: &str) -> PathBuf {
     let mut s = raw.trim();
     if (s.starts_with('"') && s.ends_with('"')) || (s.starts_with('\'') && s.ends_with('\'')) {
@@ -958,65 +3065,15 @@ fn decode_file_url(s: &str) -> Option<String> {
     Some(percent_decode(s))
 }
 
-fn build_demo_score(id: &str) -> Score {
-    let ppq: u16 = 480;
-    let tempo_map = vec![cadenza_domain_score::TempoPoint {
-        tick: 0,
-        us_per_quarter: 500_000,
-    }];
-
-    let (title, notes) = match id {
-        "c_major_scale" | "scale_c_major" | "scale" => (
-            "Demo: C major scale".to_string(),
-            vec![60u8, 62, 64, 65, 67, 69, 71, 72],
-        ),
-        _ => (
-            "Demo: C major scale".to_string(),
-            vec![60u8, 62, 64, 65, 67, 69, 71, 72],
-        ),
-    };
-
-    let mut playback_events = Vec::new();
-    let mut targets = Vec::new();
-
-    let dur = Tick::from(ppq);
-    for (idx, note) in notes.into_iter().enumerate() {
-        let tick = Tick::from(idx as i64) * dur;
-        let velocity = 92u8;
-        playback_events.push(cadenza_domain_score::PlaybackMidiEvent {
-            tick,
-            event: MidiLikeEvent::NoteOn { note, velocity },
-            hand: None,
-        });
-        playback_events.push(cadenza_domain_score::PlaybackMidiEvent {
-            tick: tick + dur,
-            event: MidiLikeEvent::NoteOff { note },
-            hand: None,
-        });
-
-        targets.push(TargetEvent {
-            id: (idx as u64) + 1,
-            tick,
-            notes: vec![note],
-            hand: None,
-            measure_index: None,
-        });
-    }
-
-    Score {
-        meta: cadenza_domain_score::ScoreMeta {
-            title: Some(title),
-            source: cadenza_domain_score::ScoreSource::Internal,
-        },
-        ppq,
-        tempo_map,
-        tracks: vec![cadenza_domain_score::Track {
-            id: 0,
-            name: "Demo".to_string(),
-            hand: None,
-            targets,
-            playback_events,
-        }],
+/// The track practiced from `score`: all tracks merged, or the selected ones.
+fn practice_track<'a>(
+    score: &'a Score,
+    track_ids: &[u32],
+) -> Option<Cow<'a, cadenza_domain_score::Track>> {
+    if track_ids.is_empty() {
+        score.merged_track()
+    } else {
+        Some(Cow::Owned(score.merged_for_practice(track_ids)))
     }
 }
 
@@ -1047,7 +3104,7 @@ fn percent_decode(s: &str) -> String {
     String::from_utf8_lossy(&out).into_owned()
 }
 
-fn expand_tilde(path: &str) -> PathBuf {
+pub(crate) fn expand_tilde(path: &str) -> PathBuf {
     let Some(rest) = path.strip_prefix("~/") else {
         return PathBuf::from(path);
     };
@@ -1063,35 +3120,69 @@ fn default_judge_config() -> JudgeConfig {
             perfect: 30,
             good: 80,
         },
+        // The tick windows above at 120 bpm, kept at any tempo.
+        window_ms: Some(TimingWindowMs {
+            perfect: 31.25,
+            good: 83.3,
+        }),
         chord_roll: ChordRollTicks(24),
         wrong_note_policy: WrongNotePolicy::DegradePerfect,
         advance: AdvanceMode::OnResolve,
+        first_target_grace: 120,
+        // 120 ticks at 120 bpm.
+        first_target_grace_ms: Some(125.0),
     }
 }
 
+/// Pairs each note off with the note on sharing its id, or with the earliest note of its
+/// pitch still sounding, so voices holding the same key keep their own lengths.
 fn derive_note_spans(
     ppq: u16,
+    key_signatures: &[KeySignaturePoint],
     events: &[cadenza_domain_score::PlaybackMidiEvent],
 ) -> Vec<PianoRollNoteDto> {
     let default_len = Tick::from(ppq.max(1));
-    let mut stacks: Vec<Vec<(Tick, u8, Option<cadenza_domain_score::Hand>)>> =
-        vec![Vec::new(); 128];
+    type Sounding = (
+        Tick,
+        u8,
+        Option<cadenza_domain_score::Hand>,
+        Option<NoteId>,
+        String,
+    );
+    let mut sounding: Vec<VecDeque<Sounding>> = vec![VecDeque::new(); 128];
     let mut notes: Vec<PianoRollNoteDto> = Vec::new();
 
     for event in events {
         match event.event {
             MidiLikeEvent::NoteOn { note, velocity } => {
                 let idx = note as usize;
-                if idx < stacks.len() {
-                    stacks[idx].push((event.tick, velocity, event.hand));
+                if idx < sounding.len() {
+                    let name = note_on_spelling(event, key_signatures)
+                        .map(|spelling| spelling.to_string())
+                        .unwrap_or_default();
+                    sounding[idx].push_back((
+                        event.tick,
+                        velocity,
+                        event.hand,
+                        event.note_id,
+                        name,
+                    ));
                 }
             }
             MidiLikeEvent::NoteOff { note } => {
                 let idx = note as usize;
-                if idx >= stacks.len() {
+                if idx >= sounding.len() {
                     continue;
                 }
-                if let Some((start_tick, velocity, hand)) = stacks[idx].pop() {
+                let closes = event
+                    .note_id
+                    .and_then(|off_id| {
+                        sounding[idx]
+                            .iter()
+                            .position(|(_, _, _, id, _)| *id == Some(off_id))
+                    })
+                    .unwrap_or(0);
+                if let Some((start_tick, velocity, hand, id, name)) = sounding[idx].remove(closes) {
                     let mut end_tick = event.tick;
                     if end_tick <= start_tick {
                         end_tick = start_tick.saturating_add(1);
@@ -1102,15 +3193,17 @@ fn derive_note_spans(
                         end_tick,
                         velocity,
                         hand,
+                        id,
+                        name,
                     });
                 }
             }
-            MidiLikeEvent::Cc64 { .. } => {}
+            MidiLikeEvent::Cc64 { .. } | MidiLikeEvent::ControlChange { .. } => {}
         }
     }
 
-    for (note, stack) in stacks.iter_mut().enumerate() {
-        while let Some((start_tick, velocity, hand)) = stack.pop() {
+    for (note, held) in sounding.iter_mut().enumerate() {
+        while let Some((start_tick, velocity, hand, id, name)) = held.pop_front() {
             let end_tick = start_tick.saturating_add(default_len);
             notes.push(PianoRollNoteDto {
                 note: note as u8,
@@ -1118,6 +3211,8 @@ fn derive_note_spans(
                 end_tick,
                 velocity,
                 hand,
+                id,
+                name,
             });
         }
     }
@@ -1126,6 +3221,66 @@ fn derive_note_spans(
     notes
 }
 
+fn derive_note_names(
+    key_signatures: &[KeySignaturePoint],
+    events: &[cadenza_domain_score::PlaybackMidiEvent],
+) -> HashMap<NoteId, String> {
+    events
+        .iter()
+        .filter_map(|event| {
+            let spelling = note_on_spelling(event, key_signatures)?;
+            Some((event.note_id?, spelling.to_string()))
+        })
+        .collect()
+}
+
+/// Lines for every bar, beat and half beat up to `end_tick`. Compound meters such as 6/8
+/// beat in dotted groups of three with each written beat as a subdivision; a pickup bar
+/// gets the beats it holds but no opening bar line.
+fn derive_grid_lines(meters: &TimeSignatureMap, ppq: u16, end_tick: Tick) -> Vec<GridLineDto> {
+    let mut lines = Vec::new();
+    let mut bar = if meters.pickup_ticks() > 0 { 0 } else { 1 };
+    loop {
+        let start = meters.tick_of_bar(bar);
+        if start > end_tick {
+            break;
+        }
+        let next = meters.tick_of_bar(bar + 1);
+        let (beats, beat_ticks) = meters.meter_at(start);
+        let compound = beats > 3 && beats % 3 == 0 && beat_ticks < Tick::from(ppq);
+        let (group_ticks, sub_ticks) = if compound {
+            (beat_ticks * 3, beat_ticks)
+        } else if beat_ticks % 2 == 0 {
+            (beat_ticks, beat_ticks / 2)
+        } else {
+            (beat_ticks, beat_ticks)
+        };
+        // A pickup is the end of a full bar.
+        let phase = if bar == 0 {
+            beat_ticks * Tick::from(beats) - (next - start)
+        } else {
+            0
+        };
+
+        let first = start + (sub_ticks - phase % sub_ticks) % sub_ticks;
+        let mut tick = first;
+        while tick < next && tick <= end_tick {
+            let offset = tick - start + phase;
+            let kind = if offset == 0 {
+                GridLineKind::Bar
+            } else if offset % group_ticks == 0 {
+                GridLineKind::Beat
+            } else {
+                GridLineKind::Subdivision
+            };
+            lines.push(GridLineDto { tick, kind });
+            tick += sub_ticks;
+        }
+        bar += 1;
+    }
+    lines
+}
+
 fn derive_pedal_spans(
     events: &[cadenza_domain_score::PlaybackMidiEvent],
 ) -> Vec<PianoRollPedalDto> {
@@ -1143,7 +3298,7 @@ fn derive_pedal_spans(
         return Vec::new();
     }
 
-    cc.sort_by(|a, b| a.0.cmp(&b.0));
+    cc.sort_by_key(|a| a.0);
 
     let mut spans = Vec::new();
     let mut down = false;
@@ -1174,3 +3329,117 @@ fn derive_pedal_spans(
 
     spans
 }
+
+fn soundfont_status(path: &str, result: &Result<SoundFontInfo, SynthError>) -> SynthStatus {
+    match result {
+        Ok(info) => {
+            diag_log!(
+                Info,
+                "soundfont loaded: {path} ({} presets)",
+                info.preset_count
+            );
+            SynthStatus {
+                soundfont_loaded: true,
+                soundfont_path: Some(path.to_string()),
+                soundfont_name: Some(info.name.clone()),
+                preset_count: Some(info.preset_count as u32),
+                message: None,
+            }
+        }
+        Err(err) => {
+            diag_log!(Error, "soundfont load failed for {path}: {err}");
+            SynthStatus {
+                soundfont_loaded: false,
+                soundfont_path: Some(path.to_string()),
+                soundfont_name: None,
+                preset_count: None,
+                message: Some(err.to_string()),
+            }
+        }
+    }
+}
+
+fn all_notes_off(sample_time: SampleTime, bus: Bus, generation: u32) -> ScheduledEvent {
+    ScheduledEvent {
+        sample_time,
+        bus,
+        event: MidiLikeEvent::ControlChange {
+            controller: ALL_NOTES_OFF,
+            value: 0,
+        },
+        generation,
+    }
+}
+
+/// What a live input event updates: a key, or a controller (CC64 is controller 64).
+fn input_source(event: MidiLikeEvent) -> MidiControl {
+    match event {
+        MidiLikeEvent::NoteOn { note, .. } | MidiLikeEvent::NoteOff { note } => {
+            MidiControl::Note(note)
+        }
+        MidiLikeEvent::Cc64 { .. } => MidiControl::Controller(64),
+        MidiLikeEvent::ControlChange { controller, .. } => MidiControl::Controller(controller),
+    }
+}
+
+fn buffer_size_supported(device: Option<&AudioOutputDevice>, frames: u32) -> bool {
+    device
+        .and_then(|device| device.buffer_size_range)
+        .is_none_or(|range| range.contains(frames))
+}
+
+fn command_failed_event(command_kind: &str, err: &AppError) -> Event {
+    Event::CommandFailed {
+        command_kind: command_kind.to_string(),
+        message: err.to_string(),
+        recoverable: err.is_recoverable(),
+    }
+}
+
+/// Sets the saved programs again; a new soundfont or sample rate may have reset them.
+/// Reads and removes the snapshot a session left behind when the app last ended without
+/// stopping it.
+fn take_session_snapshot(storage: &dyn StoragePort) -> Option<SessionSnapshot> {
+    let data = match storage.load_session_snapshot() {
+        Ok(data) => data?,
+        Err(err) => {
+            diag_log!(Warn, "session snapshot not read: {err}");
+            return None;
+        }
+    };
+    if let Err(err) = storage.clear_session_snapshot() {
+        diag_log!(Warn, "session snapshot not removed: {err}");
+    }
+    match SessionSnapshot::from_json(&data) {
+        Ok(snapshot) => {
+            diag_log!(
+                Info,
+                "recovered a session stopped at tick {}",
+                snapshot.tick
+            );
+            Some(snapshot)
+        }
+        Err(err) => {
+            diag_log!(Warn, "session snapshot unreadable, dropped: {err}");
+            None
+        }
+    }
+}
+
+fn apply_bus_programs(synth: &dyn SynthPort, programs: &BTreeMap<Bus, u8>) {
+    for (&bus, &gm_program) in programs {
+        if let Err(err) = synth.set_program(bus, gm_program) {
+            diag_log!(Warn, "program {gm_program} for {bus:?} not applied: {err}");
+        }
+    }
+}
+
+fn soundfont_status_event(status: &SynthStatus) -> Event {
+    Event::SoundFontStatus {
+        loaded: status.soundfont_loaded,
+        path: status.soundfont_path.clone(),
+        name: status.soundfont_name.clone(),
+        preset_count: status.preset_count,
+        message: status.message.clone(),
+    }
+}
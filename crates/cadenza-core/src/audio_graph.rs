@@ -1,14 +1,25 @@
+use crate::audio_meters::{AudioMeters, MeterAccumulator};
 use crate::audio_params::AudioParams;
+use crate::audio_recorder::AudioRecorderTap;
+use crate::ducker::Ducker;
+use crate::limiter::Limiter;
 use cadenza_ports::audio::AudioRenderCallback;
-use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::midi::{MidiLikeEvent, ALL_NOTES_OFF};
 use cadenza_ports::playback::ScheduledEvent;
 use cadenza_ports::synth::SynthPort;
 use cadenza_ports::types::{Bus, SampleTime};
 use rtrb::Consumer;
+use serde::{Deserialize, Serialize};
 use std::sync::{
-    atomic::{AtomicU64, Ordering},
+    atomic::{AtomicU32, AtomicU64, Ordering},
     Arc,
 };
+use std::time::{Duration, Instant};
+
+/// Late NoteOns beyond this are dropped rather than played out of time.
+pub const DEFAULT_LATE_DROP_MS: f32 = 100.0;
+/// Time constant of the smoothed [`SynthLoad`].
+pub const SYNTH_LOAD_SMOOTHING_SECS: f64 = 0.5;
 
 pub struct AudioClock {
     sample_time: AtomicU64,
@@ -36,16 +47,207 @@ impl Default for AudioClock {
     }
 }
 
+/// Events that reached the audio thread after their scheduled time, counted since the graph
+/// was created. Written by the audio thread, read by the core thread.
+#[derive(Debug, Default)]
+pub struct LateEventStats {
+    late_events: AtomicU64,
+    dropped_note_ons: AtomicU64,
+    max_lateness_ms: AtomicU32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LateEventCounts {
+    pub late_events: u64,
+    pub dropped_note_ons: u64,
+    pub max_lateness_ms: f32,
+}
+
+impl LateEventStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn record(&self, late_events: u64, dropped_note_ons: u64, max_lateness_ms: f32) {
+        self.late_events.fetch_add(late_events, Ordering::Relaxed);
+        self.dropped_note_ons
+            .fetch_add(dropped_note_ons, Ordering::Relaxed);
+        // Non-negative floats order the same as their bit patterns.
+        self.max_lateness_ms
+            .fetch_max(max_lateness_ms.max(0.0).to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> LateEventCounts {
+        LateEventCounts {
+            late_events: self.late_events.load(Ordering::Relaxed),
+            dropped_note_ons: self.dropped_note_ons.load(Ordering::Relaxed),
+            max_lateness_ms: f32::from_bits(self.max_lateness_ms.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Render-callback timing, written by the audio thread and read for diagnostics.
+#[derive(Debug, Default)]
+pub struct AudioCallbackStats {
+    callbacks: AtomicU64,
+    overruns: AtomicU64,
+    total_callback_us: AtomicU64,
+    max_callback_us: AtomicU64,
+    buffer_frames: AtomicU32,
+    sample_rate_hz: AtomicU32,
+    /// Smoothed [`SynthLoad`] percentages as f32 bits, indexed like the graph's bus slots.
+    synth_load: [AtomicU32; 4],
+}
+
+/// Time the synth takes to render each bus, as a percentage of the audio it renders, smoothed
+/// over about [`SYNTH_LOAD_SMOOTHING_SECS`]. Over 100 the output cannot keep up.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SynthLoad {
+    pub total_percent: f32,
+    pub user_percent: f32,
+    pub autopilot_percent: f32,
+    pub metronome_percent: f32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AudioCallbackCounts {
+    pub callbacks: u64,
+    /// Callbacks that took longer than the audio they rendered, i.e. likely dropouts.
+    pub overruns: u64,
+    pub mean_callback_us: u64,
+    pub max_callback_us: u64,
+    pub buffer_frames: u32,
+    /// Output latency contributed by one buffer.
+    pub buffer_latency_ms: f32,
+    #[serde(default)]
+    pub synth_load: SynthLoad,
+}
+
+impl AudioCallbackStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn record(&self, frames: usize, sample_rate_hz: u32, elapsed: Duration) {
+        let elapsed_us = elapsed.as_micros() as u64;
+        let budget_us = frames as u64 * 1_000_000 / sample_rate_hz.max(1) as u64;
+        self.callbacks.fetch_add(1, Ordering::Relaxed);
+        if elapsed_us > budget_us {
+            self.overruns.fetch_add(1, Ordering::Relaxed);
+        }
+        self.total_callback_us
+            .fetch_add(elapsed_us, Ordering::Relaxed);
+        self.max_callback_us
+            .fetch_max(elapsed_us, Ordering::Relaxed);
+        self.buffer_frames.store(frames as u32, Ordering::Relaxed);
+        self.sample_rate_hz.store(sample_rate_hz, Ordering::Relaxed);
+    }
+
+    /// Folds one callback's synth render times, per bus slot with the total in slot 0, into
+    /// the smoothed load.
+    fn record_synth_load(&self, frames: usize, sample_rate_hz: u32, render_time: &[Duration; 4]) {
+        let budget_secs = frames as f64 / sample_rate_hz.max(1) as f64;
+        if budget_secs <= 0.0 {
+            return;
+        }
+        let alpha = 1.0 - (-budget_secs / SYNTH_LOAD_SMOOTHING_SECS).exp();
+        for (load, time) in self.synth_load.iter().zip(render_time) {
+            let percent = time.as_secs_f64() / budget_secs * 100.0;
+            let previous = f32::from_bits(load.load(Ordering::Relaxed)) as f64;
+            let smoothed = previous + (percent - previous) * alpha;
+            load.store((smoothed as f32).to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    pub fn snapshot(&self) -> AudioCallbackCounts {
+        let callbacks = self.callbacks.load(Ordering::Relaxed);
+        let buffer_frames = self.buffer_frames.load(Ordering::Relaxed);
+        let sample_rate_hz = self.sample_rate_hz.load(Ordering::Relaxed).max(1);
+        AudioCallbackCounts {
+            callbacks,
+            overruns: self.overruns.load(Ordering::Relaxed),
+            mean_callback_us: self.total_callback_us.load(Ordering::Relaxed) / callbacks.max(1),
+            max_callback_us: self.max_callback_us.load(Ordering::Relaxed),
+            buffer_frames,
+            buffer_latency_ms: buffer_frames as f32 * 1000.0 / sample_rate_hz as f32,
+            synth_load: self.synth_load(),
+        }
+    }
+
+    pub fn synth_load(&self) -> SynthLoad {
+        let percent = |slot: usize| f32::from_bits(self.synth_load[slot].load(Ordering::Relaxed));
+        SynthLoad {
+            total_percent: percent(0),
+            user_percent: percent(bus_slot(Bus::UserMonitor)),
+            autopilot_percent: percent(bus_slot(Bus::Autopilot)),
+            metronome_percent: percent(bus_slot(Bus::MetronomeFx)),
+        }
+    }
+}
+
 pub struct AudioGraph {
     synth: Arc<dyn SynthPort>,
     params: Arc<AudioParams>,
     clock: Arc<AudioClock>,
+    meters: Arc<AudioMeters>,
     consumer: Consumer<ScheduledEvent>,
     scratch_l: Vec<f32>,
     scratch_r: Vec<f32>,
     events: Vec<ScheduledEvent>,
     pending: Option<ScheduledEvent>,
-    limiter_gain: f32,
+    limiter: Limiter,
+    ducker: Ducker,
+    /// Per frame of a segment: the monitor level, then the autopilot gain the ducker makes
+    /// of it.
+    duck_gain: Vec<f32>,
+    /// Per-slot levels (see [`bus_slot`]), published at the end of each callback.
+    meter_acc: [MeterAccumulator; 4],
+    /// Per-slot gains, eased towards the `AudioParams` values to avoid zipper noise.
+    gains: [SmoothedGain; 4],
+    smoothing_coeff: f32,
+    /// Per-slot: set when an event reaches the bus, cleared once the synth reports it silent.
+    /// Inactive buses are neither rendered nor mixed.
+    active: [bool; 4],
+    recorder: Option<AudioRecorderTap>,
+    sample_rate_hz: u32,
+    late_drop_samples: u64,
+    late_stats: Arc<LateEventStats>,
+    callback_stats: Arc<AudioCallbackStats>,
+    /// Synth render time in the current callback, per slot; slot 0 sums the buses.
+    synth_time: [Duration; 4],
+}
+
+/// Time constant for volume changes, including the autopilot mute when playback stops.
+const GAIN_SMOOTHING_MS: f32 = 10.0;
+
+#[derive(Clone, Copy, Debug)]
+struct SmoothedGain {
+    current: f32,
+    target: f32,
+}
+
+impl SmoothedGain {
+    fn new(value: f32) -> Self {
+        Self {
+            current: value,
+            target: value,
+        }
+    }
+
+    #[inline]
+    fn next(&mut self, coeff: f32) -> f32 {
+        self.current = self.target + (self.current - self.target) * coeff;
+        self.current
+    }
+
+    /// Advances the smoothing by `frames` samples without producing them.
+    fn skip(&mut self, coeff: f32, frames: usize) {
+        self.current = self.target + (self.current - self.target) * coeff.powi(frames as i32);
+    }
+
+    fn is_silent(&self) -> bool {
+        self.target == 0.0 && self.current < 1e-4
+    }
 }
 
 impl AudioGraph {
@@ -54,25 +256,94 @@ impl AudioGraph {
         params: Arc<AudioParams>,
         consumer: Consumer<ScheduledEvent>,
         clock: Arc<AudioClock>,
+        meters: Arc<AudioMeters>,
+        sample_rate_hz: u32,
         max_frames: usize,
     ) -> Self {
+        let limiter = Limiter::new(sample_rate_hz, params.limiter());
+        let ducker = Ducker::new(sample_rate_hz, params.ducking());
+        let mut gains = [SmoothedGain::new(params.master()); 4];
+        for bus in [Bus::UserMonitor, Bus::Autopilot, Bus::MetronomeFx] {
+            gains[bus_slot(bus)] = SmoothedGain::new(bus_target(&params, bus));
+        }
+        let smoothing_samples = (GAIN_SMOOTHING_MS / 1000.0 * sample_rate_hz as f32).max(1.0);
         Self {
             synth,
-            params,
             clock,
+            meters,
             consumer,
             scratch_l: vec![0.0; max_frames],
             scratch_r: vec![0.0; max_frames],
             events: Vec::with_capacity(512),
             pending: None,
-            limiter_gain: 1.0,
+            limiter,
+            ducker,
+            duck_gain: vec![0.0; max_frames],
+            meter_acc: [MeterAccumulator::default(); 4],
+            gains,
+            smoothing_coeff: (-1.0 / smoothing_samples).exp(),
+            active: [true; 4],
+            recorder: None,
+            sample_rate_hz,
+            late_drop_samples: ms_to_samples(DEFAULT_LATE_DROP_MS, sample_rate_hz),
+            late_stats: Arc::new(LateEventStats::new()),
+            callback_stats: Arc::new(AudioCallbackStats::new()),
+            synth_time: [Duration::ZERO; 4],
+            params,
         }
     }
 
+    /// Late NoteOns beyond `ms` are dropped; their NoteOffs and all other late events are
+    /// still played.
+    pub fn with_late_drop_ms(mut self, ms: f32) -> Self {
+        self.late_drop_samples = ms_to_samples(ms, self.sample_rate_hz);
+        self
+    }
+
+    pub fn with_late_event_stats(mut self, stats: Arc<LateEventStats>) -> Self {
+        self.late_stats = stats;
+        self
+    }
+
+    pub fn with_callback_stats(mut self, stats: Arc<AudioCallbackStats>) -> Self {
+        self.callback_stats = stats;
+        self
+    }
+
+    /// Copies every rendered block, after the limiter, to `tap` while it is armed.
+    pub fn with_recorder(mut self, tap: AudioRecorderTap) -> Self {
+        self.recorder = Some(tap);
+        self
+    }
+
+    fn read_gain_targets(&mut self) {
+        self.gains[0].target = self.params.master();
+        for bus in [Bus::UserMonitor, Bus::Autopilot, Bus::MetronomeFx] {
+            self.gains[bus_slot(bus)].target = bus_target(&self.params, bus);
+        }
+    }
+
+    fn release_bus(&mut self, bus: Bus, at: SampleTime) {
+        for note in 0..128u8 {
+            self.synth
+                .handle_event(bus, MidiLikeEvent::NoteOff { note }, at);
+        }
+        self.synth
+            .handle_event(bus, MidiLikeEvent::Cc64 { value: 0 }, at);
+        self.active[bus_slot(bus)] = true;
+    }
+
+    /// Takes the events due before `sample_time_end`, dropping those of an older generation
+    /// unplayed. The core releases the affected buses whenever it starts a generation.
     fn collect_events(&mut self, sample_time_end: SampleTime) {
         self.events.clear();
+        let generation = self.params.event_generation();
 
-        if let Some(event) = self.pending.take() {
+        if let Some(event) = self
+            .pending
+            .take()
+            .filter(|event| !is_stale(event, generation))
+        {
             if event.sample_time < sample_time_end {
                 self.events.push(event);
             } else {
@@ -82,6 +353,9 @@ impl AudioGraph {
         }
 
         while let Ok(event) = self.consumer.pop() {
+            if is_stale(&event, generation) {
+                continue;
+            }
             if event.sample_time < sample_time_end {
                 self.events.push(event);
             } else {
@@ -98,16 +372,72 @@ impl AudioGraph {
         });
     }
 
+    /// Moves events scheduled before `sample_time_start` into this block. The late batch
+    /// keeps its order and spacing, starting at the block start, but never passes the first
+    /// on-time event. NoteOns later than the drop threshold are removed.
+    fn retime_late_events(&mut self, sample_time_start: SampleTime, sample_time_end: SampleTime) {
+        let Some(earliest) = self.events.first().map(|event| event.sample_time) else {
+            return;
+        };
+        if earliest >= sample_time_start {
+            return;
+        }
+        let late_len = self
+            .events
+            .partition_point(|event| event.sample_time < sample_time_start);
+        let latest_allowed = self
+            .events
+            .get(late_len)
+            .map_or(sample_time_end.saturating_sub(1), |event| event.sample_time)
+            .max(sample_time_start);
+        // Spacing is measured from the earliest event that will actually play.
+        let earliest_kept = self.events[..late_len]
+            .iter()
+            .find(|event| !self.drops_late(event, sample_time_start))
+            .map_or(earliest, |event| event.sample_time);
+        let shift = sample_time_start - earliest_kept;
+
+        let mut dropped = 0u64;
+        let mut max_lateness = 0u64;
+        let mut write = 0;
+        for read in 0..self.events.len() {
+            let mut event = self.events[read];
+            if read < late_len {
+                max_lateness = max_lateness.max(sample_time_start - event.sample_time);
+                if self.drops_late(&event, sample_time_start) {
+                    dropped += 1;
+                    continue;
+                }
+                event.sample_time = (event.sample_time + shift).min(latest_allowed);
+            }
+            self.events[write] = event;
+            write += 1;
+        }
+        self.events.truncate(write);
+
+        let max_lateness_ms = max_lateness as f32 * 1000.0 / self.sample_rate_hz.max(1) as f32;
+        self.late_stats
+            .record(late_len as u64, dropped, max_lateness_ms);
+    }
+
+    fn drops_late(&self, event: &ScheduledEvent, sample_time_start: SampleTime) -> bool {
+        matches!(event.event, MidiLikeEvent::NoteOn { .. })
+            && sample_time_start.saturating_sub(event.sample_time) > self.late_drop_samples
+    }
+
     fn ensure_scratch(&mut self, frames: usize) {
         if self.scratch_l.len() < frames {
             self.scratch_l.resize(frames, 0.0);
             self.scratch_r.resize(frames, 0.0);
+            self.duck_gain.resize(frames, 0.0);
         }
     }
 
     fn render_segment(&mut self, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
         let scratch_l = &mut self.scratch_l[..frames];
         let scratch_r = &mut self.scratch_r[..frames];
+        let duck_gain = &mut self.duck_gain[..frames];
+        duck_gain.fill(0.0);
 
         for value in out_l.iter_mut() {
             *value = 0.0;
@@ -116,52 +446,106 @@ impl AudioGraph {
             *value = 0.0;
         }
 
-        let master = self.params.master();
-        let monitor_enabled = self.params.monitor_enabled();
-
+        let coeff = self.smoothing_coeff;
         for bus in [Bus::UserMonitor, Bus::Autopilot, Bus::MetronomeFx] {
-            if bus == Bus::UserMonitor && !monitor_enabled {
+            let slot = bus_slot(bus);
+            if bus == Bus::Autopilot {
+                // The monitor, rendered first, has left its levels in `duck_gain`.
+                self.ducker.process(duck_gain);
+            }
+            let gain = &mut self.gains[slot];
+            if !self.active[slot] || (bus == Bus::UserMonitor && gain.is_silent()) {
+                gain.skip(coeff, frames);
                 continue;
             }
+            let render_started = Instant::now();
             self.synth.render(bus, frames, scratch_l, scratch_r);
-            let bus_volume = self.params.bus(bus);
+            let render_time = render_started.elapsed();
+            self.synth_time[slot] += render_time;
+            self.synth_time[0] += render_time;
+            if self.synth.is_silent(bus) {
+                self.active[slot] = false;
+            }
+            let acc = &mut self.meter_acc[slot];
             for i in 0..frames {
-                out_l[i] += scratch_l[i] * bus_volume;
-                out_r[i] += scratch_r[i] * bus_volume;
+                let mut bus_volume = gain.next(coeff);
+                if bus == Bus::Autopilot {
+                    bus_volume *= duck_gain[i];
+                }
+                let l = scratch_l[i] * bus_volume;
+                let r = scratch_r[i] * bus_volume;
+                if bus == Bus::UserMonitor {
+                    duck_gain[i] = l.abs().max(r.abs());
+                }
+                acc.add(l, r);
+                out_l[i] += l;
+                out_r[i] += r;
             }
         }
 
+        let master = &mut self.gains[0];
         for i in 0..frames {
-            out_l[i] *= master;
-            out_r[i] *= master;
+            let gain = master.next(coeff);
+            out_l[i] *= gain;
+            out_r[i] *= gain;
         }
 
-        let limit = 0.98_f32;
-        let mut peak = 0.0_f32;
+        self.limiter.process(out_l, out_r);
+
+        let master_acc = &mut self.meter_acc[0];
         for i in 0..frames {
-            peak = peak.max(out_l[i].abs());
-            peak = peak.max(out_r[i].abs());
+            master_acc.add(out_l[i], out_r[i]);
         }
+    }
 
-        let target_gain = if peak > limit { limit / peak } else { 1.0 };
-        let current_gain = self.limiter_gain;
-        let coeff = if target_gain < current_gain {
-            0.25
-        } else {
-            0.01
-        };
-        let new_gain = (current_gain + coeff * (target_gain - current_gain)).clamp(0.0, 1.0);
-        self.limiter_gain = new_gain;
-
-        if new_gain < 0.999 {
-            for i in 0..frames {
-                out_l[i] *= new_gain;
-                out_r[i] *= new_gain;
-            }
+    fn publish_meters(&mut self) {
+        self.meters.publish_master(&self.meter_acc[0]);
+        for bus in [Bus::UserMonitor, Bus::Autopilot, Bus::MetronomeFx] {
+            self.meters.publish_bus(bus, &self.meter_acc[bus_slot(bus)]);
         }
+        self.meters
+            .publish_limiter_gain(self.limiter.take_min_gain());
+        self.meter_acc = [MeterAccumulator::default(); 4];
     }
 }
 
+fn ms_to_samples(ms: f32, sample_rate_hz: u32) -> u64 {
+    (ms.max(0.0) / 1000.0 * sample_rate_hz as f32).round() as u64
+}
+
+/// Index of a bus in the per-signal arrays, where slot 0 is the master.
+fn bus_slot(bus: Bus) -> usize {
+    match bus {
+        Bus::UserMonitor => 1,
+        Bus::Autopilot => 2,
+        Bus::MetronomeFx => 3,
+    }
+}
+
+/// Volume a bus should settle at: its fader, or silence while it is muted (monitor off, or
+/// playback stopped for the autopilot and metronome buses).
+fn bus_target(params: &AudioParams, bus: Bus) -> f32 {
+    if bus == Bus::UserMonitor && !params.monitor_enabled() {
+        return 0.0;
+    }
+    params.bus(bus)
+}
+
+/// Older than `generation`, allowing for the counter wrapping around.
+fn is_stale(event: &ScheduledEvent, generation: u32) -> bool {
+    (generation.wrapping_sub(event.generation) as i32) > 0
+}
+
+fn is_all_notes_off(event: &MidiLikeEvent) -> bool {
+    matches!(
+        event,
+        MidiLikeEvent::ControlChange {
+            controller: ALL_NOTES_OFF,
+            ..
+        }
+    )
+}
+
 fn midi_event_rank(event: &MidiLikeEvent) -> u8 {
     match event {
         MidiLikeEvent::Cc64 { value } => {
@@ -171,6 +555,7 @@ fn midi_event_rank(event: &MidiLikeEvent) -> u8 {
                 3
             }
         }
+        MidiLikeEvent::ControlChange { .. } => 0,
         MidiLikeEvent::NoteOff { .. } => 1,
         MidiLikeEvent::NoteOn { .. } => 2,
     }
@@ -180,17 +565,23 @@ fn midi_event_note_key(event: &MidiLikeEvent) -> u8 {
     match event {
         MidiLikeEvent::NoteOn { note, .. } => *note,
         MidiLikeEvent::NoteOff { note } => *note,
-        MidiLikeEvent::Cc64 { .. } => 0,
+        MidiLikeEvent::Cc64 { .. } | MidiLikeEvent::ControlChange { .. } => 0,
     }
 }
 
 impl AudioRenderCallback for AudioGraph {
     fn render(&mut self, sample_time_start: SampleTime, out_l: &mut [f32], out_r: &mut [f32]) {
+        let started = Instant::now();
         let frames = out_l.len().min(out_r.len());
         let sample_time_end = sample_time_start.saturating_add(frames as u64);
 
         self.ensure_scratch(frames);
+        self.synth_time = [Duration::ZERO; 4];
         self.collect_events(sample_time_end);
+        self.retime_late_events(sample_time_start, sample_time_end);
+        self.limiter.set_params(self.params.limiter());
+        self.ducker.set_params(self.params.ducking());
+        self.read_gain_targets();
 
         let playback_enabled = self.params.playback_enabled();
         let mut cursor_sample = sample_time_start;
@@ -222,8 +613,13 @@ impl AudioRenderCallback for AudioGraph {
                 cursor_frame = end;
                 cursor_sample = event_sample;
             }
+            if is_all_notes_off(&event.event) {
+                self.release_bus(event.bus, event_sample);
+                continue;
+            }
             self.synth
                 .handle_event(event.bus, event.event, event_sample);
+            self.active[bus_slot(event.bus)] = true;
         }
 
         if cursor_frame < frames {
@@ -234,6 +630,14 @@ impl AudioRenderCallback for AudioGraph {
             );
         }
 
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.push(&out_l[..frames], &out_r[..frames]);
+        }
+        self.publish_meters();
         self.clock.set(sample_time_end);
+        self.callback_stats
+            .record(frames, self.sample_rate_hz, started.elapsed());
+        self.callback_stats
+            .record_synth_load(frames, self.sample_rate_hz, &self.synth_time);
     }
 }
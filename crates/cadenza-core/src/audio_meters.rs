@@ -0,0 +1,161 @@
+use cadenza_ports::types::Bus;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Peak and RMS amplitude (linear, 1.0 = full scale) of one signal.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MeterLevel {
+    pub peak: f32,
+    pub rms: f32,
+}
+
+impl MeterLevel {
+    /// Holds the louder of `self` and `previous` scaled down by `decay`, so meters fall
+    /// smoothly instead of jumping to silence between reads.
+    pub fn decayed_from(self, previous: MeterLevel, decay: f32) -> Self {
+        Self {
+            peak: self.peak.max(previous.peak * decay),
+            rms: self.rms.max(previous.rms * decay),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AudioLevels {
+    pub master: MeterLevel,
+    pub user: MeterLevel,
+    pub autopilot: MeterLevel,
+    pub metronome: MeterLevel,
+    /// Deepest limiter gain reduction, in positive dB.
+    pub gain_reduction_db: f32,
+}
+
+impl AudioLevels {
+    pub fn decayed_from(self, previous: AudioLevels, decay: f32) -> Self {
+        Self {
+            master: self.master.decayed_from(previous.master, decay),
+            user: self.user.decayed_from(previous.user, decay),
+            autopilot: self.autopilot.decayed_from(previous.autopilot, decay),
+            metronome: self.metronome.decayed_from(previous.metronome, decay),
+            gain_reduction_db: self
+                .gain_reduction_db
+                .max(previous.gain_reduction_db * decay),
+        }
+    }
+}
+
+/// Running peak/RMS for one signal inside a render callback. Plain fields, published to
+/// [`AudioMeters`] once per callback.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MeterAccumulator {
+    peak: f32,
+    sum_squares: f32,
+    frames: u32,
+}
+
+impl MeterAccumulator {
+    #[inline]
+    pub fn add(&mut self, l: f32, r: f32) {
+        self.peak = self.peak.max(l.abs()).max(r.abs());
+        self.sum_squares += (l * l + r * r) * 0.5;
+        self.frames = self.frames.saturating_add(1);
+    }
+}
+
+/// Level cells shared between the audio thread, which accumulates into them, and the core
+/// thread, which drains them. Values are stored as f32 bit patterns so neither side locks
+/// or allocates.
+#[derive(Debug)]
+pub struct AudioMeters {
+    master: MeterCell,
+    user: MeterCell,
+    autopilot: MeterCell,
+    metronome: MeterCell,
+    limiter_gain: AtomicU32,
+}
+
+impl AudioMeters {
+    pub fn new() -> Self {
+        Self {
+            master: MeterCell::default(),
+            user: MeterCell::default(),
+            autopilot: MeterCell::default(),
+            metronome: MeterCell::default(),
+            limiter_gain: AtomicU32::new(1.0_f32.to_bits()),
+        }
+    }
+
+    pub fn publish_limiter_gain(&self, gain: f32) {
+        self.limiter_gain
+            .fetch_min(gain.max(0.0).to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn publish_master(&self, acc: &MeterAccumulator) {
+        self.master.publish(acc);
+    }
+
+    pub fn publish_bus(&self, bus: Bus, acc: &MeterAccumulator) {
+        let cell = match bus {
+            Bus::UserMonitor => &self.user,
+            Bus::Autopilot => &self.autopilot,
+            Bus::MetronomeFx => &self.metronome,
+        };
+        cell.publish(acc);
+    }
+
+    /// Levels since the previous call; resets the cells.
+    pub fn take(&self) -> AudioLevels {
+        AudioLevels {
+            master: self.master.take(),
+            user: self.user.take(),
+            autopilot: self.autopilot.take(),
+            metronome: self.metronome.take(),
+            gain_reduction_db: {
+                let gain =
+                    f32::from_bits(self.limiter_gain.swap(1.0_f32.to_bits(), Ordering::Relaxed));
+                (-20.0 * gain.max(1e-6).log10()).max(0.0)
+            },
+        }
+    }
+}
+
+impl Default for AudioMeters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Default)]
+struct MeterCell {
+    peak: AtomicU32,
+    sum_squares: AtomicU32,
+    frames: AtomicU32,
+}
+
+impl MeterCell {
+    fn publish(&self, acc: &MeterAccumulator) {
+        if acc.frames == 0 {
+            return;
+        }
+        // Non-negative floats order the same as their bit patterns.
+        self.peak.fetch_max(acc.peak.to_bits(), Ordering::Relaxed);
+        let _ = self
+            .sum_squares
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f32::from_bits(bits) + acc.sum_squares).to_bits())
+            });
+        self.frames.fetch_add(acc.frames, Ordering::Relaxed);
+    }
+
+    fn take(&self) -> MeterLevel {
+        let peak = f32::from_bits(self.peak.swap(0, Ordering::Relaxed));
+        let sum_squares = f32::from_bits(self.sum_squares.swap(0, Ordering::Relaxed));
+        let frames = self.frames.swap(0, Ordering::Relaxed);
+        let rms = if frames > 0 {
+            (sum_squares / frames as f32).sqrt()
+        } else {
+            0.0
+        };
+        MeterLevel { peak, rms }
+    }
+}
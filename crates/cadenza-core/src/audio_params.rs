@@ -1,7 +1,9 @@
 use cadenza_ports::storage::SettingsDto;
-use cadenza_ports::types::{Bus, Volume01};
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use cadenza_ports::types::{Bus, DuckingParams, LimiterParams, Volume01, VolumeCurve};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering};
 
+/// Mixer state shared with the audio thread. Volumes are stored as fader positions and
+/// mapped through the volume curve when read.
 #[derive(Debug)]
 pub struct AudioParams {
     master: AtomicU32,
@@ -10,6 +12,16 @@ pub struct AudioParams {
     bus_metronome: AtomicU32,
     monitor_enabled: AtomicBool,
     playback_enabled: AtomicBool,
+    volume_curve: AtomicU8,
+    limiter_ceiling: AtomicU32,
+    limiter_attack_ms: AtomicU32,
+    limiter_release_ms: AtomicU32,
+    ducking_enabled: AtomicBool,
+    ducking_depth_db: AtomicU32,
+    ducking_attack_ms: AtomicU32,
+    ducking_release_ms: AtomicU32,
+    /// Current queue generation; older scheduled events are dropped by the audio thread.
+    event_generation: AtomicU32,
 }
 
 impl AudioParams {
@@ -21,6 +33,15 @@ impl AudioParams {
             bus_metronome: AtomicU32::new(settings.bus_metronome_volume.get().to_bits()),
             monitor_enabled: AtomicBool::new(settings.monitor_enabled),
             playback_enabled: AtomicBool::new(false),
+            volume_curve: AtomicU8::new(curve_to_u8(settings.volume_curve)),
+            limiter_ceiling: AtomicU32::new(settings.limiter.ceiling.to_bits()),
+            limiter_attack_ms: AtomicU32::new(settings.limiter.attack_ms.to_bits()),
+            limiter_release_ms: AtomicU32::new(settings.limiter.release_ms.to_bits()),
+            ducking_enabled: AtomicBool::new(settings.ducking.enabled),
+            ducking_depth_db: AtomicU32::new(settings.ducking.depth_db.to_bits()),
+            ducking_attack_ms: AtomicU32::new(settings.ducking.attack_ms.to_bits()),
+            ducking_release_ms: AtomicU32::new(settings.ducking.release_ms.to_bits()),
+            event_generation: AtomicU32::new(0),
         }
     }
 
@@ -37,6 +58,18 @@ impl AudioParams {
         target.store(volume.get().to_bits(), Ordering::Relaxed);
     }
 
+    pub fn set_volume_curve(&self, curve: VolumeCurve) {
+        self.volume_curve
+            .store(curve_to_u8(curve), Ordering::Relaxed);
+    }
+
+    pub fn volume_curve(&self) -> VolumeCurve {
+        match self.volume_curve.load(Ordering::Relaxed) {
+            0 => VolumeCurve::Linear,
+            _ => VolumeCurve::Db60,
+        }
+    }
+
     pub fn set_monitor_enabled(&self, enabled: bool) {
         self.monitor_enabled.store(enabled, Ordering::Relaxed);
     }
@@ -45,10 +78,58 @@ impl AudioParams {
         self.playback_enabled.store(enabled, Ordering::Relaxed);
     }
 
+    pub fn set_limiter(&self, params: LimiterParams) {
+        self.limiter_ceiling
+            .store(params.ceiling.to_bits(), Ordering::Relaxed);
+        self.limiter_attack_ms
+            .store(params.attack_ms.to_bits(), Ordering::Relaxed);
+        self.limiter_release_ms
+            .store(params.release_ms.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn limiter(&self) -> LimiterParams {
+        LimiterParams {
+            ceiling: f32::from_bits(self.limiter_ceiling.load(Ordering::Relaxed)),
+            attack_ms: f32::from_bits(self.limiter_attack_ms.load(Ordering::Relaxed)),
+            release_ms: f32::from_bits(self.limiter_release_ms.load(Ordering::Relaxed)),
+        }
+    }
+
+    pub fn set_ducking(&self, params: DuckingParams) {
+        self.ducking_enabled
+            .store(params.enabled, Ordering::Relaxed);
+        self.ducking_depth_db
+            .store(params.depth_db.to_bits(), Ordering::Relaxed);
+        self.ducking_attack_ms
+            .store(params.attack_ms.to_bits(), Ordering::Relaxed);
+        self.ducking_release_ms
+            .store(params.release_ms.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn ducking(&self) -> DuckingParams {
+        DuckingParams {
+            enabled: self.ducking_enabled.load(Ordering::Relaxed),
+            depth_db: f32::from_bits(self.ducking_depth_db.load(Ordering::Relaxed)),
+            attack_ms: f32::from_bits(self.ducking_attack_ms.load(Ordering::Relaxed)),
+            release_ms: f32::from_bits(self.ducking_release_ms.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Set before pushing events of the new generation.
+    pub fn set_event_generation(&self, generation: u32) {
+        self.event_generation.store(generation, Ordering::Release);
+    }
+
+    pub fn event_generation(&self) -> u32 {
+        self.event_generation.load(Ordering::Acquire)
+    }
+
+    /// Master gain, after the volume curve.
     pub fn master(&self) -> f32 {
-        f32::from_bits(self.master.load(Ordering::Relaxed))
+        self.gain(&self.master)
     }
 
+    /// Bus gain, after the volume curve; zero for the playback buses while stopped.
     pub fn bus(&self, bus: Bus) -> f32 {
         if !self.playback_enabled.load(Ordering::Relaxed)
             && matches!(bus, Bus::Autopilot | Bus::MetronomeFx)
@@ -61,7 +142,12 @@ impl AudioParams {
             Bus::Autopilot => &self.bus_autopilot,
             Bus::MetronomeFx => &self.bus_metronome,
         };
-        f32::from_bits(value.load(Ordering::Relaxed))
+        self.gain(value)
+    }
+
+    fn gain(&self, volume: &AtomicU32) -> f32 {
+        let volume = Volume01(f32::from_bits(volume.load(Ordering::Relaxed)));
+        self.volume_curve().gain(volume)
     }
 
     pub fn monitor_enabled(&self) -> bool {
@@ -72,3 +158,10 @@ impl AudioParams {
         self.playback_enabled.load(Ordering::Relaxed)
     }
 }
+
+fn curve_to_u8(curve: VolumeCurve) -> u8 {
+    match curve {
+        VolumeCurve::Linear => 0,
+        VolumeCurve::Db60 => 1,
+    }
+}
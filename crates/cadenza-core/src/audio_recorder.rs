@@ -0,0 +1,222 @@
+use rtrb::{Consumer, Producer, RingBuffer};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Seconds of stereo audio the ring holds before the audio thread starts dropping blocks.
+const RECORDER_BUFFER_SECS: usize = 2;
+
+#[derive(thiserror::Error, Debug)]
+pub enum RecorderError {
+    #[error("already recording")]
+    AlreadyRecording,
+    #[error("not recording")]
+    NotRecording,
+    #[error("wav error: {0}")]
+    Wav(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordingSummary {
+    pub path: PathBuf,
+    pub frames_written: u64,
+    pub dropped_frames: u64,
+    pub sample_rate_hz: u32,
+}
+
+impl RecordingSummary {
+    pub fn duration_secs(&self) -> f64 {
+        self.frames_written as f64 / self.sample_rate_hz.max(1) as f64
+    }
+}
+
+#[derive(Debug, Default)]
+struct RecorderShared {
+    armed: AtomicBool,
+    stop: AtomicBool,
+    frames_written: AtomicU64,
+    dropped_frames: AtomicU64,
+}
+
+/// Audio-thread side: copies the master output into the ring while armed. A block that does
+/// not fit is counted as dropped instead of waiting for the writer.
+pub struct AudioRecorderTap {
+    producer: Producer<f32>,
+    shared: Arc<RecorderShared>,
+}
+
+impl AudioRecorderTap {
+    pub fn push(&mut self, out_l: &[f32], out_r: &[f32]) {
+        if !self.shared.armed.load(Ordering::Acquire) {
+            return;
+        }
+        let frames = out_l.len().min(out_r.len());
+        match self.producer.write_chunk_uninit(frames * 2) {
+            Ok(chunk) => {
+                let interleaved = out_l[..frames]
+                    .iter()
+                    .zip(&out_r[..frames])
+                    .flat_map(|(&l, &r)| [l, r]);
+                chunk.fill_from_iter(interleaved);
+            }
+            Err(_) => {
+                self.shared
+                    .dropped_frames
+                    .fetch_add(frames as u64, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Core-thread side: owns the writer thread that drains the ring into a WAV file.
+pub struct AudioRecorder {
+    consumer: Option<Consumer<f32>>,
+    shared: Arc<RecorderShared>,
+    sample_rate_hz: u32,
+    writer: Option<(PathBuf, JoinHandle<WriterResult>)>,
+}
+
+type WriterResult = (Consumer<f32>, Result<(), RecorderError>);
+
+/// Creates the two halves of a recorder; the tap goes to the `AudioGraph`.
+pub fn audio_recorder(sample_rate_hz: u32) -> (AudioRecorderTap, AudioRecorder) {
+    let capacity = sample_rate_hz.max(1) as usize * 2 * RECORDER_BUFFER_SECS;
+    let (producer, consumer) = RingBuffer::new(capacity);
+    let shared = Arc::new(RecorderShared::default());
+    (
+        AudioRecorderTap {
+            producer,
+            shared: shared.clone(),
+        },
+        AudioRecorder {
+            consumer: Some(consumer),
+            shared,
+            sample_rate_hz,
+            writer: None,
+        },
+    )
+}
+
+impl AudioRecorder {
+    pub fn is_recording(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.writer.as_ref().map(|(path, _)| path.as_path())
+    }
+
+    pub fn frames_written(&self) -> u64 {
+        self.shared.frames_written.load(Ordering::Relaxed)
+    }
+
+    pub fn dropped_frames(&self) -> u64 {
+        self.shared.dropped_frames.load(Ordering::Relaxed)
+    }
+
+    pub fn sample_rate_hz(&self) -> u32 {
+        self.sample_rate_hz
+    }
+
+    pub fn start(&mut self, path: &Path) -> Result<(), RecorderError> {
+        if self.writer.is_some() {
+            return Err(RecorderError::AlreadyRecording);
+        }
+        let Some(mut consumer) = self.consumer.take() else {
+            return Err(RecorderError::AlreadyRecording);
+        };
+        // Leftovers from a block that raced the previous stop.
+        let stale = consumer.slots();
+        if let Ok(chunk) = consumer.read_chunk(stale) {
+            chunk.commit_all();
+        }
+
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: self.sample_rate_hz,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let wav = match hound::WavWriter::create(path, spec) {
+            Ok(wav) => wav,
+            Err(err) => {
+                self.consumer = Some(consumer);
+                return Err(RecorderError::Wav(err.to_string()));
+            }
+        };
+
+        self.shared.stop.store(false, Ordering::Relaxed);
+        self.shared.frames_written.store(0, Ordering::Relaxed);
+        self.shared.dropped_frames.store(0, Ordering::Relaxed);
+        let shared = self.shared.clone();
+        let handle = std::thread::spawn(move || {
+            let result = write_until_stopped(&mut consumer, wav, &shared);
+            (consumer, result)
+        });
+        self.writer = Some((path.to_path_buf(), handle));
+        self.shared.armed.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    pub fn stop(&mut self) -> Result<RecordingSummary, RecorderError> {
+        let Some((path, handle)) = self.writer.take() else {
+            return Err(RecorderError::NotRecording);
+        };
+        self.shared.armed.store(false, Ordering::Release);
+        self.shared.stop.store(true, Ordering::Release);
+        let (consumer, result) = handle
+            .join()
+            .map_err(|_| RecorderError::Wav("writer thread panicked".to_string()))?;
+        self.consumer = Some(consumer);
+        result?;
+        Ok(RecordingSummary {
+            path,
+            frames_written: self.frames_written(),
+            dropped_frames: self.dropped_frames(),
+            sample_rate_hz: self.sample_rate_hz,
+        })
+    }
+}
+
+impl Drop for AudioRecorder {
+    fn drop(&mut self) {
+        if self.writer.is_some() {
+            let _ = self.stop();
+        }
+    }
+}
+
+fn write_until_stopped<W: std::io::Write + std::io::Seek>(
+    consumer: &mut Consumer<f32>,
+    mut wav: hound::WavWriter<W>,
+    shared: &RecorderShared,
+) -> Result<(), RecorderError> {
+    let wav_error = |err: hound::Error| RecorderError::Wav(err.to_string());
+    loop {
+        // Checked before draining so samples pushed just before the stop are still written.
+        let stopping = shared.stop.load(Ordering::Acquire);
+        let available = consumer.slots() & !1;
+        if available > 0 {
+            let chunk = consumer
+                .read_chunk(available)
+                .map_err(|e| RecorderError::Wav(e.to_string()))?;
+            let (first, second) = chunk.as_slices();
+            for &sample in first.iter().chain(second) {
+                wav.write_sample(sample).map_err(wav_error)?;
+            }
+            chunk.commit_all();
+            shared
+                .frames_written
+                .fetch_add(available as u64 / 2, Ordering::Relaxed);
+        }
+        if stopping {
+            break;
+        }
+        if available == 0 {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+    wav.finalize().map_err(wav_error)
+}
@@ -0,0 +1,378 @@
+use crate::audio_meters::{AudioLevels, MeterLevel};
+use crate::audio_params::AudioParams;
+use cadenza_ports::types::{Bus, SampleTime};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Buses exercised by the self-test, in order.
+pub const SELF_TEST_BUSES: [Bus; 3] = [Bus::UserMonitor, Bus::Autopilot, Bus::MetronomeFx];
+/// Peak above which a meter counts as "producing sound" (-80 dBFS).
+const AUDIBLE_PEAK: f32 = 1.0e-4;
+/// Audio time given to each bus: the tone plus room for the limiter delay and meter reads.
+const BUS_WINDOW_SECS: f32 = 0.35;
+const TONE_SECS: f32 = 0.2;
+/// Wall-clock limit for the audio callback to make progress before the test gives up.
+const STALL_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SelfTestStageKind {
+    DeviceOpen,
+    /// The output callback is running and advancing the audio clock.
+    Clock,
+    /// The bus is not muted by its fader or the monitor switch.
+    ParamsGating,
+    /// The synth produced sound on the bus.
+    SynthRender,
+    /// The bus reached the master output.
+    Mixing,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SelfTestStatus {
+    Passed,
+    Failed,
+    /// Not run because an earlier stage failed.
+    Skipped,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SelfTestStageResult {
+    pub stage: SelfTestStageKind,
+    pub bus: Option<Bus>,
+    pub status: SelfTestStatus,
+    pub detail: String,
+    pub level: Option<MeterLevel>,
+}
+
+impl SelfTestStageResult {
+    fn new(stage: SelfTestStageKind, bus: Option<Bus>, status: SelfTestStatus) -> Self {
+        Self {
+            stage,
+            bus,
+            status,
+            detail: String::new(),
+            level: None,
+        }
+    }
+
+    fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = detail.into();
+        self
+    }
+
+    fn with_level(mut self, level: MeterLevel) -> Self {
+        self.level = Some(level);
+        self
+    }
+}
+
+/// A tone the self-test wants played, in absolute sample time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SelfTestTone {
+    pub bus: Bus,
+    pub start: SampleTime,
+    pub end: SampleTime,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Phase {
+    WaitingForClock {
+        since: SampleTime,
+    },
+    Bus {
+        index: usize,
+        window_end: SampleTime,
+        bus_level: MeterLevel,
+        master_level: MeterLevel,
+    },
+    Done,
+}
+
+/// Tick-driven audio path check: waits for the clock, then plays a tone on each bus in turn
+/// and checks the bus and master meters while it sounds.
+#[derive(Debug)]
+pub struct AudioSelfTest {
+    sample_rate_hz: u32,
+    phase: Phase,
+    progress_at: Instant,
+    progress_sample: SampleTime,
+    results: Vec<SelfTestStageResult>,
+}
+
+impl AudioSelfTest {
+    pub fn start(sample_rate_hz: u32, now: SampleTime) -> Self {
+        Self {
+            sample_rate_hz: sample_rate_hz.max(1),
+            phase: Phase::WaitingForClock { since: now },
+            progress_at: Instant::now(),
+            progress_sample: now,
+            results: vec![SelfTestStageResult::new(
+                SelfTestStageKind::DeviceOpen,
+                None,
+                SelfTestStatus::Passed,
+            )],
+        }
+    }
+
+    /// A result for a test that could not open the output at all.
+    pub fn device_open_failed(detail: impl Into<String>) -> Vec<SelfTestStageResult> {
+        let mut results = vec![SelfTestStageResult::new(
+            SelfTestStageKind::DeviceOpen,
+            None,
+            SelfTestStatus::Failed,
+        )
+        .with_detail(detail)];
+        results.push(SelfTestStageResult::new(
+            SelfTestStageKind::Clock,
+            None,
+            SelfTestStatus::Skipped,
+        ));
+        for bus in SELF_TEST_BUSES {
+            push_skipped_bus(&mut results, bus);
+        }
+        results
+    }
+
+    pub fn is_done(&self) -> bool {
+        matches!(self.phase, Phase::Done)
+    }
+
+    pub fn results(&self) -> &[SelfTestStageResult] {
+        &self.results
+    }
+
+    /// Feeds the meters read since the previous call.
+    pub fn observe(&mut self, levels: &AudioLevels) {
+        if let Phase::Bus {
+            index,
+            bus_level,
+            master_level,
+            ..
+        } = &mut self.phase
+        {
+            let measured = bus_meter(levels, SELF_TEST_BUSES[*index]);
+            *bus_level = measured.decayed_from(*bus_level, 1.0);
+            *master_level = levels.master.decayed_from(*master_level, 1.0);
+        }
+    }
+
+    /// Advances the test to audio time `now`; returns a tone to schedule when a bus starts.
+    pub fn advance(&mut self, now: SampleTime, params: &AudioParams) -> Option<SelfTestTone> {
+        if now > self.progress_sample {
+            self.progress_sample = now;
+            self.progress_at = Instant::now();
+        } else if self.progress_at.elapsed() > STALL_TIMEOUT {
+            self.fail_stalled();
+            return None;
+        }
+
+        match self.phase {
+            Phase::WaitingForClock { since } => {
+                if now <= since {
+                    return None;
+                }
+                self.results.push(SelfTestStageResult::new(
+                    SelfTestStageKind::Clock,
+                    None,
+                    SelfTestStatus::Passed,
+                ));
+                self.start_bus(0, now, params)
+            }
+            Phase::Bus {
+                index,
+                window_end,
+                bus_level,
+                master_level,
+            } => {
+                if now < window_end {
+                    return None;
+                }
+                let bus = SELF_TEST_BUSES[index];
+                self.results.push(render_result(bus, bus_level));
+                self.results
+                    .push(mixing_result(bus, bus_level, master_level, params));
+                self.start_bus(index + 1, now, params)
+            }
+            Phase::Done => None,
+        }
+    }
+
+    fn start_bus(
+        &mut self,
+        mut index: usize,
+        now: SampleTime,
+        params: &AudioParams,
+    ) -> Option<SelfTestTone> {
+        while index < SELF_TEST_BUSES.len() {
+            let bus = SELF_TEST_BUSES[index];
+            match gating_problem(bus, params) {
+                Some(problem) => {
+                    self.results.push(
+                        SelfTestStageResult::new(
+                            SelfTestStageKind::ParamsGating,
+                            Some(bus),
+                            SelfTestStatus::Failed,
+                        )
+                        .with_detail(problem),
+                    );
+                    for stage in [SelfTestStageKind::SynthRender, SelfTestStageKind::Mixing] {
+                        self.results.push(SelfTestStageResult::new(
+                            stage,
+                            Some(bus),
+                            SelfTestStatus::Skipped,
+                        ));
+                    }
+                    index += 1;
+                }
+                None => {
+                    self.results.push(SelfTestStageResult::new(
+                        SelfTestStageKind::ParamsGating,
+                        Some(bus),
+                        SelfTestStatus::Passed,
+                    ));
+                    // Start a little ahead so the tone is not late for the next callback.
+                    let start = now + self.samples(0.02);
+                    self.phase = Phase::Bus {
+                        index,
+                        window_end: start + self.samples(BUS_WINDOW_SECS),
+                        bus_level: MeterLevel::default(),
+                        master_level: MeterLevel::default(),
+                    };
+                    return Some(SelfTestTone {
+                        bus,
+                        start,
+                        end: start + self.samples(TONE_SECS),
+                    });
+                }
+            }
+        }
+        self.phase = Phase::Done;
+        None
+    }
+
+    fn fail_stalled(&mut self) {
+        let detail = "audio callback stopped advancing the clock";
+        match self.phase {
+            Phase::WaitingForClock { .. } => {
+                self.results.push(
+                    SelfTestStageResult::new(
+                        SelfTestStageKind::Clock,
+                        None,
+                        SelfTestStatus::Failed,
+                    )
+                    .with_detail(detail),
+                );
+                for bus in SELF_TEST_BUSES {
+                    push_skipped_bus(&mut self.results, bus);
+                }
+            }
+            Phase::Bus { index, .. } => {
+                let bus = SELF_TEST_BUSES[index];
+                self.results.push(
+                    SelfTestStageResult::new(
+                        SelfTestStageKind::SynthRender,
+                        Some(bus),
+                        SelfTestStatus::Failed,
+                    )
+                    .with_detail(detail),
+                );
+                self.results.push(SelfTestStageResult::new(
+                    SelfTestStageKind::Mixing,
+                    Some(bus),
+                    SelfTestStatus::Skipped,
+                ));
+                for &bus in &SELF_TEST_BUSES[index + 1..] {
+                    push_skipped_bus(&mut self.results, bus);
+                }
+            }
+            Phase::Done => {}
+        }
+        self.phase = Phase::Done;
+    }
+
+    fn samples(&self, secs: f32) -> SampleTime {
+        (secs * self.sample_rate_hz as f32).round() as SampleTime
+    }
+}
+
+fn push_skipped_bus(results: &mut Vec<SelfTestStageResult>, bus: Bus) {
+    for stage in [
+        SelfTestStageKind::ParamsGating,
+        SelfTestStageKind::SynthRender,
+        SelfTestStageKind::Mixing,
+    ] {
+        results.push(SelfTestStageResult::new(
+            stage,
+            Some(bus),
+            SelfTestStatus::Skipped,
+        ));
+    }
+}
+
+/// Why `bus` cannot be heard with the current parameters, if it cannot. Expects playback to
+/// be enabled for the duration of the test.
+fn gating_problem(bus: Bus, params: &AudioParams) -> Option<String> {
+    if bus == Bus::UserMonitor && !params.monitor_enabled() {
+        return Some("user monitor is disabled".to_string());
+    }
+    if params.bus(bus) <= 0.0 {
+        return Some(format!("{bus:?} bus volume is 0"));
+    }
+    None
+}
+
+fn render_result(bus: Bus, bus_level: MeterLevel) -> SelfTestStageResult {
+    let result = SelfTestStageResult::new(
+        SelfTestStageKind::SynthRender,
+        Some(bus),
+        SelfTestStatus::Passed,
+    )
+    .with_level(bus_level);
+    if bus_level.peak > AUDIBLE_PEAK {
+        result
+    } else {
+        SelfTestStageResult {
+            status: SelfTestStatus::Failed,
+            ..result
+        }
+        .with_detail("synth rendered silence")
+    }
+}
+
+fn mixing_result(
+    bus: Bus,
+    bus_level: MeterLevel,
+    master_level: MeterLevel,
+    params: &AudioParams,
+) -> SelfTestStageResult {
+    let result =
+        SelfTestStageResult::new(SelfTestStageKind::Mixing, Some(bus), SelfTestStatus::Passed)
+            .with_level(master_level);
+    if bus_level.peak <= AUDIBLE_PEAK {
+        return SelfTestStageResult {
+            status: SelfTestStatus::Skipped,
+            ..result
+        };
+    }
+    if master_level.peak > AUDIBLE_PEAK {
+        return result;
+    }
+    let detail = if params.master() <= 0.0 {
+        "master volume is 0"
+    } else {
+        "bus output did not reach the master"
+    };
+    SelfTestStageResult {
+        status: SelfTestStatus::Failed,
+        ..result
+    }
+    .with_detail(detail)
+}
+
+fn bus_meter(levels: &AudioLevels, bus: Bus) -> MeterLevel {
+    match bus {
+        Bus::UserMonitor => levels.user,
+        Bus::Autopilot => levels.autopilot,
+        Bus::MetronomeFx => levels.metronome,
+    }
+}
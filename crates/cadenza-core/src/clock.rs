@@ -0,0 +1,16 @@
+use std::time::Instant;
+
+/// Where `AppCore` reads wall time: input timestamps are mapped against it and periodic
+/// events are paced by it. Headless runs substitute a virtual one.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
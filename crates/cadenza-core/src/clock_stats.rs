@@ -0,0 +1,158 @@
+use cadenza_ports::types::SampleTime;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How far back anchors are fitted.
+const WINDOW: Duration = Duration::from_secs(10);
+/// Shortest span worth reporting; below it block quantization swamps the fit.
+const MIN_SPAN: Duration = Duration::from_secs(2);
+const RECENT_INPUTS: usize = 32;
+const JITTER_SMOOTHING: f64 = 0.2;
+/// Beyond this the audio clock runs at a different rate than the one it reports,
+/// usually a sample-rate mismatch between the app and the device.
+pub const CLOCK_DRIFT_WARN_PPM: f64 = 1000.0;
+
+/// How well the wall clock maps to the audio clock for timestamping MIDI input.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClockStatsSnapshot {
+    /// Smoothed spread of the estimated sample time of an input around the true one.
+    pub input_jitter_ms: f64,
+    /// How much faster (positive) the audio clock runs than the wall clock.
+    pub clock_drift_ppm: f64,
+    /// Set on the snapshot where the drift first exceeds [`CLOCK_DRIFT_WARN_PPM`].
+    pub drift_warning: bool,
+}
+
+/// Fits a line through recent clock anchors (wall time, audio clock). The slope gives the
+/// drift and the residuals around it the error of a single anchor, which every input
+/// timestamp inherits.
+#[derive(Debug)]
+pub struct ClockStats {
+    sample_rate_hz: u32,
+    anchors: VecDeque<(Instant, SampleTime)>,
+    input_deltas_ms: VecDeque<f64>,
+    smoothed_jitter_ms: Option<f64>,
+    drift_warned: bool,
+}
+
+impl ClockStats {
+    pub fn new(sample_rate_hz: u32) -> Self {
+        Self {
+            sample_rate_hz: sample_rate_hz.max(1),
+            anchors: VecDeque::new(),
+            input_deltas_ms: VecDeque::with_capacity(RECENT_INPUTS),
+            smoothed_jitter_ms: None,
+            drift_warned: false,
+        }
+    }
+
+    /// Starts over, for a new stream.
+    pub fn reset(&mut self, sample_rate_hz: u32) {
+        *self = Self::new(sample_rate_hz);
+    }
+
+    pub fn record_anchor(&mut self, at: Instant, sample_time: SampleTime) {
+        // The clock restarting means a new stream; its anchors don't share a line.
+        if self
+            .anchors
+            .back()
+            .is_some_and(|&(last_at, last_sample)| sample_time < last_sample || at < last_at)
+        {
+            self.anchors.clear();
+        }
+        self.anchors.push_back((at, sample_time));
+        while self
+            .anchors
+            .front()
+            .is_some_and(|&(first_at, _)| at.duration_since(first_at) > WINDOW)
+        {
+            self.anchors.pop_front();
+        }
+    }
+
+    /// Records how long after the anchor an input arrived; the estimate extrapolates over
+    /// that gap.
+    pub fn record_input(&mut self, at: Instant, anchor_at: Instant) {
+        if self.input_deltas_ms.len() == RECENT_INPUTS {
+            self.input_deltas_ms.pop_front();
+        }
+        let delta = if at >= anchor_at {
+            at.duration_since(anchor_at).as_secs_f64()
+        } else {
+            -anchor_at.duration_since(at).as_secs_f64()
+        };
+        self.input_deltas_ms.push_back(delta * 1000.0);
+    }
+
+    /// Current estimate, or `None` until the anchors span long enough to fit.
+    pub fn snapshot(&mut self) -> Option<ClockStatsSnapshot> {
+        let &(first_at, first_sample) = self.anchors.front()?;
+        let &(last_at, _) = self.anchors.back()?;
+        if last_at.duration_since(first_at) < MIN_SPAN {
+            return None;
+        }
+
+        let sample_rate_hz = self.sample_rate_hz as f64;
+        let points: Vec<(f64, f64)> = self
+            .anchors
+            .iter()
+            .map(|&(at, sample_time)| {
+                (
+                    at.duration_since(first_at).as_secs_f64(),
+                    (sample_time - first_sample) as f64 / sample_rate_hz,
+                )
+            })
+            .collect();
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let (cov, var) = points.iter().fold((0.0, 0.0), |(cov, var), (x, y)| {
+            (
+                cov + (x - mean_x) * (y - mean_y),
+                var + (x - mean_x) * (x - mean_x),
+            )
+        });
+        let slope = cov / var;
+        let residual_var = points
+            .iter()
+            .map(|(x, y)| {
+                let residual = y - (mean_y + slope * (x - mean_x));
+                residual * residual
+            })
+            .sum::<f64>()
+            / n;
+        let drift = slope - 1.0;
+
+        // Extrapolating over the inputs' gaps to the anchor adds the drift over that gap.
+        let spread_ms = match (
+            self.input_deltas_ms.iter().copied().reduce(f64::min),
+            self.input_deltas_ms.iter().copied().reduce(f64::max),
+        ) {
+            (Some(min), Some(max)) => max - min,
+            _ => 0.0,
+        };
+        let extrapolation_ms = drift.abs() * spread_ms;
+        let jitter_ms = (residual_var * 1e6 + extrapolation_ms * extrapolation_ms).sqrt();
+        let smoothed = match self.smoothed_jitter_ms {
+            Some(previous) => previous + (jitter_ms - previous) * JITTER_SMOOTHING,
+            None => jitter_ms,
+        };
+        self.smoothed_jitter_ms = Some(smoothed);
+
+        let clock_drift_ppm = drift * 1e6;
+        let exceeded = clock_drift_ppm.abs() > CLOCK_DRIFT_WARN_PPM;
+        let drift_warning = exceeded && !self.drift_warned;
+        // Warn again only after the drift has settled well below the limit.
+        if exceeded {
+            self.drift_warned = true;
+        } else if clock_drift_ppm.abs() < CLOCK_DRIFT_WARN_PPM / 2.0 {
+            self.drift_warned = false;
+        }
+
+        Some(ClockStatsSnapshot {
+            input_jitter_ms: smoothed,
+            clock_drift_ppm,
+            drift_warning,
+        })
+    }
+}
@@ -0,0 +1,253 @@
+use cadenza_domain_score::{
+    Hand, PlaybackMidiEvent, Score, ScoreMeta, ScoreSource, TargetEvent, TempoPoint,
+    TimeSignaturePoint, Track,
+};
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::types::Tick;
+use serde::{Deserialize, Serialize};
+
+const PPQ: u16 = 480;
+const QUARTER: Tick = PPQ as Tick;
+const EIGHTH: Tick = QUARTER / 2;
+const WHOLE: Tick = QUARTER * 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DemoDifficulty {
+    Beginner,
+    Easy,
+    Intermediate,
+}
+
+/// A score built into the app, loaded with `ScoreSource::InternalDemo(id)`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DemoInfo {
+    pub id: String,
+    pub title: String,
+    pub difficulty: DemoDifficulty,
+}
+
+const DEMOS: [(&str, &str, DemoDifficulty); 5] = [
+    (
+        "c_major_scale",
+        "Demo: C major scale",
+        DemoDifficulty::Beginner,
+    ),
+    (
+        "chromatic_scale",
+        "Demo: Chromatic scale",
+        DemoDifficulty::Beginner,
+    ),
+    (
+        "c_major_arpeggios",
+        "Demo: C major arpeggios, hands together",
+        DemoDifficulty::Easy,
+    ),
+    (
+        "chord_progression",
+        "Demo: I-IV-V-I with pedal",
+        DemoDifficulty::Easy,
+    ),
+    (
+        "two_voice_minuet",
+        "Demo: Two-voice minuet",
+        DemoDifficulty::Intermediate,
+    ),
+];
+
+/// The built-in demos, easiest first.
+pub fn internal_demos() -> Vec<DemoInfo> {
+    DEMOS
+        .iter()
+        .map(|&(id, title, difficulty)| DemoInfo {
+            id: id.to_string(),
+            title: title.to_string(),
+            difficulty,
+        })
+        .collect()
+}
+
+/// Builds the demo with this id; `None` for an unknown id.
+pub fn build_demo_score(id: &str) -> Option<Score> {
+    let id = match id {
+        "scale" | "scale_c_major" => "c_major_scale",
+        id => id,
+    };
+    let &(_, title, _) = DEMOS.iter().find(|(demo, _, _)| *demo == id)?;
+    let mut score = Score::new(
+        ScoreMeta {
+            title: Some(title.to_string()),
+            composer: None,
+            copyright: None,
+            source: ScoreSource::Internal,
+            origin_path: None,
+        },
+        PPQ,
+    );
+    match id {
+        "c_major_scale" => {
+            let notes = [60, 62, 64, 65, 67, 69, 71, 72];
+            let mut part = Part::new(0, "Demo", None);
+            for (idx, note) in notes.into_iter().enumerate() {
+                part.chord(idx as Tick * QUARTER, QUARTER, &[note], 92);
+            }
+            score.tracks = vec![part.into_track()];
+        }
+        "chromatic_scale" => {
+            let notes = (60..=72).chain((60..72).rev());
+            let mut part = Part::new(1, "Right hand", Some(Hand::Right));
+            for (idx, note) in notes.enumerate() {
+                part.chord(idx as Tick * EIGHTH, EIGHTH, &[note], 84);
+            }
+            score.tracks = vec![part.into_track()];
+        }
+        "c_major_arpeggios" => {
+            // Two bars of eighths in parallel octaves, then the tonic held for a bar.
+            let figure = [60, 64, 67, 72, 76, 72, 67, 64];
+            let mut right = Part::new(1, "Right hand", Some(Hand::Right));
+            let mut left = Part::new(2, "Left hand", Some(Hand::Left));
+            for (idx, note) in figure.iter().chain(&figure).enumerate() {
+                let tick = idx as Tick * EIGHTH;
+                right.chord(tick, EIGHTH, &[*note], 84);
+                left.chord(tick, EIGHTH, &[*note - 12], 76);
+            }
+            right.chord(2 * WHOLE, WHOLE, &[72], 84);
+            left.chord(2 * WHOLE, WHOLE, &[60], 76);
+            score.tracks = vec![right.into_track(), left.into_track()];
+        }
+        "chord_progression" => {
+            let chords: [(&[u8], u8); 4] = [
+                (&[60, 64, 67], 48),
+                (&[60, 65, 69], 41),
+                (&[59, 62, 67], 43),
+                (&[60, 64, 67], 48),
+            ];
+            let mut right = Part::new(1, "Right hand", Some(Hand::Right));
+            let mut left = Part::new(2, "Left hand", Some(Hand::Left));
+            for (bar, (chord, bass)) in chords.into_iter().enumerate() {
+                let tick = bar as Tick * WHOLE;
+                right.chord(tick, WHOLE, chord, 80);
+                left.chord(tick, WHOLE, &[bass], 72);
+                // Legato pedal: lifted with each new chord and caught just after it.
+                left.pedal(tick + EIGHTH, true);
+                left.pedal(tick + WHOLE, false);
+            }
+            score.tracks = vec![right.into_track(), left.into_track()];
+        }
+        "two_voice_minuet" => {
+            // Melody over one dotted-half bass note a bar, slowing into the last two bars.
+            let bar = 3 * QUARTER;
+            let melody: [&[(u8, Tick)]; 8] = [
+                &[(76, 1), (74, 1), (72, 1)],
+                &[(74, 2), (67, 1)],
+                &[(76, 1), (77, 1), (79, 1)],
+                &[(72, 3)],
+                &[(81, 1), (79, 1), (77, 1)],
+                &[(76, 1), (74, 1), (72, 1)],
+                &[(74, 2), (71, 1)],
+                &[(72, 3)],
+            ];
+            let bass = [48, 43, 48, 48, 41, 48, 43, 48];
+            let mut right = Part::new(1, "Right hand", Some(Hand::Right));
+            let mut left = Part::new(2, "Left hand", Some(Hand::Left));
+            for (idx, (notes, bass)) in melody.into_iter().zip(bass).enumerate() {
+                let mut tick = idx as Tick * bar;
+                left.chord(tick, bar, &[bass], 64);
+                for &(note, beats) in notes {
+                    right.chord(tick, beats * QUARTER, &[note], 84);
+                    tick += beats * QUARTER;
+                }
+            }
+            score.time_signatures = vec![TimeSignaturePoint {
+                tick: 0,
+                numerator: 3,
+                denominator: 4,
+            }];
+            score.tempo_map = vec![
+                TempoPoint {
+                    tick: 0,
+                    us_per_quarter: 600_000,
+                },
+                TempoPoint {
+                    tick: 6 * bar,
+                    us_per_quarter: 857_143,
+                },
+            ];
+            score.tracks = vec![right.into_track(), left.into_track()];
+        }
+        _ => return None,
+    }
+    score.assign_note_ids();
+    Some(score)
+}
+
+/// One hand's notes and targets, written chord by chord.
+struct Part {
+    track: Track,
+}
+
+impl Part {
+    fn new(id: u32, name: &str, hand: Option<Hand>) -> Self {
+        Self {
+            track: Track {
+                id,
+                name: name.to_string(),
+                hand,
+                targets: Vec::new(),
+                playback_events: Vec::new(),
+            },
+        }
+    }
+
+    fn event(&self, tick: Tick, event: MidiLikeEvent) -> PlaybackMidiEvent {
+        PlaybackMidiEvent {
+            tick,
+            event,
+            hand: self.track.hand,
+            ornament_of: None,
+            bus: None,
+            note_id: None,
+            spelling: None,
+            transpose: 0,
+            articulations: Vec::new(),
+        }
+    }
+
+    fn chord(&mut self, tick: Tick, duration: Tick, notes: &[u8], velocity: u8) {
+        for &note in notes {
+            let on = self.event(tick, MidiLikeEvent::NoteOn { note, velocity });
+            let off = self.event(tick + duration, MidiLikeEvent::NoteOff { note });
+            self.track.playback_events.extend([on, off]);
+        }
+        self.track.targets.push(TargetEvent {
+            id: self.track.targets.len() as u64 + 1,
+            tick,
+            notes: notes.to_vec(),
+            hand: self.track.hand,
+            measure_index: None,
+            optional: false,
+            note_ids: Vec::new(),
+            note_tracks: Vec::new(),
+        });
+    }
+
+    /// The pedal is not tied to a hand, so either hand's routing keeps it.
+    fn pedal(&mut self, tick: Tick, down: bool) {
+        let value = if down { 127 } else { 0 };
+        let mut event = self.event(tick, MidiLikeEvent::Cc64 { value });
+        event.hand = None;
+        self.track.playback_events.push(event);
+    }
+
+    /// Events in tick order, a note ending before the pedal and notes starting at the
+    /// same tick.
+    fn into_track(mut self) -> Track {
+        self.track
+            .playback_events
+            .sort_by_key(|event| match event.event {
+                MidiLikeEvent::NoteOff { .. } => (event.tick, 0),
+                MidiLikeEvent::NoteOn { .. } => (event.tick, 2),
+                MidiLikeEvent::Cc64 { .. } | MidiLikeEvent::ControlChange { .. } => (event.tick, 1),
+            });
+        self.track
+    }
+}
@@ -1,9 +1,112 @@
-use cadenza_ports::midi::MidiLikeEvent;
+use crate::audio_graph::{AudioCallbackCounts, LateEventCounts};
+use cadenza_domain_eval::JudgeSnapshot;
+use cadenza_ports::midi::{MidiLikeEvent, RawMidiMessage};
 use cadenza_ports::storage::{SettingsDto, StorageError};
-use cadenza_ports::types::{AudioOutputDevice, MidiInputDevice};
+use cadenza_ports::types::{AudioOutputDevice, MidiInputDevice, SampleTime, Tick};
+use parking_lot::Mutex;
 use serde::Serialize;
+use std::collections::VecDeque;
 use std::fs;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+/// Lines kept by the in-memory log; older lines are discarded.
+pub const LOG_CAPACITY: usize = 500;
+
+static LOG: Mutex<VecDeque<String>> = parking_lot::const_mutex(VecDeque::new());
+static LOG_EPOCH: OnceLock<Instant> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogLevel {
+    Info,
+    Warn,
+    Error,
+}
+
+/// Appends a line to the rolling diagnostics log. Not for the audio thread.
+pub fn log_line(level: LogLevel, message: impl AsRef<str>) {
+    let elapsed = LOG_EPOCH.get_or_init(Instant::now).elapsed();
+    let level = match level {
+        LogLevel::Info => "INFO",
+        LogLevel::Warn => "WARN",
+        LogLevel::Error => "ERROR",
+    };
+    let line = format!(
+        "[{:>10.3}s] {level:<5} {}",
+        elapsed.as_secs_f64(),
+        message.as_ref()
+    );
+    let mut log = LOG.lock();
+    if log.len() >= LOG_CAPACITY {
+        log.pop_front();
+    }
+    log.push_back(line);
+}
+
+/// Most recent log lines, oldest first.
+pub fn recent_log_lines() -> Vec<String> {
+    LOG.lock().iter().cloned().collect()
+}
+
+/// `diag_log!(Warn, "audio queue full, dropped {n} events")`
+#[macro_export]
+macro_rules! diag_log {
+    ($level:ident, $($arg:tt)*) => {
+        $crate::diagnostics::log_line(
+            $crate::diagnostics::LogLevel::$level,
+            format!($($arg)*),
+        )
+    };
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SynthStatus {
+    pub soundfont_loaded: bool,
+    pub soundfont_path: Option<String>,
+    pub soundfont_name: Option<String>,
+    pub preset_count: Option<u32>,
+    pub message: Option<String>,
+}
+
+/// Events lost because a queue was full.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct QueueDropCounts {
+    pub audio_queue: u64,
+    pub midi_queue: u64,
+    /// Core events the frontend did not drain in time.
+    pub event_queue: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TransportSnapshot {
+    pub state: String,
+    pub tick: Tick,
+    pub sample: SampleTime,
+    pub tempo_multiplier: f32,
+    pub sample_rate_hz: u32,
+    /// Effective scheduler lookahead.
+    pub lookahead_ms: f64,
+}
+
+/// Everything written by [`export_diagnostics`], gathered by the caller.
+#[derive(Clone, Debug)]
+pub struct DiagnosticsSnapshot {
+    pub settings: SettingsDto,
+    pub midi_inputs: Vec<MidiInputDevice>,
+    pub audio_outputs: Vec<AudioOutputDevice>,
+    pub recent_events: Vec<MidiLikeEvent>,
+    /// Empty unless the MIDI backend keeps raw messages.
+    pub raw_midi_messages: Vec<RawMidiMessage>,
+    /// `None` while no output stream is open.
+    pub audio_callbacks: Option<AudioCallbackCounts>,
+    pub late_events: LateEventCounts,
+    pub queue_drops: QueueDropCounts,
+    pub synth: SynthStatus,
+    pub transport: TransportSnapshot,
+    pub judge: JudgeSnapshot,
+}
 
 #[derive(Serialize)]
 struct AppVersion {
@@ -18,23 +121,37 @@ struct PlatformInfo {
 }
 
 #[derive(Serialize)]
-struct DeviceSnapshot {
-    midi_inputs: Vec<MidiInputDevice>,
-    audio_outputs: Vec<AudioOutputDevice>,
+struct DeviceSnapshot<'a> {
+    midi_inputs: &'a [MidiInputDevice],
+    audio_outputs: &'a [AudioOutputDevice],
 }
 
 #[derive(Serialize)]
-struct RecentEvents {
-    events: Vec<MidiLikeEvent>,
+struct RecentEvents<'a> {
+    events: &'a [MidiLikeEvent],
+    raw_messages: &'a [RawMidiMessage],
 }
 
+#[derive(Serialize)]
+struct RuntimeStats<'a> {
+    audio_callbacks: Option<AudioCallbackCounts>,
+    late_events: LateEventCounts,
+    queue_drops: QueueDropCounts,
+    synth: &'a SynthStatus,
+}
+
+#[derive(Serialize)]
+struct SessionSnapshot<'a> {
+    transport: &'a TransportSnapshot,
+    judge: JudgeSnapshot,
+}
+
+/// Writes the diagnostics files into `dir` and bundles them into `<dir>.zip`, whose path is
+/// returned.
 pub fn export_diagnostics(
     dir: &Path,
-    settings: &SettingsDto,
-    midi_inputs: Vec<MidiInputDevice>,
-    audio_outputs: Vec<AudioOutputDevice>,
-    recent_events: Vec<MidiLikeEvent>,
-) -> Result<(), StorageError> {
+    snapshot: &DiagnosticsSnapshot,
+) -> Result<PathBuf, StorageError> {
     fs::create_dir_all(dir).map_err(|e| StorageError::Io(e.to_string()))?;
 
     let app_version = AppVersion {
@@ -49,25 +166,75 @@ pub fn export_diagnostics(
 
     write_json(&dir.join("app_version.json"), &app_version)?;
     write_json(&dir.join("platform.json"), &platform)?;
-    write_json(&dir.join("settings.json"), settings)?;
+    write_json(&dir.join("settings.json"), &snapshot.settings)?;
     write_json(
         &dir.join("device_snapshot.json"),
         &DeviceSnapshot {
-            midi_inputs,
-            audio_outputs,
+            midi_inputs: &snapshot.midi_inputs,
+            audio_outputs: &snapshot.audio_outputs,
         },
     )?;
     write_json(
         &dir.join("recent_events.json"),
         &RecentEvents {
-            events: recent_events,
+            events: &snapshot.recent_events,
+            raw_messages: &snapshot.raw_midi_messages,
+        },
+    )?;
+    write_json(
+        &dir.join("runtime_stats.json"),
+        &RuntimeStats {
+            audio_callbacks: snapshot.audio_callbacks,
+            late_events: snapshot.late_events,
+            queue_drops: snapshot.queue_drops,
+            synth: &snapshot.synth,
         },
     )?;
+    write_json(
+        &dir.join("session.json"),
+        &SessionSnapshot {
+            transport: &snapshot.transport,
+            judge: snapshot.judge,
+        },
+    )?;
+
+    let mut logs = recent_log_lines().join("\n");
+    logs.push('\n');
+    fs::write(dir.join("logs.txt"), logs).map_err(|e| StorageError::Io(e.to_string()))?;
+
+    zip_directory(dir)
+}
+
+/// Zips the regular files directly inside `dir` into a sibling `<dir>.zip`.
+fn zip_directory(dir: &Path) -> Result<PathBuf, StorageError> {
+    let io_error = |e: std::io::Error| StorageError::Io(e.to_string());
+    let zip_error = |e: zip::result::ZipError| StorageError::Io(e.to_string());
+
+    let mut zip_path = dir.as_os_str().to_owned();
+    zip_path.push(".zip");
+    let zip_path = PathBuf::from(zip_path);
 
-    fs::write(dir.join("logs.txt"), b"logs not configured\n")
-        .map_err(|e| StorageError::Io(e.to_string()))?;
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(io_error)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    entries.sort();
 
-    Ok(())
+    let file = fs::File::create(&zip_path).map_err(io_error)?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    for path in entries {
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        zip.start_file(name, options).map_err(zip_error)?;
+        zip.write_all(&fs::read(&path).map_err(io_error)?)
+            .map_err(io_error)?;
+    }
+    zip.finish().map_err(zip_error)?;
+    Ok(zip_path)
 }
 
 fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), StorageError> {
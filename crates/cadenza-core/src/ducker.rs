@@ -0,0 +1,94 @@
+use crate::limiter::SETTLE_TIME_CONSTANTS;
+use cadenza_ports::types::DuckingParams;
+
+/// Monitor level above which the player counts as playing (-50 dBFS).
+pub const DUCKING_THRESHOLD: f32 = 0.003_16;
+/// How long the player still counts as playing after the monitor drops below the threshold,
+/// so the gain holds between notes and through the zero crossings of a quiet note.
+pub const DUCKING_HOLD_MS: f32 = 50.0;
+
+/// Gain for the autopilot bus that dips while the user monitor is playing. The level is
+/// taken from the monitor's rendered output, so a muted monitor never ducks.
+pub struct Ducker {
+    sample_rate_hz: u32,
+    params: DuckingParams,
+    attack_coeff: f32,
+    release_coeff: f32,
+    floor: f32,
+    hold_samples: u32,
+    /// Samples left before the player stops counting as playing.
+    held: u32,
+    gain: f32,
+}
+
+impl Ducker {
+    pub fn new(sample_rate_hz: u32, params: DuckingParams) -> Self {
+        let mut ducker = Self {
+            sample_rate_hz,
+            params,
+            attack_coeff: 0.0,
+            release_coeff: 0.0,
+            floor: 1.0,
+            hold_samples: (DUCKING_HOLD_MS / 1000.0 * sample_rate_hz as f32).round() as u32,
+            held: 0,
+            gain: 1.0,
+        };
+        ducker.apply_params(params);
+        ducker
+    }
+
+    pub fn set_params(&mut self, params: DuckingParams) {
+        if params != self.params {
+            self.apply_params(params);
+        }
+    }
+
+    fn apply_params(&mut self, params: DuckingParams) {
+        let params = params.clamped();
+        self.params = params;
+        let coeff = |ms: f32| {
+            let samples = (ms / 1000.0 * self.sample_rate_hz as f32).max(1.0);
+            (-SETTLE_TIME_CONSTANTS / samples).exp()
+        };
+        self.attack_coeff = coeff(params.attack_ms);
+        self.release_coeff = coeff(params.release_ms);
+        self.floor = 10.0_f32.powf(-params.depth_db / 20.0);
+    }
+
+    /// The gain applied to the last sample.
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Replaces each monitor level in `levels` with the autopilot gain for that sample.
+    pub fn process(&mut self, levels: &mut [f32]) {
+        if !self.params.enabled && self.gain == 1.0 {
+            self.held = 0;
+            levels.fill(1.0);
+            return;
+        }
+        for level in levels.iter_mut() {
+            if *level > DUCKING_THRESHOLD {
+                self.held = self.hold_samples;
+            } else {
+                self.held = self.held.saturating_sub(1);
+            }
+            let target = if self.params.enabled && self.held > 0 {
+                self.floor
+            } else {
+                1.0
+            };
+            let coeff = if target < self.gain {
+                self.attack_coeff
+            } else {
+                self.release_coeff
+            };
+            self.gain = target + (self.gain - target) * coeff;
+            // Land on unity so the idle path above is taken again.
+            if target == 1.0 && self.gain > 0.999_9 {
+                self.gain = 1.0;
+            }
+            *level = self.gain;
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use cadenza_ports::playback::LoopRange;
+use cadenza_ports::types::Tick;
+
+pub const DEFAULT_FOLLOW_CORRECTION_RATE: f32 = 0.5;
+/// Largest shift from a single hit, in quarters, so one stray note cannot jump the music.
+const MAX_CORRECTION_QUARTERS: f64 = 0.25;
+
+/// Experimental: moves the transport towards the player on every hit instead of leaving
+/// the audio clock alone in charge. A player who keeps rushing or dragging stays a bounded
+/// distance from the accompaniment instead of drifting away from it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FollowMode {
+    correction_rate: f32,
+}
+
+impl FollowMode {
+    /// `correction_rate` is the share of each hit's timing error taken out, 0 to 1.
+    pub fn new(correction_rate: f32) -> Self {
+        Self {
+            correction_rate: correction_rate.clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn correction_rate(&self) -> f32 {
+        self.correction_rate
+    }
+
+    /// Ticks to move the transport at `now_tick` after a hit `delta_tick` off its target:
+    /// forward for an early hit, back for a late one. Never leaves `loop_range`, so a
+    /// correction can neither skip nor repeat a wrap.
+    pub fn correction(
+        &self,
+        delta_tick: Tick,
+        ppq: u16,
+        now_tick: Tick,
+        loop_range: Option<LoopRange>,
+    ) -> Tick {
+        let max = (f64::from(ppq) * MAX_CORRECTION_QUARTERS).round() as Tick;
+        let wanted = (-(delta_tick as f64) * f64::from(self.correction_rate)).round() as Tick;
+        let mut target = now_tick + wanted.clamp(-max, max);
+        if let Some(range) = loop_range.filter(|range| range.end_tick > range.start_tick) {
+            if (range.start_tick..range.end_tick).contains(&now_tick) {
+                target = target.clamp(range.start_tick, range.end_tick - 1);
+            }
+        }
+        target.max(0) - now_tick
+    }
+}
+
+impl Default for FollowMode {
+    fn default() -> Self {
+        Self::new(DEFAULT_FOLLOW_CORRECTION_RATE)
+    }
+}
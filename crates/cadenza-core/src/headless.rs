@@ -0,0 +1,344 @@
+use crate::app::{AppCore, AppError};
+use crate::clock::Clock;
+use crate::ipc::{Command, Event, ScoreSource};
+use cadenza_domain_eval::Grade;
+use cadenza_ports::audio::{AudioError, AudioOutputPort, AudioRenderCallback, AudioStreamHandle};
+use cadenza_ports::midi::{
+    MidiError, MidiInputPort, MidiInputStream, MidiLikeEvent, PlayerEvent, PlayerEventCallback,
+};
+use cadenza_ports::storage::StoragePort;
+use cadenza_ports::synth::{SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::types::{
+    AudioConfig, AudioOutputDevice, Bus, DeviceId, MidiInputDevice, SampleTime,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub const HEADLESS_AUDIO_DEVICE_ID: &str = "virtual";
+pub const HEADLESS_MIDI_DEVICE_ID: &str = "script";
+const SAMPLE_RATE_HZ: u32 = 48_000;
+const BLOCK_FRAMES: usize = 512;
+
+type SharedRender = Arc<Mutex<Option<Box<dyn AudioRenderCallback>>>>;
+type SharedMidiCallback = Arc<Mutex<Option<PlayerEventCallback>>>;
+
+#[derive(thiserror::Error, Debug)]
+pub enum HeadlessError {
+    #[error(transparent)]
+    App(#[from] AppError),
+    #[error("midi script: {0}")]
+    Script(String),
+    #[error("practice still running after {0:?}")]
+    Timeout(Duration),
+}
+
+/// Wall time that only moves when the runner renders audio, so a run is the same every time.
+#[derive(Debug)]
+pub struct VirtualClock {
+    origin: Instant,
+    elapsed_nanos: AtomicU64,
+}
+
+impl VirtualClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+            elapsed_nanos: AtomicU64::new(0),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.elapsed_nanos
+            .fetch_add(by.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.elapsed_nanos.load(Ordering::Relaxed))
+    }
+}
+
+impl Default for VirtualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Instant {
+        self.origin + self.elapsed()
+    }
+}
+
+/// Output whose callback the runner calls itself, one block per step.
+struct VirtualAudioOutputPort {
+    render: SharedRender,
+}
+
+struct VirtualAudioStream;
+
+impl AudioStreamHandle for VirtualAudioStream {
+    fn close(self: Box<Self>) {}
+}
+
+impl AudioOutputPort for VirtualAudioOutputPort {
+    fn list_outputs(&self) -> Result<Vec<AudioOutputDevice>, AudioError> {
+        Ok(vec![AudioOutputDevice {
+            id: DeviceId(HEADLESS_AUDIO_DEVICE_ID.to_string()),
+            name: "Virtual".to_string(),
+            default_config: AudioConfig {
+                sample_rate_hz: SAMPLE_RATE_HZ,
+                channels: 2,
+                buffer_size_frames: Some(BLOCK_FRAMES as u32),
+            },
+            buffer_size_range: None,
+        }])
+    }
+
+    fn open_output(
+        &self,
+        _device_id: &DeviceId,
+        _config: AudioConfig,
+        cb: Box<dyn AudioRenderCallback>,
+    ) -> Result<Box<dyn AudioStreamHandle>, AudioError> {
+        *self.render.lock() = Some(cb);
+        Ok(Box::new(VirtualAudioStream))
+    }
+}
+
+/// Input the runner plays a [`ScriptedMidiEvent`] list through.
+struct ScriptedMidiInputPort {
+    callback: SharedMidiCallback,
+}
+
+struct ScriptedMidiStream;
+
+impl MidiInputStream for ScriptedMidiStream {
+    fn close(self: Box<Self>) {}
+}
+
+impl MidiInputPort for ScriptedMidiInputPort {
+    fn list_inputs(&self) -> Result<Vec<MidiInputDevice>, MidiError> {
+        Ok(vec![MidiInputDevice {
+            id: DeviceId(HEADLESS_MIDI_DEVICE_ID.to_string()),
+            name: "Script".to_string(),
+            is_available: true,
+        }])
+    }
+
+    fn open_input(
+        &self,
+        _device_id: &DeviceId,
+        cb: PlayerEventCallback,
+    ) -> Result<Box<dyn MidiInputStream>, MidiError> {
+        *self.callback.lock() = Some(cb);
+        Ok(Box::new(ScriptedMidiStream))
+    }
+}
+
+/// Renders nothing, for runs where only judging matters.
+#[derive(Debug, Default)]
+pub struct SilentSynth;
+
+impl SynthPort for SilentSynth {
+    fn load_soundfont_from_path(&self, _path: &str) -> Result<SoundFontInfo, SynthError> {
+        Err(SynthError::UnsupportedFormat)
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, _bus: Bus, _gm_program: u8) -> Result<(), SynthError> {
+        Ok(())
+    }
+
+    fn handle_event(&self, _bus: Bus, _event: MidiLikeEvent, _at: SampleTime) {}
+
+    fn render(&self, _bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        out_l[..frames].fill(0.0);
+        out_r[..frames].fill(0.0);
+    }
+}
+
+/// A player event `at_ms` after practice starts.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScriptedMidiEvent {
+    pub at_ms: f64,
+    pub event: MidiLikeEvent,
+}
+
+/// Reads a JSON array of [`ScriptedMidiEvent`]s.
+pub fn load_midi_script(path: &Path) -> Result<Vec<ScriptedMidiEvent>, HeadlessError> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| HeadlessError::Script(format!("{}: {e}", path.display())))?;
+    let mut script: Vec<ScriptedMidiEvent> = serde_json::from_slice(&bytes)
+        .map_err(|e| HeadlessError::Script(format!("{}: {e}", path.display())))?;
+    script.sort_by(|a, b| a.at_ms.total_cmp(&b.at_ms));
+    Ok(script)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TargetResult {
+    pub target_id: u64,
+    pub notes: Vec<u8>,
+    pub grade: Grade,
+    pub delta_tick: i64,
+}
+
+/// How a practice run went, target by target.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PracticeReport {
+    pub targets: Vec<TargetResult>,
+    pub hits: u32,
+    pub misses: u32,
+    pub score: i64,
+    pub accuracy: f32,
+    /// Notes played between targets rather than for one.
+    pub extra_notes: u32,
+}
+
+/// `AppCore` on a virtual clock, with a virtual output and a scripted input, for tools and
+/// tests that practice without devices or real time.
+pub struct HeadlessRunner {
+    core: AppCore,
+    clock: Arc<VirtualClock>,
+    render: SharedRender,
+    midi: SharedMidiCallback,
+    sample_time: SampleTime,
+}
+
+impl HeadlessRunner {
+    pub fn new(
+        synth: Arc<dyn SynthPort>,
+        storage: Option<Box<dyn StoragePort>>,
+    ) -> Result<Self, HeadlessError> {
+        let render: SharedRender = Arc::new(Mutex::new(None));
+        let midi: SharedMidiCallback = Arc::new(Mutex::new(None));
+        let clock = Arc::new(VirtualClock::new());
+        let mut core = AppCore::new(
+            Box::new(VirtualAudioOutputPort {
+                render: render.clone(),
+            }),
+            Box::new(ScriptedMidiInputPort {
+                callback: midi.clone(),
+            }),
+            synth,
+            None,
+            storage,
+        )?;
+        core.set_clock(clock.clone());
+        core.handle_command(Command::SelectAudioOutput {
+            device_id: DeviceId(HEADLESS_AUDIO_DEVICE_ID.to_string()),
+            config: None,
+        })?;
+        core.handle_command(Command::SelectMidiInput {
+            device_id: DeviceId(HEADLESS_MIDI_DEVICE_ID.to_string()),
+        })?;
+        Ok(Self {
+            core,
+            clock,
+            render,
+            midi,
+            sample_time: 0,
+        })
+    }
+
+    pub fn core(&mut self) -> &mut AppCore {
+        &mut self.core
+    }
+
+    pub fn load_score(&mut self, source: ScoreSource) -> Result<(), HeadlessError> {
+        self.core.handle_command(Command::LoadScore { source })?;
+        Ok(())
+    }
+
+    /// Renders one block, moves the clock over it and ticks the core.
+    pub fn step(&mut self) {
+        let mut left = [0.0; BLOCK_FRAMES];
+        let mut right = [0.0; BLOCK_FRAMES];
+        if let Some(render) = self.render.lock().as_mut() {
+            render.render(self.sample_time, &mut left, &mut right);
+        }
+        self.sample_time += BLOCK_FRAMES as SampleTime;
+        self.clock.advance(Duration::from_secs_f64(
+            BLOCK_FRAMES as f64 / f64::from(SAMPLE_RATE_HZ),
+        ));
+        self.core.tick();
+    }
+
+    /// Practices the loaded score from its current position, playing `script`, until every
+    /// target is judged. Gives up after `max_duration` of virtual time.
+    pub fn practice(
+        &mut self,
+        script: &[ScriptedMidiEvent],
+        max_duration: Duration,
+    ) -> Result<PracticeReport, HeadlessError> {
+        self.core.drain_events();
+        self.core.handle_command(Command::StartPractice)?;
+        let started = self.clock.now();
+        let mut pending = script.iter().peekable();
+        let mut report = PracticeReport::default();
+        let mut judged_all = false;
+
+        while !judged_all || pending.peek().is_some() {
+            let elapsed = self.clock.now().duration_since(started);
+            if elapsed > max_duration {
+                return Err(HeadlessError::Timeout(max_duration));
+            }
+            let callback = self.midi.lock().clone();
+            while let Some(scripted) = pending.next_if(|scripted| {
+                Duration::from_secs_f64(scripted.at_ms.max(0.0) / 1000.0) <= elapsed
+            }) {
+                if let Some(callback) = callback.as_ref() {
+                    callback(PlayerEvent {
+                        at: started + Duration::from_secs_f64(scripted.at_ms.max(0.0) / 1000.0),
+                        event: scripted.event,
+                    });
+                }
+            }
+            self.step();
+            for event in self.core.drain_events() {
+                match event {
+                    Event::JudgeFeedback {
+                        target_id,
+                        grade,
+                        delta_tick,
+                        expected_notes,
+                        ..
+                    } => {
+                        if grade == Grade::Miss {
+                            report.misses += 1;
+                        } else {
+                            report.hits += 1;
+                        }
+                        report.targets.push(TargetResult {
+                            target_id,
+                            notes: expected_notes,
+                            grade,
+                            delta_tick,
+                        });
+                    }
+                    Event::ScoreSummaryUpdated {
+                        score,
+                        accuracy,
+                        extra_notes,
+                        ..
+                    } => {
+                        report.score = score;
+                        report.accuracy = accuracy;
+                        report.extra_notes = extra_notes;
+                    }
+                    Event::FocusChanged {
+                        target_id: None, ..
+                    } => judged_all = true,
+                    _ => {}
+                }
+            }
+        }
+
+        self.core.handle_command(Command::StopPractice)?;
+        Ok(report)
+    }
+}
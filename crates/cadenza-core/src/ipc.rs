@@ -1,12 +1,26 @@
+use crate::audio_graph::SynthLoad;
+use crate::audio_meters::MeterLevel;
+use crate::audio_self_test::SelfTestStageResult;
+use crate::demos::DemoInfo;
+use crate::midi_capture::MidiCaptureReport;
+use crate::session_snapshot::SessionSnapshot;
 use cadenza_domain_eval::Grade;
-use cadenza_domain_score::Hand;
-use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_domain_score::{
+    Hand, KeySignaturePoint, NoteId, PartSelection, ScoreAnalysis, ScoreSoundOverrides,
+};
+use cadenza_ports::midi::{MidiAction, MidiLikeEvent, MidiMapping};
 use cadenza_ports::playback::{LoopRange, PlaybackMode};
 use cadenza_ports::storage::SettingsDto;
+use cadenza_ports::synth::QualityHint;
 use cadenza_ports::types::{
-    AudioConfig, AudioOutputDevice, Bus, DeviceId, MidiInputDevice, SampleTime, Tick, Volume01,
+    AudioConfig, AudioOutputDevice, Bus, DeviceId, DuckingParams, LimiterParams, MidiInputDevice,
+    SampleTime, Tick, Volume01, VolumeCurve,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Events held for the frontend before the oldest are dropped.
+pub const EVENT_QUEUE_CAPACITY: usize = 4096;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PianoRollNoteDto {
@@ -15,6 +29,24 @@ pub struct PianoRollNoteDto {
     pub end_tick: Tick,
     pub velocity: u8,
     pub hand: Option<Hand>,
+    #[serde(default)]
+    pub id: Option<NoteId>,
+    /// Spelled as written, or in the key at `start_tick`, e.g. "F#4".
+    #[serde(default)]
+    pub name: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GridLineKind {
+    Bar,
+    Beat,
+    Subdivision,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GridLineDto {
+    pub tick: Tick,
+    pub kind: GridLineKind,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -28,14 +60,25 @@ pub struct PianoRollTargetDto {
     pub id: u64,
     pub tick: Tick,
     pub notes: Vec<u8>,
+    #[serde(default)]
+    pub hand: Option<Hand>,
+    /// Ids of the notes' spans, parallel to `notes`.
+    #[serde(default)]
+    pub note_ids: Vec<NoteId>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", content = "payload")]
 pub enum ScoreSource {
     MidiFile(String),
-    MusicXmlFile(String),
+    MusicXmlFile {
+        path: String,
+        #[serde(default)]
+        parts: PartSelection,
+    },
     InternalDemo(String),
+    /// Project saved by `SaveScoreFile`.
+    CadenzaFile(String),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -51,6 +94,15 @@ pub enum Command {
         device_id: DeviceId,
         config: Option<AudioConfig>,
     },
+    /// Closes and reopens the audio output and MIDI input, as after the system wakes from
+    /// sleep. Uses the saved devices, or the first listed ones when those are gone, and
+    /// keeps the transport position.
+    RefreshDevices,
+    /// Reopens the current output at the same sample rate with a new buffer size; `None` is
+    /// the device default.
+    SetAudioBufferSize {
+        frames: Option<u32>,
+    },
     TestAudio,
     SetMonitorEnabled {
         enabled: bool,
@@ -62,6 +114,28 @@ pub enum Command {
     SetMasterVolume {
         volume: Volume01,
     },
+    /// Changes the fader curve; stored volumes are converted so loudness is unchanged.
+    SetVolumeCurve {
+        curve: VolumeCurve,
+    },
+    SetLimiterParams {
+        params: LimiterParams,
+    },
+    /// Turns the autopilot bus down while the user monitor is playing.
+    SetDuckingParams {
+        params: DuckingParams,
+    },
+    /// Records the master output, after the limiter, to a 32-bit float WAV file.
+    StartAudioRecording {
+        path: String,
+    },
+    StopAudioRecording,
+    /// Plays a short tone on each bus and reports which stage of the audio path works.
+    RunAudioSelfTest,
+    /// Records raw MIDI input for `seconds`, then reports its timing.
+    StartMidiCapture {
+        seconds: f32,
+    },
     LoadSoundFont {
         path: String,
     },
@@ -69,6 +143,21 @@ pub enum Command {
         bus: Bus,
         gm_program: u8,
     },
+    /// Shortens (below 1) or lengthens (above 1) how long notes struck from now on ring.
+    SetDecayScale {
+        scale: f32,
+    },
+    /// Holds the synth at a quality; `None` lets it drop to reduced quality while the audio
+    /// output keeps overrunning and come back once it is stable.
+    SetSynthQuality {
+        quality: Option<QualityHint>,
+    },
+    /// How many targets `UpcomingTargets` lists; 0 stops the event.
+    SetUpcomingTargetCount {
+        count: u32,
+    },
+    /// Lists the scores `ScoreSource::InternalDemo` can load.
+    ListInternalDemos,
     LoadScore {
         source: ScoreSource,
     },
@@ -79,6 +168,12 @@ pub enum Command {
     StartPractice,
     PausePractice,
     StopPractice,
+    /// Loads the score of the session offered by `RecoveredSession`, at its position and
+    /// with its counts, ready to start.
+    ResumeRecoveredSession,
+    /// Releases every note and the pedal on all buses and drops events queued for audio,
+    /// in any session state. Practice settings and the transport are left alone.
+    PanicAllNotes,
     Seek {
         tick: Tick,
     },
@@ -89,17 +184,55 @@ pub enum Command {
     },
     SetTempoMultiplier {
         x: f32,
+        /// Glide to `x` over this many milliseconds instead of jumping.
+        #[serde(default)]
+        ramp_ms: Option<u32>,
     },
     SetPlaybackMode {
         mode: PlaybackMode,
     },
+    /// Experimental: each hit pulls the transport towards the player. `correction_rate` is
+    /// the share of a hit's timing error corrected, 0 to 1.
+    SetFollowMode {
+        enabled: bool,
+        #[serde(default)]
+        correction_rate: Option<f32>,
+    },
     SetAccompanimentRoute {
         play_left: bool,
         play_right: bool,
+        /// Drop the score's pedal events so the player pedals alone.
+        #[serde(default)]
+        suppress_pedal: bool,
+    },
+    /// Velocity scaling and humanization for autopilot notes.
+    SetAutopilotFeel {
+        velocity_scale: f32,
+        humanize_timing_ms: f32,
+        humanize_velocity: u8,
+        /// Velocity of held notes struck again at a seek or loop start; 0 skips them.
+        #[serde(default)]
+        resound_velocity_scale: Option<f32>,
     },
     SetInputOffsetMs {
         ms: i32,
     },
+    /// Rate of live `MidiInputEvent`s; 0 turns them off, leaving `RecentInputEvents`.
+    SetInputEventRate {
+        hz: u32,
+    },
+    /// Replaces the table of keyboard controls bound to transport actions.
+    SetMidiMapping {
+        mappings: Vec<MidiMapping>,
+    },
+    /// Binds the next key or controller pressed on the MIDI input to `action`.
+    LearnMidiMapping {
+        action: MidiAction,
+    },
+    /// Marks a first-run setup step done, whether it was configured or skipped.
+    CompleteSetupStep {
+        step: SetupStep,
+    },
     SetAudiverisPath {
         path: String,
     },
@@ -107,11 +240,131 @@ pub enum Command {
         pdf_path: String,
         output_path: String,
         audiveris_path: Option<String>,
+        #[serde(default)]
+        split_by_hand: bool,
+        /// Drop misread notes and clamp stray velocities; see `cleanup_omr_score`.
+        #[serde(default = "default_omr_cleanup")]
+        omr_cleanup: bool,
     },
     CancelPdfToMidi,
+    /// Unset fields default to the current loop range (or the whole score)
+    /// and the current tempo multiplier.
+    ExportMidiRange {
+        path: String,
+        #[serde(default)]
+        start_tick: Option<Tick>,
+        #[serde(default)]
+        end_tick: Option<Tick>,
+        #[serde(default)]
+        tempo_multiplier: Option<f32>,
+    },
     ExportDiagnostics {
         path: String,
     },
+    /// Saves the loaded score as a Cadenza project, keeping hands, targets and measures
+    /// exactly as they are now.
+    SaveScoreFile {
+        path: String,
+    },
+    /// Assigns notes starting in `start_tick..end_tick` with a pitch in
+    /// `min_note..=max_note` to `hand`.
+    SetNotesHand {
+        start_tick: Tick,
+        end_tick: Tick,
+        min_note: u8,
+        max_note: u8,
+        hand: Hand,
+    },
+    /// Trades left and right for every note starting in `start_tick..end_tick`.
+    SwapHands {
+        start_tick: Tick,
+        end_tick: Tick,
+    },
+    /// Practices and plays only the score tracks with these ids, merged into one; empty
+    /// selects every track. Lasts until the next score loads.
+    SelectScoreTracks {
+        ids: Vec<u32>,
+    },
+    /// Sounds for the loaded score in place of the global ones, until another score loads.
+    /// Saved with the score by `SaveScoreFile`.
+    SetScoreSoundOverrides {
+        overrides: ScoreSoundOverrides,
+    },
+    ClearScoreSoundOverrides,
+}
+
+fn default_omr_cleanup() -> bool {
+    true
+}
+
+impl Command {
+    /// Variant name, as reported in [`Event::CommandFailed`].
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Command::GetSessionState => "GetSessionState",
+            Command::ListMidiInputs => "ListMidiInputs",
+            Command::SelectMidiInput { .. } => "SelectMidiInput",
+            Command::ListAudioOutputs => "ListAudioOutputs",
+            Command::SelectAudioOutput { .. } => "SelectAudioOutput",
+            Command::RefreshDevices => "RefreshDevices",
+            Command::SetAudioBufferSize { .. } => "SetAudioBufferSize",
+            Command::TestAudio => "TestAudio",
+            Command::SetMonitorEnabled { .. } => "SetMonitorEnabled",
+            Command::SetBusVolume { .. } => "SetBusVolume",
+            Command::SetMasterVolume { .. } => "SetMasterVolume",
+            Command::SetVolumeCurve { .. } => "SetVolumeCurve",
+            Command::SetLimiterParams { .. } => "SetLimiterParams",
+            Command::SetDuckingParams { .. } => "SetDuckingParams",
+            Command::StartAudioRecording { .. } => "StartAudioRecording",
+            Command::StopAudioRecording => "StopAudioRecording",
+            Command::RunAudioSelfTest => "RunAudioSelfTest",
+            Command::StartMidiCapture { .. } => "StartMidiCapture",
+            Command::LoadSoundFont { .. } => "LoadSoundFont",
+            Command::SetProgram { .. } => "SetProgram",
+            Command::SetDecayScale { .. } => "SetDecayScale",
+            Command::SetSynthQuality { .. } => "SetSynthQuality",
+            Command::SetUpcomingTargetCount { .. } => "SetUpcomingTargetCount",
+            Command::ListInternalDemos => "ListInternalDemos",
+            Command::LoadScore { .. } => "LoadScore",
+            Command::SetPracticeRange { .. } => "SetPracticeRange",
+            Command::StartPractice => "StartPractice",
+            Command::PausePractice => "PausePractice",
+            Command::StopPractice => "StopPractice",
+            Command::ResumeRecoveredSession => "ResumeRecoveredSession",
+            Command::PanicAllNotes => "PanicAllNotes",
+            Command::Seek { .. } => "Seek",
+            Command::SetLoop { .. } => "SetLoop",
+            Command::SetTempoMultiplier { .. } => "SetTempoMultiplier",
+            Command::SetPlaybackMode { .. } => "SetPlaybackMode",
+            Command::SetFollowMode { .. } => "SetFollowMode",
+            Command::SetAccompanimentRoute { .. } => "SetAccompanimentRoute",
+            Command::SetAutopilotFeel { .. } => "SetAutopilotFeel",
+            Command::SetInputOffsetMs { .. } => "SetInputOffsetMs",
+            Command::SetInputEventRate { .. } => "SetInputEventRate",
+            Command::SetMidiMapping { .. } => "SetMidiMapping",
+            Command::LearnMidiMapping { .. } => "LearnMidiMapping",
+            Command::CompleteSetupStep { .. } => "CompleteSetupStep",
+            Command::SetAudiverisPath { .. } => "SetAudiverisPath",
+            Command::ConvertPdfToMidi { .. } => "ConvertPdfToMidi",
+            Command::CancelPdfToMidi => "CancelPdfToMidi",
+            Command::ExportMidiRange { .. } => "ExportMidiRange",
+            Command::ExportDiagnostics { .. } => "ExportDiagnostics",
+            Command::SaveScoreFile { .. } => "SaveScoreFile",
+            Command::SetNotesHand { .. } => "SetNotesHand",
+            Command::SwapHands { .. } => "SwapHands",
+            Command::SelectScoreTracks { .. } => "SelectScoreTracks",
+            Command::SetScoreSoundOverrides { .. } => "SetScoreSoundOverrides",
+            Command::ClearScoreSoundOverrides => "ClearScoreSoundOverrides",
+        }
+    }
+}
+
+/// Step of the first-run setup.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SetupStep {
+    MidiInput,
+    AudioOutput,
+    Score,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -127,20 +380,41 @@ pub enum SessionState {
 pub enum Event {
     ScoreViewUpdated {
         title: Option<String>,
+        composer: Option<String>,
+        /// Read from a PDF by OMR.
+        #[serde(default)]
+        from_omr: bool,
+        /// The file the score was first read from, such as the PDF behind an OMR score.
+        #[serde(default)]
+        origin_path: Option<String>,
         ppq: u16,
+        key_signatures: Vec<KeySignaturePoint>,
         notes: Vec<PianoRollNoteDto>,
         targets: Vec<PianoRollTargetDto>,
         pedal: Vec<PianoRollPedalDto>,
+        /// Bar, beat and subdivision lines up to the end of the score, in tick order.
+        #[serde(default)]
+        grid: Vec<GridLineDto>,
     },
     MidiInputsUpdated {
         devices: Vec<MidiInputDevice>,
     },
+    /// The open MIDI input went away; it is reopened when the device is listed again.
+    MidiInputLost {
+        device_id: DeviceId,
+    },
+    MidiInputRestored {
+        device_id: DeviceId,
+    },
     AudioOutputsUpdated {
         devices: Vec<AudioOutputDevice>,
     },
     SessionStateUpdated {
         state: SessionState,
         settings: SettingsDto,
+        /// What the loaded score changes of `settings`; empty when it changes nothing.
+        #[serde(default)]
+        score_sound_overrides: ScoreSoundOverrides,
     },
     SoundFontStatus {
         loaded: bool,
@@ -149,11 +423,88 @@ pub enum Event {
         preset_count: Option<u32>,
         message: Option<String>,
     },
+    AudioLevels {
+        master: MeterLevel,
+        user: MeterLevel,
+        autopilot: MeterLevel,
+        metronome: MeterLevel,
+        gain_reduction_db: f32,
+    },
+    /// Events that reached the audio thread after their scheduled time, since the app started.
+    LateAudioEvents {
+        late_events: u64,
+        dropped_note_ons: u64,
+        max_lateness_ms: f32,
+    },
+    AudioRecordingUpdated {
+        recording: bool,
+        path: Option<String>,
+        duration_secs: f64,
+        /// Frames lost because the writer thread fell behind the audio callback.
+        dropped_frames: u64,
+    },
     OmrProgress {
         page: u32,
         total: u32,
         stage: String,
     },
+    /// Sent about once a second while an output is open, once enough clock anchors exist.
+    AudioStats {
+        input_jitter_ms: f32,
+        clock_drift_ppm: f32,
+        #[serde(default)]
+        synth_load: SynthLoad,
+    },
+    /// The synth has needed most of the realtime budget for several seconds; dropouts are
+    /// likely.
+    SynthLoadWarning {
+        load: SynthLoad,
+        message: String,
+    },
+    /// The synth switched to cheaper voices, on its own after sustained overruns when
+    /// `automatic`, otherwise on request.
+    QualityReduced {
+        automatic: bool,
+        message: String,
+    },
+    QualityRestored {
+        automatic: bool,
+    },
+    /// The audio clock runs off its nominal rate; input timing and tempo will be off.
+    ClockDriftWarning {
+        clock_drift_ppm: f32,
+        message: String,
+    },
+    /// The output opened at another rate than requested, and was reopened at that rate so
+    /// notes keep their pitch.
+    SampleRateCorrected {
+        device_id: DeviceId,
+        requested_hz: u32,
+        effective_hz: u32,
+        message: String,
+    },
+    /// An audio output was opened. `effective` is what the backend granted, which may differ
+    /// from the requested buffer size.
+    AudioOutputConfigured {
+        device_id: DeviceId,
+        requested_buffer_size_frames: Option<u32>,
+        effective: AudioConfig,
+    },
+    /// No audio output could be opened, so practice continues on the silent output.
+    SilentAudioFallback {
+        reason: String,
+    },
+    /// Non-fatal problems found while importing the score that was just loaded.
+    ImportWarnings {
+        messages: Vec<String>,
+    },
+    InternalDemosListed {
+        demos: Vec<DemoInfo>,
+    },
+    /// Difficulty of the score that was just loaded, with a tempo to start at.
+    ScoreAnalysis {
+        analysis: ScoreAnalysis,
+    },
     OmrDiagnostics {
         severity: String,
         message: String,
@@ -167,24 +518,71 @@ pub enum Event {
         diagnostics_path: Option<String>,
         message: String,
     },
+    AudioSelfTestResult {
+        passed: bool,
+        stages: Vec<SelfTestStageResult>,
+    },
+    /// `path` is where the full capture was saved, if storage allowed it.
+    MidiCaptureReport {
+        report: MidiCaptureReport,
+        path: Option<String>,
+    },
+    /// Diagnostics were written and zipped; `path` is the archive.
+    DiagnosticsExported {
+        path: String,
+    },
+    ScoreFileSaved {
+        path: String,
+    },
+    /// The loaded score has edits not yet saved (`dirty`), or no longer has.
+    ScoreDirtyChanged {
+        dirty: bool,
+    },
+    /// `correction_rate` is set while follow mode is on.
+    FollowModeChanged {
+        correction_rate: Option<f32>,
+    },
     TransportUpdated {
         tick: Tick,
+        /// `tick` interpolated to the moment of emission, for drawing the playhead.
+        tick_precise: f64,
         sample_time: SampleTime,
+        /// Bar 0 is a pickup.
+        bar: u32,
+        beat: u32,
+        position_ms: u64,
+        /// Length of the loaded score at the current tempo; 0 without a score.
+        duration_ms: u64,
         playing: bool,
         tempo_multiplier: f32,
         loop_range: Option<LoopRange>,
     },
+    /// The next targets to play, from the judge's focus or else the transport position.
+    /// Sent when they change and at least once a beat while the position moves.
+    UpcomingTargets {
+        now_tick: Tick,
+        targets: Vec<PianoRollTargetDto>,
+    },
     JudgeFeedback {
         target_id: u64,
         grade: Grade,
         delta_tick: i64,
         expected_notes: Vec<u8>,
+        /// `expected_notes` spelled as the piano roll names them.
+        #[serde(default)]
+        expected_names: Vec<String>,
         played_notes: Vec<u8>,
+        /// The target's notes on a hit, the unplayed ones on a miss; these match the
+        /// piano-roll spans.
+        #[serde(default)]
+        note_ids: Vec<NoteId>,
     },
     ScoreSummaryUpdated {
         combo: u32,
         score: i64,
         accuracy: f32,
+        /// Notes played between targets; they count against nothing.
+        extra_notes: u32,
     },
     MidiInputEvent {
         event: MidiLikeEvent,
@@ -192,4 +590,106 @@ pub enum Event {
     RecentInputEvents {
         events: Vec<MidiLikeEvent>,
     },
+    /// The judge moved on to the target at `tick`; `None` when no target is left to play.
+    FocusChanged {
+        target_id: Option<u64>,
+        tick: Option<Tick>,
+    },
+    /// `LearnMidiMapping` bound a control; the full table is in the session settings.
+    MidiMappingLearned {
+        mapping: MidiMapping,
+    },
+    /// First run: the frontend should walk these steps, each ending in `CompleteSetupStep`.
+    /// The demo score and the default audio output are already set up as starting points.
+    SetupRequired {
+        missing: Vec<SetupStep>,
+    },
+    SetupStepCompleted {
+        step: SetupStep,
+        remaining: Vec<SetupStep>,
+    },
+    /// Answers `PanicAllNotes`: nothing queued before it will sound.
+    AllNotesReleased,
+    /// Every setup step is done; setup will not be offered again.
+    SetupCompleted,
+    /// Last event from [`crate::AppCore::shutdown`].
+    ShutDown,
+    /// The previous run ended mid-session, most likely in a crash; sent once at startup.
+    /// `ResumeRecoveredSession` picks it up again.
+    RecoveredSession {
+        snapshot: SessionSnapshot,
+    },
+    /// A command, or work it started, failed. `recoverable` is false when the failing
+    /// backend itself is broken rather than the input or device choice.
+    CommandFailed {
+        command_kind: String,
+        message: String,
+        recoverable: bool,
+    },
+}
+
+impl Event {
+    /// Status refreshes that a later event of the same kind supersedes.
+    pub fn is_periodic(&self) -> bool {
+        matches!(
+            self,
+            Event::TransportUpdated { .. }
+                | Event::MidiInputEvent { .. }
+                | Event::RecentInputEvents { .. }
+                | Event::AudioLevels { .. }
+                | Event::AudioStats { .. }
+                | Event::UpcomingTargets { .. }
+        )
+    }
+}
+
+/// Events waiting for the frontend. When full, the oldest periodic event makes room, or
+/// the oldest event if there is none; the drops are counted.
+#[derive(Debug)]
+pub struct EventQueue {
+    events: VecDeque<Event>,
+    capacity: usize,
+    dropped: u64,
+}
+
+impl EventQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::new(),
+            capacity: capacity.max(1),
+            dropped: 0,
+        }
+    }
+
+    pub fn push_back(&mut self, event: Event) {
+        if self.events.len() >= self.capacity {
+            match self.events.iter().position(Event::is_periodic) {
+                Some(index) => {
+                    self.events.remove(index);
+                }
+                None => {
+                    self.events.pop_front();
+                }
+            }
+            self.dropped += 1;
+        }
+        self.events.push_back(event);
+    }
+
+    pub fn drain(&mut self) -> Vec<Event> {
+        self.events.drain(..).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Events dropped since the queue was created.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
 }
@@ -1,17 +1,53 @@
 pub mod app;
 pub mod audio_graph;
+pub mod audio_meters;
 pub mod audio_params;
+pub mod audio_recorder;
+pub mod audio_self_test;
+pub mod clock;
+pub mod clock_stats;
+pub mod demos;
 pub mod diagnostics;
+pub mod ducker;
+pub mod follow;
+pub mod headless;
 pub mod ipc;
+pub mod limiter;
+pub mod midi_capture;
+pub mod null_audio;
+pub mod null_midi;
+pub mod pdf_job;
 pub mod playback_engine;
+pub mod quality_governor;
 pub mod scheduler;
+pub mod session_snapshot;
 pub mod transport;
+pub mod upcoming;
+pub mod wav_audio;
 
 pub use app::*;
 pub use audio_graph::*;
+pub use audio_meters::*;
 pub use audio_params::*;
+pub use audio_recorder::*;
+pub use audio_self_test::*;
+pub use clock::*;
+pub use clock_stats::*;
+pub use demos::*;
 pub use diagnostics::*;
+pub use ducker::*;
+pub use follow::*;
+pub use headless::*;
 pub use ipc::*;
+pub use limiter::*;
+pub use midi_capture::*;
+pub use null_audio::*;
+pub use null_midi::*;
+pub use pdf_job::*;
 pub use playback_engine::*;
+pub use quality_governor::*;
 pub use scheduler::*;
+pub use session_snapshot::*;
 pub use transport::*;
+pub use upcoming::*;
+pub use wav_audio::*;
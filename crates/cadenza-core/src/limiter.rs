@@ -0,0 +1,160 @@
+use cadenza_ports::types::LimiterParams;
+
+/// How far ahead the limiter looks; also the latency it adds to the output.
+pub const LIMITER_LOOKAHEAD_MS: f32 = 1.5;
+/// Width of the soft knee around the ceiling.
+const KNEE_DB: f32 = 2.0;
+/// ln(100): a one-pole filter with this many time constants settles to within 1%.
+pub(crate) const SETTLE_TIME_CONSTANTS: f32 = 4.605_17;
+
+/// Stereo lookahead peak limiter. The gain needed for each incoming sample is known
+/// `lookahead` samples before that sample is played, so the gain ramps down ahead of a
+/// transient instead of chopping it. All buffers are allocated up front.
+pub struct Limiter {
+    sample_rate_hz: u32,
+    params: LimiterParams,
+    attack_coeff: f32,
+    release_coeff: f32,
+    knee_start: f32,
+    lookahead: usize,
+    delay_l: Vec<f32>,
+    delay_r: Vec<f32>,
+    delay_pos: usize,
+    /// Sliding-window minimum of the required gain, as a monotonic deque over a ring.
+    window_index: Vec<u64>,
+    window_gain: Vec<f32>,
+    window_head: usize,
+    window_len: usize,
+    sample_index: u64,
+    envelope: f32,
+    min_gain: f32,
+}
+
+impl Limiter {
+    pub fn new(sample_rate_hz: u32, params: LimiterParams) -> Self {
+        let lookahead =
+            ((LIMITER_LOOKAHEAD_MS / 1000.0 * sample_rate_hz as f32).round() as usize).max(1);
+        let mut limiter = Self {
+            sample_rate_hz,
+            params,
+            attack_coeff: 0.0,
+            release_coeff: 0.0,
+            knee_start: 0.0,
+            lookahead,
+            delay_l: vec![0.0; lookahead],
+            delay_r: vec![0.0; lookahead],
+            delay_pos: 0,
+            window_index: vec![0; lookahead + 1],
+            window_gain: vec![1.0; lookahead + 1],
+            window_head: 0,
+            window_len: 0,
+            sample_index: 0,
+            envelope: 1.0,
+            min_gain: 1.0,
+        };
+        limiter.apply_params(params);
+        limiter
+    }
+
+    pub fn set_params(&mut self, params: LimiterParams) {
+        if params != self.params {
+            self.apply_params(params);
+        }
+    }
+
+    fn apply_params(&mut self, params: LimiterParams) {
+        let params = params.clamped();
+        self.params = params;
+        let coeff = |ms: f32| {
+            let samples = (ms / 1000.0 * self.sample_rate_hz as f32).max(1.0);
+            (-SETTLE_TIME_CONSTANTS / samples).exp()
+        };
+        self.attack_coeff = coeff(params.attack_ms);
+        self.release_coeff = coeff(params.release_ms);
+        self.knee_start = params.ceiling * db_to_gain(-KNEE_DB / 2.0);
+    }
+
+    /// Lowest gain applied since the previous call.
+    pub fn take_min_gain(&mut self) -> f32 {
+        std::mem::replace(&mut self.min_gain, 1.0)
+    }
+
+    pub fn process(&mut self, out_l: &mut [f32], out_r: &mut [f32]) {
+        let ceiling = self.params.ceiling;
+        for (l, r) in out_l.iter_mut().zip(out_r.iter_mut()) {
+            let required = self.required_gain(l.abs().max(r.abs()));
+            let target = self.push_window(required);
+
+            let coeff = if target < self.envelope {
+                self.attack_coeff
+            } else {
+                self.release_coeff
+            };
+            self.envelope = target + (self.envelope - target) * coeff;
+
+            let delayed_l = std::mem::replace(&mut self.delay_l[self.delay_pos], *l);
+            let delayed_r = std::mem::replace(&mut self.delay_r[self.delay_pos], *r);
+            self.delay_pos = (self.delay_pos + 1) % self.lookahead;
+
+            // The envelope may still be settling when a peak leaves the delay line; never
+            // let that overshoot the ceiling.
+            let mut gain = self.envelope;
+            let peak = delayed_l.abs().max(delayed_r.abs()) * gain;
+            if peak > ceiling {
+                gain *= ceiling / peak;
+            }
+            self.min_gain = self.min_gain.min(gain);
+            *l = delayed_l * gain;
+            *r = delayed_r * gain;
+        }
+    }
+
+    /// Soft-knee gain computer with an infinite ratio above the knee.
+    fn required_gain(&self, peak: f32) -> f32 {
+        if peak <= self.knee_start {
+            return 1.0;
+        }
+        let over_db = gain_to_db(peak) - gain_to_db(self.params.ceiling);
+        let reduction_db = if over_db <= KNEE_DB / 2.0 {
+            let into_knee = over_db + KNEE_DB / 2.0;
+            into_knee * into_knee / (2.0 * KNEE_DB)
+        } else {
+            over_db
+        };
+        db_to_gain(-reduction_db)
+    }
+
+    /// Adds the gain required by the newest sample and returns the minimum over the
+    /// lookahead window, i.e. over every sample still waiting in the delay line.
+    fn push_window(&mut self, gain: f32) -> f32 {
+        let capacity = self.window_gain.len();
+        let index = self.sample_index;
+        self.sample_index += 1;
+
+        while self.window_len > 0 {
+            let back = (self.window_head + self.window_len - 1) % capacity;
+            if self.window_gain[back] < gain {
+                break;
+            }
+            self.window_len -= 1;
+        }
+        let slot = (self.window_head + self.window_len) % capacity;
+        self.window_index[slot] = index;
+        self.window_gain[slot] = gain;
+        self.window_len += 1;
+
+        while self.window_index[self.window_head] + (self.lookahead as u64) < index {
+            self.window_head = (self.window_head + 1) % capacity;
+            self.window_len -= 1;
+        }
+        self.window_gain[self.window_head]
+    }
+}
+
+fn gain_to_db(gain: f32) -> f32 {
+    20.0 * gain.max(1e-9).log10()
+}
+
+fn db_to_gain(db: f32) -> f32 {
+    10.0_f32.powf(db / 20.0)
+}
@@ -0,0 +1,223 @@
+use cadenza_ports::midi::{MidiLikeEvent, RawMidiMessage};
+use cadenza_ports::types::{SampleTime, Tick};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Events kept per capture; later events are only counted.
+pub const MIDI_CAPTURE_CAPACITY: usize = 4096;
+/// Longest capture accepted by `StartMidiCapture`.
+pub const MAX_MIDI_CAPTURE_SECS: f32 = 120.0;
+/// Upper edges in ms of the inter-event interval buckets; a final open-ended bucket follows.
+pub const INTERVAL_BUCKET_EDGES_MS: [f64; 10] =
+    [1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0];
+/// An identical event this close to the previous one is counted as a duplicate.
+const DUPLICATE_WINDOW: Duration = Duration::from_millis(2);
+
+/// One raw input event with the times the core derived from it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CapturedEvent {
+    pub at: Instant,
+    pub sample_time: SampleTime,
+    pub tick: Tick,
+    pub event: MidiLikeEvent,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MidiEventCounts {
+    pub note_on: u64,
+    pub note_off: u64,
+    pub cc64: u64,
+    pub control_change: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IntervalBucket {
+    /// Inclusive upper edge; `None` for the last, open-ended bucket.
+    pub upper_ms: Option<f64>,
+    pub count: u64,
+}
+
+/// How far the mapped sample times wander from the driver timestamps.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ClockJitter {
+    pub std_dev_ms: f64,
+    pub max_abs_ms: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MidiCaptureReport {
+    pub duration_secs: f64,
+    pub captured: u64,
+    pub counts: MidiEventCounts,
+    pub interval_histogram: Vec<IntervalBucket>,
+    /// `None` with fewer than two captured events.
+    pub jitter: Option<ClockJitter>,
+    pub duplicates: u64,
+    /// Events past [`MIDI_CAPTURE_CAPACITY`].
+    pub overflowed: u64,
+    /// Events the MIDI callback could not queue during the capture.
+    pub queue_drops: u64,
+}
+
+/// Captured event as written to the report file, timed relative to the first event.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct CapturedEventDto {
+    pub offset_ms: f64,
+    pub sample_time: SampleTime,
+    pub tick: Tick,
+    pub event: MidiLikeEvent,
+}
+
+#[derive(Debug)]
+pub struct MidiCapture {
+    started_at: Instant,
+    duration: Duration,
+    sample_rate_hz: u32,
+    queue_drops_at_start: u64,
+    events: Vec<CapturedEvent>,
+    last_seen: Option<(Instant, MidiLikeEvent)>,
+    counts: MidiEventCounts,
+    duplicates: u64,
+    overflowed: u64,
+    /// Index of the first raw message received during the capture.
+    raw_from: u64,
+}
+
+impl MidiCapture {
+    pub fn new(
+        started_at: Instant,
+        duration: Duration,
+        sample_rate_hz: u32,
+        queue_drops_at_start: u64,
+    ) -> Self {
+        Self {
+            started_at,
+            duration,
+            sample_rate_hz: sample_rate_hz.max(1),
+            queue_drops_at_start,
+            events: Vec::new(),
+            last_seen: None,
+            counts: MidiEventCounts::default(),
+            duplicates: 0,
+            overflowed: 0,
+            raw_from: 0,
+        }
+    }
+
+    /// Starts the raw messages this capture keeps at `index`, the next one the tap will see.
+    pub fn with_raw_messages_from(mut self, index: u64) -> Self {
+        self.raw_from = index;
+        self
+    }
+
+    pub fn is_finished(&self, now: Instant) -> bool {
+        now.duration_since(self.started_at) >= self.duration
+    }
+
+    pub fn record(&mut self, captured: CapturedEvent) {
+        match captured.event {
+            MidiLikeEvent::NoteOn { .. } => self.counts.note_on += 1,
+            MidiLikeEvent::NoteOff { .. } => self.counts.note_off += 1,
+            MidiLikeEvent::Cc64 { .. } => self.counts.cc64 += 1,
+            MidiLikeEvent::ControlChange { .. } => self.counts.control_change += 1,
+        }
+        if let Some((last_at, last_event)) = self.last_seen {
+            if last_event == captured.event
+                && captured.at.saturating_duration_since(last_at) <= DUPLICATE_WINDOW
+            {
+                self.duplicates += 1;
+            }
+        }
+        self.last_seen = Some((captured.at, captured.event));
+        if self.events.len() >= MIDI_CAPTURE_CAPACITY {
+            self.overflowed += 1;
+            return;
+        }
+        self.events.push(captured);
+    }
+
+    pub fn events(&self) -> Vec<CapturedEventDto> {
+        let Some(first) = self.events.first() else {
+            return Vec::new();
+        };
+        self.events
+            .iter()
+            .map(|captured| CapturedEventDto {
+                offset_ms: millis(captured.at.saturating_duration_since(first.at)),
+                sample_time: captured.sample_time,
+                tick: captured.tick,
+                event: captured.event,
+            })
+            .collect()
+    }
+
+    /// The messages in `tapped` received since the capture started, verbatim.
+    pub fn raw_messages(&self, tapped: Vec<RawMidiMessage>) -> Vec<RawMidiMessage> {
+        tapped
+            .into_iter()
+            .filter(|message| message.index >= self.raw_from)
+            .collect()
+    }
+
+    pub fn report(&self, queue_drops_now: u64) -> MidiCaptureReport {
+        MidiCaptureReport {
+            duration_secs: self.duration.as_secs_f64(),
+            captured: self.events.len() as u64,
+            counts: self.counts,
+            interval_histogram: self.interval_histogram(),
+            jitter: self.jitter(),
+            duplicates: self.duplicates,
+            overflowed: self.overflowed,
+            queue_drops: queue_drops_now.saturating_sub(self.queue_drops_at_start),
+        }
+    }
+
+    fn interval_histogram(&self) -> Vec<IntervalBucket> {
+        let mut buckets: Vec<IntervalBucket> = INTERVAL_BUCKET_EDGES_MS
+            .iter()
+            .map(|&edge| Some(edge))
+            .chain(std::iter::once(None))
+            .map(|upper_ms| IntervalBucket { upper_ms, count: 0 })
+            .collect();
+        for pair in self.events.windows(2) {
+            let interval_ms = millis(pair[1].at.saturating_duration_since(pair[0].at));
+            let index = INTERVAL_BUCKET_EDGES_MS
+                .iter()
+                .position(|&edge| interval_ms <= edge)
+                .unwrap_or(INTERVAL_BUCKET_EDGES_MS.len());
+            buckets[index].count += 1;
+        }
+        buckets
+    }
+
+    /// Spread of (mapped audio time - driver time) around its mean. Both clocks are taken
+    /// relative to the first event, so a constant offset does not count as jitter.
+    fn jitter(&self) -> Option<ClockJitter> {
+        let first = self.events.first()?;
+        if self.events.len() < 2 {
+            return None;
+        }
+        let sample_rate_hz = self.sample_rate_hz as f64;
+        let offsets: Vec<f64> = self
+            .events
+            .iter()
+            .map(|captured| {
+                let audio_ms = (captured.sample_time as f64 - first.sample_time as f64) * 1000.0
+                    / sample_rate_hz;
+                audio_ms - millis(captured.at.saturating_duration_since(first.at))
+            })
+            .collect();
+        let mean = offsets.iter().sum::<f64>() / offsets.len() as f64;
+        let variance =
+            offsets.iter().map(|o| (o - mean).powi(2)).sum::<f64>() / offsets.len() as f64;
+        let max_abs_ms = offsets.iter().map(|o| (o - mean).abs()).fold(0.0, f64::max);
+        Some(ClockJitter {
+            std_dev_ms: variance.sqrt(),
+            max_abs_ms,
+        })
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
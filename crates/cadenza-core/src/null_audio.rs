@@ -0,0 +1,98 @@
+use cadenza_ports::audio::{AudioError, AudioOutputPort, AudioRenderCallback, AudioStreamHandle};
+use cadenza_ports::types::{AudioConfig, AudioOutputDevice, DeviceId};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Device id of the silent output. Selecting it in settings practices without sound.
+pub const NULL_AUDIO_DEVICE_ID: &str = "null";
+const NULL_AUDIO_BLOCK_FRAMES: u32 = 512;
+
+pub fn null_audio_device() -> AudioOutputDevice {
+    AudioOutputDevice {
+        id: DeviceId(NULL_AUDIO_DEVICE_ID.to_string()),
+        name: "No audio (silent)".to_string(),
+        default_config: AudioConfig {
+            sample_rate_hz: 48_000,
+            channels: 2,
+            buffer_size_frames: Some(NULL_AUDIO_BLOCK_FRAMES),
+        },
+        buffer_size_range: None,
+    }
+}
+
+/// Output that discards audio but renders it in real time from a timer thread, so the audio
+/// clock, transport and judging run as with a real device.
+#[derive(Debug, Default)]
+pub struct NullAudioOutputPort;
+
+struct NullAudioStream {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl AudioStreamHandle for NullAudioStream {
+    fn close(mut self: Box<Self>) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl AudioOutputPort for NullAudioOutputPort {
+    fn list_outputs(&self) -> Result<Vec<AudioOutputDevice>, AudioError> {
+        Ok(vec![null_audio_device()])
+    }
+
+    fn open_output(
+        &self,
+        _device_id: &DeviceId,
+        config: AudioConfig,
+        cb: Box<dyn AudioRenderCallback>,
+    ) -> Result<Box<dyn AudioStreamHandle>, AudioError> {
+        spawn_paced_output("null-audio", config, cb, |_, _| {})
+    }
+}
+
+/// Renders `cb` block by block on its own thread at the pace a device would, handing each
+/// block to `sink`. Closing the stream stops the thread and drops `sink`.
+pub(crate) fn spawn_paced_output(
+    thread_name: &str,
+    config: AudioConfig,
+    mut cb: Box<dyn AudioRenderCallback>,
+    mut sink: impl FnMut(&[f32], &[f32]) + Send + 'static,
+) -> Result<Box<dyn AudioStreamHandle>, AudioError> {
+    let sample_rate_hz = config.sample_rate_hz.max(1) as u64;
+    let frames = config
+        .buffer_size_frames
+        .unwrap_or(NULL_AUDIO_BLOCK_FRAMES)
+        .max(1) as usize;
+    let running = Arc::new(AtomicBool::new(true));
+    let thread_running = running.clone();
+    let thread = thread::Builder::new()
+        .name(thread_name.to_string())
+        .spawn(move || {
+            let mut left = vec![0.0; frames];
+            let mut right = vec![0.0; frames];
+            let started = Instant::now();
+            let mut sample_time = 0u64;
+            while thread_running.load(Ordering::Relaxed) {
+                cb.render(sample_time, &mut left, &mut right);
+                sink(&left, &right);
+                sample_time += frames as u64;
+                // Sleep to the block's deadline rather than a fixed period, so the clock
+                // keeps pace with wall time.
+                let due = started + Duration::from_micros(sample_time * 1_000_000 / sample_rate_hz);
+                if let Some(wait) = due.checked_duration_since(Instant::now()) {
+                    thread::sleep(wait);
+                }
+            }
+        })
+        .map_err(|e| AudioError::Backend(e.to_string()))?;
+    Ok(Box::new(NullAudioStream {
+        running,
+        thread: Some(thread),
+    }))
+}
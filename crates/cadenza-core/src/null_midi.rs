@@ -0,0 +1,20 @@
+use cadenza_ports::midi::{MidiError, MidiInputPort, MidiInputStream, PlayerEventCallback};
+use cadenza_ports::types::{DeviceId, MidiInputDevice};
+
+/// MIDI input with no devices, for running without a keyboard.
+#[derive(Debug, Default)]
+pub struct NullMidiInputPort;
+
+impl MidiInputPort for NullMidiInputPort {
+    fn list_inputs(&self) -> Result<Vec<MidiInputDevice>, MidiError> {
+        Ok(Vec::new())
+    }
+
+    fn open_input(
+        &self,
+        device_id: &DeviceId,
+        _cb: PlayerEventCallback,
+    ) -> Result<Box<dyn MidiInputStream>, MidiError> {
+        Err(MidiError::DeviceNotFound(device_id.to_string()))
+    }
+}
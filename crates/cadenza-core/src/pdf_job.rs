@@ -0,0 +1,312 @@
+use crate::app::expand_tilde;
+use crate::ipc::{Event, EventQueue};
+use cadenza_domain_score::{
+    export_midi_path_with_options, import_omr_musicxml_path, ExportOptions, ExportSourceInfo,
+    OmrCleanupOptions,
+};
+use cadenza_ports::omr::{OmrCancel, OmrError, OmrOptions, OmrPort};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where converted MIDI goes when the request names no directory: `Downloads/Cadenza` in the
+/// user's home, else a `Cadenza` folder in the temp directory.
+pub fn default_export_dir() -> PathBuf {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join("Downloads"))
+        .unwrap_or_else(std::env::temp_dir)
+        .join("Cadenza")
+}
+
+pub(crate) struct PdfJobRequest {
+    pub pdf_path: String,
+    pub output_path: PathBuf,
+    pub engine_path: Option<String>,
+    pub split_by_hand: bool,
+    pub omr_cleanup: bool,
+}
+
+/// A PDF → MIDI conversion running on its own thread. The core polls it each tick.
+pub(crate) struct PdfJob {
+    pdf_path: String,
+    output_path: PathBuf,
+    cancel: OmrCancel,
+    progress: Receiver<String>,
+    done: Receiver<Result<Converted, Failed>>,
+}
+
+struct Converted {
+    message: String,
+    warnings: Vec<String>,
+    musicxml_path: PathBuf,
+    diagnostics_path: Option<PathBuf>,
+}
+
+struct Failed {
+    message: String,
+    diagnostics_path: Option<PathBuf>,
+    cancelled: bool,
+}
+
+impl PdfJob {
+    pub(crate) fn start(omr: Arc<dyn OmrPort>, request: PdfJobRequest) -> Self {
+        let cancel = OmrCancel::default();
+        let (progress_tx, progress) = mpsc::channel();
+        let (done_tx, done) = mpsc::channel();
+        let options = OmrOptions {
+            enable_diagnostics: true,
+            engine_path: request.engine_path.clone(),
+            cancel: cancel.clone(),
+            progress: Some(progress_tx),
+        };
+        let pdf_path = request.pdf_path.clone();
+        let output_path = request.output_path.clone();
+        std::thread::spawn(move || {
+            let _ = done_tx.send(convert(omr.as_ref(), &request, options));
+        });
+        Self {
+            pdf_path,
+            output_path,
+            cancel,
+            progress,
+            done,
+        }
+    }
+
+    pub(crate) fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Moves the stages reached since the last poll into `events`, followed by the outcome
+    /// once there is one. Returns whether the job is over.
+    pub(crate) fn poll(&self, events: &mut EventQueue) -> bool {
+        // Checked first: every stage is sent before the outcome, so none is left behind.
+        let outcome = match self.done.try_recv() {
+            Ok(outcome) => Some(outcome),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(Failed {
+                message: "PDF conversion stopped unexpectedly".to_string(),
+                diagnostics_path: None,
+                cancelled: false,
+            })),
+        };
+        while let Ok(stage) = self.progress.try_recv() {
+            events.push_back(Event::OmrProgress {
+                page: 0,
+                total: 0,
+                stage,
+            });
+        }
+        let Some(outcome) = outcome else {
+            return false;
+        };
+
+        let display = |path: &Path| path.to_string_lossy().into_owned();
+        match outcome {
+            Ok(done) => {
+                if !done.warnings.is_empty() {
+                    events.push_back(Event::ImportWarnings {
+                        messages: done.warnings,
+                    });
+                }
+                events.push_back(Event::OmrDiagnostics {
+                    severity: "info".to_string(),
+                    message: done.message.clone(),
+                    page: None,
+                });
+                events.push_back(Event::PdfToMidiFinished {
+                    ok: true,
+                    pdf_path: self.pdf_path.clone(),
+                    output_path: display(&self.output_path),
+                    musicxml_path: Some(display(&done.musicxml_path)),
+                    diagnostics_path: done.diagnostics_path.as_deref().map(display),
+                    message: done.message,
+                });
+            }
+            Err(err) => {
+                if !err.cancelled {
+                    events.push_back(Event::CommandFailed {
+                        command_kind: "ConvertPdfToMidi".to_string(),
+                        message: err.message.clone(),
+                        recoverable: true,
+                    });
+                }
+                events.push_back(Event::OmrDiagnostics {
+                    severity: "error".to_string(),
+                    message: err.message.clone(),
+                    page: None,
+                });
+                events.push_back(Event::PdfToMidiFinished {
+                    ok: false,
+                    pdf_path: self.pdf_path.clone(),
+                    output_path: display(&self.output_path),
+                    musicxml_path: None,
+                    diagnostics_path: err.diagnostics_path.as_deref().map(display),
+                    message: err.message,
+                });
+            }
+        }
+        true
+    }
+}
+
+fn convert(
+    omr: &dyn OmrPort,
+    request: &PdfJobRequest,
+    options: OmrOptions,
+) -> Result<Converted, Failed> {
+    let fail = |message: String| Failed {
+        message,
+        diagnostics_path: omr.diagnostics().ok().flatten(),
+        cancelled: false,
+    };
+    let stages = options.clone();
+
+    let recognized = omr
+        .recognize_pdf(&request.pdf_path, options)
+        .map_err(|err| match err {
+            OmrError::Cancelled => Failed {
+                cancelled: true,
+                ..fail("Conversion cancelled".to_string())
+            },
+            OmrError::EngineNotFound(message)
+            | OmrError::RecognitionFailed(message)
+            | OmrError::Backend(message) => fail(message),
+            other => fail(other.to_string()),
+        })?;
+    let musicxml_path = recognized
+        .musicxml_path
+        .ok_or_else(|| fail("OMR did not produce MusicXML".to_string()))?;
+
+    stages.report_progress("Import MusicXML");
+    let pdf_path = Path::new(&request.pdf_path);
+    let cleanup = request.omr_cleanup.then(OmrCleanupOptions::default);
+    let import = import_omr_musicxml_path(&musicxml_path, pdf_path, cleanup.as_ref())
+        .map_err(|e| fail(format!("MusicXML import failed: {e}")))?;
+    let warning_count = import.warnings.len();
+
+    stages.report_progress("Export MIDI");
+    let output_path = &request.output_path;
+    if let Some(parent) = output_path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let export_options = ExportOptions {
+        split_by_hand: request.split_by_hand,
+        source_info: Some(ExportSourceInfo {
+            source_file: pdf_path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned()),
+            import_warnings: warning_count,
+        }),
+        ..ExportOptions::default()
+    };
+    export_midi_path_with_options(&import.score, output_path, export_options).map_err(|e| {
+        fail(format!(
+            "MIDI export failed writing to {}: {e}",
+            output_path.display()
+        ))
+    })?;
+
+    stages.report_progress("Done");
+    Ok(Converted {
+        message: format!(
+            "Wrote MIDI to {} (MusicXML: {}, {warning_count} import warning{})",
+            output_path.display(),
+            musicxml_path.display(),
+            if warning_count == 1 { "" } else { "s" }
+        ),
+        warnings: import.warnings.iter().map(ToString::to_string).collect(),
+        musicxml_path,
+        diagnostics_path: recognized.diagnostics_path,
+    })
+}
+
+/// Where a conversion writes. An empty `output_path` means `<pdf name>.mid` in `export_dir`;
+/// relative paths are taken from `export_dir`, a directory gets the default name and the
+/// extension is forced to `.mid`. Parent directories are created, and an existing file is
+/// never overwritten: the name gets a `-1`, `-2`, … suffix instead.
+pub(crate) fn resolve_output_path(
+    pdf_path: &str,
+    output_path: &str,
+    export_dir: &Path,
+) -> std::io::Result<PathBuf> {
+    let default_name = Path::new(pdf_path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .map(sanitize_file_stem)
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "score".to_string());
+
+    let output_path = output_path.trim();
+    let mut candidate = if output_path.is_empty() {
+        export_dir.join(format!("{default_name}.mid"))
+    } else {
+        expand_tilde(output_path)
+    };
+    if candidate.is_relative() {
+        candidate = export_dir.join(candidate);
+    }
+
+    let ends_with_sep = output_path.ends_with('/') || output_path.ends_with('\\');
+    if ends_with_sep || candidate.is_dir() {
+        candidate = candidate.join(format!("{default_name}.mid"));
+    }
+
+    let ext = candidate.extension().and_then(|e| e.to_str()).unwrap_or("");
+    if !(ext.eq_ignore_ascii_case("mid") || ext.eq_ignore_ascii_case("midi")) {
+        candidate.set_extension("mid");
+    }
+
+    if let Some(parent) = candidate.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    Ok(make_unique_path(candidate))
+}
+
+fn sanitize_file_stem(stem: &str) -> String {
+    let mut out = String::new();
+    for ch in stem.chars() {
+        if ch.is_control()
+            || matches!(
+                ch,
+                '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '\u{0}'
+            )
+        {
+            out.push('_');
+            continue;
+        }
+        out.push(ch);
+    }
+    out.trim().trim_matches('.').to_string()
+}
+
+fn make_unique_path(path: PathBuf) -> PathBuf {
+    if !path.exists() {
+        return path;
+    }
+
+    let parent = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("export");
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("mid");
+
+    for idx in 1..=999 {
+        let candidate = parent.join(format!("{stem}-{idx}.{ext}"));
+        if !candidate.exists() {
+            return candidate;
+        }
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    parent.join(format!("{stem}-{now}.{ext}"))
+}
@@ -1,17 +1,21 @@
 use crate::scheduler::{Scheduler, SchedulerConfig};
-use crate::transport::Transport;
+use crate::transport::{Transport, TransportState};
 use cadenza_domain_score::{Hand, PlaybackMidiEvent, TempoPoint};
 use cadenza_ports::playback::{
-    LoopRange, PlaybackError, PlaybackMode, PlaybackPort, PlaybackRouteHint, PlaybackScore,
-    ScheduledEvent,
+    LoopRange, PlaybackError, PlaybackMode, PlaybackNotification, PlaybackPort, PlaybackRouteHint,
+    PlaybackScore, PlaybackTransportSnapshot, ScheduledEvent,
 };
-use cadenza_ports::types::Tick;
+use cadenza_ports::types::{SampleTime, Tick};
 use parking_lot::Mutex;
 
 struct PlaybackState {
     transport: Transport,
     scheduler: Scheduler,
     loop_range: Option<LoopRange>,
+    /// Set by play and seek: the next `advance_to_sample` pins the current tick to the
+    /// embedder's clock instead of following it.
+    align_pending: bool,
+    notifications: Vec<PlaybackNotification>,
 }
 
 pub struct PlaybackEngine {
@@ -23,8 +27,16 @@ impl PlaybackEngine {
         Self {
             state: Mutex::new(PlaybackState {
                 transport: Transport::new(480, sample_rate_hz, Vec::new()),
-                scheduler: Scheduler::new(sample_rate_hz, SchedulerConfig { lookahead_ms: 30 }),
+                scheduler: Scheduler::new(
+                    sample_rate_hz,
+                    SchedulerConfig {
+                        lookahead_ms: 30,
+                        buffer_frames: None,
+                    },
+                ),
                 loop_range: None,
+                align_pending: true,
+                notifications: Vec::new(),
             }),
         }
     }
@@ -53,6 +65,12 @@ impl PlaybackPort for PlaybackEngine {
                     PlaybackRouteHint::Right => Some(Hand::Right),
                     PlaybackRouteHint::None => None,
                 },
+                ornament_of: None,
+                bus: None,
+                note_id: None,
+                spelling: None,
+                transpose: 0,
+                articulations: Vec::new(),
             })
             .collect::<Vec<_>>();
 
@@ -61,12 +79,14 @@ impl PlaybackPort for PlaybackEngine {
         state.scheduler.set_score(events);
         let loop_range = state.loop_range;
         state.scheduler.set_loop(loop_range);
+        state.align_pending = true;
         Ok(())
     }
 
     fn play(&self) -> Result<(), PlaybackError> {
         let mut state = self.state.lock();
         state.transport.play();
+        state.align_pending = true;
         Ok(())
     }
 
@@ -79,6 +99,9 @@ impl PlaybackPort for PlaybackEngine {
     fn stop(&self) -> Result<(), PlaybackError> {
         let mut state = self.state.lock();
         state.transport.stop();
+        let tick = state.transport.now_tick();
+        state.scheduler.seek(tick);
+        state.align_pending = true;
         Ok(())
     }
 
@@ -86,6 +109,10 @@ impl PlaybackPort for PlaybackEngine {
         let mut state = self.state.lock();
         state.transport.seek(tick);
         state.scheduler.seek(tick);
+        state.align_pending = true;
+        state
+            .notifications
+            .push(PlaybackNotification::Seeked { tick });
         Ok(())
     }
 
@@ -109,16 +136,51 @@ impl PlaybackPort for PlaybackEngine {
         Ok(())
     }
 
+    fn advance_to_sample(&self, sample_time: SampleTime) -> Result<(), PlaybackError> {
+        let mut state = self.state.lock();
+        if state.transport.state() != TransportState::Playing {
+            return Ok(());
+        }
+        if state.align_pending {
+            state.align_pending = false;
+            state.transport.align_to_sample_time(sample_time);
+            let tick = state.transport.now_tick();
+            state.scheduler.seek(tick);
+        } else {
+            state.transport.sync_to_sample_time(sample_time);
+        }
+        Ok(())
+    }
+
+    fn transport_snapshot(&self) -> PlaybackTransportSnapshot {
+        let state = self.state.lock();
+        PlaybackTransportSnapshot {
+            tick: state.transport.now_tick(),
+            sample_time: state.transport.now_sample(),
+            playing: state.transport.state() == TransportState::Playing,
+        }
+    }
+
     fn poll_scheduled_events(
         &self,
         _window_samples: u64,
     ) -> Result<Vec<ScheduledEvent>, PlaybackError> {
         let mut state = self.state.lock();
+        if state.transport.state() != TransportState::Playing || state.align_pending {
+            return Ok(Vec::new());
+        }
         let PlaybackState {
             transport,
             scheduler,
+            notifications,
             ..
         } = &mut *state;
-        Ok(scheduler.schedule(transport))
+        let events = scheduler.schedule(transport);
+        notifications.extend(scheduler.take_notifications());
+        Ok(events)
+    }
+
+    fn poll_notifications(&self) -> Vec<PlaybackNotification> {
+        std::mem::take(&mut self.state.lock().notifications)
     }
 }
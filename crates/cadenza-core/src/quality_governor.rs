@@ -0,0 +1,75 @@
+use crate::audio_graph::AudioCallbackCounts;
+use cadenza_ports::synth::QualityHint;
+
+/// Checks in a row, one a second, with callback overruns before quality is reduced.
+pub const QUALITY_REDUCE_AFTER_CHECKS: u32 = 3;
+/// Checks in a row without overruns before full quality comes back. Much longer than the
+/// reduce side so a machine on the edge does not flip back and forth.
+pub const QUALITY_RESTORE_AFTER_CHECKS: u32 = 30;
+
+/// Picks the synth quality from the audio callback overruns, unless a quality is forced.
+#[derive(Clone, Debug, Default)]
+pub struct QualityGovernor {
+    forced: Option<QualityHint>,
+    automatic: QualityHint,
+    last_overruns: u64,
+    /// Checks in a row with new overruns, and in a row without.
+    overrun_checks: u32,
+    clean_checks: u32,
+}
+
+impl QualityGovernor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The quality the synth should run at.
+    pub fn quality(&self) -> QualityHint {
+        self.forced.unwrap_or(self.automatic)
+    }
+
+    pub fn forced(&self) -> Option<QualityHint> {
+        self.forced
+    }
+
+    /// Forces a quality, or hands it back to the governor with `None`, which starts from
+    /// full quality again. Returns the new quality when it changed.
+    pub fn set_forced(&mut self, forced: Option<QualityHint>) -> Option<QualityHint> {
+        let before = self.quality();
+        self.forced = forced;
+        self.automatic = QualityHint::Full;
+        self.overrun_checks = 0;
+        self.clean_checks = 0;
+        (self.quality() != before).then(|| self.quality())
+    }
+
+    /// Takes the callback counts once a second. Returns the new quality when it changed.
+    pub fn check(&mut self, counts: &AudioCallbackCounts) -> Option<QualityHint> {
+        // The counts only grow; a smaller value is a fresh set of stats.
+        let overran = counts.overruns > self.last_overruns;
+        self.last_overruns = counts.overruns;
+        if overran {
+            self.overrun_checks += 1;
+            self.clean_checks = 0;
+        } else {
+            self.clean_checks += 1;
+            self.overrun_checks = 0;
+        }
+        if self.forced.is_some() {
+            return None;
+        }
+        let next = match self.automatic {
+            QualityHint::Full if self.overrun_checks >= QUALITY_REDUCE_AFTER_CHECKS => {
+                QualityHint::Reduced
+            }
+            QualityHint::Reduced if self.clean_checks >= QUALITY_RESTORE_AFTER_CHECKS => {
+                QualityHint::Full
+            }
+            _ => return None,
+        };
+        self.automatic = next;
+        self.overrun_checks = 0;
+        self.clean_checks = 0;
+        Some(next)
+    }
+}
@@ -1,34 +1,125 @@
-use crate::transport::Transport;
+use crate::transport::{TempoCursor, Transport};
 use cadenza_domain_score::{Hand, PlaybackMidiEvent};
-use cadenza_ports::playback::{LoopRange, PlaybackMode, ScheduledEvent};
-use cadenza_ports::types::Bus;
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::playback::{LoopRange, PlaybackMode, PlaybackNotification, ScheduledEvent};
+use cadenza_ports::types::{Bus, SampleTime, Tick};
 use std::collections::VecDeque;
 
+const MAX_NOTIFICATIONS: usize = 64;
+/// Default [`AutopilotFeel::resound_velocity_scale`].
+pub const DEFAULT_RESOUND_VELOCITY_SCALE: f32 = 0.6;
+
 #[derive(Clone, Copy, Debug)]
 pub struct SchedulerConfig {
+    /// Shortest lookahead window; large audio buffers widen it.
     pub lookahead_ms: u64,
+    /// Frames per audio callback, when the stream reports it.
+    pub buffer_frames: Option<u32>,
 }
 
 #[derive(Clone, Copy, Debug)]
 pub struct AccompanimentRoute {
     pub play_left: bool,
     pub play_right: bool,
+    /// Leaves pedaling to the player. Score pedal is also dropped when neither hand plays.
+    pub suppress_pedal: bool,
+}
+
+/// How the autopilot bus plays the score's notes. Jitter is derived from each note's tick
+/// and pitch, so every loop pass sounds the same.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AutopilotFeel {
+    pub velocity_scale: f32,
+    /// Largest timing offset either way; a NoteOff moves with its NoteOn.
+    pub humanize_timing_ms: f32,
+    /// Largest velocity offset either way.
+    pub humanize_velocity: u8,
+    /// Velocity of notes struck before a seek or loop start and still held there, which are
+    /// sounded again, relative to how they were first struck. Zero leaves them out.
+    pub resound_velocity_scale: f32,
+}
+
+impl Default for AutopilotFeel {
+    fn default() -> Self {
+        Self {
+            velocity_scale: 1.0,
+            humanize_timing_ms: 0.0,
+            humanize_velocity: 0,
+            resound_velocity_scale: DEFAULT_RESOUND_VELOCITY_SCALE,
+        }
+    }
+}
+
+/// What a straight play-through of the score has sounding at a tick.
+#[derive(Clone, Debug, Default)]
+pub struct ScoreStateAtTick {
+    /// NoteOns from before the tick whose notes are still held at it, in score order.
+    pub notes: Vec<PlaybackMidiEvent>,
+    /// The last pedal event before the tick for each hand and explicit bus, in score order.
+    pub pedal: Vec<PlaybackMidiEvent>,
 }
 
 #[derive(Clone, Copy, Debug)]
 pub struct PlaybackSettings {
     pub mode: PlaybackMode,
     pub accompaniment: AccompanimentRoute,
+    pub feel: AutopilotFeel,
+}
+
+/// A note the scheduler has sent on and not yet off.
+#[derive(Clone, Copy, Debug)]
+struct SoundingNote {
+    bus: Bus,
+    note: u8,
+    hand: Option<Hand>,
+    /// Sent to an explicit bus, so hand routing changes leave it alone.
+    tagged: bool,
+    on_sample: SampleTime,
+    /// Humanization offset applied to the NoteOn, reused for its NoteOff.
+    offset: i64,
+}
+
+/// A note sent to audio, kept until it has certainly ended so a flush can release it.
+#[derive(Clone, Copy, Debug)]
+struct SentNote {
+    bus: Bus,
+    note: u8,
+    on_sample: SampleTime,
+    off_sample: Option<SampleTime>,
 }
 
 pub struct Scheduler {
     config: SchedulerConfig,
     events: Vec<PlaybackMidiEvent>,
     cursor: usize,
+    /// Tempo segment of the last event placed; events are placed in tick order.
+    tempo_cursor: TempoCursor,
     queue: VecDeque<ScheduledEvent>,
     loop_range: Option<LoopRange>,
+    /// Sample at which the transport will wrap to the loop start, once the lookahead has
+    /// already moved the cursor into the next pass.
+    pending_wrap: Option<SampleTime>,
+    last_transport_tick: Tick,
+    /// Notes scheduled on but not yet off in the current pass. Released at a loop wrap.
+    sounding: Vec<SoundingNote>,
+    /// Notes of a hand that was just routed off, released on the next schedule.
+    muted: Vec<SoundingNote>,
+    /// Notes sent to audio that may still sound. Unlike `sounding` this follows the audio
+    /// rather than the score, so seeks and score changes keep it.
+    sent: Vec<SentNote>,
+    /// Buses sent a pedal down since the last [`Scheduler::release_sent`].
+    pedal_sent: Vec<Bus>,
+    /// Latest NoteOff sample per autopilot note, so a jittered re-strike never lands first.
+    released: [SampleTime; 128],
+    reached_end: bool,
+    /// Set by a seek: the next [`Scheduler::schedule`] first sounds again the notes and pedal
+    /// the score holds across this tick, since seeking flushes them.
+    restore_state: Option<Tick>,
+    notifications: VecDeque<PlaybackNotification>,
     settings: PlaybackSettings,
     sample_rate_hz: u32,
+    /// Stamped on every event sent, see [`ScheduledEvent::generation`].
+    generation: u32,
 }
 
 impl Scheduler {
@@ -37,16 +128,30 @@ impl Scheduler {
             config,
             events: Vec::new(),
             cursor: 0,
+            tempo_cursor: TempoCursor::default(),
             queue: VecDeque::new(),
             loop_range: None,
+            pending_wrap: None,
+            last_transport_tick: 0,
+            sounding: Vec::new(),
+            muted: Vec::new(),
+            sent: Vec::new(),
+            pedal_sent: Vec::new(),
+            released: [0; 128],
+            reached_end: false,
+            restore_state: None,
+            notifications: VecDeque::new(),
             settings: PlaybackSettings {
                 mode: PlaybackMode::Demo,
                 accompaniment: AccompanimentRoute {
                     play_left: true,
                     play_right: true,
+                    suppress_pedal: false,
                 },
+                feel: AutopilotFeel::default(),
             },
             sample_rate_hz,
+            generation: 0,
         }
     }
 
@@ -60,10 +165,17 @@ impl Scheduler {
         self.events = events;
         self.cursor = 0;
         self.queue.clear();
+        self.pending_wrap = None;
+        self.last_transport_tick = 0;
+        self.sounding.clear();
+        self.muted.clear();
+        self.released = [0; 128];
+        self.reached_end = false;
     }
 
     pub fn set_loop(&mut self, range: Option<LoopRange>) {
         self.loop_range = range;
+        self.pending_wrap = None;
     }
 
     pub fn loop_range(&self) -> Option<LoopRange> {
@@ -72,13 +184,27 @@ impl Scheduler {
 
     pub fn set_mode(&mut self, mode: PlaybackMode) {
         self.settings.mode = mode;
+        self.mute_unrouted();
     }
 
+    /// Notes already sent for a hand that gets routed off are released on the next
+    /// [`Scheduler::schedule`], since the hand filter also drops their score NoteOffs.
     pub fn set_accompaniment_route(&mut self, play_left: bool, play_right: bool) {
-        self.settings.accompaniment = AccompanimentRoute {
-            play_left,
-            play_right,
-        };
+        self.settings.accompaniment.play_left = play_left;
+        self.settings.accompaniment.play_right = play_right;
+        self.mute_unrouted();
+    }
+
+    pub fn set_accompaniment_pedal_suppressed(&mut self, suppressed: bool) {
+        self.settings.accompaniment.suppress_pedal = suppressed;
+    }
+
+    pub fn set_autopilot_feel(&mut self, feel: AutopilotFeel) {
+        self.settings.feel = feel;
+    }
+
+    pub fn autopilot_feel(&self) -> AutopilotFeel {
+        self.settings.feel
     }
 
     pub fn seek(&mut self, tick: i64) {
@@ -88,62 +214,499 @@ impl Scheduler {
             .position(|event| event.tick >= tick)
             .unwrap_or(self.events.len());
         self.queue.clear();
+        self.pending_wrap = None;
+        self.last_transport_tick = tick;
+        self.sounding.clear();
+        self.muted.clear();
+        self.released = [0; 128];
+        self.reached_end = false;
+        self.restore_state = Some(tick);
     }
 
+    /// Generation for the events sent from now on.
+    pub fn set_generation(&mut self, generation: u32) {
+        self.generation = generation;
+    }
+
+    /// Whether the lookahead has already sent events for the loop's next pass, which a
+    /// change of loop range would leave playing at the wrong time.
+    pub fn wrap_queued(&self) -> bool {
+        self.pending_wrap.is_some()
+    }
+
+    /// Forgets events queued but not yet returned by [`Scheduler::schedule`], and the notes
+    /// it holds, after a panic has released them. The cursor stays put, so playback carries on
+    /// from the next score event; the pedal stays up until the score next presses it.
+    pub fn clear_pending(&mut self) {
+        self.queue.clear();
+        self.sounding.clear();
+        self.muted.clear();
+        self.sent.clear();
+        self.pedal_sent.clear();
+        self.restore_state = None;
+    }
+
+    /// NoteOffs at `now` for the notes sent that are still sounding, and a pedal up for the
+    /// buses sent a pedal down; to be queued once the events not yet played are dropped. A
+    /// note due within the audio buffer after `now` counts as struck, since the callback in
+    /// progress may already have played it.
+    pub fn release_sent(&mut self, now: SampleTime) -> Vec<ScheduledEvent> {
+        let struck_by = now.saturating_add(self.config.buffer_frames.unwrap_or(0) as SampleTime);
+        let mut released: Vec<ScheduledEvent> = Vec::new();
+        for sent in self.sent.drain(..) {
+            let sounding =
+                sent.on_sample < struck_by && sent.off_sample.is_none_or(|off| off >= now);
+            let event = MidiLikeEvent::NoteOff { note: sent.note };
+            if sounding
+                && !released
+                    .iter()
+                    .any(|held| (held.bus, held.event) == (sent.bus, event))
+            {
+                released.push(ScheduledEvent {
+                    sample_time: now,
+                    bus: sent.bus,
+                    event,
+                    generation: self.generation,
+                });
+            }
+        }
+        for bus in self.pedal_sent.drain(..) {
+            released.push(ScheduledEvent {
+                sample_time: now,
+                bus,
+                event: MidiLikeEvent::Cc64 { value: 0 },
+                generation: self.generation,
+            });
+        }
+        released
+    }
+
+    /// Follows a transport moved by [`Transport::shift_ticks`] without resetting. Events
+    /// already scheduled keep their time; the rest are timed from the new position.
+    pub fn retime(&mut self, transport: &Transport) {
+        // Moving back is not a wrap.
+        self.last_transport_tick = transport.now_tick();
+        if let (Some(_), Some(range)) = (self.pending_wrap, self.loop_range) {
+            self.pending_wrap = Some(transport.tick_to_sample(range.end_tick));
+        }
+    }
+
+    /// Lookahead window actually used: at least one and a half audio buffers, so events are
+    /// queued before the callback that has to play them.
+    pub fn effective_lookahead_ms(&self) -> f64 {
+        let buffer_ms = self.config.buffer_frames.unwrap_or(0) as f64 * 1000.0
+            / self.sample_rate_hz.max(1) as f64;
+        (self.config.lookahead_ms as f64).max(buffer_ms * 1.5)
+    }
+
+    /// Loop wraps and the end of the score seen by [`Scheduler::schedule`] since the last call.
+    pub fn take_notifications(&mut self) -> Vec<PlaybackNotification> {
+        self.notifications.drain(..).collect()
+    }
+
+    /// Emits the events due within the lookahead window. Near the loop end the cursor moves on
+    /// to the next pass early, timed from the sample where the transport will wrap, so the
+    /// loop start is never late; notes still held at the loop end are released at the wrap.
+    /// Expects to be polled at least once per lookahead window.
     pub fn schedule(&mut self, transport: &mut Transport) -> Vec<ScheduledEvent> {
         let lookahead_samples =
-            (self.config.lookahead_ms as f64 * self.sample_rate_hz as f64 / 1000.0).round() as u64;
+            (self.effective_lookahead_ms() * self.sample_rate_hz as f64 / 1000.0).round() as u64;
         let window_end_sample = transport.now_sample().saturating_add(lookahead_samples);
-        let window_end_tick = transport.sample_to_tick(window_end_sample);
 
-        let mut emitted = Vec::new();
-        while let Some(event) = self.events.get(self.cursor) {
-            if event.tick > window_end_tick {
-                break;
+        let loop_range = self
+            .loop_range
+            .filter(|range| range.end_tick > range.start_tick);
+
+        // The transport only moves backwards by wrapping (seeks reset the scheduler).
+        if transport.now_tick() < self.last_transport_tick {
+            if let Some(range) = loop_range {
+                let at_sample = self
+                    .pending_wrap
+                    .take()
+                    .unwrap_or_else(|| transport.tick_to_sample(range.start_tick));
+                self.notify(PlaybackNotification::LoopWrapped { at_sample });
+            }
+        }
+        self.last_transport_tick = transport.now_tick();
+        let last_tick = self.events.last().map(|event| event.tick);
+        if loop_range.is_none()
+            && !self.reached_end
+            && self.cursor >= self.events.len()
+            && last_tick.is_some_and(|tick| transport.now_tick() >= tick)
+        {
+            self.reached_end = true;
+            self.notify(PlaybackNotification::ReachedEnd);
+        }
+        for held in self.muted.drain(..) {
+            self.queue.push_back(ScheduledEvent {
+                sample_time: held.on_sample.max(transport.now_sample()),
+                bus: held.bus,
+                event: MidiLikeEvent::NoteOff { note: held.note },
+                generation: self.generation,
+            });
+        }
+        if let Some(tick) = self.restore_state.take() {
+            // A sample after the flush, whose pedal up would sort after a down on the same
+            // sample.
+            self.restore_state_at(tick, transport.now_sample() + 1);
+        }
+        let mut next_pass = match (self.pending_wrap, loop_range) {
+            (Some(wrap_sample), Some(range)) => {
+                Some(next_pass_timeline(transport, range.start_tick, wrap_sample))
             }
+            _ => None,
+        };
+
+        loop {
+            let timeline = next_pass.as_ref().unwrap_or(transport);
+            let window_end_tick = timeline.sample_to_tick(window_end_sample);
 
-            if let Some(loop_range) = self.loop_range {
-                if event.tick >= loop_range.end_tick {
-                    transport.seek(loop_range.start_tick);
-                    self.seek(loop_range.start_tick);
-                    break;
+            if let Some(event) = self.events.get(self.cursor).cloned() {
+                let before_loop_end = loop_range.is_none_or(|range| event.tick < range.end_tick);
+                if event.tick <= window_end_tick && before_loop_end {
+                    let bus = event
+                        .bus
+                        .or_else(|| self.route_bus(event.hand, event.event));
+                    if let Some(bus) = bus {
+                        let sample_time =
+                            timeline.tick_to_sample_from(event.tick, &mut self.tempo_cursor);
+                        let scheduled =
+                            self.place(bus, &event, sample_time, transport.now_sample());
+                        self.queue.push_back(scheduled);
+                    }
+                    self.cursor += 1;
+                    continue;
                 }
             }
 
-            if let Some(bus) = self.route_bus(event.hand) {
-                let sample_time = transport.tick_to_sample(event.tick);
-                let scheduled = ScheduledEvent {
-                    sample_time,
+            let Some(range) = loop_range else {
+                break;
+            };
+            if self.pending_wrap.is_some() || window_end_tick < range.end_tick {
+                break;
+            }
+            let wrap_sample = timeline.tick_to_sample(range.end_tick);
+            for held in self.sounding.drain(..) {
+                self.queue.push_back(ScheduledEvent {
+                    sample_time: wrap_sample,
+                    bus: held.bus,
+                    event: MidiLikeEvent::NoteOff { note: held.note },
+                    generation: self.generation,
+                });
+            }
+            let end_state = Self::state_at_tick(&self.events, range.end_tick);
+            for bus in self.pedal_down_buses(&end_state) {
+                self.queue.push_back(ScheduledEvent {
+                    sample_time: wrap_sample,
                     bus,
-                    event: event.event,
-                };
-                self.queue.push_back(scheduled);
+                    event: MidiLikeEvent::Cc64 { value: 0 },
+                    generation: self.generation,
+                });
+            }
+            self.cursor = self
+                .events
+                .iter()
+                .position(|event| event.tick >= range.start_tick)
+                .unwrap_or(self.events.len());
+            // Notes and pedal held into the loop start, as a play-through would have them.
+            self.restore_state_at(range.start_tick, wrap_sample + 1);
+            self.pending_wrap = Some(wrap_sample);
+            next_pass = Some(next_pass_timeline(transport, range.start_tick, wrap_sample));
+        }
+
+        let scheduled: Vec<ScheduledEvent> = self.queue.drain(..).collect();
+        self.record_sent(&scheduled, transport.now_sample());
+        scheduled
+    }
+
+    /// Notes and pedal sounding at `tick` in a straight play-through of `events`, which are
+    /// sorted by tick as [`Scheduler::set_score`] leaves them. A NoteOff ends the note with its
+    /// id, or else the earliest held note of its pitch; notes ending at `tick` are not held.
+    pub fn state_at_tick(events: &[PlaybackMidiEvent], tick: Tick) -> ScoreStateAtTick {
+        let mut state = ScoreStateAtTick::default();
+        for event in events.iter().take_while(|event| event.tick <= tick) {
+            let at_tick = event.tick == tick;
+            match event.event {
+                MidiLikeEvent::NoteOn { velocity, .. } if velocity > 0 => {
+                    if !at_tick {
+                        state.notes.push(event.clone());
+                    }
+                }
+                MidiLikeEvent::NoteOn { note, .. } | MidiLikeEvent::NoteOff { note } => {
+                    let pitch_of = |held: &PlaybackMidiEvent| matches!(held.event, MidiLikeEvent::NoteOn { note: held_note, .. } if held_note == note);
+                    let index = event
+                        .note_id
+                        .and_then(|id| {
+                            state
+                                .notes
+                                .iter()
+                                .position(|held| held.note_id == Some(id) && pitch_of(held))
+                        })
+                        .or_else(|| state.notes.iter().position(pitch_of));
+                    if let Some(index) = index {
+                        state.notes.remove(index);
+                    }
+                }
+                MidiLikeEvent::Cc64 { .. } if !at_tick => {
+                    state
+                        .pedal
+                        .retain(|pedal| (pedal.hand, pedal.bus) != (event.hand, event.bus));
+                    state.pedal.push(event.clone());
+                }
+                MidiLikeEvent::Cc64 { .. } | MidiLikeEvent::ControlChange { .. } => {}
+            }
+        }
+        state
+    }
+
+    /// Sounds again the notes held across `tick` and puts the pedal back down where the score
+    /// holds it, from `sample_time`.
+    fn restore_state_at(&mut self, tick: Tick, sample_time: SampleTime) {
+        let state = Self::state_at_tick(&self.events, tick);
+        for bus in self.pedal_down_buses(&state) {
+            self.queue.push_back(ScheduledEvent {
+                sample_time,
+                bus,
+                event: MidiLikeEvent::Cc64 { value: 127 },
+                generation: self.generation,
+            });
+        }
+        let scale = self.settings.feel.velocity_scale * self.settings.feel.resound_velocity_scale;
+        if scale <= 0.0 {
+            return;
+        }
+        for held in state.notes {
+            let MidiLikeEvent::NoteOn { note, velocity } = held.event else {
+                continue;
+            };
+            // Ornaments are too short to be worth striking again.
+            if held.ornament_of.is_some() {
+                continue;
+            }
+            let Some(bus) = held.bus.or_else(|| self.route_bus(held.hand, held.event)) else {
+                continue;
+            };
+            if self
+                .sounding
+                .iter()
+                .any(|sounding| (sounding.bus, sounding.note) == (bus, note))
+            {
+                continue;
+            }
+            self.sounding.push(SoundingNote {
+                bus,
+                note,
+                hand: held.hand,
+                tagged: held.bus.is_some(),
+                on_sample: sample_time,
+                offset: 0,
+            });
+            let velocity = (velocity as f32 * scale).round().clamp(1.0, 127.0) as u8;
+            self.queue.push_back(ScheduledEvent {
+                sample_time,
+                bus,
+                event: MidiLikeEvent::NoteOn { note, velocity },
+                generation: self.generation,
+            });
+        }
+    }
+
+    /// Keeps `sent` in step with `scheduled`, forgetting the notes ended before `now`.
+    fn record_sent(&mut self, scheduled: &[ScheduledEvent], now: SampleTime) {
+        self.sent
+            .retain(|sent| sent.off_sample.is_none_or(|off| off >= now));
+        for event in scheduled {
+            match event.event {
+                MidiLikeEvent::NoteOn { note, .. } => self.sent.push(SentNote {
+                    bus: event.bus,
+                    note,
+                    on_sample: event.sample_time,
+                    off_sample: None,
+                }),
+                MidiLikeEvent::NoteOff { note } => {
+                    if let Some(sent) = self.sent.iter_mut().find(|sent| {
+                        (sent.bus, sent.note) == (event.bus, note) && sent.off_sample.is_none()
+                    }) {
+                        sent.off_sample = Some(event.sample_time);
+                    }
+                }
+                MidiLikeEvent::Cc64 { value } => {
+                    if value >= 64 && !self.pedal_sent.contains(&event.bus) {
+                        self.pedal_sent.push(event.bus);
+                    }
+                }
+                MidiLikeEvent::ControlChange { .. } => {}
             }
+        }
+    }
 
-            self.cursor += 1;
+    /// Buses whose last routed score pedal in `state` is down.
+    fn pedal_down_buses(&self, state: &ScoreStateAtTick) -> Vec<Bus> {
+        let mut last: Vec<(Bus, u8)> = Vec::new();
+        for event in &state.pedal {
+            let MidiLikeEvent::Cc64 { value } = event.event else {
+                continue;
+            };
+            let Some(bus) = event
+                .bus
+                .or_else(|| self.route_bus(event.hand, event.event))
+            else {
+                continue;
+            };
+            match last.iter_mut().find(|(held, _)| *held == bus) {
+                Some((_, held_value)) => *held_value = value,
+                None => last.push((bus, value)),
+            }
         }
+        last.into_iter()
+            .filter(|&(_, value)| value >= 64)
+            .map(|(bus, _)| bus)
+            .collect()
+    }
 
-        while let Some(event) = self.queue.pop_front() {
-            emitted.push(event);
+    fn mute_unrouted(&mut self) {
+        let mut index = 0;
+        while index < self.sounding.len() {
+            let held = self.sounding[index];
+            let note_off = MidiLikeEvent::NoteOff { note: held.note };
+            if !held.tagged && self.route_bus(held.hand, note_off).is_none() {
+                self.muted.push(self.sounding.swap_remove(index));
+            } else {
+                index += 1;
+            }
         }
+    }
 
-        emitted
+    /// Applies the autopilot feel to an event due at `sample_time`, and keeps `sounding` in
+    /// step. Jitter never moves an event that is still on time into the past.
+    fn place(
+        &mut self,
+        bus: Bus,
+        event: &PlaybackMidiEvent,
+        sample_time: SampleTime,
+        now: SampleTime,
+    ) -> ScheduledEvent {
+        let feel = self.settings.feel;
+        let earliest = sample_time.min(now);
+        let humanized = bus == Bus::Autopilot;
+        let (sample_time, midi) = match event.event {
+            MidiLikeEvent::NoteOn { note, velocity } => {
+                let mut offset = 0;
+                let mut velocity = velocity;
+                let mut sample_time = sample_time;
+                if humanized {
+                    offset = (jitter(event.tick, note, 0)
+                        * feel.humanize_timing_ms as f64
+                        * self.sample_rate_hz as f64
+                        / 1000.0)
+                        .round() as i64;
+                    let spread = jitter(event.tick, note, 1) * feel.humanize_velocity as f64;
+                    let scaled = velocity as f64 * feel.velocity_scale as f64 + spread;
+                    velocity = scaled.round().clamp(1.0, 127.0) as u8;
+                    sample_time = offset_sample(sample_time, offset)
+                        .max(earliest)
+                        .max(self.released[note as usize & 127]);
+                }
+                if !self
+                    .sounding
+                    .iter()
+                    .any(|held| (held.bus, held.note) == (bus, note))
+                {
+                    self.sounding.push(SoundingNote {
+                        bus,
+                        note,
+                        hand: event.hand,
+                        tagged: event.bus.is_some(),
+                        on_sample: sample_time,
+                        offset,
+                    });
+                }
+                (sample_time, MidiLikeEvent::NoteOn { note, velocity })
+            }
+            MidiLikeEvent::NoteOff { note } => {
+                let mut offset = 0;
+                self.sounding.retain(|held| {
+                    let matches = (held.bus, held.note) == (bus, note);
+                    if matches {
+                        offset = held.offset;
+                    }
+                    !matches
+                });
+                let sample_time = offset_sample(sample_time, offset).max(earliest);
+                if humanized {
+                    let released = &mut self.released[note as usize & 127];
+                    *released = (*released).max(sample_time);
+                }
+                (sample_time, event.event)
+            }
+            MidiLikeEvent::Cc64 { .. } | MidiLikeEvent::ControlChange { .. } => {
+                (sample_time, event.event)
+            }
+        };
+        ScheduledEvent {
+            sample_time,
+            bus,
+            event: midi,
+            generation: self.generation,
+        }
+    }
+
+    fn notify(&mut self, notification: PlaybackNotification) {
+        // Embedders that never drain (AppCore today) only keep the most recent ones.
+        if self.notifications.len() >= MAX_NOTIFICATIONS {
+            self.notifications.pop_front();
+        }
+        self.notifications.push_back(notification);
     }
 
-    fn route_bus(&self, hand: Option<Hand>) -> Option<Bus> {
+    /// Bus for the score's musical material; events with an explicit bus bypass this.
+    fn route_bus(&self, hand: Option<Hand>, event: MidiLikeEvent) -> Option<Bus> {
+        let route = self.settings.accompaniment;
         match self.settings.mode {
             PlaybackMode::Demo => Some(Bus::Autopilot),
-            PlaybackMode::Accompaniment => match hand {
-                Some(Hand::Left) if !self.settings.accompaniment.play_left => None,
-                Some(Hand::Right) if !self.settings.accompaniment.play_right => None,
+            PlaybackMode::Accompaniment => match (event, hand) {
+                (MidiLikeEvent::Cc64 { .. }, _)
+                    if route.suppress_pedal || !(route.play_left || route.play_right) =>
+                {
+                    None
+                }
+                (_, Some(Hand::Left)) if !route.play_left => None,
+                (_, Some(Hand::Right)) if !route.play_right => None,
                 _ => Some(Bus::Autopilot),
             },
         }
     }
 }
 
-fn midi_event_rank(event: &cadenza_ports::midi::MidiLikeEvent) -> u8 {
-    use cadenza_ports::midi::MidiLikeEvent;
+/// The transport as it will be right after wrapping to `start_tick` at `wrap_sample`.
+fn next_pass_timeline(
+    transport: &Transport,
+    start_tick: Tick,
+    wrap_sample: SampleTime,
+) -> Transport {
+    let mut timeline = transport.clone();
+    timeline.seek(start_tick);
+    timeline.align_to_sample_time(wrap_sample);
+    timeline
+}
+
+fn offset_sample(sample_time: SampleTime, offset: i64) -> SampleTime {
+    sample_time.saturating_add_signed(offset)
+}
+
+/// Deterministic value in [-1, 1] for one note of the score.
+fn jitter(tick: Tick, note: u8, salt: u64) -> f64 {
+    // splitmix64 finalizer
+    let mut x = (tick as u64)
+        .wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        .wrapping_add((note as u64) << 8 | salt);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^= x >> 31;
+    (x >> 11) as f64 / (1u64 << 52) as f64 - 1.0
+}
+
+fn midi_event_rank(event: &MidiLikeEvent) -> u8 {
     match event {
         MidiLikeEvent::Cc64 { value } => {
             if *value >= 64 {
@@ -152,16 +715,16 @@ fn midi_event_rank(event: &cadenza_ports::midi::MidiLikeEvent) -> u8 {
                 3
             }
         }
+        MidiLikeEvent::ControlChange { .. } => 0,
         MidiLikeEvent::NoteOff { .. } => 1,
         MidiLikeEvent::NoteOn { .. } => 2,
     }
 }
 
-fn midi_event_note_key(event: &cadenza_ports::midi::MidiLikeEvent) -> u8 {
-    use cadenza_ports::midi::MidiLikeEvent;
+fn midi_event_note_key(event: &MidiLikeEvent) -> u8 {
     match event {
         MidiLikeEvent::NoteOn { note, .. } => *note,
         MidiLikeEvent::NoteOff { note } => *note,
-        MidiLikeEvent::Cc64 { .. } => 0,
+        MidiLikeEvent::Cc64 { .. } | MidiLikeEvent::ControlChange { .. } => 0,
     }
 }
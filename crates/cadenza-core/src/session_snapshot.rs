@@ -0,0 +1,35 @@
+use crate::ipc::ScoreSource;
+use cadenza_domain_eval::JudgeSnapshot;
+use cadenza_ports::types::Tick;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How often a running session is written out; a crash loses at most this much practice.
+pub const SESSION_AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A practice session in progress, as written by autosave and offered back after a crash.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SessionSnapshot {
+    /// Where the score came from, to load it again on resume.
+    pub score_source: ScoreSource,
+    pub score_title: Option<String>,
+    pub tick: Tick,
+    pub stats: JudgeSnapshot,
+    pub saved_at_unix_ms: u64,
+}
+
+impl SessionSnapshot {
+    pub fn to_json(&self) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec_pretty(self)
+    }
+
+    pub fn from_json(data: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(data)
+    }
+}
+
+pub(crate) fn unix_ms_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
@@ -1,4 +1,4 @@
-use cadenza_domain_score::TempoPoint;
+use cadenza_domain_score::{TempoPoint, TimeSignaturePoint};
 use cadenza_ports::playback::LoopRange;
 use cadenza_ports::types::{SampleTime, Tick};
 
@@ -15,6 +15,14 @@ pub struct TempoMap {
     segments: Vec<TempoSegment>,
 }
 
+/// Remembers the tempo segment of the last tick looked up, so a run of ticks in order finds
+/// each segment without searching. Any cursor gives the same answers; a stale one is only
+/// slower.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TempoCursor {
+    segment: usize,
+}
+
 #[derive(Clone, Copy, Debug)]
 struct TempoSegment {
     start_tick: Tick,
@@ -22,22 +30,93 @@ struct TempoSegment {
     us_per_quarter: u32,
 }
 
+/// Musical position; `bar` is 1-based, with bar 0 reserved for a pickup.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BarBeat {
+    pub bar: u32,
+    /// 1-based beat within the bar, counted in the signature's denominator.
+    pub beat: u32,
+    pub tick_in_beat: Tick,
+}
+
+/// Bar lines derived from time signature changes. Each change starts a new bar; a change
+/// falling mid-bar cuts that bar short.
+#[derive(Clone, Debug)]
+pub struct TimeSignatureMap {
+    pickup_ticks: Tick,
+    segments: Vec<MeterSegment>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct MeterSegment {
+    start_tick: Tick,
+    first_bar: u32,
+    beats: u32,
+    beat_ticks: Tick,
+}
+
+impl MeterSegment {
+    fn bar_ticks(&self) -> Tick {
+        self.beat_ticks * self.beats as Tick
+    }
+}
+
+/// A tempo multiplier change spread over a span of audio samples.
+#[derive(Clone, Copy, Debug)]
+struct TempoRamp {
+    from: f32,
+    to: f32,
+    start_sample: SampleTime,
+    duration_samples: u64,
+}
+
+impl TempoRamp {
+    fn multiplier_at(&self, sample: SampleTime) -> f32 {
+        let elapsed = sample.saturating_sub(self.start_sample);
+        if elapsed >= self.duration_samples {
+            return self.to;
+        }
+        let t = elapsed as f64 / self.duration_samples as f64;
+        (self.from as f64 + (self.to - self.from) as f64 * t) as f32
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Transport {
     state: TransportState,
     ppq: u16,
     sample_rate_hz: u32,
-    origin_sample: SampleTime,
+    /// Sample at which tick 0 would play; negative when playback starts further into the
+    /// score than the audio clock has run.
+    origin_sample: i64,
     tempo_map: TempoMap,
+    time_signatures: TimeSignatureMap,
     tempo_multiplier: f32,
+    tempo_ramp: Option<TempoRamp>,
     position_tick: Tick,
     position_sample: SampleTime,
     loop_range: Option<LoopRange>,
+    loop_wraps: u64,
 }
 
+/// Fastest tempo a map holds, 60000 BPM. Corrupt files can carry a tempo of zero.
+const MIN_US_PER_QUARTER: u32 = 1_000;
+
 impl TempoMap {
+    /// Without a point at tick 0 the score starts at 120 BPM. Points at negative ticks are
+    /// dropped, of several at one tick the last wins, and tempos are clamped to
+    /// [`MIN_US_PER_QUARTER`].
     pub fn new(ppq: u16, mut points: Vec<TempoPoint>) -> Self {
-        if points.is_empty() || points[0].tick != 0 {
+        points.retain(|point| point.tick >= 0);
+        points.sort_by_key(|p| p.tick);
+        points.dedup_by(|later, earlier| {
+            let same_tick = later.tick == earlier.tick;
+            if same_tick {
+                *earlier = *later;
+            }
+            same_tick
+        });
+        if points.first().is_none_or(|point| point.tick > 0) {
             points.insert(
                 0,
                 TempoPoint {
@@ -46,7 +125,9 @@ impl TempoMap {
                 },
             );
         }
-        points.sort_by_key(|p| p.tick);
+        for point in &mut points {
+            point.us_per_quarter = point.us_per_quarter.max(MIN_US_PER_QUARTER);
+        }
 
         let mut segments = Vec::with_capacity(points.len());
         let mut current_us = 0i64;
@@ -66,8 +147,25 @@ impl TempoMap {
         Self { ppq, segments }
     }
 
+    /// The tempo changes as the map holds them, starting at tick 0.
+    pub fn points(&self) -> Vec<TempoPoint> {
+        self.segments
+            .iter()
+            .map(|seg| TempoPoint {
+                tick: seg.start_tick,
+                us_per_quarter: seg.us_per_quarter,
+            })
+            .collect()
+    }
+
     pub fn tick_to_micros(&self, tick: Tick) -> i64 {
-        let seg = self.segment_for_tick(tick);
+        self.tick_to_micros_from(tick, &mut TempoCursor::default())
+    }
+
+    /// [`Self::tick_to_micros`], starting the segment lookup at `cursor`.
+    pub fn tick_to_micros_from(&self, tick: Tick, cursor: &mut TempoCursor) -> i64 {
+        cursor.segment = self.segment_index_from(tick, cursor.segment);
+        let seg = self.segments[cursor.segment];
         let delta_ticks = tick - seg.start_tick;
         seg.start_us + ticks_to_us(delta_ticks, seg.us_per_quarter, self.ppq)
     }
@@ -79,15 +177,41 @@ impl TempoMap {
         seg.start_tick + delta_ticks
     }
 
+    fn micros_to_tick_f64(&self, micros: f64) -> f64 {
+        let seg = self.segment_for_micros(micros.floor() as i64);
+        let delta_us = micros - seg.start_us as f64;
+        seg.start_tick as f64 + delta_us * self.ppq as f64 / seg.us_per_quarter as f64
+    }
+
     fn segment_for_tick(&self, tick: Tick) -> TempoSegment {
-        let mut current = self.segments[0];
-        for seg in &self.segments {
-            if seg.start_tick > tick {
-                break;
-            }
-            current = *seg;
+        let idx = self
+            .segments
+            .partition_point(|seg| seg.start_tick <= tick)
+            .saturating_sub(1);
+        self.segments[idx]
+    }
+
+    /// Index of the segment holding `tick`. Checks `hint` and the segment after it before
+    /// searching, as consecutive lookups mostly land there.
+    fn segment_index_from(&self, tick: Tick, hint: usize) -> usize {
+        let holds = |idx: usize| {
+            self.segments
+                .get(idx)
+                .is_some_and(|seg| seg.start_tick <= tick)
+                && self
+                    .segments
+                    .get(idx + 1)
+                    .is_none_or(|next| next.start_tick > tick)
+        };
+        if holds(hint) {
+            return hint;
         }
-        current
+        if holds(hint + 1) {
+            return hint + 1;
+        }
+        self.segments
+            .partition_point(|seg| seg.start_tick <= tick)
+            .saturating_sub(1)
     }
 
     fn us_per_quarter_at(&self, tick: Tick) -> u32 {
@@ -95,14 +219,143 @@ impl TempoMap {
     }
 
     fn segment_for_micros(&self, micros: i64) -> TempoSegment {
-        let mut current = self.segments[0];
-        for seg in &self.segments {
-            if seg.start_us > micros {
-                break;
+        let idx = self
+            .segments
+            .partition_point(|seg| seg.start_us <= micros)
+            .saturating_sub(1);
+        self.segments[idx]
+    }
+}
+
+impl TimeSignatureMap {
+    /// `pickup_ticks` is the length of an incomplete first bar, which becomes bar 0.
+    /// Without points the score is in 4/4.
+    pub fn new(ppq: u16, mut points: Vec<TimeSignaturePoint>, pickup_ticks: Tick) -> Self {
+        points.retain(|point| point.numerator > 0 && point.denominator > 0 && point.tick >= 0);
+        points.sort_by_key(|point| point.tick);
+        points.dedup_by(|later, earlier| {
+            let same_tick = later.tick == earlier.tick;
+            if same_tick {
+                *earlier = *later;
             }
-            current = *seg;
+            same_tick
+        });
+        if points.first().is_none_or(|point| point.tick > 0) {
+            points.insert(
+                0,
+                TimeSignaturePoint {
+                    tick: 0,
+                    numerator: 4,
+                    denominator: 4,
+                },
+            );
+        }
+
+        let beat_ticks = |denominator: u8| (Tick::from(ppq) * 4 / Tick::from(denominator)).max(1);
+        let first_bar_ticks = beat_ticks(points[0].denominator) * Tick::from(points[0].numerator);
+        let next_change = points.get(1).map_or(Tick::MAX, |point| point.tick);
+        let pickup_ticks = if pickup_ticks > 0 && pickup_ticks < first_bar_ticks {
+            pickup_ticks.min(next_change)
+        } else {
+            0
+        };
+
+        let mut segments: Vec<MeterSegment> = Vec::with_capacity(points.len());
+        for point in &points {
+            let start_tick = point.tick.max(pickup_ticks);
+            let first_bar = match segments.last() {
+                Some(prev) => {
+                    let span = start_tick - prev.start_tick;
+                    prev.first_bar + ((span + prev.bar_ticks() - 1) / prev.bar_ticks()) as u32
+                }
+                None => 1,
+            };
+            segments.push(MeterSegment {
+                start_tick,
+                first_bar,
+                beats: u32::from(point.numerator),
+                beat_ticks: beat_ticks(point.denominator),
+            });
+        }
+
+        Self {
+            pickup_ticks,
+            segments,
         }
-        current
+    }
+
+    pub fn position_at(&self, tick: Tick) -> BarBeat {
+        let tick = tick.max(0);
+        if tick < self.pickup_ticks {
+            // The pickup holds the last beats of a full bar.
+            let seg = self.segments[0];
+            let offset = tick + seg.bar_ticks() - self.pickup_ticks;
+            return BarBeat {
+                bar: 0,
+                beat: (offset / seg.beat_ticks) as u32 + 1,
+                tick_in_beat: offset % seg.beat_ticks,
+            };
+        }
+        let seg = self.segment_for_tick(tick);
+        let offset = tick - seg.start_tick;
+        let in_bar = offset % seg.bar_ticks();
+        BarBeat {
+            bar: seg.first_bar + (offset / seg.bar_ticks()) as u32,
+            beat: (in_bar / seg.beat_ticks) as u32 + 1,
+            tick_in_beat: in_bar % seg.beat_ticks,
+        }
+    }
+
+    /// First tick of `bar`; bar 0 and bar 1 share tick 0 when there is no pickup.
+    pub fn tick_of_bar(&self, bar: u32) -> Tick {
+        if bar == 0 {
+            return 0;
+        }
+        let seg = self.segment_for_bar(bar);
+        seg.start_tick + Tick::from(bar - seg.first_bar) * seg.bar_ticks()
+    }
+
+    /// Beats started in `bar`, counting a partial final beat of a shortened bar.
+    pub fn beats_in_bar(&self, bar: u32) -> u32 {
+        if bar == 0 {
+            let seg = self.segments[0];
+            return ((self.pickup_ticks + seg.beat_ticks - 1) / seg.beat_ticks) as u32;
+        }
+        let seg = self.segment_for_bar(bar);
+        let start = self.tick_of_bar(bar);
+        let next_change = self
+            .segments
+            .iter()
+            .find(|next| next.start_tick > start)
+            .map_or(Tick::MAX, |next| next.start_tick);
+        let len = seg.bar_ticks().min(next_change - start);
+        ((len + seg.beat_ticks - 1) / seg.beat_ticks) as u32
+    }
+
+    pub fn pickup_ticks(&self) -> Tick {
+        self.pickup_ticks
+    }
+
+    /// Beats per bar and ticks per beat of the signature in force at `tick`.
+    pub fn meter_at(&self, tick: Tick) -> (u32, Tick) {
+        let seg = self.segment_for_tick(tick.max(0));
+        (seg.beats, seg.beat_ticks)
+    }
+
+    fn segment_for_tick(&self, tick: Tick) -> MeterSegment {
+        let index = self
+            .segments
+            .partition_point(|seg| seg.start_tick <= tick)
+            .saturating_sub(1);
+        self.segments[index]
+    }
+
+    fn segment_for_bar(&self, bar: u32) -> MeterSegment {
+        let index = self
+            .segments
+            .partition_point(|seg| seg.first_bar <= bar)
+            .saturating_sub(1);
+        self.segments[index]
     }
 }
 
@@ -115,10 +368,13 @@ impl Transport {
             sample_rate_hz,
             origin_sample: 0,
             tempo_map,
+            time_signatures: TimeSignatureMap::new(ppq, Vec::new(), 0),
             tempo_multiplier: 1.0,
+            tempo_ramp: None,
             position_tick: 0,
             position_sample: 0,
             loop_range: None,
+            loop_wraps: 0,
         }
     }
 
@@ -147,12 +403,20 @@ impl Transport {
 
     pub fn align_to_sample_time(&mut self, sample_time: SampleTime) {
         let relative = self.tick_to_sample_relative(self.position_tick);
-        self.origin_sample = sample_time.saturating_sub(relative);
+        self.origin_sample = sample_time as i64 - relative as i64;
         self.position_sample = sample_time;
     }
 
+    /// Moves the position by `delta` ticks without moving in audio time; the music after it
+    /// comes that much sooner (or later).
+    pub fn shift_ticks(&mut self, delta: Tick) {
+        let sample_time = self.position_sample;
+        self.position_tick = (self.position_tick + delta).max(0);
+        self.align_to_sample_time(sample_time);
+    }
+
     pub fn set_origin_sample(&mut self, origin_sample: SampleTime) {
-        self.origin_sample = origin_sample;
+        self.origin_sample = origin_sample as i64;
         self.position_sample = self.tick_to_sample(self.position_tick);
     }
 
@@ -161,10 +425,32 @@ impl Transport {
     }
 
     pub fn set_tempo_multiplier(&mut self, multiplier: f32) {
+        self.tempo_ramp = None;
         self.tempo_multiplier = multiplier.max(0.1);
         self.recalculate_origin();
     }
 
+    /// Moves the multiplier to `target` linearly over `over_ms` of audio time, stepping on
+    /// each `sync_to_sample_time`. Starts from the current value, so a new ramp smoothly
+    /// takes over from one still in progress.
+    pub fn ramp_tempo_multiplier(&mut self, target: f32, over_ms: u32) {
+        let duration_samples = over_ms as u64 * self.sample_rate_hz as u64 / 1000;
+        if duration_samples == 0 {
+            self.set_tempo_multiplier(target);
+            return;
+        }
+        self.tempo_ramp = Some(TempoRamp {
+            from: self.tempo_multiplier,
+            to: target.max(0.1),
+            start_sample: self.position_sample,
+            duration_samples,
+        });
+    }
+
+    pub fn is_ramping_tempo(&self) -> bool {
+        self.tempo_ramp.is_some()
+    }
+
     pub fn set_sample_rate(&mut self, sample_rate_hz: u32) {
         self.sample_rate_hz = sample_rate_hz;
         self.recalculate_origin();
@@ -179,24 +465,60 @@ impl Transport {
         self.recalculate_origin();
     }
 
+    pub fn update_time_signatures(&mut self, points: Vec<TimeSignaturePoint>, pickup_ticks: Tick) {
+        self.time_signatures = TimeSignatureMap::new(self.ppq, points, pickup_ticks);
+    }
+
+    pub fn time_signatures(&self) -> &TimeSignatureMap {
+        &self.time_signatures
+    }
+
+    pub fn tempo_map(&self) -> &TempoMap {
+        &self.tempo_map
+    }
+
+    pub fn now_bar_beat(&self) -> BarBeat {
+        self.time_signatures.position_at(self.position_tick)
+    }
+
     pub fn advance_by_samples(&mut self, frames: u32) {
         if self.state != TransportState::Playing {
             return;
         }
         self.position_sample = self.position_sample.saturating_add(frames as u64);
         self.position_tick = self.sample_to_tick(self.position_sample);
-
-        if let Some(loop_range) = self.loop_range {
-            if self.position_tick >= loop_range.end_tick {
-                self.seek(loop_range.start_tick);
-            }
-        }
+        self.wrap_loop();
     }
 
     pub fn now_tick(&self) -> Tick {
         self.position_tick
     }
 
+    /// Fractional tick at `sample_time`, for drawing a smooth playhead between syncs.
+    /// Never behind [`Transport::now_tick`], and within one tick of it at the synced sample.
+    pub fn now_tick_f64(&self, sample_time: f64) -> f64 {
+        let position = self.position_tick as f64;
+        if self.state != TransportState::Playing {
+            return position;
+        }
+        let relative_sample = (sample_time - self.origin_sample as f64).max(0.0);
+        let micros = relative_sample * 1_000_000.0 / self.sample_rate_hz.max(1) as f64;
+        let mut tick = self
+            .tempo_map
+            .micros_to_tick_f64(micros * self.tempo_multiplier as f64)
+            .max(position);
+        if sample_time <= self.position_sample as f64 {
+            tick = tick.min(position + 1.0);
+        }
+        match self.loop_range {
+            Some(range) if range.end_tick > range.start_tick && tick >= range.end_tick as f64 => {
+                let len = (range.end_tick - range.start_tick) as f64;
+                range.start_tick as f64 + (tick - range.end_tick as f64) % len
+            }
+            _ => tick,
+        }
+    }
+
     pub fn now_sample(&self) -> SampleTime {
         self.position_sample
     }
@@ -205,9 +527,42 @@ impl Transport {
         self.tempo_multiplier
     }
 
+    /// Moves to `sample_time`. Crossing the loop end jumps back to the loop start.
     pub fn sync_to_sample_time(&mut self, sample_time: SampleTime) {
+        let tick = self.sample_to_tick(sample_time);
+        // Re-anchoring after a tempo change can round the mapping back by a tick.
+        self.position_tick = if sample_time >= self.position_sample {
+            tick.max(self.position_tick)
+        } else {
+            tick
+        };
+        self.position_sample = sample_time;
+        self.step_tempo_ramp(sample_time);
+        self.wrap_loop();
+    }
+
+    /// Jumps back to the loop start once the position passes the loop end. The start is
+    /// re-anchored to the sample where the end fell, so the overshoot carries into the next
+    /// pass instead of being dropped and the loop keeps its length in samples.
+    fn wrap_loop(&mut self) {
+        let Some(range) = self.loop_range else {
+            return;
+        };
+        if range.end_tick <= range.start_tick || self.position_tick < range.end_tick {
+            return;
+        }
+        let sample_time = self.position_sample;
+        let wrap_sample = self.tick_to_sample(range.end_tick);
+        self.seek(range.start_tick);
+        self.align_to_sample_time(wrap_sample);
         self.position_sample = sample_time;
         self.position_tick = self.sample_to_tick(sample_time);
+        self.loop_wraps += 1;
+    }
+
+    /// How many times playback has jumped from the loop end back to its start.
+    pub fn loop_wraps(&self) -> u64 {
+        self.loop_wraps
     }
 
     pub fn ms_to_ticks(&self, ms: i32) -> Tick {
@@ -216,22 +571,48 @@ impl Transport {
         us_to_ticks(us, us_per_quarter, self.ppq)
     }
 
+    /// Ticks that pass per wall-clock millisecond around `tick`, at the current tempo
+    /// multiplier.
+    pub fn ticks_per_ms_at(&self, tick: Tick) -> f64 {
+        let us_per_quarter = self.tempo_map.us_per_quarter_at(tick) as f64;
+        self.ppq as f64 * 1000.0 / us_per_quarter * self.tempo_multiplier as f64
+    }
+
+    /// Wall-clock time from tick 0 to `tick` at the current tempo multiplier.
+    pub fn tick_to_ms(&self, tick: Tick) -> u64 {
+        (self.tick_to_micros_scaled(tick).max(0) / 1000) as u64
+    }
+
+    /// Playing time of a score ending at `last_tick`.
+    pub fn total_duration_ms(&self, last_tick: Tick) -> u64 {
+        self.tick_to_ms(last_tick)
+    }
+
     pub fn tick_to_sample(&self, tick: Tick) -> SampleTime {
-        let micros = self.tick_to_micros_scaled(tick);
-        self.origin_sample
-            .saturating_add(micros_to_samples(micros, self.sample_rate_hz))
+        self.tick_to_sample_from(tick, &mut TempoCursor::default())
+    }
+
+    /// [`Self::tick_to_sample`] for ticks visited in order, with `cursor` carried from one
+    /// call to the next.
+    pub fn tick_to_sample_from(&self, tick: Tick, cursor: &mut TempoCursor) -> SampleTime {
+        let micros = self.scale_micros(self.tempo_map.tick_to_micros_from(tick, cursor));
+        (self.origin_sample + micros_to_samples(micros, self.sample_rate_hz) as i64).max(0)
+            as SampleTime
     }
 
     pub fn sample_to_tick(&self, sample: SampleTime) -> Tick {
-        let relative_sample = sample.saturating_sub(self.origin_sample);
+        let relative_sample = (sample as i64 - self.origin_sample).max(0) as SampleTime;
         let micros = samples_to_micros(relative_sample, self.sample_rate_hz);
         let scaled = (micros as f64 * self.tempo_multiplier as f64).round() as i64;
         self.tempo_map.micros_to_tick(scaled)
     }
 
     fn tick_to_micros_scaled(&self, tick: Tick) -> i64 {
-        let base = self.tempo_map.tick_to_micros(tick) as f64;
-        (base / self.tempo_multiplier as f64).round() as i64
+        self.scale_micros(self.tempo_map.tick_to_micros(tick))
+    }
+
+    fn scale_micros(&self, micros: i64) -> i64 {
+        (micros as f64 / self.tempo_multiplier as f64).round() as i64
     }
 
     fn tick_to_sample_relative(&self, tick: Tick) -> SampleTime {
@@ -239,10 +620,23 @@ impl Transport {
         micros_to_samples(micros, self.sample_rate_hz)
     }
 
+    /// Applies the ramp's multiplier for `sample_time`, re-anchoring at the current position
+    /// so the step changes the rate ahead without moving the tick.
+    fn step_tempo_ramp(&mut self, sample_time: SampleTime) {
+        let Some(ramp) = self.tempo_ramp else {
+            return;
+        };
+        self.tempo_multiplier = ramp.multiplier_at(sample_time);
+        if sample_time.saturating_sub(ramp.start_sample) >= ramp.duration_samples {
+            self.tempo_ramp = None;
+        }
+        self.recalculate_origin();
+    }
+
     fn recalculate_origin(&mut self) {
         let current_sample = self.position_sample;
         let relative = self.tick_to_sample_relative(self.position_tick);
-        self.origin_sample = current_sample.saturating_sub(relative);
+        self.origin_sample = current_sample as i64 - relative as i64;
     }
 }
 
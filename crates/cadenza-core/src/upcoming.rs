@@ -0,0 +1,88 @@
+use crate::ipc::PianoRollTargetDto;
+use cadenza_ports::types::Tick;
+use std::collections::HashMap;
+
+pub const DEFAULT_UPCOMING_TARGET_COUNT: usize = 3;
+
+/// The next few targets from the judge's focus, for `Event::UpcomingTargets`. Targets are
+/// sorted once per score; a cursor follows the transport forward so most ticks cost a
+/// comparison, and only a seek back searches again.
+#[derive(Clone, Debug)]
+pub struct UpcomingTargets {
+    targets: Vec<PianoRollTargetDto>,
+    index_of: HashMap<u64, usize>,
+    /// First target at or after the last polled tick.
+    cursor: usize,
+    count: usize,
+    /// Position changes within one bucket don't count as a change on their own.
+    bucket_ticks: Tick,
+    /// First target and bucket of the last report.
+    reported: Option<(usize, Tick)>,
+}
+
+impl UpcomingTargets {
+    pub fn new(count: usize) -> Self {
+        Self {
+            targets: Vec::new(),
+            index_of: HashMap::new(),
+            cursor: 0,
+            count,
+            bucket_ticks: 1,
+            reported: None,
+        }
+    }
+
+    /// Replaces the targets, which must be sorted by tick. The next poll reports.
+    pub fn set_targets(&mut self, targets: Vec<PianoRollTargetDto>, bucket_ticks: Tick) {
+        self.index_of = targets
+            .iter()
+            .enumerate()
+            .map(|(idx, target)| (target.id, idx))
+            .collect();
+        self.targets = targets;
+        self.bucket_ticks = bucket_ticks.max(1);
+        self.cursor = 0;
+        self.reported = None;
+    }
+
+    /// How many targets each report holds; 0 stops the reports.
+    pub fn set_count(&mut self, count: usize) {
+        self.count = count;
+        self.reported = None;
+    }
+
+    /// The targets from `focus`, or from `now_tick` without one, when they or the position
+    /// bucket have changed since the last report.
+    pub fn poll(&mut self, now_tick: Tick, focus: Option<u64>) -> Option<Vec<PianoRollTargetDto>> {
+        if self.count == 0 || self.targets.is_empty() {
+            return None;
+        }
+        if self.cursor > 0 && self.targets[self.cursor - 1].tick >= now_tick {
+            self.cursor = self.targets.partition_point(|t| t.tick < now_tick);
+        }
+        while self
+            .targets
+            .get(self.cursor)
+            .is_some_and(|t| t.tick < now_tick)
+        {
+            self.cursor += 1;
+        }
+
+        let start = focus
+            .and_then(|id| self.index_of.get(&id).copied())
+            .unwrap_or(self.cursor);
+        let bucket = now_tick.div_euclid(self.bucket_ticks);
+        if self.reported == Some((start, bucket)) {
+            return None;
+        }
+        self.reported = Some((start, bucket));
+        let end = (start + self.count).min(self.targets.len());
+        Some(self.targets[start..end].to_vec())
+    }
+}
+
+impl Default for UpcomingTargets {
+    fn default() -> Self {
+        Self::new(DEFAULT_UPCOMING_TARGET_COUNT)
+    }
+}
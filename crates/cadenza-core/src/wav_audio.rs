@@ -0,0 +1,71 @@
+use crate::diag_log;
+use crate::null_audio::spawn_paced_output;
+use cadenza_ports::audio::{AudioError, AudioOutputPort, AudioRenderCallback, AudioStreamHandle};
+use cadenza_ports::types::{AudioConfig, AudioOutputDevice, DeviceId};
+use std::path::{Path, PathBuf};
+
+pub const WAV_AUDIO_DEVICE_ID: &str = "wav";
+
+/// Output that renders in real time like [`crate::NullAudioOutputPort`] but keeps the audio
+/// in a WAV file, rewritten each time the output opens. For listening back to a session on
+/// machines without a usable device.
+#[derive(Clone, Debug)]
+pub struct WavFileAudioOutputPort {
+    path: PathBuf,
+}
+
+impl WavFileAudioOutputPort {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AudioOutputPort for WavFileAudioOutputPort {
+    fn list_outputs(&self) -> Result<Vec<AudioOutputDevice>, AudioError> {
+        Ok(vec![AudioOutputDevice {
+            id: DeviceId(WAV_AUDIO_DEVICE_ID.to_string()),
+            name: format!("WAV file ({})", self.path.display()),
+            default_config: AudioConfig {
+                sample_rate_hz: 48_000,
+                channels: 2,
+                buffer_size_frames: Some(512),
+            },
+            buffer_size_range: None,
+        }])
+    }
+
+    fn open_output(
+        &self,
+        _device_id: &DeviceId,
+        config: AudioConfig,
+        cb: Box<dyn AudioRenderCallback>,
+    ) -> Result<Box<dyn AudioStreamHandle>, AudioError> {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: config.sample_rate_hz,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut wav = hound::WavWriter::create(&self.path, spec)
+            .map_err(|e| AudioError::Backend(format!("{}: {e}", self.path.display())))?;
+        let path = self.path.clone();
+        let mut failed = false;
+        // The writer finalizes the file when the stream drops it on close.
+        spawn_paced_output("wav-audio", config, cb, move |left, right| {
+            if failed {
+                return;
+            }
+            for (&l, &r) in left.iter().zip(right) {
+                if let Err(err) = wav.write_sample(l).and_then(|()| wav.write_sample(r)) {
+                    diag_log!(Error, "writing {} failed: {err}", path.display());
+                    failed = true;
+                    return;
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,211 @@
+use cadenza_core::{AppCore, AppError, Command, Event, NullMidiInputPort, SilentSynth};
+use cadenza_ports::audio::{AudioError, AudioOutputPort, AudioRenderCallback, AudioStreamHandle};
+use cadenza_ports::types::{AudioConfig, AudioOutputDevice, BufferSizeRange, DeviceId};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+mod common;
+
+use common::MemoryStorage;
+
+const DEFAULT_FRAMES: u32 = 1024;
+
+/// Output taking 64 to 2048 frames, rounded up to a power of two like some drivers do.
+struct Interface {
+    opened: Arc<Mutex<Vec<AudioConfig>>>,
+}
+
+struct FakeAudioStream {
+    granted: AudioConfig,
+}
+
+impl AudioStreamHandle for FakeAudioStream {
+    fn close(self: Box<Self>) {}
+
+    fn effective_config(&self) -> Option<AudioConfig> {
+        Some(self.granted)
+    }
+}
+
+impl AudioOutputPort for Interface {
+    fn list_outputs(&self) -> Result<Vec<AudioOutputDevice>, AudioError> {
+        Ok(vec![AudioOutputDevice {
+            id: DeviceId("interface".to_string()),
+            name: "Interface".to_string(),
+            default_config: AudioConfig {
+                sample_rate_hz: 48_000,
+                channels: 2,
+                buffer_size_frames: Some(DEFAULT_FRAMES),
+            },
+            buffer_size_range: Some(BufferSizeRange {
+                min_frames: 64,
+                max_frames: 2048,
+            }),
+        }])
+    }
+
+    fn open_output(
+        &self,
+        _device_id: &DeviceId,
+        config: AudioConfig,
+        _cb: Box<dyn AudioRenderCallback>,
+    ) -> Result<Box<dyn AudioStreamHandle>, AudioError> {
+        self.opened.lock().push(config);
+        Ok(Box::new(FakeAudioStream {
+            granted: AudioConfig {
+                buffer_size_frames: config.buffer_size_frames.map(u32::next_power_of_two),
+                ..config
+            },
+        }))
+    }
+}
+
+struct Rig {
+    core: AppCore,
+    opened: Arc<Mutex<Vec<AudioConfig>>>,
+    storage: MemoryStorage,
+}
+
+impl Rig {
+    fn launch(storage: MemoryStorage) -> Self {
+        let opened = Arc::new(Mutex::new(Vec::new()));
+        let mut core = AppCore::new(
+            Box::new(Interface {
+                opened: opened.clone(),
+            }),
+            Box::new(NullMidiInputPort),
+            Arc::new(SilentSynth),
+            None,
+            Some(Box::new(storage.clone())),
+        )
+        .expect("core");
+        core.drain_events();
+        Self {
+            core,
+            opened,
+            storage,
+        }
+    }
+
+    fn select(&mut self, config: Option<AudioConfig>) {
+        self.core
+            .handle_command(Command::SelectAudioOutput {
+                device_id: DeviceId("interface".to_string()),
+                config,
+            })
+            .expect("select");
+    }
+
+    fn set_buffer_size(&mut self, frames: Option<u32>) -> Result<(), AppError> {
+        self.core
+            .handle_command(Command::SetAudioBufferSize { frames })
+    }
+
+    fn last_opened(&self) -> AudioConfig {
+        *self.opened.lock().last().expect("opened")
+    }
+
+    fn saved_frames(&self) -> Option<u32> {
+        self.storage.settings.lock().audio_buffer_size_frames
+    }
+}
+
+fn config(sample_rate_hz: u32, buffer_size_frames: Option<u32>) -> AudioConfig {
+    AudioConfig {
+        sample_rate_hz,
+        channels: 2,
+        buffer_size_frames,
+    }
+}
+
+#[test]
+fn buffer_size_changes_keep_the_sample_rate_and_report_the_granted_size() {
+    let mut rig = Rig::launch(MemoryStorage::default());
+    rig.select(Some(config(96_000, None)));
+    assert_eq!(rig.last_opened(), config(96_000, Some(DEFAULT_FRAMES)));
+    rig.core.drain_events();
+
+    rig.set_buffer_size(Some(300)).expect("300 frames");
+    assert_eq!(rig.last_opened(), config(96_000, Some(300)));
+    assert_eq!(rig.saved_frames(), Some(300));
+    let configured: Vec<_> = rig
+        .core
+        .drain_events()
+        .into_iter()
+        .filter_map(|event| match event {
+            Event::AudioOutputConfigured {
+                requested_buffer_size_frames,
+                effective,
+                ..
+            } => Some((requested_buffer_size_frames, effective)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(configured, vec![(Some(300), config(96_000, Some(512)))]);
+
+    // Outside the device's range: refused, and the stream is left alone.
+    let opens = rig.opened.lock().len();
+    let err = rig.set_buffer_size(Some(4096)).expect_err("too large");
+    assert!(matches!(
+        err,
+        AppError::Audio(AudioError::UnsupportedConfig(_))
+    ));
+    assert_eq!(rig.opened.lock().len(), opens);
+    assert_eq!(rig.saved_frames(), Some(300));
+
+    rig.set_buffer_size(None).expect("default");
+    assert_eq!(rig.last_opened(), config(96_000, Some(DEFAULT_FRAMES)));
+    assert_eq!(rig.saved_frames(), None);
+}
+
+#[test]
+fn buffer_size_precedence() {
+    let mut rig = Rig::launch(MemoryStorage::default());
+
+    // Nothing requested or saved: the device default.
+    rig.select(None);
+    assert_eq!(rig.last_opened().buffer_size_frames, Some(DEFAULT_FRAMES));
+
+    // A saved size beats the device default when reselecting without a config.
+    rig.set_buffer_size(Some(256)).expect("256 frames");
+    rig.select(None);
+    assert_eq!(rig.last_opened().buffer_size_frames, Some(256));
+
+    // A requested size is used and saved.
+    rig.select(Some(config(48_000, Some(128))));
+    assert_eq!(rig.last_opened().buffer_size_frames, Some(128));
+    assert_eq!(rig.saved_frames(), Some(128));
+
+    // Requesting no size, or 0, asks for the device default and clears the saved size.
+    rig.select(Some(config(48_000, Some(0))));
+    assert_eq!(rig.last_opened().buffer_size_frames, Some(DEFAULT_FRAMES));
+    assert_eq!(rig.saved_frames(), None);
+}
+
+#[test]
+fn saved_sizes_apply_on_launch_unless_the_device_refuses_them() {
+    let storage = MemoryStorage::default();
+    storage.settings.lock().audio_buffer_size_frames = Some(512);
+    let mut rig = Rig::launch(storage.clone());
+    rig.select(None);
+    assert_eq!(rig.last_opened().buffer_size_frames, Some(512));
+
+    // Saved while a different device was in use.
+    storage.settings.lock().audio_buffer_size_frames = Some(8192);
+    let mut rig = Rig::launch(storage);
+    rig.select(None);
+    assert_eq!(rig.last_opened().buffer_size_frames, Some(DEFAULT_FRAMES));
+    // Kept for a device that does take it.
+    assert_eq!(rig.saved_frames(), Some(8192));
+}
+
+#[test]
+fn buffer_size_without_an_open_output_applies_to_the_next_one() {
+    let mut rig = Rig::launch(MemoryStorage::default());
+    rig.set_buffer_size(Some(256)).expect("saved");
+    assert!(rig.opened.lock().is_empty());
+    assert_eq!(rig.saved_frames(), Some(256));
+
+    rig.select(None);
+    assert_eq!(rig.last_opened().buffer_size_frames, Some(256));
+}
@@ -0,0 +1,180 @@
+use cadenza_core::{AppCore, AppError, Command, Event, VirtualClock};
+use cadenza_ports::audio::{AudioError, AudioOutputPort, AudioRenderCallback, AudioStreamHandle};
+use cadenza_ports::midi::{
+    MidiError, MidiInputPort, MidiInputStream, MidiLikeEvent, PlayerEventCallback,
+};
+use cadenza_ports::synth::{SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::types::{
+    AudioConfig, AudioOutputDevice, Bus, DeviceId, MidiInputDevice, SampleTime,
+};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// An output held by another application for its first `busy_for` opens.
+#[derive(Clone)]
+struct BusySpeakers {
+    busy_for: u32,
+    opens: Arc<AtomicU32>,
+}
+
+struct FakeAudioStream;
+
+impl AudioStreamHandle for FakeAudioStream {
+    fn close(self: Box<Self>) {}
+}
+
+impl AudioOutputPort for BusySpeakers {
+    fn list_outputs(&self) -> Result<Vec<AudioOutputDevice>, AudioError> {
+        Ok(vec![AudioOutputDevice {
+            id: DeviceId("speakers".to_string()),
+            name: "Speakers".to_string(),
+            default_config: AudioConfig {
+                sample_rate_hz: 48_000,
+                channels: 2,
+                buffer_size_frames: Some(256),
+            },
+            buffer_size_range: None,
+        }])
+    }
+
+    fn open_output(
+        &self,
+        device_id: &DeviceId,
+        _config: AudioConfig,
+        _cb: Box<dyn AudioRenderCallback>,
+    ) -> Result<Box<dyn AudioStreamHandle>, AudioError> {
+        if self.opens.fetch_add(1, Ordering::SeqCst) < self.busy_for {
+            return Err(AudioError::DeviceBusy {
+                device: device_id.to_string(),
+                retry_after_ms: 10,
+            });
+        }
+        Ok(Box::new(FakeAudioStream))
+    }
+}
+
+struct NoMidi;
+
+impl MidiInputPort for NoMidi {
+    fn list_inputs(&self) -> Result<Vec<MidiInputDevice>, MidiError> {
+        Ok(Vec::new())
+    }
+
+    fn open_input(
+        &self,
+        device_id: &DeviceId,
+        _cb: PlayerEventCallback,
+    ) -> Result<Box<dyn MidiInputStream>, MidiError> {
+        Err(MidiError::DeviceNotFound(device_id.to_string()))
+    }
+}
+
+struct SilentSynth;
+
+impl SynthPort for SilentSynth {
+    fn load_soundfont_from_path(&self, _path: &str) -> Result<SoundFontInfo, SynthError> {
+        Err(SynthError::UnsupportedFormat)
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, _bus: Bus, _gm_program: u8) -> Result<(), SynthError> {
+        Ok(())
+    }
+
+    fn handle_event(&self, _bus: Bus, _event: MidiLikeEvent, _at: SampleTime) {}
+
+    fn render(&self, _bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        out_l[..frames].fill(0.0);
+        out_r[..frames].fill(0.0);
+    }
+}
+
+fn core(port: &BusySpeakers) -> (AppCore, Arc<VirtualClock>) {
+    let clock = Arc::new(VirtualClock::new());
+    let mut core = AppCore::new(
+        Box::new(port.clone()),
+        Box::new(NoMidi),
+        Arc::new(SilentSynth),
+        None,
+        None,
+    )
+    .expect("core");
+    core.set_clock(clock.clone());
+    core.drain_events();
+    (core, clock)
+}
+
+/// Ticks every 5 ms for `ms` milliseconds of virtual time.
+fn run(core: &mut AppCore, clock: &VirtualClock, ms: u64) {
+    for _ in 0..ms / 5 {
+        clock.advance(Duration::from_millis(5));
+        core.tick();
+    }
+}
+
+fn select_speakers(core: &mut AppCore) -> Result<(), AppError> {
+    core.handle_command(Command::SelectAudioOutput {
+        device_id: DeviceId("speakers".to_string()),
+        config: None,
+    })
+}
+
+#[test]
+fn a_briefly_busy_output_opens_on_a_later_attempt() {
+    let port = BusySpeakers {
+        busy_for: 2,
+        opens: Arc::new(AtomicU32::new(0)),
+    };
+    let (mut core, clock) = core(&port);
+
+    let started = Instant::now();
+    select_speakers(&mut core).expect("retried later");
+    // The command returns at once; the retries wait on the core's clock, not the thread.
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert_eq!(port.opens.load(Ordering::SeqCst), 1);
+
+    run(&mut core, &clock, 100);
+
+    assert_eq!(port.opens.load(Ordering::SeqCst), 3);
+    let events = core.drain_events();
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, Event::AudioOutputConfigured { .. })));
+    assert!(!events
+        .iter()
+        .any(|event| matches!(event, Event::CommandFailed { .. })));
+}
+
+#[test]
+fn an_output_that_stays_busy_reports_why() {
+    let port = BusySpeakers {
+        busy_for: u32::MAX,
+        opens: Arc::new(AtomicU32::new(0)),
+    };
+    let (mut core, clock) = core(&port);
+
+    select_speakers(&mut core).expect("retried later");
+    run(&mut core, &clock, 100);
+
+    assert_eq!(port.opens.load(Ordering::SeqCst), 3);
+    let failed: Vec<(String, bool)> = core
+        .drain_events()
+        .into_iter()
+        .filter_map(|event| match event {
+            Event::CommandFailed {
+                message,
+                recoverable,
+                ..
+            } => Some((message, recoverable)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(failed.len(), 1, "{failed:?}");
+    assert!(
+        failed[0].0.contains("in use by another application"),
+        "{failed:?}"
+    );
+    assert!(failed[0].1);
+}
@@ -0,0 +1,476 @@
+use cadenza_core::{
+    audio_recorder, AudioClock, AudioGraph, AudioMeters, AudioParams, LateEventStats,
+};
+use cadenza_ports::audio::AudioRenderCallback;
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::playback::ScheduledEvent;
+use cadenza_ports::storage::SettingsDto;
+use cadenza_ports::synth::{SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::types::{Bus, SampleTime, Volume01};
+use rtrb::RingBuffer;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SAMPLE_RATE: f32 = 48_000.0;
+
+/// Renders a constant level on the autopilot bus, so output steps come only from gain changes.
+struct DcSynth;
+
+impl SynthPort for DcSynth {
+    fn load_soundfont_from_path(&self, _path: &str) -> Result<SoundFontInfo, SynthError> {
+        Err(SynthError::UnsupportedFormat)
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, _bus: Bus, _gm_program: u8) -> Result<(), SynthError> {
+        Ok(())
+    }
+
+    fn handle_event(&self, _bus: Bus, _event: MidiLikeEvent, _at: SampleTime) {}
+
+    fn render(&self, bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        let value = if bus == Bus::Autopilot { 0.5 } else { 0.0 };
+        out_l[..frames].fill(value);
+        out_r[..frames].fill(value);
+    }
+}
+
+/// Renders a fixed-amplitude sine on the autopilot bus and silence elsewhere.
+struct SineSynth {
+    amplitude: f32,
+    frequency_hz: f32,
+    frame: AtomicU64,
+}
+
+impl SynthPort for SineSynth {
+    fn load_soundfont_from_path(&self, _path: &str) -> Result<SoundFontInfo, SynthError> {
+        Err(SynthError::UnsupportedFormat)
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, _bus: Bus, _gm_program: u8) -> Result<(), SynthError> {
+        Ok(())
+    }
+
+    fn handle_event(&self, _bus: Bus, _event: MidiLikeEvent, _at: SampleTime) {}
+
+    fn render(&self, bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        if bus != Bus::Autopilot {
+            out_l[..frames].fill(0.0);
+            out_r[..frames].fill(0.0);
+            return;
+        }
+        let start = self.frame.fetch_add(frames as u64, Ordering::Relaxed);
+        for i in 0..frames {
+            let t = (start + i as u64) as f32 / SAMPLE_RATE;
+            let value = self.amplitude * (std::f32::consts::TAU * self.frequency_hz * t).sin();
+            out_l[i] = value;
+            out_r[i] = value;
+        }
+    }
+}
+
+/// Counts render calls and plays a constant level on the autopilot bus while a note is held.
+#[derive(Default)]
+struct CountingSynth {
+    renders: AtomicUsize,
+    note_held: AtomicBool,
+}
+
+impl SynthPort for CountingSynth {
+    fn load_soundfont_from_path(&self, _path: &str) -> Result<SoundFontInfo, SynthError> {
+        Err(SynthError::UnsupportedFormat)
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, _bus: Bus, _gm_program: u8) -> Result<(), SynthError> {
+        Ok(())
+    }
+
+    fn handle_event(&self, bus: Bus, event: MidiLikeEvent, _at: SampleTime) {
+        if bus == Bus::Autopilot {
+            let held = matches!(event, MidiLikeEvent::NoteOn { .. });
+            self.note_held.store(held, Ordering::Relaxed);
+        }
+    }
+
+    fn render(&self, bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        self.renders.fetch_add(1, Ordering::Relaxed);
+        let held = bus == Bus::Autopilot && self.note_held.load(Ordering::Relaxed);
+        let value = if held { 0.25 } else { 0.0 };
+        out_l[..frames].fill(value);
+        out_r[..frames].fill(value);
+    }
+
+    fn is_silent(&self, bus: Bus) -> bool {
+        bus != Bus::Autopilot || !self.note_held.load(Ordering::Relaxed)
+    }
+}
+
+/// Records every event it receives with the sample time it was played at.
+#[derive(Default)]
+struct EventLogSynth {
+    events: Mutex<Vec<(MidiLikeEvent, SampleTime)>>,
+}
+
+impl SynthPort for EventLogSynth {
+    fn load_soundfont_from_path(&self, _path: &str) -> Result<SoundFontInfo, SynthError> {
+        Err(SynthError::UnsupportedFormat)
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, _bus: Bus, _gm_program: u8) -> Result<(), SynthError> {
+        Ok(())
+    }
+
+    fn handle_event(&self, _bus: Bus, event: MidiLikeEvent, at: SampleTime) {
+        self.events.lock().unwrap().push((event, at));
+    }
+
+    fn render(&self, _bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        out_l[..frames].fill(0.0);
+        out_r[..frames].fill(0.0);
+    }
+}
+
+fn graph_with(synth: Arc<dyn SynthPort>) -> (AudioGraph, Arc<AudioParams>, Arc<AudioMeters>) {
+    let params = Arc::new(AudioParams::new(&SettingsDto::default()));
+    params.set_master(Volume01::new(1.0));
+    params.set_bus(Bus::Autopilot, Volume01::new(1.0));
+    params.set_playback_enabled(true);
+    let meters = Arc::new(AudioMeters::new());
+    let (_producer, consumer) = RingBuffer::<ScheduledEvent>::new(16);
+    let graph = AudioGraph::new(
+        synth,
+        params.clone(),
+        consumer,
+        Arc::new(AudioClock::new()),
+        meters.clone(),
+        48_000,
+        1024,
+    );
+    (graph, params, meters)
+}
+
+fn sine_graph(amplitude: f32) -> (AudioGraph, Arc<AudioMeters>) {
+    let (graph, _params, meters) = graph_with(Arc::new(SineSynth {
+        amplitude,
+        frequency_hz: 1_000.0,
+        frame: AtomicU64::new(0),
+    }));
+    (graph, meters)
+}
+
+/// Renders `blocks` callbacks of 256 frames and returns the left channel.
+fn render_blocks(graph: &mut AudioGraph, start: SampleTime, blocks: usize) -> Vec<f32> {
+    let mut out = Vec::new();
+    let mut out_l = vec![0.0; 256];
+    let mut out_r = vec![0.0; 256];
+    for block in 0..blocks {
+        graph.render(start + (block * 256) as u64, &mut out_l, &mut out_r);
+        out.extend_from_slice(&out_l);
+    }
+    out
+}
+
+fn max_step(samples: &[f32]) -> f32 {
+    samples
+        .windows(2)
+        .fold(0.0_f32, |acc, pair| acc.max((pair[1] - pair[0]).abs()))
+}
+
+#[test]
+fn meters_report_sine_rms_and_peak_per_bus() {
+    let (mut graph, meters) = sine_graph(0.5);
+    let mut out_l = vec![0.0; 480];
+    let mut out_r = vec![0.0; 480];
+    // 100 full periods of 1 kHz at 48 kHz, over several callbacks.
+    for block in 0..10u64 {
+        graph.render(block * 480, &mut out_l, &mut out_r);
+    }
+
+    let levels = meters.take();
+    let expected_rms = 0.5 / std::f32::consts::SQRT_2;
+    assert!((levels.autopilot.rms - expected_rms).abs() < 0.005);
+    assert!((levels.autopilot.peak - 0.5).abs() < 0.005);
+    assert!((levels.master.rms - expected_rms).abs() < 0.005);
+    assert_eq!(levels.user.peak, 0.0);
+    assert_eq!(levels.metronome.rms, 0.0);
+
+    // Reading drains the cells.
+    assert_eq!(meters.take().master.rms, 0.0);
+}
+
+#[test]
+fn meter_levels_decay_instead_of_dropping_to_silence() {
+    let (mut graph, meters) = sine_graph(0.5);
+    let mut out_l = vec![0.0; 480];
+    let mut out_r = vec![0.0; 480];
+    graph.render(0, &mut out_l, &mut out_r);
+
+    let loud = meters.take();
+    let quiet = meters.take().decayed_from(loud, 0.5);
+    assert!((quiet.master.peak - loud.master.peak * 0.5).abs() < 1e-6);
+}
+
+#[test]
+fn volume_step_is_smoothed_without_zipper_jumps() {
+    let (mut graph, params, _meters) = graph_with(Arc::new(DcSynth));
+    params.set_bus(Bus::Autopilot, Volume01::new(0.0));
+    // Let the fade to silence and the limiter delay line settle first.
+    let warm_up = render_blocks(&mut graph, 0, 40);
+    assert!(warm_up.last().copied().unwrap_or_default().abs() < 1e-6);
+
+    params.set_bus(Bus::Autopilot, Volume01::new(1.0));
+    let mut out = vec![0.0];
+    out.extend(render_blocks(&mut graph, 40 * 256, 40));
+
+    // An unsmoothed step would jump by the full 0.5 in one sample.
+    let step = max_step(&out);
+    assert!(step < 0.005, "max per-sample step {step}");
+    assert!((out.last().copied().unwrap_or_default() - 0.5).abs() < 1e-3);
+}
+
+#[test]
+fn stopping_playback_fades_the_autopilot_bus() {
+    let (mut graph, params, _meters) = graph_with(Arc::new(DcSynth));
+    let warm_up = render_blocks(&mut graph, 0, 4);
+    params.set_playback_enabled(false);
+    let mut out = vec![warm_up.last().copied().unwrap_or_default()];
+    out.extend(render_blocks(&mut graph, 4 * 256, 40));
+
+    let step = max_step(&out);
+    assert!(step < 0.005, "max per-sample step {step}");
+    assert!(out.last().copied().unwrap_or_default().abs() < 1e-3);
+}
+
+#[test]
+fn recording_writes_every_rendered_frame_to_wav() {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let path = std::env::temp_dir().join(format!("cadenza-recording-{nanos}.wav"));
+    let (tap, mut recorder) = audio_recorder(48_000);
+    let (graph, _params, _meters) = graph_with(Arc::new(DcSynth));
+    let mut graph = graph.with_recorder(tap);
+
+    // Blocks rendered before the recorder is armed are not written.
+    render_blocks(&mut graph, 0, 4);
+    recorder.start(&path).expect("start recording");
+    let rendered = render_blocks(&mut graph, 4 * 256, 40);
+    let summary = recorder.stop().expect("stop recording");
+    render_blocks(&mut graph, 44 * 256, 4);
+
+    assert_eq!(summary.frames_written, 40 * 256);
+    assert_eq!(summary.dropped_frames, 0);
+
+    let mut reader = hound::WavReader::open(&path).expect("open wav");
+    assert_eq!(reader.spec().channels, 2);
+    assert_eq!(reader.spec().sample_rate, 48_000);
+    assert_eq!(reader.duration(), 40 * 256);
+    let left: Vec<f32> = reader
+        .samples::<f32>()
+        .step_by(2)
+        .map(|sample| sample.expect("read sample"))
+        .collect();
+    assert_eq!(left, rendered);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn silent_buses_are_not_rendered() {
+    let synth = Arc::new(CountingSynth::default());
+    let params = Arc::new(AudioParams::new(&SettingsDto::default()));
+    params.set_bus(Bus::Autopilot, Volume01::new(1.0));
+    params.set_playback_enabled(true);
+    let (mut producer, consumer) = RingBuffer::<ScheduledEvent>::new(16);
+    let mut graph = AudioGraph::new(
+        synth.clone(),
+        params,
+        consumer,
+        Arc::new(AudioClock::new()),
+        Arc::new(AudioMeters::new()),
+        48_000,
+        1024,
+    );
+
+    // Every bus is rendered once, reports silence, and is skipped from then on.
+    render_blocks(&mut graph, 0, 100);
+    assert_eq!(synth.renders.load(Ordering::Relaxed), 3);
+
+    let note_on = ScheduledEvent {
+        sample_time: 100 * 256,
+        bus: Bus::Autopilot,
+        event: MidiLikeEvent::NoteOn {
+            note: 60,
+            velocity: 100,
+        },
+        generation: 0,
+    };
+    let note_off = ScheduledEvent {
+        sample_time: 110 * 256,
+        bus: Bus::Autopilot,
+        event: MidiLikeEvent::NoteOff { note: 60 },
+        generation: 0,
+    };
+    producer.push(note_on).expect("queue note on");
+    producer.push(note_off).expect("queue note off");
+
+    // Only the autopilot bus wakes up: ten blocks while the note is held, plus the block
+    // that renders the note off.
+    synth.renders.store(0, Ordering::Relaxed);
+    let out = render_blocks(&mut graph, 100 * 256, 20);
+    assert_eq!(synth.renders.load(Ordering::Relaxed), 11);
+    assert!(out[5 * 256] > 0.1);
+    assert_eq!(out.last().copied(), Some(0.0));
+}
+
+fn note_on(note: u8, sample_time: SampleTime) -> ScheduledEvent {
+    ScheduledEvent {
+        sample_time,
+        bus: Bus::Autopilot,
+        event: MidiLikeEvent::NoteOn {
+            note,
+            velocity: 100,
+        },
+        generation: 0,
+    }
+}
+
+fn note_off(note: u8, sample_time: SampleTime) -> ScheduledEvent {
+    ScheduledEvent {
+        sample_time,
+        bus: Bus::Autopilot,
+        event: MidiLikeEvent::NoteOff { note },
+        generation: 0,
+    }
+}
+
+/// Renders 30 blocks, then queues a stale batch and renders the block starting at 7680.
+fn play_stale_batch(
+    late_drop_ms: Option<f32>,
+) -> (Vec<(MidiLikeEvent, SampleTime)>, Arc<LateEventStats>) {
+    let synth = Arc::new(EventLogSynth::default());
+    let params = Arc::new(AudioParams::new(&SettingsDto::default()));
+    params.set_playback_enabled(true);
+    let stats = Arc::new(LateEventStats::new());
+    let (mut producer, consumer) = RingBuffer::<ScheduledEvent>::new(16);
+    let mut graph = AudioGraph::new(
+        synth.clone(),
+        params,
+        consumer,
+        Arc::new(AudioClock::new()),
+        Arc::new(AudioMeters::new()),
+        48_000,
+        1024,
+    )
+    .with_late_event_stats(stats.clone());
+    if let Some(ms) = late_drop_ms {
+        graph = graph.with_late_drop_ms(ms);
+    }
+    render_blocks(&mut graph, 0, 30);
+
+    // 6680 samples (139 ms) late, then a chord and its release about 43 ms late.
+    for event in [
+        note_on(48, 1_000),
+        note_off(48, 5_600),
+        note_on(60, 5_610),
+        note_on(64, 5_620),
+        note_off(60, 5_700),
+    ] {
+        producer.push(event).expect("queue event");
+    }
+    render_blocks(&mut graph, 30 * 256, 1);
+    let events = synth.events.lock().unwrap().clone();
+    (events, stats)
+}
+
+#[test]
+fn late_events_keep_their_order_and_spacing() {
+    let (events, stats) = play_stale_batch(None);
+
+    // The 139 ms late NoteOn is dropped; its NoteOff still plays.
+    assert_eq!(
+        events,
+        vec![
+            (MidiLikeEvent::NoteOff { note: 48 }, 7_680),
+            (note_on(60, 0).event, 7_690),
+            (note_on(64, 0).event, 7_700),
+            (MidiLikeEvent::NoteOff { note: 60 }, 7_780),
+        ]
+    );
+
+    let counts = stats.snapshot();
+    assert_eq!(counts.late_events, 5);
+    assert_eq!(counts.dropped_note_ons, 1);
+    assert!((counts.max_lateness_ms - 6_680.0 / 48.0).abs() < 0.01);
+}
+
+#[test]
+fn late_drop_threshold_is_configurable() {
+    let (events, stats) = play_stale_batch(Some(200.0));
+
+    // Spacing that no longer fits in the block collapses onto its last sample, in order.
+    assert_eq!(
+        events,
+        vec![
+            (note_on(48, 0).event, 7_680),
+            (MidiLikeEvent::NoteOff { note: 48 }, 7_935),
+            (note_on(60, 0).event, 7_935),
+            (note_on(64, 0).event, 7_935),
+            (MidiLikeEvent::NoteOff { note: 60 }, 7_935),
+        ]
+    );
+    assert_eq!(stats.snapshot().dropped_note_ons, 0);
+}
+
+#[test]
+fn events_from_an_older_generation_never_reach_the_synth() {
+    let synth = Arc::new(EventLogSynth::default());
+    let params = Arc::new(AudioParams::new(&SettingsDto::default()));
+    params.set_playback_enabled(true);
+    let (mut producer, consumer) = RingBuffer::<ScheduledEvent>::new(16);
+    let mut graph = AudioGraph::new(
+        synth.clone(),
+        params.clone(),
+        consumer,
+        Arc::new(AudioClock::new()),
+        Arc::new(AudioMeters::new()),
+        48_000,
+        1024,
+    );
+    producer.push(note_on(60, 100)).expect("queue");
+    producer.push(note_off(60, 2_000)).expect("queue");
+    // The NoteOff is held back as the graph's next event.
+    render_blocks(&mut graph, 0, 2);
+
+    // A seek: the rest of the old generation is still queued, in the future.
+    for event in [note_on(62, 1_000), note_off(62, 1_200)] {
+        producer.push(event).expect("queue");
+    }
+    params.set_event_generation(1);
+    for event in [note_on(67, 800), note_off(67, 1_100)] {
+        producer
+            .push(ScheduledEvent {
+                generation: 1,
+                ..event
+            })
+            .expect("queue");
+    }
+    render_blocks(&mut graph, 512, 8);
+
+    assert_eq!(
+        synth.events.lock().unwrap().clone(),
+        vec![
+            (note_on(60, 0).event, 100),
+            (note_on(67, 0).event, 800),
+            (MidiLikeEvent::NoteOff { note: 67 }, 1_100),
+        ]
+    );
+}
@@ -0,0 +1,55 @@
+use cadenza_core::AudioParams;
+use cadenza_ports::storage::SettingsDto;
+use cadenza_ports::types::{Bus, Volume01, VolumeCurve};
+
+#[test]
+fn db60_curve_endpoints_and_midpoint() {
+    let curve = VolumeCurve::Db60;
+    assert_eq!(curve.gain(Volume01::new(0.0)), 0.0);
+    assert!((curve.gain(Volume01::new(1.0)) - 1.0).abs() < 1e-6);
+    // Halfway up the fader is -30 dB.
+    let midpoint_db = 20.0 * curve.gain(Volume01::new(0.5)).log10();
+    assert!(
+        (midpoint_db + 30.0).abs() < 1e-3,
+        "midpoint {midpoint_db} dB"
+    );
+    // Just above the bottom stop is the -60 dB floor.
+    let floor_db = 20.0 * curve.gain(Volume01::new(1e-6)).log10();
+    assert!((floor_db + 60.0).abs() < 1e-2, "floor {floor_db} dB");
+}
+
+#[test]
+fn volume_for_gain_inverts_the_curve() {
+    for curve in [VolumeCurve::Linear, VolumeCurve::Db60] {
+        for v in [0.0, 0.25, 0.5, 0.8, 1.0] {
+            let back = curve.volume_for_gain(curve.gain(Volume01::new(v)));
+            assert!(
+                (back.get() - v).abs() < 1e-5,
+                "{curve:?} {v} -> {}",
+                back.get()
+            );
+        }
+    }
+}
+
+#[test]
+fn default_settings_are_as_loud_as_the_old_linear_defaults() {
+    let params = AudioParams::new(&SettingsDto::default());
+    assert!((params.master() - 0.8).abs() < 1e-4);
+    assert!((params.bus(Bus::UserMonitor) - 0.8).abs() < 1e-4);
+}
+
+#[test]
+fn settings_saved_before_curves_keep_linear_gain() {
+    let legacy = r#"{ "master_volume": 0.8, "bus_user_volume": 0.5 }"#;
+    let mut settings: SettingsDto = serde_json::from_str(legacy).expect("parse settings");
+    assert_eq!(settings.volume_curve, VolumeCurve::Linear);
+    let params = AudioParams::new(&settings);
+    assert!((params.master() - 0.8).abs() < 1e-6);
+
+    settings.convert_volume_curve(VolumeCurve::Db60);
+    let converted = AudioParams::new(&settings);
+    assert_eq!(converted.volume_curve(), VolumeCurve::Db60);
+    assert!((converted.master() - 0.8).abs() < 1e-4);
+    assert!((converted.bus(Bus::UserMonitor) - 0.5).abs() < 1e-4);
+}
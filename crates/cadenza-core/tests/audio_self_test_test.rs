@@ -0,0 +1,209 @@
+use cadenza_core::{AppCore, Command, Event, SelfTestStageKind, SelfTestStatus};
+use cadenza_ports::audio::{AudioError, AudioOutputPort, AudioRenderCallback, AudioStreamHandle};
+use cadenza_ports::midi::{
+    MidiError, MidiInputPort, MidiInputStream, MidiLikeEvent, PlayerEventCallback,
+};
+use cadenza_ports::synth::{SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::types::{
+    AudioConfig, AudioOutputDevice, Bus, DeviceId, MidiInputDevice, SampleTime, Volume01,
+};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+type SharedCallback = Arc<Mutex<Option<Box<dyn AudioRenderCallback>>>>;
+
+/// Output port whose "device" is the test rendering blocks by hand.
+struct FakeAudioPort {
+    callback: SharedCallback,
+}
+
+struct FakeStream;
+
+impl AudioStreamHandle for FakeStream {
+    fn close(self: Box<Self>) {}
+}
+
+impl AudioOutputPort for FakeAudioPort {
+    fn list_outputs(&self) -> Result<Vec<AudioOutputDevice>, AudioError> {
+        Ok(vec![AudioOutputDevice {
+            id: DeviceId("fake".to_string()),
+            name: "Fake Output".to_string(),
+            default_config: AudioConfig {
+                sample_rate_hz: 48_000,
+                channels: 2,
+                buffer_size_frames: Some(480),
+            },
+            buffer_size_range: None,
+        }])
+    }
+
+    fn open_output(
+        &self,
+        _device_id: &DeviceId,
+        _config: AudioConfig,
+        cb: Box<dyn AudioRenderCallback>,
+    ) -> Result<Box<dyn AudioStreamHandle>, AudioError> {
+        *self.callback.lock() = Some(cb);
+        Ok(Box::new(FakeStream))
+    }
+}
+
+struct NoMidi;
+
+impl MidiInputPort for NoMidi {
+    fn list_inputs(&self) -> Result<Vec<MidiInputDevice>, MidiError> {
+        Ok(Vec::new())
+    }
+
+    fn open_input(
+        &self,
+        device_id: &DeviceId,
+        _cb: PlayerEventCallback,
+    ) -> Result<Box<dyn MidiInputStream>, MidiError> {
+        Err(MidiError::DeviceNotFound(device_id.to_string()))
+    }
+}
+
+/// Plays a constant level on each bus while a note is held there.
+#[derive(Default)]
+struct HeldNoteSynth {
+    held: [AtomicBool; 3],
+}
+
+fn bus_index(bus: Bus) -> usize {
+    match bus {
+        Bus::UserMonitor => 0,
+        Bus::Autopilot => 1,
+        Bus::MetronomeFx => 2,
+    }
+}
+
+impl SynthPort for HeldNoteSynth {
+    fn load_soundfont_from_path(&self, _path: &str) -> Result<SoundFontInfo, SynthError> {
+        Err(SynthError::UnsupportedFormat)
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, _bus: Bus, _gm_program: u8) -> Result<(), SynthError> {
+        Ok(())
+    }
+
+    fn handle_event(&self, bus: Bus, event: MidiLikeEvent, _at: SampleTime) {
+        let held = matches!(event, MidiLikeEvent::NoteOn { .. });
+        self.held[bus_index(bus)].store(held, Ordering::Relaxed);
+    }
+
+    fn render(&self, bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        let held = self.held[bus_index(bus)].load(Ordering::Relaxed);
+        let value = if held { 0.3 } else { 0.0 };
+        out_l[..frames].fill(value);
+        out_r[..frames].fill(value);
+    }
+}
+
+fn core_with_fake_output() -> (AppCore, SharedCallback) {
+    let callback: SharedCallback = Arc::new(Mutex::new(None));
+    let core = AppCore::new(
+        Box::new(FakeAudioPort {
+            callback: callback.clone(),
+        }),
+        Box::new(NoMidi),
+        Arc::new(HeldNoteSynth::default()),
+        None,
+        None,
+    )
+    .expect("core");
+    (core, callback)
+}
+
+/// Renders audio and ticks the core until the self-test reports, at most two seconds of audio.
+fn run_self_test(core: &mut AppCore, callback: &SharedCallback) -> (bool, Vec<Event>) {
+    core.handle_command(Command::RunAudioSelfTest)
+        .expect("start self-test");
+    let mut out_l = vec![0.0; 480];
+    let mut out_r = vec![0.0; 480];
+    for block in 0..200u64 {
+        if let Some(cb) = callback.lock().as_mut() {
+            cb.render(block * 480, &mut out_l, &mut out_r);
+        }
+        core.tick();
+        let events = core.drain_events();
+        if let Some(passed) = events.iter().find_map(|event| match event {
+            Event::AudioSelfTestResult { passed, .. } => Some(*passed),
+            _ => None,
+        }) {
+            return (passed, events);
+        }
+    }
+    panic!("self-test did not finish");
+}
+
+fn stage_status(events: &[Event], stage: SelfTestStageKind, bus: Option<Bus>) -> SelfTestStatus {
+    events
+        .iter()
+        .find_map(|event| match event {
+            Event::AudioSelfTestResult { stages, .. } => stages
+                .iter()
+                .find(|result| result.stage == stage && result.bus == bus)
+                .map(|result| result.status),
+            _ => None,
+        })
+        .unwrap_or_else(|| panic!("no {stage:?} result for {bus:?}"))
+}
+
+#[test]
+fn self_test_passes_every_stage_on_a_working_path() {
+    let (mut core, callback) = core_with_fake_output();
+    let (passed, events) = run_self_test(&mut core, &callback);
+
+    assert!(passed);
+    assert_eq!(
+        stage_status(&events, SelfTestStageKind::Clock, None),
+        SelfTestStatus::Passed
+    );
+    for bus in [Bus::UserMonitor, Bus::Autopilot, Bus::MetronomeFx] {
+        assert_eq!(
+            stage_status(&events, SelfTestStageKind::Mixing, Some(bus)),
+            SelfTestStatus::Passed
+        );
+    }
+}
+
+#[test]
+fn self_test_pinpoints_a_muted_bus() {
+    let (mut core, callback) = core_with_fake_output();
+    core.handle_command(Command::SetBusVolume {
+        bus: Bus::Autopilot,
+        volume: Volume01::new(0.0),
+    })
+    .expect("mute autopilot");
+
+    let (passed, events) = run_self_test(&mut core, &callback);
+
+    assert!(!passed);
+    assert_eq!(
+        stage_status(
+            &events,
+            SelfTestStageKind::ParamsGating,
+            Some(Bus::Autopilot)
+        ),
+        SelfTestStatus::Failed
+    );
+    assert_eq!(
+        stage_status(
+            &events,
+            SelfTestStageKind::SynthRender,
+            Some(Bus::Autopilot)
+        ),
+        SelfTestStatus::Skipped
+    );
+    // The buses on either side are still checked.
+    for bus in [Bus::UserMonitor, Bus::MetronomeFx] {
+        assert_eq!(
+            stage_status(&events, SelfTestStageKind::SynthRender, Some(bus)),
+            SelfTestStatus::Passed
+        );
+    }
+}
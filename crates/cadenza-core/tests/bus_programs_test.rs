@@ -0,0 +1,194 @@
+use cadenza_core::{AppCore, Command, Event};
+use cadenza_ports::audio::{AudioError, AudioOutputPort, AudioRenderCallback, AudioStreamHandle};
+use cadenza_ports::midi::{
+    MidiError, MidiInputPort, MidiInputStream, MidiLikeEvent, PlayerEventCallback,
+};
+use cadenza_ports::storage::{SettingsDto, StorageError, StoragePort};
+use cadenza_ports::synth::{SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::types::{
+    AudioConfig, AudioOutputDevice, Bus, DeviceId, MidiInputDevice, SampleTime,
+};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+struct Speakers;
+
+struct FakeAudioStream;
+
+impl AudioStreamHandle for FakeAudioStream {
+    fn close(self: Box<Self>) {}
+}
+
+impl AudioOutputPort for Speakers {
+    fn list_outputs(&self) -> Result<Vec<AudioOutputDevice>, AudioError> {
+        Ok(vec![AudioOutputDevice {
+            id: DeviceId("speakers".to_string()),
+            name: "Speakers".to_string(),
+            default_config: AudioConfig {
+                sample_rate_hz: 44_100,
+                channels: 2,
+                buffer_size_frames: Some(256),
+            },
+            buffer_size_range: None,
+        }])
+    }
+
+    fn open_output(
+        &self,
+        _device_id: &DeviceId,
+        _config: AudioConfig,
+        _cb: Box<dyn AudioRenderCallback>,
+    ) -> Result<Box<dyn AudioStreamHandle>, AudioError> {
+        Ok(Box::new(FakeAudioStream))
+    }
+}
+
+struct NoMidi;
+
+struct FakeMidiStream;
+
+impl MidiInputStream for FakeMidiStream {
+    fn close(self: Box<Self>) {}
+}
+
+impl MidiInputPort for NoMidi {
+    fn list_inputs(&self) -> Result<Vec<MidiInputDevice>, MidiError> {
+        Ok(Vec::new())
+    }
+
+    fn open_input(
+        &self,
+        _device_id: &DeviceId,
+        _cb: PlayerEventCallback,
+    ) -> Result<Box<dyn MidiInputStream>, MidiError> {
+        Ok(Box::new(FakeMidiStream))
+    }
+}
+
+/// Accepts any soundfont and remembers every program change, in order.
+#[derive(Clone, Default)]
+struct ProgramSynth {
+    programs: Arc<Mutex<Vec<(Bus, u8)>>>,
+}
+
+impl SynthPort for ProgramSynth {
+    fn load_soundfont_from_path(&self, path: &str) -> Result<SoundFontInfo, SynthError> {
+        Ok(SoundFontInfo {
+            name: path.to_string(),
+            preset_count: 128,
+        })
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, bus: Bus, gm_program: u8) -> Result<(), SynthError> {
+        self.programs.lock().push((bus, gm_program));
+        Ok(())
+    }
+
+    fn handle_event(&self, _bus: Bus, _event: MidiLikeEvent, _at: SampleTime) {}
+
+    fn render(&self, _bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        out_l[..frames].fill(0.0);
+        out_r[..frames].fill(0.0);
+    }
+}
+
+#[derive(Clone, Default)]
+struct MemoryStorage {
+    settings: Arc<Mutex<SettingsDto>>,
+}
+
+impl StoragePort for MemoryStorage {
+    fn load_settings(&self) -> Result<SettingsDto, StorageError> {
+        Ok(self.settings.lock().clone())
+    }
+
+    fn save_settings(&self, s: &SettingsDto) -> Result<(), StorageError> {
+        *self.settings.lock() = s.clone();
+        Ok(())
+    }
+}
+
+fn launch(storage: &MemoryStorage, synth: &ProgramSynth) -> AppCore {
+    AppCore::new(
+        Box::new(Speakers),
+        Box::new(NoMidi),
+        Arc::new(synth.clone()),
+        None,
+        Some(Box::new(storage.clone())),
+    )
+    .expect("core")
+}
+
+#[test]
+fn saved_programs_are_applied_at_startup_and_after_every_reload() {
+    let storage = MemoryStorage::default();
+    {
+        let mut settings = storage.settings.lock();
+        settings.default_sf2_path = Some("piano.sf2".to_string());
+        settings.bus_programs.insert(Bus::UserMonitor, 4);
+    }
+    let synth = ProgramSynth::default();
+    let mut core = launch(&storage, &synth);
+    assert_eq!(
+        std::mem::take(&mut *synth.programs.lock()),
+        vec![(Bus::UserMonitor, 4)]
+    );
+
+    core.handle_command(Command::LoadSoundFont {
+        path: "strings.sf2".to_string(),
+    })
+    .expect("soundfont");
+    assert_eq!(
+        std::mem::take(&mut *synth.programs.lock()),
+        vec![(Bus::UserMonitor, 4)]
+    );
+
+    // A new stream may change the sample rate and rebuild the synth.
+    core.handle_command(Command::SelectAudioOutput {
+        device_id: DeviceId("speakers".to_string()),
+        config: None,
+    })
+    .expect("audio");
+    assert_eq!(
+        std::mem::take(&mut *synth.programs.lock()),
+        vec![(Bus::UserMonitor, 4)]
+    );
+}
+
+#[test]
+fn program_changes_are_saved_and_reported() {
+    let storage = MemoryStorage::default();
+    let synth = ProgramSynth::default();
+    let mut core = launch(&storage, &synth);
+    core.drain_events();
+
+    core.handle_command(Command::SetProgram {
+        bus: Bus::Autopilot,
+        gm_program: 48,
+    })
+    .expect("program");
+    core.flush_settings();
+
+    let reported = core
+        .drain_events()
+        .into_iter()
+        .find_map(|event| match event {
+            Event::SessionStateUpdated { settings, .. } => Some(settings.bus_programs),
+            _ => None,
+        });
+    assert_eq!(
+        reported.and_then(|programs| programs.get(&Bus::Autopilot).copied()),
+        Some(48)
+    );
+    assert_eq!(
+        storage.settings.lock().bus_programs.get(&Bus::Autopilot),
+        Some(&48)
+    );
+
+    // The next launch starts with it.
+    let next_synth = ProgramSynth::default();
+    launch(&storage, &next_synth);
+    assert_eq!(*next_synth.programs.lock(), vec![(Bus::Autopilot, 48)]);
+}
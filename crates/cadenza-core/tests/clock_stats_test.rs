@@ -0,0 +1,84 @@
+use cadenza_core::ClockStats;
+use cadenza_ports::types::SampleTime;
+use std::time::{Duration, Instant};
+
+const SAMPLE_RATE_HZ: u32 = 48_000;
+const BLOCK: u64 = 512;
+/// The core refreshes its anchor every 16 ms tick.
+const TICK_US: u64 = 16_000;
+
+/// Anchors as the core takes them: the audio clock only moves a block at a time, running
+/// `drift_ppm` fast, and each reading lands up to `jitter_us` late.
+fn feed(stats: &mut ClockStats, start: Instant, secs: u64, drift_ppm: f64, jitter_us: u64) {
+    let rate = SAMPLE_RATE_HZ as f64 * (1.0 + drift_ppm * 1e-6);
+    for tick in 0..secs * 1_000_000 / TICK_US {
+        let wall_us = tick * TICK_US;
+        // Deterministic spread between 0 and jitter_us.
+        let late_us = (tick * 7919) % (jitter_us + 1);
+        let rendered = (wall_us as f64 / 1e6 * rate) as u64;
+        let clock: SampleTime = rendered / BLOCK * BLOCK;
+        stats.record_anchor(start + Duration::from_micros(wall_us + late_us), clock);
+    }
+}
+
+#[test]
+fn stats_wait_for_enough_anchors() {
+    let mut stats = ClockStats::new(SAMPLE_RATE_HZ);
+    feed(&mut stats, Instant::now(), 1, 0.0, 0);
+    assert!(stats.snapshot().is_none());
+}
+
+#[test]
+fn a_steady_clock_reports_little_drift_and_block_sized_jitter() {
+    let mut stats = ClockStats::new(SAMPLE_RATE_HZ);
+    feed(&mut stats, Instant::now(), 10, 0.0, 0);
+    let snapshot = stats.snapshot().expect("stats");
+
+    assert!(snapshot.clock_drift_ppm.abs() < 200.0, "{snapshot:?}");
+    // One 512-frame block is 10.7 ms; readings fall evenly within it.
+    assert!(
+        (2.0..5.0).contains(&snapshot.input_jitter_ms),
+        "{snapshot:?}"
+    );
+    assert!(!snapshot.drift_warning);
+}
+
+#[test]
+fn injected_drift_is_measured_and_warned_about_once() {
+    let mut stats = ClockStats::new(SAMPLE_RATE_HZ);
+    let start = Instant::now();
+    // 44.1 kHz hardware behind a stream believed to run at 48 kHz.
+    let drift_ppm = (44_100.0 / 48_000.0 - 1.0) * 1e6;
+    feed(&mut stats, start, 10, drift_ppm, 0);
+    let first = stats.snapshot().expect("stats");
+    assert!(
+        (first.clock_drift_ppm - drift_ppm).abs() < 500.0,
+        "{first:?}"
+    );
+    assert!(first.drift_warning);
+    assert!(!stats.snapshot().expect("stats").drift_warning);
+
+    // A new stream starts over and warns about its own drift.
+    stats.reset(SAMPLE_RATE_HZ);
+    feed(&mut stats, start + Duration::from_secs(20), 10, 2_500.0, 0);
+    let small = stats.snapshot().expect("stats");
+    assert!((small.clock_drift_ppm - 2_500.0).abs() < 300.0, "{small:?}");
+    assert!(small.drift_warning);
+}
+
+#[test]
+fn late_anchor_readings_raise_the_jitter() {
+    let start = Instant::now();
+    let mut steady = ClockStats::new(SAMPLE_RATE_HZ);
+    feed(&mut steady, start, 10, 0.0, 0);
+    let mut jittery = ClockStats::new(SAMPLE_RATE_HZ);
+    feed(&mut jittery, start, 10, 0.0, 15_000);
+
+    let steady = steady.snapshot().expect("stats");
+    let jittery = jittery.snapshot().expect("stats");
+    assert!(
+        jittery.input_jitter_ms > steady.input_jitter_ms + 1.0,
+        "{steady:?} {jittery:?}"
+    );
+    assert!(jittery.clock_drift_ppm.abs() < 500.0, "{jittery:?}");
+}
@@ -0,0 +1,153 @@
+use cadenza_core::{AppCore, Command, Event, ScoreSource};
+use cadenza_ports::audio::{AudioError, AudioOutputPort, AudioRenderCallback, AudioStreamHandle};
+use cadenza_ports::midi::{
+    MidiError, MidiInputPort, MidiInputStream, MidiLikeEvent, PlayerEventCallback,
+};
+use cadenza_ports::synth::{SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::types::{
+    AudioConfig, AudioOutputDevice, Bus, DeviceId, MidiInputDevice, SampleTime,
+};
+use std::sync::Arc;
+
+/// One output, "speakers"; any other id is unknown.
+struct SpeakersOnly;
+
+struct FakeAudioStream;
+
+impl AudioStreamHandle for FakeAudioStream {
+    fn close(self: Box<Self>) {}
+}
+
+impl AudioOutputPort for SpeakersOnly {
+    fn list_outputs(&self) -> Result<Vec<AudioOutputDevice>, AudioError> {
+        Ok(vec![AudioOutputDevice {
+            id: DeviceId("speakers".to_string()),
+            name: "Speakers".to_string(),
+            default_config: AudioConfig {
+                sample_rate_hz: 48_000,
+                channels: 2,
+                buffer_size_frames: Some(256),
+            },
+            buffer_size_range: None,
+        }])
+    }
+
+    fn open_output(
+        &self,
+        device_id: &DeviceId,
+        _config: AudioConfig,
+        _cb: Box<dyn AudioRenderCallback>,
+    ) -> Result<Box<dyn AudioStreamHandle>, AudioError> {
+        if device_id.0 != "speakers" {
+            return Err(AudioError::DeviceNotFound(device_id.to_string()));
+        }
+        Ok(Box::new(FakeAudioStream))
+    }
+}
+
+struct NoMidi;
+
+impl MidiInputPort for NoMidi {
+    fn list_inputs(&self) -> Result<Vec<MidiInputDevice>, MidiError> {
+        Ok(Vec::new())
+    }
+
+    fn open_input(
+        &self,
+        device_id: &DeviceId,
+        _cb: PlayerEventCallback,
+    ) -> Result<Box<dyn MidiInputStream>, MidiError> {
+        Err(MidiError::DeviceNotFound(device_id.to_string()))
+    }
+}
+
+struct SilentSynth;
+
+impl SynthPort for SilentSynth {
+    fn load_soundfont_from_path(&self, _path: &str) -> Result<SoundFontInfo, SynthError> {
+        Err(SynthError::UnsupportedFormat)
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, _bus: Bus, _gm_program: u8) -> Result<(), SynthError> {
+        Ok(())
+    }
+
+    fn handle_event(&self, _bus: Bus, _event: MidiLikeEvent, _at: SampleTime) {}
+
+    fn render(&self, _bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        out_l[..frames].fill(0.0);
+        out_r[..frames].fill(0.0);
+    }
+}
+
+fn core() -> AppCore {
+    let mut core = AppCore::new(
+        Box::new(SpeakersOnly),
+        Box::new(NoMidi),
+        Arc::new(SilentSynth),
+        None,
+        None,
+    )
+    .expect("core");
+    core.drain_events();
+    core
+}
+
+/// The `(command_kind, message, recoverable)` of every `CommandFailed` event.
+fn failures(core: &mut AppCore) -> Vec<(String, String, bool)> {
+    core.drain_events()
+        .into_iter()
+        .filter_map(|event| match event {
+            Event::CommandFailed {
+                command_kind,
+                message,
+                recoverable,
+            } => Some((command_kind, message, recoverable)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn loading_a_missing_score_file_reports_the_failed_command() {
+    let mut core = core();
+    let result = core.handle_command(Command::LoadScore {
+        source: ScoreSource::MidiFile("/nonexistent/cadenza/missing.mid".to_string()),
+    });
+
+    let err = result.expect_err("missing file");
+    let failed = failures(&mut core);
+    assert_eq!(failed.len(), 1, "{failed:?}");
+    let (kind, message, recoverable) = &failed[0];
+    assert_eq!(kind, "LoadScore");
+    assert_eq!(message, &err.to_string());
+    assert!(message.contains("missing.mid"), "{message}");
+    assert!(recoverable);
+}
+
+#[test]
+fn selecting_an_unknown_audio_output_reports_the_failed_command() {
+    let mut core = core();
+    let result = core.handle_command(Command::SelectAudioOutput {
+        device_id: DeviceId("bogus".to_string()),
+        config: None,
+    });
+
+    assert!(result.is_err());
+    let failed = failures(&mut core);
+    assert_eq!(failed.len(), 1, "{failed:?}");
+    let (kind, message, recoverable) = &failed[0];
+    assert_eq!(kind, "SelectAudioOutput");
+    assert!(message.contains("bogus"), "{message}");
+    assert!(recoverable);
+
+    // Succeeding commands report nothing.
+    core.handle_command(Command::SelectAudioOutput {
+        device_id: DeviceId("speakers".to_string()),
+        config: None,
+    })
+    .expect("speakers");
+    assert!(failures(&mut core).is_empty());
+}
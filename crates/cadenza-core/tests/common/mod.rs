@@ -0,0 +1,246 @@
+//! Fixture shared by the core's integration tests: an output whose audio callback the test
+//! drives by hand, a keyboard it plays by hand, in-memory settings, a synth that logs its
+//! notes, and a core wired to them with the demo scale loaded. Each test binary uses a
+//! different part of it.
+#![allow(dead_code)]
+
+use cadenza_core::{AppCore, Command, ScoreSource, SilentSynth};
+use cadenza_ports::audio::{AudioError, AudioOutputPort, AudioRenderCallback, AudioStreamHandle};
+use cadenza_ports::midi::{
+    MidiError, MidiInputPort, MidiInputStream, MidiLikeEvent, PlayerEvent, PlayerEventCallback,
+};
+use cadenza_ports::storage::{SettingsDto, StorageError, StoragePort};
+use cadenza_ports::synth::{SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::types::{
+    AudioConfig, AudioOutputDevice, Bus, DeviceId, MidiInputDevice, SampleTime,
+};
+use parking_lot::Mutex;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Frames per block a [`Rig`] renders.
+pub const BLOCK: usize = 512;
+/// One quarter of the demo scale, 120 bpm at 48 kHz.
+pub const QUARTER_SAMPLES: SampleTime = 24_000;
+
+pub type SharedRender = Arc<Mutex<Option<Box<dyn AudioRenderCallback>>>>;
+pub type SharedMidiCallback = Arc<Mutex<Option<PlayerEventCallback>>>;
+
+/// Output whose audio callback the test drives by hand, `block` frames at a time.
+pub struct ManualAudio {
+    pub render: SharedRender,
+    pub block: usize,
+}
+
+pub struct ManualAudioStream;
+
+impl AudioStreamHandle for ManualAudioStream {
+    fn close(self: Box<Self>) {}
+}
+
+impl AudioOutputPort for ManualAudio {
+    fn list_outputs(&self) -> Result<Vec<AudioOutputDevice>, AudioError> {
+        Ok(vec![AudioOutputDevice {
+            id: DeviceId("manual".to_string()),
+            name: "Manual".to_string(),
+            default_config: AudioConfig {
+                sample_rate_hz: 48_000,
+                channels: 2,
+                buffer_size_frames: Some(self.block as u32),
+            },
+            buffer_size_range: None,
+        }])
+    }
+
+    fn open_output(
+        &self,
+        _device_id: &DeviceId,
+        _config: AudioConfig,
+        cb: Box<dyn AudioRenderCallback>,
+    ) -> Result<Box<dyn AudioStreamHandle>, AudioError> {
+        *self.render.lock() = Some(cb);
+        Ok(Box::new(ManualAudioStream))
+    }
+}
+
+/// Keyboard whose callback the test calls by hand.
+pub struct FakeMidi {
+    pub callback: SharedMidiCallback,
+}
+
+pub struct FakeMidiStream;
+
+impl MidiInputStream for FakeMidiStream {
+    fn close(self: Box<Self>) {}
+}
+
+impl MidiInputPort for FakeMidi {
+    fn list_inputs(&self) -> Result<Vec<MidiInputDevice>, MidiError> {
+        Ok(Vec::new())
+    }
+
+    fn open_input(
+        &self,
+        _device_id: &DeviceId,
+        cb: PlayerEventCallback,
+    ) -> Result<Box<dyn MidiInputStream>, MidiError> {
+        *self.callback.lock() = Some(cb);
+        Ok(Box::new(FakeMidiStream))
+    }
+}
+
+/// Settings kept in memory, shared with the test.
+#[derive(Clone, Default)]
+pub struct MemoryStorage {
+    pub settings: Arc<Mutex<SettingsDto>>,
+}
+
+impl StoragePort for MemoryStorage {
+    fn load_settings(&self) -> Result<SettingsDto, StorageError> {
+        Ok(self.settings.lock().clone())
+    }
+
+    fn save_settings(&self, s: &SettingsDto) -> Result<(), StorageError> {
+        *self.settings.lock() = s.clone();
+        Ok(())
+    }
+}
+
+/// Plays a constant level on a bus while any of its notes is held, and logs the NoteOns.
+#[derive(Default)]
+pub struct HeldNoteSynth {
+    pub held: Mutex<HashSet<(Bus, u8)>>,
+    pub note_ons: Mutex<Vec<(Bus, u8, SampleTime)>>,
+}
+
+impl SynthPort for HeldNoteSynth {
+    fn load_soundfont_from_path(&self, _path: &str) -> Result<SoundFontInfo, SynthError> {
+        Err(SynthError::UnsupportedFormat)
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, _bus: Bus, _gm_program: u8) -> Result<(), SynthError> {
+        Ok(())
+    }
+
+    fn handle_event(&self, bus: Bus, event: MidiLikeEvent, at: SampleTime) {
+        match event {
+            MidiLikeEvent::NoteOn { note, .. } => {
+                self.held.lock().insert((bus, note));
+                self.note_ons.lock().push((bus, note, at));
+            }
+            MidiLikeEvent::NoteOff { note } => {
+                self.held.lock().remove(&(bus, note));
+            }
+            MidiLikeEvent::Cc64 { .. } | MidiLikeEvent::ControlChange { .. } => {}
+        }
+    }
+
+    fn render(&self, bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        let level = if self.held.lock().iter().any(|(held, _)| *held == bus) {
+            0.5
+        } else {
+            0.0
+        };
+        out_l[..frames].fill(level);
+        out_r[..frames].fill(level);
+    }
+}
+
+/// A core on a [`ManualAudio`] output and a [`FakeMidi`] keyboard.
+pub struct Rig {
+    pub core: AppCore,
+    pub render: SharedRender,
+    pub midi: SharedMidiCallback,
+    pub sample_time: SampleTime,
+}
+
+impl Rig {
+    /// The demo scale loaded, with a few blocks already rendered.
+    pub fn new() -> Self {
+        let mut rig = Self::with_synth(Arc::new(SilentSynth));
+        rig.warm_up();
+        rig.core.drain_events();
+        rig
+    }
+
+    /// The demo scale loaded over `synth`, with nothing rendered and no events drained yet.
+    pub fn with_synth(synth: Arc<dyn SynthPort>) -> Self {
+        let render: SharedRender = Arc::new(Mutex::new(None));
+        let midi: SharedMidiCallback = Arc::new(Mutex::new(None));
+        let mut core = AppCore::new(
+            Box::new(ManualAudio {
+                render: render.clone(),
+                block: BLOCK,
+            }),
+            Box::new(FakeMidi {
+                callback: midi.clone(),
+            }),
+            synth,
+            None,
+            None,
+        )
+        .expect("core");
+        core.handle_command(Command::SelectAudioOutput {
+            device_id: DeviceId("manual".to_string()),
+            config: None,
+        })
+        .expect("audio");
+        core.handle_command(Command::SelectMidiInput {
+            device_id: DeviceId("keyboard".to_string()),
+        })
+        .expect("midi");
+        core.handle_command(Command::LoadScore {
+            source: ScoreSource::InternalDemo("scale".to_string()),
+        })
+        .expect("score");
+        Self {
+            core,
+            render,
+            midi,
+            sample_time: 0,
+        }
+    }
+
+    /// Renders the few blocks it takes the output to get going.
+    pub fn warm_up(&mut self) {
+        for _ in 0..4 {
+            self.step();
+        }
+    }
+
+    /// Renders one block and ticks the core.
+    pub fn step(&mut self) {
+        self.render_to(self.sample_time + BLOCK as SampleTime);
+    }
+
+    /// Renders up to exactly `until`, ticking the core after each block.
+    pub fn render_to(&mut self, until: SampleTime) {
+        let mut left = [0.0; BLOCK];
+        let mut right = [0.0; BLOCK];
+        while self.sample_time < until {
+            let frames = (until - self.sample_time).min(BLOCK as SampleTime) as usize;
+            self.render.lock().as_mut().expect("audio opened").render(
+                self.sample_time,
+                &mut left[..frames],
+                &mut right[..frames],
+            );
+            self.sample_time += frames as SampleTime;
+            self.core.tick();
+        }
+    }
+
+    pub fn send(&self, event: MidiLikeEvent) {
+        let callback = self.midi.lock().clone().expect("midi opened");
+        callback(PlayerEvent {
+            at: Instant::now(),
+            event,
+        });
+    }
+
+    pub fn press(&self, note: u8) {
+        self.send(MidiLikeEvent::NoteOn { note, velocity: 80 });
+    }
+}
@@ -0,0 +1,89 @@
+use cadenza_core::{AppCore, Command, Event, NullAudioOutputPort, NullMidiInputPort};
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::storage::{SettingsDto, StorageError, StoragePort};
+use cadenza_ports::synth::{SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::types::{Bus, SampleTime};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+/// Remembers every decay scale it is given, in order.
+#[derive(Clone, Default)]
+struct DecaySynth {
+    scales: Arc<Mutex<Vec<f32>>>,
+}
+
+impl SynthPort for DecaySynth {
+    fn load_soundfont_from_path(&self, _path: &str) -> Result<SoundFontInfo, SynthError> {
+        Err(SynthError::UnsupportedFormat)
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, _bus: Bus, _gm_program: u8) -> Result<(), SynthError> {
+        Ok(())
+    }
+
+    fn handle_event(&self, _bus: Bus, _event: MidiLikeEvent, _at: SampleTime) {}
+
+    fn render(&self, _bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        out_l[..frames].fill(0.0);
+        out_r[..frames].fill(0.0);
+    }
+
+    fn set_decay_scale(&self, scale: f32) {
+        self.scales.lock().push(scale);
+    }
+}
+
+#[derive(Clone, Default)]
+struct MemoryStorage {
+    settings: Arc<Mutex<SettingsDto>>,
+}
+
+impl StoragePort for MemoryStorage {
+    fn load_settings(&self) -> Result<SettingsDto, StorageError> {
+        Ok(self.settings.lock().clone())
+    }
+
+    fn save_settings(&self, s: &SettingsDto) -> Result<(), StorageError> {
+        *self.settings.lock() = s.clone();
+        Ok(())
+    }
+}
+
+fn launch(synth: &DecaySynth, storage: &MemoryStorage) -> AppCore {
+    AppCore::new(
+        Box::new(NullAudioOutputPort),
+        Box::new(NullMidiInputPort),
+        Arc::new(synth.clone()),
+        None,
+        Some(Box::new(storage.clone())),
+    )
+    .expect("core")
+}
+
+#[test]
+fn the_decay_scale_is_saved_and_applied_on_the_next_start() {
+    let storage = MemoryStorage::default();
+    let synth = DecaySynth::default();
+    let mut core = launch(&synth, &storage);
+    assert_eq!(*synth.scales.lock(), vec![1.0]);
+
+    core.handle_command(Command::SetDecayScale { scale: 0.5 })
+        .expect("set");
+    assert!(core.drain_events().iter().any(|event| matches!(
+        event,
+        Event::SessionStateUpdated { settings, .. } if settings.decay_scale == 0.5
+    )));
+
+    // Out of range, so held to the longest the synth allows.
+    core.handle_command(Command::SetDecayScale { scale: 9.0 })
+        .expect("set");
+    assert_eq!(*synth.scales.lock(), vec![1.0, 0.5, 1.5]);
+    drop(core);
+    assert_eq!(storage.settings.lock().decay_scale, 1.5);
+
+    let restarted = DecaySynth::default();
+    let _core = launch(&restarted, &storage);
+    assert_eq!(*restarted.scales.lock(), vec![1.5]);
+}
@@ -0,0 +1,164 @@
+use cadenza_core::{
+    build_demo_score, internal_demos, Command, DemoDifficulty, Event, HeadlessRunner,
+    PianoRollPedalDto, ScoreSource, SilentSynth,
+};
+use cadenza_domain_score::{Hand, Score};
+use cadenza_ports::midi::MidiLikeEvent;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+fn demo(id: &str) -> Score {
+    build_demo_score(id).unwrap_or_else(|| panic!("demo {id}"))
+}
+
+fn note_ons(score: &Score, hand: Hand) -> Vec<u8> {
+    score
+        .tracks
+        .iter()
+        .flat_map(|track| &track.playback_events)
+        .filter(|event| event.hand == Some(hand))
+        .filter_map(|event| match event.event {
+            MidiLikeEvent::NoteOn { note, .. } => Some(note),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn every_demo_is_a_well_formed_score() {
+    let demos = internal_demos();
+    assert_eq!(demos.len(), 5);
+    assert_eq!(demos[0].id, "c_major_scale");
+    assert_eq!(demos[0].difficulty, DemoDifficulty::Beginner);
+
+    for info in &demos {
+        let score = demo(&info.id);
+        assert_eq!(score.meta.title.as_deref(), Some(info.title.as_str()));
+        assert!(!score.tracks.is_empty(), "{}", info.id);
+        for track in &score.tracks {
+            let events = &track.playback_events;
+            assert!(events.windows(2).all(|pair| pair[0].tick <= pair[1].tick));
+
+            // Every note ends after it starts, and ends once.
+            let mut open = HashMap::new();
+            for event in events {
+                match event.event {
+                    MidiLikeEvent::NoteOn { .. } => {
+                        assert!(open
+                            .insert(event.note_id.expect("id"), event.tick)
+                            .is_none());
+                    }
+                    MidiLikeEvent::NoteOff { .. } => {
+                        let start = open.remove(&event.note_id.expect("id")).expect("open");
+                        assert!(start < event.tick, "{}", info.id);
+                    }
+                    _ => {}
+                }
+            }
+            assert!(open.is_empty(), "{}", info.id);
+
+            // One target note per note on, in the track's hand.
+            let target_notes: usize = track.targets.iter().map(|t| t.notes.len()).sum();
+            let note_on_count = events
+                .iter()
+                .filter(|e| matches!(e.event, MidiLikeEvent::NoteOn { .. }))
+                .count();
+            assert_eq!(target_notes, note_on_count, "{}", info.id);
+            assert!(track.targets.iter().all(|t| t.hand == track.hand));
+            assert!(track
+                .targets
+                .iter()
+                .all(|t| t.note_ids.len() == t.notes.len()));
+        }
+    }
+    assert!(build_demo_score("fugue").is_none());
+}
+
+#[test]
+fn the_old_scale_ids_still_load_the_c_major_scale() {
+    for id in ["scale", "scale_c_major"] {
+        let score = demo(id);
+        assert_eq!(score.meta.title.as_deref(), Some("Demo: C major scale"));
+        assert_eq!(score.tracks[0].targets.len(), 8);
+    }
+}
+
+#[test]
+fn the_arpeggios_keep_the_left_hand_below_the_right() {
+    let score = demo("c_major_arpeggios");
+    let right = note_ons(&score, Hand::Right);
+    let left = note_ons(&score, Hand::Left);
+    assert_eq!(right.len(), 17);
+    assert_eq!(left.len(), right.len());
+    assert!(left.iter().zip(&right).all(|(l, r)| l + 12 == *r));
+}
+
+#[test]
+fn the_chord_drill_changes_pedal_every_bar() {
+    let mut runner = HeadlessRunner::new(Arc::new(SilentSynth), None).expect("runner");
+    runner
+        .load_score(ScoreSource::InternalDemo("chord_progression".to_string()))
+        .expect("load");
+    let pedal = runner
+        .core()
+        .drain_events()
+        .into_iter()
+        .find_map(|event| match event {
+            Event::ScoreViewUpdated { pedal, .. } => Some(pedal),
+            _ => None,
+        })
+        .expect("score view");
+    let spans: Vec<_> = pedal
+        .iter()
+        .map(
+            |PianoRollPedalDto {
+                 start_tick,
+                 end_tick,
+             }| (*start_tick, *end_tick),
+        )
+        .collect();
+    assert_eq!(
+        spans,
+        vec![(240, 1920), (2160, 3840), (4080, 5760), (6000, 7680)]
+    );
+}
+
+#[test]
+fn the_minuet_slows_down_for_its_last_two_bars() {
+    let score = demo("two_voice_minuet");
+    assert_eq!(score.time_signatures.len(), 1);
+    assert_eq!(score.time_signatures[0].numerator, 3);
+    assert_eq!(score.tempo_map.len(), 2);
+    assert_eq!(score.tempo_map[1].tick, 6 * 1440);
+    assert!(score.tempo_map[1].us_per_quarter > score.tempo_map[0].us_per_quarter);
+    assert_eq!(score.end_tick(), 8 * 1440);
+    assert_eq!(note_ons(&score, Hand::Left).len(), 8);
+}
+
+#[test]
+fn demos_are_listed_and_unknown_ids_fail_to_load() {
+    let mut runner = HeadlessRunner::new(Arc::new(SilentSynth), None).expect("runner");
+    runner
+        .core()
+        .handle_command(Command::ListInternalDemos)
+        .expect("list");
+    let listed = runner
+        .core()
+        .drain_events()
+        .into_iter()
+        .find_map(|event| match event {
+            Event::InternalDemosListed { demos } => Some(demos),
+            _ => None,
+        })
+        .expect("demos listed");
+    assert_eq!(listed, internal_demos());
+
+    for info in &listed {
+        runner
+            .load_score(ScoreSource::InternalDemo(info.id.clone()))
+            .expect("load");
+    }
+    assert!(runner
+        .load_score(ScoreSource::InternalDemo("fugue".to_string()))
+        .is_err());
+}
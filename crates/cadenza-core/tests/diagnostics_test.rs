@@ -0,0 +1,104 @@
+use cadenza_core::{
+    diag_log, export_diagnostics, recent_log_lines, AudioCallbackCounts, DiagnosticsSnapshot,
+    LateEventCounts, QueueDropCounts, SynthLoad, SynthStatus, TransportSnapshot, LOG_CAPACITY,
+};
+use cadenza_domain_eval::JudgeSnapshot;
+use cadenza_ports::midi::RawMidiMessage;
+use cadenza_ports::storage::SettingsDto;
+use std::io::Read;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn snapshot() -> DiagnosticsSnapshot {
+    DiagnosticsSnapshot {
+        settings: SettingsDto::default(),
+        midi_inputs: Vec::new(),
+        audio_outputs: Vec::new(),
+        recent_events: Vec::new(),
+        raw_midi_messages: vec![RawMidiMessage {
+            index: 7,
+            stamp_us: 1_500,
+            status: 0xE0,
+            data: vec![0x00, 0x48],
+        }],
+        audio_callbacks: Some(AudioCallbackCounts {
+            callbacks: 100,
+            overruns: 2,
+            mean_callback_us: 800,
+            max_callback_us: 6_000,
+            buffer_frames: 256,
+            buffer_latency_ms: 5.33,
+            synth_load: SynthLoad::default(),
+        }),
+        late_events: LateEventCounts::default(),
+        queue_drops: QueueDropCounts {
+            audio_queue: 3,
+            midi_queue: 0,
+            event_queue: 0,
+        },
+        synth: SynthStatus::default(),
+        transport: TransportSnapshot {
+            state: "Playing".to_string(),
+            tick: 960,
+            sample: 48_000,
+            tempo_multiplier: 1.0,
+            sample_rate_hz: 48_000,
+            lookahead_ms: 30.0,
+        },
+        judge: JudgeSnapshot::default(),
+    }
+}
+
+fn read_entry(archive: &mut zip::ZipArchive<std::fs::File>, name: &str) -> String {
+    let mut text = String::new();
+    archive
+        .by_name(name)
+        .unwrap_or_else(|_| panic!("missing {name}"))
+        .read_to_string(&mut text)
+        .expect("read entry");
+    text
+}
+
+#[test]
+fn export_bundles_logs_and_runtime_stats_into_a_zip() {
+    // The log is process-wide, so the rollover check shares this test.
+    for i in 0..LOG_CAPACITY + 10 {
+        diag_log!(Info, "rolling line {i}");
+    }
+    let lines = recent_log_lines();
+    assert_eq!(lines.len(), LOG_CAPACITY);
+    assert!(lines[0].ends_with("rolling line 10"));
+
+    diag_log!(Warn, "diagnostics test marker");
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let dir = std::env::temp_dir().join(format!("cadenza-diagnostics-{nanos}"));
+
+    let archive_path = export_diagnostics(&dir, &snapshot()).expect("export diagnostics");
+    assert_eq!(
+        archive_path,
+        dir.with_file_name(format!("cadenza-diagnostics-{nanos}.zip"))
+    );
+
+    let mut archive =
+        zip::ZipArchive::new(std::fs::File::open(&archive_path).expect("open zip")).expect("zip");
+    assert!(read_entry(&mut archive, "logs.txt").contains("WARN  diagnostics test marker"));
+    let stats: serde_json::Value =
+        serde_json::from_str(&read_entry(&mut archive, "runtime_stats.json")).expect("json");
+    assert_eq!(stats["audio_callbacks"]["overruns"], 2);
+    assert_eq!(stats["queue_drops"]["audio_queue"], 3);
+    let session: serde_json::Value =
+        serde_json::from_str(&read_entry(&mut archive, "session.json")).expect("json");
+    assert_eq!(session["transport"]["tick"], 960);
+    let recent: serde_json::Value =
+        serde_json::from_str(&read_entry(&mut archive, "recent_events.json")).expect("json");
+    assert_eq!(recent["raw_messages"][0]["status"], 0xE0);
+    assert_eq!(
+        recent["raw_messages"][0]["data"],
+        serde_json::json!([0x00, 0x48])
+    );
+
+    let _ = std::fs::remove_dir_all(&dir);
+    let _ = std::fs::remove_file(&archive_path);
+}
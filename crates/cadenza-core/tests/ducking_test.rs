@@ -0,0 +1,188 @@
+use cadenza_core::{AudioClock, AudioGraph, AudioMeters, AudioParams, DUCKING_HOLD_MS};
+use cadenza_ports::audio::AudioRenderCallback;
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::playback::ScheduledEvent;
+use cadenza_ports::storage::SettingsDto;
+use cadenza_ports::synth::{SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::types::{Bus, DuckingParams, SampleTime, Volume01};
+use rtrb::{Producer, RingBuffer};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// 5 ms blocks at 48 kHz.
+const BLOCK: usize = 240;
+const BLOCK_MS: f32 = 5.0;
+const AUTOPILOT_LEVEL: f32 = 0.5;
+
+/// A steady autopilot accompaniment, and a steady monitor level while the user holds a key.
+#[derive(Default)]
+struct DuetSynth {
+    user_held: AtomicBool,
+}
+
+impl SynthPort for DuetSynth {
+    fn load_soundfont_from_path(&self, _path: &str) -> Result<SoundFontInfo, SynthError> {
+        Err(SynthError::UnsupportedFormat)
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, _bus: Bus, _gm_program: u8) -> Result<(), SynthError> {
+        Ok(())
+    }
+
+    fn handle_event(&self, bus: Bus, event: MidiLikeEvent, _at: SampleTime) {
+        if bus == Bus::UserMonitor {
+            let held = matches!(event, MidiLikeEvent::NoteOn { .. });
+            self.user_held.store(held, Ordering::Relaxed);
+        }
+    }
+
+    fn render(&self, bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        let value = match bus {
+            Bus::Autopilot => AUTOPILOT_LEVEL,
+            Bus::UserMonitor if self.user_held.load(Ordering::Relaxed) => 0.2,
+            _ => 0.0,
+        };
+        out_l[..frames].fill(value);
+        out_r[..frames].fill(value);
+    }
+}
+
+struct Rig {
+    graph: AudioGraph,
+    params: Arc<AudioParams>,
+    meters: Arc<AudioMeters>,
+    producer: Producer<ScheduledEvent>,
+    block: usize,
+}
+
+impl Rig {
+    fn new(ducking: DuckingParams) -> Self {
+        let params = Arc::new(AudioParams::new(&SettingsDto::default()));
+        params.set_master(Volume01::new(1.0));
+        params.set_bus(Bus::UserMonitor, Volume01::new(1.0));
+        params.set_bus(Bus::Autopilot, Volume01::new(1.0));
+        params.set_playback_enabled(true);
+        params.set_ducking(ducking);
+        let meters = Arc::new(AudioMeters::new());
+        let (producer, consumer) = RingBuffer::<ScheduledEvent>::new(16);
+        let graph = AudioGraph::new(
+            Arc::new(DuetSynth::default()),
+            params.clone(),
+            consumer,
+            Arc::new(AudioClock::new()),
+            meters.clone(),
+            48_000,
+            1024,
+        );
+        Self {
+            graph,
+            params,
+            meters,
+            producer,
+            block: 0,
+        }
+    }
+
+    /// Queues a user key press or release at the start of block `block`.
+    fn user_key(&mut self, block: usize, down: bool) {
+        let event = if down {
+            MidiLikeEvent::NoteOn {
+                note: 60,
+                velocity: 100,
+            }
+        } else {
+            MidiLikeEvent::NoteOff { note: 60 }
+        };
+        self.producer
+            .push(ScheduledEvent {
+                sample_time: (block * BLOCK) as u64,
+                bus: Bus::UserMonitor,
+                event,
+                generation: 0,
+            })
+            .expect("queue event");
+    }
+
+    /// Renders `blocks` blocks and returns the autopilot gain measured in each, as its peak.
+    fn render(&mut self, blocks: usize) -> Vec<f32> {
+        let mut out_l = vec![0.0; BLOCK];
+        let mut out_r = vec![0.0; BLOCK];
+        (0..blocks)
+            .map(|_| {
+                self.graph
+                    .render((self.block * BLOCK) as u64, &mut out_l, &mut out_r);
+                self.block += 1;
+                self.meters.take().autopilot.peak / AUTOPILOT_LEVEL
+            })
+            .collect()
+    }
+}
+
+fn blocks_in(ms: f32) -> usize {
+    (ms / BLOCK_MS).ceil() as usize
+}
+
+#[test]
+fn the_autopilot_dips_while_the_user_plays_and_recovers_after() {
+    let ducking = DuckingParams {
+        enabled: true,
+        depth_db: 6.0,
+        attack_ms: 20.0,
+        release_ms: 300.0,
+    };
+    let floor = 10.0_f32.powf(-ducking.depth_db / 20.0);
+    let mut rig = Rig::new(ducking);
+    rig.user_key(20, true);
+    rig.user_key(60, false);
+    let gains = rig.render(200);
+
+    // Untouched before the user plays.
+    assert!(gains[..20].iter().all(|gain| (gain - 1.0).abs() < 1e-4));
+    // The dip is ramped, not a step, and reaches the depth within the attack time.
+    assert!(gains[21] > floor * 1.1, "{gains:?}");
+    let ducked_from = 20 + blocks_in(ducking.attack_ms);
+    for gain in &gains[ducked_from..61] {
+        assert!((gain - floor).abs() < floor * 0.02, "{gains:?}");
+    }
+    // Held for a moment after the key is let go, then released over the release time.
+    let released_from = 60 + blocks_in(DUCKING_HOLD_MS);
+    assert!(gains[released_from - 1] < floor * 1.02, "{gains:?}");
+    assert!(gains[released_from + blocks_in(ducking.release_ms) / 3] < 0.95);
+    let recovered_from = released_from + blocks_in(ducking.release_ms) + 1;
+    for gain in &gains[recovered_from..] {
+        assert!(*gain > 0.99, "{gains:?}");
+    }
+
+    // A second phrase ducks again.
+    rig.user_key(200, true);
+    let again = rig.render(blocks_in(ducking.attack_ms) + 2);
+    assert!(again
+        .last()
+        .is_some_and(|gain| (gain - floor).abs() < floor * 0.02));
+}
+
+#[test]
+fn nothing_ducks_while_ducking_is_off_or_the_monitor_is_muted() {
+    let mut rig = Rig::new(DuckingParams::default());
+    rig.user_key(10, true);
+    let gains = rig.render(40);
+    assert!(
+        gains.iter().all(|gain| (gain - 1.0).abs() < 1e-4),
+        "{gains:?}"
+    );
+
+    let mut rig = Rig::new(DuckingParams {
+        enabled: true,
+        ..DuckingParams::default()
+    });
+    rig.params.set_monitor_enabled(false);
+    rig.render(10);
+    rig.user_key(10, true);
+    let gains = rig.render(40);
+    assert!(
+        gains.iter().all(|gain| (gain - 1.0).abs() < 1e-4),
+        "{gains:?}"
+    );
+}
@@ -0,0 +1,137 @@
+use cadenza_core::{Command, Event, EventQueue};
+use cadenza_domain_eval::Grade;
+use cadenza_ports::midi::MidiLikeEvent;
+use std::thread;
+use std::time::Duration;
+
+mod common;
+
+use common::Rig;
+
+/// A glissando of 1000 events over ten keys.
+fn blast(rig: &Rig) {
+    for i in 0..500 {
+        let note = 90 + (i % 10) as u8;
+        rig.send(MidiLikeEvent::NoteOn { note, velocity: 80 });
+        rig.send(MidiLikeEvent::NoteOff { note });
+    }
+}
+
+#[test]
+fn an_input_storm_is_coalesced_and_still_judged() {
+    let mut rig = Rig::new();
+    rig.core
+        .handle_command(Command::StartPractice)
+        .expect("start");
+    blast(&rig);
+    rig.send(MidiLikeEvent::NoteOn {
+        note: 60,
+        velocity: 80,
+    });
+    // Past the 20 Hz emission interval.
+    thread::sleep(Duration::from_millis(60));
+    rig.step();
+    let events = rig.core.drain_events();
+
+    let live: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            Event::MidiInputEvent { event } => Some(*event),
+            _ => None,
+        })
+        .collect();
+    // Each tapped key keeps one press before its latest release; the held key only its press.
+    assert_eq!(live.len(), 21, "{live:?}");
+    let key_95: Vec<_> = live
+        .iter()
+        .filter(|event| {
+            matches!(
+                event,
+                MidiLikeEvent::NoteOn { note: 95, .. } | MidiLikeEvent::NoteOff { note: 95 }
+            )
+        })
+        .collect();
+    assert_eq!(
+        key_95,
+        vec![
+            &MidiLikeEvent::NoteOn {
+                note: 95,
+                velocity: 80
+            },
+            &MidiLikeEvent::NoteOff { note: 95 },
+        ]
+    );
+    assert!(live.contains(&MidiLikeEvent::NoteOn {
+        note: 60,
+        velocity: 80
+    }));
+    assert!(events.len() < 50, "{} events", events.len());
+
+    // The wrong notes still reached the judge, costing the perfect grade.
+    let judged: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            Event::JudgeFeedback {
+                expected_notes,
+                grade,
+                ..
+            } => Some((expected_notes.clone(), *grade)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(judged, vec![(vec![60], Grade::Good)]);
+}
+
+#[test]
+fn live_input_events_can_be_turned_off() {
+    let mut rig = Rig::new();
+    rig.core
+        .handle_command(Command::SetInputEventRate { hz: 0 })
+        .expect("off");
+    blast(&rig);
+    thread::sleep(Duration::from_millis(60));
+    rig.step();
+    let events = rig.core.drain_events();
+    assert!(!events
+        .iter()
+        .any(|event| matches!(event, Event::MidiInputEvent { .. })));
+    let batch = events.iter().find_map(|event| match event {
+        Event::RecentInputEvents { events } => Some(events.len()),
+        _ => None,
+    });
+    assert_eq!(batch, Some(20));
+}
+
+#[test]
+fn a_full_queue_drops_the_oldest_periodic_events_first() {
+    let mut queue = EventQueue::new(100);
+    queue.push_back(Event::SetupCompleted);
+    for note in 0..1000 {
+        queue.push_back(Event::MidiInputEvent {
+            event: MidiLikeEvent::NoteOff {
+                note: (note % 128) as u8,
+            },
+        });
+    }
+    assert_eq!(queue.len(), 100);
+    assert_eq!(queue.dropped(), 901);
+    let events = queue.drain();
+    assert!(matches!(events[0], Event::SetupCompleted));
+    assert!(matches!(
+        events[99],
+        Event::MidiInputEvent {
+            event: MidiLikeEvent::NoteOff { note: 103 }
+        }
+    ));
+
+    // With nothing periodic left, the oldest goes.
+    let mut queue = EventQueue::new(2);
+    queue.push_back(Event::SetupCompleted);
+    queue.push_back(Event::ShutDown);
+    queue.push_back(Event::ShutDown);
+    assert_eq!(queue.dropped(), 1);
+    assert!(queue
+        .drain()
+        .iter()
+        .all(|event| matches!(event, Event::ShutDown)));
+}
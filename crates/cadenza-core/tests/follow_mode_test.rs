@@ -0,0 +1,120 @@
+use cadenza_core::{Command, Event, FollowMode};
+use cadenza_domain_eval::Grade;
+use cadenza_ports::playback::LoopRange;
+use cadenza_ports::types::SampleTime;
+
+mod common;
+
+use common::Rig;
+
+/// One quarter of the demo scale, 120 bpm at 48 kHz.
+const QUARTER_SAMPLES: f64 = 24_000.0;
+/// The player's quarters are 5% short.
+const RUSHED_QUARTER_SAMPLES: f64 = QUARTER_SAMPLES * 0.95;
+const SCALE: [u8; 8] = [60, 62, 64, 65, 67, 69, 71, 72];
+
+/// `(note, grade, delta_tick)` of every judged target.
+fn feedback(events: &[Event]) -> Vec<(u8, Grade, i64)> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            Event::JudgeFeedback {
+                expected_notes,
+                grade,
+                delta_tick,
+                ..
+            } => Some((expected_notes[0], *grade, *delta_tick)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Plays the whole scale 5% fast and returns what was judged.
+fn play_rushed_scale(rig: &mut Rig) -> Vec<(u8, Grade, i64)> {
+    rig.core
+        .handle_command(Command::StartPractice)
+        .expect("start");
+    let start = rig.sample_time;
+    let mut events = Vec::new();
+    let mut presses = SCALE.iter().enumerate().peekable();
+    while rig.sample_time < start + 9 * QUARTER_SAMPLES as SampleTime {
+        rig.step();
+        events.extend(rig.core.drain_events());
+        if let Some(&(idx, &note)) = presses.peek() {
+            let due = start + (idx as f64 * RUSHED_QUARTER_SAMPLES) as SampleTime;
+            if rig.sample_time >= due {
+                rig.press(note);
+                presses.next();
+            }
+        }
+    }
+    feedback(&events)
+}
+
+#[test]
+fn a_rushing_player_drifts_out_of_the_windows_without_follow_mode() {
+    let mut rig = Rig::new();
+    let judged = play_rushed_scale(&mut rig);
+
+    assert_eq!(judged.len(), SCALE.len(), "{judged:?}");
+    assert!(
+        judged
+            .iter()
+            .rev()
+            .take(3)
+            .all(|&(_, grade, _)| grade == Grade::Miss),
+        "{judged:?}"
+    );
+}
+
+#[test]
+fn follow_mode_keeps_a_rushing_player_within_a_bounded_distance() {
+    let mut rig = Rig::new();
+    rig.core
+        .handle_command(Command::SetFollowMode {
+            enabled: true,
+            correction_rate: Some(0.5),
+        })
+        .expect("follow");
+    assert!(rig.core.drain_events().iter().any(|event| matches!(
+        event,
+        Event::FollowModeChanged {
+            correction_rate: Some(rate)
+        } if *rate == 0.5
+    )));
+
+    let judged = play_rushed_scale(&mut rig);
+
+    assert_eq!(judged.len(), SCALE.len(), "{judged:?}");
+    assert!(
+        judged.iter().all(|&(_, grade, _)| grade != Grade::Miss),
+        "{judged:?}"
+    );
+    // Each quarter the player gains 24 ticks and half the error is taken out, so the
+    // error settles near 48 ticks early instead of growing.
+    let deltas: Vec<i64> = judged.iter().map(|&(_, _, delta)| delta).collect();
+    for pair in deltas.windows(2).skip(4) {
+        assert!((pair[1] - pair[0]).abs() <= 12, "{deltas:?}");
+    }
+    for &delta in &deltas[4..] {
+        assert!((-64..=-32).contains(&delta), "{deltas:?}");
+    }
+}
+
+#[test]
+fn corrections_are_bounded_and_stay_inside_the_loop() {
+    let follow = FollowMode::new(1.0);
+    // A quarter of a beat at most, either way.
+    assert_eq!(follow.correction(-400, 480, 1000, None), 120);
+    assert_eq!(follow.correction(400, 480, 1000, None), -120);
+    assert_eq!(FollowMode::new(0.5).correction(-40, 480, 1000, None), 20);
+
+    let range = Some(LoopRange {
+        start_tick: 960,
+        end_tick: 1920,
+    });
+    // Neither past the loop end nor back before its start.
+    assert_eq!(follow.correction(-100, 480, 1900, range), 19);
+    assert_eq!(follow.correction(100, 480, 1000, range), -40);
+    assert_eq!(follow.correction(-100, 480, 1000, range), 100);
+}
@@ -0,0 +1,239 @@
+use cadenza_core::{AppCore, AppError, Command, Event, ScoreSource};
+use cadenza_domain_score::{load_scorefile_path, Hand};
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::playback::PlaybackMode;
+use cadenza_ports::synth::{SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::types::{Bus, DeviceId, SampleTime};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+mod common;
+
+use common::{FakeMidi, ManualAudio, SharedRender, BLOCK, QUARTER_SAMPLES};
+
+/// The demo scale's first two notes, C4 and D4, then its next two, E4 and F4.
+const FIRST_HALF: (i64, i64) = (0, 960);
+const SECOND_HALF: (i64, i64) = (960, 1920);
+
+/// Remembers which notes reached which bus.
+#[derive(Clone, Default)]
+struct RecordingSynth {
+    note_ons: Arc<Mutex<Vec<(Bus, u8)>>>,
+}
+
+impl SynthPort for RecordingSynth {
+    fn load_soundfont_from_path(&self, _path: &str) -> Result<SoundFontInfo, SynthError> {
+        Err(SynthError::UnsupportedFormat)
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, _bus: Bus, _gm_program: u8) -> Result<(), SynthError> {
+        Ok(())
+    }
+
+    fn handle_event(&self, bus: Bus, event: MidiLikeEvent, _at: SampleTime) {
+        if let MidiLikeEvent::NoteOn { note, .. } = event {
+            self.note_ons.lock().push((bus, note));
+        }
+    }
+
+    fn render(&self, _bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        out_l[..frames].fill(0.0);
+        out_r[..frames].fill(0.0);
+    }
+}
+
+struct Rig {
+    core: AppCore,
+    render: SharedRender,
+    synth: RecordingSynth,
+    sample_time: SampleTime,
+}
+
+impl Rig {
+    /// The demo scale loaded as an accompaniment that leaves the left hand to the player.
+    fn new() -> Self {
+        let render: SharedRender = Arc::new(Mutex::new(None));
+        let synth = RecordingSynth::default();
+        let mut core = AppCore::new(
+            Box::new(ManualAudio {
+                render: render.clone(),
+                block: BLOCK,
+            }),
+            Box::new(FakeMidi {
+                callback: Arc::new(Mutex::new(None)),
+            }),
+            Arc::new(synth.clone()),
+            None,
+            None,
+        )
+        .expect("core");
+        core.handle_command(Command::SelectAudioOutput {
+            device_id: DeviceId("manual".to_string()),
+            config: None,
+        })
+        .expect("audio");
+        core.handle_command(Command::LoadScore {
+            source: ScoreSource::InternalDemo("scale".to_string()),
+        })
+        .expect("score");
+        core.handle_command(Command::SetPlaybackMode {
+            mode: PlaybackMode::Accompaniment,
+        })
+        .expect("mode");
+        core.handle_command(Command::SetAccompanimentRoute {
+            play_left: false,
+            play_right: true,
+            suppress_pedal: false,
+        })
+        .expect("route");
+        let mut rig = Self {
+            core,
+            render,
+            synth,
+            sample_time: 0,
+        };
+        for _ in 0..4 {
+            rig.step();
+        }
+        rig.core.drain_events();
+        rig
+    }
+
+    /// Renders one block and ticks the core.
+    fn step(&mut self) {
+        let mut left = [0.0; BLOCK];
+        let mut right = [0.0; BLOCK];
+        self.render.lock().as_mut().expect("audio opened").render(
+            self.sample_time,
+            &mut left,
+            &mut right,
+        );
+        self.sample_time += BLOCK as SampleTime;
+        self.core.tick();
+    }
+
+    /// Notes the autopilot plays over the first four quarters.
+    fn play_four_quarters(&mut self) -> Vec<u8> {
+        self.core
+            .handle_command(Command::StartPractice)
+            .expect("start");
+        let start = self.sample_time;
+        while self.sample_time < start + 4 * QUARTER_SAMPLES - BLOCK as SampleTime {
+            self.step();
+        }
+        self.synth
+            .note_ons
+            .lock()
+            .iter()
+            .filter(|(bus, _)| *bus == Bus::Autopilot)
+            .map(|&(_, note)| note)
+            .collect()
+    }
+}
+
+fn dirty_changes(events: &[Event]) -> Vec<bool> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            Event::ScoreDirtyChanged { dirty } => Some(*dirty),
+            _ => None,
+        })
+        .collect()
+}
+
+fn set_hand(rig: &mut Rig, (start_tick, end_tick): (i64, i64), hand: Hand) {
+    rig.core
+        .handle_command(Command::SetNotesHand {
+            start_tick,
+            end_tick,
+            min_note: 0,
+            max_note: 127,
+            hand,
+        })
+        .expect("set hand");
+}
+
+#[test]
+fn notes_moved_to_the_left_hand_are_left_to_the_player() {
+    let mut rig = Rig::new();
+    rig.core
+        .handle_command(Command::SetNotesHand {
+            start_tick: FIRST_HALF.0,
+            end_tick: FIRST_HALF.1,
+            min_note: 60,
+            max_note: 62,
+            hand: Hand::Left,
+        })
+        .expect("set hand");
+
+    let events = rig.core.drain_events();
+    assert_eq!(dirty_changes(&events), vec![true]);
+    let (notes, targets) = events
+        .iter()
+        .find_map(|event| match event {
+            Event::ScoreViewUpdated { notes, targets, .. } => Some((notes, targets)),
+            _ => None,
+        })
+        .expect("score view");
+    let hands: Vec<_> = notes.iter().take(3).map(|n| (n.note, n.hand)).collect();
+    assert_eq!(
+        hands,
+        vec![(60, Some(Hand::Left)), (62, Some(Hand::Left)), (64, None)]
+    );
+    assert_eq!(targets[0].hand, Some(Hand::Left));
+    assert_eq!(targets[2].hand, None);
+
+    assert_eq!(rig.play_four_quarters(), vec![64, 65]);
+}
+
+#[test]
+fn swapped_staves_are_swapped_back_and_saved_with_the_edits() {
+    let mut rig = Rig::new();
+    set_hand(&mut rig, FIRST_HALF, Hand::Left);
+    set_hand(&mut rig, SECOND_HALF, Hand::Right);
+    rig.core
+        .handle_command(Command::SwapHands {
+            start_tick: FIRST_HALF.0,
+            end_tick: SECOND_HALF.1,
+        })
+        .expect("swap");
+    // Past the score's end nothing changes, so nothing is logged.
+    rig.core
+        .handle_command(Command::SwapHands {
+            start_tick: 100_000,
+            end_tick: 200_000,
+        })
+        .expect("empty swap");
+    assert_eq!(dirty_changes(&rig.core.drain_events()), vec![true]);
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let path = std::env::temp_dir().join(format!("cadenza-hand-edit-{nanos}.cadenza"));
+    rig.core
+        .handle_command(Command::SaveScoreFile {
+            path: path.display().to_string(),
+        })
+        .expect("save");
+    assert_eq!(dirty_changes(&rig.core.drain_events()), vec![false]);
+    let saved = load_scorefile_path(&path).expect("load");
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(saved.edit_log.len(), 3, "{:?}", saved.edit_log);
+
+    let unwritable = std::env::temp_dir()
+        .join(format!("cadenza-missing-{nanos}"))
+        .join("piece.cadenza");
+    let error = rig
+        .core
+        .handle_command(Command::SaveScoreFile {
+            path: unwritable.display().to_string(),
+        })
+        .expect_err("save into a missing directory");
+    assert!(matches!(error, AppError::ScoreSave(_)), "{error:?}");
+
+    assert_eq!(rig.play_four_quarters(), vec![60, 62]);
+}
@@ -0,0 +1,112 @@
+use cadenza_core::{
+    load_midi_script, Event, HeadlessRunner, PracticeReport, ScoreSource, ScriptedMidiEvent,
+    SilentSynth,
+};
+use cadenza_domain_eval::Grade;
+use cadenza_ports::midi::MidiLikeEvent;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SCALE: [u8; 8] = [60, 62, 64, 65, 67, 69, 71, 72];
+/// A quarter of the demo scale at 120 bpm.
+const QUARTER_MS: f64 = 500.0;
+
+/// Every note of the scale on the beat, held for most of it.
+fn perfect_scale() -> Vec<ScriptedMidiEvent> {
+    SCALE
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, &note)| {
+            let at_ms = idx as f64 * QUARTER_MS;
+            [
+                ScriptedMidiEvent {
+                    at_ms,
+                    event: MidiLikeEvent::NoteOn { note, velocity: 80 },
+                },
+                ScriptedMidiEvent {
+                    at_ms: at_ms + 0.8 * QUARTER_MS,
+                    event: MidiLikeEvent::NoteOff { note },
+                },
+            ]
+        })
+        .collect()
+}
+
+fn practice_demo(script: &[ScriptedMidiEvent]) -> PracticeReport {
+    let mut runner = HeadlessRunner::new(Arc::new(SilentSynth), None).expect("runner");
+    runner
+        .load_score(ScoreSource::InternalDemo("scale".to_string()))
+        .expect("score");
+    runner
+        .practice(script, Duration::from_secs(60))
+        .expect("practice")
+}
+
+#[test]
+fn a_perfect_run_of_the_demo_is_reported_perfect() {
+    let report = practice_demo(&perfect_scale());
+
+    assert_eq!((report.hits, report.misses), (8, 0), "{report:?}");
+    assert_eq!(report.accuracy, 1.0);
+    assert!(report.score > 0);
+    let notes: Vec<Vec<u8>> = report.targets.iter().map(|t| t.notes.clone()).collect();
+    assert_eq!(
+        notes,
+        SCALE.iter().map(|&note| vec![note]).collect::<Vec<_>>()
+    );
+    for target in &report.targets {
+        assert_eq!(target.grade, Grade::Perfect, "{report:?}");
+        assert!(target.delta_tick.abs() <= 1, "{report:?}");
+    }
+
+    // The virtual clock makes every run the same.
+    assert_eq!(practice_demo(&perfect_scale()), report);
+}
+
+#[test]
+fn scripts_are_read_from_json_and_missing_notes_are_missed() {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let path = std::env::temp_dir().join(format!("cadenza-script-{nanos}.json"));
+    // Only the first half of the scale, out of order in the file.
+    let mut script: Vec<ScriptedMidiEvent> = perfect_scale().into_iter().take(8).collect();
+    script.reverse();
+    std::fs::write(&path, serde_json::to_vec(&script).expect("json")).expect("write");
+    let loaded = load_midi_script(&path).expect("script");
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(loaded, perfect_scale()[..8]);
+
+    let report = practice_demo(&loaded);
+    assert_eq!((report.hits, report.misses), (4, 4), "{report:?}");
+    assert_eq!(report.accuracy, 0.5);
+    assert!(report.targets[4..]
+        .iter()
+        .all(|target| target.grade == Grade::Miss));
+}
+
+#[test]
+fn the_demo_scale_is_analyzed_as_easy_on_load() {
+    let mut runner = HeadlessRunner::new(Arc::new(SilentSynth), None).expect("runner");
+    runner
+        .load_score(ScoreSource::InternalDemo("scale".to_string()))
+        .expect("score");
+    let analysis = runner
+        .core()
+        .drain_events()
+        .into_iter()
+        .find_map(|event| match event {
+            Event::ScoreAnalysis { analysis } => Some(analysis),
+            _ => None,
+        })
+        .expect("analysis");
+
+    // One note a beat at 120 bpm: two in any second.
+    assert_eq!(analysis.peak_notes_per_second, 2.0);
+    assert_eq!(analysis.max_chord_size, 1);
+    assert_eq!(analysis.left_hand_span, 0);
+    assert_eq!(analysis.right_hand_span, 0);
+    assert_eq!(analysis.fast_repeated_notes, 0);
+    assert_eq!(analysis.suggested_tempo_multiplier, 1.0);
+}
@@ -0,0 +1,63 @@
+use cadenza_core::{Command, Event};
+use cadenza_domain_eval::Grade;
+use cadenza_ports::types::SampleTime;
+
+mod common;
+
+use common::{Rig, QUARTER_SAMPLES};
+
+/// Keyboard latency the player calibrated away.
+const LATENCY_MS: i32 = 30;
+const LATENCY_SAMPLES: SampleTime = 1440;
+
+/// A key press arriving now, at the current sample, judged right away.
+fn judged_now(rig: &mut Rig, note: u8) -> Vec<Event> {
+    rig.press(note);
+    rig.core.tick();
+    rig.core.drain_events()
+}
+
+/// Plays D4, the scale's second note at tick 480, exactly on time at `tempo`, with the
+/// press arriving [`LATENCY_MS`] late. Returns the judged delta in ticks.
+fn judged_delta(tempo: f32) -> i64 {
+    let mut rig = Rig::new();
+    rig.core
+        .handle_command(Command::SetInputOffsetMs { ms: -LATENCY_MS })
+        .expect("offset");
+    rig.core
+        .handle_command(Command::SetTempoMultiplier {
+            x: tempo,
+            ramp_ms: None,
+        })
+        .expect("tempo");
+    rig.core
+        .handle_command(Command::StartPractice)
+        .expect("start");
+    let start = rig.sample_time;
+
+    let due = start + (QUARTER_SAMPLES as f32 / tempo) as SampleTime;
+    rig.render_to(due + LATENCY_SAMPLES);
+    judged_now(&mut rig, 62)
+        .into_iter()
+        .find_map(|event| match event {
+            Event::JudgeFeedback {
+                expected_notes,
+                delta_tick,
+                grade,
+                ..
+            } if expected_notes == [62] => {
+                assert_ne!(grade, Grade::Miss);
+                Some(delta_tick)
+            }
+            _ => None,
+        })
+        .expect("D4 judged")
+}
+
+#[test]
+fn input_offset_compensates_the_same_latency_at_any_tempo() {
+    let at_full_speed = judged_delta(1.0);
+    let at_half_speed = judged_delta(0.5);
+    assert!(at_full_speed.abs() <= 1, "{at_full_speed}");
+    assert!(at_half_speed.abs() <= 1, "{at_half_speed}");
+}
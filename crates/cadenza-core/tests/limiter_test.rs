@@ -0,0 +1,77 @@
+use cadenza_core::{Limiter, LIMITER_LOOKAHEAD_MS};
+use cadenza_ports::types::LimiterParams;
+
+const SAMPLE_RATE: u32 = 48_000;
+
+fn ms_to_samples(ms: f32) -> usize {
+    (ms / 1000.0 * SAMPLE_RATE as f32).round() as usize
+}
+
+/// 1 kHz sine whose amplitude follows `amplitude(sample_index)`.
+fn run(limiter: &mut Limiter, samples: usize, amplitude: impl Fn(usize) -> f32) -> Vec<f32> {
+    let mut out = Vec::with_capacity(samples);
+    let block = 256;
+    let mut start = 0;
+    while start < samples {
+        let len = block.min(samples - start);
+        let mut l: Vec<f32> = (start..start + len)
+            .map(|n| {
+                let t = n as f32 / SAMPLE_RATE as f32;
+                amplitude(n) * (std::f32::consts::TAU * 1_000.0 * t).sin()
+            })
+            .collect();
+        let mut r = l.clone();
+        limiter.process(&mut l, &mut r);
+        out.extend(l);
+        start += len;
+    }
+    out
+}
+
+#[test]
+fn limiter_step_never_exceeds_ceiling_and_recovers_within_release() {
+    let params = LimiterParams {
+        ceiling: 0.9,
+        attack_ms: 1.0,
+        release_ms: 200.0,
+    };
+    let mut limiter = Limiter::new(SAMPLE_RATE, params);
+    let loud_from = ms_to_samples(50.0);
+    let quiet_from = ms_to_samples(150.0);
+    let total = ms_to_samples(500.0);
+    let out = run(&mut limiter, total, |n| {
+        if (loud_from..quiet_from).contains(&n) {
+            2.0
+        } else {
+            0.1
+        }
+    });
+
+    let max = out.iter().fold(0.0_f32, |acc, v| acc.max(v.abs()));
+    assert!(max <= params.ceiling + 1e-6, "peak {max} over ceiling");
+    assert!(limiter.take_min_gain() < 0.5);
+
+    // Once the loud part has left the delay line, the gain is back to unity within the
+    // release time: the quiet sine plays at its input amplitude again.
+    let delay = ms_to_samples(LIMITER_LOOKAHEAD_MS);
+    let recovered_from = quiet_from + delay + ms_to_samples(params.release_ms);
+    let tail_peak = out[recovered_from..]
+        .iter()
+        .fold(0.0_f32, |acc, v| acc.max(v.abs()));
+    assert!((tail_peak - 0.1).abs() < 0.002, "tail peak {tail_peak}");
+}
+
+#[test]
+fn limiter_leaves_quiet_signal_untouched_apart_from_latency() {
+    let mut limiter = Limiter::new(SAMPLE_RATE, LimiterParams::default());
+    let input: Vec<f32> = (0..1_000).map(|n| (n as f32 * 0.01).sin() * 0.5).collect();
+    let mut l = input.clone();
+    let mut r = input.clone();
+    limiter.process(&mut l, &mut r);
+
+    let delay = ms_to_samples(LIMITER_LOOKAHEAD_MS);
+    for n in delay..input.len() {
+        assert!((l[n] - input[n - delay]).abs() < 1e-6);
+    }
+    assert_eq!(limiter.take_min_gain(), 1.0);
+}
@@ -0,0 +1,123 @@
+use cadenza_core::{CapturedEvent, IntervalBucket, MidiCapture, INTERVAL_BUCKET_EDGES_MS};
+use cadenza_ports::midi::{MidiLikeEvent, RawMidiTap};
+use cadenza_ports::types::Tick;
+use std::time::{Duration, Instant};
+
+const SAMPLE_RATE_HZ: u32 = 48_000;
+
+fn bucket_count(buckets: &[IntervalBucket], upper_ms: Option<f64>) -> u64 {
+    buckets
+        .iter()
+        .find(|bucket| bucket.upper_ms == upper_ms)
+        .map(|bucket| bucket.count)
+        .expect("bucket")
+}
+
+/// Note-on/off pairs on a 10 ms grid where every other event arrives 3 ms late, and the
+/// audio clock mapping runs 1 ms ahead on every other event.
+fn jittered_capture(start: Instant, events: usize) -> MidiCapture {
+    let mut capture = MidiCapture::new(start, Duration::from_secs(1), SAMPLE_RATE_HZ, 5);
+    for index in 0..events {
+        let late_ms = if index % 2 == 1 { 3 } else { 0 };
+        let at = start + Duration::from_millis(index as u64 * 10 + late_ms);
+        let ideal_sample = (index as u64 * 10 + late_ms) * 48;
+        let clock_error = if index % 2 == 1 { 48 } else { 0 };
+        let event = if index % 2 == 0 {
+            MidiLikeEvent::NoteOn {
+                note: 60,
+                velocity: 100,
+            }
+        } else {
+            MidiLikeEvent::NoteOff { note: 60 }
+        };
+        capture.record(CapturedEvent {
+            at,
+            sample_time: 10_000 + ideal_sample + clock_error,
+            tick: index as Tick,
+            event,
+        });
+    }
+    capture
+}
+
+#[test]
+fn capture_report_buckets_jittered_intervals() {
+    let start = Instant::now();
+    let capture = jittered_capture(start, 9);
+    let report = capture.report(7);
+
+    assert_eq!(report.captured, 9);
+    assert_eq!(report.counts.note_on, 5);
+    assert_eq!(report.counts.note_off, 4);
+    assert_eq!(report.queue_drops, 2);
+    assert_eq!(report.duplicates, 0);
+
+    // Intervals alternate 13 ms and 7 ms.
+    let histogram = &report.interval_histogram;
+    assert_eq!(histogram.len(), INTERVAL_BUCKET_EDGES_MS.len() + 1);
+    assert_eq!(bucket_count(histogram, Some(10.0)), 4);
+    assert_eq!(bucket_count(histogram, Some(20.0)), 4);
+    assert_eq!(histogram.iter().map(|bucket| bucket.count).sum::<u64>(), 8);
+
+    // Offsets alternate 0 and 1 ms: 5 zeros, 4 ones.
+    let jitter = report.jitter.expect("jitter");
+    let mean = 4.0 / 9.0;
+    let expected_std = ((5.0 * mean * mean + 4.0 * (1.0 - mean) * (1.0 - mean)) / 9.0_f64).sqrt();
+    assert!((jitter.std_dev_ms - expected_std).abs() < 1e-6);
+    assert!((jitter.max_abs_ms - (1.0 - mean)).abs() < 1e-6);
+
+    assert!(!capture.is_finished(start + Duration::from_millis(999)));
+    assert!(capture.is_finished(start + Duration::from_secs(1)));
+}
+
+#[test]
+fn capture_counts_duplicates_and_overflow() {
+    let start = Instant::now();
+    let mut capture = MidiCapture::new(start, Duration::from_secs(1), SAMPLE_RATE_HZ, 0);
+    let event = MidiLikeEvent::Cc64 { value: 127 };
+    for index in 0..cadenza_core::MIDI_CAPTURE_CAPACITY as u64 + 3 {
+        // Pairs 1 ms apart: the second of each pair is a bounced duplicate.
+        let at = start + Duration::from_millis((index / 2) * 50 + index % 2);
+        capture.record(CapturedEvent {
+            at,
+            sample_time: index,
+            tick: 0,
+            event,
+        });
+    }
+    let report = capture.report(0);
+
+    assert_eq!(report.captured, cadenza_core::MIDI_CAPTURE_CAPACITY as u64);
+    assert_eq!(report.overflowed, 3);
+    assert_eq!(
+        report.counts.cc64,
+        cadenza_core::MIDI_CAPTURE_CAPACITY as u64 + 3
+    );
+    assert_eq!(
+        report.duplicates,
+        (cadenza_core::MIDI_CAPTURE_CAPACITY as u64 + 3) / 2
+    );
+    assert_eq!(bucket_count(&report.interval_histogram, Some(1.0)), 2048);
+    assert_eq!(bucket_count(&report.interval_histogram, Some(50.0)), 2047);
+}
+
+#[test]
+fn capture_keeps_only_the_raw_messages_received_since_it_started() {
+    let tap = RawMidiTap::new();
+    tap.push(100, &[0x90, 60, 80]);
+    let capture = MidiCapture::new(Instant::now(), Duration::from_secs(1), SAMPLE_RATE_HZ, 0)
+        .with_raw_messages_from(1);
+    tap.push(200, &[0xE0, 0x00, 0x48]);
+    tap.push(300, &[0xD0, 0x40]);
+
+    let raw = capture.raw_messages(tap.messages());
+
+    let kept: Vec<(u64, u8, Vec<u8>)> = raw
+        .into_iter()
+        .map(|message| (message.stamp_us, message.status, message.data))
+        .collect();
+    assert_eq!(
+        kept,
+        vec![(200, 0xE0, vec![0x00, 0x48]), (300, 0xD0, vec![0x40])]
+    );
+}
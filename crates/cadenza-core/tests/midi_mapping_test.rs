@@ -0,0 +1,242 @@
+use cadenza_core::{AppCore, Command, Event, ScoreSource, SessionState};
+use cadenza_ports::audio::{AudioError, AudioOutputPort, AudioRenderCallback, AudioStreamHandle};
+use cadenza_ports::midi::{
+    MidiAction, MidiControl, MidiError, MidiInputPort, MidiInputStream, MidiLikeEvent, MidiMapping,
+    PlayerEvent, PlayerEventCallback,
+};
+use cadenza_ports::synth::{SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::types::{
+    AudioConfig, AudioOutputDevice, Bus, DeviceId, MidiInputDevice, SampleTime,
+};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::Instant;
+
+type SharedMidiCallback = Arc<Mutex<Option<PlayerEventCallback>>>;
+
+/// Output that never calls back; these tests do not need the clock to run.
+struct IdleAudio;
+
+struct IdleAudioStream;
+
+impl AudioStreamHandle for IdleAudioStream {
+    fn close(self: Box<Self>) {}
+}
+
+impl AudioOutputPort for IdleAudio {
+    fn list_outputs(&self) -> Result<Vec<AudioOutputDevice>, AudioError> {
+        Ok(vec![AudioOutputDevice {
+            id: DeviceId("idle".to_string()),
+            name: "Idle".to_string(),
+            default_config: AudioConfig {
+                sample_rate_hz: 48_000,
+                channels: 2,
+                buffer_size_frames: Some(512),
+            },
+            buffer_size_range: None,
+        }])
+    }
+
+    fn open_output(
+        &self,
+        _device_id: &DeviceId,
+        _config: AudioConfig,
+        _cb: Box<dyn AudioRenderCallback>,
+    ) -> Result<Box<dyn AudioStreamHandle>, AudioError> {
+        Ok(Box::new(IdleAudioStream))
+    }
+}
+
+struct FakeMidi {
+    callback: SharedMidiCallback,
+}
+
+struct FakeMidiStream;
+
+impl MidiInputStream for FakeMidiStream {
+    fn close(self: Box<Self>) {}
+}
+
+impl MidiInputPort for FakeMidi {
+    fn list_inputs(&self) -> Result<Vec<MidiInputDevice>, MidiError> {
+        Ok(Vec::new())
+    }
+
+    fn open_input(
+        &self,
+        _device_id: &DeviceId,
+        cb: PlayerEventCallback,
+    ) -> Result<Box<dyn MidiInputStream>, MidiError> {
+        *self.callback.lock() = Some(cb);
+        Ok(Box::new(FakeMidiStream))
+    }
+}
+
+struct SilentSynth;
+
+impl SynthPort for SilentSynth {
+    fn load_soundfont_from_path(&self, _path: &str) -> Result<SoundFontInfo, SynthError> {
+        Err(SynthError::UnsupportedFormat)
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, _bus: Bus, _gm_program: u8) -> Result<(), SynthError> {
+        Ok(())
+    }
+
+    fn handle_event(&self, _bus: Bus, _event: MidiLikeEvent, _at: SampleTime) {}
+
+    fn render(&self, _bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        out_l[..frames].fill(0.0);
+        out_r[..frames].fill(0.0);
+    }
+}
+
+fn practice_ready() -> (AppCore, SharedMidiCallback) {
+    let midi: SharedMidiCallback = Arc::new(Mutex::new(None));
+    let mut core = AppCore::new(
+        Box::new(IdleAudio),
+        Box::new(FakeMidi {
+            callback: midi.clone(),
+        }),
+        Arc::new(SilentSynth),
+        None,
+        None,
+    )
+    .expect("core");
+    core.handle_command(Command::SelectMidiInput {
+        device_id: DeviceId("keyboard".to_string()),
+    })
+    .expect("midi");
+    core.handle_command(Command::LoadScore {
+        source: ScoreSource::InternalDemo("scale".to_string()),
+    })
+    .expect("score");
+    core.drain_events();
+    (core, midi)
+}
+
+fn send(core: &mut AppCore, midi: &SharedMidiCallback, event: MidiLikeEvent) -> Vec<Event> {
+    let callback = midi.lock().clone().expect("midi opened");
+    callback(PlayerEvent {
+        at: Instant::now(),
+        event,
+    });
+    core.tick();
+    core.drain_events()
+}
+
+fn session_states(events: &[Event]) -> Vec<SessionState> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            Event::SessionStateUpdated { state, .. } => Some(*state),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn mapped_controller_stops_practice() {
+    let (mut core, midi) = practice_ready();
+    core.handle_command(Command::SetMidiMapping {
+        mappings: vec![MidiMapping {
+            controller_or_note: MidiControl::Controller(80),
+            action: MidiAction::StopPractice,
+        }],
+    })
+    .expect("mapping");
+    core.handle_command(Command::StartPractice).expect("start");
+    core.drain_events();
+
+    // An unmapped controller changes nothing.
+    let events = send(
+        &mut core,
+        &midi,
+        MidiLikeEvent::ControlChange {
+            controller: 81,
+            value: 127,
+        },
+    );
+    assert!(session_states(&events).is_empty());
+
+    let events = send(
+        &mut core,
+        &midi,
+        MidiLikeEvent::ControlChange {
+            controller: 80,
+            value: 127,
+        },
+    );
+    assert_eq!(session_states(&events), vec![SessionState::Ready]);
+    // The button's release does nothing further.
+    let events = send(
+        &mut core,
+        &midi,
+        MidiLikeEvent::ControlChange {
+            controller: 80,
+            value: 0,
+        },
+    );
+    assert!(session_states(&events).is_empty());
+}
+
+#[test]
+fn learned_key_toggles_practice_and_is_not_judged() {
+    let (mut core, midi) = practice_ready();
+    core.handle_command(Command::LearnMidiMapping {
+        action: MidiAction::TogglePractice,
+    })
+    .expect("learn");
+
+    // The lowest A is learned rather than played.
+    let events = send(
+        &mut core,
+        &midi,
+        MidiLikeEvent::NoteOn {
+            note: 21,
+            velocity: 90,
+        },
+    );
+    let learned = MidiMapping {
+        controller_or_note: MidiControl::Note(21),
+        action: MidiAction::TogglePractice,
+    };
+    assert!(events.iter().any(|event| matches!(
+        event,
+        Event::MidiMappingLearned { mapping } if *mapping == learned
+    )));
+    assert!(events.iter().any(|event| matches!(
+        event,
+        Event::SessionStateUpdated { settings, .. } if settings.midi_mappings == [learned]
+    )));
+    assert!(session_states(&events)
+        .iter()
+        .all(|state| *state == SessionState::Ready));
+    send(&mut core, &midi, MidiLikeEvent::NoteOff { note: 21 });
+
+    let events = send(
+        &mut core,
+        &midi,
+        MidiLikeEvent::NoteOn {
+            note: 21,
+            velocity: 90,
+        },
+    );
+    // Opening the output on start reports the session first.
+    assert_eq!(session_states(&events).last(), Some(&SessionState::Running));
+    assert!(!events
+        .iter()
+        .any(|event| matches!(event, Event::JudgeFeedback { .. })));
+
+    let events = send(
+        &mut core,
+        &midi,
+        MidiLikeEvent::NoteOn {
+            note: 21,
+            velocity: 90,
+        },
+    );
+    assert_eq!(session_states(&events), vec![SessionState::Paused]);
+}
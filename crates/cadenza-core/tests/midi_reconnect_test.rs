@@ -0,0 +1,257 @@
+use cadenza_core::{AppCore, Command, Event, VirtualClock};
+use cadenza_ports::audio::{AudioError, AudioOutputPort, AudioRenderCallback, AudioStreamHandle};
+use cadenza_ports::midi::{
+    MidiError, MidiInputPort, MidiInputStream, MidiLikeEvent, PlayerEvent, PlayerEventCallback,
+};
+use cadenza_ports::synth::{SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::types::{
+    AudioConfig, AudioOutputDevice, Bus, DeviceId, MidiInputDevice, SampleTime,
+};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+struct NoAudio;
+
+impl AudioOutputPort for NoAudio {
+    fn list_outputs(&self) -> Result<Vec<AudioOutputDevice>, AudioError> {
+        Ok(Vec::new())
+    }
+
+    fn open_output(
+        &self,
+        device_id: &DeviceId,
+        _config: AudioConfig,
+        _cb: Box<dyn AudioRenderCallback>,
+    ) -> Result<Box<dyn AudioStreamHandle>, AudioError> {
+        Err(AudioError::DeviceNotFound(device_id.to_string()))
+    }
+}
+
+#[derive(Default)]
+struct KeyboardState {
+    unplugged: bool,
+    /// Events the keyboard still sends before its cable comes loose.
+    events_left: Option<u32>,
+    callback: Option<PlayerEventCallback>,
+    opens: u32,
+    closes: u32,
+    /// Other devices listed ahead of the keyboard.
+    ahead: Vec<&'static str>,
+}
+
+/// A USB keyboard that can be unplugged: it stops calling back and is no longer listed.
+/// Ports are numbered by position like midir's, so devices listed ahead shift its id.
+#[derive(Clone, Default)]
+struct Keyboard(Arc<Mutex<KeyboardState>>);
+
+impl Keyboard {
+    fn id(&self) -> DeviceId {
+        DeviceId(format!("midir:{}:Keyboard", self.0.lock().ahead.len()))
+    }
+
+    fn play(&self, note: u8) {
+        let callback = {
+            let mut state = self.0.lock();
+            if state.unplugged {
+                return;
+            }
+            if let Some(left) = state.events_left.as_mut() {
+                if *left == 0 {
+                    state.unplugged = true;
+                    return;
+                }
+                *left -= 1;
+            }
+            state.callback.clone()
+        };
+        if let Some(callback) = callback {
+            callback(PlayerEvent {
+                at: Instant::now(),
+                event: MidiLikeEvent::NoteOn { note, velocity: 80 },
+            });
+        }
+    }
+}
+
+struct KeyboardStream(Keyboard);
+
+impl MidiInputStream for KeyboardStream {
+    fn close(self: Box<Self>) {
+        let mut state = self.0 .0.lock();
+        state.closes += 1;
+        state.callback = None;
+    }
+}
+
+impl MidiInputPort for Keyboard {
+    fn list_inputs(&self) -> Result<Vec<MidiInputDevice>, MidiError> {
+        let state = self.0.lock();
+        let mut names = state.ahead.clone();
+        if !state.unplugged {
+            names.push("Keyboard");
+        }
+        Ok(names
+            .into_iter()
+            .enumerate()
+            .map(|(index, name)| MidiInputDevice {
+                id: DeviceId(format!("midir:{index}:{name}")),
+                name: name.to_string(),
+                is_available: true,
+            })
+            .collect())
+    }
+
+    fn open_input(
+        &self,
+        device_id: &DeviceId,
+        cb: PlayerEventCallback,
+    ) -> Result<Box<dyn MidiInputStream>, MidiError> {
+        let id = self.id();
+        let mut state = self.0.lock();
+        if state.unplugged || *device_id != id {
+            return Err(MidiError::DeviceNotFound(device_id.to_string()));
+        }
+        state.opens += 1;
+        state.callback = Some(cb);
+        Ok(Box::new(KeyboardStream(self.clone())))
+    }
+}
+
+struct SilentSynth;
+
+impl SynthPort for SilentSynth {
+    fn load_soundfont_from_path(&self, _path: &str) -> Result<SoundFontInfo, SynthError> {
+        Err(SynthError::UnsupportedFormat)
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, _bus: Bus, _gm_program: u8) -> Result<(), SynthError> {
+        Ok(())
+    }
+
+    fn handle_event(&self, _bus: Bus, _event: MidiLikeEvent, _at: SampleTime) {}
+
+    fn render(&self, _bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        out_l[..frames].fill(0.0);
+        out_r[..frames].fill(0.0);
+    }
+}
+
+fn keyboard_open(keyboard: &Keyboard) -> (AppCore, Arc<VirtualClock>) {
+    let clock = Arc::new(VirtualClock::new());
+    let mut core = AppCore::new(
+        Box::new(NoAudio),
+        Box::new(keyboard.clone()),
+        Arc::new(SilentSynth),
+        None,
+        None,
+    )
+    .expect("core");
+    core.set_clock(clock.clone());
+    core.handle_command(Command::SelectMidiInput {
+        device_id: keyboard.id(),
+    })
+    .expect("midi");
+    core.drain_events();
+    (core, clock)
+}
+
+/// Ticks every 100 ms for `secs` seconds and returns what the core reported about the input.
+fn run(core: &mut AppCore, clock: &VirtualClock, secs: u64) -> Vec<String> {
+    let mut reports = Vec::new();
+    for _ in 0..secs * 10 {
+        clock.advance(Duration::from_millis(100));
+        core.tick();
+        for event in core.drain_events() {
+            match event {
+                Event::MidiInputLost { device_id } => reports.push(format!("lost {device_id}")),
+                Event::MidiInputRestored { device_id } => {
+                    reports.push(format!("restored {device_id}"))
+                }
+                _ => {}
+            }
+        }
+    }
+    reports
+}
+
+#[test]
+fn an_unplugged_keyboard_is_reopened_once_it_is_back() {
+    let keyboard = Keyboard::default();
+    keyboard.0.lock().events_left = Some(3);
+    let (mut core, clock) = keyboard_open(&keyboard);
+
+    for note in 60..66 {
+        keyboard.play(note);
+    }
+    assert!(keyboard.0.lock().unplugged);
+    assert_eq!(run(&mut core, &clock, 3), vec!["lost midir:0:Keyboard"]);
+    assert_eq!(keyboard.0.lock().closes, 1);
+
+    // Nothing to open while it stays away.
+    assert!(run(&mut core, &clock, 10).is_empty());
+    assert_eq!(keyboard.0.lock().opens, 1);
+
+    {
+        let mut state = keyboard.0.lock();
+        state.unplugged = false;
+        state.events_left = None;
+    }
+    assert_eq!(run(&mut core, &clock, 4), vec!["restored midir:0:Keyboard"]);
+    let state = keyboard.0.lock();
+    assert_eq!(state.opens, 2);
+    assert!(state.callback.is_some());
+}
+
+#[test]
+fn selecting_the_input_by_hand_while_lost_stops_the_retries() {
+    let keyboard = Keyboard::default();
+    let (mut core, clock) = keyboard_open(&keyboard);
+    keyboard.0.lock().unplugged = true;
+    assert_eq!(run(&mut core, &clock, 3), vec!["lost midir:0:Keyboard"]);
+
+    keyboard.0.lock().unplugged = false;
+    core.handle_command(Command::SelectMidiInput {
+        device_id: keyboard.id(),
+    })
+    .expect("midi");
+    assert!(run(&mut core, &clock, 10).is_empty());
+    assert_eq!(keyboard.0.lock().opens, 2);
+}
+
+#[test]
+fn a_device_plugged_in_ahead_of_the_keyboard_does_not_lose_it() {
+    let keyboard = Keyboard::default();
+    let (mut core, clock) = keyboard_open(&keyboard);
+
+    keyboard.0.lock().ahead.push("Drum Pad");
+    assert!(run(&mut core, &clock, 5).is_empty());
+    {
+        let state = keyboard.0.lock();
+        assert_eq!(state.opens, 1);
+        assert!(state.callback.is_some());
+    }
+
+    // Unplugging the other device shifts the keyboard back; still no loss.
+    keyboard.0.lock().ahead.clear();
+    assert!(run(&mut core, &clock, 5).is_empty());
+    assert_eq!(keyboard.0.lock().opens, 1);
+}
+
+#[test]
+fn an_unplugged_keyboard_is_found_again_under_a_new_index() {
+    let keyboard = Keyboard::default();
+    let (mut core, clock) = keyboard_open(&keyboard);
+    keyboard.0.lock().unplugged = true;
+    assert_eq!(run(&mut core, &clock, 3), vec!["lost midir:0:Keyboard"]);
+
+    {
+        let mut state = keyboard.0.lock();
+        state.ahead.push("Drum Pad");
+        state.unplugged = false;
+    }
+    assert_eq!(run(&mut core, &clock, 4), vec!["restored midir:1:Keyboard"]);
+    assert_eq!(keyboard.0.lock().opens, 2);
+}
@@ -0,0 +1,98 @@
+use cadenza_core::{Command, Event, PianoRollNoteDto, PianoRollTargetDto, SilentSynth};
+use cadenza_domain_eval::Grade;
+use std::sync::Arc;
+
+mod common;
+
+use common::{Rig, QUARTER_SAMPLES};
+
+/// The demo scale loaded, with a few blocks already rendered, and the events so far.
+fn loaded() -> (Rig, Vec<Event>) {
+    let mut rig = Rig::with_synth(Arc::new(SilentSynth));
+    let mut events = rig.core.drain_events();
+    rig.warm_up();
+    events.extend(rig.core.drain_events());
+    (rig, events)
+}
+
+fn score_view(events: &[Event]) -> (Vec<PianoRollNoteDto>, Vec<PianoRollTargetDto>) {
+    events
+        .iter()
+        .rev()
+        .find_map(|event| match event {
+            Event::ScoreViewUpdated { notes, targets, .. } => {
+                Some((notes.clone(), targets.clone()))
+            }
+            _ => None,
+        })
+        .expect("score view")
+}
+
+#[test]
+fn piano_roll_spans_targets_and_feedback_share_note_ids() {
+    let (mut rig, events) = loaded();
+    let (notes, targets) = score_view(&events);
+
+    assert!(notes.iter().all(|n| n.id.is_some()));
+    for target in &targets {
+        assert_eq!(target.note_ids.len(), target.notes.len());
+        for (&note, id) in target.notes.iter().zip(&target.note_ids) {
+            let span = notes
+                .iter()
+                .find(|n| n.id == Some(*id))
+                .unwrap_or_else(|| panic!("no span for {id:?}"));
+            assert_eq!((span.note, span.start_tick), (note, target.tick));
+        }
+    }
+
+    // Play the first note of the scale and leave the second.
+    rig.core
+        .handle_command(Command::StartPractice)
+        .expect("start");
+    let start = rig.sample_time;
+    rig.press(targets[0].notes[0]);
+    let mut events = Vec::new();
+    while rig.sample_time < start + 2 * QUARTER_SAMPLES {
+        rig.step();
+        events.extend(rig.core.drain_events());
+    }
+
+    let judged: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            Event::JudgeFeedback {
+                target_id,
+                grade,
+                note_ids,
+                expected_names,
+                ..
+            } => Some((*target_id, *grade, note_ids.clone(), expected_names.clone())),
+            _ => None,
+        })
+        .collect();
+    assert!(judged.len() >= 2, "{judged:?}");
+    assert_eq!(judged[0].0, targets[0].id);
+    assert_ne!(judged[0].1, Grade::Miss);
+    assert_eq!(judged[0].2, targets[0].note_ids);
+    assert_eq!(judged[1].0, targets[1].id);
+    assert_eq!(judged[1].1, Grade::Miss);
+    assert_eq!(judged[1].2, targets[1].note_ids);
+
+    // Feedback names the notes as the piano roll does.
+    for (target, judged) in targets.iter().zip(&judged) {
+        let names: Vec<String> = target
+            .note_ids
+            .iter()
+            .map(|id| {
+                notes
+                    .iter()
+                    .find(|n| n.id == Some(*id))
+                    .expect("span")
+                    .name
+                    .clone()
+            })
+            .collect();
+        assert_eq!(judged.3, names);
+    }
+    assert_eq!(judged[0].3, vec!["C4".to_string()]);
+}
@@ -0,0 +1,161 @@
+use cadenza_core::{AppCore, Command, NullMidiInputPort, ScoreSource};
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::synth::{SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::types::{Bus, DeviceId, SampleTime};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+mod common;
+
+use common::{ManualAudio, SharedRender, BLOCK, QUARTER_SAMPLES};
+
+/// Records what reaches the playback bus.
+#[derive(Clone, Default)]
+struct EventLogSynth {
+    events: Arc<Mutex<Vec<MidiLikeEvent>>>,
+}
+
+impl SynthPort for EventLogSynth {
+    fn load_soundfont_from_path(&self, _path: &str) -> Result<SoundFontInfo, SynthError> {
+        Err(SynthError::UnsupportedFormat)
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, _bus: Bus, _gm_program: u8) -> Result<(), SynthError> {
+        Ok(())
+    }
+
+    fn handle_event(&self, bus: Bus, event: MidiLikeEvent, _at: SampleTime) {
+        if bus == Bus::Autopilot {
+            self.events.lock().push(event);
+        }
+    }
+
+    fn render(&self, _bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        out_l[..frames].fill(0.0);
+        out_r[..frames].fill(0.0);
+    }
+}
+
+struct Rig {
+    core: AppCore,
+    render: SharedRender,
+    synth: EventLogSynth,
+    sample_time: SampleTime,
+}
+
+impl Rig {
+    /// The demo scale playing, stopped halfway through its first note.
+    fn mid_note() -> Self {
+        let render: SharedRender = Arc::new(Mutex::new(None));
+        let synth = EventLogSynth::default();
+        let mut core = AppCore::new(
+            Box::new(ManualAudio {
+                render: render.clone(),
+                block: BLOCK,
+            }),
+            Box::new(NullMidiInputPort),
+            Arc::new(synth.clone()),
+            None,
+            None,
+        )
+        .expect("core");
+        core.handle_command(Command::SelectAudioOutput {
+            device_id: DeviceId("manual".to_string()),
+            config: None,
+        })
+        .expect("audio");
+        core.handle_command(Command::LoadScore {
+            source: ScoreSource::InternalDemo("scale".to_string()),
+        })
+        .expect("score");
+        let mut rig = Self {
+            core,
+            render,
+            synth,
+            sample_time: 0,
+        };
+        rig.steps(4);
+        rig.core
+            .handle_command(Command::StartPractice)
+            .expect("start");
+        let start = rig.sample_time;
+        while rig.sample_time < start + QUARTER_SAMPLES / 2 {
+            rig.steps(1);
+        }
+        assert!(rig
+            .synth
+            .events
+            .lock()
+            .iter()
+            .any(|event| matches!(event, MidiLikeEvent::NoteOn { note: 60, .. })));
+        rig.synth.events.lock().clear();
+        rig
+    }
+
+    /// Renders `blocks` blocks, ticking the core after each.
+    fn steps(&mut self, blocks: usize) {
+        for _ in 0..blocks {
+            let mut left = [0.0; BLOCK];
+            let mut right = [0.0; BLOCK];
+            self.render.lock().as_mut().expect("audio opened").render(
+                self.sample_time,
+                &mut left,
+                &mut right,
+            );
+            self.sample_time += BLOCK as SampleTime;
+            self.core.tick();
+        }
+    }
+
+    fn note_offs(&self) -> Vec<u8> {
+        self.synth
+            .events
+            .lock()
+            .iter()
+            .filter_map(|event| match event {
+                MidiLikeEvent::NoteOff { note } => Some(*note),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn a_pause_mid_note_releases_just_that_note() {
+    let mut rig = Rig::mid_note();
+    rig.core
+        .handle_command(Command::PausePractice)
+        .expect("pause");
+    rig.steps(8);
+    assert_eq!(rig.note_offs(), vec![60]);
+}
+
+#[test]
+fn a_stop_mid_note_releases_just_that_note() {
+    let mut rig = Rig::mid_note();
+    rig.core
+        .handle_command(Command::StopPractice)
+        .expect("stop");
+    rig.steps(8);
+    assert_eq!(rig.note_offs(), vec![60]);
+}
+
+#[test]
+fn a_seek_mid_note_releases_it_once_before_playing_on() {
+    let mut rig = Rig::mid_note();
+    // Into F4, which sounds again from there.
+    rig.core
+        .handle_command(Command::Seek { tick: 1_800 })
+        .expect("seek");
+    rig.steps(8);
+    let events = rig.synth.events.lock().clone();
+    assert_eq!(rig.note_offs(), vec![60]);
+    assert!(
+        events
+            .iter()
+            .any(|event| matches!(event, MidiLikeEvent::NoteOn { note: 65, .. })),
+        "{events:?}"
+    );
+}
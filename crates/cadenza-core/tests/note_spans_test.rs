@@ -0,0 +1,229 @@
+use cadenza_core::{Event, HeadlessRunner, PianoRollNoteDto, ScoreSource, SilentSynth};
+use cadenza_domain_score::{
+    save_scorefile_path, Hand, KeyMode, KeySignaturePoint, PartSelection, PlaybackMidiEvent, Score,
+    ScoreFile, ScoreMeta, SpelledPitch, Step, Track,
+};
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::types::Tick;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Both hands on one key. In bar 1 the right hand's G4 lets go while the left hand's is
+/// still held; in bar 2 the left hand's short C4 falls inside the right hand's whole note.
+const SHARED_KEYS: &str = r#"
+<score-partwise version="3.1">
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>1</divisions>
+        <time><beats>4</beats><beat-type>4</beat-type></time>
+        <staves>2</staves>
+      </attributes>
+      <note><pitch><step>G</step><octave>4</octave></pitch><duration>2</duration><voice>1</voice><staff>1</staff></note>
+      <note><rest/><duration>2</duration><voice>1</voice><staff>1</staff></note>
+      <backup><duration>4</duration></backup>
+      <note><rest/><duration>1</duration><voice>2</voice><staff>2</staff></note>
+      <note><pitch><step>G</step><octave>4</octave></pitch><duration>2</duration><voice>2</voice><staff>2</staff></note>
+      <note><rest/><duration>1</duration><voice>2</voice><staff>2</staff></note>
+    </measure>
+    <measure number="2">
+      <note><pitch><step>C</step><octave>4</octave></pitch><duration>4</duration><voice>1</voice><staff>1</staff></note>
+      <backup><duration>4</duration></backup>
+      <note><rest/><duration>1</duration><voice>2</voice><staff>2</staff></note>
+      <note><pitch><step>C</step><octave>4</octave></pitch><duration>1</duration><voice>2</voice><staff>2</staff></note>
+      <note><rest/><duration>2</duration><voice>2</voice><staff>2</staff></note>
+    </measure>
+  </part>
+</score-partwise>
+"#;
+
+fn temp_path(name: &str, extension: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    std::env::temp_dir().join(format!("cadenza-{name}-{nanos}.{extension}"))
+}
+
+/// Loads `source`, then removes `path`, and returns the piano-roll notes.
+fn spans(source: ScoreSource, path: &PathBuf) -> Vec<PianoRollNoteDto> {
+    let mut runner = HeadlessRunner::new(Arc::new(SilentSynth), None).expect("runner");
+    let loaded = runner.load_score(source);
+    let _ = std::fs::remove_file(path);
+    loaded.expect("load");
+    runner
+        .core()
+        .drain_events()
+        .into_iter()
+        .find_map(|event| match event {
+            Event::ScoreViewUpdated { notes, .. } => Some(notes),
+            _ => None,
+        })
+        .expect("score view")
+}
+
+fn lengths(notes: &[PianoRollNoteDto], note: u8) -> Vec<(Tick, Tick, Option<Hand>)> {
+    notes
+        .iter()
+        .filter(|n| n.note == note)
+        .map(|n| (n.start_tick, n.end_tick, n.hand))
+        .collect()
+}
+
+#[test]
+fn voices_sharing_a_key_keep_their_own_lengths() {
+    let path = temp_path("shared-keys", "xml");
+    std::fs::write(&path, SHARED_KEYS).expect("write");
+    let notes = spans(
+        ScoreSource::MusicXmlFile {
+            path: path.display().to_string(),
+            parts: PartSelection::default(),
+        },
+        &path,
+    );
+
+    let quarter = 480;
+    assert_eq!(
+        lengths(&notes, 67),
+        vec![
+            (0, 2 * quarter, Some(Hand::Right)),
+            (quarter, 3 * quarter, Some(Hand::Left)),
+        ]
+    );
+    // Earliest-first pairing alone would end the whole note here instead.
+    assert_eq!(
+        lengths(&notes, 60),
+        vec![
+            (4 * quarter, 8 * quarter, Some(Hand::Right)),
+            (5 * quarter, 6 * quarter, Some(Hand::Left)),
+        ]
+    );
+    assert!(notes.iter().all(|n| n.id.is_some()));
+}
+
+#[test]
+fn without_pairing_ids_a_note_off_ends_the_earliest_held_note() {
+    let event = |tick: Tick, event: MidiLikeEvent| PlaybackMidiEvent {
+        tick,
+        event,
+        hand: None,
+        ornament_of: None,
+        bus: None,
+        note_id: None,
+        spelling: None,
+        transpose: 0,
+        articulations: Vec::new(),
+    };
+    let mut score = Score::new(
+        ScoreMeta {
+            title: None,
+            composer: None,
+            copyright: None,
+            source: cadenza_domain_score::ScoreSource::Internal,
+            origin_path: None,
+        },
+        480,
+    );
+    score.tracks = vec![Track {
+        id: 1,
+        name: "Piano".to_string(),
+        hand: None,
+        targets: Vec::new(),
+        playback_events: vec![
+            event(
+                0,
+                MidiLikeEvent::NoteOn {
+                    note: 67,
+                    velocity: 80,
+                },
+            ),
+            event(
+                480,
+                MidiLikeEvent::NoteOn {
+                    note: 67,
+                    velocity: 80,
+                },
+            ),
+            event(960, MidiLikeEvent::NoteOff { note: 67 }),
+            event(1440, MidiLikeEvent::NoteOff { note: 67 }),
+        ],
+    }];
+    let path = temp_path("unpaired", "cadenza");
+    save_scorefile_path(&ScoreFile::new(score, Vec::new()), &path).expect("save");
+    let notes = spans(ScoreSource::CadenzaFile(path.display().to_string()), &path);
+
+    assert_eq!(lengths(&notes, 67), vec![(0, 960, None), (480, 1440, None)]);
+}
+
+#[test]
+fn notes_are_named_as_written_or_else_in_the_key() {
+    let note_on = |tick: Tick, spelling: Option<SpelledPitch>| PlaybackMidiEvent {
+        tick,
+        event: MidiLikeEvent::NoteOn {
+            note: 63,
+            velocity: 80,
+        },
+        hand: None,
+        ornament_of: None,
+        bus: None,
+        note_id: None,
+        spelling,
+        transpose: 0,
+        articulations: Vec::new(),
+    };
+    let note_off = |tick: Tick| PlaybackMidiEvent {
+        event: MidiLikeEvent::NoteOff { note: 63 },
+        ..note_on(tick, None)
+    };
+    let mut score = Score::new(
+        ScoreMeta {
+            title: None,
+            composer: None,
+            copyright: None,
+            source: cadenza_domain_score::ScoreSource::Internal,
+            origin_path: None,
+        },
+        480,
+    );
+    score.key_signatures = vec![
+        KeySignaturePoint {
+            tick: 0,
+            fifths: -3,
+            mode: KeyMode::Major,
+        },
+        KeySignaturePoint {
+            tick: 960,
+            fifths: 4,
+            mode: KeyMode::Major,
+        },
+    ];
+    let written = SpelledPitch {
+        step: Step::D,
+        alter: 1,
+        octave: 4,
+    };
+    score.tracks = vec![Track {
+        id: 1,
+        name: "Piano".to_string(),
+        hand: None,
+        targets: Vec::new(),
+        playback_events: vec![
+            note_on(0, None),
+            note_off(480),
+            note_on(480, Some(written)),
+            note_off(960),
+            note_on(960, None),
+            note_off(1440),
+        ],
+    }];
+    let path = temp_path("spelled", "cadenza");
+    save_scorefile_path(&ScoreFile::new(score, Vec::new()), &path).expect("save");
+    let notes = spans(ScoreSource::CadenzaFile(path.display().to_string()), &path);
+
+    let names: Vec<&str> = notes.iter().map(|n| n.name.as_str()).collect();
+    assert_eq!(names, vec!["Eb4", "D#4", "D#4"]);
+}
@@ -0,0 +1,149 @@
+use cadenza_core::{AppCore, Command, Event, ScoreSource};
+use cadenza_domain_eval::Grade;
+use cadenza_ports::audio::{AudioError, AudioOutputPort, AudioRenderCallback, AudioStreamHandle};
+use cadenza_ports::midi::{
+    MidiError, MidiInputPort, MidiInputStream, MidiLikeEvent, PlayerEvent, PlayerEventCallback,
+};
+use cadenza_ports::synth::{SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::types::{
+    AudioConfig, AudioOutputDevice, Bus, DeviceId, MidiInputDevice, SampleTime,
+};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A machine without any audio output.
+struct NoOutputs;
+
+impl AudioOutputPort for NoOutputs {
+    fn list_outputs(&self) -> Result<Vec<AudioOutputDevice>, AudioError> {
+        Ok(Vec::new())
+    }
+
+    fn open_output(
+        &self,
+        device_id: &DeviceId,
+        _config: AudioConfig,
+        _cb: Box<dyn AudioRenderCallback>,
+    ) -> Result<Box<dyn AudioStreamHandle>, AudioError> {
+        Err(AudioError::DeviceNotFound(device_id.to_string()))
+    }
+}
+
+type SharedMidiCallback = Arc<Mutex<Option<PlayerEventCallback>>>;
+
+/// MIDI input whose "keyboard" is the test.
+struct FakeMidi {
+    callback: SharedMidiCallback,
+}
+
+struct FakeMidiStream;
+
+impl MidiInputStream for FakeMidiStream {
+    fn close(self: Box<Self>) {}
+}
+
+impl MidiInputPort for FakeMidi {
+    fn list_inputs(&self) -> Result<Vec<MidiInputDevice>, MidiError> {
+        Ok(Vec::new())
+    }
+
+    fn open_input(
+        &self,
+        _device_id: &DeviceId,
+        cb: PlayerEventCallback,
+    ) -> Result<Box<dyn MidiInputStream>, MidiError> {
+        *self.callback.lock() = Some(cb);
+        Ok(Box::new(FakeMidiStream))
+    }
+}
+
+struct SilentSynth;
+
+impl SynthPort for SilentSynth {
+    fn load_soundfont_from_path(&self, _path: &str) -> Result<SoundFontInfo, SynthError> {
+        Err(SynthError::UnsupportedFormat)
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, _bus: Bus, _gm_program: u8) -> Result<(), SynthError> {
+        Ok(())
+    }
+
+    fn handle_event(&self, _bus: Bus, _event: MidiLikeEvent, _at: SampleTime) {}
+
+    fn render(&self, _bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        out_l[..frames].fill(0.0);
+        out_r[..frames].fill(0.0);
+    }
+}
+
+#[test]
+fn practice_runs_on_the_silent_output_without_audio_devices() {
+    let midi: SharedMidiCallback = Arc::new(Mutex::new(None));
+    let mut core = AppCore::new(
+        Box::new(NoOutputs),
+        Box::new(FakeMidi {
+            callback: midi.clone(),
+        }),
+        Arc::new(SilentSynth),
+        None,
+        None,
+    )
+    .expect("core");
+    core.handle_command(Command::SelectMidiInput {
+        device_id: DeviceId("keyboard".to_string()),
+    })
+    .expect("midi");
+    core.handle_command(Command::LoadScore {
+        source: ScoreSource::InternalDemo("scale".to_string()),
+    })
+    .expect("score");
+    core.handle_command(Command::StartPractice)
+        .expect("practice starts without a device");
+    let events = core.drain_events();
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, Event::SilentAudioFallback { .. })));
+
+    // The demo scale's second note, D4, is due half a second in.
+    let started = Instant::now();
+    let mut pressed = false;
+    let mut graded = None;
+    let mut last_tick = 0;
+    while started.elapsed() < Duration::from_secs(3) && graded.is_none() {
+        thread::sleep(Duration::from_millis(5));
+        if !pressed && started.elapsed() >= Duration::from_millis(500) {
+            let callback = midi.lock().clone().expect("midi opened");
+            callback(PlayerEvent {
+                at: Instant::now(),
+                event: MidiLikeEvent::NoteOn {
+                    note: 62,
+                    velocity: 80,
+                },
+            });
+            pressed = true;
+        }
+        core.tick();
+        for event in core.drain_events() {
+            match event {
+                Event::TransportUpdated { tick, .. } => last_tick = tick,
+                Event::JudgeFeedback {
+                    grade,
+                    expected_notes,
+                    ..
+                } if expected_notes == [62] => graded = Some(grade),
+                _ => {}
+            }
+        }
+    }
+
+    // The silent output's clock drives the transport, and judging sees the key press.
+    assert!(last_tick > 0);
+    assert!(
+        matches!(graded, Some(Grade::Perfect | Grade::Good)),
+        "{graded:?}"
+    );
+}
@@ -0,0 +1,132 @@
+use cadenza_core::{
+    AppCore, Command, Event, NullAudioOutputPort, NullMidiInputPort, ScoreSource, SilentSynth,
+};
+use cadenza_ports::omr::{OmrError, OmrOptions, OmrPort, OmrResult};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const ONE_NOTE: &str = r#"
+<score-partwise version="3.1">
+  <work><work-title>Scanned Etude</work-title></work>
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes><divisions>1</divisions></attributes>
+      <note><pitch><step>C</step><octave>4</octave></pitch><duration>4</duration></note>
+    </measure>
+  </part>
+</score-partwise>
+"#;
+
+/// Recognizes every PDF as the same MusicXML file.
+struct FakeOmr {
+    musicxml_path: PathBuf,
+}
+
+impl OmrPort for FakeOmr {
+    fn recognize_pdf(&self, _pdf_path: &str, _options: OmrOptions) -> Result<OmrResult, OmrError> {
+        Ok(OmrResult {
+            musicxml_path: Some(self.musicxml_path.clone()),
+            diagnostics_path: None,
+        })
+    }
+
+    fn diagnostics(&self) -> Result<Option<PathBuf>, OmrError> {
+        Ok(None)
+    }
+}
+
+fn temp_path(name: &str, extension: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    std::env::temp_dir().join(format!("cadenza-{name}-{nanos}.{extension}"))
+}
+
+/// Ticks until the conversion job is over; returns whether it succeeded.
+fn wait_for_conversion(core: &mut AppCore) -> bool {
+    for _ in 0..500 {
+        core.tick();
+        let finished = core
+            .drain_events()
+            .into_iter()
+            .find_map(|event| match event {
+                Event::PdfToMidiFinished { ok, .. } => Some(ok),
+                _ => None,
+            });
+        if let Some(ok) = finished {
+            return ok;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("conversion did not finish");
+}
+
+fn score_origin(core: &mut AppCore) -> (Option<String>, bool, Option<String>) {
+    core.drain_events()
+        .into_iter()
+        .find_map(|event| match event {
+            Event::ScoreViewUpdated {
+                title,
+                from_omr,
+                origin_path,
+                ..
+            } => Some((title, from_omr, origin_path)),
+            _ => None,
+        })
+        .expect("score view")
+}
+
+#[test]
+fn a_converted_pdf_loads_tagged_with_its_origin() {
+    let musicxml_path = temp_path("omr-origin", "xml");
+    std::fs::write(&musicxml_path, ONE_NOTE).expect("write");
+    let midi_path = temp_path("omr-origin", "mid");
+    let pdf_path = "/scans/etude.pdf";
+
+    let mut core = AppCore::new(
+        Box::new(NullAudioOutputPort),
+        Box::new(NullMidiInputPort),
+        Arc::new(SilentSynth),
+        Some(Box::new(FakeOmr {
+            musicxml_path: musicxml_path.clone(),
+        })),
+        None,
+    )
+    .expect("core");
+    let converted = core.handle_command(Command::ConvertPdfToMidi {
+        pdf_path: pdf_path.to_string(),
+        output_path: midi_path.display().to_string(),
+        audiveris_path: None,
+        split_by_hand: false,
+        omr_cleanup: true,
+    });
+    converted.expect("convert");
+    let ok = wait_for_conversion(&mut core);
+    let _ = std::fs::remove_file(&musicxml_path);
+    assert!(ok);
+
+    let loaded = core.handle_command(Command::LoadScore {
+        source: ScoreSource::MidiFile(midi_path.display().to_string()),
+    });
+    let _ = std::fs::remove_file(&midi_path);
+    loaded.expect("load");
+
+    let (title, from_omr, origin_path) = score_origin(&mut core);
+    assert_eq!(title.as_deref(), Some("Scanned Etude"));
+    assert!(from_omr);
+    assert_eq!(origin_path.as_deref(), Some(pdf_path));
+
+    // A plain score has no origin.
+    core.handle_command(Command::LoadScore {
+        source: ScoreSource::InternalDemo("scale".to_string()),
+    })
+    .expect("demo");
+    let (_, from_omr, origin_path) = score_origin(&mut core);
+    assert!(!from_omr);
+    assert_eq!(origin_path, None);
+}
@@ -0,0 +1,169 @@
+use cadenza_core::{AppCore, Command, Event, NullMidiInputPort, ScoreSource, SessionState};
+use cadenza_ports::types::{Bus, DeviceId, SampleTime};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+mod common;
+
+use common::{HeldNoteSynth, ManualAudio, SharedRender, BLOCK, QUARTER_SAMPLES};
+
+struct Rig {
+    core: AppCore,
+    render: SharedRender,
+    synth: Arc<HeldNoteSynth>,
+    sample_time: SampleTime,
+}
+
+impl Rig {
+    /// Audio open with a few blocks rendered; no score unless one is loaded later.
+    fn new() -> Self {
+        let render: SharedRender = Arc::new(Mutex::new(None));
+        let synth = Arc::new(HeldNoteSynth::default());
+        let mut core = AppCore::new(
+            Box::new(ManualAudio {
+                render: render.clone(),
+                block: BLOCK,
+            }),
+            Box::new(NullMidiInputPort),
+            synth.clone(),
+            None,
+            None,
+        )
+        .expect("core");
+        core.handle_command(Command::SelectAudioOutput {
+            device_id: DeviceId("manual".to_string()),
+            config: None,
+        })
+        .expect("audio");
+        let mut rig = Self {
+            core,
+            render,
+            synth,
+            sample_time: 0,
+        };
+        for _ in 0..4 {
+            rig.step();
+        }
+        rig.core.drain_events();
+        rig
+    }
+
+    /// Renders one block and ticks the core; true when the block had any sound.
+    fn step(&mut self) -> bool {
+        let mut left = [0.0; BLOCK];
+        let mut right = [0.0; BLOCK];
+        self.render.lock().as_mut().expect("audio opened").render(
+            self.sample_time,
+            &mut left,
+            &mut right,
+        );
+        self.sample_time += BLOCK as SampleTime;
+        self.core.tick();
+        left.iter().chain(right.iter()).any(|sample| *sample != 0.0)
+    }
+
+    fn note_ons_from(&self, sample_time: SampleTime) -> Vec<(Bus, u8, SampleTime)> {
+        self.synth
+            .note_ons
+            .lock()
+            .iter()
+            .copied()
+            .filter(|&(_, _, at)| at >= sample_time)
+            .collect()
+    }
+
+    fn panic(&mut self) {
+        self.core
+            .handle_command(Command::PanicAllNotes)
+            .expect("panic");
+        assert!(self
+            .core
+            .drain_events()
+            .iter()
+            .any(|event| matches!(event, Event::AllNotesReleased)));
+    }
+}
+
+#[test]
+fn a_panic_drops_the_test_note_before_it_plays() {
+    let mut rig = Rig::new();
+    rig.core
+        .handle_command(Command::TestAudio)
+        .expect("test audio");
+    let panicked_at = rig.sample_time;
+    rig.panic();
+
+    for _ in 0..30 {
+        assert!(!rig.step(), "silent after the panic");
+    }
+    assert!(rig.note_ons_from(panicked_at).is_empty());
+    assert!(rig.synth.held.lock().is_empty());
+
+    // Without a panic the same note sounds.
+    rig.core
+        .handle_command(Command::TestAudio)
+        .expect("test audio");
+    assert!((0..4).any(|_| rig.step()));
+}
+
+#[test]
+fn a_panic_releases_a_held_note_and_its_queued_note_off() {
+    let mut rig = Rig::new();
+    rig.core
+        .handle_command(Command::TestAudio)
+        .expect("test audio");
+    assert!((0..4).any(|_| rig.step()));
+    assert_eq!(rig.synth.held.lock().len(), 1);
+
+    rig.panic();
+    // The limiter's lookahead still holds the last 1.5 ms before the panic.
+    rig.step();
+    for _ in 0..30 {
+        assert!(!rig.step(), "silent after the panic");
+    }
+    assert!(rig.synth.held.lock().is_empty());
+}
+
+#[test]
+fn a_panic_while_practicing_drops_notes_already_scheduled() {
+    let mut rig = Rig::new();
+    rig.core
+        .handle_command(Command::LoadScore {
+            source: ScoreSource::InternalDemo("scale".to_string()),
+        })
+        .expect("score");
+    rig.core
+        .handle_command(Command::StartPractice)
+        .expect("start");
+    let start = rig.sample_time;
+    assert!(rig.step());
+
+    // The second note of the scale is queued for audio a block before it is due.
+    while rig.sample_time + BLOCK as SampleTime * 2 < start + QUARTER_SAMPLES {
+        rig.step();
+    }
+    rig.panic();
+    rig.step();
+    while rig.sample_time < start + QUARTER_SAMPLES + 4 * BLOCK as SampleTime {
+        assert!(!rig.step(), "silent after the panic");
+    }
+    assert!(rig.note_ons_from(start + QUARTER_SAMPLES / 2).is_empty());
+
+    // Practice carries on with the next note.
+    while rig.sample_time < start + 2 * QUARTER_SAMPLES + 4 * BLOCK as SampleTime {
+        rig.step();
+    }
+    let note_ons = rig.note_ons_from(start + QUARTER_SAMPLES / 2);
+    assert_eq!(note_ons.len(), 1);
+    assert_eq!(note_ons[0].0, Bus::Autopilot);
+    rig.core
+        .handle_command(Command::GetSessionState)
+        .expect("state");
+    assert!(rig.core.drain_events().iter().any(|event| matches!(
+        event,
+        Event::SessionStateUpdated {
+            state: SessionState::Running,
+            ..
+        }
+    )));
+}
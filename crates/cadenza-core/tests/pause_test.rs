@@ -0,0 +1,77 @@
+use cadenza_core::{Command, Event};
+use cadenza_domain_eval::Grade;
+use cadenza_ports::types::SampleTime;
+
+mod common;
+
+use common::{Rig, QUARTER_SAMPLES};
+
+/// The judge's good window, 80 ticks, in samples.
+const GOOD_SAMPLES: SampleTime = 4000;
+const PAUSE_SAMPLES: SampleTime = 5 * 48_000;
+
+fn feedback(events: &[Event]) -> Vec<(Vec<u8>, Grade)> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            Event::JudgeFeedback {
+                expected_notes,
+                grade,
+                ..
+            } => Some((expected_notes.clone(), *grade)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn a_pause_inside_a_window_does_not_time_the_target_out() {
+    let mut rig = Rig::new();
+    rig.core
+        .handle_command(Command::StartPractice)
+        .expect("start");
+    let start = rig.sample_time;
+    let mut events = Vec::new();
+
+    // C4 on time; pause just after D4 was due, with little of its window left.
+    let pause_at = start + QUARTER_SAMPLES + GOOD_SAMPLES / 2;
+    let mut pressed = false;
+    while rig.sample_time < pause_at {
+        rig.step();
+        if !pressed {
+            rig.press(60);
+            pressed = true;
+        }
+        events.extend(rig.core.drain_events());
+    }
+    rig.core
+        .handle_command(Command::PausePractice)
+        .expect("pause");
+    let paused_at = rig.sample_time;
+    while rig.sample_time < paused_at + PAUSE_SAMPLES {
+        rig.step();
+        events.extend(rig.core.drain_events());
+    }
+    assert_eq!(feedback(&events).len(), 1);
+
+    rig.core
+        .handle_command(Command::StartPractice)
+        .expect("resume");
+    // Finding the key again takes longer than the window had left at the pause.
+    let resumed_at = rig.sample_time;
+    while rig.sample_time < resumed_at + GOOD_SAMPLES * 3 / 4 {
+        rig.step();
+        events.extend(rig.core.drain_events());
+    }
+    rig.press(62);
+    rig.step();
+    events.extend(rig.core.drain_events());
+
+    let judged = feedback(&events);
+    assert_eq!(judged.len(), 2, "{judged:?}");
+    assert_eq!(judged[1].0, vec![62]);
+    assert!(
+        matches!(judged[1].1, Grade::Perfect | Grade::Good),
+        "{judged:?}"
+    );
+}
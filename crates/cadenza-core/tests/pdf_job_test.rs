@@ -0,0 +1,277 @@
+use cadenza_core::{
+    AppCore, AppError, Command, Event, NullAudioOutputPort, NullMidiInputPort, SilentSynth,
+};
+use cadenza_ports::omr::{OmrError, OmrOptions, OmrPort, OmrResult};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const ONE_NOTE: &str = r#"
+<score-partwise version="3.1">
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes><divisions>1</divisions></attributes>
+      <note><pitch><step>C</step><octave>4</octave></pitch><duration>4</duration></note>
+    </measure>
+  </part>
+</score-partwise>
+"#;
+
+/// Recognizes every PDF as the same MusicXML file.
+struct FakeOmr {
+    musicxml_path: PathBuf,
+}
+
+impl OmrPort for FakeOmr {
+    fn recognize_pdf(&self, _pdf_path: &str, options: OmrOptions) -> Result<OmrResult, OmrError> {
+        options.report_progress("Recognizing");
+        Ok(OmrResult {
+            musicxml_path: Some(self.musicxml_path.clone()),
+            diagnostics_path: Some(PathBuf::from("/logs/omr.log")),
+        })
+    }
+
+    fn diagnostics(&self) -> Result<Option<PathBuf>, OmrError> {
+        Ok(Some(PathBuf::from("/logs/omr.log")))
+    }
+}
+
+/// Runs until cancelled.
+struct StuckOmr;
+
+impl OmrPort for StuckOmr {
+    fn recognize_pdf(&self, _pdf_path: &str, options: OmrOptions) -> Result<OmrResult, OmrError> {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while Instant::now() < deadline {
+            if options.cancel.is_cancelled() {
+                return Err(OmrError::Cancelled);
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        Err(OmrError::Backend("never cancelled".to_string()))
+    }
+
+    fn diagnostics(&self) -> Result<Option<PathBuf>, OmrError> {
+        Ok(None)
+    }
+}
+
+struct BrokenOmr;
+
+impl OmrPort for BrokenOmr {
+    fn recognize_pdf(&self, _pdf_path: &str, _options: OmrOptions) -> Result<OmrResult, OmrError> {
+        Err(OmrError::RecognitionFailed("engine crashed".to_string()))
+    }
+
+    fn diagnostics(&self) -> Result<Option<PathBuf>, OmrError> {
+        Ok(Some(PathBuf::from("/logs/omr.log")))
+    }
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    std::env::temp_dir().join(format!("cadenza-{name}-{nanos}"))
+}
+
+fn core_with(omr: Box<dyn OmrPort>, export_dir: &Path) -> AppCore {
+    let mut core = AppCore::new(
+        Box::new(NullAudioOutputPort),
+        Box::new(NullMidiInputPort),
+        Arc::new(SilentSynth),
+        Some(omr),
+        None,
+    )
+    .expect("core");
+    core.set_export_dir(export_dir.to_path_buf());
+    core.drain_events();
+    core
+}
+
+fn convert(core: &mut AppCore, pdf_path: &str, output_path: &str) -> Result<(), AppError> {
+    core.handle_command(Command::ConvertPdfToMidi {
+        pdf_path: pdf_path.to_string(),
+        output_path: output_path.to_string(),
+        audiveris_path: None,
+        split_by_hand: false,
+        omr_cleanup: true,
+    })
+}
+
+/// Everything the core emits until the job reports it is finished.
+fn run_to_finish(core: &mut AppCore) -> Vec<Event> {
+    let mut events = Vec::new();
+    for _ in 0..500 {
+        events.extend(core.drain_events());
+        if events
+            .iter()
+            .any(|event| matches!(event, Event::PdfToMidiFinished { .. }))
+        {
+            return events;
+        }
+        core.tick();
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("conversion did not finish");
+}
+
+fn stages(events: &[Event]) -> Vec<&str> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            Event::OmrProgress { stage, .. } => Some(stage.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn finished(events: &[Event]) -> (bool, String, Option<String>, String) {
+    events
+        .iter()
+        .find_map(|event| match event {
+            Event::PdfToMidiFinished {
+                ok,
+                output_path,
+                diagnostics_path,
+                message,
+                ..
+            } => Some((
+                *ok,
+                output_path.clone(),
+                diagnostics_path.clone(),
+                message.clone(),
+            )),
+            _ => None,
+        })
+        .expect("finished")
+}
+
+fn command_failures(events: &[Event]) -> Vec<&str> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            Event::CommandFailed { message, .. } => Some(message.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn a_conversion_reports_each_stage_and_writes_the_midi() {
+    let export_dir = temp_path("pdf-job-export");
+    let musicxml_path = temp_path("pdf-job.xml");
+    std::fs::write(&musicxml_path, ONE_NOTE).expect("write");
+    let mut core = core_with(
+        Box::new(FakeOmr {
+            musicxml_path: musicxml_path.clone(),
+        }),
+        &export_dir,
+    );
+
+    convert(&mut core, "/scans/Etude: No. 1.pdf", "").expect("convert");
+    let events = run_to_finish(&mut core);
+    assert_eq!(
+        stages(&events),
+        vec![
+            "Starting",
+            "Recognizing",
+            "Import MusicXML",
+            "Export MIDI",
+            "Done"
+        ]
+    );
+    let (ok, output_path, diagnostics_path, message) = finished(&events);
+    assert!(ok);
+    // Named after the PDF, with characters a file name cannot hold replaced.
+    let expected = export_dir.join("Etude_ No. 1.mid");
+    assert_eq!(output_path, expected.display().to_string());
+    assert!(expected.is_file());
+    assert_eq!(diagnostics_path.as_deref(), Some("/logs/omr.log"));
+    assert!(message.starts_with(&format!("Wrote MIDI to {}", expected.display())));
+    assert!(events.iter().any(|event| matches!(
+        event,
+        Event::OmrDiagnostics { severity, .. } if severity == "info"
+    )));
+    assert!(command_failures(&events).is_empty());
+
+    // The first file is kept; the second gets a suffix.
+    convert(&mut core, "/scans/Etude: No. 1.pdf", "").expect("convert again");
+    let (ok, output_path, _, _) = finished(&run_to_finish(&mut core));
+    assert!(ok);
+    assert_eq!(
+        output_path,
+        export_dir.join("Etude_ No. 1-1.mid").display().to_string()
+    );
+
+    // Relative paths land in the export directory, as MIDI.
+    convert(&mut core, "/scans/etude.pdf", "takes/first.txt").expect("convert relative");
+    let (ok, output_path, _, _) = finished(&run_to_finish(&mut core));
+    assert!(ok);
+    assert_eq!(
+        output_path,
+        export_dir
+            .join("takes")
+            .join("first.mid")
+            .display()
+            .to_string()
+    );
+
+    let _ = std::fs::remove_file(&musicxml_path);
+    let _ = std::fs::remove_dir_all(&export_dir);
+}
+
+#[test]
+fn a_running_conversion_can_be_cancelled_and_blocks_a_second_one() {
+    let export_dir = temp_path("pdf-job-cancel");
+    let mut core = core_with(Box::new(StuckOmr), &export_dir);
+
+    convert(&mut core, "/scans/etude.pdf", "").expect("convert");
+    core.tick();
+    let second = convert(&mut core, "/scans/other.pdf", "");
+    assert!(matches!(second, Err(AppError::InvalidState(_))));
+    assert_eq!(
+        command_failures(&core.drain_events()),
+        vec!["invalid state: PDF conversion already running"]
+    );
+
+    core.handle_command(Command::CancelPdfToMidi)
+        .expect("cancel");
+    let events = run_to_finish(&mut core);
+    let (ok, _, _, message) = finished(&events);
+    assert!(!ok);
+    assert_eq!(message, "Conversion cancelled");
+    // Cancelling is not a failure.
+    assert!(command_failures(&events).is_empty());
+
+    // A new conversion may start once the old one is over.
+    convert(&mut core, "/scans/other.pdf", "").expect("convert after cancel");
+    core.handle_command(Command::CancelPdfToMidi)
+        .expect("cancel");
+    run_to_finish(&mut core);
+    let _ = std::fs::remove_dir_all(&export_dir);
+}
+
+#[test]
+fn a_failed_recognition_is_reported_with_its_log() {
+    let export_dir = temp_path("pdf-job-failed");
+    let mut core = core_with(Box::new(BrokenOmr), &export_dir);
+
+    convert(&mut core, "/scans/etude.pdf", "").expect("convert");
+    let events = run_to_finish(&mut core);
+    assert_eq!(command_failures(&events), vec!["engine crashed"]);
+    assert!(events.iter().any(|event| matches!(
+        event,
+        Event::OmrDiagnostics { severity, message, .. }
+            if severity == "error" && message == "engine crashed"
+    )));
+    let (ok, _, diagnostics_path, message) = finished(&events);
+    assert!(!ok);
+    assert_eq!(message, "engine crashed");
+    assert_eq!(diagnostics_path.as_deref(), Some("/logs/omr.log"));
+    let _ = std::fs::remove_dir_all(&export_dir);
+}
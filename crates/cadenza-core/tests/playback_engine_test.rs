@@ -0,0 +1,216 @@
+use cadenza_core::PlaybackEngine;
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::playback::{
+    LoopRange, PlaybackEvent, PlaybackNotification, PlaybackPort, PlaybackRouteHint, PlaybackScore,
+    ScheduledEvent, TempoPoint,
+};
+use cadenza_ports::types::{SampleTime, Tick};
+
+const SAMPLE_RATE_HZ: u32 = 48_000;
+const BLOCK: u64 = 512;
+/// Arbitrary embedder clock position when playback starts.
+const START_SAMPLE: SampleTime = 100_000;
+/// One quarter at 120 bpm.
+const QUARTER_SAMPLES: SampleTime = 24_000;
+
+fn event(tick: Tick, event: MidiLikeEvent) -> PlaybackEvent {
+    PlaybackEvent {
+        tick,
+        event,
+        route_hint: PlaybackRouteHint::None,
+    }
+}
+
+fn score(events: Vec<PlaybackEvent>) -> PlaybackScore {
+    PlaybackScore {
+        ppq: 480,
+        tempo_map: vec![TempoPoint {
+            tick: 0,
+            us_per_quarter: 500_000,
+        }],
+        events,
+    }
+}
+
+fn note_on(note: u8) -> MidiLikeEvent {
+    MidiLikeEvent::NoteOn { note, velocity: 90 }
+}
+
+fn note_off(note: u8) -> MidiLikeEvent {
+    MidiLikeEvent::NoteOff { note }
+}
+
+/// Advances the engine block by block like an audio callback would, checking that every
+/// event arrives before it is due.
+fn run_blocks(engine: &PlaybackEngine, blocks: u64) -> Vec<ScheduledEvent> {
+    let mut received = Vec::new();
+    for block in 0..blocks {
+        let now = START_SAMPLE + block * BLOCK;
+        engine.advance_to_sample(now).expect("advance");
+        for scheduled in engine.poll_scheduled_events(BLOCK).expect("poll") {
+            assert!(
+                scheduled.sample_time >= now,
+                "{scheduled:?} arrived late at {now}"
+            );
+            received.push(scheduled);
+        }
+    }
+    received
+}
+
+#[test]
+fn engine_follows_the_external_sample_clock() {
+    let engine = PlaybackEngine::new(SAMPLE_RATE_HZ);
+    engine
+        .load_score(score(vec![
+            event(0, note_on(60)),
+            event(240, note_off(60)),
+            event(480, note_on(64)),
+            event(720, note_off(64)),
+        ]))
+        .expect("load");
+
+    // Nothing plays, and nothing moves, before play.
+    assert!(run_blocks(&engine, 10).is_empty());
+    assert_eq!(engine.transport_snapshot().tick, 0);
+
+    engine.play().expect("play");
+    let received = run_blocks(&engine, 100);
+
+    let timed: Vec<_> = received
+        .iter()
+        .map(|scheduled| (scheduled.sample_time, scheduled.event))
+        .collect();
+    assert_eq!(
+        timed,
+        vec![
+            (START_SAMPLE, note_on(60)),
+            (START_SAMPLE + QUARTER_SAMPLES / 2, note_off(60)),
+            (START_SAMPLE + QUARTER_SAMPLES, note_on(64)),
+            (START_SAMPLE + QUARTER_SAMPLES * 3 / 2, note_off(64)),
+        ]
+    );
+
+    let snapshot = engine.transport_snapshot();
+    assert!(snapshot.playing);
+    assert_eq!(snapshot.sample_time, START_SAMPLE + 99 * BLOCK);
+    // 99 blocks of 512 samples at 48 kHz, 120 bpm, 480 ppq.
+    assert_eq!(snapshot.tick, 99 * 512 * 480 / 24_000);
+}
+
+#[test]
+fn looped_playback_restarts_on_time_every_pass() {
+    let engine = PlaybackEngine::new(SAMPLE_RATE_HZ);
+    engine
+        .load_score(score(vec![
+            event(0, note_on(60)),
+            event(240, note_off(60)),
+            event(480, note_on(67)),
+        ]))
+        .expect("load");
+    engine
+        .set_loop(Some(LoopRange {
+            start_tick: 0,
+            end_tick: 480,
+        }))
+        .expect("loop");
+    engine.play().expect("play");
+
+    // Just over three loop passes.
+    let received = run_blocks(&engine, 150);
+
+    let note_on_samples: Vec<_> = received
+        .iter()
+        .filter(|scheduled| matches!(scheduled.event, MidiLikeEvent::NoteOn { .. }))
+        .map(|scheduled| (scheduled.sample_time, scheduled.event))
+        .collect();
+    assert_eq!(
+        note_on_samples,
+        (0..4)
+            .map(|pass| (START_SAMPLE + pass * QUARTER_SAMPLES, note_on(60)))
+            .collect::<Vec<_>>()
+    );
+    assert!(engine.transport_snapshot().tick < 480);
+}
+
+#[test]
+fn loop_wraps_are_reported_at_the_wrap_sample() {
+    let engine = PlaybackEngine::new(SAMPLE_RATE_HZ);
+    engine
+        .load_score(score(vec![event(0, note_on(60)), event(240, note_on(62))]))
+        .expect("load");
+    // Set after loading, while stopped.
+    engine
+        .set_loop(Some(LoopRange {
+            start_tick: 0,
+            end_tick: 480,
+        }))
+        .expect("loop");
+    engine.play().expect("play");
+
+    // Up to just before the loop end: nothing to report yet.
+    run_blocks(&engine, 46);
+    assert!(engine.poll_notifications().is_empty());
+
+    // Through two wraps.
+    let mut notifications = Vec::new();
+    for block in 46..100 {
+        let now = START_SAMPLE + block * BLOCK;
+        engine.advance_to_sample(now).expect("advance");
+        engine.poll_scheduled_events(BLOCK).expect("poll");
+        notifications.extend(engine.poll_notifications());
+    }
+    assert_eq!(
+        notifications,
+        vec![
+            PlaybackNotification::LoopWrapped {
+                at_sample: START_SAMPLE + QUARTER_SAMPLES,
+            },
+            PlaybackNotification::LoopWrapped {
+                at_sample: START_SAMPLE + 2 * QUARTER_SAMPLES,
+            },
+        ]
+    );
+}
+
+#[test]
+fn reaching_the_end_and_seeking_are_reported() {
+    let engine = PlaybackEngine::new(SAMPLE_RATE_HZ);
+    engine
+        .load_score(score(vec![event(0, note_on(60)), event(480, note_off(60))]))
+        .expect("load");
+    engine.play().expect("play");
+
+    run_blocks(&engine, 100);
+    assert_eq!(
+        engine.poll_notifications(),
+        vec![PlaybackNotification::ReachedEnd]
+    );
+
+    engine.seek(240).expect("seek");
+    let received = run_blocks(&engine, 60);
+    // Middle C is held across the seek: struck again, then released.
+    assert!(
+        matches!(
+            received[..],
+            [
+                ScheduledEvent {
+                    event: MidiLikeEvent::NoteOn { note: 60, .. },
+                    ..
+                },
+                ScheduledEvent {
+                    event: MidiLikeEvent::NoteOff { note: 60 },
+                    ..
+                },
+            ]
+        ),
+        "{received:?}"
+    );
+    assert_eq!(
+        engine.poll_notifications(),
+        vec![
+            PlaybackNotification::Seeked { tick: 240 },
+            PlaybackNotification::ReachedEnd,
+        ]
+    );
+}
@@ -0,0 +1,113 @@
+use cadenza_core::{Command, Event};
+use cadenza_domain_eval::Grade;
+use cadenza_ports::types::SampleTime;
+
+mod common;
+
+use common::{Rig, QUARTER_SAMPLES};
+
+/// The practice range covers the scale's fourth and fifth notes, F4 and G4.
+const RANGE_START: i64 = 1440;
+const RANGE_END: i64 = 2400;
+const PASS_SAMPLES: SampleTime = 2 * QUARTER_SAMPLES;
+
+fn feedback(events: &[Event]) -> Vec<(Vec<u8>, Grade)> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            Event::JudgeFeedback {
+                expected_notes,
+                grade,
+                ..
+            } => Some((expected_notes.clone(), *grade)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn practice_starts_at_the_range_and_only_judges_its_targets() {
+    let mut rig = Rig::new();
+    rig.core
+        .handle_command(Command::SetPracticeRange {
+            start_tick: RANGE_START,
+            end_tick: RANGE_END,
+        })
+        .expect("range");
+    let events = rig.core.drain_events();
+    assert!(events.iter().any(|event| matches!(
+        event,
+        Event::TransportUpdated { tick: RANGE_START, loop_range: Some(range), .. }
+            if range.end_tick == RANGE_END
+    )));
+    assert!(events.iter().any(|event| matches!(
+        event,
+        Event::FocusChanged {
+            tick: Some(RANGE_START),
+            ..
+        }
+    )));
+
+    rig.core
+        .handle_command(Command::StartPractice)
+        .expect("start");
+    let start = rig.sample_time;
+    let mut events = Vec::new();
+    // Untouched for one and a half passes.
+    while rig.sample_time < start + PASS_SAMPLES + QUARTER_SAMPLES {
+        rig.step();
+        events.extend(rig.core.drain_events());
+    }
+
+    assert_eq!(
+        feedback(&events),
+        vec![
+            (vec![65], Grade::Miss),
+            (vec![67], Grade::Miss),
+            (vec![65], Grade::Miss),
+        ]
+    );
+}
+
+#[test]
+fn each_loop_pass_judges_the_range_again() {
+    let mut rig = Rig::new();
+    rig.core
+        .handle_command(Command::SetPracticeRange {
+            start_tick: RANGE_START,
+            end_tick: RANGE_END,
+        })
+        .expect("range");
+    rig.core
+        .handle_command(Command::StartPractice)
+        .expect("start");
+    let start = rig.sample_time;
+
+    let mut events = Vec::new();
+    let mut presses = (0..2)
+        .flat_map(|pass| {
+            let pass_start = start + pass * PASS_SAMPLES;
+            [(pass_start, 65), (pass_start + QUARTER_SAMPLES, 67)]
+        })
+        .peekable();
+    while rig.sample_time < start + 2 * PASS_SAMPLES {
+        rig.step();
+        events.extend(rig.core.drain_events());
+        if let Some(&(due, note)) = presses.peek() {
+            if rig.sample_time >= due {
+                rig.press(note);
+                presses.next();
+            }
+        }
+    }
+    // Let the last press be judged.
+    rig.step();
+    events.extend(rig.core.drain_events());
+
+    let judged = feedback(&events);
+    assert_eq!(judged.len(), 4, "{judged:?}");
+    for ((notes, grade), expected) in judged.iter().zip([65, 67, 65, 67]) {
+        assert_eq!(notes, &vec![expected]);
+        assert!(matches!(grade, Grade::Perfect | Grade::Good), "{judged:?}");
+    }
+}
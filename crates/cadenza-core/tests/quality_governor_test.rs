@@ -0,0 +1,239 @@
+use cadenza_core::{
+    AppCore, AudioCallbackCounts, Command, Event, NullMidiInputPort, QualityGovernor, VirtualClock,
+    QUALITY_REDUCE_AFTER_CHECKS, QUALITY_RESTORE_AFTER_CHECKS,
+};
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::synth::{QualityHint, SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::types::{Bus, DeviceId, SampleTime};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+mod common;
+
+use common::{ManualAudio, SharedRender};
+
+/// 64 frames at 48 kHz leave the callback 1.3 ms.
+const BLOCK: usize = 64;
+
+fn overruns(overruns: u64) -> AudioCallbackCounts {
+    AudioCallbackCounts {
+        overruns,
+        ..AudioCallbackCounts::default()
+    }
+}
+
+#[test]
+fn quality_drops_after_sustained_overruns_and_returns_after_a_calm_stretch() {
+    let mut governor = QualityGovernor::new();
+    let mut total = 0;
+    // A single bad second, then a calm one, is not sustained.
+    total += 4;
+    assert_eq!(governor.check(&overruns(total)), None);
+    assert_eq!(governor.check(&overruns(total)), None);
+
+    for _ in 1..QUALITY_REDUCE_AFTER_CHECKS {
+        total += 2;
+        assert_eq!(governor.check(&overruns(total)), None);
+    }
+    total += 2;
+    assert_eq!(governor.check(&overruns(total)), Some(QualityHint::Reduced));
+    assert_eq!(governor.quality(), QualityHint::Reduced);
+
+    // An overrun while reduced starts the calm stretch over.
+    for _ in 1..QUALITY_RESTORE_AFTER_CHECKS {
+        assert_eq!(governor.check(&overruns(total)), None);
+    }
+    total += 1;
+    assert_eq!(governor.check(&overruns(total)), None);
+    for _ in 1..QUALITY_RESTORE_AFTER_CHECKS {
+        assert_eq!(governor.check(&overruns(total)), None);
+    }
+    assert_eq!(governor.check(&overruns(total)), Some(QualityHint::Full));
+}
+
+#[test]
+fn a_forced_quality_holds_until_handed_back() {
+    let mut governor = QualityGovernor::new();
+    assert_eq!(governor.set_forced(Some(QualityHint::Full)), None);
+    let mut total = 0;
+    for _ in 0..QUALITY_REDUCE_AFTER_CHECKS * 2 {
+        total += 3;
+        assert_eq!(governor.check(&overruns(total)), None);
+    }
+    assert_eq!(governor.quality(), QualityHint::Full);
+
+    assert_eq!(
+        governor.set_forced(Some(QualityHint::Reduced)),
+        Some(QualityHint::Reduced)
+    );
+    for _ in 0..QUALITY_RESTORE_AFTER_CHECKS * 2 {
+        assert_eq!(governor.check(&overruns(total)), None);
+    }
+    assert_eq!(governor.set_forced(None), Some(QualityHint::Full));
+    assert_eq!(governor.forced(), None);
+}
+
+/// Takes longer to render than the block lasts, and records the quality hints it gets.
+#[derive(Default)]
+struct SlowSynth {
+    hints: Mutex<Vec<QualityHint>>,
+    fast: AtomicBool,
+}
+
+impl SynthPort for SlowSynth {
+    fn load_soundfont_from_path(&self, _path: &str) -> Result<SoundFontInfo, SynthError> {
+        Err(SynthError::UnsupportedFormat)
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, _bus: Bus, _gm_program: u8) -> Result<(), SynthError> {
+        Ok(())
+    }
+
+    fn handle_event(&self, _bus: Bus, _event: MidiLikeEvent, _at: SampleTime) {}
+
+    fn render(&self, _bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        if !self.fast.load(Ordering::Relaxed) {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        out_l[..frames].fill(0.0);
+        out_r[..frames].fill(0.0);
+    }
+
+    fn set_quality(&self, hint: QualityHint) {
+        self.hints.lock().push(hint);
+        self.fast
+            .store(hint == QualityHint::Reduced, Ordering::Relaxed);
+    }
+}
+
+struct Rig {
+    core: AppCore,
+    synth: Arc<SlowSynth>,
+    render: SharedRender,
+    clock: Arc<VirtualClock>,
+    sample_time: u64,
+}
+
+impl Rig {
+    fn new() -> Self {
+        let render: SharedRender = Arc::new(Mutex::new(None));
+        let synth = Arc::new(SlowSynth::default());
+        let clock = Arc::new(VirtualClock::new());
+        let mut core = AppCore::new(
+            Box::new(ManualAudio {
+                render: render.clone(),
+                block: BLOCK,
+            }),
+            Box::new(NullMidiInputPort),
+            synth.clone(),
+            None,
+            None,
+        )
+        .expect("core");
+        core.set_clock(clock.clone());
+        core.handle_command(Command::SelectAudioOutput {
+            device_id: DeviceId("manual".to_string()),
+            config: None,
+        })
+        .expect("audio");
+        core.drain_events();
+        Self {
+            core,
+            synth,
+            render,
+            clock,
+            sample_time: 0,
+        }
+    }
+
+    /// Renders a couple of blocks, then lets a second of core time pass.
+    fn second(&mut self) -> Vec<Event> {
+        let mut left = [0.0; BLOCK];
+        let mut right = [0.0; BLOCK];
+        for _ in 0..2 {
+            self.render.lock().as_mut().expect("audio opened").render(
+                self.sample_time,
+                &mut left,
+                &mut right,
+            );
+            self.sample_time += BLOCK as u64;
+        }
+        self.clock.advance(Duration::from_secs(1));
+        self.core.tick();
+        self.core.drain_events()
+    }
+}
+
+#[test]
+fn the_core_reduces_synth_quality_while_the_output_overruns() {
+    let mut rig = Rig::new();
+    let mut events = Vec::new();
+    for _ in 0..QUALITY_REDUCE_AFTER_CHECKS + 1 {
+        events.extend(rig.second());
+    }
+    assert_eq!(*rig.synth.hints.lock(), vec![QualityHint::Reduced]);
+    assert_eq!(
+        events
+            .iter()
+            .filter(|event| matches!(
+                event,
+                Event::QualityReduced {
+                    automatic: true,
+                    ..
+                }
+            ))
+            .count(),
+        1
+    );
+
+    // Reduced quality keeps up, and full quality comes back after the calm stretch.
+    for _ in 0..QUALITY_RESTORE_AFTER_CHECKS + 1 {
+        events.extend(rig.second());
+    }
+    assert_eq!(
+        *rig.synth.hints.lock(),
+        vec![QualityHint::Reduced, QualityHint::Full]
+    );
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, Event::QualityRestored { automatic: true })));
+}
+
+#[test]
+fn a_manual_quality_overrides_the_governor() {
+    let mut rig = Rig::new();
+    rig.core
+        .handle_command(Command::SetSynthQuality {
+            quality: Some(QualityHint::Full),
+        })
+        .expect("force full");
+    assert!(rig.synth.hints.lock().is_empty());
+    for _ in 0..QUALITY_REDUCE_AFTER_CHECKS + 1 {
+        rig.second();
+    }
+    assert!(rig.synth.hints.lock().is_empty());
+
+    rig.core
+        .handle_command(Command::SetSynthQuality {
+            quality: Some(QualityHint::Reduced),
+        })
+        .expect("force reduced");
+    assert!(rig.core.drain_events().iter().any(|event| matches!(
+        event,
+        Event::QualityReduced {
+            automatic: false,
+            ..
+        }
+    )));
+    rig.core
+        .handle_command(Command::SetSynthQuality { quality: None })
+        .expect("automatic");
+    assert_eq!(
+        *rig.synth.hints.lock(),
+        vec![QualityHint::Reduced, QualityHint::Full]
+    );
+}
@@ -0,0 +1,275 @@
+use cadenza_core::{AppCore, Command, Event, ScoreSource, SessionState, SilentSynth};
+use cadenza_ports::audio::{AudioError, AudioOutputPort, AudioRenderCallback, AudioStreamHandle};
+use cadenza_ports::midi::{MidiError, MidiInputPort, MidiInputStream, PlayerEventCallback};
+use cadenza_ports::types::{AudioConfig, AudioOutputDevice, DeviceId, MidiInputDevice, Tick};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+mod common;
+
+use common::MemoryStorage;
+
+const BLOCK: usize = 512;
+
+type SharedRender = Arc<Mutex<Option<Box<dyn AudioRenderCallback>>>>;
+
+/// Opens and closes of each kind of stream, by device id.
+#[derive(Clone, Default)]
+struct StreamLog {
+    opened: Arc<Mutex<Vec<String>>>,
+    closed: Arc<Mutex<Vec<String>>>,
+}
+
+/// Output whose audio callback the test drives by hand; only the latest graph renders.
+struct ManualAudio {
+    render: SharedRender,
+    log: StreamLog,
+}
+
+struct LoggedStream {
+    id: String,
+    log: StreamLog,
+}
+
+impl AudioStreamHandle for LoggedStream {
+    fn close(self: Box<Self>) {
+        self.log.closed.lock().push(self.id);
+    }
+}
+
+impl MidiInputStream for LoggedStream {
+    fn close(self: Box<Self>) {
+        self.log.closed.lock().push(self.id);
+    }
+}
+
+impl AudioOutputPort for ManualAudio {
+    fn list_outputs(&self) -> Result<Vec<AudioOutputDevice>, AudioError> {
+        Ok(vec![AudioOutputDevice {
+            id: DeviceId("speakers".to_string()),
+            name: "Speakers".to_string(),
+            default_config: AudioConfig {
+                sample_rate_hz: 48_000,
+                channels: 2,
+                buffer_size_frames: Some(BLOCK as u32),
+            },
+            buffer_size_range: None,
+        }])
+    }
+
+    fn open_output(
+        &self,
+        device_id: &DeviceId,
+        _config: AudioConfig,
+        cb: Box<dyn AudioRenderCallback>,
+    ) -> Result<Box<dyn AudioStreamHandle>, AudioError> {
+        *self.render.lock() = Some(cb);
+        self.log.opened.lock().push(device_id.0.clone());
+        Ok(Box::new(LoggedStream {
+            id: device_id.0.clone(),
+            log: self.log.clone(),
+        }))
+    }
+}
+
+/// Lists the inputs in `listed`, which the test changes as devices come and go.
+struct ListedMidi {
+    listed: Arc<Mutex<Vec<&'static str>>>,
+    log: StreamLog,
+}
+
+impl MidiInputPort for ListedMidi {
+    fn list_inputs(&self) -> Result<Vec<MidiInputDevice>, MidiError> {
+        Ok(self
+            .listed
+            .lock()
+            .iter()
+            .map(|id| MidiInputDevice {
+                id: DeviceId(id.to_string()),
+                name: id.to_string(),
+                is_available: true,
+            })
+            .collect())
+    }
+
+    fn open_input(
+        &self,
+        device_id: &DeviceId,
+        _cb: PlayerEventCallback,
+    ) -> Result<Box<dyn MidiInputStream>, MidiError> {
+        self.log.opened.lock().push(device_id.0.clone());
+        Ok(Box::new(LoggedStream {
+            id: device_id.0.clone(),
+            log: self.log.clone(),
+        }))
+    }
+}
+
+struct Rig {
+    core: AppCore,
+    render: SharedRender,
+    audio_log: StreamLog,
+    midi_log: StreamLog,
+    listed_midi: Arc<Mutex<Vec<&'static str>>>,
+    storage: MemoryStorage,
+    sample_time: u64,
+}
+
+impl Rig {
+    fn new() -> Self {
+        let render: SharedRender = Arc::new(Mutex::new(None));
+        let audio_log = StreamLog::default();
+        let midi_log = StreamLog::default();
+        let listed_midi = Arc::new(Mutex::new(vec!["keyboard", "pads"]));
+        let storage = MemoryStorage::default();
+        let mut core = AppCore::new(
+            Box::new(ManualAudio {
+                render: render.clone(),
+                log: audio_log.clone(),
+            }),
+            Box::new(ListedMidi {
+                listed: listed_midi.clone(),
+                log: midi_log.clone(),
+            }),
+            Arc::new(SilentSynth),
+            None,
+            Some(Box::new(storage.clone())),
+        )
+        .expect("core");
+        core.handle_command(Command::SelectAudioOutput {
+            device_id: DeviceId("speakers".to_string()),
+            config: None,
+        })
+        .expect("audio");
+        core.handle_command(Command::SelectMidiInput {
+            device_id: DeviceId("keyboard".to_string()),
+        })
+        .expect("midi");
+        core.handle_command(Command::LoadScore {
+            source: ScoreSource::InternalDemo("scale".to_string()),
+        })
+        .expect("score");
+        core.drain_events();
+        Self {
+            core,
+            render,
+            audio_log,
+            midi_log,
+            listed_midi,
+            storage,
+            sample_time: 0,
+        }
+    }
+
+    /// Renders `blocks` blocks on the newest stream, ticking the core after each.
+    fn play(&mut self, blocks: usize) {
+        let mut left = [0.0; BLOCK];
+        let mut right = [0.0; BLOCK];
+        for _ in 0..blocks {
+            self.render.lock().as_mut().expect("audio opened").render(
+                self.sample_time,
+                &mut left,
+                &mut right,
+            );
+            self.sample_time += BLOCK as u64;
+            self.core.tick();
+            self.core.drain_events();
+        }
+    }
+
+    /// Transport position, as the core reports it on request.
+    fn position(&mut self) -> Tick {
+        self.core
+            .handle_command(Command::GetSessionState)
+            .expect("state");
+        last_tick(&self.core.drain_events()).expect("transport")
+    }
+
+    fn refresh(&mut self) -> Vec<Event> {
+        self.core
+            .handle_command(Command::RefreshDevices)
+            .expect("refresh");
+        // The new stream counts its samples from zero.
+        self.sample_time = 0;
+        self.core.drain_events()
+    }
+}
+
+fn last_tick(events: &[Event]) -> Option<Tick> {
+    events.iter().rev().find_map(|event| match event {
+        Event::TransportUpdated { tick, .. } => Some(*tick),
+        _ => None,
+    })
+}
+
+#[test]
+fn refreshing_reopens_both_devices_and_keeps_the_position() {
+    let mut rig = Rig::new();
+    rig.core
+        .handle_command(Command::StartPractice)
+        .expect("start");
+    rig.play(60);
+    let before = rig.position();
+    assert!(before > 0);
+
+    let events = rig.refresh();
+    assert_eq!(*rig.audio_log.opened.lock(), vec!["speakers", "speakers"]);
+    assert_eq!(*rig.audio_log.closed.lock(), vec!["speakers"]);
+    assert_eq!(*rig.midi_log.opened.lock(), vec!["keyboard", "keyboard"]);
+    assert_eq!(*rig.midi_log.closed.lock(), vec!["keyboard"]);
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, Event::MidiInputsUpdated { devices } if devices.len() == 2)));
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, Event::AudioOutputsUpdated { .. })));
+    assert!(events.iter().any(|event| matches!(
+        event,
+        Event::SessionStateUpdated {
+            state: SessionState::Running,
+            ..
+        }
+    )));
+    let reopened_at = last_tick(&events).expect("transport");
+    assert!(
+        (reopened_at - before).abs() <= 40,
+        "{before} -> {reopened_at}"
+    );
+
+    // Practice carries on from there on the new stream.
+    rig.play(20);
+    let after = rig.position();
+    assert!(after > reopened_at, "{reopened_at} -> {after}");
+    assert!(after < reopened_at + 480, "{reopened_at} -> {after}");
+}
+
+#[test]
+fn a_missing_saved_input_falls_back_to_the_first_listed_and_stays_saved() {
+    let mut rig = Rig::new();
+    *rig.listed_midi.lock() = vec!["pads"];
+    rig.refresh();
+
+    assert_eq!(
+        rig.midi_log.opened.lock().last().map(String::as_str),
+        Some("pads")
+    );
+    let saved = rig.storage.settings.lock().clone();
+    assert_eq!(
+        saved.selected_midi_in,
+        Some(DeviceId("keyboard".to_string()))
+    );
+    assert_eq!(
+        saved.selected_audio_out,
+        Some(DeviceId("speakers".to_string()))
+    );
+
+    // With nothing listed the input stays closed until its device returns.
+    *rig.listed_midi.lock() = Vec::new();
+    let opened = rig.midi_log.opened.lock().len();
+    rig.refresh();
+    assert_eq!(rig.midi_log.opened.lock().len(), opened);
+    assert_eq!(
+        rig.midi_log.closed.lock().last().map(String::as_str),
+        Some("pads")
+    );
+}
@@ -0,0 +1,206 @@
+use cadenza_core::{AppCore, Command, Event, NullMidiInputPort, SelfTestStageKind, SelfTestStatus};
+use cadenza_ports::audio::{AudioError, AudioOutputPort, AudioRenderCallback, AudioStreamHandle};
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::synth::{SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::types::{AudioConfig, AudioOutputDevice, Bus, DeviceId, SampleTime};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+/// Grants the rate it was built with, whatever is asked for, as a driver locked to the
+/// system mixer does. With `drifting` set it grants 100 Hz more on every open instead.
+struct StubbornOutput {
+    granted_hz: Arc<Mutex<u32>>,
+    drifting: bool,
+    requested: Arc<Mutex<Vec<u32>>>,
+}
+
+struct FakeAudioStream {
+    granted: AudioConfig,
+}
+
+impl AudioStreamHandle for FakeAudioStream {
+    fn close(self: Box<Self>) {}
+
+    fn effective_config(&self) -> Option<AudioConfig> {
+        Some(self.granted)
+    }
+}
+
+impl AudioOutputPort for StubbornOutput {
+    fn list_outputs(&self) -> Result<Vec<AudioOutputDevice>, AudioError> {
+        Ok(vec![AudioOutputDevice {
+            id: DeviceId("built-in".to_string()),
+            name: "Built-in".to_string(),
+            default_config: AudioConfig {
+                sample_rate_hz: 48_000,
+                channels: 2,
+                buffer_size_frames: None,
+            },
+            buffer_size_range: None,
+        }])
+    }
+
+    fn open_output(
+        &self,
+        _device_id: &DeviceId,
+        config: AudioConfig,
+        _cb: Box<dyn AudioRenderCallback>,
+    ) -> Result<Box<dyn AudioStreamHandle>, AudioError> {
+        self.requested.lock().push(config.sample_rate_hz);
+        let mut granted_hz = self.granted_hz.lock();
+        let granted = AudioConfig {
+            sample_rate_hz: *granted_hz,
+            ..config
+        };
+        if self.drifting {
+            *granted_hz += 100;
+        }
+        Ok(Box::new(FakeAudioStream { granted }))
+    }
+}
+
+/// Remembers every sample rate it is set to, in order.
+#[derive(Clone, Default)]
+struct RateSynth {
+    rates: Arc<Mutex<Vec<u32>>>,
+}
+
+impl SynthPort for RateSynth {
+    fn load_soundfont_from_path(&self, _path: &str) -> Result<SoundFontInfo, SynthError> {
+        Err(SynthError::UnsupportedFormat)
+    }
+
+    fn set_sample_rate(&self, sample_rate_hz: u32) {
+        self.rates.lock().push(sample_rate_hz);
+    }
+
+    fn set_program(&self, _bus: Bus, _gm_program: u8) -> Result<(), SynthError> {
+        Ok(())
+    }
+
+    fn handle_event(&self, _bus: Bus, _event: MidiLikeEvent, _at: SampleTime) {}
+
+    fn render(&self, _bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        out_l[..frames].fill(0.0);
+        out_r[..frames].fill(0.0);
+    }
+}
+
+struct Rig {
+    core: AppCore,
+    synth: RateSynth,
+    requested: Arc<Mutex<Vec<u32>>>,
+}
+
+fn launch(granted_hz: u32, drifting: bool) -> Rig {
+    let synth = RateSynth::default();
+    let requested = Arc::new(Mutex::new(Vec::new()));
+    let mut core = AppCore::new(
+        Box::new(StubbornOutput {
+            granted_hz: Arc::new(Mutex::new(granted_hz)),
+            drifting,
+            requested: requested.clone(),
+        }),
+        Box::new(NullMidiInputPort),
+        Arc::new(synth.clone()),
+        None,
+        None,
+    )
+    .expect("core");
+    core.drain_events();
+    synth.rates.lock().clear();
+    requested.lock().clear();
+    Rig {
+        core,
+        synth,
+        requested,
+    }
+}
+
+fn select_at(core: &mut AppCore, sample_rate_hz: u32) {
+    core.handle_command(Command::SelectAudioOutput {
+        device_id: DeviceId("built-in".to_string()),
+        config: Some(AudioConfig {
+            sample_rate_hz,
+            channels: 2,
+            buffer_size_frames: None,
+        }),
+    })
+    .expect("select");
+}
+
+#[test]
+fn a_stream_granted_another_rate_is_reopened_at_that_rate() {
+    let mut rig = launch(44_100, false);
+    select_at(&mut rig.core, 48_000);
+
+    assert_eq!(*rig.requested.lock(), vec![48_000, 44_100]);
+    assert_eq!(rig.synth.rates.lock().last(), Some(&44_100));
+    let events = rig.core.drain_events();
+    assert!(events.iter().any(|event| matches!(
+        event,
+        Event::SampleRateCorrected {
+            requested_hz: 48_000,
+            effective_hz: 44_100,
+            ..
+        }
+    )));
+    assert!(events.iter().any(|event| matches!(
+        event,
+        Event::AudioOutputConfigured { effective, .. } if effective.sample_rate_hz == 44_100
+    )));
+
+    // Reopening for a new buffer size asks for the rate that worked.
+    rig.core
+        .handle_command(Command::SetAudioBufferSize { frames: Some(256) })
+        .expect("buffer size");
+    assert_eq!(rig.requested.lock().last(), Some(&44_100));
+    assert!(!rig
+        .core
+        .drain_events()
+        .iter()
+        .any(|event| matches!(event, Event::SampleRateCorrected { .. })));
+}
+
+#[test]
+fn a_matching_rate_opens_once_without_a_warning() {
+    let mut rig = launch(48_000, false);
+    select_at(&mut rig.core, 48_000);
+    assert_eq!(*rig.requested.lock(), vec![48_000]);
+    assert_eq!(*rig.synth.rates.lock(), vec![48_000]);
+    assert!(!rig
+        .core
+        .drain_events()
+        .iter()
+        .any(|event| matches!(event, Event::SampleRateCorrected { .. })));
+}
+
+#[test]
+fn the_self_test_fails_while_the_rates_still_differ() {
+    let mut rig = launch(44_100, true);
+    select_at(&mut rig.core, 48_000);
+    rig.core.drain_events();
+
+    rig.core
+        .handle_command(Command::RunAudioSelfTest)
+        .expect("self-test");
+    let stages = rig
+        .core
+        .drain_events()
+        .into_iter()
+        .find_map(|event| match event {
+            Event::AudioSelfTestResult { passed, stages } => {
+                assert!(!passed);
+                Some(stages)
+            }
+            _ => None,
+        })
+        .expect("result");
+    assert_eq!(stages[0].stage, SelfTestStageKind::DeviceOpen);
+    assert_eq!(stages[0].status, SelfTestStatus::Failed);
+    assert!(
+        stages[0].detail.contains("44200 Hz"),
+        "{}",
+        stages[0].detail
+    );
+}
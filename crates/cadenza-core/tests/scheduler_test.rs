@@ -0,0 +1,741 @@
+use cadenza_core::{AutopilotFeel, Scheduler, SchedulerConfig, Transport};
+use cadenza_domain_score::{Hand, PlaybackMidiEvent, TempoPoint};
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::playback::{LoopRange, PlaybackMode, ScheduledEvent};
+use cadenza_ports::types::{Bus, SampleTime, Tick};
+
+const SAMPLE_RATE_HZ: u32 = 48_000;
+const START_SAMPLE: SampleTime = 100_000;
+/// One quarter at 120 bpm.
+const QUARTER_SAMPLES: SampleTime = 24_000;
+
+fn event(tick: Tick, event: MidiLikeEvent) -> PlaybackMidiEvent {
+    PlaybackMidiEvent {
+        tick,
+        event,
+        hand: None,
+        ornament_of: None,
+        bus: None,
+        note_id: None,
+        spelling: None,
+        transpose: 0,
+        articulations: Vec::new(),
+    }
+}
+
+fn playing_transport() -> Transport {
+    let mut transport = Transport::new(
+        480,
+        SAMPLE_RATE_HZ,
+        vec![TempoPoint {
+            tick: 0,
+            us_per_quarter: 500_000,
+        }],
+    );
+    transport.align_to_sample_time(START_SAMPLE);
+    transport.play();
+    transport
+}
+
+/// Polls once per audio block, returning each event with the clock at which it was emitted.
+fn run(
+    scheduler: &mut Scheduler,
+    transport: &mut Transport,
+    block: u64,
+    until: SampleTime,
+) -> Vec<(SampleTime, ScheduledEvent)> {
+    let mut received = Vec::new();
+    let mut now = START_SAMPLE;
+    while now < until {
+        transport.sync_to_sample_time(now);
+        for scheduled in scheduler.schedule(transport) {
+            received.push((now, scheduled));
+        }
+        now += block;
+    }
+    received
+}
+
+#[test]
+fn events_after_the_loop_start_are_scheduled_before_the_wrap() {
+    let mut scheduler = Scheduler::new(
+        SAMPLE_RATE_HZ,
+        SchedulerConfig {
+            lookahead_ms: 60,
+            buffer_frames: None,
+        },
+    );
+    scheduler.set_score(vec![
+        event(
+            0,
+            MidiLikeEvent::NoteOn {
+                note: 60,
+                velocity: 90,
+            },
+        ),
+        event(120, MidiLikeEvent::NoteOff { note: 60 }),
+        event(
+            360,
+            MidiLikeEvent::NoteOn {
+                note: 64,
+                velocity: 90,
+            },
+        ),
+        // Past the loop end: cut by the wrap.
+        event(720, MidiLikeEvent::NoteOff { note: 64 }),
+    ]);
+    let range = LoopRange {
+        start_tick: 0,
+        end_tick: 480,
+    };
+    scheduler.set_loop(Some(range));
+    let mut transport = playing_transport();
+    transport.set_loop(Some(range));
+
+    // 2048-frame blocks are longer than a 60 ms window, so the first notes of each pass
+    // would arrive late if they had to wait for the poll after the wrap.
+    let received = run(
+        &mut scheduler,
+        &mut transport,
+        2048,
+        START_SAMPLE + 4 * QUARTER_SAMPLES,
+    );
+
+    for (emitted_at, scheduled) in &received {
+        assert!(
+            scheduled.sample_time >= *emitted_at,
+            "{scheduled:?} emitted late at {emitted_at}"
+        );
+    }
+    let on_60: Vec<_> = received
+        .iter()
+        .filter(|(_, s)| matches!(s.event, MidiLikeEvent::NoteOn { note: 60, .. }))
+        .map(|(_, s)| s.sample_time)
+        .collect();
+    assert_eq!(on_60[0], START_SAMPLE);
+    assert!(on_60.len() >= 4);
+    for pair in on_60.windows(2) {
+        assert_eq!(pair[1] - pair[0], QUARTER_SAMPLES);
+    }
+
+    // The held note is released exactly at each wrap, ahead of the next pass.
+    let wraps = on_60[1..].to_vec();
+    let off_64: Vec<_> = received
+        .iter()
+        .filter(|(_, s)| s.event == MidiLikeEvent::NoteOff { note: 64 })
+        .map(|(_, s)| s.sample_time)
+        .collect();
+    assert_eq!(off_64, wraps);
+    for wrap in wraps {
+        let order: Vec<_> = received
+            .iter()
+            .filter(|(_, s)| s.sample_time == wrap)
+            .map(|(_, s)| s.event)
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                MidiLikeEvent::NoteOff { note: 64 },
+                MidiLikeEvent::NoteOn {
+                    note: 60,
+                    velocity: 90
+                },
+            ]
+        );
+    }
+}
+
+/// A note every 10 ms, polled once per `buffer_frames` block at 44.1 kHz.
+fn poll_dense_score(buffer_frames: u32) -> (Scheduler, usize) {
+    let sample_rate_hz = 44_100;
+    let mut scheduler = Scheduler::new(
+        sample_rate_hz,
+        SchedulerConfig {
+            lookahead_ms: 30,
+            buffer_frames: Some(buffer_frames),
+        },
+    );
+    // 120 bpm at 480 ppq: 9.6 ticks per 10 ms.
+    scheduler.set_score(
+        (0..200)
+            .map(|index| {
+                event(
+                    index * 10,
+                    MidiLikeEvent::NoteOn {
+                        note: 60,
+                        velocity: 90,
+                    },
+                )
+            })
+            .collect(),
+    );
+    let mut transport = Transport::new(480, sample_rate_hz, Vec::new());
+    transport.play();
+
+    let mut received = 0;
+    let mut now = 0;
+    while now < sample_rate_hz as u64 * 3 {
+        transport.sync_to_sample_time(now);
+        for scheduled in scheduler.schedule(&mut transport) {
+            assert!(
+                scheduled.sample_time >= transport.now_sample(),
+                "{scheduled:?} scheduled in the past at {now} with {buffer_frames}-frame buffers"
+            );
+            received += 1;
+        }
+        now += buffer_frames as u64;
+    }
+    (scheduler, received)
+}
+
+#[test]
+fn lookahead_covers_large_audio_buffers() {
+    let (scheduler, received) = poll_dense_score(2048);
+    // 1.5 buffers of 2048 frames at 44.1 kHz.
+    assert!((scheduler.effective_lookahead_ms() - 69.66).abs() < 0.01);
+    assert_eq!(received, 200);
+}
+
+#[test]
+fn lookahead_keeps_its_floor_with_small_audio_buffers() {
+    let (scheduler, received) = poll_dense_score(64);
+    assert_eq!(scheduler.effective_lookahead_ms(), 30.0);
+    assert_eq!(received, 200);
+}
+
+fn humanized_run() -> Vec<ScheduledEvent> {
+    let mut scheduler = Scheduler::new(
+        SAMPLE_RATE_HZ,
+        SchedulerConfig {
+            lookahead_ms: 30,
+            buffer_frames: None,
+        },
+    );
+    scheduler.set_autopilot_feel(AutopilotFeel {
+        velocity_scale: 0.5,
+        humanize_timing_ms: 8.0,
+        humanize_velocity: 10,
+        ..AutopilotFeel::default()
+    });
+    // Eighth notes held for a sixteenth.
+    scheduler.set_score(
+        (0..16)
+            .flat_map(|index| {
+                let note = 60 + (index % 5) as u8;
+                [
+                    event(
+                        index * 240,
+                        MidiLikeEvent::NoteOn {
+                            note,
+                            velocity: 100,
+                        },
+                    ),
+                    event(index * 240 + 120, MidiLikeEvent::NoteOff { note }),
+                ]
+            })
+            .collect(),
+    );
+    let mut transport = playing_transport();
+    run(
+        &mut scheduler,
+        &mut transport,
+        256,
+        START_SAMPLE + 9 * QUARTER_SAMPLES,
+    )
+    .into_iter()
+    .map(|(_, scheduled)| scheduled)
+    .collect()
+}
+
+#[test]
+fn humanized_playback_is_deterministic_and_bounded() {
+    let first = humanized_run();
+    assert_eq!(first, humanized_run());
+    assert_eq!(first.len(), 32);
+
+    // 8 ms at 48 kHz.
+    let max_offset = 384;
+    let mut offsets = Vec::new();
+    for (index, pair) in first.chunks(2).enumerate() {
+        let grid = START_SAMPLE + index as u64 * QUARTER_SAMPLES / 2;
+        let (MidiLikeEvent::NoteOn { velocity, .. }, MidiLikeEvent::NoteOff { .. }) =
+            (pair[0].event, pair[1].event)
+        else {
+            panic!("unexpected pair {pair:?}");
+        };
+        assert!((40..=60).contains(&velocity), "velocity {velocity}");
+        let offset = pair[0].sample_time as i64 - grid as i64;
+        assert!(offset.abs() <= max_offset, "offset {offset}");
+        // The NoteOff moves with its NoteOn.
+        assert_eq!(
+            pair[1].sample_time - pair[0].sample_time,
+            QUARTER_SAMPLES / 4
+        );
+        offsets.push(offset);
+    }
+    assert!(offsets.iter().any(|&offset| offset != 0));
+}
+
+/// Events reaching the autopilot bus in Accompaniment mode for one routing.
+fn accompaniment_output(play_left: bool, play_right: bool, suppress_pedal: bool) -> Vec<u8> {
+    let mut scheduler = Scheduler::new(
+        SAMPLE_RATE_HZ,
+        SchedulerConfig {
+            lookahead_ms: 30,
+            buffer_frames: None,
+        },
+    );
+    scheduler.set_mode(PlaybackMode::Accompaniment);
+    scheduler.set_accompaniment_route(play_left, play_right);
+    scheduler.set_accompaniment_pedal_suppressed(suppress_pedal);
+    let hand_note = |tick, note, hand| PlaybackMidiEvent {
+        hand,
+        ..event(tick, MidiLikeEvent::NoteOn { note, velocity: 90 })
+    };
+    scheduler.set_score(vec![
+        event(0, MidiLikeEvent::Cc64 { value: 127 }),
+        hand_note(0, 48, Some(Hand::Left)),
+        hand_note(0, 72, Some(Hand::Right)),
+        hand_note(0, 60, None),
+        event(240, MidiLikeEvent::Cc64 { value: 0 }),
+    ]);
+    let mut transport = playing_transport();
+    run(
+        &mut scheduler,
+        &mut transport,
+        512,
+        START_SAMPLE + QUARTER_SAMPLES,
+    )
+    .into_iter()
+    .map(|(_, scheduled)| match scheduled.event {
+        MidiLikeEvent::NoteOn { note, .. } => note,
+        // Pedal as a pseudo-note, for compact expectations.
+        MidiLikeEvent::Cc64 { value } => value,
+        MidiLikeEvent::NoteOff { note } => note,
+        MidiLikeEvent::ControlChange { .. } => unreachable!("scores only carry the pedal"),
+    })
+    .collect()
+}
+
+#[test]
+fn pedal_follows_the_accompaniment_route() {
+    const DOWN: u8 = 127;
+    const UP: u8 = 0;
+    let cases = [
+        (true, true, false, vec![DOWN, 48, 60, 72, UP]),
+        (true, false, false, vec![DOWN, 48, 60, UP]),
+        (false, true, false, vec![DOWN, 60, 72, UP]),
+        // No hand plays: the pedal would only sustain unassigned notes.
+        (false, false, false, vec![60]),
+        (true, true, true, vec![48, 60, 72]),
+        (true, false, true, vec![48, 60]),
+        (false, true, true, vec![60, 72]),
+        (false, false, true, vec![60]),
+    ];
+    for (play_left, play_right, suppress_pedal, expected) in cases {
+        assert_eq!(
+            accompaniment_output(play_left, play_right, suppress_pedal),
+            expected,
+            "left {play_left}, right {play_right}, suppress {suppress_pedal}"
+        );
+    }
+}
+
+#[test]
+fn routing_a_hand_off_releases_its_held_chord() {
+    let mut scheduler = Scheduler::new(
+        SAMPLE_RATE_HZ,
+        SchedulerConfig {
+            lookahead_ms: 30,
+            buffer_frames: None,
+        },
+    );
+    scheduler.set_mode(PlaybackMode::Accompaniment);
+    let hand_event = |tick, midi, hand| PlaybackMidiEvent {
+        hand: Some(hand),
+        ..event(tick, midi)
+    };
+    let mut score = Vec::new();
+    for note in [48, 52, 55] {
+        score.push(hand_event(
+            0,
+            MidiLikeEvent::NoteOn { note, velocity: 90 },
+            Hand::Left,
+        ));
+        score.push(hand_event(960, MidiLikeEvent::NoteOff { note }, Hand::Left));
+    }
+    score.push(hand_event(
+        0,
+        MidiLikeEvent::NoteOn {
+            note: 72,
+            velocity: 90,
+        },
+        Hand::Right,
+    ));
+    score.push(hand_event(
+        960,
+        MidiLikeEvent::NoteOff { note: 72 },
+        Hand::Right,
+    ));
+    scheduler.set_score(score);
+    let mut transport = playing_transport();
+
+    let before = run(
+        &mut scheduler,
+        &mut transport,
+        512,
+        START_SAMPLE + QUARTER_SAMPLES,
+    );
+    assert_eq!(before.len(), 4);
+
+    // Halfway through the chord.
+    scheduler.set_accompaniment_route(false, true);
+    let toggle_sample = START_SAMPLE + QUARTER_SAMPLES;
+    transport.sync_to_sample_time(toggle_sample);
+    let released = scheduler.schedule(&mut transport);
+    let mut released_notes: Vec<_> = released
+        .iter()
+        .map(|scheduled| {
+            assert_eq!(scheduled.sample_time, toggle_sample);
+            scheduled.event
+        })
+        .collect();
+    released_notes.sort_by_key(|event| match event {
+        MidiLikeEvent::NoteOff { note } => *note,
+        _ => panic!("unexpected {event:?}"),
+    });
+    assert_eq!(
+        released_notes,
+        [48, 52, 55].map(|note| MidiLikeEvent::NoteOff { note })
+    );
+
+    // Only the right hand's own NoteOff follows.
+    let mut after = Vec::new();
+    let mut now = toggle_sample;
+    while now < START_SAMPLE + 3 * QUARTER_SAMPLES {
+        now += 512;
+        transport.sync_to_sample_time(now);
+        after.extend(scheduler.schedule(&mut transport));
+    }
+    let after: Vec<_> = after.iter().map(|scheduled| scheduled.event).collect();
+    assert_eq!(after, vec![MidiLikeEvent::NoteOff { note: 72 }]);
+}
+
+#[test]
+fn tagged_events_keep_their_bus_through_accompaniment_filtering() {
+    let mut scheduler = Scheduler::new(
+        SAMPLE_RATE_HZ,
+        SchedulerConfig {
+            lookahead_ms: 30,
+            buffer_frames: None,
+        },
+    );
+    scheduler.set_mode(PlaybackMode::Accompaniment);
+    scheduler.set_accompaniment_route(false, true);
+    let click = |tick| PlaybackMidiEvent {
+        bus: Some(Bus::MetronomeFx),
+        ..event(
+            tick,
+            MidiLikeEvent::NoteOn {
+                note: 76,
+                velocity: 100,
+            },
+        )
+    };
+    let hand_note = |tick, note, hand| PlaybackMidiEvent {
+        hand: Some(hand),
+        ..event(tick, MidiLikeEvent::NoteOn { note, velocity: 90 })
+    };
+    scheduler.set_score(vec![
+        click(0),
+        hand_note(0, 48, Hand::Left),
+        hand_note(0, 72, Hand::Right),
+        click(480),
+    ]);
+    let mut transport = playing_transport();
+
+    let routed: Vec<_> = run(
+        &mut scheduler,
+        &mut transport,
+        512,
+        START_SAMPLE + 2 * QUARTER_SAMPLES,
+    )
+    .into_iter()
+    .map(|(_, scheduled)| (scheduled.bus, scheduled.event))
+    .collect();
+    assert_eq!(
+        routed,
+        vec![
+            (
+                Bus::Autopilot,
+                MidiLikeEvent::NoteOn {
+                    note: 72,
+                    velocity: 90
+                }
+            ),
+            (
+                Bus::MetronomeFx,
+                MidiLikeEvent::NoteOn {
+                    note: 76,
+                    velocity: 100
+                }
+            ),
+            (
+                Bus::MetronomeFx,
+                MidiLikeEvent::NoteOn {
+                    note: 76,
+                    velocity: 100
+                }
+            ),
+        ]
+    );
+}
+
+fn events_after_seeking(tick: Tick, suppress_pedal: bool) -> Vec<(SampleTime, Bus, MidiLikeEvent)> {
+    let mut scheduler = Scheduler::new(
+        SAMPLE_RATE_HZ,
+        SchedulerConfig {
+            lookahead_ms: 30,
+            buffer_frames: None,
+        },
+    );
+    scheduler.set_mode(PlaybackMode::Accompaniment);
+    scheduler.set_accompaniment_pedal_suppressed(suppress_pedal);
+    let note_on = |tick, note| event(tick, MidiLikeEvent::NoteOn { note, velocity: 90 });
+    scheduler.set_score(vec![
+        event(0, MidiLikeEvent::Cc64 { value: 127 }),
+        note_on(0, 48),
+        event(480, MidiLikeEvent::NoteOff { note: 48 }),
+        note_on(960, 52),
+        event(1440, MidiLikeEvent::NoteOff { note: 52 }),
+        event(1920, MidiLikeEvent::Cc64 { value: 0 }),
+        note_on(2400, 55),
+    ]);
+    let mut transport = playing_transport();
+    transport.seek(tick);
+    transport.align_to_sample_time(START_SAMPLE);
+    scheduler.seek(tick);
+    run(&mut scheduler, &mut transport, 512, START_SAMPLE + 2_048)
+        .into_iter()
+        .map(|(_, scheduled)| (scheduled.sample_time, scheduled.bus, scheduled.event))
+        .collect()
+}
+
+#[test]
+fn seeking_into_a_pedalled_passage_puts_the_pedal_back_down_first() {
+    let events = events_after_seeking(960, false);
+    assert!(
+        matches!(
+            events[..],
+            [
+                (_, Bus::Autopilot, MidiLikeEvent::Cc64 { value: 127 }),
+                (_, Bus::Autopilot, MidiLikeEvent::NoteOn { note: 52, .. }),
+                ..
+            ]
+        ),
+        "{events:?}"
+    );
+    // After the pedal up that flushing sends at the seek.
+    assert!(events[0].0 > START_SAMPLE, "{events:?}");
+
+    // Past the pedal lift, or with the score pedal left to the player, nothing is restored.
+    for (tick, suppress_pedal) in [(2400, false), (960, true)] {
+        let events = events_after_seeking(tick, suppress_pedal);
+        assert!(
+            matches!(events[..], [(_, _, MidiLikeEvent::NoteOn { .. }), ..]),
+            "{tick}: {events:?}"
+        );
+    }
+}
+
+/// A C major chord held through bar 1 under the pedal, then E5 on beat 3 and a pedal lift
+/// at the barline.
+fn held_chord_score() -> Vec<PlaybackMidiEvent> {
+    let mut score = vec![event(0, MidiLikeEvent::Cc64 { value: 127 })];
+    for note in [48, 52, 55] {
+        score.push(event(
+            0,
+            MidiLikeEvent::NoteOn {
+                note,
+                velocity: 100,
+            },
+        ));
+        score.push(event(1920, MidiLikeEvent::NoteOff { note }));
+    }
+    score.push(event(
+        960,
+        MidiLikeEvent::NoteOn {
+            note: 76,
+            velocity: 100,
+        },
+    ));
+    score.push(event(1440, MidiLikeEvent::NoteOff { note: 76 }));
+    score.push(event(1900, MidiLikeEvent::Cc64 { value: 0 }));
+    score.sort_by_key(|event| event.tick);
+    score
+}
+
+#[test]
+fn state_at_tick_holds_notes_struck_before_it() {
+    let score = held_chord_score();
+    let held = |tick| -> Vec<MidiLikeEvent> {
+        Scheduler::state_at_tick(&score, tick)
+            .notes
+            .iter()
+            .map(|event| event.event)
+            .collect()
+    };
+    let chord = [48, 52, 55].map(|note| MidiLikeEvent::NoteOn {
+        note,
+        velocity: 100,
+    });
+
+    assert_eq!(held(0), vec![]);
+    assert_eq!(held(960), chord.to_vec());
+    assert_eq!(held(1200).len(), 4);
+    // Released on the tick itself.
+    assert_eq!(held(1440), chord.to_vec());
+    assert_eq!(held(1920), vec![]);
+
+    let pedal = Scheduler::state_at_tick(&score, 1440).pedal;
+    assert_eq!(pedal.len(), 1);
+    assert_eq!(pedal[0].event, MidiLikeEvent::Cc64 { value: 127 });
+    let pedal = Scheduler::state_at_tick(&score, 1920).pedal;
+    assert_eq!(pedal[0].event, MidiLikeEvent::Cc64 { value: 0 });
+}
+
+/// Loops beats 2 to 4 of [`held_chord_score`] for three passes.
+fn looped_mid_chord(feel: AutopilotFeel) -> Vec<ScheduledEvent> {
+    let mut scheduler = Scheduler::new(
+        SAMPLE_RATE_HZ,
+        SchedulerConfig {
+            lookahead_ms: 30,
+            buffer_frames: None,
+        },
+    );
+    scheduler.set_autopilot_feel(feel);
+    scheduler.set_score(held_chord_score());
+    let range = LoopRange {
+        start_tick: 480,
+        end_tick: 1440,
+    };
+    scheduler.set_loop(Some(range));
+    let mut transport = playing_transport();
+    transport.set_loop(Some(range));
+    transport.seek(480);
+    transport.align_to_sample_time(START_SAMPLE);
+    scheduler.seek(480);
+    run(
+        &mut scheduler,
+        &mut transport,
+        512,
+        START_SAMPLE + 6 * QUARTER_SAMPLES - 4_096,
+    )
+    .into_iter()
+    .map(|(_, scheduled)| scheduled)
+    .collect()
+}
+
+#[test]
+fn a_loop_starting_mid_chord_sounds_the_chord_on_every_pass() {
+    let events = looped_mid_chord(AutopilotFeel::default());
+    let pass_starts: Vec<SampleTime> = (0..3)
+        .map(|pass| START_SAMPLE + pass * 2 * QUARTER_SAMPLES + 1)
+        .collect();
+    for &start in &pass_starts {
+        let mut at_start: Vec<MidiLikeEvent> = events
+            .iter()
+            .filter(|scheduled| scheduled.sample_time == start)
+            .map(|scheduled| scheduled.event)
+            .collect();
+        at_start.sort_by_key(|event| match event {
+            MidiLikeEvent::Cc64 { .. } => 0,
+            MidiLikeEvent::NoteOn { note, .. } => *note,
+            _ => u8::MAX,
+        });
+        assert_eq!(
+            at_start,
+            vec![
+                MidiLikeEvent::Cc64 { value: 127 },
+                MidiLikeEvent::NoteOn {
+                    note: 48,
+                    velocity: 60
+                },
+                MidiLikeEvent::NoteOn {
+                    note: 52,
+                    velocity: 60
+                },
+                MidiLikeEvent::NoteOn {
+                    note: 55,
+                    velocity: 60
+                },
+            ],
+            "pass at {start}: {events:?}"
+        );
+    }
+    // Each pass ends by releasing the chord and E5, which ends on the loop end; the pedal is
+    // still down there.
+    let wrap = START_SAMPLE + 2 * QUARTER_SAMPLES;
+    let mut released: Vec<MidiLikeEvent> = events
+        .iter()
+        .filter(|scheduled| scheduled.sample_time == wrap)
+        .map(|scheduled| scheduled.event)
+        .collect();
+    released.sort_by_key(|event| match event {
+        MidiLikeEvent::NoteOff { note } => *note,
+        _ => 0,
+    });
+    assert_eq!(
+        released,
+        vec![
+            MidiLikeEvent::Cc64 { value: 0 },
+            MidiLikeEvent::NoteOff { note: 48 },
+            MidiLikeEvent::NoteOff { note: 52 },
+            MidiLikeEvent::NoteOff { note: 55 },
+            MidiLikeEvent::NoteOff { note: 76 },
+        ]
+    );
+
+    // Without resounding, each pass starts with the pedal alone.
+    let events = looped_mid_chord(AutopilotFeel {
+        resound_velocity_scale: 0.0,
+        ..AutopilotFeel::default()
+    });
+    for &start in &pass_starts {
+        let at_start: Vec<MidiLikeEvent> = events
+            .iter()
+            .filter(|scheduled| scheduled.sample_time == start)
+            .map(|scheduled| scheduled.event)
+            .collect();
+        assert_eq!(at_start, vec![MidiLikeEvent::Cc64 { value: 127 }]);
+    }
+}
+
+#[test]
+fn release_sent_lets_go_of_the_notes_still_sounding_only() {
+    let mut scheduler = Scheduler::new(
+        SAMPLE_RATE_HZ,
+        SchedulerConfig {
+            lookahead_ms: 30,
+            buffer_frames: Some(256),
+        },
+    );
+    let note_on = |note| MidiLikeEvent::NoteOn { note, velocity: 90 };
+    scheduler.set_score(vec![
+        event(0, note_on(60)),
+        event(240, MidiLikeEvent::NoteOff { note: 60 }),
+        event(240, note_on(62)),
+        event(960, MidiLikeEvent::NoteOff { note: 62 }),
+        event(960, note_on(64)),
+    ]);
+    let mut transport = playing_transport();
+    // C4 has ended and E4 is not due yet; only D4 sounds.
+    let until = START_SAMPLE + QUARTER_SAMPLES;
+    run(&mut scheduler, &mut transport, 256, until);
+
+    let released = scheduler.release_sent(until);
+    let events: Vec<_> = released.iter().map(|event| event.event).collect();
+    assert_eq!(events, vec![MidiLikeEvent::NoteOff { note: 62 }]);
+    assert!(released.iter().all(|event| event.sample_time == until));
+    assert!(scheduler.release_sent(until).is_empty());
+}
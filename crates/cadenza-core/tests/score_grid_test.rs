@@ -0,0 +1,140 @@
+use cadenza_core::{Event, GridLineDto, GridLineKind, HeadlessRunner, ScoreSource, SilentSynth};
+use cadenza_domain_score::{
+    save_scorefile_path, KeyMode, KeySignaturePoint, PlaybackMidiEvent, Score, ScoreFile,
+    ScoreMeta, TimeSignaturePoint, Track,
+};
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::types::Tick;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// An eighth at ppq 480.
+const EIGHTH: Tick = 240;
+
+/// An eighth-note pickup, two bars of 6/8 eighths, then a bar of 3/4 ending at tick 4560.
+fn six_eight_piece() -> Score {
+    let mut score = Score::new(
+        ScoreMeta {
+            title: Some("Barcarolle".to_string()),
+            composer: None,
+            copyright: None,
+            source: cadenza_domain_score::ScoreSource::Internal,
+            origin_path: None,
+        },
+        480,
+    );
+    score.pickup_ticks = EIGHTH;
+    score.time_signatures = vec![
+        TimeSignaturePoint {
+            tick: 0,
+            numerator: 6,
+            denominator: 8,
+        },
+        TimeSignaturePoint {
+            tick: 3120,
+            numerator: 3,
+            denominator: 4,
+        },
+    ];
+    score.key_signatures = vec![KeySignaturePoint {
+        tick: 0,
+        fifths: 1,
+        mode: KeyMode::Major,
+    }];
+    let playback_events = (0..19)
+        .flat_map(|idx| {
+            let tick = idx * EIGHTH;
+            [
+                (
+                    tick,
+                    MidiLikeEvent::NoteOn {
+                        note: 67,
+                        velocity: 80,
+                    },
+                ),
+                (tick + EIGHTH, MidiLikeEvent::NoteOff { note: 67 }),
+            ]
+        })
+        .map(|(tick, event)| PlaybackMidiEvent {
+            tick,
+            event,
+            hand: None,
+            ornament_of: None,
+            bus: None,
+            note_id: None,
+            spelling: None,
+            transpose: 0,
+            articulations: Vec::new(),
+        })
+        .collect();
+    score.tracks = vec![Track {
+        id: 1,
+        name: "Piano".to_string(),
+        hand: None,
+        targets: Vec::new(),
+        playback_events,
+    }];
+    score
+}
+
+fn line(tick: Tick, kind: GridLineKind) -> GridLineDto {
+    GridLineDto { tick, kind }
+}
+
+#[test]
+fn six_eight_bars_beat_in_dotted_quarters() {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let path = std::env::temp_dir().join(format!("cadenza-score-grid-{nanos}.cadenza"));
+    save_scorefile_path(&ScoreFile::new(six_eight_piece(), Vec::new()), &path).expect("save");
+    let mut runner = HeadlessRunner::new(Arc::new(SilentSynth), None).expect("runner");
+    let loaded = runner.load_score(ScoreSource::CadenzaFile(path.display().to_string()));
+    let _ = std::fs::remove_file(&path);
+    loaded.expect("load");
+
+    let (grid, key_signatures) = runner
+        .core()
+        .drain_events()
+        .into_iter()
+        .find_map(|event| match event {
+            Event::ScoreViewUpdated {
+                grid,
+                key_signatures,
+                ..
+            } => Some((grid, key_signatures)),
+            _ => None,
+        })
+        .expect("score view");
+
+    use GridLineKind::{Bar, Beat, Subdivision};
+    let expected = vec![
+        // The pickup is the last eighth of a 6/8 bar.
+        line(0, Subdivision),
+        line(240, Bar),
+        line(480, Subdivision),
+        line(720, Subdivision),
+        line(960, Beat),
+        line(1200, Subdivision),
+        line(1440, Subdivision),
+        line(1680, Bar),
+        line(1920, Subdivision),
+        line(2160, Subdivision),
+        line(2400, Beat),
+        line(2640, Subdivision),
+        line(2880, Subdivision),
+        // 3/4 beats in quarters, split into eighths.
+        line(3120, Bar),
+        line(3360, Subdivision),
+        line(3600, Beat),
+        line(3840, Subdivision),
+        line(4080, Beat),
+        line(4320, Subdivision),
+        // The final bar line at the end of the score, and nothing after it.
+        line(4560, Bar),
+    ];
+    assert_eq!(grid, expected);
+    assert_eq!(key_signatures.len(), 1);
+    assert_eq!(key_signatures[0].fifths, 1);
+}
@@ -0,0 +1,208 @@
+use cadenza_core::{
+    AppCore, AppError, Command, Event, NullAudioOutputPort, NullMidiInputPort, ScoreSource,
+};
+use cadenza_domain_score::ScoreSoundOverrides;
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::storage::{SettingsDto, StorageError, StoragePort};
+use cadenza_ports::synth::{SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::types::{Bus, SampleTime};
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Remembers every soundfont load and program change, in order.
+#[derive(Clone, Default)]
+struct ProgramSynth {
+    soundfonts: Arc<Mutex<Vec<String>>>,
+    programs: Arc<Mutex<Vec<(Bus, u8)>>>,
+}
+
+impl ProgramSynth {
+    fn take_programs(&self) -> Vec<(Bus, u8)> {
+        std::mem::take(&mut *self.programs.lock())
+    }
+
+    /// The program each bus was last set to since the previous call.
+    fn take_settled_programs(&self) -> BTreeMap<Bus, u8> {
+        self.take_programs().into_iter().collect()
+    }
+
+    fn take_soundfonts(&self) -> Vec<String> {
+        std::mem::take(&mut *self.soundfonts.lock())
+    }
+}
+
+impl SynthPort for ProgramSynth {
+    fn load_soundfont_from_path(&self, path: &str) -> Result<SoundFontInfo, SynthError> {
+        self.soundfonts.lock().push(path.to_string());
+        Ok(SoundFontInfo {
+            name: path.to_string(),
+            preset_count: 128,
+        })
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, bus: Bus, gm_program: u8) -> Result<(), SynthError> {
+        self.programs.lock().push((bus, gm_program));
+        Ok(())
+    }
+
+    fn handle_event(&self, _bus: Bus, _event: MidiLikeEvent, _at: SampleTime) {}
+
+    fn render(&self, _bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        out_l[..frames].fill(0.0);
+        out_r[..frames].fill(0.0);
+    }
+}
+
+#[derive(Clone, Default)]
+struct MemoryStorage {
+    settings: Arc<Mutex<SettingsDto>>,
+}
+
+impl StoragePort for MemoryStorage {
+    fn load_settings(&self) -> Result<SettingsDto, StorageError> {
+        Ok(self.settings.lock().clone())
+    }
+
+    fn save_settings(&self, s: &SettingsDto) -> Result<(), StorageError> {
+        *self.settings.lock() = s.clone();
+        Ok(())
+    }
+}
+
+fn launch(synth: &ProgramSynth) -> AppCore {
+    let storage = MemoryStorage::default();
+    {
+        let mut settings = storage.settings.lock();
+        settings.default_sf2_path = Some("piano.sf2".to_string());
+        settings.bus_programs.insert(Bus::Autopilot, 1);
+    }
+    let core = AppCore::new(
+        Box::new(NullAudioOutputPort),
+        Box::new(NullMidiInputPort),
+        Arc::new(synth.clone()),
+        None,
+        Some(Box::new(storage)),
+    )
+    .expect("core");
+    synth.take_programs();
+    synth.take_soundfonts();
+    core
+}
+
+fn load_demo(core: &mut AppCore, id: &str) {
+    core.handle_command(Command::LoadScore {
+        source: ScoreSource::InternalDemo(id.to_string()),
+    })
+    .expect("demo");
+}
+
+fn baroque() -> ScoreSoundOverrides {
+    ScoreSoundOverrides {
+        bus_programs: BTreeMap::from([(Bus::Autopilot, 6), (Bus::UserMonitor, 19)]),
+        soundfont_path: Some("baroque.sf2".to_string()),
+    }
+}
+
+fn reported_overrides(core: &mut AppCore) -> Option<ScoreSoundOverrides> {
+    core.drain_events()
+        .into_iter()
+        .rev()
+        .find_map(|event| match event {
+            Event::SessionStateUpdated {
+                score_sound_overrides,
+                ..
+            } => Some(score_sound_overrides),
+            _ => None,
+        })
+}
+
+#[test]
+fn overrides_apply_to_their_score_and_revert_when_the_next_loads() {
+    let synth = ProgramSynth::default();
+    let mut core = launch(&synth);
+    load_demo(&mut core, "c_major_scale");
+    assert!(synth.take_programs().is_empty());
+
+    core.handle_command(Command::SetScoreSoundOverrides {
+        overrides: baroque(),
+    })
+    .expect("overrides");
+    assert_eq!(synth.take_soundfonts(), vec!["baroque.sf2"]);
+    assert_eq!(synth.take_settled_programs(), baroque().bus_programs);
+    assert_eq!(reported_overrides(&mut core), Some(baroque()));
+
+    let path = std::env::temp_dir().join(format!(
+        "cadenza-score-sound-{}.cadenza",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos()
+    ));
+    core.handle_command(Command::SaveScoreFile {
+        path: path.display().to_string(),
+    })
+    .expect("save");
+
+    // The next score has no overrides: back to the global soundfont and programs, and the
+    // bus with no global choice to the GM default.
+    load_demo(&mut core, "chromatic_scale");
+    assert_eq!(synth.take_soundfonts(), vec!["piano.sf2"]);
+    assert_eq!(
+        synth.take_settled_programs(),
+        BTreeMap::from([(Bus::Autopilot, 1), (Bus::UserMonitor, 0)])
+    );
+    assert_eq!(
+        reported_overrides(&mut core),
+        Some(ScoreSoundOverrides::default())
+    );
+
+    // The saved score brings them back.
+    core.handle_command(Command::LoadScore {
+        source: ScoreSource::CadenzaFile(path.display().to_string()),
+    })
+    .expect("load saved");
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(synth.take_soundfonts(), vec!["baroque.sf2"]);
+    assert_eq!(synth.take_settled_programs(), baroque().bus_programs);
+    assert_eq!(reported_overrides(&mut core), Some(baroque()));
+}
+
+#[test]
+fn clearing_overrides_restores_the_global_sounds() {
+    let synth = ProgramSynth::default();
+    let mut core = launch(&synth);
+    load_demo(&mut core, "c_major_scale");
+    core.handle_command(Command::SetScoreSoundOverrides {
+        overrides: ScoreSoundOverrides {
+            bus_programs: BTreeMap::from([(Bus::Autopilot, 6)]),
+            soundfont_path: None,
+        },
+    })
+    .expect("overrides");
+    assert_eq!(synth.take_programs(), vec![(Bus::Autopilot, 6)]);
+    assert!(core
+        .drain_events()
+        .iter()
+        .any(|event| matches!(event, Event::ScoreDirtyChanged { dirty: true })));
+
+    core.handle_command(Command::ClearScoreSoundOverrides)
+        .expect("clear");
+    assert_eq!(synth.take_programs(), vec![(Bus::Autopilot, 1)]);
+    // The soundfont never changed, so it is not reloaded.
+    assert!(synth.take_soundfonts().is_empty());
+}
+
+#[test]
+fn overrides_need_a_loaded_score() {
+    let synth = ProgramSynth::default();
+    let mut core = launch(&synth);
+    let result = core.handle_command(Command::SetScoreSoundOverrides {
+        overrides: baroque(),
+    });
+    assert!(matches!(result, Err(AppError::InvalidState(_))));
+    assert!(synth.take_programs().is_empty());
+}
@@ -0,0 +1,161 @@
+use cadenza_core::{
+    AppCore, AppError, Command, Event, NullAudioOutputPort, NullMidiInputPort, ScoreSource,
+    SessionSnapshot, SilentSynth, VirtualClock, SESSION_AUTOSAVE_INTERVAL,
+};
+use cadenza_domain_eval::JudgeSnapshot;
+use cadenza_ports::storage::{SettingsDto, StorageError, StoragePort};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Settings and the session snapshot kept in memory, shared by every core built over it.
+#[derive(Clone, Default)]
+struct MemoryStorage {
+    settings: Arc<Mutex<SettingsDto>>,
+    session: Arc<Mutex<Option<Vec<u8>>>>,
+}
+
+impl MemoryStorage {
+    fn session(&self) -> Option<SessionSnapshot> {
+        let data = self.session.lock().clone()?;
+        Some(SessionSnapshot::from_json(&data).expect("snapshot json"))
+    }
+}
+
+impl StoragePort for MemoryStorage {
+    fn load_settings(&self) -> Result<SettingsDto, StorageError> {
+        Ok(self.settings.lock().clone())
+    }
+
+    fn save_settings(&self, s: &SettingsDto) -> Result<(), StorageError> {
+        *self.settings.lock() = s.clone();
+        Ok(())
+    }
+
+    fn save_session_snapshot(&self, data: &[u8]) -> Result<(), StorageError> {
+        *self.session.lock() = Some(data.to_vec());
+        Ok(())
+    }
+
+    fn load_session_snapshot(&self) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.session.lock().clone())
+    }
+
+    fn clear_session_snapshot(&self) -> Result<(), StorageError> {
+        *self.session.lock() = None;
+        Ok(())
+    }
+}
+
+fn launch(storage: &MemoryStorage) -> (AppCore, Arc<VirtualClock>) {
+    let clock = Arc::new(VirtualClock::new());
+    let mut core = AppCore::new(
+        Box::new(NullAudioOutputPort),
+        Box::new(NullMidiInputPort),
+        Arc::new(SilentSynth),
+        None,
+        Some(Box::new(storage.clone())),
+    )
+    .expect("core");
+    core.set_clock(clock.clone());
+    (core, clock)
+}
+
+fn scale_demo() -> ScoreSource {
+    ScoreSource::InternalDemo("c_major_scale".to_string())
+}
+
+/// Loads the scale, starts practice at `tick` and runs past one autosave.
+fn practice_past_autosave(core: &mut AppCore, clock: &VirtualClock, tick: i64) {
+    core.handle_command(Command::LoadScore {
+        source: scale_demo(),
+    })
+    .expect("load");
+    core.handle_command(Command::Seek { tick }).expect("seek");
+    core.handle_command(Command::StartPractice).expect("start");
+    core.tick();
+    clock.advance(SESSION_AUTOSAVE_INTERVAL + Duration::from_secs(1));
+    core.tick();
+}
+
+fn recovered(events: &[Event]) -> Option<&SessionSnapshot> {
+    events.iter().find_map(|event| match event {
+        Event::RecoveredSession { snapshot } => Some(snapshot),
+        _ => None,
+    })
+}
+
+#[test]
+fn a_crashed_session_is_offered_back_on_the_next_start() {
+    let storage = MemoryStorage::default();
+    let (mut core, clock) = launch(&storage);
+    practice_past_autosave(&mut core, &clock, 960);
+    let saved = storage.session().expect("autosaved");
+    assert_eq!(saved.score_source, scale_demo());
+    assert!(saved.tick >= 960, "saved at tick {}", saved.tick);
+    assert!(saved.saved_at_unix_ms > 0);
+
+    // A crash: nothing is shut down and the snapshot stays behind.
+    std::mem::forget(core);
+
+    let (mut core, _clock) = launch(&storage);
+    let events = core.drain_events();
+    assert_eq!(recovered(&events), Some(&saved));
+    // Offered once; the next start finds nothing.
+    assert!(storage.session().is_none());
+    drop(core);
+    let (mut core, _clock) = launch(&storage);
+    assert!(recovered(&core.drain_events()).is_none());
+}
+
+#[test]
+fn a_resumed_session_continues_where_it_stopped() {
+    let storage = MemoryStorage::default();
+    let snapshot = SessionSnapshot {
+        score_source: scale_demo(),
+        score_title: Some("Demo: C major scale".to_string()),
+        tick: 1_920,
+        stats: JudgeSnapshot {
+            combo: 3,
+            score: 900,
+            hit: 9,
+            miss: 3,
+            ..JudgeSnapshot::default()
+        },
+        saved_at_unix_ms: 1,
+    };
+    *storage.session.lock() = Some(snapshot.to_json().expect("json"));
+
+    let (mut core, _clock) = launch(&storage);
+    core.drain_events();
+    core.handle_command(Command::ResumeRecoveredSession)
+        .expect("resume");
+    let events = core.drain_events();
+    assert!(events.iter().any(|event| matches!(
+        event,
+        Event::ScoreSummaryUpdated { combo: 3, score: 900, accuracy, .. } if *accuracy == 0.75
+    )));
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, Event::TransportUpdated { tick: 1_920, .. })));
+
+    let again = core.handle_command(Command::ResumeRecoveredSession);
+    assert!(matches!(again, Err(AppError::InvalidState(_))));
+}
+
+#[test]
+fn stopping_or_shutting_down_removes_the_snapshot() {
+    let storage = MemoryStorage::default();
+    let (mut core, clock) = launch(&storage);
+    practice_past_autosave(&mut core, &clock, 0);
+    assert!(storage.session().is_some());
+    core.handle_command(Command::StopPractice).expect("stop");
+    assert!(storage.session().is_none());
+
+    core.handle_command(Command::StartPractice).expect("start");
+    clock.advance(SESSION_AUTOSAVE_INTERVAL);
+    core.tick();
+    assert!(storage.session().is_some());
+    core.shutdown();
+    assert!(storage.session().is_none());
+}
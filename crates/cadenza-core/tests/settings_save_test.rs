@@ -0,0 +1,127 @@
+use cadenza_core::{AppCore, Command};
+use cadenza_ports::audio::{AudioError, AudioOutputPort, AudioRenderCallback, AudioStreamHandle};
+use cadenza_ports::midi::{
+    MidiError, MidiInputPort, MidiInputStream, MidiLikeEvent, PlayerEventCallback,
+};
+use cadenza_ports::storage::{SettingsDto, StorageError, StoragePort};
+use cadenza_ports::synth::{SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::types::{
+    AudioConfig, AudioOutputDevice, Bus, DeviceId, MidiInputDevice, SampleTime, Volume01,
+};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+struct NoAudio;
+
+impl AudioOutputPort for NoAudio {
+    fn list_outputs(&self) -> Result<Vec<AudioOutputDevice>, AudioError> {
+        Ok(Vec::new())
+    }
+
+    fn open_output(
+        &self,
+        device_id: &DeviceId,
+        _config: AudioConfig,
+        _cb: Box<dyn AudioRenderCallback>,
+    ) -> Result<Box<dyn AudioStreamHandle>, AudioError> {
+        Err(AudioError::DeviceNotFound(device_id.to_string()))
+    }
+}
+
+struct NoMidi;
+
+impl MidiInputPort for NoMidi {
+    fn list_inputs(&self) -> Result<Vec<MidiInputDevice>, MidiError> {
+        Ok(Vec::new())
+    }
+
+    fn open_input(
+        &self,
+        device_id: &DeviceId,
+        _cb: PlayerEventCallback,
+    ) -> Result<Box<dyn MidiInputStream>, MidiError> {
+        Err(MidiError::DeviceNotFound(device_id.to_string()))
+    }
+}
+
+struct NoSynth;
+
+impl SynthPort for NoSynth {
+    fn load_soundfont_from_path(&self, _path: &str) -> Result<SoundFontInfo, SynthError> {
+        Err(SynthError::UnsupportedFormat)
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, _bus: Bus, _gm_program: u8) -> Result<(), SynthError> {
+        Ok(())
+    }
+
+    fn handle_event(&self, _bus: Bus, _event: MidiLikeEvent, _at: SampleTime) {}
+
+    fn render(&self, _bus: Bus, _frames: usize, _out_l: &mut [f32], _out_r: &mut [f32]) {}
+}
+
+/// Records every settings write.
+#[derive(Clone, Default)]
+struct CountingStorage {
+    saved: Arc<Mutex<Vec<SettingsDto>>>,
+}
+
+impl StoragePort for CountingStorage {
+    fn load_settings(&self) -> Result<SettingsDto, StorageError> {
+        Ok(SettingsDto::default())
+    }
+
+    fn save_settings(&self, s: &SettingsDto) -> Result<(), StorageError> {
+        self.saved.lock().push(s.clone());
+        Ok(())
+    }
+}
+
+fn drag_fader(core: &mut AppCore, steps: u32) {
+    for step in 1..=steps {
+        core.handle_command(Command::SetBusVolume {
+            bus: Bus::Autopilot,
+            volume: Volume01::new(step as f32 / steps as f32 * 0.5),
+        })
+        .expect("volume");
+    }
+}
+
+#[test]
+fn a_burst_of_volume_changes_is_written_once() {
+    let storage = CountingStorage::default();
+    let mut core = AppCore::new(
+        Box::new(NoAudio),
+        Box::new(NoMidi),
+        Arc::new(NoSynth),
+        None,
+        Some(Box::new(storage.clone())),
+    )
+    .expect("core");
+
+    drag_fader(&mut core, 30);
+    core.flush_settings();
+    assert_eq!(storage.saved.lock().len(), 1);
+    assert_eq!(
+        storage.saved.lock()[0].bus_autopilot_volume,
+        Volume01::new(0.5)
+    );
+
+    // Within the interval of that write, ticks only hold on to the change.
+    drag_fader(&mut core, 30);
+    core.tick();
+    assert_eq!(storage.saved.lock().len(), 1);
+
+    thread::sleep(Duration::from_millis(550));
+    core.tick();
+    core.tick();
+    assert_eq!(storage.saved.lock().len(), 2);
+
+    // Nothing pending: flushing writes nothing.
+    core.flush_settings();
+    assert_eq!(storage.saved.lock().len(), 2);
+}
@@ -0,0 +1,242 @@
+use cadenza_core::{AppCore, Command, Event, SetupStep};
+use cadenza_ports::audio::{AudioError, AudioOutputPort, AudioRenderCallback, AudioStreamHandle};
+use cadenza_ports::midi::{
+    MidiError, MidiInputPort, MidiInputStream, MidiLikeEvent, PlayerEventCallback,
+};
+use cadenza_ports::storage::{SettingsDto, StorageError, StoragePort};
+use cadenza_ports::synth::{SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::types::{
+    AudioConfig, AudioOutputDevice, Bus, DeviceId, MidiInputDevice, SampleTime,
+};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+/// One output, "speakers", remembering which device was opened.
+struct Speakers {
+    opened: Arc<Mutex<Option<DeviceId>>>,
+}
+
+struct FakeAudioStream;
+
+impl AudioStreamHandle for FakeAudioStream {
+    fn close(self: Box<Self>) {}
+}
+
+impl AudioOutputPort for Speakers {
+    fn list_outputs(&self) -> Result<Vec<AudioOutputDevice>, AudioError> {
+        Ok(vec![AudioOutputDevice {
+            id: DeviceId("speakers".to_string()),
+            name: "Speakers".to_string(),
+            default_config: AudioConfig {
+                sample_rate_hz: 48_000,
+                channels: 2,
+                buffer_size_frames: Some(256),
+            },
+            buffer_size_range: None,
+        }])
+    }
+
+    fn open_output(
+        &self,
+        device_id: &DeviceId,
+        _config: AudioConfig,
+        _cb: Box<dyn AudioRenderCallback>,
+    ) -> Result<Box<dyn AudioStreamHandle>, AudioError> {
+        *self.opened.lock() = Some(device_id.clone());
+        Ok(Box::new(FakeAudioStream))
+    }
+}
+
+struct Keyboard;
+
+struct FakeMidiStream;
+
+impl MidiInputStream for FakeMidiStream {
+    fn close(self: Box<Self>) {}
+}
+
+impl MidiInputPort for Keyboard {
+    fn list_inputs(&self) -> Result<Vec<MidiInputDevice>, MidiError> {
+        Ok(vec![MidiInputDevice {
+            id: DeviceId("keyboard".to_string()),
+            name: "Keyboard".to_string(),
+            is_available: true,
+        }])
+    }
+
+    fn open_input(
+        &self,
+        _device_id: &DeviceId,
+        _cb: PlayerEventCallback,
+    ) -> Result<Box<dyn MidiInputStream>, MidiError> {
+        Ok(Box::new(FakeMidiStream))
+    }
+}
+
+struct SilentSynth;
+
+impl SynthPort for SilentSynth {
+    fn load_soundfont_from_path(&self, _path: &str) -> Result<SoundFontInfo, SynthError> {
+        Err(SynthError::UnsupportedFormat)
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, _bus: Bus, _gm_program: u8) -> Result<(), SynthError> {
+        Ok(())
+    }
+
+    fn handle_event(&self, _bus: Bus, _event: MidiLikeEvent, _at: SampleTime) {}
+
+    fn render(&self, _bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        out_l[..frames].fill(0.0);
+        out_r[..frames].fill(0.0);
+    }
+}
+
+/// Settings kept in memory across cores, like a settings file across launches.
+#[derive(Clone, Default)]
+struct MemoryStorage {
+    settings: Arc<Mutex<SettingsDto>>,
+}
+
+impl StoragePort for MemoryStorage {
+    fn load_settings(&self) -> Result<SettingsDto, StorageError> {
+        Ok(self.settings.lock().clone())
+    }
+
+    fn save_settings(&self, s: &SettingsDto) -> Result<(), StorageError> {
+        *self.settings.lock() = s.clone();
+        Ok(())
+    }
+}
+
+fn launch(storage: &MemoryStorage, opened: &Arc<Mutex<Option<DeviceId>>>) -> AppCore {
+    let mut core = AppCore::new(
+        Box::new(Speakers {
+            opened: opened.clone(),
+        }),
+        Box::new(Keyboard),
+        Arc::new(SilentSynth),
+        None,
+        Some(Box::new(storage.clone())),
+    )
+    .expect("core");
+    core.drain_events();
+    core
+}
+
+#[derive(Debug, PartialEq)]
+enum Setup {
+    Required(Vec<SetupStep>),
+    StepCompleted(SetupStep, Vec<SetupStep>),
+    Completed,
+}
+
+fn setup_events(events: &[Event]) -> Vec<Setup> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            Event::SetupRequired { missing } => Some(Setup::Required(missing.clone())),
+            Event::SetupStepCompleted { step, remaining } => {
+                Some(Setup::StepCompleted(*step, remaining.clone()))
+            }
+            Event::SetupCompleted => Some(Setup::Completed),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn first_run_walks_the_setup_once_and_remembers_it() {
+    let storage = MemoryStorage::default();
+    let opened = Arc::new(Mutex::new(None));
+    let mut core = launch(&storage, &opened);
+
+    core.start_first_run_setup();
+    // Starting again while it is pending changes nothing.
+    core.start_first_run_setup();
+    let events = core.drain_events();
+    assert_eq!(
+        setup_events(&events),
+        vec![Setup::Required(vec![
+            SetupStep::MidiInput,
+            SetupStep::AudioOutput,
+            SetupStep::Score,
+        ])]
+    );
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, Event::ScoreViewUpdated { .. })));
+    assert_eq!(*opened.lock(), Some(DeviceId("speakers".to_string())));
+
+    core.handle_command(Command::SelectMidiInput {
+        device_id: DeviceId("keyboard".to_string()),
+    })
+    .expect("midi");
+    for step in [
+        SetupStep::MidiInput,
+        SetupStep::MidiInput,
+        SetupStep::AudioOutput,
+    ] {
+        core.handle_command(Command::CompleteSetupStep { step })
+            .expect("step");
+    }
+    assert_eq!(
+        setup_events(&core.drain_events()),
+        vec![
+            Setup::StepCompleted(
+                SetupStep::MidiInput,
+                vec![SetupStep::AudioOutput, SetupStep::Score]
+            ),
+            Setup::StepCompleted(SetupStep::AudioOutput, vec![SetupStep::Score]),
+        ]
+    );
+    assert!(!storage.settings.lock().setup_completed);
+
+    // The score step is skipped, keeping the demo.
+    core.handle_command(Command::CompleteSetupStep {
+        step: SetupStep::Score,
+    })
+    .expect("skip");
+    core.handle_command(Command::CompleteSetupStep {
+        step: SetupStep::Score,
+    })
+    .expect("again");
+    assert_eq!(
+        setup_events(&core.drain_events()),
+        vec![
+            Setup::StepCompleted(SetupStep::Score, Vec::new()),
+            Setup::Completed,
+        ]
+    );
+    assert!(storage.settings.lock().setup_completed);
+
+    // The next launch starts straight away.
+    let mut next = launch(&storage, &Arc::new(Mutex::new(None)));
+    next.start_first_run_setup();
+    assert!(setup_events(&next.drain_events()).is_empty());
+}
+
+#[test]
+fn settings_with_chosen_devices_skip_the_setup() {
+    let storage = MemoryStorage::default();
+    storage.settings.lock().selected_midi_in = Some(DeviceId("keyboard".to_string()));
+    let opened = Arc::new(Mutex::new(None));
+    let mut core = launch(&storage, &opened);
+
+    core.start_first_run_setup();
+    let events = core.drain_events();
+    assert!(setup_events(&events).is_empty());
+    assert!(!events
+        .iter()
+        .any(|event| matches!(event, Event::ScoreViewUpdated { .. })));
+
+    // Without pending steps, completing one does nothing.
+    core.handle_command(Command::CompleteSetupStep {
+        step: SetupStep::Score,
+    })
+    .expect("no-op");
+    assert!(setup_events(&core.drain_events()).is_empty());
+    assert!(!storage.settings.lock().setup_completed);
+}
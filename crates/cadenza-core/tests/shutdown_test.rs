@@ -0,0 +1,192 @@
+use cadenza_core::{AppCore, Command, Event};
+use cadenza_ports::audio::{AudioError, AudioOutputPort, AudioRenderCallback, AudioStreamHandle};
+use cadenza_ports::midi::{
+    MidiError, MidiInputPort, MidiInputStream, MidiLikeEvent, PlayerEventCallback,
+};
+use cadenza_ports::storage::{SettingsDto, StorageError, StoragePort};
+use cadenza_ports::synth::{SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::types::{
+    AudioConfig, AudioOutputDevice, Bus, DeviceId, MidiInputDevice, SampleTime, Volume01,
+};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+type CallLog = Arc<Mutex<Vec<&'static str>>>;
+
+fn record(log: &CallLog, call: &'static str) {
+    let mut log = log.lock();
+    if log.last() != Some(&call) {
+        log.push(call);
+    }
+}
+
+struct FakeAudioPort {
+    log: CallLog,
+}
+
+/// Renders one last block when closed, as a device does while draining.
+struct FakeAudioStream {
+    log: CallLog,
+    callback: Box<dyn AudioRenderCallback>,
+}
+
+impl AudioStreamHandle for FakeAudioStream {
+    fn close(mut self: Box<Self>) {
+        let mut out_l = vec![0.0; 256];
+        let mut out_r = vec![0.0; 256];
+        self.callback.render(0, &mut out_l, &mut out_r);
+        record(&self.log, "audio closed");
+    }
+}
+
+impl AudioOutputPort for FakeAudioPort {
+    fn list_outputs(&self) -> Result<Vec<AudioOutputDevice>, AudioError> {
+        Ok(vec![AudioOutputDevice {
+            id: DeviceId("fake".to_string()),
+            name: "Fake Output".to_string(),
+            default_config: AudioConfig {
+                sample_rate_hz: 48_000,
+                channels: 2,
+                buffer_size_frames: Some(256),
+            },
+            buffer_size_range: None,
+        }])
+    }
+
+    fn open_output(
+        &self,
+        _device_id: &DeviceId,
+        _config: AudioConfig,
+        cb: Box<dyn AudioRenderCallback>,
+    ) -> Result<Box<dyn AudioStreamHandle>, AudioError> {
+        Ok(Box::new(FakeAudioStream {
+            log: self.log.clone(),
+            callback: cb,
+        }))
+    }
+}
+
+struct FakeMidiPort {
+    log: CallLog,
+}
+
+struct FakeMidiStream {
+    log: CallLog,
+}
+
+impl MidiInputStream for FakeMidiStream {
+    fn close(self: Box<Self>) {
+        record(&self.log, "midi closed");
+    }
+}
+
+impl MidiInputPort for FakeMidiPort {
+    fn list_inputs(&self) -> Result<Vec<MidiInputDevice>, MidiError> {
+        Ok(Vec::new())
+    }
+
+    fn open_input(
+        &self,
+        _device_id: &DeviceId,
+        _cb: PlayerEventCallback,
+    ) -> Result<Box<dyn MidiInputStream>, MidiError> {
+        Ok(Box::new(FakeMidiStream {
+            log: self.log.clone(),
+        }))
+    }
+}
+
+struct LoggingSynth {
+    log: CallLog,
+}
+
+impl SynthPort for LoggingSynth {
+    fn load_soundfont_from_path(&self, _path: &str) -> Result<SoundFontInfo, SynthError> {
+        Err(SynthError::UnsupportedFormat)
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, _bus: Bus, _gm_program: u8) -> Result<(), SynthError> {
+        Ok(())
+    }
+
+    fn handle_event(&self, _bus: Bus, event: MidiLikeEvent, _at: SampleTime) {
+        if matches!(event, MidiLikeEvent::NoteOff { .. }) {
+            record(&self.log, "notes off");
+        }
+    }
+
+    fn render(&self, _bus: Bus, _frames: usize, _out_l: &mut [f32], _out_r: &mut [f32]) {}
+}
+
+struct LoggingStorage {
+    log: CallLog,
+}
+
+impl StoragePort for LoggingStorage {
+    fn load_settings(&self) -> Result<SettingsDto, StorageError> {
+        Ok(SettingsDto::default())
+    }
+
+    fn save_settings(&self, _s: &SettingsDto) -> Result<(), StorageError> {
+        record(&self.log, "settings saved");
+        Ok(())
+    }
+}
+
+fn running_core(log: &CallLog) -> AppCore {
+    let mut core = AppCore::new(
+        Box::new(FakeAudioPort { log: log.clone() }),
+        Box::new(FakeMidiPort { log: log.clone() }),
+        Arc::new(LoggingSynth { log: log.clone() }),
+        None,
+        Some(Box::new(LoggingStorage { log: log.clone() })),
+    )
+    .expect("core");
+    core.handle_command(Command::SelectMidiInput {
+        device_id: DeviceId("keys".to_string()),
+    })
+    .expect("midi");
+    core.handle_command(Command::SelectAudioOutput {
+        device_id: DeviceId("fake".to_string()),
+        config: None,
+    })
+    .expect("audio");
+    // A pending, not yet written change.
+    core.handle_command(Command::SetMasterVolume {
+        volume: Volume01::new(0.4),
+    })
+    .expect("volume");
+    log.lock().clear();
+    core
+}
+
+#[test]
+fn shutdown_releases_notes_before_closing_streams_and_saves_last() {
+    let log: CallLog = Arc::default();
+    let mut core = running_core(&log);
+
+    core.shutdown();
+
+    assert_eq!(
+        *log.lock(),
+        vec!["midi closed", "notes off", "audio closed", "settings saved"]
+    );
+    assert!(matches!(core.drain_events().last(), Some(Event::ShutDown)));
+
+    // Shutting down again, explicitly or by dropping, does nothing more.
+    core.shutdown();
+    drop(core);
+    assert_eq!(log.lock().len(), 4);
+}
+
+#[test]
+fn dropping_the_core_shuts_it_down() {
+    let log: CallLog = Arc::default();
+    drop(running_core(&log));
+    assert_eq!(
+        *log.lock(),
+        vec!["midi closed", "notes off", "audio closed", "settings saved"]
+    );
+}
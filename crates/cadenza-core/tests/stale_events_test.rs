@@ -0,0 +1,121 @@
+use cadenza_core::{AppCore, Command, NullMidiInputPort, ScoreSource};
+use cadenza_ports::types::{DeviceId, SampleTime};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+mod common;
+
+use common::{HeldNoteSynth, ManualAudio, SharedRender, BLOCK, QUARTER_SAMPLES};
+
+struct Rig {
+    core: AppCore,
+    render: SharedRender,
+    synth: Arc<HeldNoteSynth>,
+    sample_time: SampleTime,
+    start: SampleTime,
+}
+
+impl Rig {
+    /// The demo scale loaded and practicing, the second note already queued for audio.
+    fn practicing() -> Self {
+        let render: SharedRender = Arc::new(Mutex::new(None));
+        let synth = Arc::new(HeldNoteSynth::default());
+        let mut core = AppCore::new(
+            Box::new(ManualAudio {
+                render: render.clone(),
+                block: BLOCK,
+            }),
+            Box::new(NullMidiInputPort),
+            synth.clone(),
+            None,
+            None,
+        )
+        .expect("core");
+        core.handle_command(Command::SelectAudioOutput {
+            device_id: DeviceId("manual".to_string()),
+            config: None,
+        })
+        .expect("audio");
+        let mut rig = Self {
+            core,
+            render,
+            synth,
+            sample_time: 0,
+            start: 0,
+        };
+        for _ in 0..4 {
+            rig.step();
+        }
+        rig.core
+            .handle_command(Command::LoadScore {
+                source: ScoreSource::InternalDemo("scale".to_string()),
+            })
+            .expect("score");
+        rig.core
+            .handle_command(Command::StartPractice)
+            .expect("start");
+        rig.start = rig.sample_time;
+        // The scheduler looks ahead by 30 ms, over a block.
+        while rig.sample_time + 2 * (BLOCK as SampleTime) < rig.start + QUARTER_SAMPLES {
+            rig.step();
+        }
+        rig.core.drain_events();
+        rig
+    }
+
+    /// Renders one block and ticks the core; true when the block had any sound.
+    fn step(&mut self) -> bool {
+        let mut left = [0.0; BLOCK];
+        let mut right = [0.0; BLOCK];
+        self.render.lock().as_mut().expect("audio opened").render(
+            self.sample_time,
+            &mut left,
+            &mut right,
+        );
+        self.sample_time += BLOCK as SampleTime;
+        self.core.tick();
+        left.iter().chain(right.iter()).any(|sample| *sample != 0.0)
+    }
+
+    fn played_notes(&self) -> Vec<u8> {
+        self.synth
+            .note_ons
+            .lock()
+            .iter()
+            .map(|&(_, note, _)| note)
+            .collect()
+    }
+
+    fn run_until(&mut self, sample_time: SampleTime) {
+        while self.sample_time < sample_time {
+            self.step();
+        }
+    }
+}
+
+#[test]
+fn a_seek_drops_notes_queued_from_the_old_position() {
+    let mut rig = Rig::practicing();
+    // To the scale's fifth note, G4.
+    rig.core
+        .handle_command(Command::Seek { tick: 1920 })
+        .expect("seek");
+    rig.run_until(rig.start + QUARTER_SAMPLES + QUARTER_SAMPLES / 2);
+
+    // D4 was queued before the seek and never plays.
+    assert_eq!(rig.played_notes(), vec![60, 67]);
+}
+
+#[test]
+fn stopping_drops_notes_queued_ahead() {
+    let mut rig = Rig::practicing();
+    rig.core
+        .handle_command(Command::StopPractice)
+        .expect("stop");
+    rig.run_until(rig.start + 2 * QUARTER_SAMPLES);
+
+    assert_eq!(rig.played_notes(), vec![60]);
+    assert!(rig.synth.held.lock().is_empty());
+    // The limiter's lookahead has long drained.
+    assert!(!rig.step());
+}
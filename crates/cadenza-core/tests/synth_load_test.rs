@@ -0,0 +1,156 @@
+use cadenza_core::{AppCore, Command, Event, NullMidiInputPort, VirtualClock};
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::synth::{SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::types::{Bus, DeviceId, SampleTime};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+mod common;
+
+use common::{ManualAudio, SharedRender};
+
+const BLOCK: usize = 256;
+/// One block at 48 kHz.
+const BLOCK_BUDGET: Duration = Duration::from_micros(5_333);
+
+/// Spins for a set time on every autopilot render, and renders the other buses at once.
+struct SlowSynth {
+    autopilot_render_us: AtomicU64,
+}
+
+impl SynthPort for SlowSynth {
+    fn load_soundfont_from_path(&self, _path: &str) -> Result<SoundFontInfo, SynthError> {
+        Err(SynthError::UnsupportedFormat)
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, _bus: Bus, _gm_program: u8) -> Result<(), SynthError> {
+        Ok(())
+    }
+
+    fn handle_event(&self, _bus: Bus, _event: MidiLikeEvent, _at: SampleTime) {}
+
+    fn render(&self, bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        if bus == Bus::Autopilot {
+            let spin = Duration::from_micros(self.autopilot_render_us.load(Ordering::Relaxed));
+            let started = Instant::now();
+            while started.elapsed() < spin {
+                std::hint::spin_loop();
+            }
+        }
+        out_l[..frames].fill(0.0);
+        out_r[..frames].fill(0.0);
+    }
+}
+
+struct Rig {
+    core: AppCore,
+    clock: Arc<VirtualClock>,
+    render: SharedRender,
+    synth: Arc<SlowSynth>,
+    sample_time: SampleTime,
+}
+
+impl Rig {
+    fn new(autopilot_render: Duration) -> Self {
+        let render: SharedRender = Arc::new(Mutex::new(None));
+        let synth = Arc::new(SlowSynth {
+            autopilot_render_us: AtomicU64::new(autopilot_render.as_micros() as u64),
+        });
+        let clock = Arc::new(VirtualClock::new());
+        let mut core = AppCore::new(
+            Box::new(ManualAudio {
+                render: render.clone(),
+                block: BLOCK,
+            }),
+            Box::new(NullMidiInputPort),
+            synth.clone(),
+            None,
+            None,
+        )
+        .expect("core");
+        core.set_clock(clock.clone());
+        core.handle_command(Command::SelectAudioOutput {
+            device_id: DeviceId("manual".to_string()),
+            config: None,
+        })
+        .expect("audio");
+        core.drain_events();
+        Self {
+            core,
+            clock,
+            render,
+            synth,
+            sample_time: 0,
+        }
+    }
+
+    /// Renders `blocks` blocks, then lets a second pass for the core's periodic checks.
+    fn second(&mut self, blocks: usize) -> Vec<Event> {
+        for _ in 0..blocks {
+            let mut left = [0.0; BLOCK];
+            let mut right = [0.0; BLOCK];
+            self.render.lock().as_mut().expect("audio opened").render(
+                self.sample_time,
+                &mut left,
+                &mut right,
+            );
+            self.sample_time += BLOCK as SampleTime;
+        }
+        self.clock.advance(Duration::from_secs(1));
+        self.core.tick();
+        self.core.drain_events()
+    }
+}
+
+fn warnings(events: &[Event]) -> usize {
+    events
+        .iter()
+        .filter(|event| matches!(event, Event::SynthLoadWarning { .. }))
+        .count()
+}
+
+#[test]
+fn a_synth_slower_than_realtime_warns_after_a_few_seconds() {
+    // The autopilot bus takes one and a half times the block's budget.
+    let mut rig = Rig::new(BLOCK_BUDGET * 3 / 2);
+    // Enough blocks for the smoothed load to pass 80%.
+    let mut events = rig.second(80);
+    assert_eq!(warnings(&events), 0);
+    events = rig.second(4);
+    assert_eq!(warnings(&events), 0);
+
+    events = rig.second(4);
+    let warning = events
+        .iter()
+        .find_map(|event| match event {
+            Event::SynthLoadWarning { load, message } => Some((*load, message.clone())),
+            _ => None,
+        })
+        .expect("synth load warning");
+    assert!(warning.0.total_percent > 80.0, "{warning:?}");
+    assert!(warning.0.autopilot_percent > 80.0, "{warning:?}");
+    assert!(warning.0.user_percent < 20.0, "{warning:?}");
+    assert!(warning.1.contains("buffer"));
+
+    // Once per stretch of overload.
+    events = rig.second(4);
+    assert_eq!(warnings(&events), 0);
+
+    // Back under the threshold, nothing more is reported.
+    rig.synth.autopilot_render_us.store(0, Ordering::Relaxed);
+    events = rig.second(200);
+    assert_eq!(warnings(&events), 0);
+}
+
+#[test]
+fn a_fast_synth_never_warns() {
+    let mut rig = Rig::new(Duration::ZERO);
+    for _ in 0..6 {
+        let events = rig.second(40);
+        assert_eq!(warnings(&events), 0);
+    }
+}
@@ -0,0 +1,181 @@
+use cadenza_core::{TempoCursor, TempoMap, Transport};
+use cadenza_domain_score::TempoPoint;
+use cadenza_ports::types::Tick;
+
+const PPQ: u16 = 480;
+
+/// Tempo changes every `spacing` ticks, cycling through a few tempos.
+fn tempo_points(count: usize, spacing: Tick) -> Vec<TempoPoint> {
+    (0..count)
+        .map(|idx| TempoPoint {
+            tick: idx as Tick * spacing,
+            us_per_quarter: [500_000, 428_571, 612_244, 375_000, 1_000_000][idx % 5],
+        })
+        .collect()
+}
+
+/// The lookup as it was before segments were searched: a walk over every tempo change.
+fn linear_tick_to_micros(points: &[TempoPoint], tick: Tick) -> i64 {
+    let ticks_to_us = |ticks: Tick, us_per_quarter: u32| {
+        (i128::from(ticks) * i128::from(us_per_quarter) / i128::from(PPQ)) as i64
+    };
+    let mut start_us = 0;
+    let mut current = points[0];
+    for pair in points.windows(2) {
+        if pair[1].tick > tick {
+            break;
+        }
+        start_us += ticks_to_us(pair[1].tick - pair[0].tick, pair[0].us_per_quarter);
+        current = pair[1];
+    }
+    start_us + ticks_to_us(tick - current.tick, current.us_per_quarter)
+}
+
+fn linear_micros_to_tick(points: &[TempoPoint], micros: i64) -> Tick {
+    let mut start_us = 0;
+    let mut current = points[0];
+    for pair in points.windows(2) {
+        let next_us = start_us
+            + (i128::from(pair[1].tick - pair[0].tick) * i128::from(pair[0].us_per_quarter)
+                / i128::from(PPQ)) as i64;
+        if next_us > micros {
+            break;
+        }
+        start_us = next_us;
+        current = pair[1];
+    }
+    current.tick
+        + (i128::from(micros - start_us) * i128::from(PPQ) / i128::from(current.us_per_quarter))
+            as Tick
+}
+
+/// Ticks in order with repeats and jumps, as a scheduler visits them across loop passes.
+fn visited_ticks() -> Vec<Tick> {
+    let mut seed = 7_u32;
+    let mut tick: Tick = 0;
+    let mut ticks = Vec::new();
+    for _ in 0..5_000 {
+        seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        match seed >> 28 {
+            0 => tick = Tick::from(seed >> 16) % 250_000,
+            1 => {}
+            _ => tick += Tick::from(seed >> 22) % 300,
+        }
+        ticks.push(tick);
+    }
+    ticks
+}
+
+#[test]
+fn searched_lookups_match_the_linear_walk() {
+    let points = tempo_points(500, 480);
+    let map = TempoMap::new(PPQ, points.clone());
+    let mut cursor = TempoCursor::default();
+    for tick in visited_ticks() {
+        let expected = linear_tick_to_micros(&points, tick);
+        assert_eq!(map.tick_to_micros(tick), expected, "tick {tick}");
+        assert_eq!(
+            map.tick_to_micros_from(tick, &mut cursor),
+            expected,
+            "tick {tick}"
+        );
+    }
+    for micros in (0..130_000_000).step_by(9_973) {
+        assert_eq!(
+            map.micros_to_tick(micros),
+            linear_micros_to_tick(&points, micros),
+            "micros {micros}"
+        );
+    }
+}
+
+#[test]
+fn tempo_changes_sharing_a_tick_resolve_to_the_last_one() {
+    let points = vec![
+        TempoPoint {
+            tick: 0,
+            us_per_quarter: 500_000,
+        },
+        TempoPoint {
+            tick: 960,
+            us_per_quarter: 250_000,
+        },
+        TempoPoint {
+            tick: 960,
+            us_per_quarter: 1_000_000,
+        },
+    ];
+    let map = TempoMap::new(PPQ, points.clone());
+    let mut cursor = TempoCursor::default();
+    for tick in [0, 480, 959, 960, 1_440, 0, 2_000] {
+        let expected = linear_tick_to_micros(&points, tick);
+        assert_eq!(map.tick_to_micros(tick), expected);
+        assert_eq!(map.tick_to_micros_from(tick, &mut cursor), expected);
+    }
+}
+
+#[test]
+fn a_carried_cursor_gives_the_same_samples() {
+    let mut transport = Transport::new(PPQ, 48_000, tempo_points(500, 480));
+    transport.set_tempo_multiplier(0.75);
+    transport.seek(10_000);
+    let mut cursor = TempoCursor::default();
+    for tick in visited_ticks() {
+        assert_eq!(
+            transport.tick_to_sample_from(tick, &mut cursor),
+            transport.tick_to_sample(tick),
+            "tick {tick}"
+        );
+    }
+}
+
+fn point(tick: Tick, us_per_quarter: u32) -> TempoPoint {
+    TempoPoint {
+        tick,
+        us_per_quarter,
+    }
+}
+
+#[test]
+fn an_empty_map_plays_at_120_bpm() {
+    let map = TempoMap::new(PPQ, Vec::new());
+    assert_eq!(map.points(), vec![point(0, 500_000)]);
+    assert_eq!(map.tick_to_micros(960), 1_000_000);
+    assert_eq!(map.micros_to_tick(1_000_000), 960);
+}
+
+#[test]
+fn duplicate_points_keep_the_last_tempo_at_their_tick() {
+    let map = TempoMap::new(
+        PPQ,
+        vec![
+            point(960, 250_000),
+            point(0, 600_000),
+            point(960, 1_000_000),
+            point(0, 500_000),
+        ],
+    );
+    assert_eq!(map.points(), vec![point(0, 500_000), point(960, 1_000_000)]);
+    assert_eq!(map.tick_to_micros(1_440), 2_000_000);
+}
+
+#[test]
+fn a_zero_tempo_is_clamped() {
+    let map = TempoMap::new(PPQ, vec![point(0, 0), point(480, 500_000)]);
+    assert_eq!(map.points(), vec![point(0, 1_000), point(480, 500_000)]);
+    assert_eq!(map.tick_to_micros(480), 1_000);
+    assert_eq!(map.micros_to_tick(500), 240);
+    assert_eq!(map.micros_to_tick(501_000), 960);
+}
+
+#[test]
+fn negative_ticks_extend_the_first_tempo_backwards() {
+    let map = TempoMap::new(PPQ, vec![point(-480, 250_000), point(480, 1_000_000)]);
+    // The point before the score is dropped.
+    assert_eq!(map.points(), vec![point(0, 500_000), point(480, 1_000_000)]);
+    assert_eq!(map.tick_to_micros(-480), -500_000);
+    let mut cursor = TempoCursor::default();
+    assert_eq!(map.tick_to_micros_from(960, &mut cursor), 1_500_000);
+    assert_eq!(map.tick_to_micros_from(-480, &mut cursor), -500_000);
+    assert_eq!(map.micros_to_tick(-500_000), -480);
+}
@@ -0,0 +1,193 @@
+use cadenza_core::{AppCore, Command, Event, PianoRollTargetDto, ScoreSource};
+use cadenza_domain_score::{
+    save_scorefile_path, Hand, PlaybackMidiEvent, Score, ScoreFile, ScoreMeta, TargetEvent, Track,
+};
+use cadenza_ports::audio::{AudioError, AudioOutputPort, AudioRenderCallback, AudioStreamHandle};
+use cadenza_ports::midi::{
+    MidiError, MidiInputPort, MidiInputStream, MidiLikeEvent, PlayerEventCallback,
+};
+use cadenza_ports::synth::{SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::types::{AudioOutputDevice, Bus, DeviceId, MidiInputDevice, SampleTime, Tick};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+struct NoAudio;
+
+impl AudioOutputPort for NoAudio {
+    fn list_outputs(&self) -> Result<Vec<AudioOutputDevice>, AudioError> {
+        Ok(Vec::new())
+    }
+
+    fn open_output(
+        &self,
+        device_id: &DeviceId,
+        _config: cadenza_ports::types::AudioConfig,
+        _cb: Box<dyn AudioRenderCallback>,
+    ) -> Result<Box<dyn AudioStreamHandle>, AudioError> {
+        Err(AudioError::DeviceNotFound(device_id.0.clone()))
+    }
+}
+
+struct NoMidi;
+
+impl MidiInputPort for NoMidi {
+    fn list_inputs(&self) -> Result<Vec<MidiInputDevice>, MidiError> {
+        Ok(Vec::new())
+    }
+
+    fn open_input(
+        &self,
+        device_id: &DeviceId,
+        _cb: PlayerEventCallback,
+    ) -> Result<Box<dyn MidiInputStream>, MidiError> {
+        Err(MidiError::DeviceNotFound(device_id.0.clone()))
+    }
+}
+
+struct SilentSynth;
+
+impl SynthPort for SilentSynth {
+    fn load_soundfont_from_path(&self, _path: &str) -> Result<SoundFontInfo, SynthError> {
+        Err(SynthError::UnsupportedFormat)
+    }
+
+    fn set_sample_rate(&self, _sample_rate_hz: u32) {}
+
+    fn set_program(&self, _bus: Bus, _gm_program: u8) -> Result<(), SynthError> {
+        Ok(())
+    }
+
+    fn handle_event(&self, _bus: Bus, _event: MidiLikeEvent, _at: SampleTime) {}
+
+    fn render(&self, _bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        out_l[..frames].fill(0.0);
+        out_r[..frames].fill(0.0);
+    }
+}
+
+fn track(id: u32, hand: Hand, notes: &[(Tick, u8)]) -> Track {
+    let playback_events = notes
+        .iter()
+        .flat_map(|&(tick, note)| {
+            [
+                (tick, MidiLikeEvent::NoteOn { note, velocity: 80 }),
+                (tick + 240, MidiLikeEvent::NoteOff { note }),
+            ]
+        })
+        .map(|(tick, event)| PlaybackMidiEvent {
+            tick,
+            event,
+            hand: Some(hand),
+            ornament_of: None,
+            bus: None,
+            note_id: None,
+            spelling: None,
+            transpose: 0,
+            articulations: Vec::new(),
+        })
+        .collect();
+    let targets = notes
+        .iter()
+        .enumerate()
+        .map(|(idx, &(tick, note))| TargetEvent {
+            id: idx as u64 + 1,
+            tick,
+            notes: vec![note],
+            hand: Some(hand),
+            measure_index: None,
+            optional: false,
+            note_ids: Vec::new(),
+            note_tracks: Vec::new(),
+        })
+        .collect();
+    Track {
+        id,
+        name: format!("Track {id}"),
+        hand: Some(hand),
+        targets,
+        playback_events,
+    }
+}
+
+/// A two-track project: right hand on track 1, left hand on track 2.
+fn load_two_hands(core: &mut AppCore) {
+    let mut score = Score::new(
+        ScoreMeta {
+            title: Some("Two hands".to_string()),
+            composer: None,
+            copyright: None,
+            source: cadenza_domain_score::ScoreSource::Internal,
+            origin_path: None,
+        },
+        480,
+    );
+    score.tracks = vec![
+        track(1, Hand::Right, &[(0, 72), (480, 76)]),
+        track(2, Hand::Left, &[(0, 48), (960, 43)]),
+    ];
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let path = std::env::temp_dir().join(format!("cadenza-track-select-{nanos}.cadenza"));
+    save_scorefile_path(&ScoreFile::new(score, Vec::new()), &path).expect("save");
+    let loaded = core.handle_command(Command::LoadScore {
+        source: ScoreSource::CadenzaFile(path.display().to_string()),
+    });
+    let _ = std::fs::remove_file(&path);
+    loaded.expect("load");
+}
+
+fn targets_shown(events: &[Event]) -> Vec<(Tick, Vec<u8>)> {
+    let targets: &Vec<PianoRollTargetDto> = events
+        .iter()
+        .rev()
+        .find_map(|event| match event {
+            Event::ScoreViewUpdated { targets, .. } => Some(targets),
+            _ => None,
+        })
+        .expect("score view");
+    targets.iter().map(|t| (t.tick, t.notes.clone())).collect()
+}
+
+#[test]
+fn selecting_tracks_rebuilds_practice_from_them_alone() {
+    let mut core = AppCore::new(
+        Box::new(NoAudio),
+        Box::new(NoMidi),
+        Arc::new(SilentSynth),
+        None,
+        None,
+    )
+    .expect("core");
+    assert!(core
+        .handle_command(Command::SelectScoreTracks { ids: vec![1] })
+        .is_err());
+
+    load_two_hands(&mut core);
+    assert_eq!(
+        targets_shown(&core.drain_events()),
+        vec![(0, vec![48, 72]), (480, vec![76]), (960, vec![43])]
+    );
+
+    core.handle_command(Command::SelectScoreTracks { ids: vec![2] })
+        .expect("left hand");
+    let events = core.drain_events();
+    assert_eq!(targets_shown(&events), vec![(0, vec![48]), (960, vec![43])]);
+    assert!(events
+        .iter()
+        .all(|event| !matches!(event, Event::ScoreDirtyChanged { .. })));
+
+    assert!(core
+        .handle_command(Command::SelectScoreTracks { ids: vec![2, 7] })
+        .is_err());
+    core.handle_command(Command::SelectScoreTracks { ids: Vec::new() })
+        .expect("all tracks");
+    assert_eq!(targets_shown(&core.drain_events()).len(), 3);
+
+    // A new score starts with every track again.
+    core.handle_command(Command::SelectScoreTracks { ids: vec![1] })
+        .expect("right hand");
+    load_two_hands(&mut core);
+    assert_eq!(targets_shown(&core.drain_events()).len(), 3);
+}
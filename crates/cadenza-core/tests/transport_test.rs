@@ -0,0 +1,320 @@
+use cadenza_core::{BarBeat, TimeSignatureMap, Transport};
+use cadenza_domain_score::{TempoPoint, TimeSignaturePoint};
+use cadenza_ports::playback::LoopRange;
+use cadenza_ports::types::Tick;
+
+const PPQ: u16 = 480;
+
+fn sig(tick: i64, numerator: u8, denominator: u8) -> TimeSignaturePoint {
+    TimeSignaturePoint {
+        tick,
+        numerator,
+        denominator,
+    }
+}
+
+fn bar_beat(bar: u32, beat: u32, tick_in_beat: i64) -> BarBeat {
+    BarBeat {
+        bar,
+        beat,
+        tick_in_beat,
+    }
+}
+
+#[test]
+fn bars_follow_a_change_from_four_four_to_three_four() {
+    // Two bars of 4/4, then 3/4 from bar 3.
+    let map = TimeSignatureMap::new(PPQ, vec![sig(0, 4, 4), sig(3840, 3, 4)], 0);
+
+    assert_eq!(map.position_at(0), bar_beat(1, 1, 0));
+    assert_eq!(map.position_at(1919), bar_beat(1, 4, 479));
+    assert_eq!(map.position_at(1920), bar_beat(2, 1, 0));
+    assert_eq!(map.position_at(3840 + 480 + 10), bar_beat(3, 2, 10));
+    assert_eq!(map.position_at(3840 + 1440), bar_beat(4, 1, 0));
+
+    assert_eq!(map.tick_of_bar(1), 0);
+    assert_eq!(map.tick_of_bar(3), 3840);
+    assert_eq!(map.tick_of_bar(5), 3840 + 2 * 1440);
+    assert_eq!(map.beats_in_bar(2), 4);
+    assert_eq!(map.beats_in_bar(3), 3);
+}
+
+#[test]
+fn implicit_pickup_is_bar_zero_ending_on_the_last_beats() {
+    // One-beat pickup in 4/4, then 6/8 from bar 3.
+    let pickup = 480;
+    let six_eight_at = pickup + 2 * 1920;
+    let map = TimeSignatureMap::new(PPQ, vec![sig(0, 4, 4), sig(six_eight_at, 6, 8)], pickup);
+
+    assert_eq!(map.position_at(0), bar_beat(0, 4, 0));
+    assert_eq!(map.position_at(pickup - 1), bar_beat(0, 4, 479));
+    assert_eq!(map.position_at(pickup), bar_beat(1, 1, 0));
+    assert_eq!(map.position_at(pickup + 1920 + 960), bar_beat(2, 3, 0));
+    assert_eq!(map.position_at(six_eight_at + 250), bar_beat(3, 2, 10));
+    assert_eq!(map.position_at(six_eight_at + 1440), bar_beat(4, 1, 0));
+
+    assert_eq!(map.tick_of_bar(0), 0);
+    assert_eq!(map.tick_of_bar(1), pickup);
+    assert_eq!(map.tick_of_bar(3), six_eight_at);
+    assert_eq!(map.beats_in_bar(0), 1);
+    assert_eq!(map.beats_in_bar(1), 4);
+    assert_eq!(map.beats_in_bar(3), 6);
+}
+
+#[test]
+fn missing_or_mid_bar_signatures_fall_back_sensibly() {
+    // No signature: 4/4 throughout.
+    let map = TimeSignatureMap::new(PPQ, Vec::new(), 0);
+    assert_eq!(map.position_at(1920 * 7 + 480), bar_beat(8, 2, 0));
+
+    // A change half-way through bar 2 cuts it short and starts bar 3.
+    let map = TimeSignatureMap::new(PPQ, vec![sig(2880, 3, 4)], 0);
+    assert_eq!(map.beats_in_bar(2), 2);
+    assert_eq!(map.position_at(2880), bar_beat(3, 1, 0));
+    assert_eq!(map.tick_of_bar(4), 2880 + 1440);
+}
+
+#[test]
+fn transport_reports_bar_and_beat_at_its_position() {
+    let mut transport = Transport::new(PPQ, 48_000, Vec::new());
+    transport.update_time_signatures(vec![sig(0, 3, 4)], 0);
+    transport.seek(1440 + 960 + 5);
+    assert_eq!(transport.now_bar_beat(), bar_beat(2, 3, 5));
+}
+
+#[test]
+fn playback_can_start_further_in_than_the_audio_clock_has_run() {
+    let mut transport = Transport::new(PPQ, 48_000, Vec::new());
+    // Bar 10 at 120 bpm is 36 s in; the device opened 2048 samples ago.
+    transport.seek(9 * 1920);
+    transport.align_to_sample_time(2048);
+    transport.play();
+
+    transport.sync_to_sample_time(2048 + 24_000);
+    assert_eq!(transport.now_tick(), 9 * 1920 + 480);
+    assert_eq!(transport.tick_to_sample(9 * 1920 + 960), 2048 + 48_000);
+}
+
+const STEP: u64 = 256;
+
+/// Syncs the transport forward in audio-callback-sized steps, returning the tick after each.
+fn sync_steps(transport: &mut Transport, from: u64, steps: u64) -> Vec<i64> {
+    (1..=steps)
+        .map(|step| {
+            transport.sync_to_sample_time(from + step * STEP);
+            transport.now_tick()
+        })
+        .collect()
+}
+
+fn assert_monotonic(ticks: &[i64]) {
+    for pair in ticks.windows(2) {
+        assert!(pair[1] >= pair[0], "tick went backwards: {pair:?}");
+    }
+}
+
+#[test]
+fn tempo_ramp_accelerates_without_jumping() {
+    let mut transport = Transport::new(PPQ, 48_000, Vec::new());
+    transport.align_to_sample_time(10_000);
+    transport.ramp_tempo_multiplier(2.0, 1000);
+    assert!(transport.is_ramping_tempo());
+
+    let ticks = sync_steps(&mut transport, 10_000, 400);
+    assert_monotonic(&ticks);
+
+    // 48_000 samples in, the ramp is done; each step then advances at double speed.
+    assert!(!transport.is_ramping_tempo());
+    assert_eq!(transport.tempo_multiplier(), 2.0);
+    let first_step = ticks[1] - ticks[0];
+    let last_step = ticks[399] - ticks[398];
+    // 256 samples at 120 bpm is about 5.1 ticks at 1x.
+    assert!((4..=6).contains(&first_step), "{first_step}");
+    assert!((9..=11).contains(&last_step), "{last_step}");
+}
+
+#[test]
+fn a_new_ramp_takes_over_from_the_current_multiplier() {
+    let mut transport = Transport::new(PPQ, 48_000, Vec::new());
+    transport.align_to_sample_time(0);
+    transport.ramp_tempo_multiplier(2.0, 1000);
+    let mut ticks = sync_steps(&mut transport, 0, 94);
+    let halfway = transport.tempo_multiplier();
+    assert!((1.45..=1.55).contains(&halfway), "{halfway}");
+
+    transport.ramp_tempo_multiplier(0.5, 500);
+    assert!(transport.tempo_multiplier() == halfway);
+    ticks.extend(sync_steps(&mut transport, 94 * STEP, 200));
+    assert_monotonic(&ticks);
+    assert_eq!(transport.tempo_multiplier(), 0.5);
+}
+
+#[test]
+fn tempo_ramp_carries_across_a_loop_wrap() {
+    let mut transport = Transport::new(PPQ, 48_000, Vec::new());
+    transport.set_loop(Some(LoopRange {
+        start_tick: 0,
+        end_tick: 960,
+    }));
+    transport.align_to_sample_time(0);
+    transport.ramp_tempo_multiplier(1.5, 2000);
+
+    let ticks = sync_steps(&mut transport, 0, 400);
+    let mut wraps = 0;
+    for pair in ticks.windows(2) {
+        if pair[1] < pair[0] {
+            // Only at the loop end, landing just past the loop start.
+            wraps += 1;
+            assert!(pair[0] > 960 - 8 && pair[1] < 8, "bad wrap {pair:?}");
+        }
+    }
+    assert!(wraps >= 2);
+    assert_eq!(transport.tempo_multiplier(), 1.5);
+}
+
+#[test]
+fn wall_clock_times_follow_tempo_changes_and_the_multiplier() {
+    // 120 bpm for two quarters, then 60 bpm.
+    let tempo = vec![
+        cadenza_domain_score::TempoPoint {
+            tick: 0,
+            us_per_quarter: 500_000,
+        },
+        cadenza_domain_score::TempoPoint {
+            tick: 960,
+            us_per_quarter: 1_000_000,
+        },
+    ];
+    let mut transport = Transport::new(PPQ, 48_000, tempo);
+
+    assert_eq!(transport.tick_to_ms(0), 0);
+    assert_eq!(transport.tick_to_ms(480), 500);
+    assert_eq!(transport.tick_to_ms(960), 1000);
+    assert_eq!(transport.tick_to_ms(1440), 2000);
+    assert_eq!(transport.total_duration_ms(960 + 480 * 4), 5000);
+
+    transport.set_tempo_multiplier(0.5);
+    assert_eq!(transport.tick_to_ms(480), 1000);
+    assert_eq!(transport.tick_to_ms(1440), 4000);
+    assert_eq!(transport.total_duration_ms(960 + 480 * 4), 10_000);
+}
+
+/// Ideal loop position at 2x for `elapsed` samples of playback from the loop start.
+fn ideal_loop_tick(elapsed: u64, start: Tick, len: Tick) -> Tick {
+    // 120 bpm at 480 ppq is 960 ticks per second at 1x.
+    let ticks = (elapsed * 960 * 2 / 48_000) as Tick;
+    start + ticks % len
+}
+
+fn assert_loop_phase(transport: &Transport, elapsed: u64, start: Tick, len: Tick) {
+    let ideal = ideal_loop_tick(elapsed, start, len);
+    let error = (transport.now_tick() - ideal).abs();
+    // Close to the wrap, the ideal may sit on the other side of the boundary.
+    let error = error.min(len - error);
+    assert!(
+        error <= 1,
+        "phase error {error} ticks after {elapsed} samples"
+    );
+}
+
+#[test]
+fn fast_loops_do_not_drift_from_an_ideal_clock() {
+    let (start, len) = (480, 1000);
+    let range = LoopRange {
+        start_tick: start,
+        end_tick: start + len,
+    };
+
+    // Driven block by block.
+    let mut transport = Transport::new(PPQ, 48_000, Vec::new());
+    transport.set_tempo_multiplier(2.0);
+    transport.set_loop(Some(range));
+    transport.seek(start);
+    transport.align_to_sample_time(100_000);
+    transport.play();
+    // 1000 ticks at 2x is about 25_000 samples; 100 passes.
+    let blocks = 100 * 25_000 / 512 + 1;
+    for block in 1..=blocks {
+        transport.advance_by_samples(512);
+        assert_loop_phase(&transport, block * 512, start, len);
+    }
+
+    // Driven by an external clock with uneven steps.
+    let mut transport = Transport::new(PPQ, 48_000, Vec::new());
+    transport.set_tempo_multiplier(2.0);
+    transport.set_loop(Some(range));
+    transport.seek(start);
+    transport.align_to_sample_time(100_000);
+    let mut now = 100_000;
+    for step in 0..5_000u64 {
+        now += 300 + (step * 37) % 700;
+        transport.sync_to_sample_time(now);
+        assert_loop_phase(&transport, now - 100_000, start, len);
+    }
+    assert!(now - 100_000 > 100 * 25_000);
+}
+
+#[test]
+fn precise_tick_is_smooth_between_syncs_and_agrees_at_them() {
+    // 40 bpm: one tick is about 156 samples, so whole ticks step visibly at 60 fps.
+    let mut transport = Transport::new(
+        PPQ,
+        48_000,
+        vec![TempoPoint {
+            tick: 0,
+            us_per_quarter: 1_500_000,
+        }],
+    );
+    transport.seek(0);
+    transport.align_to_sample_time(10_000);
+    transport.play();
+
+    let mut last = transport.now_tick_f64(10_000.0);
+    let mut synced_at = 10_000u64;
+    for step in 1..=2_000u64 {
+        let sample_time = 10_000.0 + step as f64 * 37.5;
+        // The core syncs about every 16 ms.
+        if sample_time as u64 >= synced_at + 768 {
+            synced_at = sample_time as u64;
+            transport.sync_to_sample_time(synced_at);
+            let at_sync = transport.now_tick_f64(synced_at as f64);
+            let whole = transport.now_tick() as f64;
+            assert!(
+                (whole..=whole + 1.0).contains(&at_sync),
+                "{at_sync} vs {whole}"
+            );
+        }
+        let precise = transport.now_tick_f64(sample_time);
+        assert!(precise >= last, "went back from {last} to {precise}");
+        assert!(precise - last < 1.0);
+        last = precise;
+    }
+    // 75_000 samples at 320 ticks per second.
+    assert!((last - 500.0).abs() < 1.0, "{last}");
+
+    transport.pause();
+    assert_eq!(transport.now_tick_f64(1e9), transport.now_tick() as f64);
+}
+
+#[test]
+fn ticks_per_ms_follow_the_tempo_map_and_multiplier() {
+    let mut transport = Transport::new(
+        PPQ,
+        48_000,
+        vec![
+            TempoPoint {
+                tick: 0,
+                us_per_quarter: 500_000,
+            },
+            TempoPoint {
+                tick: 1920,
+                us_per_quarter: 1_000_000,
+            },
+        ],
+    );
+
+    assert!((transport.ticks_per_ms_at(0) - 0.96).abs() < 1e-9);
+    assert!((transport.ticks_per_ms_at(1920) - 0.48).abs() < 1e-9);
+    transport.set_tempo_multiplier(0.5);
+    assert!((transport.ticks_per_ms_at(0) - 0.48).abs() < 1e-9);
+}
@@ -0,0 +1,144 @@
+use cadenza_core::{
+    AppCore, Command, Event, NullMidiInputPort, PianoRollTargetDto, ScoreSource, SilentSynth,
+};
+use cadenza_ports::types::{DeviceId, Tick};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+mod common;
+
+use common::{ManualAudio, SharedRender, BLOCK};
+
+/// Ticks per quarter of the demo scale.
+const QUARTER: Tick = 480;
+/// 2.5 quarters of the scale at 120 bpm and 48 kHz.
+const PLAY_BLOCKS: usize = 60_000 / BLOCK;
+
+struct Rig {
+    core: AppCore,
+    render: SharedRender,
+    sample_time: u64,
+}
+
+impl Rig {
+    fn new() -> Self {
+        let render: SharedRender = Arc::new(Mutex::new(None));
+        let mut core = AppCore::new(
+            Box::new(ManualAudio {
+                render: render.clone(),
+                block: BLOCK,
+            }),
+            Box::new(NullMidiInputPort),
+            Arc::new(SilentSynth),
+            None,
+            None,
+        )
+        .expect("core");
+        core.handle_command(Command::SelectAudioOutput {
+            device_id: DeviceId("manual".to_string()),
+            config: None,
+        })
+        .expect("audio");
+        core.handle_command(Command::LoadScore {
+            source: ScoreSource::InternalDemo("scale".to_string()),
+        })
+        .expect("score");
+        Self {
+            core,
+            render,
+            sample_time: 0,
+        }
+    }
+
+    /// Renders one block, ticks the core and returns the upcoming-target reports.
+    fn step(&mut self) -> Vec<(Tick, Vec<PianoRollTargetDto>)> {
+        let mut left = [0.0; BLOCK];
+        let mut right = [0.0; BLOCK];
+        self.render.lock().as_mut().expect("audio opened").render(
+            self.sample_time,
+            &mut left,
+            &mut right,
+        );
+        self.sample_time += BLOCK as u64;
+        self.core.tick();
+        upcoming(self.core.drain_events())
+    }
+}
+
+fn upcoming(events: Vec<Event>) -> Vec<(Tick, Vec<PianoRollTargetDto>)> {
+    events
+        .into_iter()
+        .filter_map(|event| match event {
+            Event::UpcomingTargets { now_tick, targets } => Some((now_tick, targets)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn ticks(targets: &[PianoRollTargetDto]) -> Vec<Tick> {
+    targets.iter().map(|target| target.tick).collect()
+}
+
+#[test]
+fn upcoming_targets_follow_the_focus_through_the_scale() {
+    let mut rig = Rig::new();
+    let first = rig.step();
+    assert_eq!(first.len(), 1);
+    assert_eq!(first[0].0, 0);
+    assert_eq!(ticks(&first[0].1), vec![0, QUARTER, 2 * QUARTER]);
+    // Nothing moved, so nothing more is sent.
+    assert!(rig.step().is_empty());
+
+    rig.core
+        .handle_command(Command::SetUpcomingTargetCount { count: 2 })
+        .expect("count");
+    let resized = rig.step();
+    assert_eq!(ticks(&resized[0].1), vec![0, QUARTER]);
+
+    rig.core
+        .handle_command(Command::StartPractice)
+        .expect("start");
+    let mut reports = Vec::new();
+    for _ in 0..PLAY_BLOCKS {
+        reports.extend(rig.step());
+    }
+
+    // Each report starts at the focus: a target stays first until it is missed. The
+    // first target was already sent before the start, so play reports from the second.
+    let mut starts: Vec<Tick> = reports.iter().map(|(_, targets)| targets[0].tick).collect();
+    starts.dedup();
+    assert_eq!(starts, vec![QUARTER, 2 * QUARTER, 3 * QUARTER]);
+    for (now_tick, targets) in &reports {
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[1].tick - targets[0].tick, QUARTER);
+        assert!(
+            targets[0].tick + QUARTER > *now_tick,
+            "{now_tick} {targets:?}"
+        );
+    }
+    // Sent on a change of focus or beat, not on every one of the ticks.
+    assert!(reports.len() <= 6, "{} reports", reports.len());
+    for pair in reports.windows(2) {
+        let key = |(now_tick, targets): &(Tick, Vec<PianoRollTargetDto>)| {
+            (now_tick / QUARTER, targets[0].id)
+        };
+        assert_ne!(key(&pair[0]), key(&pair[1]));
+    }
+
+    rig.core
+        .handle_command(Command::StopPractice)
+        .expect("stop");
+    rig.core
+        .handle_command(Command::Seek { tick: 0 })
+        .expect("seek");
+    let rewound = rig.step();
+    assert_eq!(ticks(&rewound.last().expect("report").1), vec![0, QUARTER]);
+
+    rig.core
+        .handle_command(Command::SetUpcomingTargetCount { count: 0 })
+        .expect("count");
+    rig.core
+        .handle_command(Command::Seek { tick: 3 * QUARTER })
+        .expect("seek");
+    assert!(rig.step().is_empty());
+}
@@ -0,0 +1,51 @@
+use cadenza_core::{WavFileAudioOutputPort, WAV_AUDIO_DEVICE_ID};
+use cadenza_ports::audio::{AudioOutputPort, AudioRenderCallback};
+use cadenza_ports::types::{AudioConfig, DeviceId, SampleTime};
+use std::thread;
+use std::time::Duration;
+
+/// Left at 0.25 and right at -0.25, so the channel order shows in the file.
+struct Dc;
+
+impl AudioRenderCallback for Dc {
+    fn render(&mut self, _sample_time_start: SampleTime, out_l: &mut [f32], out_r: &mut [f32]) {
+        out_l.fill(0.25);
+        out_r.fill(-0.25);
+    }
+}
+
+#[test]
+fn the_wav_output_keeps_what_was_rendered() {
+    let path = std::env::temp_dir().join(format!("cadenza-wav-audio-{}.wav", std::process::id()));
+    let port = WavFileAudioOutputPort::new(&path);
+    let device = &port.list_outputs().expect("outputs")[0];
+    assert_eq!(device.id.0, WAV_AUDIO_DEVICE_ID);
+
+    let config = AudioConfig {
+        sample_rate_hz: 8_000,
+        channels: 2,
+        buffer_size_frames: Some(80),
+    };
+    let stream = port
+        .open_output(
+            &DeviceId(WAV_AUDIO_DEVICE_ID.to_string()),
+            config,
+            Box::new(Dc),
+        )
+        .expect("open");
+    thread::sleep(Duration::from_millis(100));
+    stream.close();
+
+    let mut reader = hound::WavReader::open(&path).expect("wav");
+    assert_eq!(reader.spec().sample_rate, 8_000);
+    assert_eq!(reader.spec().channels, 2);
+    let samples: Vec<f32> = reader.samples::<f32>().map(Result::unwrap).collect();
+    let _ = std::fs::remove_file(&path);
+
+    // Whole blocks, and paced like a device rather than as fast as possible: 100 ms is
+    // 800 frames.
+    let frames = samples.len() / 2;
+    assert_eq!(frames % 80, 0, "{frames}");
+    assert!((80..=1600).contains(&frames), "{frames}");
+    assert_eq!(&samples[..2], &[0.25, -0.25]);
+}
@@ -1,14 +1,22 @@
-use cadenza_domain_score::TargetEvent;
+use cadenza_domain_score::{NoteId, TargetEvent};
 use cadenza_ports::types::Tick;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct TimingWindowTicks {
     pub perfect: i64,
     pub good: i64,
 }
 
+/// Windows in real time, so they hold at any tempo; see [`Judge::retime_windows`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimingWindowMs {
+    pub perfect: f32,
+    pub good: f32,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct ChordRollTicks(pub i64);
 
@@ -26,10 +34,17 @@ pub enum AdvanceMode {
 
 #[derive(Clone, Copy, Debug)]
 pub struct JudgeConfig {
+    /// Used as is unless `window_ms` is set, and until windows are first retimed.
     pub window: TimingWindowTicks,
+    pub window_ms: Option<TimingWindowMs>,
     pub chord_roll: ChordRollTicks,
     pub wrong_note_policy: WrongNotePolicy,
     pub advance: AdvanceMode,
+    /// Extra ticks a late hit is still accepted for on the first target after loading,
+    /// seeking or a loop wrap, when the player had no lead-in to react to. Used as is
+    /// unless `first_target_grace_ms` is set, and until windows are first retimed.
+    pub first_target_grace: Tick,
+    pub first_target_grace_ms: Option<f32>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -61,6 +76,8 @@ pub enum JudgeEvent {
         reason: MissReason,
         missing_notes: u32,
         wrong_notes: u32,
+        /// The unplayed notes, when the target carries note ids.
+        missed_note_ids: Vec<NoteId>,
     },
     Stats {
         combo: u32,
@@ -68,9 +85,24 @@ pub enum JudgeEvent {
         hit: u32,
         miss: u32,
         wrong: u32,
+        extra_notes: u32,
     },
 }
 
+/// Point-in-time view of the judge, for diagnostics.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct JudgeSnapshot {
+    pub focus_target_id: Option<u64>,
+    pub target_index: usize,
+    pub target_count: usize,
+    pub combo: u32,
+    pub score: i64,
+    pub hit: u32,
+    pub miss: u32,
+    pub wrong: u32,
+    pub extra_notes: u32,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct PlayerNoteOn {
     pub tick: Tick,
@@ -85,6 +117,9 @@ struct StatsState {
     hit: u32,
     miss: u32,
     wrong: u32,
+    /// Notes played before the focused target's window opened, such as passing tones
+    /// between targets. Unlike wrong notes they never affect a grade.
+    extra_notes: u32,
 }
 
 #[derive(Debug)]
@@ -98,9 +133,16 @@ struct TargetState {
 pub struct Judge {
     cfg: JudgeConfig,
     targets: Vec<TargetEvent>,
+    /// Per target, from `window_ms`; empty while the configured tick window applies.
+    target_windows: Vec<TimingWindowTicks>,
+    /// Per target, from `first_target_grace_ms`; empty while the configured ticks apply.
+    target_graces: Vec<Tick>,
     idx: usize,
     state: Option<TargetState>,
     stats: StatsState,
+    range: Option<Range<Tick>>,
+    /// The focused target is the first since targets were loaded or the judge sought.
+    first_after_jump: bool,
 }
 
 impl Judge {
@@ -108,16 +150,54 @@ impl Judge {
         Self {
             cfg,
             targets: Vec::new(),
+            target_windows: Vec::new(),
+            target_graces: Vec::new(),
             idx: 0,
             state: None,
             stats: StatsState::default(),
+            range: None,
+            first_after_jump: true,
         }
     }
 
     pub fn load_targets(&mut self, targets: Vec<TargetEvent>) -> Vec<JudgeEvent> {
         self.targets = targets;
-        self.idx = 0;
+        self.target_windows.clear();
+        self.target_graces.clear();
+        self.idx = self.first_required(self.first_index_in_range(0));
         self.state = self.build_state();
+        self.first_after_jump = true;
+        vec![JudgeEvent::FocusChanged {
+            target_id: self.current_focus(),
+        }]
+    }
+
+    /// Limits judging to targets in `range`; targets outside it are never focused, so
+    /// they are neither hit nor missed. `None` judges every target.
+    pub fn set_range(&mut self, range: Option<Range<Tick>>) -> Vec<JudgeEvent> {
+        self.range = range;
+        let idx = self.first_required(self.first_index_in_range(self.idx));
+        if idx == self.idx {
+            return Vec::new();
+        }
+        self.idx = idx;
+        self.state = self.build_state();
+        vec![JudgeEvent::FocusChanged {
+            target_id: self.current_focus(),
+        }]
+    }
+
+    /// Focuses the first target still open at `tick`, dropping progress on the current one.
+    /// Targets skipped over, in either direction, are not judged.
+    pub fn seek(&mut self, tick: Tick) -> Vec<JudgeEvent> {
+        // Targets from `tick` on are open; before it, only those whose window reaches it.
+        let mut open = self.targets.partition_point(|t| t.tick < tick);
+        while open > 0 && self.targets[open - 1].tick + self.window(open - 1).good >= tick {
+            open -= 1;
+        }
+        self.idx = self.first_required(self.first_index_in_range(open));
+        self.state = self.build_state();
+        self.first_after_jump = true;
         vec![JudgeEvent::FocusChanged {
             target_id: self.current_focus(),
         }]
@@ -131,13 +211,20 @@ impl Judge {
 
         let target_id = target.id;
         let target_tick = target.tick;
-        let good = self.cfg.window.good;
-        let perfect = self.cfg.window.perfect;
+        let expected = self.state.as_ref().is_some_and(|state| {
+            state.expected.contains(&e.note) && !state.matched.contains_key(&e.note)
+        });
+        if !expected && self.plays_optional(&e) {
+            return events;
+        }
+        let TimingWindowTicks { perfect, good } = self.window(self.idx);
         let window_start = target_tick - good;
-        let window_end = target_tick + good;
+        let window_end = self.window_end(target_tick);
         let mut resolved: Option<(Grade, i64, u32)> = None;
 
         if e.tick < window_start {
+            self.stats.extra_notes += 1;
+            events.push(self.stats_event());
             return events;
         }
 
@@ -196,28 +283,32 @@ impl Judge {
 
     pub fn advance_to(&mut self, now_tick: Tick) -> Vec<JudgeEvent> {
         let mut events = Vec::new();
-        loop {
-            let Some(target) = self.current_target() else {
-                break;
-            };
+        while let Some(target) = self.current_target() {
             let Some(state) = self.state.as_ref() else {
                 break;
             };
 
-            let good = self.cfg.window.good;
-            if now_tick <= target.tick + good {
+            if now_tick <= self.window_end(target.tick) {
                 break;
             }
 
             let missing_notes = state.expected.len().saturating_sub(state.matched.len()) as u32;
             let wrong_notes = state.wrong_notes;
             let target_id = target.id;
+            let missed_note_ids = target
+                .notes
+                .iter()
+                .zip(&target.note_ids)
+                .filter(|(note, _)| !state.matched.contains_key(note))
+                .map(|(_, &id)| id)
+                .collect();
 
             events.push(JudgeEvent::Miss {
                 target_id,
                 reason: MissReason::Timeout,
                 missing_notes,
                 wrong_notes,
+                missed_note_ids,
             });
 
             self.update_stats_on_miss(wrong_notes, &mut events);
@@ -227,12 +318,110 @@ impl Judge {
         events
     }
 
+    pub fn snapshot(&self) -> JudgeSnapshot {
+        JudgeSnapshot {
+            focus_target_id: self.current_focus(),
+            target_index: self.idx,
+            target_count: self.targets.len(),
+            combo: self.stats.combo,
+            score: self.stats.score,
+            hit: self.stats.hit,
+            miss: self.stats.miss,
+            wrong: self.stats.wrong,
+            extra_notes: self.stats.extra_notes,
+        }
+    }
+
+    /// Puts back the counts of an earlier run, such as one recovered after a crash. Focus
+    /// and targets are left as they are.
+    pub fn restore_stats(&mut self, snapshot: &JudgeSnapshot) -> Vec<JudgeEvent> {
+        self.stats = StatsState {
+            combo: snapshot.combo,
+            score: snapshot.score,
+            hit: snapshot.hit,
+            miss: snapshot.miss,
+            wrong: snapshot.wrong,
+            extra_notes: snapshot.extra_notes,
+        };
+        vec![self.stats_event()]
+    }
+
     pub fn current_focus(&self) -> Option<u64> {
-        self.targets.get(self.idx).map(|t| t.id)
+        self.current_target().map(|t| t.id)
+    }
+
+    /// Tick where the focused target's window opens.
+    pub fn focus_window_start(&self) -> Option<Tick> {
+        self.current_target()
+            .map(|t| t.tick - self.window(self.idx).good)
+    }
+
+    /// Window of the focused target in ticks, as currently in effect.
+    pub fn focus_window(&self) -> Option<TimingWindowTicks> {
+        self.current_target().map(|_| self.window(self.idx))
+    }
+
+    /// Converts `window_ms` and `first_target_grace_ms` into ticks for every target, at
+    /// the tempo given by `ticks_per_ms` at each target's tick. Call again whenever the
+    /// tempo changes; settings without milliseconds keep their ticks.
+    pub fn retime_windows(&mut self, ticks_per_ms: impl Fn(Tick) -> f64) {
+        if let Some(window_ms) = self.cfg.window_ms {
+            self.target_windows = self
+                .targets
+                .iter()
+                .map(|target| {
+                    let rate = ticks_per_ms(target.tick);
+                    TimingWindowTicks {
+                        perfect: (window_ms.perfect as f64 * rate).round() as i64,
+                        good: (window_ms.good as f64 * rate).round() as i64,
+                    }
+                })
+                .collect();
+        }
+        if let Some(grace_ms) = self.cfg.first_target_grace_ms {
+            self.target_graces = self
+                .targets
+                .iter()
+                .map(|target| (grace_ms as f64 * ticks_per_ms(target.tick)).round() as Tick)
+                .collect();
+        }
+    }
+
+    fn window(&self, idx: usize) -> TimingWindowTicks {
+        self.target_windows
+            .get(idx)
+            .copied()
+            .unwrap_or(self.cfg.window)
+    }
+
+    /// Last tick a hit on the focused target at `target_tick` counts.
+    fn window_end(&self, target_tick: Tick) -> Tick {
+        let grace = if self.first_after_jump {
+            self.target_graces
+                .get(self.idx)
+                .copied()
+                .unwrap_or(self.cfg.first_target_grace)
+                .max(0)
+        } else {
+            0
+        };
+        target_tick + self.window(self.idx).good + grace
     }
 
     fn current_target(&self) -> Option<&TargetEvent> {
-        self.targets.get(self.idx)
+        let target = self.targets.get(self.idx)?;
+        match &self.range {
+            Some(range) if target.tick >= range.end => None,
+            _ => Some(target),
+        }
+    }
+
+    /// First index at or after `from` that is not before the range start.
+    fn first_index_in_range(&self, from: usize) -> usize {
+        let Some(range) = &self.range else {
+            return from;
+        };
+        from.max(self.targets.partition_point(|t| t.tick < range.start))
     }
 
     fn build_state(&self) -> Option<TargetState> {
@@ -246,9 +435,30 @@ impl Judge {
         })
     }
 
+    /// First target at or after `from` the player has to play. Optional targets, such as
+    /// grace notes, are never focused, so skipping them is neither a miss nor a combo break.
+    fn first_required(&self, from: usize) -> usize {
+        from + self.targets[from.min(self.targets.len())..]
+            .iter()
+            .take_while(|target| target.optional)
+            .count()
+    }
+
+    /// Whether `e` plays a note of an optional target it falls in the window of. Such notes
+    /// count neither for nor against the player.
+    fn plays_optional(&self, e: &PlayerNoteOn) -> bool {
+        let good = self.window(self.idx).good;
+        let from = self.targets.partition_point(|t| t.tick < e.tick - good);
+        self.targets[from..]
+            .iter()
+            .take_while(|t| t.tick <= e.tick + good)
+            .any(|t| t.optional && t.notes.contains(&e.note))
+    }
+
     fn advance_focus(&mut self, events: &mut Vec<JudgeEvent>) {
-        self.idx = self.idx.saturating_add(1);
+        self.idx = self.first_required(self.idx.saturating_add(1));
         self.state = self.build_state();
+        self.first_after_jump = false;
         events.push(JudgeEvent::FocusChanged {
             target_id: self.current_focus(),
         });
@@ -285,6 +495,7 @@ impl Judge {
             hit: self.stats.hit,
             miss: self.stats.miss,
             wrong: self.stats.wrong,
+            extra_notes: self.stats.extra_notes,
         }
     }
 }
@@ -1,8 +1,10 @@
 use cadenza_domain_eval::{
     AdvanceMode, ChordRollTicks, Grade, Judge, JudgeConfig, JudgeEvent, PlayerNoteOn,
-    TimingWindowTicks, WrongNotePolicy,
+    TimingWindowMs, TimingWindowTicks, WrongNotePolicy,
+};
+use cadenza_domain_score::{
+    cleanup_omr_score, import_musicxml_str, OmrCleanupOptions, ScoreSource, TargetEvent,
 };
-use cadenza_domain_score::TargetEvent;
 
 fn target(id: u64, tick: i64, notes: &[u8]) -> TargetEvent {
     TargetEvent {
@@ -11,6 +13,9 @@ fn target(id: u64, tick: i64, notes: &[u8]) -> TargetEvent {
         notes: notes.to_vec(),
         hand: None,
         measure_index: None,
+        optional: false,
+        note_ids: Vec::new(),
+        note_tracks: Vec::new(),
     }
 }
 
@@ -21,9 +26,12 @@ fn perfect_hit_single_note() {
             perfect: 5,
             good: 10,
         },
+        window_ms: None,
         chord_roll: ChordRollTicks(4),
         wrong_note_policy: WrongNotePolicy::RecordOnly,
         advance: AdvanceMode::OnResolve,
+        first_target_grace: 0,
+        first_target_grace_ms: None,
     };
     let mut judge = Judge::new(cfg);
     judge.load_targets(vec![target(1, 100, &[60])]);
@@ -51,9 +59,12 @@ fn wrong_note_degrades_perfect() {
             perfect: 3,
             good: 8,
         },
+        window_ms: None,
         chord_roll: ChordRollTicks(4),
         wrong_note_policy: WrongNotePolicy::DegradePerfect,
         advance: AdvanceMode::OnResolve,
+        first_target_grace: 0,
+        first_target_grace_ms: None,
     };
     let mut judge = Judge::new(cfg);
     judge.load_targets(vec![target(1, 200, &[64])]);
@@ -86,9 +97,12 @@ fn chord_roll_allows_split_hits() {
             perfect: 2,
             good: 6,
         },
+        window_ms: None,
         chord_roll: ChordRollTicks(3),
         wrong_note_policy: WrongNotePolicy::RecordOnly,
         advance: AdvanceMode::OnResolve,
+        first_target_grace: 0,
+        first_target_grace_ms: None,
     };
     let mut judge = Judge::new(cfg);
     judge.load_targets(vec![target(1, 300, &[60, 64])]);
@@ -121,9 +135,12 @@ fn advance_to_emits_miss_after_window() {
             perfect: 2,
             good: 6,
         },
+        window_ms: None,
         chord_roll: ChordRollTicks(3),
         wrong_note_policy: WrongNotePolicy::RecordOnly,
         advance: AdvanceMode::OnResolve,
+        first_target_grace: 0,
+        first_target_grace_ms: None,
     };
     let mut judge = Judge::new(cfg);
     judge.load_targets(vec![target(1, 100, &[60])]);
@@ -134,3 +151,410 @@ fn advance_to_emits_miss_after_window() {
         .iter()
         .any(|event| matches!(event, JudgeEvent::Miss { target_id: 1, .. })));
 }
+
+#[test]
+fn seek_and_range_limit_which_targets_are_judged() {
+    let cfg = JudgeConfig {
+        window: TimingWindowTicks {
+            perfect: 2,
+            good: 6,
+        },
+        window_ms: None,
+        chord_roll: ChordRollTicks(3),
+        wrong_note_policy: WrongNotePolicy::RecordOnly,
+        advance: AdvanceMode::OnResolve,
+        first_target_grace: 0,
+        first_target_grace_ms: None,
+    };
+    let mut judge = Judge::new(cfg);
+    judge.load_targets(vec![
+        target(1, 100, &[60]),
+        target(2, 200, &[62]),
+        target(3, 300, &[64]),
+        target(4, 400, &[65]),
+    ]);
+    judge.set_range(Some(200..400));
+
+    let events = judge.seek(200);
+    assert!(matches!(
+        events[..],
+        [JudgeEvent::FocusChanged { target_id: Some(2) }]
+    ));
+
+    // Target 4 lies at the range end, so running past it misses only 2 and 3.
+    let events = judge.advance_to(1_000);
+    let missed: Vec<u64> = events
+        .iter()
+        .filter_map(|event| match event {
+            JudgeEvent::Miss { target_id, .. } => Some(*target_id),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(missed, vec![2, 3]);
+    assert_eq!(judge.current_focus(), None);
+
+    // Back to the start of the range, as at a loop wrap: nothing skipped is judged.
+    judge.seek(200);
+    assert_eq!(judge.current_focus(), Some(2));
+    assert_eq!(judge.snapshot().miss, 2);
+}
+
+#[test]
+fn the_first_target_after_a_jump_forgives_a_late_hit() {
+    let cfg = JudgeConfig {
+        window: TimingWindowTicks {
+            perfect: 2,
+            good: 6,
+        },
+        window_ms: None,
+        chord_roll: ChordRollTicks(3),
+        wrong_note_policy: WrongNotePolicy::RecordOnly,
+        advance: AdvanceMode::OnResolve,
+        first_target_grace: 10,
+        first_target_grace_ms: None,
+    };
+    let hit_late = |judge: &mut Judge, tick: i64, note: u8| -> Vec<JudgeEvent> {
+        judge.on_note_on(PlayerNoteOn {
+            tick,
+            note,
+            velocity: 90,
+        })
+    };
+    let graded = |events: &[JudgeEvent]| -> Vec<(u64, Grade)> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                JudgeEvent::Hit {
+                    target_id, grade, ..
+                } => Some((*target_id, *grade)),
+                JudgeEvent::Miss { target_id, .. } => Some((*target_id, Grade::Miss)),
+                _ => None,
+            })
+            .collect()
+    };
+    let mut judge = Judge::new(cfg);
+    judge.load_targets(vec![
+        target(1, 100, &[60]),
+        target(2, 200, &[62]),
+        target(3, 300, &[64]),
+    ]);
+
+    // 12 ticks late is past the good window but within the grace.
+    assert_eq!(
+        graded(&hit_late(&mut judge, 112, 60)),
+        vec![(1, Grade::Good)]
+    );
+    // The same lateness on the next target is a miss.
+    assert_eq!(
+        graded(&hit_late(&mut judge, 212, 62)),
+        vec![(2, Grade::Miss)]
+    );
+
+    // A seek grants the grace again, once.
+    judge.seek(190);
+    assert_eq!(
+        graded(&hit_late(&mut judge, 212, 62)),
+        vec![(2, Grade::Good)]
+    );
+    assert_eq!(
+        graded(&hit_late(&mut judge, 312, 64)),
+        vec![(3, Grade::Miss)]
+    );
+}
+
+#[test]
+fn notes_between_targets_are_extra_rather_than_wrong() {
+    let cfg = JudgeConfig {
+        window: TimingWindowTicks {
+            perfect: 2,
+            good: 6,
+        },
+        window_ms: None,
+        chord_roll: ChordRollTicks(3),
+        wrong_note_policy: WrongNotePolicy::DegradePerfect,
+        advance: AdvanceMode::OnResolve,
+        first_target_grace: 0,
+        first_target_grace_ms: None,
+    };
+    let mut judge = Judge::new(cfg);
+    judge.load_targets(vec![target(1, 100, &[60]), target(2, 200, &[62])]);
+    let play = |judge: &mut Judge, tick: i64, note: u8| {
+        judge.on_note_on(PlayerNoteOn {
+            tick,
+            note,
+            velocity: 90,
+        })
+    };
+
+    play(&mut judge, 100, 60);
+    // Passing tones before target 2's window opens at 194.
+    play(&mut judge, 140, 61);
+    play(&mut judge, 193, 63);
+    let events = play(&mut judge, 200, 62);
+    assert!(events.iter().any(|event| matches!(
+        event,
+        JudgeEvent::Hit {
+            target_id: 2,
+            grade: Grade::Perfect,
+            wrong_notes: 0,
+            ..
+        }
+    )));
+    let snapshot = judge.snapshot();
+    assert_eq!((snapshot.extra_notes, snapshot.wrong), (2, 0));
+
+    // Inside the window a stray note is wrong and costs the perfect.
+    judge.load_targets(vec![target(3, 300, &[64])]);
+    play(&mut judge, 296, 65);
+    let events = play(&mut judge, 300, 64);
+    assert!(events.iter().any(|event| matches!(
+        event,
+        JudgeEvent::Hit {
+            target_id: 3,
+            grade: Grade::Good,
+            wrong_notes: 1,
+            ..
+        }
+    )));
+    let snapshot = judge.snapshot();
+    assert_eq!((snapshot.extra_notes, snapshot.wrong), (2, 1));
+}
+
+#[test]
+fn grace_notes_are_optional() {
+    let cfg = JudgeConfig {
+        window: TimingWindowTicks {
+            perfect: 5,
+            good: 20,
+        },
+        window_ms: None,
+        chord_roll: ChordRollTicks(4),
+        wrong_note_policy: WrongNotePolicy::DegradePerfect,
+        advance: AdvanceMode::OnResolve,
+        first_target_grace: 0,
+        first_target_grace_ms: None,
+    };
+    // An unslashed grace on the beat, overlapping the window of the note it leads into.
+    let targets = || {
+        let mut grace = target(1, 100, &[62]);
+        grace.optional = true;
+        vec![grace, target(2, 110, &[60]), target(3, 300, &[64])]
+    };
+    let play = |judge: &mut Judge, tick: i64, note: u8| {
+        judge.on_note_on(PlayerNoteOn {
+            tick,
+            note,
+            velocity: 90,
+        })
+    };
+
+    // Only the main note: a clean hit and no miss for the skipped grace.
+    let mut judge = Judge::new(cfg);
+    let events = judge.load_targets(targets());
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, JudgeEvent::FocusChanged { target_id: Some(2) })));
+    let events = play(&mut judge, 110, 60);
+    assert!(events.iter().any(|event| matches!(
+        event,
+        JudgeEvent::Hit {
+            target_id: 2,
+            grade: Grade::Perfect,
+            wrong_notes: 0,
+            ..
+        }
+    )));
+    judge.advance_to(250);
+    let snapshot = judge.snapshot();
+    assert_eq!((snapshot.hit, snapshot.miss, snapshot.combo), (1, 0, 1));
+    assert_eq!((snapshot.wrong, snapshot.extra_notes), (0, 0));
+
+    // Playing the grace as well costs nothing.
+    let mut judge = Judge::new(cfg);
+    judge.load_targets(targets());
+    play(&mut judge, 100, 62);
+    let events = play(&mut judge, 110, 60);
+    assert!(events.iter().any(|event| matches!(
+        event,
+        JudgeEvent::Hit {
+            target_id: 2,
+            grade: Grade::Perfect,
+            wrong_notes: 0,
+            ..
+        }
+    )));
+    let snapshot = judge.snapshot();
+    assert_eq!((snapshot.wrong, snapshot.extra_notes), (0, 0));
+}
+
+#[test]
+fn millisecond_windows_follow_the_tempo() {
+    let cfg = JudgeConfig {
+        window: TimingWindowTicks {
+            perfect: 30,
+            good: 80,
+        },
+        window_ms: Some(TimingWindowMs {
+            perfect: 25.0,
+            good: 75.0,
+        }),
+        chord_roll: ChordRollTicks(24),
+        wrong_note_policy: WrongNotePolicy::RecordOnly,
+        advance: AdvanceMode::OnResolve,
+        first_target_grace: 0,
+        first_target_grace_ms: None,
+    };
+    // 480 ppq at 120 bpm is 0.96 ticks per ms, scaled by the tempo multiplier.
+    let ticks_per_ms = |multiplier: f64| move |_tick: i64| 0.96 * multiplier;
+    let mut judge = Judge::new(cfg);
+    judge.load_targets(vec![target(1, 480, &[60]), target(2, 960, &[62])]);
+
+    // Until converted, the tick window applies.
+    assert_eq!(
+        judge.focus_window(),
+        Some(TimingWindowTicks {
+            perfect: 30,
+            good: 80
+        })
+    );
+
+    judge.retime_windows(ticks_per_ms(1.0));
+    assert_eq!(
+        judge.focus_window(),
+        Some(TimingWindowTicks {
+            perfect: 24,
+            good: 72
+        })
+    );
+
+    // At half speed the same milliseconds span half the ticks.
+    judge.retime_windows(ticks_per_ms(0.5));
+    assert_eq!(
+        judge.focus_window(),
+        Some(TimingWindowTicks {
+            perfect: 12,
+            good: 36
+        })
+    );
+    let events = judge.on_note_on(PlayerNoteOn {
+        tick: 480 + 30,
+        note: 60,
+        velocity: 90,
+    });
+    assert!(events.iter().any(|event| matches!(
+        event,
+        JudgeEvent::Hit {
+            target_id: 1,
+            grade: Grade::Good,
+            ..
+        }
+    )));
+    let events = judge.advance_to(960 + 37);
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, JudgeEvent::Miss { target_id: 2, .. })));
+}
+
+#[test]
+fn the_first_target_grace_in_milliseconds_follows_the_tempo() {
+    let cfg = JudgeConfig {
+        window: TimingWindowTicks {
+            perfect: 30,
+            good: 80,
+        },
+        window_ms: Some(TimingWindowMs {
+            perfect: 25.0,
+            good: 75.0,
+        }),
+        chord_roll: ChordRollTicks(24),
+        wrong_note_policy: WrongNotePolicy::RecordOnly,
+        advance: AdvanceMode::OnResolve,
+        first_target_grace: 0,
+        first_target_grace_ms: Some(50.0),
+    };
+    let hit = |judge: &mut Judge, tick: i64| -> Option<Grade> {
+        judge
+            .on_note_on(PlayerNoteOn {
+                tick,
+                note: 60,
+                velocity: 90,
+            })
+            .iter()
+            .find_map(|event| match event {
+                JudgeEvent::Hit { grade, .. } => Some(*grade),
+                JudgeEvent::Miss { .. } => Some(Grade::Miss),
+                _ => None,
+            })
+    };
+    let mut judge = Judge::new(cfg);
+    judge.load_targets(vec![target(1, 480, &[60])]);
+
+    // At 0.96 ticks per ms the good window is 72 ticks and the grace 48 more.
+    judge.retime_windows(|_| 0.96);
+    assert_eq!(hit(&mut judge, 480 + 72 + 40), Some(Grade::Good));
+
+    // At half speed both halve, so the same lateness is now past the grace.
+    judge.seek(0);
+    judge.retime_windows(|_| 0.48);
+    assert_eq!(hit(&mut judge, 480 + 36 + 20), Some(Grade::Good));
+    judge.seek(0);
+    assert_eq!(hit(&mut judge, 480 + 72 + 40), Some(Grade::Miss));
+}
+
+#[test]
+fn notes_omr_cleanup_marked_optional_are_never_missed() {
+    // A 64th E5 speck between two quarter notes.
+    let xml = r#"
+<score-partwise version="3.1">
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>16</divisions>
+        <time><beats>4</beats><beat-type>4</beat-type></time>
+      </attributes>
+      <note><pitch><step>C</step><octave>5</octave></pitch><duration>16</duration></note>
+      <note><pitch><step>E</step><octave>5</octave></pitch><duration>1</duration></note>
+      <note><rest/><duration>15</duration></note>
+      <note><pitch><step>A</step><octave>4</octave></pitch><duration>32</duration></note>
+    </measure>
+  </part>
+</score-partwise>
+"#;
+    let mut score = import_musicxml_str(xml).expect("import");
+    score.meta.source = ScoreSource::PdfOmr;
+    let options = OmrCleanupOptions {
+        mark_optional: true,
+        ..OmrCleanupOptions::default()
+    };
+    cleanup_omr_score(&mut score, &options);
+    let targets = score.tracks[0].targets.clone();
+    assert!(targets.iter().any(|t| t.optional && t.notes == vec![76]));
+
+    let cfg = JudgeConfig {
+        window: TimingWindowTicks {
+            perfect: 30,
+            good: 80,
+        },
+        window_ms: None,
+        chord_roll: ChordRollTicks(24),
+        wrong_note_policy: WrongNotePolicy::DegradePerfect,
+        advance: AdvanceMode::OnResolve,
+        first_target_grace: 0,
+        first_target_grace_ms: None,
+    };
+    let mut judge = Judge::new(cfg);
+    judge.load_targets(targets);
+    for (tick, note) in [(0, 72), (960, 69)] {
+        judge.on_note_on(PlayerNoteOn {
+            tick,
+            note,
+            velocity: 90,
+        });
+    }
+    judge.advance_to(1920);
+    let snapshot = judge.snapshot();
+    assert_eq!((snapshot.hit, snapshot.miss, snapshot.combo), (2, 0, 2));
+}
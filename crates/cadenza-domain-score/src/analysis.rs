@@ -0,0 +1,152 @@
+use crate::model::{Hand, Score, TempoPoint};
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::types::Tick;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Onsets closer than this after the same pitch count as a fast repeat.
+pub const FAST_REPEAT_MS: f64 = 150.0;
+/// Notes per second a player can be expected to manage on a first read at full tempo.
+pub const COMFORTABLE_NOTES_PER_SECOND: f32 = 6.0;
+/// Slowest tempo [`ScoreAnalysis::suggested_tempo_multiplier`] suggests.
+pub const MIN_SUGGESTED_TEMPO: f32 = 0.5;
+
+/// How hard a score looks to play, from the notes the player is expected to play.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScoreAnalysis {
+    /// Most note onsets in any one-second stretch, at the written tempo.
+    pub peak_notes_per_second: f32,
+    /// Most different pitches starting together.
+    pub max_chord_size: u32,
+    /// Widest interval in semitones between notes one hand starts together.
+    pub left_hand_span: u8,
+    pub right_hand_span: u8,
+    /// Onsets following the same pitch within [`FAST_REPEAT_MS`].
+    pub fast_repeated_notes: u32,
+    /// Tempo to start practicing at, from [`MIN_SUGGESTED_TEMPO`] to 1.0 in steps of 0.05.
+    pub suggested_tempo_multiplier: f32,
+}
+
+/// Analyzes the written notes of every track: ornament expansions and notes routed to a
+/// fixed bus, such as metronome clicks, are left out. Notes without a hand count as left
+/// below middle C and right from it.
+pub fn analyze_score(score: &Score) -> ScoreAnalysis {
+    let mut onsets: BTreeMap<(Tick, u8), Hand> = BTreeMap::new();
+    for track in &score.tracks {
+        for event in &track.playback_events {
+            let MidiLikeEvent::NoteOn { note, velocity } = event.event else {
+                continue;
+            };
+            if velocity == 0 || event.ornament_of.is_some() || event.bus.is_some() {
+                continue;
+            }
+            let hand = event.hand.or(track.hand).unwrap_or(if note < 60 {
+                Hand::Left
+            } else {
+                Hand::Right
+            });
+            onsets.entry((event.tick, note)).or_insert(hand);
+        }
+    }
+
+    let clock = TempoClock::new(score);
+    let mut chords: BTreeMap<Tick, BTreeSet<u8>> = BTreeMap::new();
+    let mut hand_spans: HashMap<(Tick, Hand), (u8, u8)> = HashMap::new();
+    for (&(tick, note), &hand) in &onsets {
+        chords.entry(tick).or_default().insert(note);
+        let span = hand_spans.entry((tick, hand)).or_insert((note, note));
+        span.0 = span.0.min(note);
+        span.1 = span.1.max(note);
+    }
+    let max_chord_size = chords.values().map(|c| c.len() as u32).max().unwrap_or(0);
+    let widest = |hand: Hand| {
+        hand_spans
+            .iter()
+            .filter(|((_, h), _)| *h == hand)
+            .map(|(_, (low, high))| high - low)
+            .max()
+            .unwrap_or(0)
+    };
+
+    // Onset times, one per pitch and tick, in order.
+    let times: Vec<(f64, u8)> = chords
+        .iter()
+        .flat_map(|(&tick, notes)| {
+            let ms = clock.ms_at(tick);
+            notes.iter().map(move |&note| (ms, note))
+        })
+        .collect();
+
+    let mut peak = 0;
+    let mut first = 0;
+    for (last, &(ms, _)) in times.iter().enumerate() {
+        while times[first].0 <= ms - 1000.0 {
+            first += 1;
+        }
+        peak = peak.max(last + 1 - first);
+    }
+
+    let mut previous: HashMap<u8, f64> = HashMap::new();
+    let mut fast_repeated_notes = 0;
+    for &(ms, note) in &times {
+        if let Some(before) = previous.insert(note, ms) {
+            if ms - before < FAST_REPEAT_MS {
+                fast_repeated_notes += 1;
+            }
+        }
+    }
+
+    let peak_notes_per_second = peak as f32;
+    ScoreAnalysis {
+        peak_notes_per_second,
+        max_chord_size,
+        left_hand_span: widest(Hand::Left),
+        right_hand_span: widest(Hand::Right),
+        fast_repeated_notes,
+        suggested_tempo_multiplier: suggested_tempo(peak_notes_per_second),
+    }
+}
+
+/// Slows a passage down until its busiest second is comfortable.
+fn suggested_tempo(peak_notes_per_second: f32) -> f32 {
+    if peak_notes_per_second <= COMFORTABLE_NOTES_PER_SECOND {
+        return 1.0;
+    }
+    let steps = (COMFORTABLE_NOTES_PER_SECOND / peak_notes_per_second * 20.0).floor();
+    (steps / 20.0).max(MIN_SUGGESTED_TEMPO)
+}
+
+/// Milliseconds since the start at any tick, from the tempo map.
+struct TempoClock {
+    ppq: f64,
+    /// Start tick, start ms and microseconds per quarter of each tempo.
+    segments: Vec<(Tick, f64, f64)>,
+}
+
+impl TempoClock {
+    fn new(score: &Score) -> Self {
+        let mut points: Vec<&TempoPoint> = score.tempo_map.iter().collect();
+        points.sort_by_key(|point| point.tick);
+        let ppq = f64::from(score.ppq.max(1));
+        let mut segments: Vec<(Tick, f64, f64)> = vec![(0, 0.0, 500_000.0)];
+        for point in points {
+            let (tick, ms, us_per_quarter) = *segments.last().expect("segment");
+            let at = point.tick.max(tick);
+            let ms = ms + (at - tick) as f64 / ppq * us_per_quarter / 1000.0;
+            if at == tick {
+                segments.pop();
+            }
+            segments.push((at, ms, f64::from(point.us_per_quarter.max(1))));
+        }
+        Self { ppq, segments }
+    }
+
+    fn ms_at(&self, tick: Tick) -> f64 {
+        let idx = self
+            .segments
+            .partition_point(|&(start, _, _)| start <= tick)
+            .saturating_sub(1);
+        let (start, ms, us_per_quarter) = self.segments[idx];
+        ms + (tick - start) as f64 / self.ppq * us_per_quarter / 1000.0
+    }
+}
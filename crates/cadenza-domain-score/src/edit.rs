@@ -0,0 +1,121 @@
+use crate::model::{Hand, PlaybackMidiEvent, Score, Track};
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::types::Tick;
+use std::collections::HashMap;
+use std::ops::{Range, RangeInclusive};
+
+/// Notes starting in `ticks` with a pitch in `pitches`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NoteSelection {
+    pub ticks: Range<Tick>,
+    pub pitches: RangeInclusive<u8>,
+}
+
+impl NoteSelection {
+    /// Every note starting in `ticks`.
+    pub fn ticks(ticks: Range<Tick>) -> Self {
+        Self {
+            ticks,
+            pitches: 0..=127,
+        }
+    }
+
+    fn contains(&self, tick: Tick, note: u8) -> bool {
+        self.ticks.contains(&tick) && self.pitches.contains(&note)
+    }
+}
+
+/// Assigns the selected notes to `hand`. Returns how many notes changed.
+pub fn set_notes_hand(score: &mut Score, selection: &NoteSelection, hand: Hand) -> usize {
+    reassign_hands(score, selection, |_| Some(hand))
+}
+
+/// Trades left and right for every note starting in `ticks`, for staves read the wrong
+/// way round. Notes without a hand keep none. Returns how many notes changed.
+pub fn swap_hands(score: &mut Score, ticks: Range<Tick>) -> usize {
+    reassign_hands(score, &NoteSelection::ticks(ticks), |hand| match hand {
+        Some(Hand::Left) => Some(Hand::Right),
+        Some(Hand::Right) => Some(Hand::Left),
+        None => None,
+    })
+}
+
+fn reassign_hands(
+    score: &mut Score,
+    selection: &NoteSelection,
+    assign: impl Fn(Option<Hand>) -> Option<Hand>,
+) -> usize {
+    let mut changed = 0;
+    for track in &mut score.tracks {
+        changed += reassign_playback(&mut track.playback_events, selection, &assign);
+        reassign_targets(track, selection);
+    }
+    changed
+}
+
+/// A note off follows its note on, paired the way the piano roll pairs them.
+fn reassign_playback(
+    events: &mut [PlaybackMidiEvent],
+    selection: &NoteSelection,
+    assign: &impl Fn(Option<Hand>) -> Option<Hand>,
+) -> usize {
+    let mut order: Vec<usize> = (0..events.len()).collect();
+    order.sort_by_key(|&idx| events[idx].tick);
+
+    let mut sounding: Vec<Vec<Option<Option<Hand>>>> = vec![Vec::new(); 128];
+    let mut changed = 0;
+    for idx in order {
+        let event = &mut events[idx];
+        match event.event {
+            MidiLikeEvent::NoteOn { note, .. } if (note as usize) < sounding.len() => {
+                let new_hand = selection
+                    .contains(event.tick, note)
+                    .then(|| assign(event.hand));
+                if let Some(hand) = new_hand {
+                    if hand != event.hand {
+                        changed += 1;
+                    }
+                    event.hand = hand;
+                }
+                sounding[note as usize].push(new_hand);
+            }
+            MidiLikeEvent::NoteOff { note } if (note as usize) < sounding.len() => {
+                if let Some(Some(hand)) = sounding[note as usize].pop() {
+                    event.hand = hand;
+                }
+            }
+            _ => {}
+        }
+    }
+    changed
+}
+
+/// A target keeps one hand when all its notes share it and none when they are mixed,
+/// as when tracks are merged.
+fn reassign_targets(track: &mut Track, selection: &NoteSelection) {
+    let mut note_hands: HashMap<(Tick, u8), Option<Hand>> = HashMap::new();
+    for event in &track.playback_events {
+        if let MidiLikeEvent::NoteOn { note, .. } = event.event {
+            note_hands.entry((event.tick, note)).or_insert(event.hand);
+        }
+    }
+    for target in &mut track.targets {
+        if !target
+            .notes
+            .iter()
+            .any(|&note| selection.contains(target.tick, note))
+        {
+            continue;
+        }
+        let mut hands = target
+            .notes
+            .iter()
+            .map(|&note| note_hands.get(&(target.tick, note)).copied().flatten());
+        let first = hands.next().flatten();
+        target.hand = if hands.all(|hand| hand == first) {
+            first
+        } else {
+            None
+        };
+    }
+}
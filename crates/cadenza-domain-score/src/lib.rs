@@ -1,9 +1,21 @@
+pub mod analysis;
+pub mod edit;
 pub mod midi_export;
 pub mod midi_import;
 pub mod model;
 pub mod musicxml_import;
+pub mod omr_cleanup;
+pub mod pitch;
+pub mod scorefile;
+pub mod warnings;
 
+pub use analysis::*;
+pub use edit::*;
 pub use midi_export::*;
 pub use midi_import::*;
 pub use model::*;
 pub use musicxml_import::*;
+pub use omr_cleanup::*;
+pub use pitch::*;
+pub use scorefile::*;
+pub use warnings::*;
@@ -1,8 +1,13 @@
-use crate::model::{PlaybackMidiEvent, Score};
+use crate::model::{
+    Hand, KeyMode, KeySignaturePoint, PlaybackMidiEvent, Score, TempoPoint, TimeSignaturePoint,
+    Track,
+};
 use cadenza_ports::midi::MidiLikeEvent;
 use cadenza_ports::types::Tick;
 use midly::num::{u28, u4, u7};
 use midly::{Header, MetaMessage, MidiMessage, Smf, Timing, TrackEvent, TrackEventKind};
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
 use std::path::Path;
 
 #[derive(thiserror::Error, Debug)]
@@ -13,27 +18,515 @@ pub enum MidiExportError {
     InvalidScore(String),
 }
 
+/// Manufacturer ID reserved for non-commercial use; prefixes the sequencer-specific blob.
+pub(crate) const NON_COMMERCIAL_MANUFACTURER_ID: u8 = 0x7D;
+
+#[derive(Clone, Debug)]
+pub struct ExportOptions {
+    /// Write right and left hand to separate tracks instead of one track per score track.
+    pub split_by_hand: bool,
+    /// Track that receives notes and pedal without a hand assignment when splitting.
+    pub unknown_hand: Hand,
+    /// Embed where the score came from as a sequencer-specific meta event.
+    pub source_info: Option<ExportSourceInfo>,
+    /// Write expanded ornaments (trills, turns, tremolos) back as their principal note.
+    pub collapse_ornaments: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            split_by_hand: false,
+            unknown_hand: Hand::Right,
+            source_info: None,
+            collapse_ornaments: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ExportSourceInfo {
+    /// File name of the original PDF or MusicXML the score was imported from.
+    pub source_file: Option<String>,
+    pub import_warnings: usize,
+}
+
 pub fn export_midi_path(score: &Score, path: &Path) -> Result<(), MidiExportError> {
-    let track = score
+    export_midi_path_with_options(score, path, ExportOptions::default())
+}
+
+pub fn export_midi_path_with_options(
+    score: &Score,
+    path: &Path,
+    options: ExportOptions,
+) -> Result<(), MidiExportError> {
+    if score.tracks.is_empty() {
+        return Err(MidiExportError::InvalidScore("no tracks".to_string()));
+    }
+
+    let mut sources: Vec<TrackSource> = if options.split_by_hand {
+        split_tracks_by_hand(score, options.unknown_hand)
+    } else {
+        score
+            .tracks
+            .iter()
+            .map(|track| {
+                (
+                    track.name.as_str(),
+                    Cow::Borrowed(&track.playback_events[..]),
+                )
+            })
+            .collect()
+    };
+
+    if options.collapse_ornaments {
+        for (_, events) in &mut sources {
+            *events = Cow::Owned(collapse_ornaments(events));
+        }
+    }
+
+    let generator = format!("Generated by Cadenza {}", env!("CARGO_PKG_VERSION"));
+    let source_blob = options
+        .source_info
+        .as_ref()
+        .map(|info| source_info_blob(score, info));
+
+    let title = score.meta.title.as_deref().unwrap_or_default();
+    let conductor = build_conductor_events(score, &generator, source_blob.as_deref());
+    let (format, tracks) = if let [(name, playback_events)] = &sources[..] {
+        // A lone track is the whole sequence: it carries the metadata, tempo and meter itself,
+        // and its name doubles as the sequence name, so the title wins when there is one.
+        let mut events: Vec<MidiEvent> = build_events(playback_events);
+        events.extend(conductor);
+        let name = if title.is_empty() { name } else { title };
+        (midly::Format::SingleTrack, vec![encode_track(name, events)])
+    } else {
+        // Track 0 is a conductor track carrying the sequence name, metadata, tempo and meter,
+        // so Format 1 readers pick up the title and every note track stays free of meta clutter.
+        let mut tracks = Vec::with_capacity(sources.len() + 1);
+        tracks.push(encode_track(title, conductor));
+        for (name, playback_events) in &sources {
+            tracks.push(encode_track(name, build_events(playback_events)));
+        }
+        (midly::Format::Parallel, tracks)
+    };
+
+    let smf = Smf {
+        header: Header {
+            format,
+            timing: Timing::Metrical(score.ppq.into()),
+        },
+        tracks,
+    };
+
+    let mut data = Vec::new();
+    smf.write(&mut data)
+        .map_err(|e| MidiExportError::Io(e.to_string()))?;
+    std::fs::write(path, data).map_err(|e| MidiExportError::Io(e.to_string()))
+}
+
+/// Export `[start_tick, end_tick)` as a standalone file starting at tick zero, with the tempo
+/// scaled so the file plays back at `tempo_multiplier` times the written tempo.
+pub fn export_midi_range(
+    score: &Score,
+    path: &Path,
+    start_tick: Tick,
+    end_tick: Tick,
+    tempo_multiplier: f32,
+) -> Result<(), MidiExportError> {
+    if end_tick <= start_tick {
+        return Err(MidiExportError::InvalidScore(format!(
+            "empty range {start_tick}..{end_tick}"
+        )));
+    }
+    if !(tempo_multiplier.is_finite() && tempo_multiplier > 0.0) {
+        return Err(MidiExportError::InvalidScore(format!(
+            "invalid tempo multiplier {tempo_multiplier}"
+        )));
+    }
+
+    let clipped = clip_score(score, start_tick, end_tick, tempo_multiplier);
+    export_midi_path(&clipped, path)
+}
+
+fn clip_score(score: &Score, start_tick: Tick, end_tick: Tick, tempo_multiplier: f32) -> Score {
+    let scale_tempo = |us_per_quarter: u32| {
+        let scaled = (us_per_quarter as f64 / tempo_multiplier as f64).round();
+        scaled.clamp(1.0, 0xFF_FFFF as f64) as u32
+    };
+
+    let mut tempo_map: Vec<TempoPoint> = Vec::new();
+    let initial_tempo = score
+        .tempo_map
+        .iter()
+        .take_while(|point| point.tick <= start_tick)
+        .last()
+        .map(|point| point.us_per_quarter)
+        .unwrap_or(500_000);
+    tempo_map.push(TempoPoint {
+        tick: 0,
+        us_per_quarter: scale_tempo(initial_tempo),
+    });
+    for point in &score.tempo_map {
+        if point.tick > start_tick && point.tick < end_tick {
+            tempo_map.push(TempoPoint {
+                tick: point.tick - start_tick,
+                us_per_quarter: scale_tempo(point.us_per_quarter),
+            });
+        }
+    }
+
+    let mut time_signatures: Vec<TimeSignaturePoint> = Vec::new();
+    if let Some(sig) = score
+        .time_signatures
+        .iter()
+        .take_while(|sig| sig.tick <= start_tick)
+        .last()
+    {
+        time_signatures.push(TimeSignaturePoint { tick: 0, ..*sig });
+    }
+    for sig in &score.time_signatures {
+        if sig.tick > start_tick && sig.tick < end_tick {
+            time_signatures.push(TimeSignaturePoint {
+                tick: sig.tick - start_tick,
+                ..*sig
+            });
+        }
+    }
+
+    let mut key_signatures: Vec<KeySignaturePoint> = Vec::new();
+    if let Some(key) = score
+        .key_signatures
+        .iter()
+        .take_while(|key| key.tick <= start_tick)
+        .last()
+    {
+        key_signatures.push(KeySignaturePoint { tick: 0, ..*key });
+    }
+    for key in &score.key_signatures {
+        if key.tick > start_tick && key.tick < end_tick {
+            key_signatures.push(KeySignaturePoint {
+                tick: key.tick - start_tick,
+                ..*key
+            });
+        }
+    }
+
+    let tracks = score
         .tracks
-        .first()
-        .ok_or_else(|| MidiExportError::InvalidScore("no tracks".to_string()))?;
+        .iter()
+        .map(|track| Track {
+            id: track.id,
+            name: track.name.clone(),
+            hand: track.hand,
+            targets: track
+                .targets
+                .iter()
+                .filter(|target| target.tick >= start_tick && target.tick < end_tick)
+                .map(|target| {
+                    let mut target = target.clone();
+                    target.tick -= start_tick;
+                    target
+                })
+                .collect(),
+            playback_events: clip_playback_events(&track.playback_events, start_tick, end_tick),
+        })
+        .collect();
+
+    Score {
+        meta: score.meta.clone(),
+        ppq: score.ppq,
+        tempo_map,
+        time_signatures,
+        pickup_ticks: (score.pickup_ticks - start_tick).max(0),
+        key_signatures,
+        tracks,
+    }
+}
+
+fn clip_playback_events(
+    events: &[PlaybackMidiEvent],
+    start_tick: Tick,
+    end_tick: Tick,
+) -> Vec<PlaybackMidiEvent> {
+    let mut sorted: Vec<&PlaybackMidiEvent> = events.iter().collect();
+    sorted.sort_by(|a, b| {
+        a.tick
+            .cmp(&b.tick)
+            .then_with(|| midi_event_rank(&a.event).cmp(&midi_event_rank(&b.event)))
+    });
+
+    // Releases landing exactly on the start boundary belong to the material before it.
+    let before_range = |event: &PlaybackMidiEvent| {
+        event.tick < start_tick
+            || (event.tick == start_tick
+                && match event.event {
+                    MidiLikeEvent::NoteOff { .. } => true,
+                    MidiLikeEvent::Cc64 { value } => value < 64,
+                    MidiLikeEvent::NoteOn { .. } => false,
+                    MidiLikeEvent::ControlChange { .. } => true,
+                })
+    };
+
+    let length = end_tick - start_tick;
+    let mut out = Vec::new();
+    let mut active: HashMap<u8, VecDeque<(u8, Option<Hand>)>> = HashMap::new();
+    let mut pedal: Option<Option<Hand>> = None;
+
+    for event in sorted.iter().filter(|event| before_range(event)) {
+        apply_held_state(&mut active, &mut pedal, event);
+    }
+    restrike_at_start(&mut out, &active, pedal);
+
+    for event in sorted
+        .iter()
+        .filter(|event| !before_range(event) && event.tick < end_tick)
+    {
+        if !apply_held_state(&mut active, &mut pedal, event) {
+            continue;
+        }
+        out.push(PlaybackMidiEvent {
+            tick: event.tick - start_tick,
+            event: event.event,
+            hand: event.hand,
+            ornament_of: None,
+            bus: None,
+            note_id: None,
+            spelling: None,
+            transpose: 0,
+            articulations: Vec::new(),
+        });
+    }
+
+    // Close anything still sounding at the end boundary.
+    let mut notes: Vec<_> = active.into_iter().collect();
+    notes.sort_by_key(|(note, _)| *note);
+    for (note, stack) in notes {
+        for (_, hand) in stack {
+            out.push(PlaybackMidiEvent {
+                tick: length,
+                event: MidiLikeEvent::NoteOff { note },
+                hand,
+                ornament_of: None,
+                bus: None,
+                note_id: None,
+                spelling: None,
+                transpose: 0,
+                articulations: Vec::new(),
+            });
+        }
+    }
+    if let Some(hand) = pedal {
+        out.push(PlaybackMidiEvent {
+            tick: length,
+            event: MidiLikeEvent::Cc64 { value: 0 },
+            hand,
+            ornament_of: None,
+            bus: None,
+            note_id: None,
+            spelling: None,
+            transpose: 0,
+            articulations: Vec::new(),
+        });
+    }
+
+    out
+}
+
+/// Track which notes and pedal are held. A NoteOff ends the earliest held note of its pitch;
+/// returns false for NoteOffs with no matching NoteOn.
+fn apply_held_state(
+    active: &mut HashMap<u8, VecDeque<(u8, Option<Hand>)>>,
+    pedal: &mut Option<Option<Hand>>,
+    event: &PlaybackMidiEvent,
+) -> bool {
+    match event.event {
+        MidiLikeEvent::NoteOn { note, velocity } => {
+            active
+                .entry(note)
+                .or_default()
+                .push_back((velocity, event.hand));
+            true
+        }
+        MidiLikeEvent::NoteOff { note } => active
+            .get_mut(&note)
+            .is_some_and(|stack| stack.pop_front().is_some()),
+        MidiLikeEvent::Cc64 { value } => {
+            *pedal = (value >= 64).then_some(event.hand);
+            true
+        }
+        MidiLikeEvent::ControlChange { .. } => true,
+    }
+}
+
+/// Re-emit notes and pedal that are already held when the range begins.
+fn restrike_at_start(
+    out: &mut Vec<PlaybackMidiEvent>,
+    active: &HashMap<u8, VecDeque<(u8, Option<Hand>)>>,
+    pedal: Option<Option<Hand>>,
+) {
+    if let Some(hand) = pedal {
+        out.push(PlaybackMidiEvent {
+            tick: 0,
+            event: MidiLikeEvent::Cc64 { value: 127 },
+            hand,
+            ornament_of: None,
+            bus: None,
+            note_id: None,
+            spelling: None,
+            transpose: 0,
+            articulations: Vec::new(),
+        });
+    }
+    let mut notes: Vec<_> = active.iter().collect();
+    notes.sort_by_key(|(note, _)| **note);
+    for (note, stack) in notes {
+        for (velocity, hand) in stack {
+            out.push(PlaybackMidiEvent {
+                tick: 0,
+                event: MidiLikeEvent::NoteOn {
+                    note: *note,
+                    velocity: *velocity,
+                },
+                hand: *hand,
+                ornament_of: None,
+                bus: None,
+                note_id: None,
+                spelling: None,
+                transpose: 0,
+                articulations: Vec::new(),
+            });
+        }
+    }
+}
 
-    let mut events = build_events(score, &track.playback_events);
+fn midi_event_rank(event: &MidiLikeEvent) -> u8 {
+    match event {
+        MidiLikeEvent::Cc64 { value } => {
+            if *value >= 64 {
+                0
+            } else {
+                3
+            }
+        }
+        MidiLikeEvent::ControlChange { .. } => 0,
+        MidiLikeEvent::NoteOff { .. } => 1,
+        MidiLikeEvent::NoteOn { .. } => 2,
+    }
+}
+
+type TrackSource<'a> = (&'a str, Cow<'a, [PlaybackMidiEvent]>);
+
+/// Replace each run of ornament notes with a single principal note spanning the run.
+/// Back-to-back ornaments on the same principal and hand merge into one note.
+fn collapse_ornaments(events: &[PlaybackMidiEvent]) -> Vec<PlaybackMidiEvent> {
+    let mut out = Vec::with_capacity(events.len());
+    // (principal, hand) -> (start tick, velocity, end tick)
+    let mut open: HashMap<(u8, Option<Hand>), (Tick, u8, Tick)> = HashMap::new();
+    let mut spans = Vec::new();
+
+    let mut sorted: Vec<&PlaybackMidiEvent> = events.iter().collect();
+    sorted.sort_by_key(|e| (e.tick, midi_event_rank(&e.event)));
+    for event in sorted {
+        let Some(principal) = event.ornament_of else {
+            out.push(event.clone());
+            continue;
+        };
+        let key = (principal, event.hand);
+        match event.event {
+            MidiLikeEvent::NoteOn { velocity, .. } => match open.get(&key) {
+                Some(&(_, _, end)) if end >= event.tick => {}
+                _ => {
+                    if let Some(span) = open.insert(key, (event.tick, velocity, event.tick)) {
+                        spans.push((key, span));
+                    }
+                }
+            },
+            MidiLikeEvent::NoteOff { .. } => {
+                if let Some(span) = open.get_mut(&key) {
+                    span.2 = span.2.max(event.tick);
+                }
+            }
+            MidiLikeEvent::Cc64 { .. } | MidiLikeEvent::ControlChange { .. } => {
+                out.push(event.clone())
+            }
+        }
+    }
+    spans.extend(open);
+
+    for ((note, hand), (start, velocity, end)) in spans {
+        out.push(PlaybackMidiEvent {
+            tick: start,
+            event: MidiLikeEvent::NoteOn { note, velocity },
+            hand,
+            ornament_of: None,
+            bus: None,
+            note_id: None,
+            spelling: None,
+            transpose: 0,
+            articulations: Vec::new(),
+        });
+        out.push(PlaybackMidiEvent {
+            tick: end.max(start + 1),
+            event: MidiLikeEvent::NoteOff { note },
+            hand,
+            ornament_of: None,
+            bus: None,
+            note_id: None,
+            spelling: None,
+            transpose: 0,
+            articulations: Vec::new(),
+        });
+    }
+    out
+}
+
+fn split_tracks_by_hand(score: &Score, unknown_hand: Hand) -> Vec<TrackSource<'static>> {
+    let mut right = Vec::new();
+    let mut left = Vec::new();
+    for event in score.tracks.iter().flat_map(|track| &track.playback_events) {
+        match (event.event, event.hand) {
+            (_, Some(Hand::Left)) => left.push(event.clone()),
+            (_, Some(Hand::Right)) => right.push(event.clone()),
+            (_, None) => match unknown_hand {
+                Hand::Left => left.push(event.clone()),
+                Hand::Right => right.push(event.clone()),
+            },
+        }
+    }
+
+    vec![
+        ("Right Hand", Cow::Owned(right)),
+        ("Left Hand", Cow::Owned(left)),
+    ]
+}
+
+struct MidiEvent<'a> {
+    tick: Tick,
+    kind: TrackEventKind<'a>,
+}
+
+fn encode_track<'a>(name: &'a str, mut events: Vec<MidiEvent<'a>>) -> Vec<TrackEvent<'a>> {
     events.sort_by(|a, b| {
         a.tick
             .cmp(&b.tick)
             .then_with(|| track_event_rank(&a.kind).cmp(&track_event_rank(&b.kind)))
     });
 
-    let mut track_events = Vec::new();
+    let mut track_events = Vec::with_capacity(events.len() + 2);
+    if !name.is_empty() {
+        track_events.push(TrackEvent {
+            delta: u28::new(0),
+            kind: TrackEventKind::Meta(MetaMessage::TrackName(name.as_bytes())),
+        });
+    }
+
     let mut last_tick: Tick = 0;
     for event in events {
         let delta = (event.tick - last_tick).max(0) as u32;
-        last_tick = event.tick;
-        let delta = u28::new(delta);
+        last_tick = last_tick.max(event.tick);
         track_events.push(TrackEvent {
-            delta,
+            delta: u28::new(delta),
             kind: event.kind,
         });
     }
@@ -42,30 +535,16 @@ pub fn export_midi_path(score: &Score, path: &Path) -> Result<(), MidiExportErro
         delta: u28::new(0),
         kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
     });
-
-    let smf = Smf {
-        header: Header {
-            format: midly::Format::SingleTrack,
-            timing: Timing::Metrical(score.ppq.into()),
-        },
-        tracks: vec![track_events],
-    };
-
-    let mut data = Vec::new();
-    smf.write(&mut data)
-        .map_err(|e| MidiExportError::Io(e.to_string()))?;
-    std::fs::write(path, data).map_err(|e| MidiExportError::Io(e.to_string()))
-}
-
-struct MidiEvent {
-    tick: Tick,
-    kind: TrackEventKind<'static>,
+    track_events
 }
 
-fn track_event_rank(kind: &TrackEventKind<'static>) -> (u8, u8, u8) {
+fn track_event_rank(kind: &TrackEventKind) -> (u8, u8, u8) {
     match kind {
-        TrackEventKind::Meta(MetaMessage::Tempo(_)) => (0, 0, 0),
-        TrackEventKind::Meta(_) => (0, 1, 0),
+        TrackEventKind::Meta(MetaMessage::Copyright(_)) => (0, 0, 0),
+        TrackEventKind::Meta(MetaMessage::Tempo(_)) => (0, 1, 0),
+        TrackEventKind::Meta(MetaMessage::TimeSignature(..)) => (0, 2, 0),
+        TrackEventKind::Meta(MetaMessage::KeySignature(..)) => (0, 2, 1),
+        TrackEventKind::Meta(_) => (0, 3, 0),
         TrackEventKind::Midi { message, .. } => match message {
             MidiMessage::Controller { controller, value } if controller.as_int() == 64 => {
                 let rank = if value.as_int() >= 64 { 0 } else { 3 };
@@ -85,9 +564,44 @@ fn track_event_rank(kind: &TrackEventKind<'static>) -> (u8, u8, u8) {
     }
 }
 
-fn build_events(score: &Score, playback_events: &[PlaybackMidiEvent]) -> Vec<MidiEvent> {
+fn source_info_blob(score: &Score, info: &ExportSourceInfo) -> Vec<u8> {
+    let json = serde_json::json!({
+        "generator": "cadenza",
+        "version": env!("CARGO_PKG_VERSION"),
+        "source_file": info.source_file,
+        "source": score.meta.source,
+        "origin_path": score.meta.origin_path,
+        "ppq": score.ppq,
+        "import_warnings": info.import_warnings,
+    });
+    let mut blob = vec![NON_COMMERCIAL_MANUFACTURER_ID];
+    blob.extend(json.to_string().into_bytes());
+    blob
+}
+
+fn build_conductor_events<'a>(
+    score: &'a Score,
+    generator: &'a str,
+    source_blob: Option<&'a [u8]>,
+) -> Vec<MidiEvent<'a>> {
     let mut events = Vec::new();
-    let channel = u4::new(0);
+
+    if let Some(copyright) = score.meta.copyright.as_deref() {
+        events.push(MidiEvent {
+            tick: 0,
+            kind: TrackEventKind::Meta(MetaMessage::Copyright(copyright.as_bytes())),
+        });
+    }
+    events.push(MidiEvent {
+        tick: 0,
+        kind: TrackEventKind::Meta(MetaMessage::Text(generator.as_bytes())),
+    });
+    if let Some(blob) = source_blob {
+        events.push(MidiEvent {
+            tick: 0,
+            kind: TrackEventKind::Meta(MetaMessage::SequencerSpecific(blob)),
+        });
+    }
 
     for tempo in &score.tempo_map {
         let tick = tempo.tick;
@@ -98,6 +612,39 @@ fn build_events(score: &Score, playback_events: &[PlaybackMidiEvent]) -> Vec<Mid
         });
     }
 
+    for sig in &score.time_signatures {
+        if sig.numerator == 0 || !sig.denominator.is_power_of_two() {
+            continue;
+        }
+        let denom_pow2 = sig.denominator.trailing_zeros() as u8;
+        events.push(MidiEvent {
+            tick: sig.tick,
+            kind: TrackEventKind::Meta(MetaMessage::TimeSignature(
+                sig.numerator,
+                denom_pow2,
+                24,
+                8,
+            )),
+        });
+    }
+
+    for key in &score.key_signatures {
+        events.push(MidiEvent {
+            tick: key.tick,
+            kind: TrackEventKind::Meta(MetaMessage::KeySignature(
+                key.fifths.clamp(-7, 7),
+                key.mode == KeyMode::Minor,
+            )),
+        });
+    }
+
+    events
+}
+
+fn build_events(playback_events: &[PlaybackMidiEvent]) -> Vec<MidiEvent<'static>> {
+    let mut events = Vec::new();
+    let channel = u4::new(0);
+
     for event in playback_events {
         let kind = match event.event {
             MidiLikeEvent::NoteOn { note, velocity } => TrackEventKind::Midi {
@@ -121,6 +668,13 @@ fn build_events(score: &Score, playback_events: &[PlaybackMidiEvent]) -> Vec<Mid
                     value: u7::new(value),
                 },
             },
+            MidiLikeEvent::ControlChange { controller, value } => TrackEventKind::Midi {
+                channel,
+                message: MidiMessage::Controller {
+                    controller: u7::new(controller),
+                    value: u7::new(value),
+                },
+            },
         };
         events.push(MidiEvent {
             tick: event.tick,
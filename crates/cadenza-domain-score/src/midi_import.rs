@@ -1,11 +1,15 @@
+use crate::midi_export::NON_COMMERCIAL_MANUFACTURER_ID;
 use crate::model::{
-    PlaybackMidiEvent, Score, ScoreMeta, ScoreSource, TargetEvent, TempoPoint, Track,
+    KeyMode, KeySignaturePoint, PlaybackMidiEvent, Score, ScoreMeta, ScoreSource, TargetEvent,
+    TempoPoint, TimeSignaturePoint, Track,
 };
+use crate::warnings::ImportWarning;
 use cadenza_ports::midi::MidiLikeEvent;
 use cadenza_ports::types::Tick;
 use midly::{Fps, MetaMessage, MidiMessage, Smf, Timing, TrackEventKind};
+use serde::Deserialize;
 use std::collections::BTreeMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[derive(thiserror::Error, Debug)]
 pub enum MidiImportError {
@@ -15,12 +19,51 @@ pub enum MidiImportError {
     Parse(String),
 }
 
+/// GM channel 10 (zero-based 9) is reserved for percussion.
+const PERCUSSION_CHANNEL: u8 = 9;
+/// Bank select MSB values that switch a channel to a drum kit (GM2 and XG).
+const DRUM_BANK_MSB: [u8; 2] = [120, 127];
+
+#[derive(Clone, Copy, Debug)]
+pub struct MidiImportOptions {
+    /// Keep percussion notes in playback even though they never become targets.
+    pub keep_percussion_playback: bool,
+}
+
+impl Default for MidiImportOptions {
+    fn default() -> Self {
+        Self {
+            keep_percussion_playback: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MidiImport {
+    pub score: Score,
+    pub warnings: Vec<ImportWarning>,
+}
+
 pub fn import_midi_path(path: &Path) -> Result<Score, MidiImportError> {
+    import_midi_path_with_options(path, MidiImportOptions::default()).map(|import| import.score)
+}
+
+pub fn import_midi_path_with_options(
+    path: &Path,
+    options: MidiImportOptions,
+) -> Result<MidiImport, MidiImportError> {
     let data = std::fs::read(path).map_err(|e| MidiImportError::Io(e.to_string()))?;
-    import_midi_bytes(&data)
+    import_midi_bytes_with_options(&data, options)
 }
 
 pub fn import_midi_bytes(data: &[u8]) -> Result<Score, MidiImportError> {
+    import_midi_bytes_with_options(data, MidiImportOptions::default()).map(|import| import.score)
+}
+
+pub fn import_midi_bytes_with_options(
+    data: &[u8],
+    options: MidiImportOptions,
+) -> Result<MidiImport, MidiImportError> {
     let smf = Smf::parse(data).map_err(|e| MidiImportError::Parse(e.to_string()))?;
     let (ppq, tempo_override) = match smf.header.timing {
         Timing::Metrical(ticks) => (ticks.as_int(), None),
@@ -31,15 +74,55 @@ pub fn import_midi_bytes(data: &[u8]) -> Result<Score, MidiImportError> {
     };
 
     let mut tempo_points: BTreeMap<Tick, u32> = BTreeMap::new();
+    let mut time_signature_points: BTreeMap<Tick, (u8, u8)> = BTreeMap::new();
+    let mut key_signature_points: BTreeMap<Tick, (i8, KeyMode)> = BTreeMap::new();
     let mut playback_events: Vec<PlaybackMidiEvent> = Vec::new();
     let mut note_on_events: Vec<(Tick, u8)> = Vec::new();
+    let mut warnings: Vec<ImportWarning> = Vec::new();
 
-    for track in &smf.tracks {
+    for (track_index, track) in smf.tracks.iter().enumerate() {
         let mut tick: Tick = 0;
+        let mut drum_bank = [false; 16];
+        let mut percussion_notes: BTreeMap<u8, u32> = BTreeMap::new();
         for event in track {
             tick += event.delta.as_int() as Tick;
             match &event.kind {
-                TrackEventKind::Midi { message, .. } => match message {
+                TrackEventKind::Midi { channel, message } => match message {
+                    MidiMessage::Controller { controller, value } if controller.as_int() == 0 => {
+                        drum_bank[channel.as_int() as usize] =
+                            DRUM_BANK_MSB.contains(&value.as_int());
+                    }
+                    MidiMessage::NoteOn { key, vel }
+                        if is_percussion_channel(channel.as_int(), &drum_bank) =>
+                    {
+                        let note = key.as_int();
+                        let velocity = vel.as_int();
+                        if velocity > 0 {
+                            *percussion_notes.entry(channel.as_int()).or_default() += 1;
+                        }
+                        if !options.keep_percussion_playback {
+                            continue;
+                        }
+                        let event = if velocity == 0 {
+                            MidiLikeEvent::NoteOff { note }
+                        } else {
+                            MidiLikeEvent::NoteOn { note, velocity }
+                        };
+                        playback_events.push(PlaybackMidiEvent {
+                            tick,
+                            event,
+                            hand: None,
+                            ornament_of: None,
+                            bus: None,
+                            note_id: None,
+                            spelling: None,
+                            transpose: 0,
+                            articulations: Vec::new(),
+                        });
+                    }
+                    MidiMessage::NoteOff { .. }
+                        if !options.keep_percussion_playback
+                            && is_percussion_channel(channel.as_int(), &drum_bank) => {}
                     MidiMessage::NoteOn { key, vel } => {
                         let note = key.as_int();
                         let velocity = vel.as_int();
@@ -48,12 +131,24 @@ pub fn import_midi_bytes(data: &[u8]) -> Result<Score, MidiImportError> {
                                 tick,
                                 event: MidiLikeEvent::NoteOff { note },
                                 hand: None,
+                                ornament_of: None,
+                                bus: None,
+                                note_id: None,
+                                spelling: None,
+                                transpose: 0,
+                                articulations: Vec::new(),
                             });
                         } else {
                             playback_events.push(PlaybackMidiEvent {
                                 tick,
                                 event: MidiLikeEvent::NoteOn { note, velocity },
                                 hand: None,
+                                ornament_of: None,
+                                bus: None,
+                                note_id: None,
+                                spelling: None,
+                                transpose: 0,
+                                articulations: Vec::new(),
                             });
                             note_on_events.push((tick, note));
                         }
@@ -63,30 +158,74 @@ pub fn import_midi_bytes(data: &[u8]) -> Result<Score, MidiImportError> {
                             tick,
                             event: MidiLikeEvent::NoteOff { note: key.as_int() },
                             hand: None,
+                            ornament_of: None,
+                            bus: None,
+                            note_id: None,
+                            spelling: None,
+                            transpose: 0,
+                            articulations: Vec::new(),
                         });
                     }
-                    MidiMessage::Controller { controller, value } => {
-                        if controller.as_int() == 64 {
-                            playback_events.push(PlaybackMidiEvent {
-                                tick,
-                                event: MidiLikeEvent::Cc64 {
-                                    value: value.as_int(),
-                                },
-                                hand: None,
-                            });
-                        }
+                    MidiMessage::Controller { controller, value } if controller.as_int() == 64 => {
+                        playback_events.push(PlaybackMidiEvent {
+                            tick,
+                            event: MidiLikeEvent::Cc64 {
+                                value: value.as_int(),
+                            },
+                            hand: None,
+                            ornament_of: None,
+                            bus: None,
+                            note_id: None,
+                            spelling: None,
+                            transpose: 0,
+                            articulations: Vec::new(),
+                        });
                     }
                     _ => {}
                 },
                 TrackEventKind::Meta(MetaMessage::Tempo(us_per_quarter)) => {
                     tempo_points.insert(tick, us_per_quarter.as_int());
                 }
+                TrackEventKind::Meta(MetaMessage::TimeSignature(numerator, denom_pow2, ..))
+                    if *numerator > 0 && *denom_pow2 < 8 =>
+                {
+                    time_signature_points.insert(tick, (*numerator, 1u8 << *denom_pow2));
+                }
+                TrackEventKind::Meta(MetaMessage::KeySignature(fifths, minor)) => {
+                    let mode = if *minor {
+                        KeyMode::Minor
+                    } else {
+                        KeyMode::Major
+                    };
+                    key_signature_points.insert(tick, (*fifths, mode));
+                }
                 _ => {}
             }
         }
+
+        for (channel, note_count) in percussion_notes {
+            warnings.push(ImportWarning::PercussionExcluded {
+                track_index,
+                channel,
+                note_count,
+                playback_dropped: !options.keep_percussion_playback,
+            });
+        }
     }
 
     let tempo_map = build_tempo_map(tempo_points, tempo_override);
+    let time_signatures = time_signature_points
+        .into_iter()
+        .map(|(tick, (numerator, denominator))| TimeSignaturePoint {
+            tick,
+            numerator,
+            denominator,
+        })
+        .collect();
+    let key_signatures = key_signature_points
+        .into_iter()
+        .map(|(tick, (fifths, mode))| KeySignaturePoint { tick, fifths, mode })
+        .collect();
     let targets = build_targets(note_on_events);
     playback_events.sort_by(|a, b| {
         a.tick
@@ -104,17 +243,81 @@ pub fn import_midi_bytes(data: &[u8]) -> Result<Score, MidiImportError> {
         playback_events,
     };
 
-    let score = Score {
+    let (title, copyright) = sequence_meta(&smf);
+    let (source, origin_path) = cadenza_origin(&smf).unwrap_or((ScoreSource::Midi, None));
+    let mut score = Score {
         meta: ScoreMeta {
-            title: None,
-            source: ScoreSource::Midi,
+            title,
+            composer: None,
+            copyright,
+            source,
+            origin_path,
         },
         ppq,
         tempo_map,
+        time_signatures,
+        pickup_ticks: 0,
+        key_signatures,
         tracks: vec![track],
     };
+    score.assign_note_ids();
+
+    Ok(MidiImport { score, warnings })
+}
+
+/// Title and copyright from the first track. Its track name only names the whole sequence
+/// in single-track files or when the first track is a conductor track without channel events.
+fn sequence_meta(smf: &Smf) -> (Option<String>, Option<String>) {
+    let Some(first) = smf.tracks.first() else {
+        return (None, None);
+    };
+    let is_conductor = !first
+        .iter()
+        .any(|event| matches!(event.kind, TrackEventKind::Midi { .. }));
+    let names_sequence = smf.tracks.len() == 1 || is_conductor;
+
+    let text = |bytes: &[u8]| {
+        let text = String::from_utf8_lossy(bytes).trim().to_string();
+        (!text.is_empty()).then_some(text)
+    };
+    let mut title = None;
+    let mut copyright = None;
+    for event in first {
+        match event.kind {
+            TrackEventKind::Meta(MetaMessage::TrackName(name)) if names_sequence => {
+                title = title.or_else(|| text(name));
+            }
+            TrackEventKind::Meta(MetaMessage::Copyright(notice)) => {
+                copyright = copyright.or_else(|| text(notice));
+            }
+            _ => {}
+        }
+    }
+    (title, copyright)
+}
+
+/// Where a file exported by Cadenza came from, as recorded in its sequencer-specific blob.
+fn cadenza_origin(smf: &Smf) -> Option<(ScoreSource, Option<PathBuf>)> {
+    #[derive(Deserialize)]
+    struct SourceInfo {
+        generator: String,
+        source: Option<ScoreSource>,
+        #[serde(default)]
+        origin_path: Option<PathBuf>,
+    }
+
+    smf.tracks.first()?.iter().find_map(|event| {
+        let TrackEventKind::Meta(MetaMessage::SequencerSpecific(data)) = event.kind else {
+            return None;
+        };
+        let json = data.strip_prefix(&[NON_COMMERCIAL_MANUFACTURER_ID])?;
+        let info: SourceInfo = serde_json::from_slice(json).ok()?;
+        (info.generator == "cadenza").then_some((info.source?, info.origin_path))
+    })
+}
 
-    Ok(score)
+fn is_percussion_channel(channel: u8, drum_bank: &[bool; 16]) -> bool {
+    channel == PERCUSSION_CHANNEL || drum_bank[channel as usize]
 }
 
 fn midi_event_rank(event: &MidiLikeEvent) -> u8 {
@@ -126,6 +329,7 @@ fn midi_event_rank(event: &MidiLikeEvent) -> u8 {
                 3
             }
         }
+        MidiLikeEvent::ControlChange { .. } => 0,
         MidiLikeEvent::NoteOff { .. } => 1,
         MidiLikeEvent::NoteOn { .. } => 2,
     }
@@ -135,7 +339,7 @@ fn midi_event_note_key(event: &MidiLikeEvent) -> u8 {
     match event {
         MidiLikeEvent::NoteOn { note, .. } => *note,
         MidiLikeEvent::NoteOff { note } => *note,
-        MidiLikeEvent::Cc64 { .. } => 0,
+        MidiLikeEvent::Cc64 { .. } | MidiLikeEvent::ControlChange { .. } => 0,
     }
 }
 
@@ -204,6 +408,9 @@ fn build_targets(mut note_on_events: Vec<(Tick, u8)>) -> Vec<TargetEvent> {
                 notes: notes.clone(),
                 hand: None,
                 measure_index: None,
+                optional: false,
+                note_ids: Vec::new(),
+                note_tracks: Vec::new(),
             });
             next_id += 1;
             notes.clear();
@@ -221,6 +428,9 @@ fn build_targets(mut note_on_events: Vec<(Tick, u8)>) -> Vec<TargetEvent> {
             notes,
             hand: None,
             measure_index: None,
+            optional: false,
+            note_ids: Vec::new(),
+            note_tracks: Vec::new(),
         });
     }
 
@@ -250,6 +460,12 @@ fn sanitize_note_pairs(ppq: u16, events: Vec<PlaybackMidiEvent>) -> Vec<Playback
                                 tick: event.tick,
                                 event: MidiLikeEvent::NoteOff { note },
                                 hand: event.hand,
+                                ornament_of: None,
+                                bus: None,
+                                note_id: None,
+                                spelling: None,
+                                transpose: 0,
+                                articulations: Vec::new(),
                             });
                         }
                         active[idx] = 0;
@@ -266,7 +482,7 @@ fn sanitize_note_pairs(ppq: u16, events: Vec<PlaybackMidiEvent>) -> Vec<Playback
                 active[idx] = active[idx].saturating_sub(1);
                 out.push(event);
             }
-            MidiLikeEvent::Cc64 { .. } => out.push(event),
+            MidiLikeEvent::Cc64 { .. } | MidiLikeEvent::ControlChange { .. } => out.push(event),
         }
     }
 
@@ -277,6 +493,12 @@ fn sanitize_note_pairs(ppq: u16, events: Vec<PlaybackMidiEvent>) -> Vec<Playback
                 tick: end_tick,
                 event: MidiLikeEvent::NoteOff { note: note as u8 },
                 hand: None,
+                ornament_of: None,
+                bus: None,
+                note_id: None,
+                spelling: None,
+                transpose: 0,
+                articulations: Vec::new(),
             });
         }
     }
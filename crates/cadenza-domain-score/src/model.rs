@@ -1,6 +1,10 @@
+use crate::pitch::SpelledPitch;
 use cadenza_ports::midi::MidiLikeEvent;
-use cadenza_ports::types::Tick;
+use cadenza_ports::types::{Bus, Tick};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::PathBuf;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Hand {
@@ -8,10 +12,35 @@ pub enum Hand {
     Right,
 }
 
+/// Identity of one written note, shared by its note on, the target it belongs to and its
+/// piano-roll span.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct NoteId(pub u64);
+
+/// Articulation mark written on a note.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Articulation {
+    Staccato,
+    Staccatissimo,
+    Tenuto,
+    Accent,
+    StrongAccent,
+    BreathMark,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ScoreMeta {
     pub title: Option<String>,
+    #[serde(default)]
+    pub composer: Option<String>,
+    #[serde(default)]
+    pub copyright: Option<String>,
     pub source: ScoreSource,
+    /// The file the score was first read from when that was not the file it was loaded
+    /// from, such as the PDF behind an OMR score.
+    #[serde(default)]
+    pub origin_path: Option<PathBuf>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -28,11 +57,41 @@ pub struct TempoPoint {
     pub us_per_quarter: u32,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeSignaturePoint {
+    pub tick: Tick,
+    pub numerator: u8,
+    pub denominator: u8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyMode {
+    Major,
+    Minor,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeySignaturePoint {
+    pub tick: Tick,
+    /// Sharps when positive, flats when negative.
+    pub fifths: i8,
+    pub mode: KeyMode,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Score {
     pub meta: ScoreMeta,
     pub ppq: u16,
     pub tempo_map: Vec<TempoPoint>,
+    /// Empty means the score is in 4/4 throughout.
+    #[serde(default)]
+    pub time_signatures: Vec<TimeSignaturePoint>,
+    /// Length of an incomplete first measure (anacrusis); 0 when the score starts on a
+    /// downbeat.
+    #[serde(default)]
+    pub pickup_ticks: Tick,
+    #[serde(default)]
+    pub key_signatures: Vec<KeySignaturePoint>,
     pub tracks: Vec<Track>,
 }
 
@@ -52,6 +111,16 @@ pub struct TargetEvent {
     pub notes: Vec<u8>,
     pub hand: Option<Hand>,
     pub measure_index: Option<u32>,
+    /// Ornaments such as grace notes that the player may skip. The judge never focuses
+    /// them, and playing their notes near them is not a wrong note.
+    #[serde(default)]
+    pub optional: bool,
+    /// The note behind each of `notes`, in the same order.
+    #[serde(default)]
+    pub note_ids: Vec<NoteId>,
+    /// The track each of `notes` came from, in the same order; set on merged targets.
+    #[serde(default)]
+    pub note_tracks: Vec<u32>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -59,6 +128,27 @@ pub struct PlaybackMidiEvent {
     pub tick: Tick,
     pub event: MidiLikeEvent,
     pub hand: Option<Hand>,
+    /// Set on notes generated by ornament expansion to the written principal pitch, so the
+    /// ornament can be collapsed back into one note.
+    #[serde(default)]
+    pub ornament_of: Option<u8>,
+    /// Explicit output bus, e.g. for metronome clicks. `None` leaves routing to the
+    /// scheduler's mode and hand settings.
+    #[serde(default)]
+    pub bus: Option<Bus>,
+    /// Set on note ons by [`Score::assign_note_ids`], and on the note off ending each.
+    #[serde(default)]
+    pub note_id: Option<NoteId>,
+    /// How the note on was written, when the source spelled it.
+    #[serde(default)]
+    pub spelling: Option<SpelledPitch>,
+    /// Semitones from the written pitch up to the one that sounds, set on the note ons of a
+    /// part written for a transposing instrument. `spelling` stays the written pitch.
+    #[serde(default)]
+    pub transpose: i8,
+    /// Marks written on the note, set on its note on.
+    #[serde(default)]
+    pub articulations: Vec<Articulation>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -66,6 +156,24 @@ pub struct ScoreFile {
     pub schema_version: String,
     pub score: Score,
     pub edit_log: Vec<String>,
+    #[serde(default)]
+    pub sound_overrides: ScoreSoundOverrides,
+}
+
+/// Sounds a score asks for in place of the global settings while it is loaded.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoreSoundOverrides {
+    /// GM program per bus; other buses keep the global choice.
+    #[serde(default)]
+    pub bus_programs: BTreeMap<Bus, u8>,
+    #[serde(default)]
+    pub soundfont_path: Option<String>,
+}
+
+impl ScoreSoundOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.bus_programs.is_empty() && self.soundfont_path.is_none()
+    }
 }
 
 impl Score {
@@ -77,7 +185,242 @@ impl Score {
                 tick: 0,
                 us_per_quarter: 500_000,
             }],
+            time_signatures: Vec::new(),
+            pickup_ticks: 0,
+            key_signatures: Vec::new(),
             tracks: Vec::new(),
         }
     }
+
+    /// Tick of the last playback event or target in any track.
+    pub fn end_tick(&self) -> Tick {
+        self.tracks
+            .iter()
+            .flat_map(|track| {
+                let events = track.playback_events.iter().map(|event| event.tick);
+                let targets = track.targets.iter().map(|target| target.tick);
+                events.chain(targets)
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Numbers every note on from 1 in track order and gives each target the ids of the
+    /// notes sounding its pitches at its tick, an ornament standing in for its principal.
+    /// Target notes nothing sounds get ids of their own. The same events always get the
+    /// same ids.
+    ///
+    /// Each note off takes the id of the note it ends: the one it already shared an id
+    /// with, as importers that know the voices pair them, or else the earliest still
+    /// sounding note of its pitch.
+    pub fn assign_note_ids(&mut self) {
+        let mut next = 1;
+        let mut fresh = || {
+            let id = NoteId(next);
+            next += 1;
+            id
+        };
+        for track in &mut self.tracks {
+            let mut sounding: HashMap<(Tick, u8), NoteId> = HashMap::new();
+            let mut renumbered: HashMap<NoteId, NoteId> = HashMap::new();
+            let mut open: HashMap<u8, VecDeque<NoteId>> = HashMap::new();
+            for event in &mut track.playback_events {
+                let paired = event.note_id.take();
+                match event.event {
+                    MidiLikeEvent::NoteOn { note, .. } => {
+                        let id = fresh();
+                        event.note_id = Some(id);
+                        if let Some(paired) = paired {
+                            renumbered.insert(paired, id);
+                        }
+                        open.entry(note).or_default().push_back(id);
+                        sounding.entry((event.tick, note)).or_insert(id);
+                        if let Some(principal) = event.ornament_of {
+                            sounding.entry((event.tick, principal)).or_insert(id);
+                        }
+                    }
+                    MidiLikeEvent::NoteOff { note } => {
+                        let open = open.entry(note).or_default();
+                        let idx = paired
+                            .and_then(|paired| renumbered.get(&paired))
+                            .and_then(|id| open.iter().position(|open_id| open_id == id))
+                            .unwrap_or(0);
+                        event.note_id = open.remove(idx);
+                    }
+                    MidiLikeEvent::Cc64 { .. } | MidiLikeEvent::ControlChange { .. } => {}
+                }
+            }
+            for target in &mut track.targets {
+                target.note_ids = target
+                    .notes
+                    .iter()
+                    .map(|&note| {
+                        sounding
+                            .get(&(target.tick, note))
+                            .copied()
+                            .unwrap_or_else(&mut fresh)
+                    })
+                    .collect();
+            }
+        }
+    }
+
+    /// The track to practice against: the only track as-is, or every track merged into one
+    /// with chords at the same tick combined into a single target.
+    pub fn merged_track(&self) -> Option<Cow<'_, Track>> {
+        match self.tracks.as_slice() {
+            [] => None,
+            [track] => Some(Cow::Borrowed(track)),
+            tracks => Some(Cow::Owned(merge_tracks(tracks.iter()))),
+        }
+    }
+
+    /// The tracks with the given ids, every track when `selected_track_ids` is empty,
+    /// merged into one practice track. Targets at the same tick become one, a pitch written
+    /// in several tracks is expected and played once, and each target note records the
+    /// track it came from in `note_tracks`.
+    pub fn merged_for_practice(&self, selected_track_ids: &[u32]) -> Track {
+        merge_tracks(self.tracks.iter().filter(|track| {
+            selected_track_ids.is_empty() || selected_track_ids.contains(&track.id)
+        }))
+    }
+}
+
+fn merge_tracks<'a>(tracks: impl Iterator<Item = &'a Track> + Clone) -> Track {
+    let mut grouped: BTreeMap<Tick, TargetEvent> = BTreeMap::new();
+    for track in tracks.clone() {
+        for target in &track.targets {
+            let mut target = target.clone();
+            if target.note_tracks.len() != target.notes.len() {
+                target.note_tracks = vec![track.id; target.notes.len()];
+            }
+            match grouped.get_mut(&target.tick) {
+                Some(merged) => {
+                    // Ids only stay parallel to the notes if every track had them.
+                    if merged.note_ids.len() == merged.notes.len()
+                        && target.note_ids.len() == target.notes.len()
+                    {
+                        merged.note_ids.extend(&target.note_ids);
+                    } else {
+                        merged.note_ids.clear();
+                    }
+                    merged.notes.extend(&target.notes);
+                    merged.note_tracks.extend(&target.note_tracks);
+                    if merged.hand != target.hand {
+                        merged.hand = None;
+                    }
+                    merged.measure_index = merged.measure_index.or(target.measure_index);
+                    merged.optional &= target.optional;
+                }
+                None => {
+                    grouped.insert(target.tick, target);
+                }
+            }
+        }
+    }
+    let targets = grouped
+        .into_values()
+        .enumerate()
+        .map(|(idx, mut target)| {
+            target.id = idx as u64 + 1;
+            sort_target_notes(&mut target);
+            target
+        })
+        .collect();
+
+    let mut playback_events = merge_playback_events(tracks);
+    playback_events.sort_by_key(|e| (e.tick, midi_event_rank(&e.event)));
+
+    Track {
+        id: 0,
+        name: "Merged".to_string(),
+        hand: None,
+        targets,
+        playback_events,
+    }
+}
+
+/// Every track's events, except that a pitch several tracks start at the same tick sounds
+/// once, like its target: the first track's note plays, held until the last of the copies
+/// would have ended.
+fn merge_playback_events<'a>(tracks: impl Iterator<Item = &'a Track>) -> Vec<PlaybackMidiEvent> {
+    /// Tick and pitch of a note on.
+    type NoteStart = (Tick, u8);
+    let mut merged: Vec<PlaybackMidiEvent> = Vec::new();
+    // Track that plays each start, and the index of its note off once seen.
+    let mut played: HashMap<NoteStart, (u32, Option<usize>)> = HashMap::new();
+    for track in tracks {
+        // Start of each sounding note of this track, and whether it is a dropped copy.
+        let mut open: HashMap<u8, VecDeque<(NoteStart, bool)>> = HashMap::new();
+        for event in &track.playback_events {
+            match event.event {
+                MidiLikeEvent::NoteOn { note, .. } => {
+                    let key = (event.tick, note);
+                    let copy = played
+                        .get(&key)
+                        .is_some_and(|&(track_id, _)| track_id != track.id);
+                    if !copy {
+                        played.entry(key).or_insert((track.id, None));
+                    }
+                    open.entry(note).or_default().push_back((key, copy));
+                    if copy {
+                        continue;
+                    }
+                }
+                MidiLikeEvent::NoteOff { note } => {
+                    if let Some((key, copy)) = open.get_mut(&note).and_then(VecDeque::pop_front) {
+                        let Some((track_id, off)) = played.get_mut(&key) else {
+                            continue;
+                        };
+                        if copy {
+                            if let Some(played_off) = off.and_then(|idx| merged.get_mut(idx)) {
+                                played_off.tick = played_off.tick.max(event.tick);
+                            }
+                            continue;
+                        }
+                        if *track_id == track.id && off.is_none() {
+                            *off = Some(merged.len());
+                        }
+                    }
+                }
+                MidiLikeEvent::Cc64 { .. } | MidiLikeEvent::ControlChange { .. } => {}
+            }
+            merged.push(event.clone());
+        }
+    }
+    merged
+}
+
+/// Sorts by pitch and drops repeated pitches, keeping the first of each. `note_ids` and
+/// `note_tracks` stay in step with `notes`, or are cleared if they were not.
+fn sort_target_notes(target: &mut TargetEvent) {
+    let mut order: Vec<usize> = (0..target.notes.len()).collect();
+    order.sort_by_key(|&idx| target.notes[idx]);
+    order.dedup_by_key(|idx| target.notes[*idx]);
+    fn reorder<T: Copy>(values: &mut Vec<T>, order: &[usize], len: usize) {
+        *values = if values.len() == len {
+            order.iter().map(|&idx| values[idx]).collect()
+        } else {
+            Vec::new()
+        };
+    }
+    let len = target.notes.len();
+    reorder(&mut target.note_ids, &order, len);
+    reorder(&mut target.note_tracks, &order, len);
+    reorder(&mut target.notes, &order, len);
+}
+
+fn midi_event_rank(event: &MidiLikeEvent) -> u8 {
+    match event {
+        MidiLikeEvent::Cc64 { value } => {
+            if *value >= 64 {
+                0
+            } else {
+                3
+            }
+        }
+        MidiLikeEvent::ControlChange { .. } => 0,
+        MidiLikeEvent::NoteOff { .. } => 1,
+        MidiLikeEvent::NoteOn { .. } => 2,
+    }
 }
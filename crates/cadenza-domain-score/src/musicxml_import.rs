@@ -1,11 +1,18 @@
 use crate::model::{
-    Hand, PlaybackMidiEvent, Score, ScoreMeta, ScoreSource, TargetEvent, TempoPoint, Track,
+    Articulation, Hand, KeyMode, KeySignaturePoint, NoteId, PlaybackMidiEvent, Score, ScoreMeta,
+    ScoreSource, TargetEvent, TempoPoint, TimeSignaturePoint, Track,
 };
+use crate::pitch::{SpelledPitch, Step};
+use crate::warnings::{ImportWarning, MusicXmlWarningKind};
 use cadenza_ports::midi::MidiLikeEvent;
 use cadenza_ports::types::Tick;
-use roxmltree::Document;
-use std::collections::{BTreeMap, HashMap};
-use std::io::Read;
+use quick_xml::events::Event as XmlEvent;
+use quick_xml::Reader;
+use roxmltree::{Document, ParsingOptions};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{BufRead, BufReader, Cursor, Read, Seek, SeekFrom};
+use std::ops::Range;
 use std::path::Path;
 use zip::ZipArchive;
 
@@ -19,37 +26,435 @@ pub enum MusicXmlImportError {
     Unsupported(String),
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Ornament {
+    Trill {
+        upper: u8,
+    },
+    Turn {
+        upper: u8,
+        lower: u8,
+        inverted: bool,
+    },
+    /// Measured single-note tremolo; `beams` is the number of tremolo strokes.
+    Tremolo {
+        beams: u32,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ArpeggioDirection {
+    Up,
+    Down,
+}
+
 #[derive(Clone, Debug)]
 struct NoteEvent {
     tick: Tick,
+    /// Written length until articulations are applied, sounding length afterwards.
     duration_ticks: Tick,
     note: u8,
     velocity: u8,
     hand: Option<Hand>,
     measure_index: Option<u32>,
+    articulations: Vec<Articulation>,
+    /// Grace notes: played for reference but skippable in practice.
+    optional: bool,
+    ornament: Option<Ornament>,
+    arpeggio: Option<ArpeggioDirection>,
+    /// `<voice>` the note was written in; hands are settled per voice at the end of a measure.
+    voice: Option<String>,
+    /// A slur in this note's voice is still open after it, so it is played into the next note.
+    slurred: bool,
+    /// The written pitch, before `transpose`.
+    spelling: Option<SpelledPitch>,
+    /// Semitones `note` was moved from the written pitch to sound at concert pitch.
+    transpose: i8,
+}
+
+#[derive(Clone, Debug)]
+struct GraceNote {
+    notes: Vec<u8>,
+    hand: Option<Hand>,
+    /// Slashed (acciaccatura) graces are played before the beat.
+    slash: bool,
+    transpose: i8,
+}
+
+/// Grace notes held back until the measure's hands are settled, so time is stolen from the
+/// note the principal's hand actually plays.
+struct PendingGraces {
+    graces: Vec<GraceNote>,
+    principal_tick: Tick,
+    principal_duration: Tick,
+    velocity: u8,
+    /// The principal and the rest of its chord in `note_events`.
+    principal: Vec<usize>,
+    /// Still taking chord notes; closed by the next note that is not part of the chord.
+    open: bool,
+}
+
+type TargetGroup = (Vec<(u8, Option<Hand>)>, Option<u32>, bool);
+
+/// Part names that identify a keyboard part when importing with [`PartSelection::PianoLike`].
+const PIANO_PART_KEYWORDS: [&str; 5] = ["piano", "pno", "klavier", "keyboard", "clavier"];
+
+/// Which `<part>`s become tracks. Tempo and meter are read from every part regardless.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PartSelection {
+    #[default]
+    All,
+    /// Parts whose name looks like a keyboard instrument; falls back to all parts when none do.
+    PianoLike,
+    /// Zero-based part indices in document order.
+    Indices(Vec<usize>),
+}
+
+#[derive(Clone, Debug)]
+pub struct MusicXmlImportOptions {
+    pub parts: PartSelection,
+    /// Velocity added to accented notes; strong accents get twice this.
+    pub accent_velocity_boost: u8,
+    /// Time taken by a group of grace notes, split evenly between them.
+    pub grace_note_ticks: Tick,
+    /// Apply `<transpose>` so transposing instruments sound at concert pitch. When false the
+    /// written pitch is kept, e.g. for display alongside the part.
+    pub concert_pitch: bool,
+    /// Play trills, turns and tremolos as their individual notes. Targets keep the principal.
+    pub expand_ornaments: bool,
+    /// Trill speed; each trill note lasts a quarter divided by this.
+    pub trill_notes_per_quarter: u32,
+    /// Delay between successive notes of an arpeggiated chord.
+    pub arpeggio_note_ms: f32,
+    /// How much longer a note or rest under a fermata is held. Applied through the tempo map
+    /// so every part slows together and the measure grid is unchanged.
+    pub fermata_multiplier: f32,
+    /// Silence cut from the end of a note carrying a breath mark.
+    pub breath_gap_ticks: Tick,
+    /// How far a slurred note is held into the next one, as a percentage of the shorter of
+    /// the two. Repeated pitches under a slur are still separated.
+    pub slur_overlap_percent: u32,
+    /// Inputs at least this large are read measure by measure instead of as one DOM, which
+    /// keeps memory flat for long OMR books. Uncompressed files are then read from disk a
+    /// measure at a time as well. The result is the same either way.
+    pub streaming_threshold_bytes: usize,
 }
 
-type TargetGroup = (Vec<(u8, Option<Hand>)>, Option<u32>);
+impl Default for MusicXmlImportOptions {
+    fn default() -> Self {
+        Self {
+            parts: PartSelection::default(),
+            accent_velocity_boost: 16,
+            // A 32nd note at the importer's fixed 480 PPQ.
+            grace_note_ticks: 60,
+            concert_pitch: true,
+            expand_ornaments: true,
+            trill_notes_per_quarter: 8,
+            arpeggio_note_ms: 30.0,
+            fermata_multiplier: 1.8,
+            breath_gap_ticks: 60,
+            slur_overlap_percent: 10,
+            streaming_threshold_bytes: 4 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MusicXmlImport {
+    pub score: Score,
+    pub warnings: Vec<ImportWarning>,
+}
 
 pub fn import_musicxml_path(path: &Path) -> Result<Score, MusicXmlImportError> {
+    import_musicxml_path_with_options(path, &MusicXmlImportOptions::default())
+}
+
+pub fn import_musicxml_path_with_options(
+    path: &Path,
+    options: &MusicXmlImportOptions,
+) -> Result<Score, MusicXmlImportError> {
+    import_musicxml_path_with_report(path, options).map(|import| import.score)
+}
+
+pub fn import_musicxml_path_with_report(
+    path: &Path,
+    options: &MusicXmlImportOptions,
+) -> Result<MusicXmlImport, MusicXmlImportError> {
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+    if !ext.eq_ignore_ascii_case("mxl") {
+        let io_error = |e: std::io::Error| MusicXmlImportError::Io(e.to_string());
+        let file = std::fs::File::open(path).map_err(io_error)?;
+        let len = file.metadata().map_err(io_error)?.len();
+        if len >= options.streaming_threshold_bytes as u64 {
+            if let Some(import) = import_streamed(BufReader::new(file), options)? {
+                return Ok(import);
+            }
+        }
+    }
     let data = read_musicxml_file(path)?;
-    import_musicxml_str(&data)
+    import_musicxml_str_with_report(&data, options)
 }
 
 pub fn import_musicxml_str(xml: &str) -> Result<Score, MusicXmlImportError> {
-    let doc = Document::parse(xml).map_err(|e| MusicXmlImportError::Parse(e.to_string()))?;
-    let title = doc
+    import_musicxml_str_with_options(xml, &MusicXmlImportOptions::default())
+}
+
+pub fn import_musicxml_str_with_options(
+    xml: &str,
+    options: &MusicXmlImportOptions,
+) -> Result<Score, MusicXmlImportError> {
+    import_musicxml_str_with_report(xml, options).map(|import| import.score)
+}
+
+pub fn import_musicxml_str_with_report(
+    xml: &str,
+    options: &MusicXmlImportOptions,
+) -> Result<MusicXmlImport, MusicXmlImportError> {
+    if xml.len() >= options.streaming_threshold_bytes {
+        if let Some(import) = import_streamed(Cursor::new(xml.as_bytes()), options)? {
+            return Ok(import);
+        }
+    }
+    let doc = parse_document(xml)?;
+    import_document(&doc, None, options)
+}
+
+/// Parses with DTDs allowed, so entities a score declares in its internal subset resolve.
+fn parse_document(xml: &str) -> Result<Document<'_>, MusicXmlImportError> {
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    Document::parse_with_options(xml, options)
+        .map_err(|e| MusicXmlImportError::Parse(e.to_string()))
+}
+
+/// Imports a `<score-partwise>` document without building its full DOM: one pass finds where
+/// everything is, then the header and each measure are read back and parsed on their own.
+/// Returns `None` for other roots, which only the DOM path reads.
+fn import_streamed<R: BufRead + Seek>(
+    mut source: R,
+    options: &MusicXmlImportOptions,
+) -> Result<Option<MusicXmlImport>, MusicXmlImportError> {
+    let Some(layout) = scan_partwise_layout(&mut source)? else {
+        return Ok(None);
+    };
+    let mut header = String::new();
+    for range in &layout.header {
+        header.push_str(&read_source_range(&mut source, range)?);
+    }
+    let header_doc = parse_document(&header)?;
+    let context = read_source_range(&mut source, &layout.context)?;
+    let mut measures = StreamedMeasures {
+        source: &mut source,
+        layout: &layout,
+        context,
+    };
+    import_document(&header_doc, Some(&mut measures), options).map(Some)
+}
+
+/// Where the parts of a `<score-partwise>` document are, as found by a pull parser.
+struct PartwiseLayout {
+    /// Everything except measure content, with each `<part>` left empty.
+    header: Vec<Range<u64>>,
+    /// The prolog and root start tag, which declare the entities and namespaces a measure
+    /// may use.
+    context: Range<u64>,
+    /// Closing tags of the root element, e.g. `</score-partwise>`.
+    root_end: String,
+    parts: Vec<PartLayout>,
+}
+
+struct PartLayout {
+    start_tag: Range<u64>,
+    /// Closing tag of the part element, e.g. `</part>`.
+    end_tag: String,
+    measures: Vec<Range<u64>>,
+}
+
+/// Returns `None` when the root is not `<score-partwise>`, which only the DOM path reads.
+fn scan_partwise_layout<R: BufRead>(
+    source: &mut R,
+) -> Result<Option<PartwiseLayout>, MusicXmlImportError> {
+    let parse_error = |e: quick_xml::Error| MusicXmlImportError::Parse(e.to_string());
+    let closing_tag = |name: &[u8]| format!("</{}>", String::from_utf8_lossy(name));
+    let mut reader = Reader::from_reader(source);
+    let mut buf = Vec::new();
+    let mut header = Vec::new();
+    let mut copied = 0u64;
+    let mut context = 0..0;
+    let mut root_end = String::new();
+    let mut parts: Vec<PartLayout> = Vec::new();
+    let mut depth = 0usize;
+    let mut measure_start: Option<u64> = None;
+
+    loop {
+        buf.clear();
+        let start = reader.buffer_position();
+        let event = reader.read_event_into(&mut buf).map_err(parse_error)?;
+        let end = reader.buffer_position();
+        match event {
+            XmlEvent::Start(element) => {
+                depth += 1;
+                match (depth, element.local_name().as_ref()) {
+                    (1, name) if name != b"score-partwise" => return Ok(None),
+                    (1, _) => {
+                        context = 0..end;
+                        root_end = closing_tag(element.name().as_ref());
+                    }
+                    (2, b"part") => {
+                        header.push(copied..end);
+                        parts.push(PartLayout {
+                            start_tag: start..end,
+                            end_tag: closing_tag(element.name().as_ref()),
+                            measures: Vec::new(),
+                        });
+                    }
+                    (3, b"measure") if !parts.is_empty() => measure_start = Some(start),
+                    _ => {}
+                }
+            }
+            XmlEvent::Empty(element) => match (depth + 1, element.local_name().as_ref()) {
+                (1, name) if name != b"score-partwise" => return Ok(None),
+                (2, b"part") => parts.push(PartLayout {
+                    start_tag: start..end,
+                    end_tag: String::new(),
+                    measures: Vec::new(),
+                }),
+                (3, b"measure") => {
+                    if let Some(part) = parts.last_mut() {
+                        part.measures.push(start..end);
+                    }
+                }
+                _ => {}
+            },
+            XmlEvent::End(element) => {
+                match (depth, element.local_name().as_ref()) {
+                    (2, b"part") => copied = start,
+                    (3, b"measure") => {
+                        if let (Some(part), Some(measure_start)) =
+                            (parts.last_mut(), measure_start.take())
+                        {
+                            part.measures.push(measure_start..end);
+                        }
+                    }
+                    _ => {}
+                }
+                depth = depth.saturating_sub(1);
+            }
+            XmlEvent::Eof => {
+                header.push(copied..reader.buffer_position());
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(Some(PartwiseLayout {
+        header,
+        context,
+        root_end,
+        parts,
+    }))
+}
+
+trait SeekRead: Read + Seek {}
+
+impl<T: Read + Seek> SeekRead for T {}
+
+fn read_source_range<R: Read + Seek + ?Sized>(
+    source: &mut R,
+    range: &Range<u64>,
+) -> Result<String, MusicXmlImportError> {
+    let io_error = |e: std::io::Error| MusicXmlImportError::Io(e.to_string());
+    source
+        .seek(SeekFrom::Start(range.start))
+        .map_err(io_error)?;
+    let mut bytes = vec![0; (range.end - range.start) as usize];
+    source.read_exact(&mut bytes).map_err(io_error)?;
+    String::from_utf8(bytes).map_err(|e| MusicXmlImportError::Parse(e.to_string()))
+}
+
+/// Measures of a streamed document, read back from the source one at a time.
+struct StreamedMeasures<'a> {
+    source: &'a mut dyn SeekRead,
+    layout: &'a PartwiseLayout,
+    /// Text of [`PartwiseLayout::context`].
+    context: String,
+}
+
+impl StreamedMeasures<'_> {
+    fn count(&self, part: usize) -> usize {
+        self.layout
+            .parts
+            .get(part)
+            .map_or(0, |part| part.measures.len())
+    }
+
+    /// Measure `measure` of `part` inside the document's prolog, root and part start tags, so
+    /// it parses on its own with every entity and namespace the document declares.
+    fn fragment(&mut self, part: usize, measure: usize) -> Result<String, MusicXmlImportError> {
+        let layout = &self.layout.parts[part];
+        let mut text = self.context.clone();
+        text.push_str(&read_source_range(self.source, &layout.start_tag)?);
+        text.push_str(&read_source_range(self.source, &layout.measures[measure])?);
+        text.push_str(&layout.end_tag);
+        text.push_str(&self.layout.root_end);
+        Ok(text)
+    }
+}
+
+/// Reads a parsed document. With `streamed`, `doc` is only the header and each measure is
+/// parsed on its own from the source, so the full DOM never exists at once.
+fn import_document(
+    doc: &Document,
+    mut streamed: Option<&mut StreamedMeasures>,
+    options: &MusicXmlImportOptions,
+) -> Result<MusicXmlImport, MusicXmlImportError> {
+    let credits = read_credit_words(doc);
+    let title = read_title(doc, &credits);
+    let composer = read_composer(doc, &credits);
+    let copyright = doc
         .descendants()
-        .find(|node| node.has_tag_name("work-title"))
+        .find(|node| node.has_tag_name("rights"))
         .and_then(|node| node.text())
-        .map(|text| text.to_string());
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(str::to_string);
 
     let ppq: u16 = 480;
     let mut tempo_points: BTreeMap<Tick, u32> = BTreeMap::new();
-    let mut note_events: Vec<NoteEvent> = Vec::new();
-    let mut cc64_events: Vec<PlaybackMidiEvent> = Vec::new();
+    let mut time_signature_points: BTreeMap<Tick, (i64, i64)> = BTreeMap::new();
+    let mut key_signature_points: BTreeMap<Tick, (i8, KeyMode)> = BTreeMap::new();
+    let mut pickup_ticks: Tick = 0;
+    let mut part_events: Vec<(usize, Vec<NoteEvent>, Vec<PlaybackMidiEvent>)> = Vec::new();
+    let mut fermata_spans: Vec<(Tick, Tick)> = Vec::new();
+    let mut warnings: Vec<ImportWarning> = Vec::new();
+
+    let part_names = read_part_names(doc);
+    let parts: Vec<_> = doc
+        .descendants()
+        .filter(|node| node.has_tag_name("part"))
+        .collect();
+    let names: Vec<String> = parts
+        .iter()
+        .enumerate()
+        .map(|(idx, part)| {
+            part.attribute("id")
+                .and_then(|id| part_names.get(id).cloned())
+                .unwrap_or_else(|| format!("Part {}", idx + 1))
+        })
+        .collect();
+    let selected = select_parts(&names, &options.parts);
+    if selected.is_empty() && !parts.is_empty() {
+        return Err(MusicXmlImportError::Unsupported(
+            "part selection matches no parts".to_string(),
+        ));
+    }
 
-    for part in doc.descendants().filter(|node| node.has_tag_name("part")) {
+    for (part_index, part) in parts.iter().enumerate() {
+        let mut note_events: Vec<NoteEvent> = Vec::new();
+        let mut cc64_events: Vec<PlaybackMidiEvent> = Vec::new();
         let mut current_tick: Tick = 0;
         let mut divisions: i64 = 1;
         let mut current_velocity: u8 = 90;
@@ -59,15 +464,61 @@ pub fn import_musicxml_str(xml: &str) -> Result<Score, MusicXmlImportError> {
         let mut measure_index: u32 = 0;
         let mut active_ties: HashMap<(u8, Option<Hand>), usize> = HashMap::new();
         let mut max_note_end_tick: Tick = 0;
-
-        for measure in part
-            .children()
-            .filter(|node| node.is_element() && node.has_tag_name("measure"))
-        {
+        let mut pending_graces: Vec<GraceNote> = Vec::new();
+        let mut transpose_semitones: i32 = 0;
+        let mut key_fifths: i32 = 0;
+        let mut voice_hands: HashMap<String, Hand> = HashMap::new();
+        let mut open_slurs: HashMap<Option<String>, HashSet<String>> = HashMap::new();
+        let mut part_warnings: Vec<ImportWarning> = Vec::new();
+        // Start tick and note range of every measure read so far, for measure repeats.
+        let mut measure_spans: Vec<(Tick, Range<usize>)> = Vec::new();
+        // A multi-rest's (measure index, bars) while the bars after its first aren't written.
+        let mut pending_multi_rest: Option<(u32, u32)> = None;
+        let mut repeat_pattern: Option<u32> = None;
+
+        let mut read_measure = |measure: roxmltree::Node| {
             let measure_is_implicit = measure
                 .attribute("implicit")
                 .is_some_and(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "yes" | "true"));
+            let style = parse_measure_style(&measure);
+            let has_notes = measure_has_notes(&measure);
+
+            // A multi-rest is often written out as that many empty measures. When OMR output
+            // carries only the first, the missing bars are inserted before the next one with
+            // notes so later measures keep their printed numbers and ticks.
+            if let Some((rest_index, remaining)) = pending_multi_rest.take() {
+                if !has_notes {
+                    pending_multi_rest =
+                        Some((rest_index, remaining - 1)).filter(|(_, left)| *left > 0);
+                } else {
+                    let bar_ticks = measure_length_ticks(ppq, time_beats, time_beat_type);
+                    for _ in 0..remaining {
+                        measure_spans
+                            .push((current_tick.max(0), note_events.len()..note_events.len()));
+                        current_tick = current_tick.saturating_add(bar_ticks);
+                    }
+                    measure_index = measure_index.saturating_add(remaining);
+                    part_warnings.push(ImportWarning::MusicXml {
+                        part_index,
+                        measure_index: rest_index,
+                        kind: MusicXmlWarningKind::ExpandedMultiRest,
+                        detail: format!("multi-rest expanded by {remaining} empty measures"),
+                    });
+                }
+            }
+            if let Some(bars) = style.multiple_rest.filter(|bars| *bars > 1) {
+                pending_multi_rest = Some((measure_index, bars - 1));
+            }
+            if style.repeat_stop {
+                repeat_pattern = None;
+            }
+            if let Some(bars) = style.repeat_start {
+                repeat_pattern = Some(bars.max(1));
+            }
+
             let measure_start = current_tick.max(0);
+            let measure_first_note = note_events.len();
+            let mut measure_graces: Vec<PendingGraces> = Vec::new();
             let mut cursor = measure_start;
             let mut measure_end = measure_start;
 
@@ -89,6 +540,19 @@ pub fn import_musicxml_str(xml: &str) -> Result<Score, MusicXmlImportError> {
                             divisions = text.parse::<i64>().unwrap_or(1).max(1);
                         }
                     }
+                    if let Some((fifths, mode)) = parse_key(&element) {
+                        key_fifths = fifths;
+                        if let Ok(fifths) = i8::try_from(fifths) {
+                            key_signature_points
+                                .entry(measure_start)
+                                .or_insert((fifths, mode));
+                        }
+                    }
+                    if options.concert_pitch {
+                        if let Some(semitones) = parse_transpose(&element) {
+                            transpose_semitones = semitones;
+                        }
+                    }
                     if let Some(time_node) =
                         element.children().find(|node| node.has_tag_name("time"))
                     {
@@ -107,6 +571,9 @@ pub fn import_musicxml_str(xml: &str) -> Result<Score, MusicXmlImportError> {
                             if beats > 0 && beat_type > 0 {
                                 time_beats = beats;
                                 time_beat_type = beat_type;
+                                time_signature_points
+                                    .entry(measure_start)
+                                    .or_insert((beats, beat_type));
                                 let measure_len_ticks =
                                     measure_length_ticks(ppq, time_beats, time_beat_type);
                                 expected_end_tick = if measure_len_ticks > 0 {
@@ -146,6 +613,18 @@ pub fn import_musicxml_str(xml: &str) -> Result<Score, MusicXmlImportError> {
                         }
                     }
 
+                    for direction_type in element
+                        .children()
+                        .filter(|node| node.is_element() && node.has_tag_name("direction-type"))
+                    {
+                        if let Some(us_per_quarter) = parse_metronome(&direction_type) {
+                            tempo_points.insert(tick, us_per_quarter);
+                        } else if let Some(us_per_quarter) = parse_tempo_words(&direction_type) {
+                            // Words are only a fallback; a numeric mark at this tick wins.
+                            tempo_points.entry(tick).or_insert(us_per_quarter);
+                        }
+                    }
+
                     if let Some(direction_type) = element
                         .children()
                         .find(|node| node.is_element() && node.has_tag_name("direction-type"))
@@ -154,6 +633,13 @@ pub fn import_musicxml_str(xml: &str) -> Result<Score, MusicXmlImportError> {
                             .or_else(|| parse_dynamics_words(&direction_type))
                         {
                             current_velocity = vel;
+                        } else if let Some(marks) = unknown_dynamics(&direction_type) {
+                            part_warnings.push(ImportWarning::MusicXml {
+                                part_index,
+                                measure_index,
+                                kind: MusicXmlWarningKind::UnknownDynamics,
+                                detail: format!("ignored unknown dynamics {marks}"),
+                            });
                         }
                         for pedal_node in direction_type
                             .children()
@@ -182,13 +668,42 @@ pub fn import_musicxml_str(xml: &str) -> Result<Score, MusicXmlImportError> {
                     let is_rest = element.children().any(|node| node.has_tag_name("rest"));
                     let is_grace = element.children().any(|node| node.has_tag_name("grace"));
                     if is_grace {
+                        if let Some(note) = parse_note(&element)
+                            .map(|note| transpose_note(note, transpose_semitones))
+                        {
+                            match pending_graces.last_mut() {
+                                Some(grace) if is_chord => grace.notes.push(note),
+                                _ => pending_graces.push(GraceNote {
+                                    notes: vec![note],
+                                    hand: parse_hand(&element),
+                                    slash: element
+                                        .children()
+                                        .find(|node| node.has_tag_name("grace"))
+                                        .and_then(|node| node.attribute("slash"))
+                                        == Some("yes"),
+                                    transpose: transpose_semitones as i8,
+                                }),
+                            }
+                        }
                         continue;
                     }
 
                     let mut raw_duration = duration_ticks(&element, divisions, ppq);
                     let mut duration_missing = raw_duration == 0;
                     if duration_missing {
-                        if let Some(inferred) = infer_note_duration_ticks(&element, ppq) {
+                        let inferred = infer_note_duration_ticks(&element, ppq);
+                        part_warnings.push(ImportWarning::MusicXml {
+                            part_index,
+                            measure_index,
+                            kind: MusicXmlWarningKind::InferredDuration,
+                            detail: match inferred {
+                                Some(ticks) => {
+                                    format!("missing duration inferred as {ticks} ticks")
+                                }
+                                None => "missing duration could not be inferred".to_string(),
+                            },
+                        });
+                        if let Some(inferred) = inferred {
                             raw_duration = inferred;
                             duration_missing = false;
                         }
@@ -201,14 +716,64 @@ pub fn import_musicxml_str(xml: &str) -> Result<Score, MusicXmlImportError> {
                     let mut duration = raw_duration.max(0);
                     let max_len = expected_end_tick.map(|end_tick| (end_tick - base_tick).max(0));
                     if let Some(max_len) = max_len {
+                        if duration > max_len && !is_rest {
+                            part_warnings.push(ImportWarning::MusicXml {
+                                part_index,
+                                measure_index,
+                                kind: MusicXmlWarningKind::ClampedOverflow,
+                                detail: format!(
+                                    "note of {duration} ticks clamped to {max_len} at measure end"
+                                ),
+                            });
+                        }
                         duration = duration.min(max_len);
                     }
                     let duration_for_note = duration.max(1);
 
+                    if has_fermata(&element) {
+                        let start = base_tick.max(0);
+                        fermata_spans.push((start, start + duration_for_note));
+                    }
+
+                    if !is_chord {
+                        if let Some(placement) = measure_graces.last_mut() {
+                            placement.open = false;
+                        }
+                        if is_rest && !pending_graces.is_empty() {
+                            part_warnings.push(dropped_graces_warning(
+                                part_index,
+                                measure_index,
+                                &pending_graces,
+                            ));
+                            pending_graces.clear();
+                        }
+                    }
+
                     if !is_rest {
-                        if let Some(note) = parse_note(&element) {
+                        if let Some(written) = parse_note(&element) {
+                            let note = transpose_note(written, transpose_semitones);
+                            let spelling = parse_pitch(&element);
                             let hand = parse_hand(&element);
+                            if !is_chord && !pending_graces.is_empty() {
+                                measure_graces.push(PendingGraces {
+                                    graces: std::mem::take(&mut pending_graces),
+                                    principal_tick: base_tick.max(0),
+                                    principal_duration: duration_for_note,
+                                    velocity: current_velocity,
+                                    principal: Vec::new(),
+                                    open: true,
+                                });
+                            }
                             let (tie_start, tie_stop) = parse_ties(&element);
+                            let articulations = parse_articulations(&element);
+                            let ornament =
+                                parse_ornament(&element, written, key_fifths, transpose_semitones);
+                            let voice = parse_voice(&element);
+                            let slurred = update_open_slurs(
+                                open_slurs.entry(voice.clone()).or_default(),
+                                &element,
+                            );
+                            let arpeggio = parse_arpeggiate(&element);
                             let key = (note, hand);
 
                             if tie_stop {
@@ -216,6 +781,7 @@ pub fn import_musicxml_str(xml: &str) -> Result<Score, MusicXmlImportError> {
                                     note_events[idx].duration_ticks = note_events[idx]
                                         .duration_ticks
                                         .saturating_add(duration_for_note);
+                                    note_events[idx].slurred = slurred;
                                     max_note_end_tick = max_note_end_tick.max(
                                         note_events[idx]
                                             .tick
@@ -225,15 +791,38 @@ pub fn import_musicxml_str(xml: &str) -> Result<Score, MusicXmlImportError> {
                                         active_ties.remove(&key);
                                     }
                                 } else {
+                                    part_warnings.push(ImportWarning::MusicXml {
+                                        part_index,
+                                        measure_index,
+                                        kind: MusicXmlWarningKind::UnmatchedTie,
+                                        detail: format!("tie stop on note {note} without a start"),
+                                    });
                                     let idx = note_events.len();
                                     note_events.push(NoteEvent {
                                         tick: base_tick.max(0),
                                         duration_ticks: duration_for_note,
                                         note,
-                                        velocity: current_velocity,
+                                        velocity: accented_velocity(
+                                            current_velocity,
+                                            &articulations,
+                                            options.accent_velocity_boost,
+                                        ),
                                         hand,
                                         measure_index: Some(measure_index),
+                                        articulations: articulations.clone(),
+                                        optional: false,
+                                        ornament,
+                                        arpeggio,
+                                        voice: voice.clone(),
+                                        slurred,
+                                        spelling,
+                                        transpose: transpose_semitones as i8,
                                     });
+                                    if let Some(placement) =
+                                        measure_graces.last_mut().filter(|p| p.open)
+                                    {
+                                        placement.principal.push(idx);
+                                    }
                                     max_note_end_tick = max_note_end_tick
                                         .max(base_tick.saturating_add(duration_for_note));
                                     if tie_start {
@@ -246,10 +835,27 @@ pub fn import_musicxml_str(xml: &str) -> Result<Score, MusicXmlImportError> {
                                     tick: base_tick.max(0),
                                     duration_ticks: duration_for_note,
                                     note,
-                                    velocity: current_velocity,
+                                    velocity: accented_velocity(
+                                        current_velocity,
+                                        &articulations,
+                                        options.accent_velocity_boost,
+                                    ),
                                     hand,
                                     measure_index: Some(measure_index),
+                                    articulations: articulations.clone(),
+                                    optional: false,
+                                    ornament,
+                                    arpeggio,
+                                    voice: voice.clone(),
+                                    slurred,
+                                    spelling,
+                                    transpose: transpose_semitones as i8,
                                 });
+                                if let Some(placement) =
+                                    measure_graces.last_mut().filter(|p| p.open)
+                                {
+                                    placement.principal.push(idx);
+                                }
                                 max_note_end_tick = max_note_end_tick
                                     .max(base_tick.saturating_add(duration_for_note));
                                 if tie_start {
@@ -284,11 +890,101 @@ pub fn import_musicxml_str(xml: &str) -> Result<Score, MusicXmlImportError> {
             if let Some(end_tick) = expected_end_tick {
                 if !measure_is_implicit {
                     measure_end = measure_end.max(end_tick);
+                } else if measure_index == 0 && measure_end < end_tick && pickup_ticks == 0 {
+                    pickup_ticks = measure_end - measure_start;
+                }
+            }
+
+            // Like multi-rests, measure repeats should be written out in full; OMR output often
+            // leaves the bar empty instead, so the pattern is copied in.
+            let pattern_source = repeat_pattern
+                .filter(|_| !has_notes)
+                .and_then(|bars| measure_spans.len().checked_sub(bars as usize))
+                .map(|source| measure_spans[source].clone());
+            if let Some((source_start, source_notes)) = pattern_source {
+                let shift = measure_start - source_start;
+                for idx in source_notes.clone() {
+                    let mut event = note_events[idx].clone();
+                    event.tick += shift;
+                    event.measure_index = Some(measure_index);
+                    max_note_end_tick =
+                        max_note_end_tick.max(event.tick.saturating_add(event.duration_ticks));
+                    note_events.push(event);
                 }
+                part_warnings.push(ImportWarning::MusicXml {
+                    part_index,
+                    measure_index,
+                    kind: MusicXmlWarningKind::ExpandedMeasureRepeat,
+                    detail: format!("measure repeat copied {} notes", source_notes.len()),
+                });
             }
 
+            assign_voice_hands(&mut note_events[measure_first_note..], &mut voice_hands);
+            for placement in measure_graces {
+                place_grace_notes(
+                    &mut note_events,
+                    placement,
+                    options.grace_note_ticks,
+                    measure_index,
+                );
+            }
+            measure_spans.push((measure_start, measure_first_note..note_events.len()));
             current_tick = measure_end;
             measure_index = measure_index.saturating_add(1);
+        };
+        match streamed.as_deref_mut() {
+            Some(measures) => {
+                for measure in 0..measures.count(part_index) {
+                    let text = measures.fragment(part_index, measure)?;
+                    let measure_doc = parse_document(&text)?;
+                    let measure = measure_doc
+                        .root_element()
+                        .first_element_child()
+                        .and_then(|part| part.first_element_child())
+                        .ok_or_else(|| MusicXmlImportError::Parse("empty measure".to_string()))?;
+                    read_measure(measure);
+                }
+            }
+            None => {
+                for measure in part
+                    .children()
+                    .filter(|node| node.is_element() && node.has_tag_name("measure"))
+                {
+                    read_measure(measure);
+                }
+            }
+        }
+
+        if let Some((rest_index, remaining)) = pending_multi_rest {
+            let bar_ticks = measure_length_ticks(ppq, time_beats, time_beat_type);
+            current_tick = current_tick.saturating_add(bar_ticks * Tick::from(remaining));
+            measure_index = measure_index.saturating_add(remaining);
+            part_warnings.push(ImportWarning::MusicXml {
+                part_index,
+                measure_index: rest_index,
+                kind: MusicXmlWarningKind::ExpandedMultiRest,
+                detail: format!("multi-rest expanded by {remaining} empty measures"),
+            });
+        }
+
+        let last_measure = measure_index.saturating_sub(1);
+        if !pending_graces.is_empty() {
+            part_warnings.push(dropped_graces_warning(
+                part_index,
+                last_measure,
+                &pending_graces,
+            ));
+        }
+        let mut open_ties: Vec<usize> = active_ties.into_values().collect();
+        open_ties.sort_unstable();
+        for idx in open_ties {
+            let event = &note_events[idx];
+            part_warnings.push(ImportWarning::MusicXml {
+                part_index,
+                measure_index: event.measure_index.unwrap_or(last_measure),
+                kind: MusicXmlWarningKind::UnmatchedTie,
+                detail: format!("tie start on note {} never stopped", event.note),
+            });
         }
 
         // Ensure pedal is released for this part at end-of-score.
@@ -296,32 +992,206 @@ pub fn import_musicxml_str(xml: &str) -> Result<Score, MusicXmlImportError> {
             let end_tick = max_note_end_tick.max(current_tick);
             emit_cc64_change(&mut cc64_events, end_tick, &mut pedal_down, false);
         }
+
+        if selected.contains(&part_index) {
+            part_events.push((part_index, note_events, cc64_events));
+            warnings.append(&mut part_warnings);
+        }
     }
 
+    apply_fermatas(&mut tempo_points, fermata_spans, options.fermata_multiplier);
     let tempo_map = build_tempo_map(tempo_points);
-    apply_rearticulation_gaps(&mut note_events);
-    let playback_events = build_playback_events(&note_events, &cc64_events);
-    let targets = build_targets(&note_events);
+    let time_signatures = build_time_signatures(time_signature_points);
+    let key_signatures = build_key_signatures(key_signature_points);
 
-    let track = Track {
-        id: 0,
-        name: "Merged".to_string(),
-        hand: None,
-        targets,
-        playback_events,
-    };
+    // Tracks are built once every part has been read so playback timing that depends on the
+    // tempo (arpeggio rolls) sees tempo marks from all parts.
+    let tracks = part_events
+        .into_iter()
+        .map(|(part_index, mut note_events, cc64_events)| {
+            apply_articulation_lengths(&mut note_events, options.breath_gap_ticks);
+            apply_slur_overlaps(&mut note_events, options.slur_overlap_percent);
+            apply_rearticulation_gaps(&mut note_events);
+            let on_offsets = arpeggio_offsets(&note_events, ppq, &tempo_map, options);
+            Track {
+                id: part_index as u32,
+                name: names[part_index].clone(),
+                hand: None,
+                targets: build_targets(&note_events),
+                playback_events: build_playback_events(
+                    &note_events,
+                    &on_offsets,
+                    &cc64_events,
+                    ppq,
+                    options,
+                ),
+            }
+        })
+        .collect();
 
-    let score = Score {
+    let mut score = Score {
         meta: ScoreMeta {
             title,
+            composer,
+            copyright,
             source: ScoreSource::MusicXml,
+            origin_path: None,
         },
         ppq,
         tempo_map,
-        tracks: vec![track],
+        time_signatures,
+        pickup_ticks,
+        key_signatures,
+        tracks,
     };
+    score.assign_note_ids();
+
+    Ok(MusicXmlImport { score, warnings })
+}
+
+/// One `<credit-words>` block from the page header.
+struct CreditWords {
+    text: String,
+    credit_type: Option<String>,
+    font_size: f32,
+    default_y: f32,
+    right_aligned: bool,
+}
+
+fn read_credit_words(doc: &Document) -> Vec<CreditWords> {
+    doc.descendants()
+        .filter(|node| node.has_tag_name("credit"))
+        .filter(|credit| matches!(credit.attribute("page"), None | Some("1")))
+        .flat_map(|credit| {
+            let credit_type = credit
+                .children()
+                .find(|node| node.has_tag_name("credit-type"))
+                .and_then(|node| node.text())
+                .map(|text| text.trim().to_ascii_lowercase());
+            credit
+                .children()
+                .filter(|node| node.has_tag_name("credit-words"))
+                .filter_map(move |words| {
+                    let text = words.text().map(str::trim).filter(|t| !t.is_empty())?;
+                    let number = |attr: &str| {
+                        words
+                            .attribute(attr)
+                            .and_then(|value| value.trim().parse::<f32>().ok())
+                            .unwrap_or(0.0)
+                    };
+                    Some(CreditWords {
+                        text: text.to_string(),
+                        credit_type: credit_type.clone(),
+                        font_size: number("font-size"),
+                        default_y: number("default-y"),
+                        right_aligned: matches!(
+                            words.attribute("justify").or(words.attribute("halign")),
+                            Some("right")
+                        ),
+                    })
+                })
+        })
+        .collect()
+}
+
+fn element_text(doc: &Document, tag: &str) -> Option<String> {
+    doc.descendants()
+        .find(|node| node.has_tag_name(tag))
+        .and_then(|node| node.text())
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+/// `<work-title>`, then `<movement-title>`, then a credit typed as the title, then the
+/// largest (and among equals, top-most) header text, which is how OMR output lays it out.
+fn read_title(doc: &Document, credits: &[CreditWords]) -> Option<String> {
+    element_text(doc, "work-title")
+        .or_else(|| element_text(doc, "movement-title"))
+        .or_else(|| {
+            credits
+                .iter()
+                .find(|credit| credit.credit_type.as_deref() == Some("title"))
+                .or_else(|| {
+                    credits
+                        .iter()
+                        .filter(|credit| credit.credit_type.is_none() && !credit.right_aligned)
+                        .max_by(|a, b| {
+                            a.font_size
+                                .total_cmp(&b.font_size)
+                                .then(a.default_y.total_cmp(&b.default_y))
+                        })
+                })
+                .map(|credit| credit.text.clone())
+        })
+}
+
+/// `<creator type="composer">`, then a credit typed as the composer, then the top-most
+/// right-aligned header text.
+fn read_composer(doc: &Document, credits: &[CreditWords]) -> Option<String> {
+    doc.descendants()
+        .find(|node| node.has_tag_name("creator") && node.attribute("type") == Some("composer"))
+        .and_then(|node| node.text())
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+        .or_else(|| {
+            credits
+                .iter()
+                .find(|credit| credit.credit_type.as_deref() == Some("composer"))
+                .or_else(|| {
+                    credits
+                        .iter()
+                        .filter(|credit| credit.credit_type.is_none() && credit.right_aligned)
+                        .max_by(|a, b| a.default_y.total_cmp(&b.default_y))
+                })
+                .map(|credit| credit.text.clone())
+        })
+}
+
+/// `<score-part id>` to trimmed `<part-name>`, skipping unnamed parts.
+fn read_part_names(doc: &Document) -> HashMap<String, String> {
+    doc.descendants()
+        .filter(|node| node.has_tag_name("score-part"))
+        .filter_map(|node| {
+            let id = node.attribute("id")?;
+            let name = node
+                .children()
+                .find(|child| child.has_tag_name("part-name"))
+                .and_then(|child| child.text())
+                .map(str::trim)
+                .filter(|text| !text.is_empty())?;
+            Some((id.to_string(), name.to_string()))
+        })
+        .collect()
+}
 
-    Ok(score)
+fn select_parts(names: &[String], selection: &PartSelection) -> Vec<usize> {
+    let all = || (0..names.len()).collect::<Vec<_>>();
+    match selection {
+        PartSelection::All => all(),
+        PartSelection::PianoLike => {
+            let piano: Vec<usize> = names
+                .iter()
+                .enumerate()
+                .filter(|(_, name)| {
+                    let name = name.to_ascii_lowercase();
+                    PIANO_PART_KEYWORDS.iter().any(|kw| name.contains(kw))
+                })
+                .map(|(idx, _)| idx)
+                .collect();
+            if piano.is_empty() {
+                all()
+            } else {
+                piano
+            }
+        }
+        PartSelection::Indices(indices) => indices
+            .iter()
+            .copied()
+            .filter(|idx| *idx < names.len())
+            .collect(),
+    }
 }
 
 fn duration_ticks(node: &roxmltree::Node, divisions: i64, ppq: u16) -> Tick {
@@ -470,40 +1340,117 @@ fn parse_ties(node: &roxmltree::Node) -> (bool, bool) {
     (tie_start, tie_stop)
 }
 
+/// Apply a note's `<slur>` stops and starts to the slurs open in its voice and report whether
+/// any is still open after it. Slurs are told apart by `number` so chained slurs, where one
+/// stops and the next starts on the same note, stay connected.
+fn update_open_slurs(open: &mut HashSet<String>, node: &roxmltree::Node) -> bool {
+    let slurs: Vec<_> = node
+        .children()
+        .filter(|child| child.is_element() && child.has_tag_name("notations"))
+        .flat_map(|notations| notations.children())
+        .filter(|child| child.is_element() && child.has_tag_name("slur"))
+        .collect();
+    for slur in &slurs {
+        if slur.attribute("type").unwrap_or("").trim() == "stop" {
+            open.remove(slur.attribute("number").unwrap_or("1").trim());
+        }
+    }
+    for slur in &slurs {
+        if slur.attribute("type").unwrap_or("").trim() == "start" {
+            open.insert(slur.attribute("number").unwrap_or("1").trim().to_string());
+        }
+    }
+    !open.is_empty()
+}
+
+/// The `<measure-style>` marks that change how many bars a measure stands for.
+#[derive(Default)]
+struct MeasureStyle {
+    /// Bars covered by a `<multiple-rest>` starting here.
+    multiple_rest: Option<u32>,
+    /// Bars in the pattern of a `<measure-repeat>` starting here.
+    repeat_start: Option<u32>,
+    repeat_stop: bool,
+}
+
+fn parse_measure_style(measure: &roxmltree::Node) -> MeasureStyle {
+    let mut style = MeasureStyle::default();
+    for mark in measure
+        .children()
+        .filter(|child| child.is_element() && child.has_tag_name("attributes"))
+        .flat_map(|attributes| attributes.children())
+        .filter(|child| child.is_element() && child.has_tag_name("measure-style"))
+        .flat_map(|measure_style| measure_style.children())
+    {
+        let count = || mark.text().and_then(|text| text.trim().parse::<u32>().ok());
+        if mark.has_tag_name("multiple-rest") {
+            style.multiple_rest = count();
+        } else if mark.has_tag_name("measure-repeat") {
+            match mark.attribute("type").unwrap_or("").trim() {
+                "start" => style.repeat_start = Some(count().unwrap_or(1)),
+                "stop" => style.repeat_stop = true,
+                _ => {}
+            }
+        }
+    }
+    style
+}
+
+fn measure_has_notes(measure: &roxmltree::Node) -> bool {
+    measure
+        .children()
+        .filter(|child| child.is_element() && child.has_tag_name("note"))
+        .any(|note| !note.children().any(|child| child.has_tag_name("rest")))
+}
+
 fn parse_note(node: &roxmltree::Node) -> Option<u8> {
+    parse_pitch(node)?.midi_note()
+}
+
+fn parse_pitch(node: &roxmltree::Node) -> Option<SpelledPitch> {
     let pitch = node.children().find(|child| child.has_tag_name("pitch"))?;
     let step = pitch
         .children()
         .find(|child| child.has_tag_name("step"))
-        .and_then(|child| child.text())?;
+        .and_then(|child| child.text())
+        .and_then(Step::parse)?;
     let octave = pitch
         .children()
         .find(|child| child.has_tag_name("octave"))
         .and_then(|child| child.text())
-        .and_then(|text| text.parse::<i32>().ok())?;
+        .and_then(|text| text.parse::<i8>().ok())?;
     let alter = pitch
         .children()
         .find(|child| child.has_tag_name("alter"))
         .and_then(|child| child.text())
-        .and_then(|text| text.parse::<i32>().ok())
+        .and_then(|text| text.parse::<i8>().ok())
         .unwrap_or(0);
+    Some(SpelledPitch {
+        step,
+        alter,
+        octave,
+    })
+}
 
-    let base = match step {
-        "C" => 0,
-        "D" => 2,
-        "E" => 4,
-        "F" => 5,
-        "G" => 7,
-        "A" => 9,
-        "B" => 11,
-        _ => return None,
+/// Written-to-sounding offset in semitones from `<transpose>` in an attributes block.
+fn parse_transpose(attributes: &roxmltree::Node) -> Option<i32> {
+    let transpose = attributes
+        .children()
+        .find(|node| node.is_element() && node.has_tag_name("transpose"))?;
+    let value = |tag: &str| {
+        transpose
+            .children()
+            .find(|node| node.has_tag_name(tag))
+            .and_then(|node| node.text())
+            .and_then(|text| text.trim().parse::<i32>().ok())
+            .unwrap_or(0)
     };
+    // Any wider shift leaves the MIDI range anyway; the bound lets notes carry it as an i8.
+    Some((value("chromatic") + 12 * value("octave-change")).clamp(-127, 127))
+}
 
-    let midi_note = (octave + 1) * 12 + base + alter;
-    if !(0..=127).contains(&midi_note) {
-        return None;
-    }
-    Some(midi_note as u8)
+fn transpose_note(note: u8, semitones: i32) -> u8 {
+    (note as i32 + semitones).clamp(0, 127) as u8
 }
 
 fn parse_hand(node: &roxmltree::Node) -> Option<Hand> {
@@ -519,6 +1466,149 @@ fn parse_hand(node: &roxmltree::Node) -> Option<Hand> {
     }
 }
 
+fn parse_arpeggiate(node: &roxmltree::Node) -> Option<ArpeggioDirection> {
+    let mark = node
+        .children()
+        .filter(|child| child.is_element() && child.has_tag_name("notations"))
+        .flat_map(|notations| notations.children())
+        .find(|child| child.is_element() && child.has_tag_name("arpeggiate"))?;
+    match mark.attribute("direction") {
+        Some("down") => Some(ArpeggioDirection::Down),
+        _ => Some(ArpeggioDirection::Up),
+    }
+}
+
+/// NoteOn delay per note for rolled chords. Arpeggiated notes sharing a tick and hand roll
+/// in pitch order; offsets stay inside each note so NoteOffs keep their written timing.
+fn arpeggio_offsets(
+    note_events: &[NoteEvent],
+    ppq: u16,
+    tempo_map: &[TempoPoint],
+    options: &MusicXmlImportOptions,
+) -> Vec<Tick> {
+    let mut offsets = vec![0; note_events.len()];
+    let mut chords: HashMap<(Tick, Option<Hand>), Vec<usize>> = HashMap::new();
+    for (idx, event) in note_events.iter().enumerate() {
+        if event.arpeggio.is_some() {
+            chords
+                .entry((event.tick, event.hand))
+                .or_default()
+                .push(idx);
+        }
+    }
+
+    for ((tick, _), mut indices) in chords {
+        let us_per_quarter = tempo_map
+            .iter()
+            .take_while(|point| point.tick <= tick)
+            .last()
+            .map_or(500_000, |point| point.us_per_quarter);
+        let step = (options.arpeggio_note_ms as f64 * 1000.0 * ppq as f64 / us_per_quarter as f64)
+            .round() as Tick;
+        indices.sort_by_key(|idx| note_events[*idx].note);
+        if note_events[indices[0]].arpeggio == Some(ArpeggioDirection::Down) {
+            indices.reverse();
+        }
+        for (position, idx) in indices.into_iter().enumerate() {
+            let latest = (note_events[idx].duration_ticks - 1).max(0);
+            offsets[idx] = (position as Tick * step).min(latest);
+        }
+    }
+    offsets
+}
+
+fn has_fermata(node: &roxmltree::Node) -> bool {
+    node.children()
+        .filter(|child| child.is_element() && child.has_tag_name("notations"))
+        .any(|notations| {
+            notations
+                .children()
+                .any(|child| child.has_tag_name("fermata"))
+        })
+}
+
+/// Slow the tempo across each fermata span and restore it afterwards. Overlapping spans
+/// (the same fermata written in several parts or on every chord note) are merged first so
+/// the stretch is applied once.
+fn apply_fermatas(
+    tempo_points: &mut BTreeMap<Tick, u32>,
+    mut spans: Vec<(Tick, Tick)>,
+    multiplier: f32,
+) {
+    if spans.is_empty() || !(multiplier.is_finite() && multiplier > 0.0) {
+        return;
+    }
+    spans.sort_unstable();
+    let mut merged: Vec<(Tick, Tick)> = Vec::new();
+    for (start, end) in spans {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+
+    let original = tempo_points.clone();
+    let tempo_at = |tick: Tick| {
+        original
+            .range(..=tick)
+            .next_back()
+            .map_or(500_000, |(_, us)| *us)
+    };
+    let stretch = |us: u32| ((us as f64 * multiplier as f64).round() as u32).min(0xFF_FFFF);
+    for (start, end) in merged {
+        tempo_points.insert(end, tempo_at(end));
+        tempo_points.insert(start, stretch(tempo_at(start)));
+        for (_, us) in tempo_points.range_mut(start + 1..end) {
+            *us = stretch(*us);
+        }
+    }
+}
+
+fn parse_voice(node: &roxmltree::Node) -> Option<String> {
+    node.children()
+        .find(|child| child.has_tag_name("voice"))
+        .and_then(|child| child.text())
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+/// Give every note of a voice the same hand so cross-staff passages don't flip hands
+/// mid-phrase. A voice keeps its established hand while it dips into the other staff, and
+/// only moves when it stays on one staff for the whole measure.
+fn assign_voice_hands(measure_notes: &mut [NoteEvent], voice_hands: &mut HashMap<String, Hand>) {
+    let mut staffs: HashMap<String, (usize, usize, Hand)> = HashMap::new();
+    for event in measure_notes.iter() {
+        let (Some(voice), Some(hand)) = (event.voice.as_ref(), event.hand) else {
+            continue;
+        };
+        let entry = staffs.entry(voice.clone()).or_insert((0, 0, hand));
+        match hand {
+            Hand::Right => entry.0 += 1,
+            Hand::Left => entry.1 += 1,
+        }
+    }
+
+    for (voice, (right, left, first)) in staffs {
+        let hand = match (right, left) {
+            (_, 0) => Hand::Right,
+            (0, _) => Hand::Left,
+            _ => match voice_hands.get(&voice) {
+                Some(hand) => *hand,
+                None if right > left => Hand::Right,
+                None if left > right => Hand::Left,
+                None => first,
+            },
+        };
+        voice_hands.insert(voice.clone(), hand);
+        for event in measure_notes
+            .iter_mut()
+            .filter(|event| event.hand.is_some() && event.voice.as_ref() == Some(&voice))
+        {
+            event.hand = Some(hand);
+        }
+    }
+}
+
 fn build_tempo_map(tempo_points: BTreeMap<Tick, u32>) -> Vec<TempoPoint> {
     let mut map: Vec<TempoPoint> = tempo_points
         .into_iter()
@@ -542,35 +1632,290 @@ fn build_tempo_map(tempo_points: BTreeMap<Tick, u32>) -> Vec<TempoPoint> {
     map
 }
 
+fn build_time_signatures(points: BTreeMap<Tick, (i64, i64)>) -> Vec<TimeSignaturePoint> {
+    let mut out: Vec<TimeSignaturePoint> = Vec::new();
+    for (tick, (beats, beat_type)) in points {
+        let (Ok(numerator), Ok(denominator)) = (u8::try_from(beats), u8::try_from(beat_type))
+        else {
+            continue;
+        };
+        if out
+            .last()
+            .is_some_and(|prev| prev.numerator == numerator && prev.denominator == denominator)
+        {
+            continue;
+        }
+        out.push(TimeSignaturePoint {
+            tick,
+            numerator,
+            denominator,
+        });
+    }
+    out
+}
+
+fn dropped_graces_warning(
+    part_index: usize,
+    measure_index: u32,
+    graces: &[GraceNote],
+) -> ImportWarning {
+    let count: usize = graces.iter().map(|grace| grace.notes.len()).sum();
+    ImportWarning::MusicXml {
+        part_index,
+        measure_index,
+        kind: MusicXmlWarningKind::DroppedGraceNote,
+        detail: format!("dropped {count} grace note(s) with no following note"),
+    }
+}
+
+fn build_key_signatures(points: BTreeMap<Tick, (i8, KeyMode)>) -> Vec<KeySignaturePoint> {
+    let mut out: Vec<KeySignaturePoint> = Vec::new();
+    for (tick, (fifths, mode)) in points {
+        if out
+            .last()
+            .is_some_and(|prev| prev.fifths == fifths && prev.mode == mode)
+        {
+            continue;
+        }
+        out.push(KeySignaturePoint { tick, fifths, mode });
+    }
+    out
+}
+
+/// `<key><fifths>` and `<mode>`; modes other than minor/aeolian are treated as major.
+fn parse_key(attributes: &roxmltree::Node) -> Option<(i32, KeyMode)> {
+    let key = attributes
+        .children()
+        .find(|node| node.has_tag_name("key"))?;
+    let fifths = key
+        .children()
+        .find(|node| node.has_tag_name("fifths"))
+        .and_then(|node| node.text())
+        .and_then(|text| text.trim().parse::<i32>().ok())?;
+    let mode = match key
+        .children()
+        .find(|node| node.has_tag_name("mode"))
+        .and_then(|node| node.text())
+        .map(|text| text.trim().to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("minor" | "aeolian") => KeyMode::Minor,
+        _ => KeyMode::Major,
+    };
+    Some((fifths, mode))
+}
+
 fn build_targets(note_events: &[NoteEvent]) -> Vec<TargetEvent> {
     let mut grouped: BTreeMap<Tick, TargetGroup> = BTreeMap::new();
     for event in note_events {
         let entry = grouped
             .entry(event.tick)
-            .or_insert_with(|| (Vec::new(), event.measure_index));
+            .or_insert_with(|| (Vec::new(), event.measure_index, true));
         entry.0.push((event.note, event.hand));
+        entry.2 &= event.optional;
     }
 
     let mut targets = Vec::new();
-    let mut next_id = 1u64;
-    for (tick, (notes, measure_index)) in grouped {
+    for (idx, (tick, (notes, measure_index, optional))) in grouped.into_iter().enumerate() {
         let mut unique_notes: Vec<u8> = notes.iter().map(|(note, _)| *note).collect();
         unique_notes.sort_unstable();
         unique_notes.dedup();
 
         let hand = resolve_hand(&notes);
         targets.push(TargetEvent {
-            id: next_id,
+            id: idx as u64 + 1,
             tick,
             notes: unique_notes,
             hand,
             measure_index,
+            optional,
+            note_ids: Vec::new(),
+            note_tracks: Vec::new(),
         });
-        next_id += 1;
     }
     targets
 }
 
+/// Lay out a grace group around its principal note, delaying the principal when the graces
+/// are played on the beat. Unslashed graces take their time from the principal; slashed
+/// graces take it from the preceding note in the same hand so the principal stays on the beat.
+fn place_grace_notes(
+    note_events: &mut Vec<NoteEvent>,
+    placement: PendingGraces,
+    grace_note_ticks: Tick,
+    measure_index: u32,
+) {
+    let PendingGraces {
+        graces,
+        principal_tick,
+        principal_duration,
+        velocity,
+        principal,
+        ..
+    } = placement;
+    let count = graces.len() as Tick;
+    let each = (grace_note_ticks.min(principal_duration / 2) / count).max(1);
+    let total = each * count;
+
+    let slashed = graces.iter().any(|grace| grace.slash);
+    // The principal's hand once its voice is settled; graces follow it across staves.
+    let hand = principal
+        .first()
+        .map_or(graces[0].hand, |&idx| note_events[idx].hand);
+    let before_beat = slashed && principal_tick >= total && {
+        let start = principal_tick - total;
+        let mut stolen = false;
+        for event in note_events.iter_mut().filter(|event| {
+            event.hand == hand
+                && !event.optional
+                && event.tick < start
+                && event.tick + event.duration_ticks == principal_tick
+        }) {
+            event.duration_ticks = start - event.tick;
+            stolen = true;
+        }
+        stolen
+    };
+    let start = if before_beat {
+        principal_tick - total
+    } else {
+        // On the beat the graces delay the principal and its chord.
+        for &idx in &principal {
+            let event = &mut note_events[idx];
+            event.tick += total;
+            event.duration_ticks = (event.duration_ticks - total).max(1);
+        }
+        principal_tick
+    };
+
+    for (idx, grace) in graces.into_iter().enumerate() {
+        for note in grace.notes {
+            note_events.push(NoteEvent {
+                tick: start + idx as Tick * each,
+                duration_ticks: each,
+                note,
+                velocity,
+                hand: hand.or(grace.hand),
+                measure_index: Some(measure_index),
+                articulations: Vec::new(),
+                optional: true,
+                ornament: None,
+                arpeggio: None,
+                voice: None,
+                slurred: false,
+                spelling: None,
+                transpose: grace.transpose,
+            });
+        }
+    }
+}
+
+fn parse_articulations(node: &roxmltree::Node) -> Vec<Articulation> {
+    node.children()
+        .filter(|child| child.is_element() && child.has_tag_name("notations"))
+        .flat_map(|notations| notations.children())
+        .filter(|child| child.is_element() && child.has_tag_name("articulations"))
+        .flat_map(|articulations| articulations.children())
+        .filter_map(|mark| match mark.tag_name().name() {
+            "staccato" => Some(Articulation::Staccato),
+            "staccatissimo" | "spiccato" => Some(Articulation::Staccatissimo),
+            "tenuto" => Some(Articulation::Tenuto),
+            "accent" => Some(Articulation::Accent),
+            "strong-accent" => Some(Articulation::StrongAccent),
+            "breath-mark" => Some(Articulation::BreathMark),
+            _ => None,
+        })
+        .collect()
+}
+
+fn accented_velocity(velocity: u8, articulations: &[Articulation], boost: u8) -> u8 {
+    let boost = articulations
+        .iter()
+        .map(|articulation| match articulation {
+            Articulation::Accent => boost,
+            Articulation::StrongAccent => boost.saturating_mul(2),
+            _ => 0,
+        })
+        .max()
+        .unwrap_or(0);
+    velocity.saturating_add(boost).min(127)
+}
+
+/// Shorten sounding lengths for staccato marks and leave a gap before breath marks. Runs
+/// after ties are merged so a tied staccato note is shortened by its whole written length.
+fn apply_articulation_lengths(note_events: &mut [NoteEvent], breath_gap_ticks: Tick) {
+    for event in note_events {
+        let percent = event
+            .articulations
+            .iter()
+            .map(|articulation| match articulation {
+                Articulation::Staccatissimo => 25,
+                Articulation::Staccato => 50,
+                _ => 100,
+            })
+            .min()
+            .unwrap_or(100);
+        event.duration_ticks = (event.duration_ticks * percent / 100).max(1);
+        if event.articulations.contains(&Articulation::BreathMark) {
+            event.duration_ticks = (event.duration_ticks - breath_gap_ticks).max(1);
+        }
+    }
+}
+
+/// Hold each slurred note past the next attack in its voice so the line sounds connected.
+/// Notes shortened by their articulation stay detached, and a note is not carried into a
+/// repeat of its own pitch; that rearticulation keeps its gap.
+fn apply_slur_overlaps(note_events: &mut [NoteEvent], overlap_percent: u32) {
+    let mut voices: HashMap<(Option<&str>, Option<Hand>), Vec<usize>> = HashMap::new();
+    for (idx, event) in note_events.iter().enumerate() {
+        if !event.optional {
+            voices
+                .entry((event.voice.as_deref(), event.hand))
+                .or_default()
+                .push(idx);
+        }
+    }
+
+    let mut extended = Vec::new();
+    for indices in voices.values_mut() {
+        indices.sort_by_key(|idx| note_events[*idx].tick);
+        for &idx in indices.iter() {
+            let event = &note_events[idx];
+            if !event.slurred {
+                continue;
+            }
+            let Some(next_tick) = indices
+                .iter()
+                .map(|next| note_events[*next].tick)
+                .find(|tick| *tick > event.tick)
+            else {
+                continue;
+            };
+            let mut repeats = false;
+            let mut shortest_next = Tick::MAX;
+            for next in indices
+                .iter()
+                .map(|next| &note_events[*next])
+                .filter(|next| next.tick == next_tick)
+            {
+                repeats |= next.note == event.note;
+                shortest_next = shortest_next.min(next.duration_ticks);
+            }
+            let gap = next_tick - event.tick;
+            if repeats || event.duration_ticks < gap {
+                continue;
+            }
+            let overlap = gap.min(shortest_next) * Tick::from(overlap_percent) / 100;
+            extended.push((idx, event.duration_ticks.max(gap + overlap)));
+        }
+    }
+    for (idx, duration_ticks) in extended {
+        note_events[idx].duration_ticks = duration_ticks;
+    }
+}
+
+/// Works on sounding lengths, so a note already shortened by its articulation only gets a
+/// gap when it still overlaps the next attack of the same key.
 fn apply_rearticulation_gaps(note_events: &mut [NoteEvent]) {
     let mut groups: HashMap<(u8, Option<Hand>), Vec<usize>> = HashMap::new();
     for (idx, event) in note_events.iter().enumerate() {
@@ -601,9 +1946,12 @@ fn apply_rearticulation_gaps(note_events: &mut [NoteEvent]) {
 
 fn build_playback_events(
     note_events: &[NoteEvent],
+    on_offsets: &[Tick],
     cc64_events: &[PlaybackMidiEvent],
+    ppq: u16,
+    options: &MusicXmlImportOptions,
 ) -> Vec<PlaybackMidiEvent> {
-    let mut events = build_note_playback_events(note_events);
+    let mut events = build_note_playback_events(note_events, on_offsets, ppq, options);
     events.extend(cc64_events.iter().cloned());
     events.sort_by(|a, b| {
         a.tick
@@ -614,26 +1962,199 @@ fn build_playback_events(
     events
 }
 
-fn build_note_playback_events(note_events: &[NoteEvent]) -> Vec<PlaybackMidiEvent> {
+fn build_note_playback_events(
+    note_events: &[NoteEvent],
+    on_offsets: &[Tick],
+    ppq: u16,
+    options: &MusicXmlImportOptions,
+) -> Vec<PlaybackMidiEvent> {
     let mut events = Vec::new();
-    for event in note_events {
+    // Pairs each note off with its note on until `Score::assign_note_ids` numbers them, so
+    // voices sharing a key keep their own lengths.
+    let mut pair = 0;
+    let mut next_pair = || {
+        pair += 1;
+        Some(NoteId(pair))
+    };
+    for (event, on_offset) in note_events.iter().zip(on_offsets) {
+        let expansion = event
+            .ornament
+            .filter(|_| options.expand_ornaments)
+            .map(|ornament| expand_ornament(event, ornament, ppq, options.trill_notes_per_quarter))
+            .unwrap_or_default();
+        if !expansion.is_empty() {
+            for (idx, (tick, duration, note)) in expansion.into_iter().enumerate() {
+                let note_id = next_pair();
+                events.push(PlaybackMidiEvent {
+                    tick,
+                    event: MidiLikeEvent::NoteOn {
+                        note,
+                        velocity: event.velocity.max(1),
+                    },
+                    hand: event.hand,
+                    ornament_of: Some(event.note),
+                    bus: None,
+                    note_id,
+                    spelling: None,
+                    transpose: event.transpose,
+                    // The figure starts the written note, so its first note keeps the marks.
+                    articulations: if idx == 0 {
+                        event.articulations.clone()
+                    } else {
+                        Vec::new()
+                    },
+                });
+                events.push(PlaybackMidiEvent {
+                    tick: tick + duration,
+                    event: MidiLikeEvent::NoteOff { note },
+                    hand: event.hand,
+                    ornament_of: Some(event.note),
+                    bus: None,
+                    note_id,
+                    spelling: None,
+                    transpose: 0,
+                    articulations: Vec::new(),
+                });
+            }
+            continue;
+        }
+
+        let note_id = next_pair();
         events.push(PlaybackMidiEvent {
-            tick: event.tick,
+            tick: event.tick + on_offset,
             event: MidiLikeEvent::NoteOn {
                 note: event.note,
                 velocity: event.velocity.max(1),
             },
             hand: event.hand,
+            ornament_of: None,
+            bus: None,
+            note_id,
+            spelling: event.spelling,
+            transpose: event.transpose,
+            articulations: event.articulations.clone(),
         });
         events.push(PlaybackMidiEvent {
             tick: event.tick + event.duration_ticks,
             event: MidiLikeEvent::NoteOff { note: event.note },
             hand: event.hand,
+            ornament_of: None,
+            bus: None,
+            note_id,
+            spelling: None,
+            transpose: 0,
+            articulations: Vec::new(),
         });
     }
     events
 }
 
+/// Split an ornamented note into `(tick, duration, note)` parts spanning its sounding length.
+/// Returns nothing when the note is too short to hold the figure.
+fn expand_ornament(
+    event: &NoteEvent,
+    ornament: Ornament,
+    ppq: u16,
+    trill_notes_per_quarter: u32,
+) -> Vec<(Tick, Tick, u8)> {
+    let principal = event.note;
+    let (part_ticks, pitches): (Tick, Vec<u8>) = match ornament {
+        Ornament::Trill { upper } => {
+            let part = (ppq as Tick / trill_notes_per_quarter.max(1) as Tick).max(1);
+            let count = event.duration_ticks / part;
+            let pitches = (0..count)
+                .map(|idx| if idx % 2 == 0 { principal } else { upper })
+                .collect();
+            (part, pitches)
+        }
+        Ornament::Turn {
+            upper,
+            lower,
+            inverted,
+        } => {
+            let figure = if inverted {
+                vec![lower, principal, upper, principal]
+            } else {
+                vec![upper, principal, lower, principal]
+            };
+            (event.duration_ticks / 4, figure)
+        }
+        Ornament::Tremolo { beams } => {
+            let part = (ppq as Tick >> beams.min(8)).max(1);
+            let count = event.duration_ticks / part;
+            (part, vec![principal; count as usize])
+        }
+    };
+    if pitches.len() < 2 || part_ticks <= 0 {
+        return Vec::new();
+    }
+
+    let last = pitches.len() - 1;
+    pitches
+        .into_iter()
+        .enumerate()
+        .map(|(idx, note)| {
+            let tick = event.tick + idx as Tick * part_ticks;
+            // The last part absorbs the remainder so the figure ends with the written note.
+            let duration = if idx == last {
+                event.tick + event.duration_ticks - tick
+            } else {
+                part_ticks
+            };
+            (tick, duration, note)
+        })
+        .collect()
+}
+
+/// The ornament on a note written as `written` in `key_fifths`. Neighbours are taken from
+/// the written key, then moved by `transpose` like the note itself.
+fn parse_ornament(
+    node: &roxmltree::Node,
+    written: u8,
+    key_fifths: i32,
+    transpose: i32,
+) -> Option<Ornament> {
+    let neighbor = |up| transpose_note(diatonic_neighbor(written, key_fifths, up), transpose);
+    let ornaments = node
+        .children()
+        .filter(|child| child.is_element() && child.has_tag_name("notations"))
+        .flat_map(|notations| notations.children())
+        .find(|child| child.is_element() && child.has_tag_name("ornaments"))?;
+    ornaments
+        .children()
+        .filter(|mark| mark.is_element())
+        .find_map(|mark| match mark.tag_name().name() {
+            "trill-mark" => Some(Ornament::Trill {
+                upper: neighbor(true),
+            }),
+            "turn" | "inverted-turn" => Some(Ornament::Turn {
+                upper: neighbor(true),
+                lower: neighbor(false),
+                inverted: mark.has_tag_name("inverted-turn"),
+            }),
+            "tremolo" if matches!(mark.attribute("type"), None | Some("single")) => {
+                let beams = mark
+                    .text()
+                    .and_then(|text| text.trim().parse::<u32>().ok())
+                    .unwrap_or(3);
+                Some(Ornament::Tremolo { beams })
+            }
+            _ => None,
+        })
+}
+
+/// Next note of the major scale for `key_fifths` above or below `note`.
+fn diatonic_neighbor(note: u8, key_fifths: i32, up: bool) -> u8 {
+    const MAJOR_SCALE: [i32; 7] = [0, 2, 4, 5, 7, 9, 11];
+    let tonic = (key_fifths * 7).rem_euclid(12);
+    let in_key = |pitch: i32| MAJOR_SCALE.contains(&(pitch - tonic).rem_euclid(12));
+    let direction = if up { 1 } else { -1 };
+    let step = (1..=2)
+        .find(|step| in_key(note as i32 + direction * step))
+        .unwrap_or(2);
+    (note as i32 + direction * step).clamp(0, 127) as u8
+}
+
 fn event_rank(event: &MidiLikeEvent) -> u8 {
     match event {
         MidiLikeEvent::Cc64 { value } => {
@@ -643,6 +2164,7 @@ fn event_rank(event: &MidiLikeEvent) -> u8 {
                 3
             }
         }
+        MidiLikeEvent::ControlChange { .. } => 0,
         MidiLikeEvent::NoteOff { .. } => 1,
         MidiLikeEvent::NoteOn { .. } => 2,
     }
@@ -652,7 +2174,7 @@ fn event_note_key(event: &MidiLikeEvent) -> u8 {
     match event {
         MidiLikeEvent::NoteOn { note, .. } => *note,
         MidiLikeEvent::NoteOff { note } => *note,
-        MidiLikeEvent::Cc64 { .. } => 0,
+        MidiLikeEvent::Cc64 { .. } | MidiLikeEvent::ControlChange { .. } => 0,
     }
 }
 
@@ -698,6 +2220,22 @@ fn dynamics_tag_velocity(tag: &str) -> Option<u8> {
     Some(vel)
 }
 
+/// Names of the marks in a `<dynamics>` that [`parse_dynamics_mark`] could not map.
+fn unknown_dynamics(direction_type: &roxmltree::Node) -> Option<String> {
+    let dynamics = direction_type
+        .children()
+        .find(|node| node.is_element() && node.has_tag_name("dynamics"))?;
+    let marks: Vec<&str> = dynamics
+        .children()
+        .filter(|node| node.is_element())
+        .map(|child| match child.tag_name().name() {
+            "other-dynamics" => child.text().map(str::trim).unwrap_or(""),
+            name => name,
+        })
+        .collect();
+    (!marks.is_empty()).then(|| marks.join(" "))
+}
+
 fn parse_dynamics_words(direction_type: &roxmltree::Node) -> Option<u8> {
     for words in direction_type
         .children()
@@ -717,6 +2255,86 @@ fn parse_dynamics_words(direction_type: &roxmltree::Node) -> Option<u8> {
     None
 }
 
+/// `<metronome>` with a beat unit (optionally dotted) and a per-minute count.
+fn parse_metronome(direction_type: &roxmltree::Node) -> Option<u32> {
+    let metronome = direction_type
+        .children()
+        .find(|node| node.is_element() && node.has_tag_name("metronome"))?;
+    let unit = metronome
+        .children()
+        .find(|node| node.has_tag_name("beat-unit"))
+        .and_then(|node| node.text())?;
+    let mut quarters = match unit.trim() {
+        "breve" => 8.0,
+        "whole" => 4.0,
+        "half" => 2.0,
+        "quarter" => 1.0,
+        "eighth" => 0.5,
+        "16th" => 0.25,
+        "32nd" => 0.125,
+        _ => return None,
+    };
+    let dots = metronome
+        .children()
+        .filter(|node| node.has_tag_name("beat-unit-dot"))
+        .count();
+    let mut add = quarters / 2.0;
+    for _ in 0..dots {
+        quarters += add;
+        add /= 2.0;
+    }
+
+    // Per-minute text may carry extras such as "c. 96" or "96-104"; take the first number.
+    let per_minute = metronome
+        .children()
+        .find(|node| node.has_tag_name("per-minute"))
+        .and_then(|node| node.text())?;
+    let digits: String = per_minute
+        .trim_start_matches(|c: char| !c.is_ascii_digit())
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    let bpm = digits.parse::<f64>().ok().filter(|bpm| *bpm > 0.0)?;
+    Some((60_000_000.0 / (bpm * quarters)) as u32)
+}
+
+fn parse_tempo_words(direction_type: &roxmltree::Node) -> Option<u32> {
+    for words in direction_type
+        .children()
+        .filter(|node| node.is_element() && node.has_tag_name("words"))
+    {
+        let Some(text) = words.text() else {
+            continue;
+        };
+        let lower = text.to_lowercase();
+        for token in lower.split(|c: char| !c.is_alphabetic()) {
+            if let Some(bpm) = tempo_word_bpm(token) {
+                return Some(60_000_000 / bpm);
+            }
+        }
+    }
+    None
+}
+
+fn tempo_word_bpm(word: &str) -> Option<u32> {
+    match word {
+        "grave" => Some(40),
+        "largo" => Some(50),
+        "lento" => Some(52),
+        "larghetto" => Some(63),
+        "adagio" => Some(70),
+        "andante" => Some(88),
+        "andantino" => Some(92),
+        "moderato" => Some(108),
+        "allegretto" => Some(116),
+        "allegro" => Some(132),
+        "vivace" => Some(160),
+        "presto" => Some(176),
+        "prestissimo" => Some(200),
+        _ => None,
+    }
+}
+
 fn parse_pedal_words(direction_type: &roxmltree::Node, pedal_down: bool) -> Option<bool> {
     for words in direction_type
         .children()
@@ -802,6 +2420,12 @@ fn emit_cc64_change(
             value: if down { 127 } else { 0 },
         },
         hand: None,
+        ornament_of: None,
+        bus: None,
+        note_id: None,
+        spelling: None,
+        transpose: 0,
+        articulations: Vec::new(),
     });
 }
 
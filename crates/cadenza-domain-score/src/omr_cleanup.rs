@@ -0,0 +1,225 @@
+use crate::model::{NoteId, Score, ScoreSource};
+use crate::musicxml_import::{
+    import_musicxml_path_with_report, MusicXmlImport, MusicXmlImportError, MusicXmlImportOptions,
+};
+use crate::pitch::{key_signature_at, spell};
+use crate::warnings::{ImportWarning, OmrCleanupKind};
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::types::Tick;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+/// Tuning for [`cleanup_omr_score`].
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OmrCleanupOptions {
+    /// A note shorter than this with nothing else starting at its tick is taken for a
+    /// misread dot or slur.
+    pub short_note_ticks: Tick,
+    /// Keep such notes as optional targets instead of dropping them. The judge never
+    /// requires an optional target, so skipping one is no miss.
+    pub mark_optional: bool,
+    /// Note-on velocities are clamped to this range.
+    pub min_velocity: u8,
+    pub max_velocity: u8,
+}
+
+impl Default for OmrCleanupOptions {
+    fn default() -> Self {
+        Self {
+            // Shorter than a 32nd at the importer's fixed 480 PPQ.
+            short_note_ticks: 60,
+            mark_optional: false,
+            min_velocity: 24,
+            max_velocity: 112,
+        }
+    }
+}
+
+/// Imports MusicXML an OMR engine read from `pdf_path`. The score is tagged
+/// [`ScoreSource::PdfOmr`] with the PDF as its origin and, given `cleanup`, tidied by
+/// [`cleanup_omr_score`] with its changes added to the warnings.
+pub fn import_omr_musicxml_path(
+    path: &Path,
+    pdf_path: &Path,
+    cleanup: Option<&OmrCleanupOptions>,
+) -> Result<MusicXmlImport, MusicXmlImportError> {
+    let mut import = import_musicxml_path_with_report(path, &MusicXmlImportOptions::default())?;
+    import.score.meta.source = ScoreSource::PdfOmr;
+    import.score.meta.origin_path = Some(pdf_path.to_path_buf());
+    if let Some(options) = cleanup {
+        let changes = cleanup_omr_score(&mut import.score, options);
+        import.warnings.extend(changes);
+    }
+    Ok(import)
+}
+
+/// Tidies a score read by OMR; other scores are left alone. Per track, duplicate notes of
+/// one pitch at one tick become the longest of them, short isolated notes are dropped or
+/// made optional, and velocities are clamped. Ornament notes, notes routed to a fixed bus
+/// and notes of targets that are already optional, such as grace notes, are kept as they
+/// are. Ties are already joined by the importer, so a tied note is never short.
+///
+/// Returns one warning per change.
+pub fn cleanup_omr_score(score: &mut Score, options: &OmrCleanupOptions) -> Vec<ImportWarning> {
+    if !matches!(score.meta.source, ScoreSource::PdfOmr) {
+        return Vec::new();
+    }
+    let mut warnings = Vec::new();
+    for (track_index, track) in score.tracks.iter_mut().enumerate() {
+        let measures: HashMap<Tick, u32> = track
+            .targets
+            .iter()
+            .filter_map(|target| Some((target.tick, target.measure_index?)))
+            .collect();
+        let optional: HashSet<(Tick, u8)> = track
+            .targets
+            .iter()
+            .filter(|target| target.optional)
+            .flat_map(|target| target.notes.iter().map(move |&note| (target.tick, note)))
+            .collect();
+        let keys = &score.key_signatures;
+        let note_name =
+            |tick: Tick, note: u8| spell(note, key_signature_at(keys, tick)).to_string();
+        let mut warn = |tick: Tick, kind: OmrCleanupKind, detail: String| {
+            warnings.push(ImportWarning::OmrCleanup {
+                track_index,
+                tick,
+                measure_index: measures.get(&tick).copied(),
+                kind,
+                detail,
+            });
+        };
+
+        let mut ends: HashMap<NoteId, (usize, Tick)> = HashMap::new();
+        for (idx, event) in track.playback_events.iter().enumerate() {
+            if let (MidiLikeEvent::NoteOff { .. }, Some(id)) = (event.event, event.note_id) {
+                ends.insert(id, (idx, event.tick));
+            }
+        }
+        let mut onsets: BTreeMap<(Tick, u8), Vec<WrittenNote>> = BTreeMap::new();
+        for (idx, event) in track.playback_events.iter().enumerate() {
+            let MidiLikeEvent::NoteOn { note, velocity } = event.event else {
+                continue;
+            };
+            if velocity == 0
+                || event.ornament_of.is_some()
+                || event.bus.is_some()
+                || optional.contains(&(event.tick, note))
+            {
+                continue;
+            }
+            let end = event.note_id.and_then(|id| ends.get(&id));
+            let length = end.map_or(Tick::MAX, |&(_, end)| end - event.tick);
+            onsets.entry((event.tick, note)).or_default().push((
+                idx,
+                end.map(|&(idx, _)| idx),
+                length,
+            ));
+        }
+
+        let mut removed: HashSet<usize> = HashSet::new();
+        for (&(tick, note), notes) in &mut onsets {
+            if notes.len() < 2 {
+                continue;
+            }
+            notes.sort_by_key(|&(_, _, length)| std::cmp::Reverse(length));
+            for (on, off, _) in notes.drain(1..) {
+                removed.extend(std::iter::once(on).chain(off));
+            }
+            warn(
+                tick,
+                OmrCleanupKind::MergedUnison,
+                format!("duplicate {} merged into one note", note_name(tick, note)),
+            );
+        }
+
+        let mut chords: HashMap<Tick, HashSet<u8>> = HashMap::new();
+        for (idx, event) in track.playback_events.iter().enumerate() {
+            if let MidiLikeEvent::NoteOn { note, velocity } = event.event {
+                if velocity > 0 && !removed.contains(&idx) {
+                    chords.entry(event.tick).or_default().insert(note);
+                }
+            }
+        }
+        let mut pruned: HashSet<(Tick, u8)> = HashSet::new();
+        for (&(tick, note), notes) in &onsets {
+            let (on, off, length) = notes[0];
+            if length >= options.short_note_ticks || chords[&tick].len() > 1 {
+                continue;
+            }
+            pruned.insert((tick, note));
+            if options.mark_optional {
+                warn(
+                    tick,
+                    OmrCleanupKind::OptionalShortNote,
+                    format!("{length}-tick {} made optional", note_name(tick, note)),
+                );
+            } else {
+                removed.extend(std::iter::once(on).chain(off));
+                warn(
+                    tick,
+                    OmrCleanupKind::DroppedShortNote,
+                    format!("isolated {length}-tick {} dropped", note_name(tick, note)),
+                );
+            }
+        }
+
+        for (idx, event) in track.playback_events.iter_mut().enumerate() {
+            let MidiLikeEvent::NoteOn { note, velocity } = &mut event.event else {
+                continue;
+            };
+            if *velocity == 0 || removed.contains(&idx) {
+                continue;
+            }
+            let clamped = (*velocity).clamp(options.min_velocity, options.max_velocity);
+            if clamped != *velocity {
+                warn(
+                    event.tick,
+                    OmrCleanupKind::ClampedVelocity,
+                    format!(
+                        "{} velocity {} clamped to {clamped}",
+                        note_name(event.tick, *note),
+                        *velocity
+                    ),
+                );
+                *velocity = clamped;
+            }
+        }
+
+        let mut idx = 0;
+        track.playback_events.retain(|_| {
+            idx += 1;
+            !removed.contains(&(idx - 1))
+        });
+        for target in &mut track.targets {
+            let keep: Vec<bool> = target
+                .notes
+                .iter()
+                .map(|&note| !pruned.contains(&(target.tick, note)))
+                .collect();
+            if options.mark_optional {
+                target.optional |= keep.iter().any(|keep| !keep);
+                continue;
+            }
+            retain_parallel(&mut target.notes, &keep);
+            retain_parallel(&mut target.note_ids, &keep);
+            retain_parallel(&mut target.note_tracks, &keep);
+        }
+        track.targets.retain(|target| !target.notes.is_empty());
+    }
+    // Targets pointed at whichever duplicate came first.
+    score.assign_note_ids();
+    warnings
+}
+
+/// Index of a note's note on, of its note off, and its length.
+type WrittenNote = (usize, Option<usize>, Tick);
+
+fn retain_parallel<T>(values: &mut Vec<T>, keep: &[bool]) {
+    if values.len() != keep.len() {
+        return;
+    }
+    let mut keep = keep.iter();
+    values.retain(|_| *keep.next().expect("parallel"));
+}
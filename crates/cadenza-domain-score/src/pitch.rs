@@ -0,0 +1,181 @@
+use crate::model::{KeyMode, KeySignaturePoint, PlaybackMidiEvent};
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::types::Tick;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Step {
+    C,
+    D,
+    E,
+    F,
+    G,
+    A,
+    B,
+}
+
+impl Step {
+    const ALL: [Step; 7] = [
+        Step::C,
+        Step::D,
+        Step::E,
+        Step::F,
+        Step::G,
+        Step::A,
+        Step::B,
+    ];
+
+    /// Semitones above C.
+    pub fn semitones(self) -> i32 {
+        match self {
+            Step::C => 0,
+            Step::D => 2,
+            Step::E => 4,
+            Step::F => 5,
+            Step::G => 7,
+            Step::A => 9,
+            Step::B => 11,
+        }
+    }
+
+    /// Place on the line of fifths, F = -1 through B = 5.
+    fn fifths(self) -> i32 {
+        match self {
+            Step::F => -1,
+            Step::C => 0,
+            Step::G => 1,
+            Step::D => 2,
+            Step::A => 3,
+            Step::E => 4,
+            Step::B => 5,
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Step> {
+        match text.trim() {
+            "C" => Some(Step::C),
+            "D" => Some(Step::D),
+            "E" => Some(Step::E),
+            "F" => Some(Step::F),
+            "G" => Some(Step::G),
+            "A" => Some(Step::A),
+            "B" => Some(Step::B),
+            _ => None,
+        }
+    }
+}
+
+/// A pitch as written: letter, accidental and octave, with middle C (MIDI 60) as C4.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SpelledPitch {
+    pub step: Step,
+    /// Sharps when positive, flats when negative.
+    pub alter: i8,
+    pub octave: i8,
+}
+
+impl SpelledPitch {
+    /// The MIDI note this spelling sounds, when it is in range.
+    pub fn midi_note(&self) -> Option<u8> {
+        let note =
+            (i32::from(self.octave) + 1) * 12 + self.step.semitones() + i32::from(self.alter);
+        u8::try_from(note).ok().filter(|note| *note <= 127)
+    }
+}
+
+impl fmt::Display for SpelledPitch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.step)?;
+        let accidental = if self.alter > 0 { "#" } else { "b" };
+        for _ in 0..self.alter.unsigned_abs() {
+            f.write_str(accidental)?;
+        }
+        write!(f, "{}", self.octave)
+    }
+}
+
+/// The key a note is spelled in; C major when a score states none.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeySignature {
+    /// Sharps when positive, flats when negative.
+    pub fifths: i8,
+    pub mode: KeyMode,
+}
+
+impl Default for KeySignature {
+    fn default() -> Self {
+        Self {
+            fifths: 0,
+            mode: KeyMode::Major,
+        }
+    }
+}
+
+impl From<KeySignaturePoint> for KeySignature {
+    fn from(point: KeySignaturePoint) -> Self {
+        Self {
+            fifths: point.fifths,
+            mode: point.mode,
+        }
+    }
+}
+
+/// The key in force at `tick`, given points sorted by tick.
+pub fn key_signature_at(points: &[KeySignaturePoint], tick: Tick) -> KeySignature {
+    points
+        .iter()
+        .take_while(|point| point.tick <= tick)
+        .last()
+        .map(|point| KeySignature::from(*point))
+        .unwrap_or_default()
+}
+
+/// Spell `note` as it would be written in `key`. Notes of the key take its accidentals;
+/// other notes take the spelling closest to the key on the line of fifths, so sharp keys
+/// get sharps and flat keys flats. The tritone from the middle of the key is spelled as
+/// the lowered sixth in major and the raised seventh in minor.
+pub fn spell(note: u8, key: KeySignature) -> SpelledPitch {
+    // Middle of the seven fifths the key's scale occupies.
+    let center = i32::from(key.fifths) + 2;
+    let pitch_class = i32::from(note) % 12;
+    // Fifths position with this pitch class nearest below the middle; 7 is its own inverse
+    // mod 12, so it maps a pitch class back onto the line of fifths.
+    let below = center - (center - pitch_class * 7).rem_euclid(12);
+    let above = below + 12;
+    let position = match (center - below).cmp(&(above - center)) {
+        std::cmp::Ordering::Less => below,
+        std::cmp::Ordering::Greater => above,
+        std::cmp::Ordering::Equal if key.mode == KeyMode::Minor => above,
+        std::cmp::Ordering::Equal => below,
+    };
+    let natural = (position + 1).rem_euclid(7) - 1;
+    let step = Step::ALL
+        .into_iter()
+        .find(|step| step.fifths() == natural)
+        .unwrap_or(Step::C);
+    let alter = (position - natural).div_euclid(7);
+    let octave = (i32::from(note) - step.semitones() - alter).div_euclid(12) - 1;
+    SpelledPitch {
+        step,
+        alter: alter as i8,
+        octave: octave as i8,
+    }
+}
+
+/// The spelling of a note on: as written in the source when the importer kept it and the
+/// note sounds as written, otherwise in the key in force at its tick.
+pub fn note_on_spelling(
+    event: &PlaybackMidiEvent,
+    key_signatures: &[KeySignaturePoint],
+) -> Option<SpelledPitch> {
+    let MidiLikeEvent::NoteOn { note, .. } = event.event else {
+        return None;
+    };
+    Some(
+        event
+            .spelling
+            .filter(|_| event.transpose == 0)
+            .unwrap_or_else(|| spell(note, key_signature_at(key_signatures, event.tick))),
+    )
+}
@@ -0,0 +1,63 @@
+use crate::model::{Score, ScoreFile};
+use std::path::Path;
+
+/// Schema written by this version. Files with a higher number come from a newer Cadenza.
+pub const SCOREFILE_SCHEMA_VERSION: u32 = 1;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ScoreFileError {
+    #[error("io error: {0}")]
+    Io(String),
+    #[error("invalid score file: {0}")]
+    Format(String),
+    #[error("score file schema {found} is newer than the supported {supported}; update Cadenza to open it")]
+    UnsupportedVersion { found: String, supported: u32 },
+}
+
+impl ScoreFile {
+    pub fn new(score: Score, edit_log: Vec<String>) -> Self {
+        Self {
+            schema_version: SCOREFILE_SCHEMA_VERSION.to_string(),
+            score,
+            edit_log,
+            sound_overrides: Default::default(),
+        }
+    }
+}
+
+pub fn save_scorefile_path(file: &ScoreFile, path: &Path) -> Result<(), ScoreFileError> {
+    let json =
+        serde_json::to_vec_pretty(file).map_err(|e| ScoreFileError::Format(e.to_string()))?;
+    std::fs::write(path, json).map_err(|e| ScoreFileError::Io(e.to_string()))
+}
+
+pub fn load_scorefile_path(path: &Path) -> Result<ScoreFile, ScoreFileError> {
+    let bytes = std::fs::read(path).map_err(|e| ScoreFileError::Io(e.to_string()))?;
+    load_scorefile_slice(&bytes)
+}
+
+pub fn load_scorefile_slice(bytes: &[u8]) -> Result<ScoreFile, ScoreFileError> {
+    // Check the version before the shape, which a newer schema may have changed.
+    let value: serde_json::Value =
+        serde_json::from_slice(bytes).map_err(|e| ScoreFileError::Format(e.to_string()))?;
+    let version = value
+        .get("schema_version")
+        .and_then(serde_json::Value::as_str)
+        .ok_or_else(|| ScoreFileError::Format("missing schema_version".to_string()))?;
+    let major = version
+        .split('.')
+        .next()
+        .and_then(|major| major.parse::<u32>().ok())
+        .ok_or_else(|| ScoreFileError::Format(format!("bad schema_version {version:?}")))?;
+    if major > SCOREFILE_SCHEMA_VERSION {
+        return Err(ScoreFileError::UnsupportedVersion {
+            found: version.to_string(),
+            supported: SCOREFILE_SCHEMA_VERSION,
+        });
+    }
+    let mut file: ScoreFile =
+        serde_json::from_value(value).map_err(|e| ScoreFileError::Format(e.to_string()))?;
+    // Files saved before notes had ids get them here.
+    file.score.assign_note_ids();
+    Ok(file)
+}
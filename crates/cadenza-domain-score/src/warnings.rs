@@ -0,0 +1,111 @@
+use cadenza_ports::types::Tick;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Non-fatal issues found while importing a score. The score is still usable,
+/// but some source content was dropped or reinterpreted.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImportWarning {
+    /// Percussion notes (GM channel 10 or a drum-kit bank) were not turned into targets.
+    PercussionExcluded {
+        track_index: usize,
+        channel: u8,
+        note_count: u32,
+        playback_dropped: bool,
+    },
+    /// A MusicXML heuristic filled in or discarded something the file did not state cleanly,
+    /// which usually points at dubious OMR output.
+    MusicXml {
+        part_index: usize,
+        /// Zero-based, like [`crate::TargetEvent::measure_index`].
+        measure_index: u32,
+        kind: MusicXmlWarningKind,
+        detail: String,
+    },
+    /// A note changed by [`crate::cleanup_omr_score`].
+    OmrCleanup {
+        track_index: usize,
+        tick: Tick,
+        /// Zero-based, when the target at `tick` knows its measure.
+        measure_index: Option<u32>,
+        kind: OmrCleanupKind,
+        detail: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OmrCleanupKind {
+    /// A short note with nothing else starting with it, likely a misread dot or slur.
+    DroppedShortNote,
+    OptionalShortNote,
+    /// The same pitch started more than once at one tick.
+    MergedUnison,
+    ClampedVelocity,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MusicXmlWarningKind {
+    /// `<duration>` was missing and the length was guessed from `<type>`, or not at all.
+    InferredDuration,
+    /// A note ran past the end of its measure and was shortened.
+    ClampedOverflow,
+    /// A grace note had no principal note to attach to.
+    DroppedGraceNote,
+    /// A `<dynamics>` mark that maps to no velocity.
+    UnknownDynamics,
+    /// A tie stop without a start, or a start never stopped.
+    UnmatchedTie,
+    /// A `<multiple-rest>` whose bars after the first were missing; they were inserted empty.
+    ExpandedMultiRest,
+    /// An empty measure under a `<measure-repeat>` was filled with the repeated bars.
+    ExpandedMeasureRepeat,
+}
+
+impl fmt::Display for ImportWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PercussionExcluded {
+                track_index,
+                channel,
+                note_count,
+                playback_dropped,
+            } => {
+                write!(
+                    f,
+                    "track {track_index}: {note_count} percussion notes on channel {} excluded",
+                    channel + 1
+                )?;
+                if *playback_dropped {
+                    write!(f, " from playback")?;
+                }
+                Ok(())
+            }
+            Self::MusicXml {
+                part_index,
+                measure_index,
+                detail,
+                ..
+            } => write!(
+                f,
+                "part {}, measure {}: {detail}",
+                part_index + 1,
+                measure_index + 1
+            ),
+            Self::OmrCleanup {
+                track_index,
+                tick,
+                measure_index,
+                detail,
+                ..
+            } => match measure_index {
+                Some(measure_index) => write!(
+                    f,
+                    "track {}, measure {}: {detail}",
+                    track_index + 1,
+                    measure_index + 1
+                ),
+                None => write!(f, "track {}, tick {tick}: {detail}", track_index + 1),
+            },
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use cadenza_domain_score::{
+    analyze_score, Hand, PlaybackMidiEvent, Score, ScoreMeta, ScoreSource, TempoPoint, Track,
+};
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::types::{Bus, Tick};
+
+fn note_on(tick: Tick, note: u8, hand: Hand) -> PlaybackMidiEvent {
+    PlaybackMidiEvent {
+        tick,
+        event: MidiLikeEvent::NoteOn { note, velocity: 80 },
+        hand: Some(hand),
+        ornament_of: None,
+        bus: None,
+        note_id: None,
+        spelling: None,
+        transpose: 0,
+        articulations: Vec::new(),
+    }
+}
+
+fn score(tempo_map: Vec<TempoPoint>, mut playback_events: Vec<PlaybackMidiEvent>) -> Score {
+    let mut score = Score::new(
+        ScoreMeta {
+            title: None,
+            composer: None,
+            copyright: None,
+            source: ScoreSource::Internal,
+            origin_path: None,
+        },
+        480,
+    );
+    score.tempo_map = tempo_map;
+    playback_events.sort_by_key(|e| e.tick);
+    score.tracks = vec![Track {
+        id: 1,
+        name: "Piano".to_string(),
+        hand: None,
+        targets: Vec::new(),
+        playback_events,
+    }];
+    score
+}
+
+#[test]
+fn a_dense_passage_suggests_starting_slowly() {
+    // At 120 bpm: eight sixteenths of C5 over a left-hand octave chord, then a wide
+    // right-hand chord on the third beat, with metronome clicks that are not to be played.
+    let mut events: Vec<PlaybackMidiEvent> =
+        (0..8).map(|i| note_on(i * 120, 72, Hand::Right)).collect();
+    events.extend([36, 43, 48].map(|note| note_on(0, note, Hand::Left)));
+    events.extend([60, 64, 67, 71, 74].map(|note| note_on(960, note, Hand::Right)));
+    for tick in [480, 960] {
+        events.push(PlaybackMidiEvent {
+            bus: Some(Bus::MetronomeFx),
+            ..note_on(tick, 100, Hand::Right)
+        });
+    }
+    let analysis = analyze_score(&score(
+        vec![TempoPoint {
+            tick: 0,
+            us_per_quarter: 500_000,
+        }],
+        events,
+    ));
+
+    // The second ending on the chord holds seven sixteenths and the chord.
+    assert_eq!(analysis.peak_notes_per_second, 12.0);
+    assert_eq!(analysis.max_chord_size, 5);
+    assert_eq!(analysis.left_hand_span, 12);
+    assert_eq!(analysis.right_hand_span, 14);
+    assert_eq!(analysis.fast_repeated_notes, 7);
+    assert_eq!(analysis.suggested_tempo_multiplier, 0.5);
+}
+
+#[test]
+fn note_rates_follow_the_tempo_map() {
+    // Four quarters at 120 bpm, then sixteen eighths alternating D and C at 240 bpm.
+    let mut events: Vec<PlaybackMidiEvent> =
+        (0..4).map(|i| note_on(i * 480, 60, Hand::Right)).collect();
+    events.extend((0..16).map(|i| note_on(1920 + i * 240, 62 - (i % 2) as u8 * 2, Hand::Right)));
+    let tempo_map = vec![
+        TempoPoint {
+            tick: 0,
+            us_per_quarter: 500_000,
+        },
+        TempoPoint {
+            tick: 1920,
+            us_per_quarter: 250_000,
+        },
+    ];
+    let analysis = analyze_score(&score(tempo_map, events.clone()));
+
+    assert_eq!(analysis.peak_notes_per_second, 8.0);
+    assert_eq!(analysis.max_chord_size, 1);
+    assert_eq!(analysis.right_hand_span, 0);
+    // Each pitch comes back every 250 ms.
+    assert_eq!(analysis.fast_repeated_notes, 0);
+    assert_eq!(analysis.suggested_tempo_multiplier, 0.75);
+
+    // Without the tempo change the eighths are a comfortable four a second.
+    let analysis = analyze_score(&score(
+        vec![TempoPoint {
+            tick: 0,
+            us_per_quarter: 500_000,
+        }],
+        events,
+    ));
+    assert_eq!(analysis.peak_notes_per_second, 4.0);
+    assert_eq!(analysis.suggested_tempo_multiplier, 1.0);
+}
@@ -0,0 +1,97 @@
+use cadenza_domain_score::{
+    import_musicxml_str, set_notes_hand, swap_hands, Hand, NoteSelection, Score,
+};
+use cadenza_ports::midi::MidiLikeEvent;
+
+/// A melody on the bass staff and a bass on the treble staff, as OMR misreads them.
+const SWAPPED_STAVES: &str = r#"
+<score-partwise version="3.1">
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>1</divisions>
+        <time><beats>2</beats><beat-type>4</beat-type></time>
+        <staves>2</staves>
+      </attributes>
+      <note><pitch><step>D</step><octave>3</octave></pitch><duration>2</duration><staff>1</staff></note>
+      <backup><duration>2</duration></backup>
+      <note><pitch><step>D</step><octave>5</octave></pitch><duration>1</duration><staff>2</staff></note>
+      <note><chord/><pitch><step>F</step><octave>5</octave></pitch><duration>1</duration><staff>2</staff></note>
+      <note><pitch><step>E</step><octave>5</octave></pitch><duration>1</duration><staff>2</staff></note>
+    </measure>
+  </part>
+</score-partwise>
+"#;
+
+/// `(tick, note, is_note_on, hand)` of every note on and off, in order.
+fn note_hands(score: &Score) -> Vec<(i64, u8, bool, Option<Hand>)> {
+    let mut hands: Vec<_> = score.tracks[0]
+        .playback_events
+        .iter()
+        .filter_map(|event| match event.event {
+            MidiLikeEvent::NoteOn { note, .. } => Some((event.tick, note, true, event.hand)),
+            MidiLikeEvent::NoteOff { note } => Some((event.tick, note, false, event.hand)),
+            _ => None,
+        })
+        .collect();
+    hands.sort_by_key(|&(tick, note, on, _)| (tick, note, on));
+    hands
+}
+
+fn target_hand(score: &Score, tick: i64) -> Option<Hand> {
+    score.tracks[0]
+        .targets
+        .iter()
+        .find(|target| target.tick == tick)
+        .expect("target")
+        .hand
+}
+
+#[test]
+fn swapping_hands_flips_notes_and_their_note_offs() {
+    use Hand::{Left, Right};
+    let mut score = import_musicxml_str(SWAPPED_STAVES).expect("import");
+    assert_eq!(target_hand(&score, 480), Some(Hand::Left));
+
+    assert_eq!(swap_hands(&mut score, 0..960), 4);
+    assert_eq!(
+        note_hands(&score),
+        vec![
+            (0, 50, true, Some(Left)),
+            (0, 74, true, Some(Right)),
+            (0, 77, true, Some(Right)),
+            (480, 74, false, Some(Right)),
+            (480, 76, true, Some(Right)),
+            (480, 77, false, Some(Right)),
+            (960, 50, false, Some(Left)),
+            (960, 76, false, Some(Right)),
+        ]
+    );
+    // The downbeat mixes both hands; the second beat is the melody alone.
+    assert_eq!(target_hand(&score, 0), None);
+    assert_eq!(target_hand(&score, 480), Some(Right));
+}
+
+#[test]
+fn setting_a_hand_only_touches_the_selected_pitches_and_ticks() {
+    let mut score = import_musicxml_str(SWAPPED_STAVES).expect("import");
+    let bass = NoteSelection {
+        ticks: 0..480,
+        pitches: 0..=59,
+    };
+
+    assert_eq!(set_notes_hand(&mut score, &bass, Hand::Left), 1);
+    // Already assigned, nothing changes.
+    assert_eq!(set_notes_hand(&mut score, &bass, Hand::Left), 0);
+
+    let hands = note_hands(&score);
+    assert!(hands.contains(&(0, 50, true, Some(Hand::Left))));
+    assert!(hands.contains(&(960, 50, false, Some(Hand::Left))));
+    // The chord and the melody after it are left alone.
+    assert!(hands.contains(&(0, 74, true, Some(Hand::Left))));
+    assert!(hands.contains(&(480, 76, true, Some(Hand::Left))));
+    assert_eq!(target_hand(&score, 0), Some(Hand::Left));
+}
@@ -0,0 +1,256 @@
+use cadenza_domain_score::{
+    export_midi_range, import_midi_path, PlaybackMidiEvent, Score, ScoreMeta, ScoreSource,
+    TempoPoint, Track,
+};
+use cadenza_ports::midi::MidiLikeEvent;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn temp_midi_path(name: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    std::env::temp_dir().join(format!("cadenza-{name}-{nanos}.mid"))
+}
+
+fn event(tick: i64, event: MidiLikeEvent) -> PlaybackMidiEvent {
+    PlaybackMidiEvent {
+        tick,
+        event,
+        hand: None,
+        ornament_of: None,
+        bus: None,
+        note_id: None,
+        spelling: None,
+        transpose: 0,
+        articulations: Vec::new(),
+    }
+}
+
+fn score_with(playback_events: Vec<PlaybackMidiEvent>) -> Score {
+    Score {
+        meta: ScoreMeta {
+            title: None,
+            composer: None,
+            copyright: None,
+            source: ScoreSource::Internal,
+            origin_path: None,
+        },
+        ppq: 480,
+        tempo_map: vec![TempoPoint {
+            tick: 0,
+            us_per_quarter: 500_000,
+        }],
+        time_signatures: Vec::new(),
+        pickup_ticks: 0,
+        key_signatures: Vec::new(),
+        tracks: vec![Track {
+            id: 0,
+            name: "Piano".to_string(),
+            hand: None,
+            targets: Vec::new(),
+            playback_events,
+        }],
+    }
+}
+
+fn export_and_reload(score: &Score, name: &str, start: i64, end: i64, x: f32) -> Score {
+    let path = temp_midi_path(name);
+    export_midi_range(score, &path, start, end, x).expect("export should succeed");
+    let loaded = import_midi_path(&path).expect("import should succeed");
+    let _ = std::fs::remove_file(&path);
+    loaded
+}
+
+#[test]
+fn midi_export_range_clips_notes_straddling_both_boundaries() {
+    // 60 straddles the start, 64 is fully inside, 67 straddles the end.
+    let score = score_with(vec![
+        event(
+            0,
+            MidiLikeEvent::NoteOn {
+                note: 60,
+                velocity: 80,
+            },
+        ),
+        event(960, MidiLikeEvent::NoteOff { note: 60 }),
+        event(
+            480,
+            MidiLikeEvent::NoteOn {
+                note: 64,
+                velocity: 90,
+            },
+        ),
+        event(720, MidiLikeEvent::NoteOff { note: 64 }),
+        event(
+            1200,
+            MidiLikeEvent::NoteOn {
+                note: 67,
+                velocity: 100,
+            },
+        ),
+        event(1920, MidiLikeEvent::NoteOff { note: 67 }),
+        event(
+            1920,
+            MidiLikeEvent::NoteOn {
+                note: 72,
+                velocity: 100,
+            },
+        ),
+        event(2400, MidiLikeEvent::NoteOff { note: 72 }),
+    ]);
+
+    let loaded = export_and_reload(&score, "midi-range-notes", 480, 1440, 1.0);
+    let events: Vec<(i64, MidiLikeEvent)> = loaded.tracks[0]
+        .playback_events
+        .iter()
+        .map(|e| (e.tick, e.event))
+        .collect();
+
+    assert!(events.contains(&(
+        0,
+        MidiLikeEvent::NoteOn {
+            note: 60,
+            velocity: 80
+        }
+    )));
+    assert!(events.contains(&(480, MidiLikeEvent::NoteOff { note: 60 })));
+    assert!(events.contains(&(
+        0,
+        MidiLikeEvent::NoteOn {
+            note: 64,
+            velocity: 90
+        }
+    )));
+    assert!(events.contains(&(240, MidiLikeEvent::NoteOff { note: 64 })));
+    assert!(events.contains(&(
+        720,
+        MidiLikeEvent::NoteOn {
+            note: 67,
+            velocity: 100
+        }
+    )));
+    assert!(events.contains(&(960, MidiLikeEvent::NoteOff { note: 67 })));
+    assert!(!events.iter().any(|(_, e)| matches!(
+        e,
+        MidiLikeEvent::NoteOn { note: 72, .. } | MidiLikeEvent::NoteOff { note: 72 }
+    )));
+    assert!(events.iter().all(|(tick, _)| (0..=960).contains(tick)));
+}
+
+#[test]
+fn midi_export_range_restores_pedal_held_across_start() {
+    let score = score_with(vec![
+        event(0, MidiLikeEvent::Cc64 { value: 127 }),
+        event(
+            0,
+            MidiLikeEvent::NoteOn {
+                note: 48,
+                velocity: 70,
+            },
+        ),
+        event(240, MidiLikeEvent::NoteOff { note: 48 }),
+        event(
+            960,
+            MidiLikeEvent::NoteOn {
+                note: 52,
+                velocity: 70,
+            },
+        ),
+        event(1200, MidiLikeEvent::NoteOff { note: 52 }),
+        event(1920, MidiLikeEvent::Cc64 { value: 0 }),
+    ]);
+
+    let loaded = export_and_reload(&score, "midi-range-pedal", 480, 1440, 1.0);
+    let pedal: Vec<(i64, u8)> = loaded.tracks[0]
+        .playback_events
+        .iter()
+        .filter_map(|e| match e.event {
+            MidiLikeEvent::Cc64 { value } => Some((e.tick, value)),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(pedal, vec![(0, 127), (960, 0)]);
+    // The note released before the range must not be restruck.
+    assert!(!loaded.tracks[0]
+        .playback_events
+        .iter()
+        .any(|e| matches!(e.event, MidiLikeEvent::NoteOn { note: 48, .. })));
+}
+
+#[test]
+fn midi_export_range_pairs_repeated_notes_first_in_first_out() {
+    // Two overlapping 60s: the first NoteOff ends the soft one, so the loud one is
+    // still held when the range starts.
+    let score = score_with(vec![
+        event(
+            0,
+            MidiLikeEvent::NoteOn {
+                note: 60,
+                velocity: 40,
+            },
+        ),
+        event(
+            240,
+            MidiLikeEvent::NoteOn {
+                note: 60,
+                velocity: 110,
+            },
+        ),
+        event(360, MidiLikeEvent::NoteOff { note: 60 }),
+        event(960, MidiLikeEvent::NoteOff { note: 60 }),
+    ]);
+
+    let loaded = export_and_reload(&score, "midi-range-fifo", 480, 1440, 1.0);
+    let events: Vec<(i64, MidiLikeEvent)> = loaded.tracks[0]
+        .playback_events
+        .iter()
+        .map(|e| (e.tick, e.event))
+        .collect();
+
+    assert_eq!(
+        events,
+        vec![
+            (
+                0,
+                MidiLikeEvent::NoteOn {
+                    note: 60,
+                    velocity: 110
+                }
+            ),
+            (480, MidiLikeEvent::NoteOff { note: 60 }),
+        ]
+    );
+}
+
+#[test]
+fn midi_export_range_scales_tempo_by_multiplier() {
+    let mut score = score_with(vec![
+        event(
+            0,
+            MidiLikeEvent::NoteOn {
+                note: 60,
+                velocity: 80,
+            },
+        ),
+        event(1920, MidiLikeEvent::NoteOff { note: 60 }),
+    ]);
+    score.tempo_map.push(TempoPoint {
+        tick: 960,
+        us_per_quarter: 400_000,
+    });
+
+    let loaded = export_and_reload(&score, "midi-range-tempo", 480, 1920, 2.0);
+    let tempo: Vec<(i64, u32)> = loaded
+        .tempo_map
+        .iter()
+        .map(|p| (p.tick, p.us_per_quarter))
+        .collect();
+    assert_eq!(tempo, vec![(0, 250_000), (480, 200_000)]);
+
+    let path = temp_midi_path("midi-range-invalid");
+    assert!(export_midi_range(&score, &path, 960, 960, 1.0).is_err());
+    assert!(export_midi_range(&score, &path, 0, 960, 0.0).is_err());
+}
@@ -0,0 +1,142 @@
+use cadenza_domain_score::{import_midi_bytes_with_options, ImportWarning, MidiImportOptions};
+use cadenza_ports::midi::MidiLikeEvent;
+use midly::num::{u28, u4, u7};
+use midly::{Format, Header, MetaMessage, MidiMessage, Smf, Timing, TrackEvent, TrackEventKind};
+
+fn note(delta: u32, channel: u8, key: u8, vel: u8) -> TrackEvent<'static> {
+    TrackEvent {
+        delta: u28::new(delta),
+        kind: TrackEventKind::Midi {
+            channel: u4::new(channel),
+            message: MidiMessage::NoteOn {
+                key: u7::new(key),
+                vel: u7::new(vel),
+            },
+        },
+    }
+}
+
+fn end_of_track() -> TrackEvent<'static> {
+    TrackEvent {
+        delta: u28::new(0),
+        kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+    }
+}
+
+/// Track 0: piano C-E-G quarter notes. Track 1: kick drum on channel 10 every beat.
+fn piano_and_drums_midi() -> Vec<u8> {
+    let piano = vec![
+        note(0, 0, 60, 90),
+        note(480, 0, 60, 0),
+        note(0, 0, 64, 90),
+        note(480, 0, 64, 0),
+        note(0, 0, 67, 90),
+        note(480, 0, 67, 0),
+        end_of_track(),
+    ];
+    let mut drums = Vec::new();
+    for beat in 0..4 {
+        drums.push(note(if beat == 0 { 0 } else { 460 }, 9, 35, 100));
+        drums.push(note(20, 9, 35, 0));
+    }
+    drums.push(end_of_track());
+
+    let smf = Smf {
+        header: Header {
+            format: Format::Parallel,
+            timing: Timing::Metrical(480.into()),
+        },
+        tracks: vec![piano, drums],
+    };
+    let mut data = Vec::new();
+    smf.write(&mut data).expect("midi write should succeed");
+    data
+}
+
+#[test]
+fn midi_import_drops_percussion_targets_but_keeps_playback() {
+    let midi = piano_and_drums_midi();
+    let import = import_midi_bytes_with_options(&midi, MidiImportOptions::default())
+        .expect("import should succeed");
+    let track = &import.score.tracks[0];
+
+    assert_eq!(track.targets.len(), 3);
+    assert!(track.targets.iter().all(|t| !t.notes.contains(&35)));
+    let kick_ons = track
+        .playback_events
+        .iter()
+        .filter(|e| matches!(e.event, MidiLikeEvent::NoteOn { note: 35, .. }))
+        .count();
+    assert_eq!(kick_ons, 4);
+
+    assert_eq!(
+        import.warnings,
+        vec![ImportWarning::PercussionExcluded {
+            track_index: 1,
+            channel: 9,
+            note_count: 4,
+            playback_dropped: false,
+        }]
+    );
+}
+
+#[test]
+fn midi_import_can_drop_percussion_playback() {
+    let midi = piano_and_drums_midi();
+    let options = MidiImportOptions {
+        keep_percussion_playback: false,
+    };
+    let import = import_midi_bytes_with_options(&midi, options).expect("import should succeed");
+    let track = &import.score.tracks[0];
+
+    assert_eq!(track.targets.len(), 3);
+    assert!(!track.playback_events.iter().any(|e| matches!(
+        e.event,
+        MidiLikeEvent::NoteOn { note: 35, .. } | MidiLikeEvent::NoteOff { note: 35 }
+    )));
+    assert!(matches!(
+        import.warnings.as_slice(),
+        [ImportWarning::PercussionExcluded {
+            playback_dropped: true,
+            ..
+        }]
+    ));
+}
+
+#[test]
+fn midi_import_treats_drum_bank_channel_as_percussion() {
+    let bank_select = TrackEvent {
+        delta: u28::new(0),
+        kind: TrackEventKind::Midi {
+            channel: u4::new(3),
+            message: MidiMessage::Controller {
+                controller: u7::new(0),
+                value: u7::new(127),
+            },
+        },
+    };
+    let track = vec![
+        bank_select,
+        note(0, 3, 38, 100),
+        note(0, 0, 60, 100),
+        note(480, 3, 38, 0),
+        note(0, 0, 60, 0),
+        end_of_track(),
+    ];
+    let smf = Smf {
+        header: Header {
+            format: Format::SingleTrack,
+            timing: Timing::Metrical(480.into()),
+        },
+        tracks: vec![track],
+    };
+    let mut midi = Vec::new();
+    smf.write(&mut midi).expect("midi write should succeed");
+
+    let import = import_midi_bytes_with_options(&midi, MidiImportOptions::default())
+        .expect("import should succeed");
+    let targets = &import.score.tracks[0].targets;
+    assert_eq!(targets.len(), 1);
+    assert_eq!(targets[0].notes, vec![60]);
+    assert_eq!(import.warnings.len(), 1);
+}
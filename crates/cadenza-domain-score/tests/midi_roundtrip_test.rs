@@ -1,8 +1,10 @@
 use cadenza_domain_score::{
-    export_midi_path, import_midi_path, PlaybackMidiEvent, Score, ScoreMeta, ScoreSource,
-    TargetEvent, TempoPoint, Track,
+    export_midi_path, export_midi_path_with_options, import_midi_path, ExportOptions,
+    ExportSourceInfo, Hand, KeyMode, KeySignaturePoint, PlaybackMidiEvent, Score, ScoreMeta,
+    ScoreSource, TargetEvent, TempoPoint, TimeSignaturePoint, Track,
 };
 use cadenza_ports::midi::MidiLikeEvent;
+use midly::{Format, MetaMessage, MidiMessage, Smf, TrackEventKind};
 use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -27,11 +29,23 @@ fn midi_export_import_roundtrip() {
                 velocity: 100,
             },
             hand: None,
+            ornament_of: None,
+            bus: None,
+            note_id: None,
+            spelling: None,
+            transpose: 0,
+            articulations: Vec::new(),
         },
         PlaybackMidiEvent {
             tick: 480,
             event: MidiLikeEvent::NoteOff { note: 60 },
             hand: None,
+            ornament_of: None,
+            bus: None,
+            note_id: None,
+            spelling: None,
+            transpose: 0,
+            articulations: Vec::new(),
         },
     ];
 
@@ -45,6 +59,9 @@ fn midi_export_import_roundtrip() {
             notes: vec![60],
             hand: None,
             measure_index: None,
+            optional: false,
+            note_ids: Vec::new(),
+            note_tracks: Vec::new(),
         }],
         playback_events,
     };
@@ -52,13 +69,19 @@ fn midi_export_import_roundtrip() {
     let score = Score {
         meta: ScoreMeta {
             title: Some("Roundtrip".to_string()),
+            composer: None,
+            copyright: None,
             source: ScoreSource::Internal,
+            origin_path: None,
         },
         ppq,
         tempo_map: vec![TempoPoint {
             tick: 0,
             us_per_quarter: 500_000,
         }],
+        time_signatures: Vec::new(),
+        pickup_ticks: 0,
+        key_signatures: Vec::new(),
         tracks: vec![track],
     };
 
@@ -74,3 +97,359 @@ fn midi_export_import_roundtrip() {
 
     let _ = std::fs::remove_file(&path);
 }
+
+fn note_track(id: u32, name: &str, notes: &[u8]) -> Track {
+    let mut playback_events = Vec::new();
+    for (idx, note) in notes.iter().copied().enumerate() {
+        let tick = idx as i64 * 480;
+        playback_events.push(PlaybackMidiEvent {
+            tick,
+            event: MidiLikeEvent::NoteOn { note, velocity: 90 },
+            hand: None,
+            ornament_of: None,
+            bus: None,
+            note_id: None,
+            spelling: None,
+            transpose: 0,
+            articulations: Vec::new(),
+        });
+        playback_events.push(PlaybackMidiEvent {
+            tick: tick + 480,
+            event: MidiLikeEvent::NoteOff { note },
+            hand: None,
+            ornament_of: None,
+            bus: None,
+            note_id: None,
+            spelling: None,
+            transpose: 0,
+            articulations: Vec::new(),
+        });
+    }
+    Track {
+        id,
+        name: name.to_string(),
+        hand: None,
+        targets: Vec::new(),
+        playback_events,
+    }
+}
+
+#[test]
+fn midi_export_writes_named_tracks_time_and_key_signatures() {
+    let path = temp_midi_path("midi-two-track");
+    let key_signatures = vec![
+        KeySignaturePoint {
+            tick: 0,
+            fifths: 2,
+            mode: KeyMode::Major,
+        },
+        KeySignaturePoint {
+            tick: 1440,
+            fifths: -3,
+            mode: KeyMode::Minor,
+        },
+    ];
+
+    let score = Score {
+        meta: ScoreMeta {
+            title: Some("Waltz".to_string()),
+            composer: None,
+            copyright: None,
+            source: ScoreSource::Internal,
+            origin_path: None,
+        },
+        ppq: 480,
+        tempo_map: vec![TempoPoint {
+            tick: 0,
+            us_per_quarter: 600_000,
+        }],
+        time_signatures: vec![TimeSignaturePoint {
+            tick: 0,
+            numerator: 3,
+            denominator: 4,
+        }],
+        pickup_ticks: 0,
+        key_signatures: key_signatures.clone(),
+        tracks: vec![
+            note_track(0, "Right Hand", &[72, 74, 76]),
+            note_track(1, "Left Hand", &[48, 55, 55]),
+        ],
+    };
+
+    export_midi_path(&score, &path).expect("export should succeed");
+
+    let data = std::fs::read(&path).expect("read exported file");
+    let smf = Smf::parse(&data).expect("exported file should parse");
+    assert_eq!(smf.header.format, Format::Parallel);
+    assert_eq!(smf.tracks.len(), 3);
+
+    let names: Vec<&[u8]> = smf
+        .tracks
+        .iter()
+        .filter_map(|track| {
+            track.iter().find_map(|e| match e.kind {
+                TrackEventKind::Meta(MetaMessage::TrackName(name)) => Some(name),
+                _ => None,
+            })
+        })
+        .collect();
+    assert_eq!(
+        names,
+        vec![&b"Waltz"[..], &b"Right Hand"[..], &b"Left Hand"[..]]
+    );
+
+    let has_tempo = |idx: usize| {
+        smf.tracks[idx]
+            .iter()
+            .any(|e| matches!(e.kind, TrackEventKind::Meta(MetaMessage::Tempo(_))))
+    };
+    assert!(has_tempo(0));
+    assert!(!has_tempo(1));
+    assert!(!has_tempo(2));
+
+    let loaded = import_midi_path(&path).expect("import should succeed");
+    assert_eq!(
+        loaded.time_signatures,
+        vec![TimeSignaturePoint {
+            tick: 0,
+            numerator: 3,
+            denominator: 4,
+        }]
+    );
+    assert_eq!(loaded.key_signatures, key_signatures);
+    assert_eq!(loaded.tempo_map[0].us_per_quarter, 600_000);
+    assert_eq!(loaded.tracks[0].targets.len(), 3);
+
+    let _ = std::fs::remove_file(&path);
+}
+
+fn event(tick: i64, event: MidiLikeEvent, hand: Option<Hand>) -> PlaybackMidiEvent {
+    PlaybackMidiEvent {
+        tick,
+        event,
+        hand,
+        ornament_of: None,
+        bus: None,
+        note_id: None,
+        spelling: None,
+        transpose: 0,
+        articulations: Vec::new(),
+    }
+}
+
+fn note_on_keys(track: &[midly::TrackEvent]) -> Vec<u8> {
+    track
+        .iter()
+        .filter_map(|e| match e.kind {
+            TrackEventKind::Midi {
+                message: MidiMessage::NoteOn { key, vel },
+                ..
+            } if vel.as_int() > 0 => Some(key.as_int()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn pedal_count(track: &[midly::TrackEvent]) -> usize {
+    track
+        .iter()
+        .filter(|e| {
+            matches!(
+                e.kind,
+                TrackEventKind::Midi {
+                    message: MidiMessage::Controller { .. },
+                    ..
+                }
+            )
+        })
+        .count()
+}
+
+#[test]
+fn midi_export_split_by_hand_partitions_tracks() {
+    let path = temp_midi_path("midi-split-hands");
+
+    let on = |note| MidiLikeEvent::NoteOn { note, velocity: 80 };
+    let off = |note| MidiLikeEvent::NoteOff { note };
+    let playback_events = vec![
+        event(0, MidiLikeEvent::Cc64 { value: 127 }, None),
+        event(0, on(72), Some(Hand::Right)),
+        event(0, on(48), Some(Hand::Left)),
+        event(0, on(60), None),
+        event(480, off(72), Some(Hand::Right)),
+        event(480, off(48), Some(Hand::Left)),
+        event(480, off(60), None),
+        event(480, MidiLikeEvent::Cc64 { value: 0 }, None),
+    ];
+    let mut score = Score::new(
+        ScoreMeta {
+            title: None,
+            composer: None,
+            copyright: None,
+            source: ScoreSource::MusicXml,
+            origin_path: None,
+        },
+        480,
+    );
+    score.tracks.push(Track {
+        id: 0,
+        name: "Merged".to_string(),
+        hand: None,
+        targets: Vec::new(),
+        playback_events,
+    });
+
+    let options = ExportOptions {
+        split_by_hand: true,
+        unknown_hand: Hand::Left,
+        ..ExportOptions::default()
+    };
+    export_midi_path_with_options(&score, &path, options).expect("export should succeed");
+
+    let data = std::fs::read(&path).expect("read exported file");
+    let smf = Smf::parse(&data).expect("exported file should parse");
+    assert_eq!(smf.header.format, Format::Parallel);
+    assert_eq!(smf.tracks.len(), 3);
+
+    assert!(matches!(
+        smf.tracks[1][0].kind,
+        TrackEventKind::Meta(MetaMessage::TrackName(b"Right Hand"))
+    ));
+    assert!(matches!(
+        smf.tracks[2][0].kind,
+        TrackEventKind::Meta(MetaMessage::TrackName(b"Left Hand"))
+    ));
+
+    assert_eq!(note_on_keys(&smf.tracks[1]), vec![72]);
+    let mut left = note_on_keys(&smf.tracks[2]);
+    left.sort_unstable();
+    assert_eq!(left, vec![48, 60]);
+
+    // Pedal without a hand goes with the unknown hand, once, so merging the tracks back
+    // does not double it.
+    assert_eq!(pedal_count(&smf.tracks[1]), 0);
+    assert_eq!(pedal_count(&smf.tracks[2]), 2);
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn midi_export_split_by_hand_keeps_handless_pedal_on_one_track() {
+    let path = temp_midi_path("midi-split-pedal");
+
+    let playback_events = vec![
+        event(0, MidiLikeEvent::Cc64 { value: 127 }, None),
+        event(
+            0,
+            MidiLikeEvent::NoteOn {
+                note: 48,
+                velocity: 80,
+            },
+            Some(Hand::Left),
+        ),
+        event(480, MidiLikeEvent::NoteOff { note: 48 }, Some(Hand::Left)),
+        event(480, MidiLikeEvent::Cc64 { value: 0 }, None),
+    ];
+    let mut score = Score::new(
+        ScoreMeta {
+            title: None,
+            composer: None,
+            copyright: None,
+            source: ScoreSource::MusicXml,
+            origin_path: None,
+        },
+        480,
+    );
+    score.tracks.push(Track {
+        id: 0,
+        name: "Merged".to_string(),
+        hand: None,
+        targets: Vec::new(),
+        playback_events,
+    });
+
+    let options = ExportOptions {
+        split_by_hand: true,
+        ..ExportOptions::default()
+    };
+    export_midi_path_with_options(&score, &path, options).expect("export should succeed");
+
+    let data = std::fs::read(&path).expect("read exported file");
+    let smf = Smf::parse(&data).expect("exported file should parse");
+    assert_eq!(pedal_count(&smf.tracks[1]), 2);
+    assert_eq!(pedal_count(&smf.tracks[2]), 0);
+
+    let reimported = import_midi_path(&path).expect("reimport should succeed");
+    let pedals = reimported
+        .tracks
+        .iter()
+        .flat_map(|track| &track.playback_events)
+        .filter(|e| matches!(e.event, MidiLikeEvent::Cc64 { .. }))
+        .count();
+    assert_eq!(pedals, 2);
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn midi_export_embeds_metadata_and_reimports_title() {
+    let path = temp_midi_path("midi-metadata");
+
+    let score = Score {
+        meta: ScoreMeta {
+            title: Some("Clair de Lune".to_string()),
+            composer: None,
+            copyright: Some("Public domain".to_string()),
+            source: ScoreSource::PdfOmr,
+            origin_path: None,
+        },
+        ppq: 480,
+        tempo_map: vec![TempoPoint {
+            tick: 0,
+            us_per_quarter: 500_000,
+        }],
+        time_signatures: Vec::new(),
+        pickup_ticks: 0,
+        key_signatures: Vec::new(),
+        tracks: vec![note_track(0, "Piano", &[60, 64])],
+    };
+    let options = ExportOptions {
+        source_info: Some(ExportSourceInfo {
+            source_file: Some("clair.pdf".to_string()),
+            import_warnings: 2,
+        }),
+        ..ExportOptions::default()
+    };
+    export_midi_path_with_options(&score, &path, options).expect("export should succeed");
+
+    let data = std::fs::read(&path).expect("read exported file");
+    let smf = Smf::parse(&data).expect("exported file should parse");
+    // A single-track score stays a Format 0 file, its one track carrying the metadata.
+    assert_eq!(smf.header.format, Format::SingleTrack);
+    assert_eq!(smf.tracks.len(), 1);
+    let conductor = &smf.tracks[0];
+    assert!(conductor.iter().any(|e| matches!(
+        e.kind,
+        TrackEventKind::Meta(MetaMessage::Copyright(b"Public domain"))
+    )));
+    assert!(conductor.iter().any(|e| matches!(
+        e.kind,
+        TrackEventKind::Meta(MetaMessage::Text(text)) if text.starts_with(b"Generated by Cadenza ")
+    )));
+    let blob = conductor
+        .iter()
+        .find_map(|e| match e.kind {
+            TrackEventKind::Meta(MetaMessage::SequencerSpecific(data)) => Some(data),
+            _ => None,
+        })
+        .expect("source info blob");
+    let json = String::from_utf8_lossy(&blob[1..]);
+    assert!(json.contains("\"source_file\":\"clair.pdf\""));
+    assert!(json.contains("\"import_warnings\":2"));
+
+    let loaded = import_midi_path(&path).expect("import should succeed");
+    assert_eq!(loaded.meta.title.as_deref(), Some("Clair de Lune"));
+    assert_eq!(loaded.meta.copyright.as_deref(), Some("Public domain"));
+
+    let _ = std::fs::remove_file(&path);
+}
@@ -1,6 +1,60 @@
-use cadenza_domain_score::import_musicxml_str;
+use cadenza_domain_score::{
+    import_musicxml_path_with_report, import_musicxml_str_with_options,
+    import_musicxml_str_with_report, Articulation, Hand, ImportWarning, KeyMode, KeySignaturePoint,
+    MusicXmlImport, MusicXmlImportError, MusicXmlImportOptions, MusicXmlWarningKind, Score,
+};
 use cadenza_ports::midi::MidiLikeEvent;
 
+/// Imports through the DOM and the streaming reader and checks they agree.
+fn import_both_paths(xml: &str) -> Result<MusicXmlImport, MusicXmlImportError> {
+    let dom_options = MusicXmlImportOptions {
+        streaming_threshold_bytes: usize::MAX,
+        ..MusicXmlImportOptions::default()
+    };
+    let streaming_options = MusicXmlImportOptions {
+        streaming_threshold_bytes: 0,
+        ..MusicXmlImportOptions::default()
+    };
+    let dom = import_musicxml_str_with_report(xml, &dom_options);
+    let streamed = import_musicxml_str_with_report(xml, &streaming_options);
+    match (&dom, &streamed) {
+        (Ok(dom), Ok(streamed)) => {
+            assert_eq!(
+                serde_json::to_value(&dom.score).expect("serialize"),
+                serde_json::to_value(&streamed.score).expect("serialize"),
+                "streaming import differs from DOM import"
+            );
+            assert_eq!(dom.warnings, streamed.warnings);
+        }
+        (Err(_), Err(_)) => {}
+        _ => panic!("DOM and streaming imports disagree on success: {dom:?} vs {streamed:?}"),
+    }
+    dom
+}
+
+/// Imports a well-formed fixture, which must not trip any import heuristic.
+fn import_clean(xml: &str) -> Result<Score, MusicXmlImportError> {
+    import_both_paths(xml).map(|import| {
+        assert_eq!(
+            import.warnings,
+            Vec::new(),
+            "clean fixture produced warnings"
+        );
+        import.score
+    })
+}
+
+fn warning_kinds(import: &MusicXmlImport) -> Vec<MusicXmlWarningKind> {
+    import
+        .warnings
+        .iter()
+        .filter_map(|warning| match warning {
+            ImportWarning::MusicXml { kind, .. } => Some(*kind),
+            _ => None,
+        })
+        .collect()
+}
+
 fn note_on_ticks(score: &cadenza_domain_score::Score) -> Vec<(i64, u8)> {
     let track = score.tracks.first().expect("track");
     track
@@ -54,7 +108,7 @@ fn musicxml_chord_notes_share_start_tick() {
 </score-partwise>
 "#;
 
-    let score = import_musicxml_str(xml).expect("import ok");
+    let score = import_clean(xml).expect("import ok");
     let track = score.tracks.first().expect("track");
     assert_eq!(track.targets.len(), 1);
     assert_eq!(track.targets[0].tick, 0);
@@ -100,7 +154,7 @@ fn musicxml_backup_keeps_voices_aligned() {
 </score-partwise>
 "#;
 
-    let score = import_musicxml_str(xml).expect("import ok");
+    let score = import_clean(xml).expect("import ok");
     let track = score.tracks.first().expect("track");
     assert_eq!(track.targets.len(), 1);
     assert_eq!(track.targets[0].tick, 0);
@@ -140,7 +194,7 @@ fn musicxml_ties_merge_into_single_note() {
 </score-partwise>
 "#;
 
-    let score = import_musicxml_str(xml).expect("import ok");
+    let score = import_clean(xml).expect("import ok");
     let track = score.tracks.first().expect("track");
 
     assert_eq!(track.targets.len(), 1);
@@ -179,9 +233,13 @@ fn musicxml_clamps_notes_to_measure_end_without_tie() {
 </score-partwise>
 "#;
 
-    let score = import_musicxml_str(xml).expect("import ok");
-    let offs = note_off_ticks(&score);
+    let import = import_both_paths(xml).expect("import ok");
+    let offs = note_off_ticks(&import.score);
     assert!(offs.iter().any(|(t, n)| *t == 1920 && *n == 60));
+    assert_eq!(
+        warning_kinds(&import),
+        vec![MusicXmlWarningKind::ClampedOverflow]
+    );
 }
 
 #[test]
@@ -214,11 +272,12 @@ fn musicxml_pickup_measure_does_not_pad_to_time_signature() {
 </score-partwise>
 "#;
 
-    let score = import_musicxml_str(xml).expect("import ok");
+    let score = import_clean(xml).expect("import ok");
     let mut ons = note_on_ticks(&score);
     ons.sort();
     assert!(ons.contains(&(0, 60)));
     assert!(ons.contains(&(480, 62)));
+    assert_eq!(score.pickup_ticks, 480);
 }
 
 #[test]
@@ -249,12 +308,1049 @@ fn musicxml_infers_duration_from_type_when_missing() {
 </score-partwise>
 "#;
 
-    let score = import_musicxml_str(xml).expect("import ok");
-    let mut ons = note_on_ticks(&score);
+    let import = import_both_paths(xml).expect("import ok");
+    let mut ons = note_on_ticks(&import.score);
     ons.sort();
     assert_eq!(ons, vec![(0, 60), (480, 62)]);
 
-    let mut offs = note_off_ticks(&score);
+    let mut offs = note_off_ticks(&import.score);
     offs.sort();
     assert_eq!(offs, vec![(480, 60), (960, 62)]);
+    assert_eq!(
+        warning_kinds(&import),
+        vec![MusicXmlWarningKind::InferredDuration; 2]
+    );
+}
+
+fn single_measure_with_direction(direction: &str) -> String {
+    format!(
+        r#"
+<score-partwise version="3.1">
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>1</divisions>
+        <time><beats>4</beats><beat-type>4</beat-type></time>
+      </attributes>
+      {direction}
+      <note>
+        <pitch><step>C</step><octave>4</octave></pitch>
+        <duration>4</duration>
+      </note>
+    </measure>
+  </part>
+</score-partwise>
+"#
+    )
+}
+
+#[test]
+fn musicxml_metronome_marks_set_tempo() {
+    let half_note = single_measure_with_direction(
+        r#"<direction><direction-type><metronome>
+             <beat-unit>half</beat-unit><per-minute>60</per-minute>
+           </metronome></direction-type></direction>"#,
+    );
+    let score = import_clean(&half_note).expect("import ok");
+    assert_eq!(score.tempo_map.len(), 1);
+    assert_eq!(score.tempo_map[0].us_per_quarter, 500_000);
+
+    let dotted_quarter = single_measure_with_direction(
+        r#"<direction><direction-type><metronome>
+             <beat-unit>quarter</beat-unit><beat-unit-dot/><per-minute>c. 80</per-minute>
+           </metronome></direction-type></direction>"#,
+    );
+    let score = import_clean(&dotted_quarter).expect("import ok");
+    assert_eq!(score.tempo_map[0].us_per_quarter, 500_000);
+}
+
+#[test]
+fn musicxml_tempo_words_apply_only_without_numeric_tempo() {
+    let words_only = single_measure_with_direction(
+        r#"<direction><direction-type><words>Adagio cantabile</words></direction-type></direction>"#,
+    );
+    let score = import_clean(&words_only).expect("import ok");
+    assert_eq!(score.tempo_map[0].us_per_quarter, 60_000_000 / 70);
+
+    let words_and_sound = single_measure_with_direction(
+        r#"<direction><direction-type><words>Allegro</words></direction-type>
+           <sound tempo="100"/></direction>"#,
+    );
+    let score = import_clean(&words_and_sound).expect("import ok");
+    assert_eq!(score.tempo_map[0].us_per_quarter, 600_000);
+}
+
+#[test]
+fn musicxml_articulations_shape_length_and_velocity() {
+    let xml = r#"
+<score-partwise version="3.1">
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>2</divisions>
+        <time><beats>4</beats><beat-type>4</beat-type></time>
+      </attributes>
+      <direction><sound dynamics="80"/></direction>
+      <note>
+        <pitch><step>C</step><octave>4</octave></pitch>
+        <duration>2</duration>
+        <notations><articulations><staccato/></articulations></notations>
+      </note>
+      <note>
+        <pitch><step>C</step><octave>4</octave></pitch>
+        <duration>2</duration>
+        <notations><articulations><staccatissimo/></articulations></notations>
+      </note>
+      <note>
+        <pitch><step>E</step><octave>4</octave></pitch>
+        <duration>2</duration>
+        <notations><articulations><tenuto/><accent/></articulations></notations>
+      </note>
+      <note>
+        <pitch><step>G</step><octave>4</octave></pitch>
+        <duration>2</duration>
+      </note>
+    </measure>
+  </part>
+</score-partwise>
+"#;
+
+    let score = import_clean(xml).expect("import ok");
+    let track = score.tracks.first().expect("track");
+    let ticks: Vec<i64> = track.targets.iter().map(|t| t.tick).collect();
+    assert_eq!(ticks, vec![0, 480, 960, 1440]);
+
+    let mut offs = note_off_ticks(&score);
+    offs.sort();
+    assert_eq!(offs, vec![(240, 60), (600, 60), (1440, 64), (1920, 67)]);
+
+    let velocities: Vec<(u8, u8)> = track
+        .playback_events
+        .iter()
+        .filter_map(|e| match e.event {
+            MidiLikeEvent::NoteOn { note, velocity } => Some((note, velocity)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(velocities, vec![(60, 102), (60, 102), (64, 118), (67, 102)]);
+
+    // The marks stay on each note on for later use.
+    let marks: Vec<Vec<Articulation>> = track
+        .playback_events
+        .iter()
+        .filter(|e| matches!(e.event, MidiLikeEvent::NoteOn { .. }))
+        .map(|e| e.articulations.clone())
+        .collect();
+    assert_eq!(
+        marks,
+        vec![
+            vec![Articulation::Staccato],
+            vec![Articulation::Staccatissimo],
+            vec![Articulation::Tenuto, Articulation::Accent],
+            Vec::new(),
+        ]
+    );
+    assert!(track
+        .playback_events
+        .iter()
+        .filter(|e| matches!(e.event, MidiLikeEvent::NoteOff { .. }))
+        .all(|e| e.articulations.is_empty()));
+}
+
+fn grace_fixture(graces: &str) -> String {
+    format!(
+        r#"
+<score-partwise version="3.1">
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>1</divisions>
+        <time><beats>2</beats><beat-type>4</beat-type></time>
+      </attributes>
+      <note>
+        <pitch><step>C</step><octave>4</octave></pitch>
+        <duration>1</duration>
+      </note>
+      {graces}
+      <note>
+        <pitch><step>G</step><octave>4</octave></pitch>
+        <duration>1</duration>
+      </note>
+    </measure>
+  </part>
+</score-partwise>
+"#
+    )
+}
+
+#[test]
+fn musicxml_grace_note_steals_time_from_principal() {
+    let xml = grace_fixture(
+        r#"<note><grace/><pitch><step>A</step><octave>4</octave></pitch><type>eighth</type></note>"#,
+    );
+    let score = import_clean(&xml).expect("import ok");
+
+    let mut ons = note_on_ticks(&score);
+    ons.sort();
+    assert_eq!(ons, vec![(0, 60), (480, 69), (540, 67)]);
+    let mut offs = note_off_ticks(&score);
+    offs.sort();
+    assert_eq!(offs, vec![(480, 60), (540, 69), (960, 67)]);
+
+    let targets = &score.tracks[0].targets;
+    let summary: Vec<(i64, Vec<u8>, bool)> = targets
+        .iter()
+        .map(|t| (t.tick, t.notes.clone(), t.optional))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (0, vec![60], false),
+            (480, vec![69], true),
+            (540, vec![67], false),
+        ]
+    );
+}
+
+#[test]
+fn musicxml_grace_chain_divides_stolen_time() {
+    let xml = grace_fixture(
+        r#"<note><grace/><pitch><step>A</step><octave>4</octave></pitch><type>16th</type></note>
+           <note><grace/><pitch><step>B</step><octave>4</octave></pitch><type>16th</type></note>"#,
+    );
+    let score = import_clean(&xml).expect("import ok");
+    let mut ons = note_on_ticks(&score);
+    ons.sort();
+    assert_eq!(ons, vec![(0, 60), (480, 69), (510, 71), (540, 67)]);
+
+    let slashed = grace_fixture(
+        r#"<note><grace slash="yes"/><pitch><step>A</step><octave>4</octave></pitch><type>16th</type></note>
+           <note><grace slash="yes"/><pitch><step>B</step><octave>4</octave></pitch><type>16th</type></note>"#,
+    );
+    let score = import_clean(&slashed).expect("import ok");
+    let mut ons = note_on_ticks(&score);
+    ons.sort();
+    assert_eq!(ons, vec![(0, 60), (420, 69), (450, 71), (480, 67)]);
+    let mut offs = note_off_ticks(&score);
+    offs.sort();
+    assert_eq!(offs, vec![(420, 60), (450, 69), (480, 71), (960, 67)]);
+}
+
+#[test]
+fn musicxml_transpose_sounds_bb_instrument_a_tone_lower() {
+    let xml = r#"
+<score-partwise version="3.1">
+  <part-list>
+    <score-part id="P1"><part-name>Clarinet in Bb</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>1</divisions>
+        <time><beats>2</beats><beat-type>4</beat-type></time>
+        <transpose><diatonic>-1</diatonic><chromatic>-2</chromatic></transpose>
+      </attributes>
+      <note>
+        <pitch><step>D</step><octave>4</octave></pitch>
+        <duration>1</duration>
+      </note>
+      <note>
+        <pitch><step>E</step><octave>4</octave></pitch>
+        <duration>1</duration>
+      </note>
+    </measure>
+    <measure number="2">
+      <attributes>
+        <transpose><chromatic>-2</chromatic><octave-change>-1</octave-change></transpose>
+      </attributes>
+      <note>
+        <pitch><step>D</step><octave>4</octave></pitch>
+        <duration>2</duration>
+      </note>
+    </measure>
+  </part>
+</score-partwise>
+"#;
+
+    let score = import_clean(xml).expect("import ok");
+    let notes: Vec<Vec<u8>> = score.tracks[0]
+        .targets
+        .iter()
+        .map(|t| t.notes.clone())
+        .collect();
+    assert_eq!(notes, vec![vec![60], vec![62], vec![48]]);
+
+    let options = MusicXmlImportOptions {
+        concert_pitch: false,
+        ..MusicXmlImportOptions::default()
+    };
+    let written = import_musicxml_str_with_options(xml, &options).expect("import ok");
+    assert_eq!(written.tracks[0].targets[0].notes, vec![62]);
+}
+
+#[test]
+fn musicxml_transposed_notes_keep_their_written_pitch() {
+    let xml = r#"
+<score-partwise version="3.1">
+  <part-list>
+    <score-part id="P1"><part-name>Clarinet in Bb</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>1</divisions>
+        <time><beats>1</beats><beat-type>4</beat-type></time>
+        <transpose><diatonic>-1</diatonic><chromatic>-2</chromatic></transpose>
+      </attributes>
+      <note><pitch><step>D</step><octave>4</octave></pitch><duration>1</duration></note>
+    </measure>
+  </part>
+</score-partwise>
+"#;
+    let score = import_clean(xml).expect("import ok");
+    let note_on = score.tracks[0]
+        .playback_events
+        .iter()
+        .find(|e| matches!(e.event, MidiLikeEvent::NoteOn { .. }))
+        .expect("note on");
+    assert!(matches!(
+        note_on.event,
+        MidiLikeEvent::NoteOn { note: 60, .. }
+    ));
+    assert_eq!(note_on.transpose, -2);
+    assert_eq!(
+        note_on.spelling.map(|pitch| pitch.to_string()).as_deref(),
+        Some("D4")
+    );
+}
+
+fn cross_staff_note(step: &str, octave: u8, staff: u8, chord: bool) -> String {
+    format!(
+        "<note>{}<pitch><step>{step}</step><octave>{octave}</octave></pitch>\
+         <duration>1</duration><voice>1</voice><staff>{staff}</staff></note>",
+        if chord { "<chord/>" } else { "" }
+    )
+}
+
+#[test]
+fn musicxml_cross_staff_voice_keeps_its_hand() {
+    // Measure 1: a right-hand arpeggio dipping into the bass staff, plus a chord whose
+    // lower note is written on staff 2. Measure 2: voice 1 moves to staff 2 for good.
+    let measure1 = [
+        cross_staff_note("G", 3, 2, false),
+        cross_staff_note("E", 4, 1, false),
+        cross_staff_note("G", 4, 1, false),
+        cross_staff_note("C", 5, 1, false),
+        cross_staff_note("C", 4, 2, true),
+    ]
+    .concat();
+    let measure2 = [
+        cross_staff_note("C", 3, 2, false),
+        cross_staff_note("E", 3, 2, false),
+        cross_staff_note("G", 3, 2, false),
+        cross_staff_note("C", 4, 2, false),
+    ]
+    .concat();
+    let xml = format!(
+        r#"
+<score-partwise version="3.1">
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>1</divisions>
+        <staves>2</staves>
+        <time><beats>4</beats><beat-type>4</beat-type></time>
+      </attributes>
+      {measure1}
+    </measure>
+    <measure number="2">{measure2}</measure>
+  </part>
+</score-partwise>
+"#
+    );
+
+    let score = import_clean(&xml).expect("import ok");
+    let targets = &score.tracks[0].targets;
+    let summary: Vec<(i64, Vec<u8>, Option<Hand>)> = targets
+        .iter()
+        .map(|t| (t.tick, t.notes.clone(), t.hand))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (0, vec![55], Some(Hand::Right)),
+            (480, vec![64], Some(Hand::Right)),
+            (960, vec![67], Some(Hand::Right)),
+            (1440, vec![60, 72], Some(Hand::Right)),
+            (1920, vec![48], Some(Hand::Left)),
+            (2400, vec![52], Some(Hand::Left)),
+            (2880, vec![55], Some(Hand::Left)),
+            (3360, vec![60], Some(Hand::Left)),
+        ]
+    );
+}
+
+#[test]
+fn musicxml_cross_staff_grace_steals_from_its_voices_hand() {
+    // Voice 1 stays in the right hand; its slashed grace and principal are written on staff 2.
+    let grace = "<note><grace slash=\"yes\"/><pitch><step>B</step><octave>3</octave></pitch>\
+                 <type>16th</type><voice>1</voice><staff>2</staff></note>";
+    let notes = [
+        cross_staff_note("E", 4, 1, false),
+        cross_staff_note("G", 4, 1, false),
+        grace.to_string(),
+        cross_staff_note("C", 4, 2, false),
+        cross_staff_note("E", 4, 1, false),
+    ]
+    .concat();
+    let xml = format!(
+        r#"
+<score-partwise version="3.1">
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>1</divisions>
+        <staves>2</staves>
+        <time><beats>4</beats><beat-type>4</beat-type></time>
+      </attributes>
+      {notes}
+    </measure>
+  </part>
+</score-partwise>
+"#
+    );
+
+    let score = import_clean(&xml).expect("import ok");
+    let mut ons = note_on_ticks(&score);
+    ons.sort();
+    assert_eq!(
+        ons,
+        vec![(0, 64), (480, 67), (900, 59), (960, 60), (1440, 64)]
+    );
+    let mut offs = note_off_ticks(&score);
+    offs.sort();
+    assert_eq!(
+        offs,
+        vec![(480, 64), (900, 67), (960, 59), (1440, 60), (1920, 64)]
+    );
+    assert!(score.tracks[0]
+        .targets
+        .iter()
+        .all(|t| t.hand == Some(Hand::Right)));
+}
+
+fn arpeggiated_chord(direction: &str) -> String {
+    let note = |step: &str, chord: bool| {
+        format!(
+            "<note>{}<pitch><step>{step}</step><octave>4</octave></pitch><duration>1</duration>\
+             <notations><arpeggiate{direction}/></notations></note>",
+            if chord { "<chord/>" } else { "" }
+        )
+    };
+    format!(
+        r#"
+<score-partwise version="3.1">
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>1</divisions>
+        <time><beats>1</beats><beat-type>4</beat-type></time>
+      </attributes>
+      <direction><sound tempo="60"/></direction>
+      {}{}{}
+    </measure>
+  </part>
+</score-partwise>
+"#,
+        note("C", false),
+        note("E", true),
+        note("G", true)
+    )
+}
+
+#[test]
+fn musicxml_arpeggiate_rolls_playback_but_keeps_one_target() {
+    // 30 ms per note at 60 BPM is 14.4 ticks, rounded to 14.
+    let score = import_clean(&arpeggiated_chord("")).expect("import ok");
+    let mut ons = note_on_ticks(&score);
+    ons.sort();
+    assert_eq!(ons, vec![(0, 60), (14, 64), (28, 67)]);
+    let mut offs = note_off_ticks(&score);
+    offs.sort();
+    assert_eq!(offs, vec![(480, 60), (480, 64), (480, 67)]);
+
+    let targets = &score.tracks[0].targets;
+    assert_eq!(targets.len(), 1);
+    assert_eq!(targets[0].tick, 0);
+    assert_eq!(targets[0].notes, vec![60, 64, 67]);
+
+    let score = import_clean(&arpeggiated_chord(r#" direction="down""#)).expect("import ok");
+    let mut ons = note_on_ticks(&score);
+    ons.sort();
+    assert_eq!(ons, vec![(0, 67), (14, 64), (28, 60)]);
+}
+
+#[test]
+fn musicxml_fermata_on_final_chord_slows_tempo() {
+    let xml = r#"
+<score-partwise version="3.1">
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>1</divisions>
+        <time><beats>3</beats><beat-type>4</beat-type></time>
+      </attributes>
+      <note>
+        <pitch><step>C</step><octave>4</octave></pitch>
+        <duration>1</duration>
+        <notations><articulations><breath-mark/></articulations></notations>
+      </note>
+      <note>
+        <pitch><step>E</step><octave>4</octave></pitch>
+        <duration>2</duration>
+        <notations><fermata type="upright"/></notations>
+      </note>
+      <note>
+        <chord/>
+        <pitch><step>G</step><octave>4</octave></pitch>
+        <duration>2</duration>
+        <notations><fermata type="upright"/></notations>
+      </note>
+    </measure>
+  </part>
+</score-partwise>
+"#;
+
+    let score = import_clean(xml).expect("import ok");
+    let tempo: Vec<(i64, u32)> = score
+        .tempo_map
+        .iter()
+        .map(|p| (p.tick, p.us_per_quarter))
+        .collect();
+    assert_eq!(tempo, vec![(0, 500_000), (480, 900_000), (1440, 500_000)]);
+
+    let mut ons = note_on_ticks(&score);
+    ons.sort();
+    assert_eq!(ons, vec![(0, 60), (480, 64), (480, 67)]);
+    let mut offs = note_off_ticks(&score);
+    offs.sort();
+    assert_eq!(offs, vec![(420, 60), (1440, 64), (1440, 67)]);
+}
+
+#[test]
+fn musicxml_title_and_composer_fall_back_to_credits() {
+    let xml = r#"
+<score-partwise version="3.1">
+  <credit page="1">
+    <credit-words default-x="600" default-y="1500" font-size="24" justify="center">Gymnopédie No. 1</credit-words>
+  </credit>
+  <credit page="1">
+    <credit-words default-x="600" default-y="1440" font-size="24" justify="center">Lent et douloureux</credit-words>
+  </credit>
+  <credit page="1">
+    <credit-words default-x="1100" default-y="1400" font-size="12" justify="right">Erik Satie</credit-words>
+  </credit>
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes><divisions>1</divisions></attributes>
+      <note>
+        <pitch><step>C</step><octave>4</octave></pitch>
+        <duration>1</duration>
+      </note>
+    </measure>
+  </part>
+</score-partwise>
+"#;
+    let score = import_clean(xml).expect("import ok");
+    assert_eq!(score.meta.title.as_deref(), Some("Gymnopédie No. 1"));
+    assert_eq!(score.meta.composer.as_deref(), Some("Erik Satie"));
+
+    let with_movement = xml.replace(
+        "<part-list>",
+        "<movement-title>Trois Gymnopédies</movement-title>\n  <part-list>",
+    );
+    let score = import_clean(&with_movement).expect("import ok");
+    assert_eq!(score.meta.title.as_deref(), Some("Trois Gymnopédies"));
+
+    let typed = xml.replace(
+        "<credit-words default-x=\"1100\"",
+        "<credit-type>composer</credit-type>\n    <credit-words default-x=\"1100\"",
+    );
+    let typed = typed.replace(
+        "<credit page=\"1\">\n    <credit-words default-x=\"600\" default-y=\"1440\"",
+        "<credit page=\"1\">\n    <credit-type>title</credit-type>\n    <credit-words default-x=\"600\" default-y=\"1440\"",
+    );
+    let score = import_clean(&typed).expect("import ok");
+    assert_eq!(score.meta.title.as_deref(), Some("Lent et douloureux"));
+    assert_eq!(score.meta.composer.as_deref(), Some("Erik Satie"));
+}
+
+#[test]
+fn musicxml_key_signature_changes_are_captured() {
+    let xml = r#"
+<score-partwise version="3.1">
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>1</divisions>
+        <key><fifths>2</fifths><mode>major</mode></key>
+        <time><beats>2</beats><beat-type>4</beat-type></time>
+      </attributes>
+      <note>
+        <pitch><step>D</step><octave>4</octave></pitch>
+        <duration>2</duration>
+      </note>
+    </measure>
+    <measure number="2">
+      <attributes>
+        <key><fifths>2</fifths><mode>major</mode></key>
+      </attributes>
+      <note>
+        <pitch><step>D</step><octave>4</octave></pitch>
+        <duration>2</duration>
+      </note>
+    </measure>
+    <measure number="3">
+      <attributes>
+        <key><fifths>-3</fifths><mode>minor</mode></key>
+      </attributes>
+      <note>
+        <pitch><step>C</step><octave>4</octave></pitch>
+        <duration>2</duration>
+      </note>
+    </measure>
+  </part>
+</score-partwise>
+"#;
+    let score = import_clean(xml).expect("import ok");
+    assert_eq!(
+        score.key_signatures,
+        vec![
+            KeySignaturePoint {
+                tick: 0,
+                fifths: 2,
+                mode: KeyMode::Major,
+            },
+            KeySignaturePoint {
+                tick: 1920,
+                fifths: -3,
+                mode: KeyMode::Minor,
+            },
+        ]
+    );
+}
+
+#[test]
+fn musicxml_streaming_import_handles_prolog_and_empty_measures() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<!DOCTYPE score-partwise PUBLIC "-//Recordare//DTD MusicXML 3.1 Partwise//EN" "http://www.musicxml.org/dtds/partwise.dtd">
+<score-partwise version="3.1">
+  <work><work-title>Étude</work-title></work>
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+    <score-part id="P2"><part-name>Cello</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>2</divisions>
+        <time><beats>2</beats><beat-type>4</beat-type></time>
+      </attributes>
+      <direction><sound tempo="72"/></direction>
+      <note>
+        <pitch><step>G</step><octave>4</octave></pitch>
+        <duration>4</duration>
+      </note>
+    </measure>
+    <measure number="2"/>
+    <measure number="3">
+      <note>
+        <pitch><step>A</step><octave>4</octave></pitch>
+        <duration>2</duration>
+      </note>
+    </measure>
+  </part>
+  <part id="P2"/>
+</score-partwise>
+"#;
+    let score = import_clean(xml).expect("import ok");
+    assert_eq!(score.meta.title.as_deref(), Some("Étude"));
+    assert_eq!(score.tracks.len(), 2);
+    assert_eq!(note_on_ticks(&score), vec![(0, 67), (1920, 69)]);
+}
+
+#[test]
+fn musicxml_streaming_import_keeps_header_entities_and_namespaces() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE score-partwise [
+  <!ENTITY octave "5">
+]>
+<score-partwise version="3.1" xmlns:xlink="http://www.w3.org/1999/xlink">
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>1</divisions>
+        <time><beats>2</beats><beat-type>4</beat-type></time>
+      </attributes>
+      <note xlink:type="simple">
+        <pitch><step>C</step><octave>&octave;</octave></pitch>
+        <duration>2</duration>
+      </note>
+    </measure>
+  </part>
+</score-partwise>
+"#;
+    let score = import_clean(xml).expect("import ok");
+    assert_eq!(note_on_ticks(&score), vec![(0, 72)]);
+}
+
+#[test]
+fn musicxml_streaming_import_reads_files_from_disk() {
+    let xml = slurred_scale();
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("clock")
+        .as_nanos();
+    let path = std::env::temp_dir().join(format!("cadenza-streaming-{nanos}.musicxml"));
+    std::fs::write(&path, &xml).expect("write fixture");
+    let streaming_options = MusicXmlImportOptions {
+        streaming_threshold_bytes: 0,
+        ..MusicXmlImportOptions::default()
+    };
+    let streamed = import_musicxml_path_with_report(&path, &streaming_options);
+    std::fs::remove_file(&path).ok();
+    let streamed = streamed.expect("import ok");
+    let dom = import_both_paths(&xml).expect("import ok");
+    assert_eq!(
+        serde_json::to_value(&dom.score).expect("serialize"),
+        serde_json::to_value(&streamed.score).expect("serialize")
+    );
+}
+
+#[test]
+fn musicxml_import_reports_dropped_graces_ties_and_unknown_dynamics() {
+    let xml = r#"
+<score-partwise version="3.1">
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>1</divisions>
+        <time><beats>2</beats><beat-type>4</beat-type></time>
+      </attributes>
+      <direction>
+        <direction-type><dynamics><rfz/></dynamics></direction-type>
+      </direction>
+      <note>
+        <grace/>
+        <pitch><step>D</step><octave>4</octave></pitch>
+        <type>eighth</type>
+      </note>
+      <note><rest/><duration>1</duration></note>
+      <note>
+        <pitch><step>C</step><octave>4</octave></pitch>
+        <duration>1</duration>
+        <tie type="stop"/>
+      </note>
+    </measure>
+    <measure number="2">
+      <note>
+        <pitch><step>E</step><octave>4</octave></pitch>
+        <duration>2</duration>
+        <tie type="start"/>
+      </note>
+    </measure>
+  </part>
+</score-partwise>
+"#;
+    let import = import_both_paths(xml).expect("import ok");
+    assert_eq!(
+        warning_kinds(&import),
+        vec![
+            MusicXmlWarningKind::UnknownDynamics,
+            MusicXmlWarningKind::DroppedGraceNote,
+            MusicXmlWarningKind::UnmatchedTie,
+            MusicXmlWarningKind::UnmatchedTie,
+        ]
+    );
+    assert_eq!(
+        import.warnings[3].to_string(),
+        "part 1, measure 2: tie start on note 64 never stopped"
+    );
+    // The unmatched notes still play.
+    assert_eq!(note_on_ticks(&import.score), vec![(480, 60), (960, 64)]);
+}
+
+fn slurred_note(step: &str, slur: &str) -> String {
+    let notations = match slur {
+        "" => String::new(),
+        kind => format!(r#"<notations><slur type="{kind}" number="1"/></notations>"#),
+    };
+    format!(
+        r#"<note><pitch><step>{step}</step><octave>4</octave></pitch><duration>1</duration>{notations}</note>"#
+    )
+}
+
+/// C D E E slurred across the barline into F, then G A A without a slur.
+fn slurred_scale() -> String {
+    let first: String = [("C", "start"), ("D", ""), ("E", ""), ("E", "")]
+        .iter()
+        .map(|(step, slur)| slurred_note(step, slur))
+        .collect();
+    let second: String = [("F", "stop"), ("G", ""), ("A", ""), ("A", "")]
+        .iter()
+        .map(|(step, slur)| slurred_note(step, slur))
+        .collect();
+    format!(
+        r#"
+<score-partwise version="3.1">
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>1</divisions>
+        <time><beats>4</beats><beat-type>4</beat-type></time>
+      </attributes>
+      {first}
+    </measure>
+    <measure number="2">{second}</measure>
+  </part>
+</score-partwise>
+"#
+    )
+}
+
+#[test]
+fn musicxml_slurred_notes_overlap_the_next_note() {
+    let score = import_clean(&slurred_scale()).expect("import ok");
+    let ticks: Vec<i64> = score.tracks[0].targets.iter().map(|t| t.tick).collect();
+    assert_eq!(ticks, vec![0, 480, 960, 1440, 1920, 2400, 2880, 3360]);
+
+    let mut offs = note_off_ticks(&score);
+    offs.sort();
+    assert_eq!(
+        offs,
+        vec![
+            // Held 10% of a quarter into the next note, except into the repeated E.
+            (528, 60),
+            (1008, 62),
+            (1439, 64),
+            (1968, 64),
+            // The slur ends on F; from there notes end on the next attack as before.
+            (2400, 65),
+            (2880, 67),
+            (3359, 69),
+            (3840, 69),
+        ]
+    );
+
+    let options = MusicXmlImportOptions {
+        slur_overlap_percent: 25,
+        ..MusicXmlImportOptions::default()
+    };
+    let score = import_musicxml_str_with_options(&slurred_scale(), &options).expect("import ok");
+    let offs = note_off_ticks(&score);
+    assert!(offs.contains(&(600, 60)), "{offs:?}");
+}
+
+fn single_part(measures: &str) -> String {
+    format!(
+        r#"
+<score-partwise version="3.1">
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>1</divisions>
+        <time><beats>4</beats><beat-type>4</beat-type></time>
+      </attributes>
+      <note><pitch><step>C</step><octave>4</octave></pitch><duration>4</duration></note>
+    </measure>
+    {measures}
+  </part>
+</score-partwise>
+"#
+    )
+}
+
+const WHOLE_REST: &str = r#"<note><rest measure="yes"/><duration>4</duration></note>"#;
+
+fn target_positions(score: &Score) -> Vec<(i64, Option<u32>)> {
+    score.tracks[0]
+        .targets
+        .iter()
+        .map(|target| (target.tick, target.measure_index))
+        .collect()
+}
+
+#[test]
+fn musicxml_multi_rest_covers_all_its_bars() {
+    let multi_rest = format!(
+        r#"<measure number="2">
+      <attributes><measure-style><multiple-rest>4</multiple-rest></measure-style></attributes>
+      {WHOLE_REST}
+    </measure>"#
+    );
+    let d_in_bar_6 = r#"<measure number="6">
+      <note><pitch><step>D</step><octave>4</octave></pitch><duration>4</duration></note>
+    </measure>"#;
+
+    // Only the first bar of the rest is written, as OMR output often has it.
+    let import =
+        import_both_paths(&single_part(&format!("{multi_rest}{d_in_bar_6}"))).expect("import ok");
+    assert_eq!(
+        target_positions(&import.score),
+        vec![(0, Some(0)), (9_600, Some(5))]
+    );
+    assert_eq!(
+        warning_kinds(&import),
+        vec![MusicXmlWarningKind::ExpandedMultiRest]
+    );
+    assert_eq!(
+        import.warnings[0].to_string(),
+        "part 1, measure 2: multi-rest expanded by 3 empty measures"
+    );
+
+    // Written out in full, the bars are not counted twice.
+    let written: String = (3..=5)
+        .map(|number| format!(r#"<measure number="{number}">{WHOLE_REST}</measure>"#))
+        .collect();
+    let score = import_clean(&single_part(&format!("{multi_rest}{written}{d_in_bar_6}")))
+        .expect("import ok");
+    assert_eq!(
+        target_positions(&score),
+        vec![(0, Some(0)), (9_600, Some(5))]
+    );
+}
+
+#[test]
+fn musicxml_measure_repeat_replays_the_previous_bar() {
+    let scale = r#"<measure number="1">
+      <attributes>
+        <divisions>1</divisions>
+        <time><beats>4</beats><beat-type>4</beat-type></time>
+      </attributes>
+      <note><pitch><step>C</step><octave>4</octave></pitch><duration>1</duration></note>
+      <note><pitch><step>D</step><octave>4</octave></pitch><duration>1</duration></note>
+      <note><pitch><step>E</step><octave>4</octave></pitch><duration>1</duration></note>
+      <note><pitch><step>F</step><octave>4</octave></pitch><duration>1</duration></note>
+    </measure>"#;
+    let xml = format!(
+        r#"
+<score-partwise version="3.1">
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    {scale}
+    <measure number="2">
+      <attributes>
+        <measure-style><measure-repeat type="start" slashes="1">1</measure-repeat></measure-style>
+      </attributes>
+      {WHOLE_REST}
+    </measure>
+    <measure number="3">
+      <attributes><measure-style><measure-repeat type="stop"/></measure-style></attributes>
+      <note><pitch><step>G</step><octave>4</octave></pitch><duration>4</duration></note>
+    </measure>
+  </part>
+</score-partwise>
+"#
+    );
+
+    let import = import_both_paths(&xml).expect("import ok");
+    assert_eq!(
+        target_positions(&import.score),
+        vec![
+            (0, Some(0)),
+            (480, Some(0)),
+            (960, Some(0)),
+            (1440, Some(0)),
+            (1920, Some(1)),
+            (2400, Some(1)),
+            (2880, Some(1)),
+            (3360, Some(1)),
+            (3840, Some(2)),
+        ]
+    );
+    assert_eq!(
+        note_on_ticks(&import.score)[4..8],
+        [(1920, 60), (2400, 62), (2880, 64), (3360, 65)]
+    );
+    assert_eq!(
+        warning_kinds(&import),
+        vec![MusicXmlWarningKind::ExpandedMeasureRepeat]
+    );
+}
+
+#[test]
+fn musicxml_keeps_the_written_spelling_of_each_note() {
+    let xml = r#"
+<score-partwise version="3.1">
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>1</divisions>
+        <key><fifths>-1</fifths></key>
+        <time><beats>2</beats><beat-type>4</beat-type></time>
+      </attributes>
+      <note><pitch><step>B</step><alter>-1</alter><octave>4</octave></pitch><duration>1</duration></note>
+      <note><pitch><step>A</step><alter>1</alter><octave>4</octave></pitch><duration>1</duration></note>
+    </measure>
+  </part>
+</score-partwise>
+"#;
+    let score = import_clean(xml).expect("import ok");
+    let spelled: Vec<(u8, String)> = score.tracks[0]
+        .playback_events
+        .iter()
+        .filter_map(|e| match e.event {
+            MidiLikeEvent::NoteOn { note, .. } => Some((note, e.spelling?.to_string())),
+            _ => None,
+        })
+        .collect();
+    // Both sound the same key; the second keeps its sharp although F major would flatten it.
+    assert_eq!(
+        spelled,
+        vec![(70, "Bb4".to_string()), (70, "A#4".to_string())]
+    );
 }
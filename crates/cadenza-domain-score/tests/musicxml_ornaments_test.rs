@@ -0,0 +1,124 @@
+use cadenza_domain_score::{
+    export_midi_path_with_options, import_midi_path, import_musicxml_str,
+    import_musicxml_str_with_options, ExportOptions, MusicXmlImportOptions, Score,
+};
+use cadenza_ports::midi::MidiLikeEvent;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const HALF_NOTE_TRILL: &str = r#"
+<score-partwise version="3.1">
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>1</divisions>
+        <key><fifths>0</fifths></key>
+        <time><beats>2</beats><beat-type>4</beat-type></time>
+      </attributes>
+      <note>
+        <pitch><step>E</step><octave>4</octave></pitch>
+        <duration>2</duration>
+        <notations><ornaments><trill-mark/></ornaments></notations>
+      </note>
+    </measure>
+  </part>
+</score-partwise>
+"#;
+
+fn note_ons(score: &Score) -> Vec<(i64, u8)> {
+    score.tracks[0]
+        .playback_events
+        .iter()
+        .filter_map(|e| match e.event {
+            MidiLikeEvent::NoteOn { note, .. } => Some((e.tick, note)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn musicxml_half_note_trill_expands_playback_only() {
+    let score = import_musicxml_str(HALF_NOTE_TRILL).expect("import ok");
+
+    // Default rate is eight notes per quarter: 16 alternating E/F over a half note in C major.
+    let ons = note_ons(&score);
+    assert_eq!(ons.len(), 16);
+    for (idx, (tick, note)) in ons.iter().enumerate() {
+        assert_eq!(*tick, idx as i64 * 60);
+        assert_eq!(*note, if idx % 2 == 0 { 64 } else { 65 });
+    }
+    assert!(score.tracks[0]
+        .playback_events
+        .iter()
+        .all(|e| e.ornament_of == Some(64)));
+
+    let targets = &score.tracks[0].targets;
+    assert_eq!(targets.len(), 1);
+    assert_eq!(targets[0].notes, vec![64]);
+
+    let options = MusicXmlImportOptions {
+        expand_ornaments: false,
+        ..MusicXmlImportOptions::default()
+    };
+    let plain = import_musicxml_str_with_options(HALF_NOTE_TRILL, &options).expect("import ok");
+    assert_eq!(note_ons(&plain), vec![(0, 64)]);
+}
+
+#[test]
+fn musicxml_trill_on_a_transposed_part_takes_its_neighbor_from_the_written_key() {
+    // Clarinet in Bb: written E4 in C major trills to F4, sounding D4 to Eb4.
+    let xml = HALF_NOTE_TRILL
+        .replace(
+            "<part-name>Piano</part-name>",
+            "<part-name>Clarinet in Bb</part-name>",
+        )
+        .replace(
+            "</time>",
+            "</time>\n        <transpose><diatonic>-1</diatonic><chromatic>-2</chromatic></transpose>",
+        );
+    let score = import_musicxml_str(&xml).expect("import ok");
+    let ons = note_ons(&score);
+    assert_eq!(ons.len(), 16);
+    for (idx, (_, note)) in ons.iter().enumerate() {
+        assert_eq!(*note, if idx % 2 == 0 { 62 } else { 63 });
+    }
+}
+
+#[test]
+fn midi_export_can_collapse_expanded_trill() {
+    let score = import_musicxml_str(HALF_NOTE_TRILL).expect("import ok");
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let path = std::env::temp_dir().join(format!("cadenza-collapse-trill-{nanos}.mid"));
+
+    let options = ExportOptions {
+        collapse_ornaments: true,
+        ..ExportOptions::default()
+    };
+    export_midi_path_with_options(&score, &path, options).expect("export should succeed");
+    let loaded = import_midi_path(&path).expect("import should succeed");
+    let _ = std::fs::remove_file(&path);
+
+    let events: Vec<(i64, MidiLikeEvent)> = loaded.tracks[0]
+        .playback_events
+        .iter()
+        .map(|e| (e.tick, e.event))
+        .collect();
+    assert_eq!(
+        events,
+        vec![
+            (
+                0,
+                MidiLikeEvent::NoteOn {
+                    note: 64,
+                    velocity: 90
+                }
+            ),
+            (960, MidiLikeEvent::NoteOff { note: 64 }),
+        ]
+    );
+}
@@ -0,0 +1,91 @@
+use cadenza_domain_score::{
+    import_musicxml_str, import_musicxml_str_with_options, MusicXmlImportOptions, PartSelection,
+};
+
+const VIOLIN_AND_PIANO: &str = r#"
+<score-partwise version="3.1">
+  <part-list>
+    <score-part id="P1"><part-name>Violin</part-name></score-part>
+    <score-part id="P2"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>1</divisions>
+        <time><beats>2</beats><beat-type>4</beat-type></time>
+      </attributes>
+      <direction><sound tempo="90"/></direction>
+      <note>
+        <pitch><step>A</step><octave>5</octave></pitch>
+        <duration>2</duration>
+      </note>
+    </measure>
+  </part>
+  <part id="P2">
+    <measure number="1">
+      <attributes>
+        <divisions>1</divisions>
+        <time><beats>2</beats><beat-type>4</beat-type></time>
+      </attributes>
+      <note>
+        <pitch><step>C</step><octave>4</octave></pitch>
+        <duration>1</duration>
+        <staff>1</staff>
+      </note>
+      <note>
+        <pitch><step>E</step><octave>4</octave></pitch>
+        <duration>1</duration>
+        <staff>1</staff>
+      </note>
+    </measure>
+  </part>
+</score-partwise>
+"#;
+
+fn import_with(parts: PartSelection) -> cadenza_domain_score::Score {
+    let options = MusicXmlImportOptions {
+        parts,
+        ..MusicXmlImportOptions::default()
+    };
+    import_musicxml_str_with_options(VIOLIN_AND_PIANO, &options).expect("import ok")
+}
+
+#[test]
+fn musicxml_parts_become_named_tracks() {
+    let score = import_musicxml_str(VIOLIN_AND_PIANO).expect("import ok");
+    assert_eq!(score.tracks.len(), 2);
+
+    let violin = &score.tracks[0];
+    assert_eq!(violin.name, "Violin");
+    assert_eq!(violin.targets.len(), 1);
+    assert_eq!(violin.targets[0].notes, vec![81]);
+
+    let piano = &score.tracks[1];
+    assert_eq!(piano.name, "Piano");
+    let notes: Vec<Vec<u8>> = piano.targets.iter().map(|t| t.notes.clone()).collect();
+    assert_eq!(notes, vec![vec![60], vec![64]]);
+
+    let merged = score.merged_track().expect("merged track");
+    let merged_notes: Vec<Vec<u8>> = merged.targets.iter().map(|t| t.notes.clone()).collect();
+    assert_eq!(merged_notes, vec![vec![60, 81], vec![64]]);
+    let ids: Vec<u64> = merged.targets.iter().map(|t| t.id).collect();
+    assert_eq!(ids, vec![1, 2]);
+}
+
+#[test]
+fn musicxml_part_selection_filters_tracks_but_keeps_tempo() {
+    let score = import_with(PartSelection::PianoLike);
+    assert_eq!(score.tracks.len(), 1);
+    assert_eq!(score.tracks[0].name, "Piano");
+    assert_eq!(score.tempo_map[0].us_per_quarter, 666_666);
+
+    let score = import_with(PartSelection::Indices(vec![0]));
+    assert_eq!(score.tracks.len(), 1);
+    assert_eq!(score.tracks[0].name, "Violin");
+
+    let options = MusicXmlImportOptions {
+        parts: PartSelection::Indices(vec![5]),
+        ..MusicXmlImportOptions::default()
+    };
+    assert!(import_musicxml_str_with_options(VIOLIN_AND_PIANO, &options).is_err());
+}
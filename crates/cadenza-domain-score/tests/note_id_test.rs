@@ -0,0 +1,149 @@
+use cadenza_domain_score::{
+    import_musicxml_str, load_scorefile_path, save_scorefile_path, NoteId, Score, ScoreFile,
+};
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::types::Tick;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A two-note chord over a bass note, then a mordent on G4.
+const CHORDS_AND_MORDENT: &str = r#"
+<score-partwise version="3.1">
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>1</divisions>
+        <time><beats>2</beats><beat-type>4</beat-type></time>
+        <staves>2</staves>
+      </attributes>
+      <note><pitch><step>C</step><octave>5</octave></pitch><duration>1</duration><staff>1</staff></note>
+      <note><chord/><pitch><step>E</step><octave>5</octave></pitch><duration>1</duration><staff>1</staff></note>
+      <note>
+        <pitch><step>G</step><octave>4</octave></pitch>
+        <duration>1</duration>
+        <staff>1</staff>
+        <notations><ornaments><mordent/></ornaments></notations>
+      </note>
+      <backup><duration>2</duration></backup>
+      <note><pitch><step>C</step><octave>3</octave></pitch><duration>2</duration><staff>2</staff></note>
+    </measure>
+  </part>
+</score-partwise>
+"#;
+
+fn note_on_ids(score: &Score) -> Vec<(Tick, u8, NoteId)> {
+    score
+        .tracks
+        .iter()
+        .flat_map(|track| &track.playback_events)
+        .filter_map(|e| match e.event {
+            MidiLikeEvent::NoteOn { note, .. } => Some((e.tick, note, e.note_id?)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn every_note_on_gets_a_unique_id_that_its_target_shares() {
+    let score = import_musicxml_str(CHORDS_AND_MORDENT).expect("import ok");
+    let ons = note_on_ids(&score);
+    let all_ons = score
+        .tracks
+        .iter()
+        .flat_map(|track| &track.playback_events)
+        .filter(|e| matches!(e.event, MidiLikeEvent::NoteOn { .. }))
+        .count();
+    assert_eq!(ons.len(), all_ons);
+    let unique: HashSet<NoteId> = ons.iter().map(|&(_, _, id)| id).collect();
+    assert_eq!(unique.len(), ons.len());
+    // Each note off carries the id of the note on it ends, and nothing else has one.
+    for event in score.tracks.iter().flat_map(|track| &track.playback_events) {
+        match event.event {
+            MidiLikeEvent::NoteOn { .. } => {}
+            MidiLikeEvent::NoteOff { note } => {
+                let id = event.note_id.expect("note off id");
+                assert!(ons.iter().any(|&(_, on, on_id)| on == note && on_id == id));
+            }
+            _ => assert_eq!(event.note_id, None),
+        }
+    }
+
+    for track in &score.tracks {
+        for target in &track.targets {
+            assert_eq!(target.note_ids.len(), target.notes.len(), "{target:?}");
+            for (&note, id) in target.notes.iter().zip(&target.note_ids) {
+                let on = ons
+                    .iter()
+                    .find(|(_, _, on_id)| on_id == id)
+                    .unwrap_or_else(|| panic!("no note on for {id:?}"));
+                assert_eq!(on.0, target.tick);
+                // The mordent's first note stands in for the written G.
+                let ornament = track
+                    .playback_events
+                    .iter()
+                    .any(|e| e.note_id == Some(*id) && e.ornament_of == Some(note));
+                assert!(on.1 == note || ornament, "{target:?} {on:?}");
+            }
+        }
+    }
+
+    // The practice track keeps each pitch with its own id.
+    let written: HashSet<(Tick, u8, NoteId)> = score
+        .tracks
+        .iter()
+        .flat_map(|track| &track.targets)
+        .flat_map(|t| {
+            t.notes
+                .iter()
+                .zip(&t.note_ids)
+                .map(|(&n, &id)| (t.tick, n, id))
+        })
+        .collect();
+    let merged = score.merged_track().expect("track");
+    for target in &merged.targets {
+        assert_eq!(target.note_ids.len(), target.notes.len(), "{target:?}");
+        for (&note, &id) in target.notes.iter().zip(&target.note_ids) {
+            assert!(written.contains(&(target.tick, note, id)), "{target:?}");
+        }
+    }
+    let first = &merged.targets[0];
+    assert_eq!(first.notes, vec![48, 72, 76]);
+    assert_eq!(first.note_ids.len(), 3);
+}
+
+#[test]
+fn ids_survive_a_project_round_trip_and_are_assigned_to_old_files() {
+    let score = import_musicxml_str(CHORDS_AND_MORDENT).expect("import ok");
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let path: PathBuf = std::env::temp_dir().join(format!("cadenza-note-ids-{nanos}.cadenza"));
+
+    save_scorefile_path(&ScoreFile::new(score.clone(), Vec::new()), &path).expect("save");
+    let loaded = load_scorefile_path(&path).expect("load");
+    assert_eq!(note_on_ids(&loaded.score), note_on_ids(&score));
+
+    // A file written before notes had ids.
+    let mut old = score.clone();
+    for track in &mut old.tracks {
+        for event in &mut track.playback_events {
+            event.note_id = None;
+        }
+        for target in &mut track.targets {
+            target.note_ids.clear();
+        }
+    }
+    save_scorefile_path(&ScoreFile::new(old, Vec::new()), &path).expect("save");
+    let loaded = load_scorefile_path(&path).expect("load");
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(note_on_ids(&loaded.score), note_on_ids(&score));
+    assert_eq!(
+        loaded.score.tracks[0].targets[0].note_ids,
+        score.tracks[0].targets[0].note_ids
+    );
+}
@@ -0,0 +1,228 @@
+use cadenza_domain_score::{
+    cleanup_omr_score, import_musicxml_str_with_report, import_omr_musicxml_path, ImportWarning,
+    MusicXmlImport, MusicXmlImportOptions, OmrCleanupKind, OmrCleanupOptions, PlaybackMidiEvent,
+    Score, ScoreMeta, ScoreSource, Track,
+};
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::types::Tick;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Sixteen divisions a quarter, so a division is a 64th of 30 ticks. Bar 1 has an isolated
+/// 64th E5 and a 64th G4 struck with a C4 in the other voice; bar 2 has D5 in both voices.
+const NOISY: &str = r#"
+<score-partwise version="3.1">
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>16</divisions>
+        <time><beats>4</beats><beat-type>4</beat-type></time>
+      </attributes>
+      <note><pitch><step>C</step><octave>5</octave></pitch><duration>16</duration><voice>1</voice></note>
+      <note><pitch><step>E</step><octave>5</octave></pitch><duration>1</duration><voice>1</voice></note>
+      <note><rest/><duration>15</duration><voice>1</voice></note>
+      <note><pitch><step>G</step><octave>4</octave></pitch><duration>1</duration><voice>1</voice></note>
+      <note><rest/><duration>15</duration><voice>1</voice></note>
+      <note><pitch><step>A</step><octave>4</octave></pitch><duration>16</duration><voice>1</voice></note>
+      <backup><duration>64</duration></backup>
+      <note><rest/><duration>32</duration><voice>2</voice></note>
+      <note><pitch><step>C</step><octave>4</octave></pitch><duration>32</duration><voice>2</voice></note>
+    </measure>
+    <measure number="2">
+      <note><pitch><step>D</step><octave>5</octave></pitch><duration>64</duration><voice>1</voice></note>
+      <backup><duration>64</duration></backup>
+      <note><pitch><step>D</step><octave>5</octave></pitch><duration>32</duration><voice>2</voice></note>
+      <note><rest/><duration>32</duration><voice>2</voice></note>
+    </measure>
+  </part>
+</score-partwise>
+"#;
+
+fn import_noisy(cleanup: Option<&OmrCleanupOptions>) -> MusicXmlImport {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let path = std::env::temp_dir().join(format!("cadenza-omr-noisy-{nanos}.xml"));
+    std::fs::write(&path, NOISY).expect("write");
+    let import = import_omr_musicxml_path(&path, Path::new("noisy.pdf"), cleanup);
+    let _ = std::fs::remove_file(&path);
+    import.expect("import")
+}
+
+fn cleanup_kinds(import: &MusicXmlImport) -> Vec<(Tick, OmrCleanupKind)> {
+    import
+        .warnings
+        .iter()
+        .filter_map(|warning| match warning {
+            ImportWarning::OmrCleanup { tick, kind, .. } => Some((*tick, *kind)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Note ons with their lengths, in order.
+fn notes(score: &Score) -> Vec<(Tick, u8, Tick)> {
+    let events = &score.tracks[0].playback_events;
+    events
+        .iter()
+        .filter_map(|event| match event.event {
+            MidiLikeEvent::NoteOn { note, .. } => {
+                let end = events
+                    .iter()
+                    .find(|off| {
+                        matches!(off.event, MidiLikeEvent::NoteOff { .. })
+                            && off.note_id == event.note_id
+                    })
+                    .expect("note off")
+                    .tick;
+                Some((event.tick, note, end - event.tick))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn isolated_specks_are_dropped_and_unisons_merged() {
+    let uncleaned = import_noisy(None);
+    assert!(matches!(uncleaned.score.meta.source, ScoreSource::PdfOmr));
+    assert!(cleanup_kinds(&uncleaned).is_empty());
+    assert_eq!(notes(&uncleaned.score).len(), 7);
+
+    let import = import_noisy(Some(&OmrCleanupOptions::default()));
+    assert_eq!(
+        cleanup_kinds(&import),
+        vec![
+            (1920, OmrCleanupKind::MergedUnison),
+            (480, OmrCleanupKind::DroppedShortNote),
+        ]
+    );
+    assert_eq!(
+        import.warnings[1].to_string(),
+        "track 1, measure 1: isolated 30-tick E5 dropped"
+    );
+    assert_eq!(
+        notes(&import.score),
+        vec![
+            (0, 72, 480),
+            // Kept: the C4 starts with it.
+            (960, 60, 960),
+            (960, 67, 30),
+            (1440, 69, 480),
+            // The longer of the two.
+            (1920, 74, 1920),
+        ]
+    );
+    let targets = &import.score.tracks[0].targets;
+    assert_eq!(
+        targets.iter().map(|t| t.tick).collect::<Vec<_>>(),
+        vec![0, 960, 1440, 1920]
+    );
+    // The surviving D5 is the target's note.
+    let d5 = import.score.tracks[0]
+        .playback_events
+        .iter()
+        .find(|event| event.tick == 1920 && matches!(event.event, MidiLikeEvent::NoteOn { .. }))
+        .and_then(|event| event.note_id);
+    assert_eq!(targets[3].note_ids, vec![d5.expect("id")]);
+}
+
+#[test]
+fn specks_can_be_kept_as_optional_targets() {
+    let options = OmrCleanupOptions {
+        mark_optional: true,
+        ..OmrCleanupOptions::default()
+    };
+    let import = import_noisy(Some(&options));
+    assert_eq!(
+        cleanup_kinds(&import),
+        vec![
+            (1920, OmrCleanupKind::MergedUnison),
+            (480, OmrCleanupKind::OptionalShortNote),
+        ]
+    );
+    assert_eq!(notes(&import.score).len(), 6);
+    let optional: Vec<Tick> = import.score.tracks[0]
+        .targets
+        .iter()
+        .filter(|t| t.optional)
+        .map(|t| t.tick)
+        .collect();
+    assert_eq!(optional, vec![480]);
+}
+
+#[test]
+fn outlier_velocities_are_clamped_only_in_omr_scores() {
+    let note_on = |tick: Tick, note: u8, velocity: u8| PlaybackMidiEvent {
+        tick,
+        event: MidiLikeEvent::NoteOn { note, velocity },
+        hand: None,
+        ornament_of: None,
+        bus: None,
+        note_id: None,
+        spelling: None,
+        transpose: 0,
+        articulations: Vec::new(),
+    };
+    let note_off = |tick: Tick, note: u8| PlaybackMidiEvent {
+        event: MidiLikeEvent::NoteOff { note },
+        ..note_on(tick, note, 0)
+    };
+    let mut score = Score::new(
+        ScoreMeta {
+            title: None,
+            composer: None,
+            copyright: None,
+            source: ScoreSource::MusicXml,
+            origin_path: None,
+        },
+        480,
+    );
+    score.tracks = vec![Track {
+        id: 1,
+        name: "Piano".to_string(),
+        hand: None,
+        targets: Vec::new(),
+        playback_events: vec![
+            note_on(0, 60, 127),
+            note_off(480, 60),
+            note_on(480, 62, 80),
+            note_off(960, 62),
+            note_on(960, 64, 8),
+            note_off(1440, 64),
+        ],
+    }];
+    score.assign_note_ids();
+
+    assert!(cleanup_omr_score(&mut score, &OmrCleanupOptions::default()).is_empty());
+
+    score.meta.source = ScoreSource::PdfOmr;
+    let warnings = cleanup_omr_score(&mut score, &OmrCleanupOptions::default());
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(
+        warnings[1].to_string(),
+        "track 1, tick 960: E4 velocity 8 clamped to 24"
+    );
+    let velocities: Vec<u8> = score.tracks[0]
+        .playback_events
+        .iter()
+        .filter_map(|event| match event.event {
+            MidiLikeEvent::NoteOn { velocity, .. } => Some(velocity),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(velocities, vec![112, 80, 24]);
+}
+
+#[test]
+fn plain_musicxml_imports_are_not_cleaned() {
+    let mut import =
+        import_musicxml_str_with_report(NOISY, &MusicXmlImportOptions::default()).expect("import");
+    assert!(matches!(import.score.meta.source, ScoreSource::MusicXml));
+    assert!(cleanup_omr_score(&mut import.score, &OmrCleanupOptions::default()).is_empty());
+    assert_eq!(notes(&import.score).len(), 7);
+}
@@ -0,0 +1,119 @@
+use cadenza_domain_score::{
+    key_signature_at, note_on_spelling, spell, Hand, KeyMode, KeySignature, KeySignaturePoint,
+    PlaybackMidiEvent, SpelledPitch, Step,
+};
+use cadenza_ports::midi::MidiLikeEvent;
+
+fn key(fifths: i8, mode: KeyMode) -> KeySignature {
+    KeySignature { fifths, mode }
+}
+
+fn name(note: u8, fifths: i8, mode: KeyMode) -> String {
+    spell(note, key(fifths, mode)).to_string()
+}
+
+#[test]
+fn black_keys_follow_the_key_signature() {
+    assert_eq!(name(66, 0, KeyMode::Major), "F#4");
+    assert_eq!(name(66, -3, KeyMode::Major), "Gb4");
+    assert_eq!(name(70, 0, KeyMode::Major), "Bb4");
+    assert_eq!(name(70, 5, KeyMode::Major), "A#4");
+    assert_eq!(name(61, 2, KeyMode::Major), "C#4");
+    assert_eq!(name(61, -4, KeyMode::Major), "Db4");
+}
+
+#[test]
+fn the_ambiguous_note_is_a_lowered_sixth_in_major_and_a_leading_tone_in_minor() {
+    assert_eq!(name(68, 0, KeyMode::Major), "Ab4");
+    assert_eq!(name(68, 0, KeyMode::Minor), "G#4");
+    assert_eq!(name(70, 2, KeyMode::Major), "Bb4");
+    // G# minor's leading tone.
+    assert_eq!(name(67, 5, KeyMode::Minor), "F##4");
+}
+
+#[test]
+fn keys_with_many_accidentals_respell_white_keys_and_carry_the_octave() {
+    assert_eq!(
+        spell(71, key(-6, KeyMode::Major)),
+        SpelledPitch {
+            step: Step::C,
+            alter: -1,
+            octave: 5,
+        }
+    );
+    assert_eq!(name(60, 7, KeyMode::Major), "B#3");
+    assert_eq!(name(65, 6, KeyMode::Major), "E#4");
+    assert_eq!(name(60, 0, KeyMode::Major), "C4");
+    assert_eq!(name(0, 0, KeyMode::Major), "C-1");
+}
+
+#[test]
+fn every_spelling_sounds_the_note_it_spells() {
+    for fifths in -7..=7 {
+        for mode in [KeyMode::Major, KeyMode::Minor] {
+            for note in 0..=127 {
+                let spelled = spell(note, key(fifths, mode));
+                assert_eq!(spelled.midi_note(), Some(note), "{spelled} in {fifths}");
+                assert!(spelled.alter.abs() <= 2, "{spelled} in {fifths}");
+            }
+        }
+    }
+    let too_high = SpelledPitch {
+        step: Step::G,
+        alter: 1,
+        octave: 9,
+    };
+    assert_eq!(too_high.midi_note(), None);
+}
+
+#[test]
+fn written_spellings_win_over_the_key() {
+    let keys = [
+        KeySignaturePoint {
+            tick: 0,
+            fifths: 0,
+            mode: KeyMode::Major,
+        },
+        KeySignaturePoint {
+            tick: 1920,
+            fifths: -2,
+            mode: KeyMode::Major,
+        },
+    ];
+    assert_eq!(key_signature_at(&keys, 1919), key(0, KeyMode::Major));
+    assert_eq!(key_signature_at(&keys, 1920), key(-2, KeyMode::Major));
+    assert_eq!(key_signature_at(&[], 0), KeySignature::default());
+
+    let note_on = |tick, spelling| PlaybackMidiEvent {
+        tick,
+        event: MidiLikeEvent::NoteOn {
+            note: 70,
+            velocity: 80,
+        },
+        hand: Some(Hand::Right),
+        ornament_of: None,
+        bus: None,
+        note_id: None,
+        spelling,
+        transpose: 0,
+        articulations: Vec::new(),
+    };
+    let spelled = |event| note_on_spelling(&event, &keys).map(|pitch| pitch.to_string());
+    assert_eq!(spelled(note_on(0, None)).as_deref(), Some("Bb4"));
+    assert_eq!(spelled(note_on(1920, None)).as_deref(), Some("Bb4"));
+    let written = SpelledPitch {
+        step: Step::A,
+        alter: 1,
+        octave: 4,
+    };
+    assert_eq!(
+        spelled(note_on(1920, Some(written))).as_deref(),
+        Some("A#4")
+    );
+    // A transposed part's spelling is the written pitch, not the one that sounds.
+    let transposed = PlaybackMidiEvent {
+        transpose: -2,
+        ..note_on(1920, Some(written))
+    };
+    assert_eq!(spelled(transposed).as_deref(), Some("Bb4"));
+}
@@ -0,0 +1,105 @@
+use cadenza_domain_score::{
+    import_musicxml_str, load_scorefile_path, save_scorefile_path, Hand, ScoreFile, ScoreFileError,
+    SCOREFILE_SCHEMA_VERSION,
+};
+use cadenza_ports::midi::MidiLikeEvent;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn temp_scorefile_path(name: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    std::env::temp_dir().join(format!("cadenza-{name}-{nanos}.cadenza"))
+}
+
+const TWO_HANDS: &str = r#"
+<score-partwise version="3.1">
+  <work><work-title>Two Hands</work-title></work>
+  <part-list>
+    <score-part id="P1"><part-name>Piano</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="0" implicit="yes">
+      <attributes>
+        <divisions>1</divisions>
+        <key><fifths>-1</fifths><mode>minor</mode></key>
+        <time><beats>3</beats><beat-type>4</beat-type></time>
+        <staves>2</staves>
+      </attributes>
+      <direction><sound tempo="90"/></direction>
+      <note><pitch><step>A</step><octave>4</octave></pitch><duration>1</duration><staff>1</staff></note>
+    </measure>
+    <measure number="1">
+      <note><pitch><step>D</step><octave>5</octave></pitch><duration>2</duration><staff>1</staff></note>
+      <note><chord/><pitch><step>F</step><octave>5</octave></pitch><duration>2</duration><staff>1</staff></note>
+      <note><pitch><step>E</step><octave>5</octave></pitch><duration>1</duration><staff>1</staff></note>
+      <backup><duration>3</duration></backup>
+      <note><pitch><step>D</step><octave>3</octave></pitch><duration>3</duration><staff>2</staff></note>
+    </measure>
+  </part>
+</score-partwise>
+"#;
+
+#[test]
+fn scorefile_round_trips_an_imported_musicxml_score() {
+    let score = import_musicxml_str(TWO_HANDS).expect("import");
+    let file = ScoreFile::new(score, vec!["imported two-hands.musicxml".to_string()]);
+    let path = temp_scorefile_path("roundtrip");
+
+    save_scorefile_path(&file, &path).expect("save");
+    let loaded = load_scorefile_path(&path).expect("load");
+    let _ = std::fs::remove_file(&path);
+
+    assert_eq!(loaded.schema_version, SCOREFILE_SCHEMA_VERSION.to_string());
+    assert_eq!(loaded.edit_log, file.edit_log);
+    // Every field, hands and measures included, comes back unchanged.
+    assert_eq!(
+        serde_json::to_value(&loaded.score).expect("json"),
+        serde_json::to_value(&file.score).expect("json")
+    );
+    let track = &loaded.score.tracks[0];
+    let downbeat = track
+        .targets
+        .iter()
+        .find(|target| target.tick == 480)
+        .expect("measure 1 downbeat");
+    assert_eq!(downbeat.notes, vec![50, 74, 77]);
+    assert_eq!(downbeat.measure_index, Some(1));
+    let bass_hand = track
+        .playback_events
+        .iter()
+        .find_map(|event| match event.event {
+            MidiLikeEvent::NoteOn { note: 50, .. } => Some(event.hand),
+            _ => None,
+        });
+    assert_eq!(bass_hand, Some(Some(Hand::Left)));
+    assert_eq!(loaded.score.pickup_ticks, 480);
+    assert_eq!(loaded.score.key_signatures[0].fifths, -1);
+}
+
+#[test]
+fn scorefile_from_a_newer_version_is_refused() {
+    let score = import_musicxml_str(TWO_HANDS).expect("import");
+    let mut file = ScoreFile::new(score, Vec::new());
+    file.schema_version = format!("{}.0", SCOREFILE_SCHEMA_VERSION + 1);
+    let path = temp_scorefile_path("newer");
+    save_scorefile_path(&file, &path).expect("save");
+
+    let err = load_scorefile_path(&path).expect_err("newer");
+    let _ = std::fs::remove_file(&path);
+    assert!(
+        matches!(err, ScoreFileError::UnsupportedVersion { supported, .. } if supported == SCOREFILE_SCHEMA_VERSION)
+    );
+    assert!(err.to_string().contains("update Cadenza"), "{err}");
+}
+
+#[test]
+fn scorefile_without_a_version_is_invalid() {
+    let path = temp_scorefile_path("unversioned");
+    std::fs::write(&path, br#"{"score": {}, "edit_log": []}"#).expect("write");
+    let err = load_scorefile_path(&path).expect_err("unversioned");
+    let _ = std::fs::remove_file(&path);
+    assert!(matches!(err, ScoreFileError::Format(_)), "{err}");
+}
@@ -0,0 +1,186 @@
+use cadenza_domain_score::{
+    Hand, PlaybackMidiEvent, Score, ScoreMeta, ScoreSource, TargetEvent, Track,
+};
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::types::Tick;
+
+fn track(id: u32, hand: Hand, notes: &[(Tick, u8)]) -> Track {
+    let playback_events = notes
+        .iter()
+        .flat_map(|&(tick, note)| {
+            [
+                (tick, MidiLikeEvent::NoteOn { note, velocity: 80 }),
+                (tick + 240, MidiLikeEvent::NoteOff { note }),
+            ]
+        })
+        .map(|(tick, event)| PlaybackMidiEvent {
+            tick,
+            event,
+            hand: Some(hand),
+            ornament_of: None,
+            bus: None,
+            note_id: None,
+            spelling: None,
+            transpose: 0,
+            articulations: Vec::new(),
+        })
+        .collect();
+    let targets = notes
+        .iter()
+        .enumerate()
+        .map(|(idx, &(tick, note))| TargetEvent {
+            id: idx as u64 + 1,
+            tick,
+            notes: vec![note],
+            hand: Some(hand),
+            measure_index: None,
+            optional: false,
+            note_ids: Vec::new(),
+            note_tracks: Vec::new(),
+        })
+        .collect();
+    Track {
+        id,
+        name: format!("Track {id}"),
+        hand: Some(hand),
+        targets,
+        playback_events,
+    }
+}
+
+/// Target id, tick, notes, their tracks and hand.
+type Grouped = (u64, Tick, Vec<u8>, Vec<u32>, Option<Hand>);
+
+/// Right hand on track 1, left hand on track 2, both writing middle C at tick 960.
+fn two_hands() -> Score {
+    let mut score = Score::new(
+        ScoreMeta {
+            title: None,
+            composer: None,
+            copyright: None,
+            source: ScoreSource::Internal,
+            origin_path: None,
+        },
+        480,
+    );
+    score.tracks = vec![
+        track(1, Hand::Right, &[(0, 72), (480, 76), (960, 60)]),
+        track(2, Hand::Left, &[(0, 48), (960, 60), (1440, 43)]),
+    ];
+    score.assign_note_ids();
+    score
+}
+
+#[test]
+fn merged_targets_group_simultaneous_notes_and_keep_their_tracks() {
+    let score = two_hands();
+    let merged = score.merged_for_practice(&[]);
+
+    let grouped: Vec<Grouped> = merged
+        .targets
+        .iter()
+        .map(|t| (t.id, t.tick, t.notes.clone(), t.note_tracks.clone(), t.hand))
+        .collect();
+    assert_eq!(
+        grouped,
+        vec![
+            (1, 0, vec![48, 72], vec![2, 1], None),
+            (2, 480, vec![76], vec![1], Some(Hand::Right)),
+            // The doubled middle C is expected once, from the first track.
+            (3, 960, vec![60], vec![1], None),
+            (4, 1440, vec![43], vec![2], Some(Hand::Left)),
+        ]
+    );
+    assert_eq!(
+        merged.targets[2].note_ids,
+        score.tracks[0].targets[2].note_ids
+    );
+    assert_eq!(
+        merged.targets[0].note_ids,
+        vec![
+            score.tracks[1].targets[0].note_ids[0],
+            score.tracks[0].targets[0].note_ids[0],
+        ]
+    );
+
+    // Playback keeps every other event in time order, each with its own hand.
+    assert_eq!(merged.playback_events.len(), 10);
+    assert!(merged
+        .playback_events
+        .windows(2)
+        .all(|pair| pair[0].tick <= pair[1].tick));
+    assert!(merged.playback_events.iter().any(|e| e.tick == 1440
+        && e.hand == Some(Hand::Left)
+        && matches!(e.event, MidiLikeEvent::NoteOn { note: 43, .. })));
+}
+
+#[test]
+fn only_the_selected_tracks_are_merged() {
+    let score = two_hands();
+    let left = score.merged_for_practice(&[2]);
+
+    let notes: Vec<(Tick, Vec<u8>, Vec<u32>)> = left
+        .targets
+        .iter()
+        .map(|t| (t.tick, t.notes.clone(), t.note_tracks.clone()))
+        .collect();
+    assert_eq!(
+        notes,
+        vec![
+            (0, vec![48], vec![2]),
+            (960, vec![60], vec![2]),
+            (1440, vec![43], vec![2]),
+        ]
+    );
+    assert_eq!(
+        left.targets[1].note_ids,
+        score.tracks[1].targets[1].note_ids
+    );
+    assert!(left
+        .playback_events
+        .iter()
+        .all(|e| e.hand == Some(Hand::Left)));
+    assert_eq!(left.playback_events.len(), 6);
+}
+
+#[test]
+fn a_pitch_doubled_across_tracks_plays_once() {
+    let mut score = two_hands();
+    // The left hand holds its middle C twice as long as the right.
+    for event in &mut score.tracks[1].playback_events {
+        if event.tick == 1200 && matches!(event.event, MidiLikeEvent::NoteOff { note: 60 }) {
+            event.tick = 1440;
+        }
+    }
+    let merged = score.merged_for_practice(&[]);
+
+    let middle_c: Vec<(Tick, bool, Option<Hand>)> = merged
+        .playback_events
+        .iter()
+        .filter_map(|e| match e.event {
+            MidiLikeEvent::NoteOn { note: 60, .. } => Some((e.tick, true, e.hand)),
+            MidiLikeEvent::NoteOff { note: 60 } => Some((e.tick, false, e.hand)),
+            _ => None,
+        })
+        .collect();
+    // The first track's note, held until the longer copy ends.
+    assert_eq!(
+        middle_c,
+        vec![
+            (960, true, Some(Hand::Right)),
+            (1440, false, Some(Hand::Right)),
+        ]
+    );
+    let ids: Vec<_> = merged
+        .playback_events
+        .iter()
+        .filter(|e| {
+            matches!(
+                e.event,
+                MidiLikeEvent::NoteOn { note: 60, .. } | MidiLikeEvent::NoteOff { note: 60 }
+            )
+        })
+        .map(|e| e.note_id)
+        .collect();
+    assert_eq!(ids, vec![merged.targets[2].note_ids.first().copied(); 2]);
+}
@@ -1,7 +1,10 @@
 use cadenza_ports::audio::{AudioError, AudioOutputPort, AudioRenderCallback, AudioStreamHandle};
-use cadenza_ports::types::{AudioConfig, AudioOutputDevice, DeviceId};
+use cadenza_ports::types::{AudioConfig, AudioOutputDevice, BufferSizeRange, DeviceId};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{BufferSize, SampleFormat, SampleRate, StreamConfig, SupportedStreamConfigRange};
+use cpal::{
+    BackendSpecificError, BufferSize, BuildStreamError, PlayStreamError, SampleFormat, SampleRate,
+    StreamConfig, SupportedBufferSize, SupportedStreamConfigRange, SupportedStreamConfigsError,
+};
 use std::sync::mpsc;
 use std::thread;
 
@@ -47,13 +50,14 @@ impl CpalAudioOutputPort {
 
     fn select_stream_config(
         device: &cpal::Device,
+        device_id: &DeviceId,
         desired: AudioConfig,
     ) -> Result<SelectedStreamConfig, AudioError> {
         let mut supported = device
             .supported_output_configs()
-            .map_err(|e| AudioError::Backend(e.to_string()))?;
+            .map_err(|e| map_supported_configs_error(device_id, e))?;
 
-        let chosen = select_supported_config(&mut supported, desired)?;
+        let chosen = select_supported_config(&mut supported, device_id, desired)?;
 
         let sample_format = chosen.sample_format();
         let mut config = chosen.config();
@@ -79,6 +83,7 @@ impl Default for CpalAudioOutputPort {
 pub struct CpalAudioStreamHandle {
     stop_tx: mpsc::Sender<()>,
     join_handle: Option<thread::JoinHandle<()>>,
+    config: AudioConfig,
 }
 
 impl AudioStreamHandle for CpalAudioStreamHandle {
@@ -88,6 +93,10 @@ impl AudioStreamHandle for CpalAudioStreamHandle {
             let _ = handle.join();
         }
     }
+
+    fn effective_config(&self) -> Option<AudioConfig> {
+        Some(self.config)
+    }
 }
 
 impl AudioOutputPort for CpalAudioOutputPort {
@@ -110,10 +119,19 @@ impl AudioOutputPort for CpalAudioOutputPort {
                 buffer_size_frames: None,
             };
 
+            let buffer_size_range = match default_config.buffer_size() {
+                SupportedBufferSize::Range { min, max } => Some(BufferSizeRange {
+                    min_frames: *min,
+                    max_frames: *max,
+                }),
+                SupportedBufferSize::Unknown => None,
+            };
+
             results.push(AudioOutputDevice {
                 id,
                 name,
                 default_config: config,
+                buffer_size_range,
             });
         }
 
@@ -149,7 +167,7 @@ impl AudioOutputPort for CpalAudioOutputPort {
                 }
             };
 
-            let stream_config = match Self::select_stream_config(&device, desired) {
+            let stream_config = match Self::select_stream_config(&device, &device_id, desired) {
                 Ok(config) => config,
                 Err(err) => {
                     let _ = ready_tx.send(Err(err));
@@ -158,10 +176,15 @@ impl AudioOutputPort for CpalAudioOutputPort {
             };
 
             let channels = stream_config.config.channels as usize;
-            let initial_frames = match stream_config.config.buffer_size {
-                BufferSize::Fixed(frames) => frames as usize,
-                BufferSize::Default => 8192,
+            let effective = AudioConfig {
+                sample_rate_hz: stream_config.config.sample_rate.0,
+                channels: stream_config.config.channels,
+                buffer_size_frames: match stream_config.config.buffer_size {
+                    BufferSize::Fixed(frames) => Some(frames),
+                    BufferSize::Default => None,
+                },
             };
+            let initial_frames = effective.buffer_size_frames.unwrap_or(8192) as usize;
             let left: Vec<f32> = vec![0.0; initial_frames];
             let right: Vec<f32> = vec![0.0; initial_frames];
             let sample_time: u64 = 0;
@@ -252,17 +275,17 @@ impl AudioOutputPort for CpalAudioOutputPort {
             let stream = match stream {
                 Ok(stream) => stream,
                 Err(err) => {
-                    let _ = ready_tx.send(Err(AudioError::Backend(err.to_string())));
+                    let _ = ready_tx.send(Err(map_build_stream_error(&device_id, desired, err)));
                     return;
                 }
             };
 
             if let Err(err) = stream.play() {
-                let _ = ready_tx.send(Err(AudioError::Backend(err.to_string())));
+                let _ = ready_tx.send(Err(map_play_stream_error(&device_id, err)));
                 return;
             }
 
-            let _ = ready_tx.send(Ok(()));
+            let _ = ready_tx.send(Ok(effective));
             let _ = stop_rx.recv();
             drop(stream);
         });
@@ -271,9 +294,10 @@ impl AudioOutputPort for CpalAudioOutputPort {
             .recv()
             .map_err(|e| AudioError::Backend(e.to_string()))?
         {
-            Ok(()) => Ok(Box::new(CpalAudioStreamHandle {
+            Ok(config) => Ok(Box::new(CpalAudioStreamHandle {
                 stop_tx,
                 join_handle: Some(join_handle),
+                config,
             })),
             Err(err) => Err(err),
         }
@@ -282,12 +306,21 @@ impl AudioOutputPort for CpalAudioOutputPort {
 
 fn select_supported_config(
     supported: &mut dyn Iterator<Item = SupportedStreamConfigRange>,
+    device_id: &DeviceId,
     desired: AudioConfig,
 ) -> Result<cpal::SupportedStreamConfig, AudioError> {
     let mut best: Option<cpal::SupportedStreamConfig> = None;
     let mut best_score: i32 = -1;
+    let mut offered = Vec::new();
 
     for config_range in supported {
+        offered.push(format!(
+            "{} ch at {}-{} Hz ({:?})",
+            config_range.channels(),
+            config_range.min_sample_rate().0,
+            config_range.max_sample_rate().0,
+            config_range.sample_format()
+        ));
         if config_range.channels() != desired.channels {
             continue;
         }
@@ -314,9 +347,73 @@ fn select_supported_config(
         return Ok(best);
     }
 
-    Err(AudioError::UnsupportedConfig(
-        "no matching stream config".to_string(),
-    ))
+    Err(AudioError::FormatNegotiationFailed {
+        device: device_id.to_string(),
+        requested: desired,
+        supported: offered,
+    })
+}
+
+/// How long a busy device is given before opening it again.
+const BUSY_RETRY_MS: u32 = 1000;
+
+/// Backends only describe "in use" in text: WASAPI exclusive mode reports
+/// AUDCLNT_E_DEVICE_IN_USE, ALSA "Device or resource busy", CoreAudio "busy".
+fn is_busy(description: &str) -> bool {
+    let description = description.to_ascii_lowercase();
+    ["busy", "in use", "device_in_use"]
+        .iter()
+        .any(|marker| description.contains(marker))
+}
+
+fn backend_error(device_id: &DeviceId, err: BackendSpecificError) -> AudioError {
+    if is_busy(&err.description) {
+        AudioError::DeviceBusy {
+            device: device_id.to_string(),
+            retry_after_ms: BUSY_RETRY_MS,
+        }
+    } else {
+        AudioError::Backend(err.description)
+    }
+}
+
+pub fn map_build_stream_error(
+    device_id: &DeviceId,
+    requested: AudioConfig,
+    err: BuildStreamError,
+) -> AudioError {
+    match err {
+        BuildStreamError::DeviceNotAvailable => {
+            AudioError::DeviceUnavailable(device_id.to_string())
+        }
+        BuildStreamError::StreamConfigNotSupported => AudioError::FormatNegotiationFailed {
+            device: device_id.to_string(),
+            requested,
+            supported: Vec::new(),
+        },
+        BuildStreamError::BackendSpecific { err } => backend_error(device_id, err),
+        err => AudioError::Backend(err.to_string()),
+    }
+}
+
+pub fn map_play_stream_error(device_id: &DeviceId, err: PlayStreamError) -> AudioError {
+    match err {
+        PlayStreamError::DeviceNotAvailable => AudioError::DeviceUnavailable(device_id.to_string()),
+        PlayStreamError::BackendSpecific { err } => backend_error(device_id, err),
+    }
+}
+
+pub fn map_supported_configs_error(
+    device_id: &DeviceId,
+    err: SupportedStreamConfigsError,
+) -> AudioError {
+    match err {
+        SupportedStreamConfigsError::DeviceNotAvailable => {
+            AudioError::DeviceUnavailable(device_id.to_string())
+        }
+        SupportedStreamConfigsError::BackendSpecific { err } => backend_error(device_id, err),
+        err => AudioError::Backend(err.to_string()),
+    }
 }
 
 fn write_interleaved_f32(data: &mut [f32], channels: usize, left: &[f32], right: &[f32]) {
@@ -1,18 +1,46 @@
 use cadenza_ports::midi::{
-    MidiError, MidiInputPort, MidiInputStream, MidiLikeEvent, PlayerEvent, PlayerEventCallback,
+    MidiError, MidiInputPort, MidiInputStream, MidiLikeEvent, MidiOptions, PlayerEvent,
+    PlayerEventCallback, RawMidiMessage, RawMidiTap,
 };
 use cadenza_ports::types::{DeviceId, MidiInputDevice};
 use midir::{Ignore, MidiInput};
+use std::sync::Arc;
 use std::time::Instant;
 
 pub struct MidirMidiInputPort {
     client_name: String,
+    /// Set when [`MidiOptions::raw_message_tap`] is on; shared by every opened input.
+    tap: Option<Arc<RawMidiTap>>,
 }
 
 impl MidirMidiInputPort {
     pub fn new(client_name: impl Into<String>) -> Self {
+        Self::with_options(client_name, MidiOptions::default())
+    }
+
+    pub fn with_options(client_name: impl Into<String>, options: MidiOptions) -> Self {
         Self {
             client_name: client_name.into(),
+            tap: options.raw_message_tap.then(|| Arc::new(RawMidiTap::new())),
+        }
+    }
+
+    /// What an open input does with each message: keeps it in `tap`, then passes it on to
+    /// `callback` if it parses. Unknown messages only ever reach the tap.
+    pub fn receive(
+        stamp_us: u64,
+        message: &[u8],
+        tap: Option<&RawMidiTap>,
+        callback: &PlayerEventCallback,
+    ) {
+        if let Some(tap) = tap {
+            tap.push(stamp_us, message);
+        }
+        if let Some(event) = Self::parse_message(message) {
+            callback(PlayerEvent {
+                at: Instant::now(),
+                event,
+            });
         }
     }
 
@@ -57,7 +85,10 @@ impl MidirMidiInputPort {
                 if message[1] == 64 {
                     Some(MidiLikeEvent::Cc64 { value: message[2] })
                 } else {
-                    None
+                    Some(MidiLikeEvent::ControlChange {
+                        controller: message[1] & 0x7F,
+                        value: message[2] & 0x7F,
+                    })
                 }
             }
             _ => None,
@@ -126,18 +157,13 @@ impl MidiInputPort for MidirMidiInputPort {
 
         let port = selected.ok_or_else(|| MidiError::DeviceNotFound(device_id.to_string()))?;
 
+        let tap = self.tap.clone();
         let connection = midi_in
             .connect(
                 &port,
                 "cadenza-midi-input",
-                move |_stamp, message, callback| {
-                    if let Some(event) = Self::parse_message(message) {
-                        let player_event = PlayerEvent {
-                            at: Instant::now(),
-                            event,
-                        };
-                        (callback)(player_event);
-                    }
+                move |stamp, message, callback| {
+                    Self::receive(stamp, message, tap.as_deref(), callback);
                 },
                 cb,
             )
@@ -147,4 +173,11 @@ impl MidiInputPort for MidirMidiInputPort {
             connection: Some(connection),
         }))
     }
+
+    fn raw_messages(&self) -> Vec<RawMidiMessage> {
+        self.tap
+            .as_ref()
+            .map(|tap| tap.messages())
+            .unwrap_or_default()
+    }
 }
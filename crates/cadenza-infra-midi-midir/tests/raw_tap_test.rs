@@ -0,0 +1,74 @@
+use cadenza_infra_midi_midir::MidirMidiInputPort;
+use cadenza_ports::midi::{
+    MidiLikeEvent, PlayerEvent, PlayerEventCallback, RawMidiTap, RAW_MIDI_TAP_CAPACITY,
+};
+use std::sync::{Arc, Mutex};
+
+fn collecting_callback() -> (PlayerEventCallback, Arc<Mutex<Vec<MidiLikeEvent>>>) {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let sink = received.clone();
+    let callback: PlayerEventCallback =
+        Arc::new(move |event: PlayerEvent| sink.lock().unwrap().push(event.event));
+    (callback, received)
+}
+
+#[test]
+fn unknown_messages_reach_the_tap_but_not_the_player() {
+    let tap = RawMidiTap::new();
+    let (callback, received) = collecting_callback();
+
+    // Pitch bend, channel aftertouch, poly aftertouch, then a pedal press.
+    let messages: [&[u8]; 4] = [
+        &[0xE0, 0x00, 0x48],
+        &[0xD0, 0x40],
+        &[0xA0, 60, 30],
+        &[0xB0, 64, 127],
+    ];
+    for (stamp, message) in messages.iter().enumerate() {
+        MidirMidiInputPort::receive(stamp as u64 * 1_000, message, Some(&tap), &callback);
+    }
+
+    let raw = tap.messages();
+    assert_eq!(raw.len(), 4);
+    for (kept, message) in raw.iter().zip(messages) {
+        assert_eq!(kept.status, message[0]);
+        assert_eq!(kept.data, &message[1..]);
+    }
+    assert_eq!(raw[1].stamp_us, 1_000);
+    assert_eq!(
+        *received.lock().unwrap(),
+        vec![MidiLikeEvent::Cc64 { value: 127 }]
+    );
+}
+
+#[test]
+fn parsing_is_the_same_with_or_without_the_tap() {
+    let (with_tap, tapped) = collecting_callback();
+    let (without_tap, untapped) = collecting_callback();
+    let tap = RawMidiTap::new();
+    let messages: [&[u8]; 5] = [
+        &[0x90, 60, 100],
+        &[0x90, 60, 0],
+        &[0xB0, 1, 64],
+        &[0xF8],
+        &[0x80, 62, 0],
+    ];
+    for message in messages {
+        MidirMidiInputPort::receive(0, message, Some(&tap), &with_tap);
+        MidirMidiInputPort::receive(0, message, None, &without_tap);
+    }
+
+    assert_eq!(*tapped.lock().unwrap(), *untapped.lock().unwrap());
+    assert_eq!(tapped.lock().unwrap().len(), 4);
+}
+
+#[test]
+fn the_tap_keeps_only_the_latest_messages() {
+    let tap = RawMidiTap::new();
+    for stamp in 0..RAW_MIDI_TAP_CAPACITY as u64 + 10 {
+        tap.push(stamp, &[0xF8]);
+    }
+    let raw = tap.messages();
+    assert_eq!(raw.len(), RAW_MIDI_TAP_CAPACITY);
+    assert_eq!(raw[0].index, 10);
+}
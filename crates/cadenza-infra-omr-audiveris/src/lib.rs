@@ -1,17 +1,23 @@
 use cadenza_ports::omr::{OmrError, OmrOptions, OmrPort, OmrResult};
-use std::fs;
+use std::fs::{self, File};
 use std::path::{Path, PathBuf};
-use std::process::Command;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How often a running engine is checked for exit or cancellation.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
 
 pub struct AudiverisOmr {
     default_engine_path: Option<String>,
+    last_log: Mutex<Option<PathBuf>>,
 }
 
 impl AudiverisOmr {
     pub fn new(default_engine_path: Option<String>) -> Self {
         Self {
             default_engine_path,
+            last_log: Mutex::new(None),
         }
     }
 
@@ -25,6 +31,13 @@ impl AudiverisOmr {
     }
 
     fn normalize_engine_path(engine: &str) -> String {
+        let engine = engine.trim();
+        if engine.eq_ignore_ascii_case("audiveris") {
+            if let Some(candidate) = Self::default_app_engine() {
+                return candidate;
+            }
+        }
+
         let path = Path::new(engine);
         let ext_is_app = path
             .extension()
@@ -41,6 +54,22 @@ impl AudiverisOmr {
         engine.to_string()
     }
 
+    /// The macOS app bundle, when installed system-wide or for the user.
+    fn default_app_engine() -> Option<String> {
+        let home = std::env::var_os("HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir);
+        let candidates = [
+            PathBuf::from("/Applications/Audiveris.app"),
+            home.join("Applications").join("Audiveris.app"),
+        ];
+        candidates
+            .into_iter()
+            .map(|candidate| candidate.join("Contents").join("MacOS").join("Audiveris"))
+            .find(|bin| bin.exists())
+            .map(|bin| bin.to_string_lossy().into_owned())
+    }
+
     fn make_workdir() -> Result<PathBuf, OmrError> {
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -63,21 +92,51 @@ impl AudiverisOmr {
         if xml.exists() {
             return Some(xml);
         }
-        let entries = fs::read_dir(output_dir).ok()?;
+        Self::find_output_musicxml_recursive(output_dir, stem, 0)
+    }
+
+    /// Audiveris nests its output in per-book folders and may rename it; prefers a file
+    /// named after the PDF, else the first MusicXML file found.
+    fn find_output_musicxml_recursive(dir: &Path, stem: &str, depth: usize) -> Option<PathBuf> {
+        if depth > 6 {
+            return None;
+        }
+
+        let entries = fs::read_dir(dir).ok()?;
+        let mut best_other: Option<PathBuf> = None;
+
         for entry in entries.flatten() {
             let path = entry.path();
-            if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-                if ext.eq_ignore_ascii_case("mxl") || ext.eq_ignore_ascii_case("xml") {
-                    return Some(path);
+            if path.is_dir() {
+                if let Some(found) = Self::find_output_musicxml_recursive(&path, stem, depth + 1) {
+                    return Some(found);
                 }
+                continue;
+            }
+
+            let Some(ext) = path.extension().and_then(|e| e.to_str()) else {
+                continue;
+            };
+            if !(ext.eq_ignore_ascii_case("mxl") || ext.eq_ignore_ascii_case("xml")) {
+                continue;
+            }
+
+            let file_stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+            if file_stem == stem {
+                return Some(path);
+            }
+            if best_other.is_none() {
+                best_other = Some(path);
             }
         }
-        None
+
+        best_other
     }
 }
 
 impl OmrPort for AudiverisOmr {
     fn recognize_pdf(&self, pdf_path: &str, options: OmrOptions) -> Result<OmrResult, OmrError> {
+        options.report_progress("Running Audiveris");
         let engine = self.engine_path(&options);
         let input_path = Path::new(pdf_path);
         let stem = input_path
@@ -86,41 +145,76 @@ impl OmrPort for AudiverisOmr {
             .ok_or_else(|| OmrError::UnsupportedFormat("invalid pdf filename".to_string()))?;
 
         let output_dir = Self::make_workdir()?;
-        let output = Command::new(engine)
+        let log_path = output_dir.join("audiveris.log");
+        let log_file = File::create(&log_path)
+            .map_err(|e| OmrError::Backend(format!("failed to create diagnostics log: {e}")))?;
+        let log_file_err = log_file
+            .try_clone()
+            .map_err(|e| OmrError::Backend(format!("failed to clone diagnostics log: {e}")))?;
+        *self.last_log.lock().expect("log path") = Some(log_path.clone());
+
+        let mut child = Command::new(engine)
             .arg("-batch")
             .arg("-export")
             .arg("-output")
             .arg(&output_dir)
             .arg(input_path)
-            .output()
-            .map_err(|e| OmrError::Backend(e.to_string()))?;
-
-        let diagnostics_path = if options.enable_diagnostics {
-            let diag_path = output_dir.join("audiveris.log");
-            let mut content = Vec::new();
-            content.extend_from_slice(&output.stdout);
-            content.extend_from_slice(&output.stderr);
-            let _ = fs::write(&diag_path, content);
-            Some(diag_path)
-        } else {
-            None
+            // Straight to the log, so a chatty engine cannot fill a pipe and stall.
+            .stdout(Stdio::from(log_file))
+            .stderr(Stdio::from(log_file_err))
+            .spawn()
+            .map_err(|e| {
+                if e.kind() == std::io::ErrorKind::NotFound {
+                    OmrError::EngineNotFound(
+                        "Audiveris not found. Install Audiveris and set its path in Settings → Audiveris (e.g., /Applications/Audiveris.app).".to_string(),
+                    )
+                } else {
+                    OmrError::Backend(format!("failed to launch Audiveris: {e}"))
+                }
+            })?;
+
+        let status = loop {
+            if options.cancel.is_cancelled() {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(OmrError::Cancelled);
+            }
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) => std::thread::sleep(POLL_INTERVAL),
+                Err(e) => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(OmrError::Backend(format!(
+                        "failed waiting for Audiveris: {e}"
+                    )));
+                }
+            }
         };
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            return Err(OmrError::RecognitionFailed(stderr));
+        if !status.success() {
+            let code = status
+                .code()
+                .map(|c| c.to_string())
+                .unwrap_or_else(|| "?".to_string());
+            return Err(OmrError::RecognitionFailed(format!(
+                "Audiveris failed (exit code: {code}). See diagnostics log for details."
+            )));
         }
 
-        let musicxml_path = Self::find_output_musicxml(&output_dir, stem)
-            .ok_or_else(|| OmrError::RecognitionFailed("musicxml not found".to_string()))?;
+        let musicxml_path = Self::find_output_musicxml(&output_dir, stem).ok_or_else(|| {
+            OmrError::RecognitionFailed(
+                "Audiveris did not produce MusicXML (.mxl/.xml)".to_string(),
+            )
+        })?;
 
         Ok(OmrResult {
             musicxml_path: Some(musicxml_path),
-            diagnostics_path,
+            diagnostics_path: options.enable_diagnostics.then_some(log_path),
         })
     }
 
     fn diagnostics(&self) -> Result<Option<PathBuf>, OmrError> {
-        Ok(None)
+        Ok(self.last_log.lock().expect("log path").clone())
     }
 }
@@ -0,0 +1,114 @@
+use cadenza_core::{NullAudioOutputPort, NullMidiInputPort, WavFileAudioOutputPort};
+use cadenza_infra_omr_audiveris::AudiverisOmr;
+use cadenza_infra_synth_rustysynth::RustySynth;
+use cadenza_infra_synth_simple::SimpleSynth;
+use cadenza_infra_synth_waveguide_piano::WaveguidePianoSynth;
+use cadenza_ports::audio::AudioOutputPort;
+use cadenza_ports::config::{
+    AudioBackend, MidiBackend, OmrBackend, PortsConfig, PortsConfigError, SynthBackend,
+};
+use cadenza_ports::midi::{MidiInputPort, MidiOptions};
+use cadenza_ports::omr::OmrPort;
+use cadenza_ports::synth::SynthPort;
+use std::any::type_name;
+use std::sync::Arc;
+
+/// Overrides the configured ports, as a spec like `synth=simple,audio=null`.
+pub const PORTS_ENV_VAR: &str = "CADENZA_PORTS";
+
+/// Type names of the implementations picked, for logs and tests.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PortKinds {
+    pub audio: &'static str,
+    pub midi: &'static str,
+    pub synth: &'static str,
+    pub omr: Option<&'static str>,
+}
+
+/// The ports `AppCore::new` takes, built from a [`PortsConfig`].
+pub struct PortStack {
+    pub audio: Box<dyn AudioOutputPort>,
+    pub midi: Box<dyn MidiInputPort>,
+    pub synth: Arc<dyn SynthPort>,
+    pub omr: Option<Box<dyn OmrPort>>,
+    pub kinds: PortKinds,
+}
+
+/// `base` with the overrides from [`PORTS_ENV_VAR`] applied, if it is set.
+pub fn ports_config_from_env(base: PortsConfig) -> Result<PortsConfig, PortsConfigError> {
+    match std::env::var(PORTS_ENV_VAR) {
+        Ok(spec) => base.with_overrides(&spec),
+        Err(_) => Ok(base),
+    }
+}
+
+/// Fails for a device backend this build was compiled without; see the crate features.
+pub fn build_ports(config: &PortsConfig) -> Result<PortStack, PortsConfigError> {
+    let (audio, audio_kind) = build_audio(&config.audio)?;
+    let (midi, midi_kind) = build_midi(config.midi, config.midi_options)?;
+    let (synth, synth_kind): (Arc<dyn SynthPort>, _) = match config.synth {
+        SynthBackend::Waveguide => shared_synth(WaveguidePianoSynth::default()),
+        SynthBackend::Simple => shared_synth(SimpleSynth::default()),
+        SynthBackend::RustySynth => shared_synth(RustySynth::default()),
+    };
+    let (omr, omr_kind): (Option<Box<dyn OmrPort>>, _) = match &config.omr {
+        OmrBackend::Audiveris { path } => (
+            Some(Box::new(AudiverisOmr::new(path.clone()))),
+            Some(type_name::<AudiverisOmr>()),
+        ),
+        OmrBackend::None => (None, None),
+    };
+    Ok(PortStack {
+        audio,
+        midi,
+        synth,
+        omr,
+        kinds: PortKinds {
+            audio: audio_kind,
+            midi: midi_kind,
+            synth: synth_kind,
+            omr: omr_kind,
+        },
+    })
+}
+
+fn shared_synth<T: SynthPort + 'static>(synth: T) -> (Arc<dyn SynthPort>, &'static str) {
+    (Arc::new(synth), type_name::<T>())
+}
+
+fn boxed_audio<T: AudioOutputPort + 'static>(port: T) -> (Box<dyn AudioOutputPort>, &'static str) {
+    (Box::new(port), type_name::<T>())
+}
+
+fn build_audio(
+    backend: &AudioBackend,
+) -> Result<(Box<dyn AudioOutputPort>, &'static str), PortsConfigError> {
+    Ok(match backend {
+        #[cfg(feature = "cpal")]
+        AudioBackend::Cpal => boxed_audio(cadenza_infra_audio_cpal::CpalAudioOutputPort::new()),
+        #[cfg(not(feature = "cpal"))]
+        AudioBackend::Cpal => return Err(PortsConfigError::Unavailable("cpal".to_string())),
+        AudioBackend::Null => boxed_audio(NullAudioOutputPort),
+        AudioBackend::Wav { path } => boxed_audio(WavFileAudioOutputPort::new(path)),
+    })
+}
+
+fn boxed_midi<T: MidiInputPort + 'static>(port: T) -> (Box<dyn MidiInputPort>, &'static str) {
+    (Box::new(port), type_name::<T>())
+}
+
+#[cfg_attr(not(feature = "midir"), allow(unused_variables))]
+fn build_midi(
+    backend: MidiBackend,
+    options: MidiOptions,
+) -> Result<(Box<dyn MidiInputPort>, &'static str), PortsConfigError> {
+    Ok(match backend {
+        #[cfg(feature = "midir")]
+        MidiBackend::Midir => boxed_midi(
+            cadenza_infra_midi_midir::MidirMidiInputPort::with_options("Cadenza", options),
+        ),
+        #[cfg(not(feature = "midir"))]
+        MidiBackend::Midir => return Err(PortsConfigError::Unavailable("midir".to_string())),
+        MidiBackend::None => boxed_midi(NullMidiInputPort),
+    })
+}
@@ -0,0 +1,161 @@
+use cadenza_core::{NullAudioOutputPort, NullMidiInputPort, WavFileAudioOutputPort};
+use cadenza_infra_omr_audiveris::AudiverisOmr;
+use cadenza_infra_stack::build_ports;
+use cadenza_infra_synth_rustysynth::RustySynth;
+use cadenza_infra_synth_simple::SimpleSynth;
+use cadenza_infra_synth_waveguide_piano::WaveguidePianoSynth;
+use cadenza_ports::config::{
+    AudioBackend, MidiBackend, OmrBackend, PortsConfig, PortsConfigError, SynthBackend,
+};
+use std::any::type_name;
+
+#[test]
+fn every_combination_builds_the_matching_implementations() {
+    let synths = [
+        (SynthBackend::Waveguide, type_name::<WaveguidePianoSynth>()),
+        (SynthBackend::Simple, type_name::<SimpleSynth>()),
+        (SynthBackend::RustySynth, type_name::<RustySynth>()),
+    ];
+    let audios = [
+        (AudioBackend::Null, type_name::<NullAudioOutputPort>()),
+        (
+            AudioBackend::Wav {
+                path: "/tmp/cadenza-ports.wav".to_string(),
+            },
+            type_name::<WavFileAudioOutputPort>(),
+        ),
+    ];
+    let omrs = [
+        (OmrBackend::None, None),
+        (
+            OmrBackend::Audiveris {
+                path: Some("/opt/audiveris".to_string()),
+            },
+            Some(type_name::<AudiverisOmr>()),
+        ),
+    ];
+
+    for (synth, synth_kind) in &synths {
+        for (audio, audio_kind) in &audios {
+            for (omr, omr_kind) in &omrs {
+                let config = PortsConfig {
+                    synth: *synth,
+                    audio: audio.clone(),
+                    midi: MidiBackend::None,
+                    omr: omr.clone(),
+                    ..PortsConfig::default()
+                };
+                let stack = build_ports(&config).unwrap_or_else(|err| panic!("{config}: {err}"));
+                assert_eq!(stack.kinds.synth, *synth_kind, "{config}");
+                assert_eq!(stack.kinds.audio, *audio_kind, "{config}");
+                assert_eq!(stack.kinds.midi, type_name::<NullMidiInputPort>());
+                assert_eq!(stack.kinds.omr, *omr_kind, "{config}");
+                assert_eq!(stack.omr.is_some(), omr_kind.is_some());
+            }
+        }
+    }
+}
+
+#[test]
+fn the_built_ports_are_usable() {
+    let stack = build_ports(&"audio=null,midi=none".parse().unwrap()).expect("stack");
+    let outputs = stack.audio.list_outputs().expect("outputs");
+    assert_eq!(outputs[0].id.0, cadenza_core::NULL_AUDIO_DEVICE_ID);
+    assert!(stack.midi.list_inputs().expect("inputs").is_empty());
+}
+
+#[cfg(not(feature = "cpal"))]
+#[test]
+fn device_backends_left_out_of_the_build_are_reported() {
+    let err = build_ports(&PortsConfig::default()).err().expect("no cpal");
+    assert_eq!(err, PortsConfigError::Unavailable("cpal".to_string()));
+    assert_eq!(err.to_string(), "this build of Cadenza has no cpal support");
+}
+
+#[test]
+fn a_spec_overrides_only_the_ports_it_names() {
+    let base = PortsConfig {
+        synth: SynthBackend::Simple,
+        ..PortsConfig::default()
+    };
+    let config = base
+        .with_overrides("audio=wav:/tmp/take.wav, omr=audiveris")
+        .expect("spec");
+
+    assert_eq!(config.synth, SynthBackend::Simple);
+    assert_eq!(
+        config.audio,
+        AudioBackend::Wav {
+            path: "/tmp/take.wav".to_string()
+        }
+    );
+    assert_eq!(config.midi, MidiBackend::Midir);
+    assert_eq!(config.omr, OmrBackend::Audiveris { path: None });
+    assert_eq!(
+        config.to_string(),
+        "synth=simple,audio=wav:/tmp/take.wav,midi=midir,omr=audiveris"
+    );
+    assert_eq!(config.to_string().parse::<PortsConfig>(), Ok(config));
+}
+
+#[test]
+fn the_midi_spec_can_ask_for_the_raw_message_tap() {
+    let config: PortsConfig = "midi=midir:tap".parse().expect("spec");
+    assert_eq!(config.midi, MidiBackend::Midir);
+    assert!(config.midi_options.raw_message_tap);
+    assert_eq!(
+        config.to_string(),
+        "synth=rustysynth,audio=cpal,midi=midir:tap,omr=none"
+    );
+    assert_eq!(
+        config.to_string().parse::<PortsConfig>(),
+        Ok(config.clone())
+    );
+
+    let config = config.with_overrides("midi=midir").expect("spec");
+    assert!(!config.midi_options.raw_message_tap);
+    assert!("midi=none:tap".parse::<PortsConfig>().is_err());
+}
+
+#[test]
+fn unknown_values_name_what_is_accepted() {
+    let cases = [
+        (
+            "synth=fluid",
+            "unknown synth implementation \"fluid\"; expected one of waveguide, simple, rustysynth",
+        ),
+        (
+            "audio=jack",
+            "unknown audio implementation \"jack\"; expected one of cpal, null, wav:<path>",
+        ),
+        (
+            "audio=wav",
+            "wav output needs a path, as in audio=wav:/tmp/out.wav",
+        ),
+        (
+            "video=none",
+            "unknown port \"video\"; expected synth, audio, midi or omr",
+        ),
+        (
+            "midi",
+            "malformed port setting \"midi\"; expected port=value, as in audio=null",
+        ),
+    ];
+    for (spec, message) in cases {
+        let err = spec.parse::<PortsConfig>().expect_err(spec);
+        assert_eq!(err.to_string(), message);
+    }
+}
+
+#[test]
+fn the_config_round_trips_through_json() {
+    let config: PortsConfig = "synth=waveguide,audio=wav:/tmp/take.wav,midi=none"
+        .parse()
+        .unwrap();
+    let json = serde_json::to_string(&config).unwrap();
+    assert_eq!(serde_json::from_str::<PortsConfig>(&json).unwrap(), config);
+
+    let err =
+        serde_json::from_str::<PortsConfig>(r#"{"synth": "fluid"}"#).expect_err("unknown synth");
+    assert!(err.to_string().contains("unknown variant `fluid`"), "{err}");
+}
@@ -21,6 +21,10 @@ impl FsStorage {
         self.base_dir.join("settings.json")
     }
 
+    fn session_snapshot_path(&self) -> PathBuf {
+        self.base_dir.join("session_inprogress.json")
+    }
+
     fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, StorageError> {
         let data = fs::read(path).map_err(|e| StorageError::Io(e.to_string()))?;
         serde_json::from_slice(&data).map_err(|e| StorageError::Serde(e.to_string()))
@@ -56,4 +60,38 @@ impl StoragePort for FsStorage {
         let path = self.settings_path();
         Self::write_json(&path, s)
     }
+
+    fn save_diagnostics_file(&self, name: &str, data: &[u8]) -> Result<PathBuf, StorageError> {
+        let dir = self.base_dir.join("diagnostics");
+        fs::create_dir_all(&dir).map_err(|e| StorageError::Io(e.to_string()))?;
+        let path = dir.join(name);
+        fs::write(&path, data).map_err(|e| StorageError::Io(e.to_string()))?;
+        Ok(path)
+    }
+
+    fn save_session_snapshot(&self, data: &[u8]) -> Result<(), StorageError> {
+        fs::create_dir_all(&self.base_dir).map_err(|e| StorageError::Io(e.to_string()))?;
+        // Written aside and renamed over, so a crash mid-write leaves the previous snapshot.
+        let path = self.session_snapshot_path();
+        let partial = path.with_extension("json.partial");
+        fs::write(&partial, data).map_err(|e| StorageError::Io(e.to_string()))?;
+        fs::rename(&partial, &path).map_err(|e| StorageError::Io(e.to_string()))
+    }
+
+    fn load_session_snapshot(&self) -> Result<Option<Vec<u8>>, StorageError> {
+        match fs::read(self.session_snapshot_path()) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(StorageError::Io(e.to_string())),
+        }
+    }
+
+    fn clear_session_snapshot(&self) -> Result<(), StorageError> {
+        match fs::remove_file(self.session_snapshot_path()) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(StorageError::Io(e.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
 }
@@ -1,17 +1,25 @@
 use cadenza_infra_synth_waveguide_piano::WaveguidePianoSynth;
 use cadenza_ports::midi::MidiLikeEvent;
-use cadenza_ports::synth::{SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::synth::{QualityHint, SoundFontInfo, SynthError, SynthPort};
 use cadenza_ports::types::{Bus, SampleTime};
 use parking_lot::Mutex;
-use rustysynth::{SoundFont, Synthesizer, SynthesizerSettings};
+use rustysynth::{SoundFont, SoundFontError, Synthesizer, SynthesizerSettings};
 use std::fs::File;
 use std::path::Path;
 use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering};
 use std::sync::Arc;
 
+/// Largest block rendered under one hold of a bus's lock, so a driver asking for thousands
+/// of frames at once does not keep events out for the whole render.
+const RENDER_CHUNK_FRAMES: usize = 512;
+/// Voices per bus at [`QualityHint::Reduced`].
+const REDUCED_POLYPHONY: usize = 24;
+
 pub struct RustySynth {
     fallback: WaveguidePianoSynth,
     sample_rate_hz: AtomicU32,
+    max_voices: usize,
+    reduced_quality: AtomicBool,
     enabled: AtomicBool,
     sound_font: Mutex<Option<Arc<SoundFont>>>,
     buses: [BusState; 3],
@@ -38,10 +46,12 @@ impl Default for RustySynth {
 }
 
 impl RustySynth {
-    pub fn new(sample_rate_hz: u32, _max_voices: usize) -> Self {
+    pub fn new(sample_rate_hz: u32, max_voices: usize) -> Self {
         Self {
             fallback: WaveguidePianoSynth::new(sample_rate_hz),
             sample_rate_hz: AtomicU32::new(sample_rate_hz),
+            max_voices: max_voices.max(1),
+            reduced_quality: AtomicBool::new(false),
             enabled: AtomicBool::new(false),
             sound_font: Mutex::new(None),
             buses: [BusState::new(), BusState::new(), BusState::new()],
@@ -60,6 +70,7 @@ impl RustySynth {
         let sample_rate_hz = self.sample_rate_hz.load(Ordering::Relaxed) as i32;
         let mut settings = SynthesizerSettings::new(sample_rate_hz);
         settings.enable_reverb_and_chorus = false;
+        settings.maximum_polyphony = self.polyphony();
 
         for (idx, bus) in [Bus::UserMonitor, Bus::Autopilot, Bus::MetronomeFx]
             .into_iter()
@@ -79,6 +90,15 @@ impl RustySynth {
         Ok(())
     }
 
+    /// Voices each bus may sound at once, at the current quality.
+    pub fn polyphony(&self) -> usize {
+        if self.reduced_quality.load(Ordering::Relaxed) {
+            REDUCED_POLYPHONY.min(self.max_voices)
+        } else {
+            self.max_voices
+        }
+    }
+
     fn with_active_synth<T>(&self, bus: Bus, f: impl FnOnce(&mut Synthesizer) -> T) -> Option<T> {
         let idx = Self::bus_index(bus);
         let mut guard = self.buses[idx].synth.try_lock()?;
@@ -90,9 +110,7 @@ impl RustySynth {
 impl SynthPort for RustySynth {
     fn load_soundfont_from_path(&self, path: &str) -> Result<SoundFontInfo, SynthError> {
         let mut file = File::open(path).map_err(|e| SynthError::SoundFontLoad(e.to_string()))?;
-        let sound_font = Arc::new(
-            SoundFont::new(&mut file).map_err(|e| SynthError::SoundFontLoad(e.to_string()))?,
-        );
+        let sound_font = Arc::new(SoundFont::new(&mut file).map_err(soundfont_error)?);
 
         let name = sound_font.get_info().get_bank_name().trim().to_string();
         let name = if name.is_empty() {
@@ -124,6 +142,16 @@ impl SynthPort for RustySynth {
     }
 
     fn set_program(&self, bus: Bus, gm_program: u8) -> Result<(), SynthError> {
+        if let Some(sound_font) = self.sound_font.lock().as_ref() {
+            let found = sound_font.get_presets().iter().any(|preset| {
+                preset.get_bank_number() == 0 && preset.get_patch_number() == i32::from(gm_program)
+            });
+            if !found {
+                return Err(SynthError::PresetNotFound {
+                    program: gm_program,
+                });
+            }
+        }
         let idx = Self::bus_index(bus);
         self.buses[idx].program.store(gm_program, Ordering::Relaxed);
         if !self.enabled.load(Ordering::Relaxed) {
@@ -151,6 +179,9 @@ impl SynthPort for RustySynth {
             MidiLikeEvent::Cc64 { value } => {
                 synth.process_midi_message(0, 0xB0, 0x40, value as i32);
             }
+            MidiLikeEvent::ControlChange { controller, value } => {
+                synth.process_midi_message(0, 0xB0, controller as i32, value as i32);
+            }
         });
     }
 
@@ -167,9 +198,65 @@ impl SynthPort for RustySynth {
             *value = 0.0;
         }
 
-        let _ = self.with_active_synth(bus, |synth| {
-            let frames = frames.min(out_l.len()).min(out_r.len());
-            synth.render(&mut out_l[..frames], &mut out_r[..frames]);
-        });
+        let frames = frames.min(out_l.len()).min(out_r.len());
+        let synth = &self.buses[Self::bus_index(bus)].synth;
+        for (chunk, (left, right)) in out_l[..frames]
+            .chunks_mut(RENDER_CHUNK_FRAMES)
+            .zip(out_r[..frames].chunks_mut(RENDER_CHUNK_FRAMES))
+            .enumerate()
+        {
+            // A soundfont being swapped out mid-render leaves the rest of the block silent.
+            if !self.enabled.load(Ordering::Relaxed) {
+                return;
+            }
+            // Only a block not yet started gives way to a held lock; one cut short would
+            // drop out mid-block.
+            let mut guard = if chunk == 0 {
+                let Some(guard) = synth.try_lock() else {
+                    return;
+                };
+                guard
+            } else {
+                synth.lock()
+            };
+            let Some(synth) = guard.as_mut() else {
+                return;
+            };
+            synth.render(left, right);
+        }
+    }
+
+    /// Only the waveguide fallback has a decay control; soundfont presets keep their own.
+    fn set_decay_scale(&self, scale: f32) {
+        self.fallback.set_decay_scale(scale);
+    }
+
+    /// Polyphony is fixed when a synthesizer is built, so a change rebuilds them and cuts
+    /// the notes still ringing.
+    fn set_quality(&self, hint: QualityHint) {
+        self.fallback.set_quality(hint);
+        let reduced = hint == QualityHint::Reduced;
+        if self.reduced_quality.swap(reduced, Ordering::Relaxed) == reduced {
+            return;
+        }
+        let sound_font = self.sound_font.lock().clone();
+        if let Some(sound_font) = sound_font {
+            let _ = self.rebuild_synthesizers(sound_font);
+        }
+    }
+}
+
+/// Unreadable files stay load failures; anything read but not understood is a bad soundfont.
+fn soundfont_error(err: SoundFontError) -> SynthError {
+    match err {
+        SoundFontError::IoError(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+            SynthError::InvalidSoundFont {
+                reason: "the file is truncated".to_string(),
+            }
+        }
+        SoundFontError::IoError(err) => SynthError::SoundFontLoad(err.to_string()),
+        err => SynthError::InvalidSoundFont {
+            reason: err.to_string(),
+        },
     }
 }
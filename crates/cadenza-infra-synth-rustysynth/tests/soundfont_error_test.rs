@@ -0,0 +1,54 @@
+use cadenza_infra_synth_rustysynth::RustySynth;
+use cadenza_ports::synth::{SynthError, SynthPort};
+use cadenza_ports::types::Bus;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn temp_file(name: &str, bytes: &[u8]) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let path = std::env::temp_dir().join(format!("cadenza-{name}-{nanos}.sf2"));
+    std::fs::write(&path, bytes).expect("write");
+    path
+}
+
+fn load(name: &str, bytes: &[u8]) -> Result<(), SynthError> {
+    let path = temp_file(name, bytes);
+    let result = RustySynth::default().load_soundfont_from_path(&path.display().to_string());
+    let _ = std::fs::remove_file(&path);
+    result.map(|_| ())
+}
+
+#[test]
+fn a_missing_file_is_a_load_failure() {
+    let err = RustySynth::default()
+        .load_soundfont_from_path("/nonexistent/cadenza/piano.sf2")
+        .expect_err("missing");
+    assert!(matches!(err, SynthError::SoundFontLoad(_)), "{err:?}");
+}
+
+#[test]
+fn files_that_are_not_soundfonts_are_reported_invalid() {
+    let mut wave = b"RIFF".to_vec();
+    wave.extend_from_slice(&4u32.to_le_bytes());
+    wave.extend_from_slice(b"WAVE");
+    for (name, bytes) in [
+        ("text", b"just some text, not a soundfont".as_slice()),
+        ("wave", wave.as_slice()),
+        ("truncated", b"RIF".as_slice()),
+    ] {
+        let err = load(name, bytes).expect_err(name);
+        assert!(
+            matches!(err, SynthError::InvalidSoundFont { .. }),
+            "{name}: {err:?}"
+        );
+    }
+}
+
+#[test]
+fn programs_are_accepted_before_any_soundfont_is_loaded() {
+    let synth = RustySynth::default();
+    assert!(synth.set_program(Bus::UserMonitor, 4).is_ok());
+}
@@ -4,6 +4,12 @@ use cadenza_ports::types::{Bus, SampleTime};
 use parking_lot::Mutex;
 use std::f32::consts::TAU;
 
+/// Time constant of a metronome click's decay; it is 60 dB down after about 28 ms.
+const CLICK_DECAY_S: f32 = 0.004;
+/// Envelope level at which a click voice is dropped, 80 dB down.
+const CLICK_END_GAIN: f32 = 1.0e-4;
+const CLICK_AMPLITUDE: f32 = 0.4;
+
 pub struct SimpleSynth {
     inner: Mutex<Inner>,
 }
@@ -12,6 +18,8 @@ pub struct SimpleSynth {
 struct Inner {
     sample_rate_hz: f32,
     max_voices: usize,
+    /// NoteOns on [`Bus::MetronomeFx`] play a decaying noise click instead of a sine.
+    metronome_click: bool,
     buses: [BusState; 3],
 }
 
@@ -33,6 +41,15 @@ struct Voice {
     release_samples_left: u32,
     release_total_samples: u32,
     age: u64,
+    click: Option<Click>,
+}
+
+/// Noise burst that decays on its own, whatever the key and pedal do.
+#[derive(Clone, Debug)]
+struct Click {
+    gain: f32,
+    decay: f32,
+    noise: u32,
 }
 
 impl SimpleSynth {
@@ -41,12 +58,21 @@ impl SimpleSynth {
             inner: Mutex::new(Inner {
                 sample_rate_hz: sample_rate_hz as f32,
                 max_voices: max_voices.max(8),
+                metronome_click: true,
                 buses: [BusState::new(), BusState::new(), BusState::new()],
             }),
         }
     }
 }
 
+impl SimpleSynth {
+    /// With `on`, the default, notes on [`Bus::MetronomeFx`] sound as short noise clicks
+    /// rather than sustained sines, so the metronome is crisp. Other buses are unaffected.
+    pub fn set_metronome_click_mode(&self, on: bool) {
+        self.inner.lock().metronome_click = on;
+    }
+}
+
 impl Default for SimpleSynth {
     fn default() -> Self {
         Self::new(48_000, 64)
@@ -81,6 +107,11 @@ impl Inner {
         let freq = 440.0 * 2.0_f32.powf((note as f32 - 69.0) / 12.0);
         let velocity = (velocity as f32 / 127.0).clamp(0.05, 1.0);
         let release_total_samples = (self.sample_rate_hz * 0.2) as u32;
+        let click = (self.metronome_click && bus == Bus::MetronomeFx).then(|| Click {
+            gain: 1.0,
+            decay: (-1.0 / (CLICK_DECAY_S * self.sample_rate_hz)).exp(),
+            noise: 0x9E37_79B9 ^ u32::from(note),
+        });
         let voice = Voice {
             note,
             freq,
@@ -91,6 +122,7 @@ impl Inner {
             release_samples_left: 0,
             release_total_samples: release_total_samples.max(1),
             age: state.note_counter,
+            click,
         };
         state.voices.push(voice);
     }
@@ -99,7 +131,7 @@ impl Inner {
         let index = Self::bus_index(bus);
         let state = &mut self.buses[index];
         for voice in &mut state.voices {
-            if voice.note == note && voice.key_down {
+            if voice.note == note && voice.key_down && voice.click.is_none() {
                 voice.key_down = false;
                 if state.sustain_down {
                     voice.sustained = true;
@@ -117,7 +149,7 @@ impl Inner {
 
         if !down {
             for voice in &mut state.voices {
-                if !voice.key_down && voice.sustained {
+                if voice.click.is_none() && !voice.key_down && voice.sustained {
                     voice.sustained = false;
                     voice.release_samples_left = voice.release_total_samples;
                 }
@@ -138,6 +170,19 @@ impl Inner {
         let amplitude = 0.2;
 
         for voice in &mut state.voices {
+            if let Some(click) = &mut voice.click {
+                for i in 0..frames {
+                    click.noise ^= click.noise << 13;
+                    click.noise ^= click.noise >> 17;
+                    click.noise ^= click.noise << 5;
+                    let noise = click.noise as f32 / u32::MAX as f32 * 2.0 - 1.0;
+                    let sample = noise * click.gain * voice.velocity * CLICK_AMPLITUDE;
+                    out_l[i] += sample;
+                    out_r[i] += sample;
+                    click.gain *= click.decay;
+                }
+                continue;
+            }
             let phase_step = TAU * voice.freq / self.sample_rate_hz;
             for i in 0..frames {
                 let mut gain = voice.velocity;
@@ -156,9 +201,10 @@ impl Inner {
             }
         }
 
-        state
-            .voices
-            .retain(|voice| voice.key_down || voice.sustained || voice.release_samples_left > 0);
+        state.voices.retain(|voice| match &voice.click {
+            Some(click) => click.gain > CLICK_END_GAIN,
+            None => voice.key_down || voice.sustained || voice.release_samples_left > 0,
+        });
     }
 }
 
@@ -192,6 +238,7 @@ impl SynthPort for SimpleSynth {
             MidiLikeEvent::NoteOn { note, velocity } => inner.note_on(bus, note, velocity),
             MidiLikeEvent::NoteOff { note } => inner.note_off(bus, note),
             MidiLikeEvent::Cc64 { value } => inner.sustain(bus, value >= 64),
+            MidiLikeEvent::ControlChange { .. } => {}
         }
     }
 
@@ -199,4 +246,9 @@ impl SynthPort for SimpleSynth {
         let mut inner = self.inner.lock();
         inner.render_bus(bus, frames, out_l, out_r);
     }
+
+    fn is_silent(&self, bus: Bus) -> bool {
+        let inner = self.inner.lock();
+        inner.buses[Inner::bus_index(bus)].voices.is_empty()
+    }
 }
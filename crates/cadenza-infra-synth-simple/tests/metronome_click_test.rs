@@ -0,0 +1,68 @@
+use cadenza_infra_synth_simple::SimpleSynth;
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::synth::SynthPort;
+use cadenza_ports::types::Bus;
+
+const SAMPLE_RATE_HZ: u32 = 48_000;
+/// 1 ms.
+const BLOCK: usize = 48;
+
+fn strike(synth: &SimpleSynth, bus: Bus) {
+    synth.handle_event(
+        bus,
+        MidiLikeEvent::NoteOn {
+            note: 76,
+            velocity: 120,
+        },
+        0,
+    );
+}
+
+/// Peak of each 1 ms block over `blocks` milliseconds.
+fn render_peaks(synth: &SimpleSynth, bus: Bus, blocks: usize) -> Vec<f32> {
+    let mut left = [0.0; BLOCK];
+    let mut right = [0.0; BLOCK];
+    (0..blocks)
+        .map(|_| {
+            synth.render(bus, BLOCK, &mut left, &mut right);
+            left.iter()
+                .chain(&right)
+                .fold(0.0_f32, |peak, sample| peak.max(sample.abs()))
+        })
+        .collect()
+}
+
+#[test]
+fn a_metronome_click_is_60_db_down_within_50_ms() {
+    let synth = SimpleSynth::new(SAMPLE_RATE_HZ, 8);
+    strike(&synth, Bus::MetronomeFx);
+    let peaks = render_peaks(&synth, Bus::MetronomeFx, 100);
+
+    let attack = peaks[0];
+    assert!(attack > 0.1, "{attack}");
+    assert!(peaks[50] < attack * 1.0e-3, "{} vs {attack}", peaks[50]);
+    assert!(synth.is_silent(Bus::MetronomeFx));
+}
+
+#[test]
+fn clicks_ignore_the_key_and_the_pedal() {
+    let synth = SimpleSynth::new(SAMPLE_RATE_HZ, 8);
+    synth.handle_event(Bus::MetronomeFx, MidiLikeEvent::Cc64 { value: 127 }, 0);
+    strike(&synth, Bus::MetronomeFx);
+    let peaks = render_peaks(&synth, Bus::MetronomeFx, 100);
+    assert!(peaks[50] < peaks[0] * 1.0e-3, "{peaks:?}");
+    assert!(synth.is_silent(Bus::MetronomeFx));
+}
+
+#[test]
+fn other_buses_and_click_mode_off_keep_the_sine() {
+    let synth = SimpleSynth::new(SAMPLE_RATE_HZ, 8);
+    strike(&synth, Bus::UserMonitor);
+    let peaks = render_peaks(&synth, Bus::UserMonitor, 100);
+    assert!(peaks[99] > peaks[0] * 0.5, "{peaks:?}");
+
+    synth.set_metronome_click_mode(false);
+    strike(&synth, Bus::MetronomeFx);
+    let peaks = render_peaks(&synth, Bus::MetronomeFx, 100);
+    assert!(peaks[99] > peaks[0] * 0.5, "{peaks:?}");
+}
@@ -1,13 +1,44 @@
 use cadenza_ports::midi::MidiLikeEvent;
-use cadenza_ports::synth::{SoundFontInfo, SynthError, SynthPort};
+use cadenza_ports::synth::{
+    QualityHint, SoundFontInfo, SynthError, SynthPort, MAX_DECAY_SCALE, MIN_DECAY_SCALE,
+};
 use cadenza_ports::types::{Bus, SampleTime};
-use parking_lot::Mutex;
+use parking_lot::{Mutex, MutexGuard};
 
 const MAX_DELAY_SAMPLES: usize = 4096;
 const MAX_VOICES: usize = 64;
 const MAX_STRINGS_PER_NOTE: usize = 3;
+/// Strings per note at [`QualityHint::Reduced`].
+const REDUCED_STRINGS_PER_NOTE: usize = 2;
 const HAMMER_SHAPER_MAX: usize = 512;
 const SOUNDBOARD_MODES: usize = 6;
+/// Frames the soundboard works through at a time, sized for its scratch buffers.
+const SOUNDBOARD_BLOCK: usize = 256;
+/// Corner of the DC blocker at the end of every bus, well below the lowest A.
+const DC_BLOCK_HZ: f32 = 10.0;
+/// Range the optional output high-pass corner is held to.
+pub const MIN_HIGH_PASS_HZ: f32 = 20.0;
+pub const MAX_HIGH_PASS_HZ: f32 = 80.0;
+/// Largest block rendered under one hold of the lock. Drivers that ask for thousands of
+/// frames at once would otherwise keep events and settings waiting for the whole render.
+const RENDER_CHUNK_FRAMES: usize = 512;
+/// Output peak below which a bus with no sounding voices counts as silent (about -100 dBFS),
+/// so the soundboard tail is allowed to ring out first.
+const SILENCE_PEAK: f32 = 1.0e-5;
+/// Highest damped note by default, G6; strings above it have no dampers on most grands.
+pub const DEFAULT_DAMPER_LIMIT_NOTE: u8 = 91;
+/// Per-sample speed at which a mid-register damper settles on its string.
+const DAMPER_COEFF: f32 = 0.02;
+/// Dampers landing this soon after the pedal comes up settle more gently, so the release of
+/// a pedalled chord does not thump.
+const PEDAL_RELEASE_SOFT_MS: f32 = 15.0;
+const PEDAL_RELEASE_DAMPER_SCALE: f32 = 0.3;
+/// First-order allpass sections per string that make higher partials run sharp.
+const DISPERSION_STAGES: usize = 4;
+const MAX_DISPERSION_COEFF: f32 = 0.97;
+/// Highest partial the dispersion is fitted to, and the highest frequency it may sit at.
+const DISPERSION_FIT_PARTIAL: u32 = 8;
+const DISPERSION_FIT_MAX_HZ_RATIO: f32 = 0.4;
 
 pub struct WaveguidePianoSynth {
     inner: Mutex<Inner>,
@@ -15,14 +46,32 @@ pub struct WaveguidePianoSynth {
 
 struct Inner {
     sample_rate_hz: u32,
+    damper_limit: u8,
+    inharmonicity_scale: f32,
+    decay_scale: f32,
+    high_pass_hz: Option<f32>,
+    quality: QualityHint,
     buses: [BusState; 3],
 }
 
+/// Synth-wide settings a note is struck with, copied out of [`Inner`] for each note on.
+#[derive(Clone, Copy)]
+struct StrikeSettings {
+    sample_rate_hz: u32,
+    damper_limit: u8,
+    inharmonicity_scale: f32,
+    decay_scale: f32,
+    max_strings: usize,
+}
+
 struct BusState {
     sustain_down: bool,
     note_counter: u64,
     voices: Vec<Voice>,
     soundboard: Soundboard,
+    output_filter: OutputFilter,
+    /// Output peak of the most recent render.
+    last_peak: f32,
 }
 
 struct Voice {
@@ -34,6 +83,11 @@ struct Voice {
     gain: f32,
     out_gain: f32,
     damper: f32,
+    /// How quickly the damper lands on release, relative to [`DAMPER_COEFF`]; 0 for
+    /// undamped strings.
+    damper_speed: f32,
+    /// Samples left during which the damper settles gently after a pedal release.
+    soft_damper_samples: u32,
     age: u64,
     pan: f32,
     hammer: HammerModel,
@@ -80,30 +134,69 @@ struct StringModel {
     lp_attack: f32,
     lp_sustain: f32,
     feedback: f32,
+    /// Feedback taken away per trip round the loop once the damper is down.
+    damper_cut: f32,
+    /// Taken from the loop filter's state per sample once the damper is down, so an offset
+    /// the darkened filter holds back dies away with the tone instead of lingering.
+    damper_leak: f32,
     last: f32,
     gain: f32,
     tone: f32,
     tone_decay: f32,
     avg_coeff: f32,
     pickup_mix: f32,
-    ap1_x1: f32,
-    ap1_y1: f32,
-    ap1_coeff: f32,
-    ap2_x1: f32,
-    ap2_y1: f32,
-    ap2_coeff: f32,
+    /// Shared by every section of the dispersion chain; 0 bypasses it.
+    dispersion_coeff: f32,
+    /// `(x1, y1)` of each dispersion section.
+    dispersion: [(f32, f32); DISPERSION_STAGES],
 }
 
+/// Body of the instrument shared by every voice on a bus: a small reverb for the case and
+/// a few resonant modes for the board's colour.
 struct Soundboard {
     sample_rate_hz: u32,
     mix: f32,
     color_mix: f32,
+    /// Off leaves out the resonant modes, the costlier half of the body.
+    color_enabled: bool,
     comb_l: [CombFilter; 4],
     comb_r: [CombFilter; 4],
     allpass_l: [AllpassFilter; 2],
     allpass_r: [AllpassFilter; 2],
     modes_l: [Resonator; SOUNDBOARD_MODES],
     modes_r: [Resonator; SOUNDBOARD_MODES],
+    /// Scratch for the mono mix the body is driven by, one block at a time.
+    mono: Vec<f32>,
+}
+
+/// The soundboard alone, for the crate's benchmark and regression tests. Not part of the
+/// API.
+#[doc(hidden)]
+pub struct BareSoundboard(Soundboard);
+
+impl BareSoundboard {
+    pub fn new(sample_rate_hz: u32) -> Self {
+        Self(Soundboard::new(sample_rate_hz))
+    }
+
+    pub fn process(&mut self, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        self.0.process(frames, out_l, out_r);
+    }
+}
+
+/// Last stage of every bus: a DC blocker, then an optional gentle high-pass for small
+/// speakers. Public so it can be tested alone.
+pub struct OutputFilter {
+    sample_rate_hz: u32,
+    dc_block: HighPass,
+    high_pass: Option<HighPass>,
+}
+
+/// First-order high-pass on both channels.
+struct HighPass {
+    coeff: f32,
+    x1: [f32; 2],
+    y1: [f32; 2],
 }
 
 struct CombFilter {
@@ -183,12 +276,14 @@ impl Soundboard {
             sample_rate_hz,
             mix: 0.06,
             color_mix: 0.07,
+            color_enabled: true,
             comb_l,
             comb_r,
             allpass_l,
             allpass_r,
             modes_l,
             modes_r,
+            mono: vec![0.0; SOUNDBOARD_BLOCK],
         }
     }
 
@@ -206,9 +301,16 @@ impl Soundboard {
             return;
         }
 
+        let color_enabled = self.color_enabled;
         *self = Self::new(sample_rate_hz);
+        self.color_enabled = color_enabled;
     }
 
+    fn set_color_enabled(&mut self, enabled: bool) {
+        self.color_enabled = enabled;
+    }
+
+    /// Mixes the body into the first `frames` frames of a stereo block in place.
     fn process(&mut self, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
         let frames = frames.min(out_l.len()).min(out_r.len());
         if frames == 0 {
@@ -216,59 +318,220 @@ impl Soundboard {
         }
 
         let mix = self.mix.clamp(0.0, 1.0);
-        let color_mix = self.color_mix.clamp(0.0, 0.5);
+        let color_mix = if self.color_enabled {
+            self.color_mix.clamp(0.0, 0.5)
+        } else {
+            0.0
+        };
         if mix <= 0.0001 && color_mix <= 0.0001 {
             return;
         }
         let apply_reverb = mix > 0.0001;
+        let apply_color = color_mix > 0.0001;
         let dry = 1.0 - mix;
-        let wet_gain = 0.18;
 
-        for i in 0..frames {
-            let dry_l = out_l[i];
-            let dry_r = out_r[i];
-            let input = (dry_l + dry_r) * 0.5;
-
-            let (mut out_l_sample, mut out_r_sample) = if apply_reverb {
-                let mut wet_l = 0.0_f32;
-                for comb in self.comb_l.iter_mut() {
-                    wet_l += comb.process(input);
-                }
-                let mut wet_r = 0.0_f32;
-                for comb in self.comb_r.iter_mut() {
-                    wet_r += comb.process(input);
+        let Self {
+            comb_l,
+            comb_r,
+            allpass_l,
+            allpass_r,
+            modes_l,
+            modes_r,
+            mono,
+            ..
+        } = self;
+        for (left, right) in out_l[..frames]
+            .chunks_mut(SOUNDBOARD_BLOCK)
+            .zip(out_r[..frames].chunks_mut(SOUNDBOARD_BLOCK))
+        {
+            let mono = &mut mono[..left.len()];
+            for ((m, &l), &r) in mono.iter_mut().zip(left.iter()).zip(right.iter()) {
+                *m = (l + r) * 0.5;
+            }
+            if apply_reverb {
+                reverb_block(
+                    [comb_l, comb_r],
+                    [allpass_l, allpass_r],
+                    mono,
+                    [&mut *left, &mut *right],
+                    dry,
+                    mix,
+                );
+            }
+            if apply_color {
+                color_block([modes_l, modes_r], mono, [left, right], color_mix);
+            }
+        }
+    }
+}
+
+/// Mixes the case reverb into both sides: on each side the combs run in parallel, then
+/// the allpasses in series. Every filter of both sides steps in lockstep so their feedback
+/// loops overlap, in stretches short enough that no delay line wraps.
+fn reverb_block(
+    [combs_l, combs_r]: [&mut [CombFilter; 4]; 2],
+    [allpasses_l, allpasses_r]: [&mut [AllpassFilter; 2]; 2],
+    input: &[f32],
+    [out_l, out_r]: [&mut [f32]; 2],
+    dry: f32,
+    mix: f32,
+) {
+    const WET_GAIN: f32 = 0.18;
+    let mut combs: [&mut CombFilter; 8] = {
+        let [a, b, c, d] = combs_l.each_mut();
+        let [e, f, g, h] = combs_r.each_mut();
+        [a, b, c, d, e, f, g, h]
+    };
+    let mut allpasses: [&mut AllpassFilter; 4] = {
+        let [a, b] = allpasses_l.each_mut();
+        let [c, d] = allpasses_r.each_mut();
+        [a, b, c, d]
+    };
+    let comb_damp = combs.each_ref().map(|c| c.damp);
+    let comb_feedback = combs.each_ref().map(|c| c.feedback);
+    let allpass_feedback = allpasses.each_ref().map(|ap| ap.feedback);
+    let mut store = combs.each_ref().map(|c| c.filter_store);
+    let mut done = 0;
+    while done < input.len() {
+        let run = combs
+            .iter()
+            .map(|c| c.buf.len() - c.idx)
+            .chain(allpasses.iter().map(|ap| ap.buf.len() - ap.idx))
+            .fold(input.len() - done, usize::min);
+        let comb_bufs = combs.each_mut().map(|c| &mut c.buf[c.idx..][..run]);
+        let allpass_bufs = allpasses.each_mut().map(|ap| &mut ap.buf[ap.idx..][..run]);
+        let input = &input[done..][..run];
+        let out = [&mut out_l[done..][..run], &mut out_r[done..][..run]];
+        for i in 0..run {
+            let x = input[i];
+            let mut wet = [0.0_f32; 2];
+            for k in 0..comb_bufs.len() {
+                let output = comb_bufs[k][i];
+                store[k] = output + (store[k] - output) * comb_damp[k];
+                comb_bufs[k][i] = x + store[k] * comb_feedback[k];
+                wet[k / 4] += output;
+            }
+            for (side, wet) in wet.iter_mut().enumerate() {
+                *wet *= WET_GAIN;
+                for k in 2 * side..2 * side + 2 {
+                    let buf_out = allpass_bufs[k][i];
+                    allpass_bufs[k][i] = *wet + buf_out * allpass_feedback[k];
+                    *wet = -*wet + buf_out;
                 }
+                out[side][i] = out[side][i] * dry + *wet * mix;
+            }
+        }
+        for c in combs.iter_mut() {
+            c.idx = (c.idx + run) % c.buf.len();
+        }
+        for ap in allpasses.iter_mut() {
+            ap.idx = (ap.idx + run) % ap.buf.len();
+        }
+        done += run;
+    }
+    for (c, store) in combs.into_iter().zip(store) {
+        c.filter_store = store;
+    }
+}
 
-                wet_l *= wet_gain;
-                wet_r *= wet_gain;
+/// Adds the board's resonant modes onto both sides. The modes of both sides step in
+/// lockstep, each side padded with silent ones to a width the arithmetic vectorizes at.
+fn color_block(
+    modes: [&mut [Resonator; SOUNDBOARD_MODES]; 2],
+    input: &[f32],
+    [out_l, out_r]: [&mut [f32]; 2],
+    color_mix: f32,
+) {
+    const SIDE: usize = SOUNDBOARD_MODES.next_multiple_of(4);
+    const LANES: usize = 2 * SIDE;
+    let lanes = |field: fn(&Resonator) -> f32| -> [f32; LANES] {
+        std::array::from_fn(|k| modes[k / SIDE].get(k % SIDE).map_or(0.0, field))
+    };
+    let a1 = lanes(|m| m.a1);
+    let a2 = lanes(|m| m.a2);
+    let b = lanes(|m| m.b);
+    let gain = lanes(|m| m.gain);
+    let mut y1 = lanes(|m| m.y1);
+    let mut y2 = lanes(|m| m.y2);
+    for ((&x, l), r) in input.iter().zip(out_l.iter_mut()).zip(out_r.iter_mut()) {
+        let mut color = [0.0_f32; LANES];
+        for k in 0..LANES {
+            let y = b[k] * x + a1[k] * y1[k] + a2[k] * y2[k];
+            y2[k] = y1[k];
+            y1[k] = y;
+            color[k] = y * gain[k];
+        }
+        let (color_l, color_r) = color.split_at(SIDE);
+        *l += color_l.iter().sum::<f32>() * color_mix;
+        *r += color_r.iter().sum::<f32>() * color_mix;
+    }
+    for (side, modes) in modes.into_iter().enumerate() {
+        for (k, mode) in modes.iter_mut().enumerate() {
+            mode.y1 = y1[side * SIDE + k];
+            mode.y2 = y2[side * SIDE + k];
+        }
+    }
+}
 
-                for ap in self.allpass_l.iter_mut() {
-                    wet_l = ap.process(wet_l);
-                }
-                for ap in self.allpass_r.iter_mut() {
-                    wet_r = ap.process(wet_r);
-                }
+impl OutputFilter {
+    /// `high_pass_hz` is held to [`MIN_HIGH_PASS_HZ`]..=[`MAX_HIGH_PASS_HZ`]; `None` leaves
+    /// only the DC blocker.
+    pub fn new(sample_rate_hz: u32, high_pass_hz: Option<f32>) -> Self {
+        let mut filter = Self {
+            sample_rate_hz,
+            dc_block: HighPass::new(sample_rate_hz, DC_BLOCK_HZ),
+            high_pass: None,
+        };
+        filter.set_high_pass(high_pass_hz);
+        filter
+    }
 
-                (dry_l * dry + wet_l * mix, dry_r * dry + wet_r * mix)
-            } else {
-                (dry_l, dry_r)
-            };
+    /// Moves the high-pass corner, keeping its state so a change mid-note does not click.
+    pub fn set_high_pass(&mut self, high_pass_hz: Option<f32>) {
+        let Some(hz) = high_pass_hz else {
+            self.high_pass = None;
+            return;
+        };
+        let hz = hz.clamp(MIN_HIGH_PASS_HZ, MAX_HIGH_PASS_HZ);
+        let coeff = HighPass::new(self.sample_rate_hz, hz).coeff;
+        match &mut self.high_pass {
+            Some(high_pass) => high_pass.coeff = coeff,
+            None => self.high_pass = Some(HighPass::new(self.sample_rate_hz, hz)),
+        }
+    }
 
-            if color_mix > 0.0001 {
-                let mut color_l = 0.0_f32;
-                for mode in self.modes_l.iter_mut() {
-                    color_l += mode.process(input);
-                }
-                let mut color_r = 0.0_f32;
-                for mode in self.modes_r.iter_mut() {
-                    color_r += mode.process(input);
-                }
-                out_l_sample += color_l * color_mix;
-                out_r_sample += color_r * color_mix;
-            }
+    /// Filters the first `frames` frames of a stereo block in place.
+    pub fn process(&mut self, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
+        let frames = frames.min(out_l.len()).min(out_r.len());
+        self.dc_block
+            .process(&mut out_l[..frames], &mut out_r[..frames]);
+        if let Some(high_pass) = &mut self.high_pass {
+            high_pass.process(&mut out_l[..frames], &mut out_r[..frames]);
+        }
+    }
+}
 
-            out_l[i] = out_l_sample;
-            out_r[i] = out_r_sample;
+impl HighPass {
+    fn new(sample_rate_hz: u32, cutoff_hz: f32) -> Self {
+        let sr = sample_rate_hz.max(1) as f32;
+        Self {
+            coeff: (-2.0 * std::f32::consts::PI * cutoff_hz / sr).exp(),
+            x1: [0.0; 2],
+            y1: [0.0; 2],
+        }
+    }
+
+    fn process(&mut self, out_l: &mut [f32], out_r: &mut [f32]) {
+        for (channel, out) in [out_l, out_r].into_iter().enumerate() {
+            let (mut x1, mut y1) = (self.x1[channel], self.y1[channel]);
+            for sample in out.iter_mut() {
+                let x = *sample;
+                y1 = self.coeff * (y1 + x - x1);
+                x1 = x;
+                *sample = y1;
+            }
+            self.x1[channel] = x1;
+            self.y1[channel] = y1;
         }
     }
 }
@@ -291,17 +554,6 @@ impl CombFilter {
         self.idx = 0;
         self.filter_store = 0.0;
     }
-
-    fn process(&mut self, input: f32) -> f32 {
-        let output = self.buf[self.idx];
-        self.filter_store = output + (self.filter_store - output) * self.damp;
-        self.buf[self.idx] = input + self.filter_store * self.feedback;
-        self.idx += 1;
-        if self.idx >= self.buf.len() {
-            self.idx = 0;
-        }
-        output
-    }
 }
 
 impl AllpassFilter {
@@ -319,17 +571,6 @@ impl AllpassFilter {
         }
         self.idx = 0;
     }
-
-    fn process(&mut self, input: f32) -> f32 {
-        let buf_out = self.buf[self.idx];
-        let output = -input + buf_out;
-        self.buf[self.idx] = input + buf_out * self.feedback;
-        self.idx += 1;
-        if self.idx >= self.buf.len() {
-            self.idx = 0;
-        }
-        output
-    }
 }
 
 impl Resonator {
@@ -356,13 +597,6 @@ impl Resonator {
         self.y1 = 0.0;
         self.y2 = 0.0;
     }
-
-    fn process(&mut self, x: f32) -> f32 {
-        let y = self.b * x + self.a1 * self.y1 + self.a2 * self.y2;
-        self.y2 = self.y1;
-        self.y1 = y;
-        y * self.gain
-    }
 }
 
 fn scale_len(base_len: usize, sample_rate_hz: u32) -> usize {
@@ -383,16 +617,61 @@ impl WaveguidePianoSynth {
             inner: Mutex::new(Inner::new(sample_rate_hz)),
         }
     }
+
+    /// Notes above `note` ring on after release as if they had no damper. Applies to notes
+    /// struck from now on.
+    pub fn set_damper_limit(&self, note: u8) {
+        self.inner.lock().damper_limit = note;
+    }
+
+    /// Scales the string stiffness that stretches the partials: 0 gives pure harmonics, 1
+    /// the usual grand. Applies to notes struck from now on.
+    pub fn set_inharmonicity(&self, scale: f32) {
+        self.inner.lock().inharmonicity_scale = scale.clamp(0.0, 4.0);
+    }
+
+    /// Scales how long the strings of notes struck from now on ring: 0.5 about halves their
+    /// T60, while the soundboard keeps its own. Held to
+    /// [`MIN_DECAY_SCALE`]..=[`MAX_DECAY_SCALE`]; 1 is the usual grand.
+    pub fn set_decay_scale(&self, scale: f32) {
+        self.inner.lock().decay_scale = scale.clamp(MIN_DECAY_SCALE, MAX_DECAY_SCALE);
+    }
+
+    /// At [`QualityHint::Reduced`] notes struck from now on get at most two strings and the
+    /// soundboard leaves out its resonant modes.
+    pub fn set_quality(&self, hint: QualityHint) {
+        let mut inner = self.inner.lock();
+        inner.quality = hint;
+        for bus in inner.buses.iter_mut() {
+            bus.soundboard.set_color_enabled(hint == QualityHint::Full);
+        }
+    }
+
+    /// Adds a gentle high-pass to every bus's output, its corner held to
+    /// [`MIN_HIGH_PASS_HZ`]..=[`MAX_HIGH_PASS_HZ`]. `None`, the default, leaves only the DC
+    /// blocker.
+    pub fn set_high_pass(&self, cutoff_hz: Option<f32>) {
+        let mut inner = self.inner.lock();
+        inner.high_pass_hz = cutoff_hz;
+        for bus in inner.buses.iter_mut() {
+            bus.output_filter.set_high_pass(cutoff_hz);
+        }
+    }
 }
 
 impl Inner {
     fn new(sample_rate_hz: u32) -> Self {
         Self {
             sample_rate_hz,
+            damper_limit: DEFAULT_DAMPER_LIMIT_NOTE,
+            inharmonicity_scale: 1.0,
+            decay_scale: 1.0,
+            high_pass_hz: None,
+            quality: QualityHint::Full,
             buses: [
-                BusState::new(sample_rate_hz),
-                BusState::new(sample_rate_hz),
-                BusState::new(sample_rate_hz),
+                BusState::new(sample_rate_hz, None),
+                BusState::new(sample_rate_hz, None),
+                BusState::new(sample_rate_hz, None),
             ],
         }
     }
@@ -404,10 +683,23 @@ impl Inner {
             Bus::MetronomeFx => 2,
         }
     }
+
+    fn strike_settings(&self) -> StrikeSettings {
+        StrikeSettings {
+            sample_rate_hz: self.sample_rate_hz,
+            damper_limit: self.damper_limit,
+            inharmonicity_scale: self.inharmonicity_scale,
+            decay_scale: self.decay_scale,
+            max_strings: match self.quality {
+                QualityHint::Full => MAX_STRINGS_PER_NOTE,
+                QualityHint::Reduced => REDUCED_STRINGS_PER_NOTE,
+            },
+        }
+    }
 }
 
 impl BusState {
-    fn new(sample_rate_hz: u32) -> Self {
+    fn new(sample_rate_hz: u32, high_pass_hz: Option<f32>) -> Self {
         let mut voices = Vec::with_capacity(MAX_VOICES);
         for _ in 0..MAX_VOICES {
             voices.push(Voice::new());
@@ -417,16 +709,24 @@ impl BusState {
             note_counter: 0,
             voices,
             soundboard: Soundboard::new(sample_rate_hz),
+            output_filter: OutputFilter::new(sample_rate_hz, high_pass_hz),
+            last_peak: 0.0,
         }
     }
 
-    fn reset(&mut self, sample_rate_hz: u32) {
+    fn reset(&mut self, sample_rate_hz: u32, high_pass_hz: Option<f32>) {
         self.sustain_down = false;
         self.note_counter = 0;
         for voice in self.voices.iter_mut() {
             voice.reset();
         }
         self.soundboard.reset(sample_rate_hz);
+        self.output_filter = OutputFilter::new(sample_rate_hz, high_pass_hz);
+        self.last_peak = 0.0;
+    }
+
+    fn is_silent(&self) -> bool {
+        self.last_peak < SILENCE_PEAK && self.voices.iter().all(|voice| !voice.active)
     }
 
     fn allocate_voice(&mut self) -> &mut Voice {
@@ -446,7 +746,14 @@ impl BusState {
         &mut self.voices[best_idx]
     }
 
-    fn note_on(&mut self, sample_rate_hz: u32, note: u8, velocity: u8) {
+    fn note_on(&mut self, strike: StrikeSettings, note: u8, velocity: u8) {
+        let StrikeSettings {
+            sample_rate_hz,
+            damper_limit,
+            inharmonicity_scale,
+            decay_scale,
+            max_strings,
+        } = strike;
         let vel = (velocity as f32 / 127.0).clamp(0.02, 1.0);
         self.note_counter = self.note_counter.wrapping_add(1);
         let age = self.note_counter;
@@ -458,12 +765,14 @@ impl BusState {
         voice.velocity = vel;
         voice.key_down = true;
         voice.sustained = false;
+        voice.damper_speed = damper_speed(note, damper_limit);
         voice.age = age;
 
         voice.pan = note_to_pan(note);
         voice.out_gain = vel.powf(1.25) * 0.32;
 
         let (string_count, detunes) = string_plan(note);
+        let string_count = string_count.min(max_strings);
         voice.string_count = string_count;
 
         let base_freq = midi_note_to_hz(note);
@@ -482,9 +791,24 @@ impl BusState {
             }
             let detune = detunes[idx];
             let freq = base_freq * (1.0 + detune);
-            let delay_len =
-                (sample_rate_hz as f32 / freq).clamp(8.0, (MAX_DELAY_SAMPLES - 1) as f32);
-            string.init(delay_len, vel, note);
+            let (dispersion_coeff, dispersion_delay) = dispersion_design(
+                sample_rate_hz,
+                freq,
+                string_inharmonicity(note) * inharmonicity_scale,
+            );
+            let delay_len = (sample_rate_hz as f32 / freq - dispersion_delay)
+                .clamp(8.0, (MAX_DELAY_SAMPLES - 1) as f32);
+            string.init(delay_len, vel, note, dispersion_coeff);
+            string.scale_decay(
+                decay_scale,
+                std::f32::consts::TAU * freq / sample_rate_hz as f32,
+            );
+            // A trip round the loop takes a whole period, dispersion included.
+            let t60 = damped_decay_s(note, damper_limit);
+            string.damper_cut = t60.map_or(0.0, |t60| 1.0 - 10.0_f32.powf(-3.0 / (freq * t60)));
+            string.damper_leak = t60.map_or(0.0, |t60| {
+                1.0 - 10.0_f32.powf(-3.0 / (sample_rate_hz as f32 * t60))
+            });
         }
     }
 
@@ -500,13 +824,19 @@ impl BusState {
         }
     }
 
-    fn sustain(&mut self, down: bool) {
+    fn sustain(&mut self, sample_rate_hz: u32, down: bool) {
+        let released = self.sustain_down && !down;
         self.sustain_down = down;
-        if down {
+        if !released {
             return;
         }
+        let soft_samples = (sample_rate_hz as f32 * PEDAL_RELEASE_SOFT_MS / 1000.0) as u32;
         for voice in self.voices.iter_mut() {
-            if voice.active && !voice.key_down && voice.sustained {
+            if !voice.active {
+                continue;
+            }
+            voice.soft_damper_samples = soft_samples;
+            if !voice.key_down && voice.sustained {
                 voice.sustained = false;
             }
         }
@@ -533,6 +863,11 @@ impl BusState {
         }
 
         self.soundboard.process(frames, out_l, out_r);
+        self.output_filter.process(frames, out_l, out_r);
+        self.last_peak = out_l[..frames]
+            .iter()
+            .chain(&out_r[..frames])
+            .fold(0.0_f32, |peak, sample| peak.max(sample.abs()));
 
         for voice in self.voices.iter_mut() {
             if voice.active && !voice.key_down && !voice.sustained && voice.gain < 0.0008 {
@@ -553,6 +888,8 @@ impl Voice {
             gain: 0.0,
             out_gain: 0.0,
             damper: 0.0,
+            damper_speed: 1.0,
+            soft_damper_samples: 0,
             age: 0,
             pan: 0.0,
             hammer: HammerModel::new(),
@@ -568,6 +905,8 @@ impl Voice {
         self.gain = 0.0;
         self.out_gain = 0.0;
         self.damper = 0.0;
+        self.damper_speed = 1.0;
+        self.soft_damper_samples = 0;
         self.hammer.reset();
         self.string_count = 0;
         for string in self.strings.iter_mut() {
@@ -576,7 +915,7 @@ impl Voice {
     }
 
     fn render(&mut self, frames: usize, out_l: &mut [f32], out_r: &mut [f32]) {
-        let damper_coeff = 0.02;
+        let damper_coeff = DAMPER_COEFF * self.damper_speed;
         let amp_coeff = 0.01;
         let mut amp = self.gain;
 
@@ -590,7 +929,13 @@ impl Voice {
             } else {
                 1.0
             };
-            self.damper += (target - self.damper) * damper_coeff;
+            let coeff = if self.soft_damper_samples > 0 {
+                self.soft_damper_samples -= 1;
+                damper_coeff * PEDAL_RELEASE_DAMPER_SCALE
+            } else {
+                damper_coeff
+            };
+            self.damper += (target - self.damper) * coeff;
 
             let mut strike_disp = 0.0_f32;
             for idx in 0..self.string_count {
@@ -821,18 +1166,16 @@ impl StringModel {
             lp_attack: 0.0,
             lp_sustain: 0.0,
             feedback: 0.0,
+            damper_cut: 0.0,
+            damper_leak: 0.0,
             last: 0.0,
             gain: 0.0,
             tone: 0.0,
             tone_decay: 0.99995,
             avg_coeff: 0.3,
             pickup_mix: 0.6,
-            ap1_x1: 0.0,
-            ap1_y1: 0.0,
-            ap1_coeff: 0.0,
-            ap2_x1: 0.0,
-            ap2_y1: 0.0,
-            ap2_coeff: 0.0,
+            dispersion_coeff: 0.0,
+            dispersion: [(0.0, 0.0); DISPERSION_STAGES],
         }
     }
 
@@ -845,21 +1188,19 @@ impl StringModel {
         self.lp_attack = 0.0;
         self.lp_sustain = 0.0;
         self.feedback = 0.0;
+        self.damper_cut = 0.0;
+        self.damper_leak = 0.0;
         self.last = 0.0;
         self.gain = 0.0;
         self.tone = 0.0;
         self.tone_decay = 0.99995;
         self.avg_coeff = 0.3;
         self.pickup_mix = 0.6;
-        self.ap1_x1 = 0.0;
-        self.ap1_y1 = 0.0;
-        self.ap1_coeff = 0.0;
-        self.ap2_x1 = 0.0;
-        self.ap2_y1 = 0.0;
-        self.ap2_coeff = 0.0;
+        self.dispersion_coeff = 0.0;
+        self.dispersion = [(0.0, 0.0); DISPERSION_STAGES];
     }
 
-    fn init(&mut self, delay_len: f32, velocity: f32, note: u8) {
+    fn init(&mut self, delay_len: f32, velocity: f32, note: u8, dispersion_coeff: f32) {
         let len_int = (delay_len.floor() as usize).clamp(8, MAX_DELAY_SAMPLES - 1);
         self.frac = (delay_len - len_int as f32).clamp(0.0, 0.999);
         self.delay.resize(len_int, 0.0);
@@ -874,10 +1215,7 @@ impl StringModel {
 
         self.lp_state = 0.0;
         self.last = 0.0;
-        self.ap1_x1 = 0.0;
-        self.ap1_y1 = 0.0;
-        self.ap2_x1 = 0.0;
-        self.ap2_y1 = 0.0;
+        self.dispersion = [(0.0, 0.0); DISPERSION_STAGES];
 
         let vel = velocity.clamp(0.02, 1.0);
         let t = ((note as f32 - 21.0) / 87.0).clamp(0.0, 1.0);
@@ -896,14 +1234,29 @@ impl StringModel {
         self.tone_decay = (0.99997 - 0.00005 * vel - 0.00002 * t).clamp(0.99985, 0.99999);
 
         self.avg_coeff = (0.38 - 0.28 * t).clamp(0.04, 0.42);
+        self.dispersion_coeff = dispersion_coeff;
         self.pickup_mix = (0.75 - 0.4 * t).clamp(0.25, 0.85);
 
-        self.ap1_coeff = (0.03 + 0.24 * t).clamp(0.0, 0.6);
-        self.ap2_coeff = (0.01 + 0.12 * t).clamp(0.0, 0.6);
-
         self.gain = 0.85;
     }
 
+    /// Scales the fundamental's T60 by `scale`, as far as the feedback ceiling allows. The
+    /// loop's filters lose energy on every trip as well as the feedback, so the whole trip gain
+    /// at `omega` is raised to `1 / scale`.
+    fn scale_decay(&mut self, scale: f32, omega: f32) {
+        if scale == 1.0 {
+            return;
+        }
+        let one_pole = |coeff: f32, pole: f32| {
+            let (sin, cos) = omega.sin_cos();
+            coeff / ((1.0 - pole * cos).powi(2) + (pole * sin).powi(2)).sqrt()
+        };
+        let filters = one_pole(self.lp_sustain, 1.0 - self.lp_sustain)
+            * one_pole(1.0 - self.avg_coeff, self.avg_coeff);
+        let trip_gain = self.feedback * filters;
+        self.feedback = (trip_gain.powf(1.0 / scale) / filters).clamp(0.0, 0.99995);
+    }
+
     fn strike_disp(&self) -> f32 {
         let len = self.delay.len();
         if len == 0 {
@@ -942,16 +1295,20 @@ impl StringModel {
         lp_coeff = lp_coeff.clamp(0.002, 0.6);
 
         self.lp_state += lp_coeff * (x - self.lp_state);
+        self.lp_state *= 1.0 - self.damper_leak * damper;
         let mut y = self.lp_state;
 
         let avg = self.avg_coeff;
         y = y * (1.0 - avg) + self.last * avg;
         self.last = y;
 
-        y = allpass(y, self.ap1_coeff, &mut self.ap1_x1, &mut self.ap1_y1);
-        y = allpass(y, self.ap2_coeff, &mut self.ap2_x1, &mut self.ap2_y1);
+        if self.dispersion_coeff > 0.0 {
+            for (x1, y1) in self.dispersion.iter_mut() {
+                y = allpass(y, self.dispersion_coeff, x1, y1);
+            }
+        }
 
-        let feedback = (self.feedback - 0.02 * damper).clamp(0.0, 0.99995);
+        let feedback = (self.feedback - self.damper_cut * damper).clamp(0.0, 0.99995);
         let write = y * feedback;
         self.delay[self.idx] = write;
         self.idx += 1;
@@ -967,9 +1324,6 @@ impl StringModel {
 }
 
 fn allpass(x: f32, coeff: f32, x1: &mut f32, y1: &mut f32) -> f32 {
-    if coeff.abs() <= 0.0001 {
-        return x;
-    }
     let y = -coeff * x + *x1 + coeff * *y1;
     *x1 = x;
     *y1 = y;
@@ -1013,6 +1367,89 @@ fn note_decay_coeff(note: u8) -> f32 {
     0.9996 - t * 0.0014
 }
 
+/// Position of `note` between the bottom of the keyboard and the damper limit, or `None`
+/// for undamped notes above it.
+fn damped_register(note: u8, damper_limit: u8) -> Option<f32> {
+    (note <= damper_limit)
+        .then(|| ((note as f32 - 21.0) / (damper_limit as f32 - 21.0).max(1.0)).clamp(0.0, 1.0))
+}
+
+/// The heavy bass dampers land quicker than the light treble ones.
+fn damper_speed(note: u8, damper_limit: u8) -> f32 {
+    damped_register(note, damper_limit).map_or(0.0, |t| lerp(1.5, 0.8, t))
+}
+
+/// Time for a damped string to fall 60 dB. Set per second rather than per trip round the
+/// loop, so long bass strings stop as soon as short treble ones instead of ringing on.
+fn damped_decay_s(note: u8, damper_limit: u8) -> Option<f32> {
+    damped_register(note, damper_limit).map(|t| lerp(0.2, 0.35, t))
+}
+
+/// Stiffness coefficient B of the strings for `note`: partial n sounds at
+/// n * f0 * sqrt(1 + B * n^2). Roughly a grand's curve, 1e-4 in the bass to 1e-2 at the top.
+fn string_inharmonicity(note: u8) -> f32 {
+    let t = ((note as f32 - 21.0) / 87.0).clamp(0.0, 1.0);
+    1.0e-4 * 100.0_f32.powf(t)
+}
+
+/// Phase delay in samples at `omega` radians per sample of one section of [`allpass`].
+fn allpass_phase_delay(coeff: f32, omega: f32) -> f32 {
+    1.0 + 2.0 / omega * (coeff * omega.sin()).atan2(1.0 - coeff * omega.cos())
+}
+
+/// Coefficient for the dispersion chain of a string tuned to `freq_hz` with stiffness `b`,
+/// and the chain's delay at `freq_hz`, which the delay line gives up so the fundamental
+/// stays in tune. The coefficient is fitted so the highest partial below the top of the
+/// band lands where the stiffness puts it; `(0.0, 0.0)` when there is nothing to fit.
+fn dispersion_design(sample_rate_hz: u32, freq_hz: f32, b: f32) -> (f32, f32) {
+    if b <= 0.0 {
+        return (0.0, 0.0);
+    }
+    let sr = sample_rate_hz.max(1) as f32;
+    let period = sr / freq_hz.max(1.0);
+    let stretched = |n: f32| n * freq_hz * ((1.0 + b * n * n) / (1.0 + b)).sqrt();
+    let Some(partial) = (2..=DISPERSION_FIT_PARTIAL)
+        .rev()
+        .map(|n| n as f32)
+        .find(|&n| stretched(n) < sr * DISPERSION_FIT_MAX_HZ_RATIO)
+    else {
+        return (0.0, 0.0);
+    };
+
+    let omega = |hz: f32| 2.0 * std::f32::consts::PI * hz / sr;
+    let chain_delay =
+        |coeff: f32, hz: f32| DISPERSION_STAGES as f32 * allpass_phase_delay(coeff, omega(hz));
+    // Cycles the partial's frequency completes in one trip round the loop, less the
+    // partial number: positive while the partial is still flatter than wanted.
+    let target = stretched(partial);
+    let mismatch = |coeff: f32| {
+        let loop_delay = period - chain_delay(coeff, freq_hz) + chain_delay(coeff, target);
+        target * loop_delay / sr - partial
+    };
+
+    // Leave the delay line its minimum length; the chain's delay grows with the coefficient.
+    let per_stage = ((period - 8.0) / DISPERSION_STAGES as f32).max(1.0);
+    let max_coeff = ((per_stage - 1.0) / (per_stage + 1.0)).min(MAX_DISPERSION_COEFF);
+    if max_coeff <= 0.0 {
+        return (0.0, 0.0);
+    }
+    let coeff = if mismatch(max_coeff) > 0.0 {
+        max_coeff
+    } else {
+        let (mut lo, mut hi) = (0.0_f32, max_coeff);
+        for _ in 0..32 {
+            let mid = 0.5 * (lo + hi);
+            if mismatch(mid) > 0.0 {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        0.5 * (lo + hi)
+    };
+    (coeff, chain_delay(coeff, freq_hz))
+}
+
 fn string_plan(note: u8) -> (usize, [f32; MAX_STRINGS_PER_NOTE]) {
     if note >= 55 {
         (3, [-0.0026, 0.0, 0.0019])
@@ -1031,8 +1468,9 @@ impl SynthPort for WaveguidePianoSynth {
     fn set_sample_rate(&self, sample_rate_hz: u32) {
         let mut inner = self.inner.lock();
         inner.sample_rate_hz = sample_rate_hz;
+        let high_pass_hz = inner.high_pass_hz;
         for bus in inner.buses.iter_mut() {
-            bus.reset(sample_rate_hz);
+            bus.reset(sample_rate_hz, high_pass_hz);
         }
     }
 
@@ -1040,23 +1478,32 @@ impl SynthPort for WaveguidePianoSynth {
         Ok(())
     }
 
+    fn set_decay_scale(&self, scale: f32) {
+        WaveguidePianoSynth::set_decay_scale(self, scale);
+    }
+
+    fn set_quality(&self, hint: QualityHint) {
+        WaveguidePianoSynth::set_quality(self, hint);
+    }
+
     fn handle_event(&self, bus: Bus, event: MidiLikeEvent, _at: SampleTime) {
         let Some(mut inner) = self.inner.try_lock() else {
             return;
         };
-        let sample_rate_hz = inner.sample_rate_hz;
+        let strike = inner.strike_settings();
         let idx = Inner::bus_index(bus);
         let bus_state = &mut inner.buses[idx];
         match event {
             MidiLikeEvent::NoteOn { note, velocity } => {
-                bus_state.note_on(sample_rate_hz, note, velocity);
+                bus_state.note_on(strike, note, velocity);
             }
             MidiLikeEvent::NoteOff { note } => {
                 bus_state.note_off(note);
             }
             MidiLikeEvent::Cc64 { value } => {
-                bus_state.sustain(value >= 64);
+                bus_state.sustain(strike.sample_rate_hz, value >= 64);
             }
+            MidiLikeEvent::ControlChange { .. } => {}
         }
     }
 
@@ -1068,10 +1515,33 @@ impl SynthPort for WaveguidePianoSynth {
             *value = 0.0;
         }
 
-        let Some(mut inner) = self.inner.try_lock() else {
-            return;
-        };
+        let frames = frames.min(out_l.len()).min(out_r.len());
         let idx = Inner::bus_index(bus);
-        inner.buses[idx].render(frames, out_l, out_r);
+        for (chunk, (left, right)) in out_l[..frames]
+            .chunks_mut(RENDER_CHUNK_FRAMES)
+            .zip(out_r[..frames].chunks_mut(RENDER_CHUNK_FRAMES))
+            .enumerate()
+        {
+            // Only a block not yet started gives way to a held lock; one cut short would
+            // drop out mid-block.
+            let mut inner = if chunk == 0 {
+                let Some(inner) = self.inner.try_lock() else {
+                    return;
+                };
+                inner
+            } else {
+                self.inner.lock()
+            };
+            inner.buses[idx].render(left.len(), left, right);
+            // Lets a waiting setter in before the next chunk.
+            MutexGuard::unlock_fair(inner);
+        }
+    }
+
+    fn is_silent(&self, bus: Bus) -> bool {
+        let Some(inner) = self.inner.try_lock() else {
+            return false;
+        };
+        inner.buses[Inner::bus_index(bus)].is_silent()
     }
 }
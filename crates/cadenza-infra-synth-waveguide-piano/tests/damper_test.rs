@@ -0,0 +1,121 @@
+use cadenza_infra_synth_waveguide_piano::WaveguidePianoSynth;
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::synth::SynthPort;
+use cadenza_ports::types::Bus;
+
+const SAMPLE_RATE_HZ: u32 = 48_000;
+/// 5 ms.
+const BLOCK: usize = 240;
+
+fn send(synth: &WaveguidePianoSynth, event: MidiLikeEvent) {
+    synth.handle_event(Bus::UserMonitor, event, 0);
+}
+
+/// RMS of each block over `blocks` blocks.
+fn render_levels(synth: &WaveguidePianoSynth, blocks: usize) -> Vec<f32> {
+    let mut left = [0.0; BLOCK];
+    let mut right = [0.0; BLOCK];
+    (0..blocks)
+        .map(|_| {
+            synth.render(Bus::UserMonitor, BLOCK, &mut left, &mut right);
+            let energy: f32 = left.iter().chain(&right).map(|s| s * s).sum();
+            (energy / (2 * BLOCK) as f32).sqrt()
+        })
+        .collect()
+}
+
+/// Strikes `note`, lets it sound for 200 ms, then lets go and returns the level per block
+/// from the last block before letting go. With `pedal` the pedal is held from the start
+/// and letting go lifts it, after the key came up early on.
+fn strike_and_let_go(synth: &WaveguidePianoSynth, note: u8, pedal: bool) -> Vec<f32> {
+    if pedal {
+        send(synth, MidiLikeEvent::Cc64 { value: 127 });
+    }
+    send(
+        synth,
+        MidiLikeEvent::NoteOn {
+            note,
+            velocity: 100,
+        },
+    );
+    if pedal {
+        send(synth, MidiLikeEvent::NoteOff { note });
+    }
+    let mut levels = vec![*render_levels(synth, 40).last().unwrap()];
+    send(
+        synth,
+        if pedal {
+            MidiLikeEvent::Cc64 { value: 0 }
+        } else {
+            MidiLikeEvent::NoteOff { note }
+        },
+    );
+    levels.extend(render_levels(synth, 100));
+    levels
+}
+
+/// Milliseconds until the level first falls 20 dB below where it started.
+fn decay_ms(levels: &[f32]) -> f32 {
+    let blocks = levels
+        .iter()
+        .position(|&level| level < levels[0] * 0.1)
+        .unwrap_or(levels.len());
+    (blocks * BLOCK) as f32 * 1000.0 / SAMPLE_RATE_HZ as f32
+}
+
+#[test]
+fn treble_notes_above_the_damper_limit_ring_on_after_release() {
+    let high = strike_and_let_go(&WaveguidePianoSynth::new(SAMPLE_RATE_HZ), 96, false);
+    let low = strike_and_let_go(&WaveguidePianoSynth::new(SAMPLE_RATE_HZ), 40, false);
+    assert!(
+        decay_ms(&high) > decay_ms(&low) * 5.0,
+        "note 96 {} ms, note 40 {} ms",
+        decay_ms(&high),
+        decay_ms(&low)
+    );
+
+    // Released or held, an undamped string sounds the same.
+    let held = WaveguidePianoSynth::new(SAMPLE_RATE_HZ);
+    send(
+        &held,
+        MidiLikeEvent::NoteOn {
+            note: 96,
+            velocity: 100,
+        },
+    );
+    let held = render_levels(&held, 141);
+    let ratio = high[100] / held[139];
+    assert!((0.95..=1.05).contains(&ratio), "{ratio}");
+}
+
+#[test]
+fn bass_notes_are_damped_within_a_few_dozen_milliseconds() {
+    for note in [28, 40, 48] {
+        let levels = strike_and_let_go(&WaveguidePianoSynth::new(SAMPLE_RATE_HZ), note, false);
+        assert!(decay_ms(&levels) <= 80.0, "note {note}: {levels:?}");
+    }
+}
+
+#[test]
+fn the_damper_limit_is_configurable() {
+    let synth = WaveguidePianoSynth::new(SAMPLE_RATE_HZ);
+    synth.set_damper_limit(108);
+    let damped = strike_and_let_go(&synth, 96, false);
+    let undamped = strike_and_let_go(&WaveguidePianoSynth::new(SAMPLE_RATE_HZ), 96, false);
+
+    // 300 ms on, the damped string is gone and only the soundboard is left.
+    let ratio = damped[60] / undamped[60];
+    assert!(ratio < 0.6, "{ratio}");
+}
+
+#[test]
+fn dampers_settle_gently_right_after_the_pedal_comes_up() {
+    let key = strike_and_let_go(&WaveguidePianoSynth::new(SAMPLE_RATE_HZ), 48, false);
+    let pedal = strike_and_let_go(&WaveguidePianoSynth::new(SAMPLE_RATE_HZ), 48, true);
+
+    // Less lost in the first 5 ms, but still damped soon after.
+    let key_drop = key[1] / key[0];
+    let pedal_drop = pedal[1] / pedal[0];
+    assert!(pedal_drop > key_drop, "pedal {pedal_drop}, key {key_drop}");
+    assert!(decay_ms(&pedal) <= 120.0, "{pedal:?}");
+}
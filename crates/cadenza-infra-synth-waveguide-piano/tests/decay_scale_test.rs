@@ -0,0 +1,83 @@
+use cadenza_infra_synth_waveguide_piano::WaveguidePianoSynth;
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::synth::SynthPort;
+use cadenza_ports::types::Bus;
+
+const SAMPLE_RATE_HZ: u32 = 48_000;
+/// 10 ms.
+const BLOCK_FRAMES: usize = 480;
+const MAX_SECS: usize = 4;
+
+/// Seconds from the loudest 10 ms block of a held C5 until its blocks stay 40 dB below it.
+fn decay_40db_secs(synth: &WaveguidePianoSynth) -> f64 {
+    synth.handle_event(
+        Bus::UserMonitor,
+        MidiLikeEvent::NoteOn {
+            note: 72,
+            velocity: 100,
+        },
+        0,
+    );
+    let mut left = vec![0.0; BLOCK_FRAMES];
+    let mut right = vec![0.0; BLOCK_FRAMES];
+    let peaks: Vec<f32> = (0..MAX_SECS * SAMPLE_RATE_HZ as usize / BLOCK_FRAMES)
+        .map(|_| {
+            synth.render(Bus::UserMonitor, BLOCK_FRAMES, &mut left, &mut right);
+            left.iter()
+                .chain(&right)
+                .fold(0.0_f32, |peak, sample| peak.max(sample.abs()))
+        })
+        .collect();
+
+    let (loudest, peak) = peaks
+        .iter()
+        .copied()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .expect("blocks");
+    let floor = peak * 0.01;
+    let last_above = peaks
+        .iter()
+        .rposition(|&block| block > floor)
+        .expect("peak block");
+    assert!(
+        last_above + 1 < peaks.len(),
+        "note still rings after {MAX_SECS} s"
+    );
+    (last_above + 1 - loudest) as f64 * BLOCK_FRAMES as f64 / f64::from(SAMPLE_RATE_HZ)
+}
+
+#[test]
+fn half_the_decay_scale_roughly_halves_the_ring_time() {
+    let full = decay_40db_secs(&WaveguidePianoSynth::new(SAMPLE_RATE_HZ));
+
+    let dry = WaveguidePianoSynth::new(SAMPLE_RATE_HZ);
+    dry.set_decay_scale(0.5);
+    let half = decay_40db_secs(&dry);
+
+    let ratio = half / full;
+    assert!(
+        (0.4..=0.65).contains(&ratio),
+        "decay {full:.2} s at 1.0, {half:.2} s at 0.5"
+    );
+}
+
+#[test]
+fn the_scale_applies_to_notes_struck_after_it_is_set() {
+    let synth = WaveguidePianoSynth::new(SAMPLE_RATE_HZ);
+    let full = decay_40db_secs(&synth);
+    // Out of range, so held to the shortest setting.
+    synth.set_decay_scale(0.0);
+    let shortest = decay_40db_secs(&synth);
+    assert!(
+        shortest < full * 0.4,
+        "decay {full:.2} s, then {shortest:.2} s"
+    );
+
+    synth.set_decay_scale(1.0);
+    let again = decay_40db_secs(&synth);
+    assert!(
+        (again - full).abs() < 0.05,
+        "decay {full:.2} s, then {again:.2} s"
+    );
+}
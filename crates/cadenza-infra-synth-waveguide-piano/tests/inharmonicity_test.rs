@@ -0,0 +1,89 @@
+use cadenza_infra_synth_waveguide_piano::WaveguidePianoSynth;
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::synth::SynthPort;
+use cadenza_ports::types::Bus;
+use std::f64::consts::PI;
+
+const SAMPLE_RATE_HZ: u32 = 48_000;
+/// A1, a single string in the model, so no unison detune blurs the partials.
+const LOW_A: u8 = 33;
+const LOW_A_HZ: f64 = 55.0;
+
+/// Half a second of a low A struck with the given inharmonicity, starting 100 ms after the
+/// strike.
+fn render_low_a(inharmonicity: f32) -> Vec<f64> {
+    let synth = WaveguidePianoSynth::new(SAMPLE_RATE_HZ);
+    synth.set_inharmonicity(inharmonicity);
+    synth.handle_event(
+        Bus::UserMonitor,
+        MidiLikeEvent::NoteOn {
+            note: LOW_A,
+            velocity: 110,
+        },
+        0,
+    );
+    let mut left = vec![0.0; 4_800];
+    let mut right = vec![0.0; 4_800];
+    synth.render(Bus::UserMonitor, left.len(), &mut left, &mut right);
+
+    let mut left = vec![0.0; 24_000];
+    let mut right = vec![0.0; 24_000];
+    synth.render(Bus::UserMonitor, left.len(), &mut left, &mut right);
+    left.iter()
+        .zip(&right)
+        .map(|(l, r)| f64::from(l + r))
+        .collect()
+}
+
+/// Magnitude of the Hann-windowed spectrum of `signal` at `hz`.
+fn magnitude(signal: &[f64], hz: f64) -> f64 {
+    let n = signal.len() as f64;
+    let step = 2.0 * PI * hz / f64::from(SAMPLE_RATE_HZ);
+    let (re, im) = signal
+        .iter()
+        .enumerate()
+        .fold((0.0, 0.0), |(re, im), (i, &x)| {
+            let window = 0.5 - 0.5 * (2.0 * PI * i as f64 / n).cos();
+            let phase = step * i as f64;
+            (re + x * window * phase.cos(), im - x * window * phase.sin())
+        });
+    (re * re + im * im).sqrt()
+}
+
+/// Frequency of the strongest peak within 4% of partial `n` of the low A, found on a
+/// coarse grid and then refined around the best point.
+fn partial_hz(signal: &[f64], n: u32) -> f64 {
+    let strongest = |center: f64, step: f64, steps: i32| {
+        (-steps..=steps)
+            .map(|i| center * (1.0 + f64::from(i) * step))
+            .max_by(|&a, &b| magnitude(signal, a).total_cmp(&magnitude(signal, b)))
+            .unwrap()
+    };
+    let coarse = strongest(LOW_A_HZ * f64::from(n), 0.002, 20);
+    strongest(coarse, 0.0001, 20)
+}
+
+/// How much sharper partial 8 is than 8 times the fundamental.
+fn stretch(signal: &[f64]) -> (f64, f64) {
+    let fundamental = partial_hz(signal, 1);
+    (fundamental, partial_hz(signal, 8) / (8.0 * fundamental))
+}
+
+#[test]
+fn partials_spread_further_apart_as_inharmonicity_rises() {
+    let (f_pure, pure) = stretch(&render_low_a(0.0));
+    let (f_grand, grand) = stretch(&render_low_a(1.0));
+    let (f_stiff, stiff) = stretch(&render_low_a(4.0));
+
+    assert!((pure - 1.0).abs() < 0.002, "pure {pure}");
+    assert!(grand > pure + 0.002, "grand {grand}, pure {pure}");
+    assert!(stiff > grand + 0.004, "stiff {stiff}, grand {grand}");
+
+    // The delay line makes up for the dispersion, so the fundamental does not move.
+    for fundamental in [f_grand, f_stiff] {
+        assert!(
+            (fundamental / f_pure - 1.0).abs() < 0.002,
+            "{fundamental} Hz vs {f_pure} Hz"
+        );
+    }
+}
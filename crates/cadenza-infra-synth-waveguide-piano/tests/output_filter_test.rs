@@ -0,0 +1,74 @@
+use cadenza_infra_synth_waveguide_piano::{OutputFilter, WaveguidePianoSynth};
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::synth::SynthPort;
+use cadenza_ports::types::Bus;
+use std::f32::consts::PI;
+
+const SAMPLE_RATE_HZ: u32 = 48_000;
+/// 10 ms.
+const BLOCK: usize = 480;
+
+/// Runs `input` through `filter` a block at a time and returns the left channel.
+fn filter_left(filter: &mut OutputFilter, input: impl Fn(usize) -> f32, frames: usize) -> Vec<f32> {
+    let mut out = Vec::with_capacity(frames);
+    let mut left = [0.0; BLOCK];
+    let mut right = [0.0; BLOCK];
+    while out.len() < frames {
+        for (i, (l, r)) in left.iter_mut().zip(right.iter_mut()).enumerate() {
+            *l = input(out.len() + i);
+            *r = *l;
+        }
+        filter.process(BLOCK, &mut left, &mut right);
+        assert_eq!(left, right);
+        out.extend_from_slice(&left);
+    }
+    out
+}
+
+#[test]
+fn a_dc_step_settles_to_zero_within_100_ms() {
+    for high_pass_hz in [None, Some(20.0), Some(80.0)] {
+        let mut filter = OutputFilter::new(SAMPLE_RATE_HZ, high_pass_hz);
+        let out = filter_left(&mut filter, |_| 0.5, 20 * BLOCK);
+        let settled = out[10 * BLOCK..]
+            .iter()
+            .fold(0.0_f32, |peak, sample| peak.max(sample.abs()));
+        assert!(settled < 0.005, "{high_pass_hz:?}: {settled}");
+    }
+}
+
+#[test]
+fn the_dc_blocker_alone_leaves_the_bass_alone() {
+    // A0, the lowest note on the keyboard.
+    let hz = 27.5;
+    let mut filter = OutputFilter::new(SAMPLE_RATE_HZ, None);
+    let sine = |i: usize| (2.0 * PI * hz * i as f32 / SAMPLE_RATE_HZ as f32).sin();
+    let out = filter_left(&mut filter, sine, 100 * BLOCK);
+    let peak = out[50 * BLOCK..]
+        .iter()
+        .fold(0.0_f32, |peak, sample| peak.max(sample.abs()));
+    assert!(peak > 0.9, "{peak}");
+}
+
+#[test]
+fn the_high_pass_thins_out_low_notes() {
+    let energy = |high_pass_hz: Option<f32>| {
+        let synth = WaveguidePianoSynth::new(SAMPLE_RATE_HZ);
+        synth.set_high_pass(high_pass_hz);
+        synth.handle_event(
+            Bus::UserMonitor,
+            MidiLikeEvent::NoteOn {
+                note: 28,
+                velocity: 100,
+            },
+            0,
+        );
+        let mut left = vec![0.0; 24_000];
+        let mut right = vec![0.0; 24_000];
+        synth.render(Bus::UserMonitor, left.len(), &mut left, &mut right);
+        left.iter().chain(&right).map(|s| s * s).sum::<f32>()
+    };
+    let full = energy(None);
+    let thinned = energy(Some(80.0));
+    assert!(thinned < full * 0.8, "{thinned} vs {full}");
+}
@@ -0,0 +1,45 @@
+use cadenza_infra_synth_waveguide_piano::WaveguidePianoSynth;
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::synth::{QualityHint, SynthPort};
+use cadenza_ports::types::Bus;
+
+const SAMPLE_RATE_HZ: u32 = 48_000;
+const FRAMES: usize = 24_000;
+
+/// Half a second of a C5, which sounds three strings at full quality.
+fn strike(synth: &WaveguidePianoSynth) -> Vec<f32> {
+    synth.handle_event(
+        Bus::UserMonitor,
+        MidiLikeEvent::NoteOn {
+            note: 72,
+            velocity: 100,
+        },
+        0,
+    );
+    let mut left = vec![0.0; FRAMES];
+    let mut right = vec![0.0; FRAMES];
+    synth.render(Bus::UserMonitor, FRAMES, &mut left, &mut right);
+    left
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+#[test]
+fn reduced_quality_changes_the_sound_but_not_its_level() {
+    let full = strike(&WaveguidePianoSynth::new(SAMPLE_RATE_HZ));
+
+    let synth = WaveguidePianoSynth::new(SAMPLE_RATE_HZ);
+    synth.set_quality(QualityHint::Reduced);
+    let reduced = strike(&synth);
+    assert_ne!(full, reduced);
+    let ratio = rms(&reduced) / rms(&full);
+    assert!((0.5..=2.0).contains(&ratio), "level ratio {ratio}");
+
+    // Back at full quality, a fresh synth sounds exactly as before.
+    let synth = WaveguidePianoSynth::new(SAMPLE_RATE_HZ);
+    synth.set_quality(QualityHint::Reduced);
+    synth.set_quality(QualityHint::Full);
+    assert_eq!(strike(&synth), full);
+}
@@ -0,0 +1,124 @@
+use cadenza_infra_synth_waveguide_piano::WaveguidePianoSynth;
+use cadenza_ports::midi::MidiLikeEvent;
+use cadenza_ports::synth::SynthPort;
+use cadenza_ports::types::Bus;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const FRAMES: usize = 16_384;
+/// The synth renders a block this many frames at a time, locking for each.
+const CHUNK_FRAMES: usize = 512;
+
+#[test]
+fn a_huge_render_never_holds_the_synth_for_long() {
+    let synth = Arc::new(WaveguidePianoSynth::new(48_000));
+    for note in 30..94 {
+        synth.handle_event(
+            Bus::Autopilot,
+            MidiLikeEvent::NoteOn {
+                note,
+                velocity: 100,
+            },
+            0,
+        );
+    }
+
+    // A setter takes the lock outright, so how long it waits is how long the render held it.
+    let rendering = Arc::new(AtomicBool::new(true));
+    let waiter = thread::spawn({
+        let synth = synth.clone();
+        let rendering = rendering.clone();
+        move || {
+            let mut longest = Duration::ZERO;
+            while rendering.load(Ordering::Relaxed) {
+                let started = Instant::now();
+                synth.set_damper_limit(91);
+                longest = longest.max(started.elapsed());
+                thread::yield_now();
+            }
+            longest
+        }
+    });
+
+    let mut left = vec![0.0; FRAMES];
+    let mut right = vec![0.0; FRAMES];
+    // A block whose first chunk meets the setter is skipped whole; try again until one
+    // starts.
+    let mut render = Duration::ZERO;
+    for _ in 0..10 {
+        let started = Instant::now();
+        synth.render(Bus::Autopilot, FRAMES, &mut left, &mut right);
+        render = started.elapsed();
+        if left[..CHUNK_FRAMES].iter().any(|&sample| sample != 0.0) {
+            break;
+        }
+    }
+    rendering.store(false, Ordering::Relaxed);
+    let longest_wait = waiter.join().expect("waiter");
+
+    // Once started, the block is finished in full rather than cut short by the setter.
+    let rendered: Vec<bool> = left
+        .chunks(CHUNK_FRAMES)
+        .map(|chunk| chunk.iter().any(|&sample| sample != 0.0))
+        .collect();
+    assert!(rendered.iter().all(|&chunk| chunk), "{rendered:?}");
+    // 32 chunks; a single hold would keep the setter out for the whole render.
+    assert!(
+        longest_wait < render / 4,
+        "waited {longest_wait:?} of a {render:?} render"
+    );
+}
+
+#[test]
+fn a_started_block_is_never_cut_short_by_a_setter() {
+    const BLOCK_FRAMES: usize = 4 * CHUNK_FRAMES;
+    let synth = Arc::new(WaveguidePianoSynth::new(48_000));
+    // Low notes ring for seconds, longer than every block below.
+    for note in 30..46 {
+        synth.handle_event(
+            Bus::Autopilot,
+            MidiLikeEvent::NoteOn {
+                note,
+                velocity: 110,
+            },
+            0,
+        );
+    }
+
+    let rendering = Arc::new(AtomicBool::new(true));
+    let setter = thread::spawn({
+        let synth = synth.clone();
+        let rendering = rendering.clone();
+        move || {
+            while rendering.load(Ordering::Relaxed) {
+                synth.set_damper_limit(91);
+                thread::yield_now();
+            }
+        }
+    });
+
+    let mut partial = Vec::new();
+    let mut full = 0;
+    for block in 0..50 {
+        let mut left = vec![0.0; BLOCK_FRAMES];
+        let mut right = vec![0.0; BLOCK_FRAMES];
+        synth.render(Bus::Autopilot, BLOCK_FRAMES, &mut left, &mut right);
+        let rendered: Vec<bool> = left
+            .chunks(CHUNK_FRAMES)
+            .map(|chunk| chunk.iter().any(|&sample| sample != 0.0))
+            .collect();
+        // A block may be skipped whole when it meets the setter, but never in part.
+        if rendered.iter().all(|&chunk| chunk) {
+            full += 1;
+        } else if rendered[0] {
+            partial.push((block, rendered));
+        }
+    }
+    rendering.store(false, Ordering::Relaxed);
+    setter.join().expect("setter");
+
+    assert!(partial.is_empty(), "{partial:?}");
+    assert!(full > 0);
+}
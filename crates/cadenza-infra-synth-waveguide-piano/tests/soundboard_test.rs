@@ -0,0 +1,65 @@
+use cadenza_infra_synth_waveguide_piano::BareSoundboard;
+
+const SAMPLE_RATE_HZ: u32 = 48_000;
+
+/// Frames of noise at the start of the input. Silence follows, so the tail is the board alone.
+const NOISE_FRAMES: usize = 5_785;
+const FRAMES: usize = 30_000;
+
+/// Runs a fresh soundboard over the input in blocks cycling through `block_sizes`. Returns
+/// both channels.
+fn render(block_sizes: &[usize]) -> (Vec<f32>, Vec<f32>) {
+    let mut board = BareSoundboard::new(SAMPLE_RATE_HZ);
+    let mut seed = 0x1234_5678_u32;
+    let (mut left, mut right) = (Vec::new(), Vec::new());
+    for &frames in block_sizes.iter().cycle() {
+        if left.len() >= FRAMES {
+            break;
+        }
+        let mut l = vec![0.0; frames];
+        let mut r = vec![0.0; frames];
+        for (i, (l, r)) in l.iter_mut().zip(r.iter_mut()).enumerate() {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let noise = (seed >> 8) as f32 / (1 << 24) as f32 - 0.5;
+            if left.len() + i < NOISE_FRAMES {
+                *l = noise;
+                *r = noise * 0.5;
+            }
+        }
+        board.process(frames, &mut l, &mut r);
+        left.extend(l);
+        right.extend(r);
+    }
+    (left, right)
+}
+
+#[test]
+fn the_soundboard_sounds_as_it_did_sample_by_sample() {
+    // Taken from the per-sample soundboard before it worked in blocks.
+    const EXPECTED: [(usize, f32, f32); 7] = [
+        (6000, 0.013201175, -0.012050124),
+        (7000, 0.005190956, -0.030967742),
+        (9000, 5.2576943e-6, 0.009060396),
+        (12000, 0.002102518, 0.0025313597),
+        (16000, 0.0020401285, 0.00017976726),
+        (22000, 7.093036e-5, -0.00065448415),
+        (28000, -1.4415855e-6, -0.00012910816),
+    ];
+    let (left, right) = render(&[128, 480, 37, 512]);
+    for (i, l, r) in EXPECTED {
+        assert!((left[i] - l).abs() <= 1e-6, "left[{i}] {} vs {l}", left[i]);
+        assert!(
+            (right[i] - r).abs() <= 1e-6,
+            "right[{i}] {} vs {r}",
+            right[i]
+        );
+    }
+}
+
+#[test]
+fn the_block_size_does_not_change_the_output() {
+    let single = render(&[1]);
+    let large = render(&[1_000]);
+    assert_eq!(single.0[..FRAMES], large.0[..FRAMES]);
+    assert_eq!(single.1[..FRAMES], large.1[..FRAMES]);
+}
@@ -0,0 +1,124 @@
+//! An output port written against the `cadenza-ports` traits alone: it renders the
+//! callback offline into memory on its own thread, as a real backend would from its
+//! device callback.
+
+use cadenza_ports::audio::{AudioError, AudioOutputPort, AudioRenderCallback, AudioStreamHandle};
+use cadenza_ports::types::{AudioConfig, AudioOutputDevice, DeviceId, SampleTime};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+const DEVICE_ID: &str = "memory";
+const BLOCK_FRAMES: usize = 256;
+
+/// Renders up to `max_blocks` blocks and hands back the peak of each.
+struct MemoryOutputPort {
+    max_blocks: usize,
+    peaks: mpsc::Sender<f32>,
+}
+
+struct MemoryStream {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl AudioStreamHandle for MemoryStream {
+    fn close(mut self: Box<Self>) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl AudioOutputPort for MemoryOutputPort {
+    fn list_outputs(&self) -> Result<Vec<AudioOutputDevice>, AudioError> {
+        Ok(vec![AudioOutputDevice {
+            id: DeviceId(DEVICE_ID.to_string()),
+            name: "Memory".to_string(),
+            default_config: AudioConfig {
+                sample_rate_hz: 48_000,
+                channels: 2,
+                buffer_size_frames: Some(BLOCK_FRAMES as u32),
+            },
+            buffer_size_range: None,
+        }])
+    }
+
+    fn open_output(
+        &self,
+        device_id: &DeviceId,
+        _config: AudioConfig,
+        mut cb: Box<dyn AudioRenderCallback>,
+    ) -> Result<Box<dyn AudioStreamHandle>, AudioError> {
+        if device_id.0 != DEVICE_ID {
+            return Err(AudioError::DeviceNotFound(device_id.to_string()));
+        }
+        let running = Arc::new(AtomicBool::new(true));
+        let max_blocks = self.max_blocks;
+        let peaks = self.peaks.clone();
+        let thread = thread::spawn({
+            let running = running.clone();
+            move || {
+                // The callback is owned by this thread alone, so it may render mutably.
+                let mut left = [0.0; BLOCK_FRAMES];
+                let mut right = [0.0; BLOCK_FRAMES];
+                let mut sample_time: SampleTime = 0;
+                for _ in 0..max_blocks {
+                    if !running.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    cb.render(sample_time, &mut left, &mut right);
+                    sample_time += BLOCK_FRAMES as SampleTime;
+                    let peak = left
+                        .iter()
+                        .chain(&right)
+                        .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+                    if peaks.send(peak).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(Box::new(MemoryStream {
+            running,
+            thread: Some(thread),
+        }))
+    }
+}
+
+/// A 440 Hz sine that keeps its phase between blocks.
+struct Sine {
+    phase: f32,
+}
+
+impl AudioRenderCallback for Sine {
+    fn render(&mut self, _sample_time_start: SampleTime, out_l: &mut [f32], out_r: &mut [f32]) {
+        let step = 440.0 * std::f32::consts::TAU / 48_000.0;
+        for (l, r) in out_l.iter_mut().zip(out_r.iter_mut()) {
+            *l = 0.5 * self.phase.sin();
+            *r = *l;
+            self.phase = (self.phase + step) % std::f32::consts::TAU;
+        }
+    }
+}
+
+fn main() -> Result<(), AudioError> {
+    let (peaks_tx, peaks_rx) = mpsc::channel();
+    let port = MemoryOutputPort {
+        max_blocks: 8,
+        peaks: peaks_tx,
+    };
+    let device = port.list_outputs()?.remove(0);
+    let stream = port.open_output(
+        &device.id,
+        device.default_config,
+        Box::new(Sine { phase: 0.0 }),
+    )?;
+    drop(port);
+    let peaks: Vec<f32> = peaks_rx.iter().collect();
+    stream.close();
+    println!("rendered {} blocks, peaks {peaks:?}", peaks.len());
+    Ok(())
+}
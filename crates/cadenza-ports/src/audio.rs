@@ -1,4 +1,5 @@
 use crate::types::*;
+use std::time::Duration;
 
 #[derive(thiserror::Error, Debug)]
 pub enum AudioError {
@@ -6,24 +7,57 @@ pub enum AudioError {
     DeviceNotFound(String),
     #[error("device unavailable: {0}")]
     DeviceUnavailable(String),
+    /// Held by another application, as with exclusive mode; it may free up shortly.
+    #[error("{device} is in use by another application; close it or choose another output")]
+    DeviceBusy { device: String, retry_after_ms: u32 },
     #[error("unsupported config: {0}")]
     UnsupportedConfig(String),
+    #[error(
+        "{device} cannot play {requested}; it supports {}",
+        if supported.is_empty() { "no stereo output".to_string() } else { supported.join(", ") }
+    )]
+    FormatNegotiationFailed {
+        device: String,
+        requested: AudioConfig,
+        /// Human-readable ranges the device offers instead.
+        supported: Vec<String>,
+    },
     #[error("backend error: {0}")]
     Backend(String),
 }
 
-/// Audio callback: must be realtime-safe.
+impl AudioError {
+    /// How long to wait before trying again, for failures that are likely to pass.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            AudioError::DeviceBusy { retry_after_ms, .. } => {
+                Some(Duration::from_millis(u64::from(*retry_after_ms)))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Audio callback: must be realtime-safe. A port owns it exclusively and calls it from one
+/// thread at a time, hence `&mut self`.
 pub trait AudioRenderCallback: Send + 'static {
     fn render(&mut self, sample_time_start: SampleTime, out_l: &mut [f32], out_r: &mut [f32]);
 }
 
 pub trait AudioStreamHandle: Send {
     fn close(self: Box<Self>);
+
+    /// Config the backend actually granted, which may differ from the one requested.
+    fn effective_config(&self) -> Option<AudioConfig> {
+        None
+    }
 }
 
+/// See `examples/custom_output_port.rs` for a port implemented against these traits alone.
 pub trait AudioOutputPort: Send + Sync {
     fn list_outputs(&self) -> Result<Vec<AudioOutputDevice>, AudioError>;
 
+    /// `cb` moves into the stream, which renders it until closed.
     fn open_output(
         &self,
         device_id: &DeviceId,
@@ -0,0 +1,170 @@
+use crate::midi::MidiOptions;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum PortsConfigError {
+    #[error("unknown port {0:?}; expected synth, audio, midi or omr")]
+    UnknownPort(String),
+    #[error("unknown {port} implementation {value:?}; expected one of {expected}")]
+    UnknownValue {
+        port: String,
+        value: String,
+        expected: String,
+    },
+    #[error("malformed port setting {0:?}; expected port=value, as in audio=null")]
+    Malformed(String),
+    #[error("{0} output needs a path, as in audio={0}:/tmp/out.wav")]
+    MissingPath(String),
+    #[error("this build of Cadenza has no {0} support")]
+    Unavailable(String),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SynthBackend {
+    Waveguide,
+    Simple,
+    #[default]
+    RustySynth,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioBackend {
+    #[default]
+    Cpal,
+    Null,
+    /// Renders in real time into a WAV file instead of a device.
+    Wav {
+        path: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MidiBackend {
+    #[default]
+    Midir,
+    None,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OmrBackend {
+    /// `path` points at the engine; `None` looks for it in the usual places.
+    Audiveris {
+        #[serde(default)]
+        path: Option<String>,
+    },
+    #[default]
+    None,
+}
+
+/// Which implementation backs each port. Written as a spec like
+/// `synth=simple,audio=wav:/tmp/out.wav,midi=none` for the `CADENZA_PORTS` variable;
+/// `midi=midir:tap` also keeps the raw input messages for diagnostics.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PortsConfig {
+    pub synth: SynthBackend,
+    pub audio: AudioBackend,
+    pub midi: MidiBackend,
+    pub midi_options: MidiOptions,
+    pub omr: OmrBackend,
+}
+
+impl PortsConfig {
+    /// Replaces the ports named in `spec`, keeping the others.
+    pub fn with_overrides(mut self, spec: &str) -> Result<Self, PortsConfigError> {
+        for setting in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (port, value) = setting
+                .split_once('=')
+                .ok_or_else(|| PortsConfigError::Malformed(setting.to_string()))?;
+            let (port, value) = (port.trim(), value.trim());
+            let (name, arg) = match value.split_once(':') {
+                Some((name, arg)) => (name, Some(arg.to_string())),
+                None => (value, None),
+            };
+            let unknown = |expected: &str| PortsConfigError::UnknownValue {
+                port: port.to_string(),
+                value: value.to_string(),
+                expected: expected.to_string(),
+            };
+            match port {
+                "synth" => {
+                    self.synth = match value {
+                        "waveguide" => SynthBackend::Waveguide,
+                        "simple" => SynthBackend::Simple,
+                        "rustysynth" => SynthBackend::RustySynth,
+                        _ => return Err(unknown("waveguide, simple, rustysynth")),
+                    }
+                }
+                "audio" => {
+                    self.audio = match (name, arg) {
+                        ("cpal", None) => AudioBackend::Cpal,
+                        ("null", None) => AudioBackend::Null,
+                        ("wav", Some(path)) if !path.is_empty() => AudioBackend::Wav { path },
+                        ("wav", _) => return Err(PortsConfigError::MissingPath("wav".to_string())),
+                        _ => return Err(unknown("cpal, null, wav:<path>")),
+                    }
+                }
+                "midi" => {
+                    (self.midi, self.midi_options.raw_message_tap) = match (name, arg.as_deref()) {
+                        ("midir", None) => (MidiBackend::Midir, false),
+                        ("midir", Some("tap")) => (MidiBackend::Midir, true),
+                        ("none", None) => (MidiBackend::None, false),
+                        _ => return Err(unknown("midir, midir:tap, none")),
+                    }
+                }
+                "omr" => {
+                    self.omr = match (name, arg) {
+                        ("audiveris", path) => OmrBackend::Audiveris {
+                            path: path.filter(|path| !path.is_empty()),
+                        },
+                        ("none", None) => OmrBackend::None,
+                        _ => return Err(unknown("audiveris, audiveris:<path>, none")),
+                    }
+                }
+                _ => return Err(PortsConfigError::UnknownPort(port.to_string())),
+            }
+        }
+        Ok(self)
+    }
+}
+
+impl FromStr for PortsConfig {
+    type Err = PortsConfigError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        Self::default().with_overrides(spec)
+    }
+}
+
+impl fmt::Display for PortsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let synth = match self.synth {
+            SynthBackend::Waveguide => "waveguide",
+            SynthBackend::Simple => "simple",
+            SynthBackend::RustySynth => "rustysynth",
+        };
+        write!(f, "synth={synth},audio=")?;
+        match &self.audio {
+            AudioBackend::Cpal => write!(f, "cpal")?,
+            AudioBackend::Null => write!(f, "null")?,
+            AudioBackend::Wav { path } => write!(f, "wav:{path}")?,
+        }
+        let midi = match (self.midi, self.midi_options.raw_message_tap) {
+            (MidiBackend::Midir, false) => "midir",
+            (MidiBackend::Midir, true) => "midir:tap",
+            (MidiBackend::None, _) => "none",
+        };
+        write!(f, ",midi={midi},omr=")?;
+        match &self.omr {
+            OmrBackend::Audiveris { path: Some(path) } => write!(f, "audiveris:{path}"),
+            OmrBackend::Audiveris { path: None } => write!(f, "audiveris"),
+            OmrBackend::None => write!(f, "none"),
+        }
+    }
+}
@@ -1,4 +1,5 @@
 pub mod audio;
+pub mod config;
 pub mod midi;
 pub mod omr;
 pub mod playback;
@@ -7,6 +8,7 @@ pub mod synth;
 pub mod types;
 
 pub use audio::*;
+pub use config::*;
 pub use midi::*;
 pub use omr::*;
 pub use playback::*;
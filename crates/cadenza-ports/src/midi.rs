@@ -1,6 +1,8 @@
 use crate::types::*;
 use serde::{Deserialize, Serialize};
-use std::{sync::Arc, time::Instant};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MidiLikeEvent {
@@ -15,6 +17,72 @@ pub enum MidiLikeEvent {
     Cc64 {
         value: u8,
     },
+    /// Any controller other than CC64, such as an assignable button.
+    ControlChange {
+        controller: u8,
+        value: u8,
+    },
+}
+
+/// Controller number of the "all notes off" message. The audio graph answers it by releasing
+/// every note and the pedal on the event's bus, whatever the synth does with controllers.
+pub const ALL_NOTES_OFF: u8 = 123;
+
+/// Key or controller that triggers a [`MidiAction`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "payload")]
+pub enum MidiControl {
+    Controller(u8),
+    Note(u8),
+}
+
+impl MidiControl {
+    /// The control `event` presses, if any. Controllers count as pressed from value 64.
+    pub fn pressed_by(event: MidiLikeEvent) -> Option<Self> {
+        match event {
+            MidiLikeEvent::NoteOn { note, .. } => Some(MidiControl::Note(note)),
+            MidiLikeEvent::Cc64 { value } if value >= 64 => Some(MidiControl::Controller(64)),
+            MidiLikeEvent::ControlChange { controller, value } if value >= 64 => {
+                Some(MidiControl::Controller(controller))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether `event`, press or release, comes from this control.
+    pub fn is_source_of(&self, event: MidiLikeEvent) -> bool {
+        match (*self, event) {
+            (
+                MidiControl::Note(mapped),
+                MidiLikeEvent::NoteOn { note, .. } | MidiLikeEvent::NoteOff { note },
+            ) => mapped == note,
+            (MidiControl::Controller(mapped), MidiLikeEvent::Cc64 { .. }) => mapped == 64,
+            (MidiControl::Controller(mapped), MidiLikeEvent::ControlChange { controller, .. }) => {
+                mapped == controller
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Transport action a mapped control runs instead of sounding or being judged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MidiAction {
+    StartPractice,
+    PausePractice,
+    StopPractice,
+    /// Starts practice, or pauses it while running.
+    TogglePractice,
+    /// Turns the last loop range off and back on.
+    ToggleLoop,
+    TempoUp,
+    TempoDown,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MidiMapping {
+    pub controller_or_note: MidiControl,
+    pub action: MidiAction,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -41,6 +109,70 @@ pub enum MidiError {
     Backend(String),
 }
 
+/// Raw messages kept by a [`RawMidiTap`]; older ones make room for newer.
+pub const RAW_MIDI_TAP_CAPACITY: usize = 1024;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MidiOptions {
+    /// Keep every message received, whether it parses or not, for diagnostics.
+    pub raw_message_tap: bool,
+}
+
+/// A message as the device sent it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawMidiMessage {
+    /// Position among all messages the tap has seen, from 0.
+    pub index: u64,
+    /// Backend timestamp in microseconds; where it counts from depends on the backend.
+    pub stamp_us: u64,
+    pub status: u8,
+    pub data: Vec<u8>,
+}
+
+/// Bounded record of the latest raw messages, shared with the backend's input callback.
+#[derive(Debug, Default)]
+pub struct RawMidiTap {
+    inner: Mutex<RawMidiTapInner>,
+}
+
+#[derive(Debug, Default)]
+struct RawMidiTapInner {
+    messages: VecDeque<RawMidiMessage>,
+    seen: u64,
+}
+
+impl RawMidiTap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps `message` verbatim; empty messages are ignored.
+    pub fn push(&self, stamp_us: u64, message: &[u8]) {
+        let Some((&status, data)) = message.split_first() else {
+            return;
+        };
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        if inner.messages.len() >= RAW_MIDI_TAP_CAPACITY {
+            inner.messages.pop_front();
+        }
+        let index = inner.seen;
+        inner.seen += 1;
+        inner.messages.push_back(RawMidiMessage {
+            index,
+            stamp_us,
+            status,
+            data: data.to_vec(),
+        });
+    }
+
+    /// Oldest first.
+    pub fn messages(&self) -> Vec<RawMidiMessage> {
+        let inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        inner.messages.iter().cloned().collect()
+    }
+}
+
 /// MIDI input stream handle: drop closes it.
 pub trait MidiInputStream: Send {
     fn close(self: Box<Self>);
@@ -57,4 +189,10 @@ pub trait MidiInputPort: Send + Sync {
         device_id: &DeviceId,
         cb: PlayerEventCallback,
     ) -> Result<Box<dyn MidiInputStream>, MidiError>;
+
+    /// Raw messages kept for diagnostics, oldest first. Empty unless the backend was set up
+    /// with [`MidiOptions::raw_message_tap`].
+    fn raw_messages(&self) -> Vec<RawMidiMessage> {
+        Vec::new()
+    }
 }
@@ -1,9 +1,39 @@
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct OmrOptions {
     pub enable_diagnostics: bool,
     pub engine_path: Option<String>,
+    /// Checked while the engine runs; once set, recognition stops with
+    /// [`OmrError::Cancelled`].
+    pub cancel: OmrCancel,
+    /// Receives the name of each stage as recognition reaches it.
+    pub progress: Option<Sender<String>>,
+}
+
+impl OmrOptions {
+    pub fn report_progress(&self, stage: &str) {
+        if let Some(progress) = &self.progress {
+            let _ = progress.send(stage.to_string());
+        }
+    }
+}
+
+/// Flag shared between a running recognition and whoever may stop it.
+#[derive(Clone, Debug, Default)]
+pub struct OmrCancel(Arc<AtomicBool>);
+
+impl OmrCancel {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -16,13 +46,19 @@ pub struct OmrResult {
 pub enum OmrError {
     #[error("unsupported format: {0}")]
     UnsupportedFormat(String),
+    /// The engine could not be started; the message says how to install or point at it.
+    #[error("{0}")]
+    EngineNotFound(String),
     #[error("recognition failed: {0}")]
     RecognitionFailed(String),
+    #[error("recognition cancelled")]
+    Cancelled,
     #[error("backend error: {0}")]
     Backend(String),
 }
 
 pub trait OmrPort: Send + Sync {
     fn recognize_pdf(&self, pdf_path: &str, options: OmrOptions) -> Result<OmrResult, OmrError>;
+    /// Log of the last recognition, kept even when it failed.
     fn diagnostics(&self) -> Result<Option<PathBuf>, OmrError>;
 }
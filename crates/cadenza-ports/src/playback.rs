@@ -52,6 +52,31 @@ pub struct ScheduledEvent {
     pub sample_time: SampleTime,
     pub bus: Bus,
     pub event: MidiLikeEvent,
+    /// Queue generation the event was sent in; the audio thread drops events from
+    /// generations older than the current one, such as those queued before a seek.
+    #[serde(default)]
+    pub generation: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaybackTransportSnapshot {
+    pub tick: Tick,
+    pub sample_time: SampleTime,
+    pub playing: bool,
+}
+
+/// Position changes an embedder may need to react to, in the order they happened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlaybackNotification {
+    /// The transport jumped from the loop end back to the loop start at `at_sample`.
+    LoopWrapped {
+        at_sample: SampleTime,
+    },
+    /// The last event of an unlooped score has played.
+    ReachedEnd,
+    Seeked {
+        tick: Tick,
+    },
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -74,8 +99,15 @@ pub trait PlaybackPort: Send + Sync {
     fn set_tempo_multiplier(&self, multiplier: f32) -> Result<(), PlaybackError>;
     fn set_mode(&self, mode: PlaybackMode) -> Result<(), PlaybackError>;
 
+    /// Moves the transport to the embedder's audio clock. Call before each poll.
+    fn advance_to_sample(&self, sample_time: SampleTime) -> Result<(), PlaybackError>;
+    fn transport_snapshot(&self) -> PlaybackTransportSnapshot;
+
     fn poll_scheduled_events(
         &self,
         window_samples: u64,
     ) -> Result<Vec<ScheduledEvent>, PlaybackError>;
+
+    /// Drains the notifications raised since the previous call.
+    fn poll_notifications(&self) -> Vec<PlaybackNotification>;
 }
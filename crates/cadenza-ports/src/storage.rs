@@ -1,10 +1,22 @@
+use crate::midi::MidiMapping;
 use crate::types::*;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 
 fn default_monitor_enabled() -> bool {
     true
 }
 
+fn legacy_volume_curve() -> VolumeCurve {
+    VolumeCurve::Linear
+}
+
+/// Fader position that is as loud under the default curve as `linear` was before curves.
+fn default_volume(linear: f32) -> Volume01 {
+    VolumeCurve::default().volume_for_gain(linear)
+}
+
 fn default_master_volume() -> Volume01 {
     Volume01::new(0.8)
 }
@@ -45,9 +57,44 @@ pub struct SettingsDto {
     pub bus_autopilot_volume: Volume01,
     #[serde(default = "default_bus_metronome_volume")]
     pub bus_metronome_volume: Volume01,
+    /// Missing from settings saved before curves existed, which keep their linear faders.
+    #[serde(default = "legacy_volume_curve")]
+    pub volume_curve: VolumeCurve,
+    pub limiter: LimiterParams,
+    pub ducking: DuckingParams,
     pub input_offset_ms: i32,
     pub default_sf2_path: Option<String>,
     pub audiveris_path: Option<String>,
+    /// Keyboard buttons, keys or pedals bound to transport actions.
+    pub midi_mappings: Vec<MidiMapping>,
+    /// Set once the first-run setup has been walked through or skipped.
+    pub setup_completed: bool,
+    /// How often live `MidiInputEvent`s are sent, coalesced per key; 0 sends none.
+    pub input_event_rate_hz: u32,
+    /// GM programs chosen per bus; buses left out keep the soundfont's default.
+    pub bus_programs: BTreeMap<Bus, u8>,
+    /// How long notes ring relative to the synth's usual sound, see
+    /// [`crate::synth::SynthPort::set_decay_scale`].
+    pub decay_scale: f32,
+    /// Implementations to start with, as a [`crate::config::PortsConfig`] spec; `None` uses
+    /// the built-in choice. Read at startup only.
+    pub ports: Option<String>,
+}
+
+impl SettingsDto {
+    /// Switches to `curve`, moving every fader so each bus keeps its current loudness.
+    pub fn convert_volume_curve(&mut self, curve: VolumeCurve) {
+        let from = self.volume_curve;
+        for volume in [
+            &mut self.master_volume,
+            &mut self.bus_user_volume,
+            &mut self.bus_autopilot_volume,
+            &mut self.bus_metronome_volume,
+        ] {
+            *volume = curve.volume_for_gain(from.gain(*volume));
+        }
+        self.volume_curve = curve;
+    }
 }
 
 impl Default for SettingsDto {
@@ -57,13 +104,22 @@ impl Default for SettingsDto {
             selected_audio_out: None,
             audio_buffer_size_frames: None,
             monitor_enabled: true,
-            master_volume: Volume01::new(0.8),
-            bus_user_volume: Volume01::new(0.8),
-            bus_autopilot_volume: Volume01::new(0.8),
-            bus_metronome_volume: Volume01::new(0.6),
+            master_volume: default_volume(0.8),
+            bus_user_volume: default_volume(0.8),
+            bus_autopilot_volume: default_volume(0.8),
+            bus_metronome_volume: default_volume(0.6),
+            volume_curve: VolumeCurve::default(),
+            limiter: LimiterParams::default(),
+            ducking: DuckingParams::default(),
             input_offset_ms: 0,
             default_sf2_path: None,
             audiveris_path: None,
+            midi_mappings: Vec::new(),
+            setup_completed: false,
+            input_event_rate_hz: 20,
+            bus_programs: BTreeMap::new(),
+            decay_scale: 1.0,
+            ports: None,
         }
     }
 }
@@ -71,4 +127,26 @@ impl Default for SettingsDto {
 pub trait StoragePort: Send + Sync {
     fn load_settings(&self) -> Result<SettingsDto, StorageError>;
     fn save_settings(&self, s: &SettingsDto) -> Result<(), StorageError>;
+
+    /// Writes a diagnostics report next to the settings and returns where it went.
+    fn save_diagnostics_file(&self, name: &str, _data: &[u8]) -> Result<PathBuf, StorageError> {
+        Err(StorageError::Io(format!(
+            "cannot save {name}: storage has no diagnostics directory"
+        )))
+    }
+
+    /// Replaces the snapshot of the practice session in progress. Storage without a place
+    /// for it keeps nothing.
+    fn save_session_snapshot(&self, _data: &[u8]) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    /// The snapshot left by a session that never ended cleanly, if any.
+    fn load_session_snapshot(&self) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(None)
+    }
+
+    fn clear_session_snapshot(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
 }
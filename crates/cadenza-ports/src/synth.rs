@@ -1,5 +1,6 @@
 use crate::midi::MidiLikeEvent;
 use crate::types::*;
+use serde::{Deserialize, Serialize};
 
 #[derive(thiserror::Error, Debug)]
 pub enum SynthError {
@@ -7,10 +8,28 @@ pub enum SynthError {
     SoundFontLoad(String),
     #[error("unsupported soundfont format")]
     UnsupportedFormat,
+    /// The file was read but is damaged or not a soundfont.
+    #[error("not a usable soundfont: {reason}")]
+    InvalidSoundFont { reason: String },
+    #[error("the soundfont has no preset for program {program}")]
+    PresetNotFound { program: u8 },
     #[error("backend error: {0}")]
     Backend(String),
 }
 
+/// Range of [`SynthPort::set_decay_scale`].
+pub const MIN_DECAY_SCALE: f32 = 0.25;
+pub const MAX_DECAY_SCALE: f32 = 1.5;
+
+/// How much work the synth may spend per voice; see [`SynthPort::set_quality`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QualityHint {
+    #[default]
+    Full,
+    /// Cheaper voices for machines that cannot render full quality in time.
+    Reduced,
+}
+
 #[derive(Clone, Debug)]
 pub struct SoundFontInfo {
     pub name: String,
@@ -30,4 +49,19 @@ pub trait SynthPort: Send + Sync {
 
     /// Called by audio thread: render frames to out_l/out_r
     fn render(&self, bus: Bus, frames: usize, out_l: &mut [f32], out_r: &mut [f32]);
+
+    /// Called by audio thread after render: true when the bus would render silence until its
+    /// next event, so the graph may skip it. Defaults to never silent.
+    fn is_silent(&self, _bus: Bus) -> bool {
+        false
+    }
+
+    /// Stretches (above 1) or shortens (below 1) how long notes struck from now on ring,
+    /// within [`MIN_DECAY_SCALE`]..=[`MAX_DECAY_SCALE`]. Synths without such a control
+    /// ignore it.
+    fn set_decay_scale(&self, _scale: f32) {}
+
+    /// Trades sound for render time when the output keeps overrunning. Called from the core
+    /// thread; synths with nothing to trade ignore it.
+    fn set_quality(&self, _hint: QualityHint) {}
 }
@@ -7,7 +7,7 @@ pub type SampleTime = u64; // audio sample index, monotonic while stream running
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct DeviceId(pub String);
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum Bus {
     UserMonitor,
     Autopilot,
@@ -26,9 +26,24 @@ pub struct AudioOutputDevice {
     pub id: DeviceId,
     pub name: String,
     pub default_config: AudioConfig,
+    /// Buffer sizes the device accepts, when the backend reports them.
+    #[serde(default)]
+    pub buffer_size_range: Option<BufferSizeRange>,
 }
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BufferSizeRange {
+    pub min_frames: u32,
+    pub max_frames: u32,
+}
+
+impl BufferSizeRange {
+    pub fn contains(&self, frames: u32) -> bool {
+        (self.min_frames..=self.max_frames).contains(&frames)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AudioConfig {
     pub sample_rate_hz: u32,
     pub channels: u16, // v1 fixed 2
@@ -48,10 +63,115 @@ impl Volume01 {
     }
 }
 
+/// How a fader position ([`Volume01`]) maps to a linear gain.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VolumeCurve {
+    /// Gain equals the fader position. Settings saved before curves existed use this.
+    Linear,
+    /// Fader spans -60 dB..0 dB evenly in decibels; the bottom stop mutes.
+    #[default]
+    Db60,
+}
+
+impl VolumeCurve {
+    const DB60_RANGE_DB: f32 = 60.0;
+
+    pub fn gain(self, volume: Volume01) -> f32 {
+        let v = volume.get();
+        match self {
+            Self::Linear => v,
+            Self::Db60 if v <= 0.0 => 0.0,
+            Self::Db60 => 10.0_f32.powf((v - 1.0) * Self::DB60_RANGE_DB / 20.0),
+        }
+    }
+
+    /// Fader position that produces `gain`; the inverse of [`VolumeCurve::gain`].
+    pub fn volume_for_gain(self, gain: f32) -> Volume01 {
+        match self {
+            Self::Linear => Volume01::new(gain),
+            Self::Db60 if gain <= 0.0 => Volume01::new(0.0),
+            Self::Db60 => Volume01::new(1.0 + 20.0 * gain.log10() / Self::DB60_RANGE_DB),
+        }
+    }
+}
+
+/// Output limiter tuning. Times are how long the gain takes to settle (to within 1%) after
+/// the level rises or falls.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LimiterParams {
+    /// Highest output amplitude, linear full scale.
+    pub ceiling: f32,
+    pub attack_ms: f32,
+    pub release_ms: f32,
+}
+
+impl LimiterParams {
+    pub fn clamped(self) -> Self {
+        Self {
+            ceiling: self.ceiling.clamp(0.1, 1.0),
+            attack_ms: self.attack_ms.clamp(0.1, 50.0),
+            release_ms: self.release_ms.clamp(1.0, 5_000.0),
+        }
+    }
+}
+
+impl Default for LimiterParams {
+    fn default() -> Self {
+        Self {
+            ceiling: 0.98,
+            attack_ms: 1.5,
+            release_ms: 1_000.0,
+        }
+    }
+}
+
+/// Turning the accompaniment down while the player is heard. Times are how long the gain
+/// takes to settle (to within 1%) once playing starts or stops.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DuckingParams {
+    pub enabled: bool,
+    /// How far the autopilot bus is turned down, in positive dB.
+    pub depth_db: f32,
+    pub attack_ms: f32,
+    pub release_ms: f32,
+}
+
+impl DuckingParams {
+    pub fn clamped(self) -> Self {
+        Self {
+            enabled: self.enabled,
+            depth_db: self.depth_db.clamp(0.0, 40.0),
+            attack_ms: self.attack_ms.clamp(1.0, 500.0),
+            release_ms: self.release_ms.clamp(10.0, 5_000.0),
+        }
+    }
+}
+
+impl Default for DuckingParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            depth_db: 6.0,
+            attack_ms: 20.0,
+            release_ms: 500.0,
+        }
+    }
+}
+
 impl fmt::Display for DeviceId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.0)
     }
 }
 
+impl fmt::Display for AudioConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ch at {} Hz", self.channels, self.sample_rate_hz)?;
+        if let Some(frames) = self.buffer_size_frames {
+            write!(f, ", {frames}-frame buffer")?;
+        }
+        Ok(())
+    }
+}
+
 pub type Shared<T> = Arc<T>;
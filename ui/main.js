@@ -17,10 +17,15 @@ const state = {
     musicxmlPath: null,
     logPath: null,
   },
-  scoreView: { title: null, ppq: 480, notes: [], targets: [], pedal: [], noteStarts: [], pedalStarts: [] },
+  scoreView: { title: null, composer: null, keySignatures: [], ppq: 480, notes: [], targets: [], pedal: [], noteStarts: [], pedalStarts: [], grid: [], gridTicks: [] },
   pressedNotes: new Set(),
+  // Next targets from the judge's focus, sent by the core as the transport moves.
+  upcomingTargets: [],
+  // Piano-roll note ids the judge reported as missed since the score loaded.
+  missedNoteIds: new Set(),
   sustainDown: false,
   sf2Loaded: false,
+  recording: false,
 };
 
 const transportInterp = {
@@ -33,15 +38,16 @@ const transportInterp = {
 function onTransportUpdate(data) {
   const nowMs = typeof performance !== "undefined" ? performance.now() : Date.now();
   const dtMs = nowMs - transportInterp.lastUpdateMs;
+  const tick = data.tick_precise ?? data.tick ?? 0;
 
   if (data && data.playing && transportInterp.playing && dtMs > 0) {
-    const dtTick = (data.tick || 0) - (transportInterp.lastTick || 0);
+    const dtTick = tick - (transportInterp.lastTick || 0);
     if (dtTick >= 0 && dtTick < 5_000_000) {
       transportInterp.tickRate = dtTick / dtMs;
     }
   }
 
-  transportInterp.lastTick = data.tick || 0;
+  transportInterp.lastTick = tick;
   transportInterp.lastUpdateMs = nowMs;
   transportInterp.playing = !!data.playing;
   if (!transportInterp.playing) {
@@ -67,12 +73,31 @@ viewButtons.forEach((button) => {
   button.addEventListener("click", () => {
     viewButtons.forEach((btn) => btn.classList.remove("is-active"));
     button.classList.add("is-active");
-    const view = button.dataset.view;
-    Object.values(views).forEach((node) => node.classList.remove("is-active"));
-    views[view].classList.add("is-active");
+    showView(button.dataset.view);
   });
 });
 
+function showView(view) {
+  viewButtons.forEach((btn) => btn.classList.toggle("is-active", btn.dataset.view === view));
+  Object.values(views).forEach((node) => node.classList.remove("is-active"));
+  views[view].classList.add("is-active");
+  if (view === "practice") completeSetupStep("Score");
+}
+
+// First-run setup steps the core still waits for.
+let setupPending = [];
+
+function completeSetupStep(step) {
+  if (!setupPending.includes(step)) return;
+  setupPending = setupPending.filter((pending) => pending !== step);
+  sendCommand({ type: "CompleteSetupStep", payload: { step } });
+}
+
+function completeSelectedDeviceSteps(settings) {
+  if (settings?.selected_midi_in) completeSetupStep("MidiInput");
+  if (settings?.selected_audio_out) completeSetupStep("AudioOutput");
+}
+
 async function sendCommandAck(command) {
   if (!invoke) {
     showError(
@@ -153,6 +178,7 @@ function setPdfConvertUi(running, statusText) {
     "btn-browse-pdf",
     "btn-browse-midi-output",
     "btn-browse-midi-output-folder",
+    "pdf-split-hands",
   ];
   disableIds.forEach((id) => {
     const el = document.getElementById(id);
@@ -286,11 +312,16 @@ function renderRecentInputs(events) {
 function updateSessionSettings(settings) {
   if (!settings) return;
   document.getElementById("monitor-toggle").checked = settings.monitor_enabled;
+  document.getElementById("ducking-toggle").checked = Boolean(settings.ducking && settings.ducking.enabled);
   document.getElementById("master-volume").value = settings.master_volume;
   document.getElementById("master-volume-value").textContent = settings.master_volume.toFixed(2);
   document.getElementById("bus-user").value = settings.bus_user_volume;
   document.getElementById("bus-auto").value = settings.bus_autopilot_volume;
   document.getElementById("bus-metro").value = settings.bus_metronome_volume;
+  const volumeCurve = document.getElementById("volume-curve");
+  if (volumeCurve && settings.volume_curve) {
+    volumeCurve.value = settings.volume_curve;
+  }
   const buffer = document.getElementById("audio-buffer");
   if (buffer) {
     buffer.value =
@@ -316,8 +347,22 @@ function updateSessionSettings(settings) {
   }
 }
 
+function formatClock(ms) {
+  const totalSeconds = Math.floor(ms / 1000);
+  const minutes = Math.floor(totalSeconds / 60);
+  const seconds = totalSeconds % 60;
+  return `${minutes}:${String(seconds).padStart(2, "0")}`;
+}
+
 function updateTransport() {
   document.getElementById("transport-tick").textContent = state.transport.tick;
+  if (typeof state.transport.position_ms === "number") {
+    document.getElementById("transport-time").textContent =
+      `${formatClock(state.transport.position_ms)} / ${formatClock(state.transport.duration_ms || 0)}`;
+  }
+  if (typeof state.transport.bar === "number") {
+    document.getElementById("transport-bar").textContent = `${state.transport.bar}.${state.transport.beat}`;
+  }
   document.getElementById("transport-tempo").textContent = `${state.transport.tempo_multiplier.toFixed(2)}x`;
   document.getElementById("practice-status").textContent = state.session;
   const loopEl = document.getElementById("transport-loop");
@@ -341,6 +386,17 @@ function formatBarBeat(tick, ppq) {
   return `${bar}.${beat}`;
 }
 
+const MAJOR_KEY_NAMES = ["C♭", "G♭", "D♭", "A♭", "E♭", "B♭", "F", "C", "G", "D", "A", "E", "B", "F♯", "C♯"];
+const MINOR_KEY_NAMES = ["A♭", "E♭", "B♭", "F", "C", "G", "D", "A", "E", "B", "F♯", "C♯", "G♯", "D♯", "A♯"];
+
+function formatKeySignature(key) {
+  if (!key) return null;
+  const minor = key.mode === "Minor";
+  const name = (minor ? MINOR_KEY_NAMES : MAJOR_KEY_NAMES)[key.fifths + 7];
+  if (!name) return null;
+  return `${name} ${minor ? "minor" : "major"}`;
+}
+
 function lowerBound(arr, value) {
   let lo = 0;
   let hi = arr.length;
@@ -483,19 +539,8 @@ function drawStaffView(nowTick, ppq) {
   ctx.arc(leftPad + 142, 10, 4, 0, Math.PI * 2);
   ctx.fill();
 
-  // Compute next expected target chord.
-  const targets = state.scoreView.targets || [];
-  let nextTarget = null;
-  if (targets.length) {
-    let lo = 0;
-    let hi = targets.length;
-    while (lo < hi) {
-      const mid = (lo + hi) >> 1;
-      if (targets[mid].tick < nowTick) lo = mid + 1;
-      else hi = mid;
-    }
-    nextTarget = targets[lo] || null;
-  }
+  // Next expected target chord, as the core's judge sees it.
+  const nextTarget = state.upcomingTargets[0] || null;
 
   const expectedNotes = new Set((nextTarget && nextTarget.notes) || []);
   const pressedNotes = state.pressedNotes || new Set();
@@ -655,22 +700,31 @@ function drawPianoRoll() {
   const startIdx = lowerBound(noteStarts, visibleMin);
   const endIdx = lowerBound(noteStarts, visibleMax + 1);
 
-  const beatStart = Math.floor(nowTick / ppq) * ppq;
-  ctx.lineWidth = 1;
-  for (let t = beatStart; t <= visibleMax; t += ppq) {
+  const gridStroke = { Bar: "rgba(15, 23, 42, 0.14)", Beat: "rgba(15, 23, 42, 0.07)", Subdivision: "rgba(15, 23, 42, 0.03)" };
+  const drawGridLine = (t, kind) => {
     const y = tickToY(t);
-    if (y < 0 || y > nowLineY) continue;
+    if (y < 0 || y > nowLineY) return;
     const isNow = Math.abs(t - nowTick) < 1;
-    const isMeasure = ((t / ppq) | 0) % 4 === 0;
-    ctx.strokeStyle = isNow
-      ? "rgba(15, 118, 110, 0.78)"
-      : isMeasure
-        ? "rgba(15, 23, 42, 0.14)"
-        : "rgba(15, 23, 42, 0.07)";
+    ctx.strokeStyle = isNow ? "rgba(15, 118, 110, 0.78)" : gridStroke[kind] || gridStroke.Beat;
     ctx.beginPath();
     ctx.moveTo(0, y);
     ctx.lineTo(w, y);
     ctx.stroke();
+  };
+  ctx.lineWidth = 1;
+  const grid = state.scoreView.grid || [];
+  if (grid.length > 0) {
+    const gridTicks = state.scoreView.gridTicks || [];
+    const gridEnd = lowerBound(gridTicks, visibleMax + 1);
+    for (let i = lowerBound(gridTicks, nowTick); i < gridEnd; i += 1) {
+      drawGridLine(grid[i].tick, grid[i].kind);
+    }
+  } else {
+    // No score: quarters in 4/4.
+    const beatStart = Math.floor(nowTick / ppq) * ppq;
+    for (let t = beatStart; t <= visibleMax; t += ppq) {
+      drawGridLine(t, ((t / ppq) | 0) % 4 === 0 ? "Bar" : "Beat");
+    }
   }
 
   const drawLoopRange = (range, fill, stroke) => {
@@ -754,7 +808,7 @@ function drawPianoRoll() {
   };
   const noteFill = (n, white) => {
     const vel = Math.min(1, Math.max(0, (n.velocity || 80) / 127));
-    const hue = handHue(n.hand);
+    const hue = state.missedNoteIds.has(n.id) ? 0 : handHue(n.hand);
     const sat = 82;
     const light = white ? 44 + vel * 8 : 40 + vel * 10;
     const alpha = 0.25 + vel * 0.65;
@@ -787,21 +841,17 @@ function drawPianoRoll() {
     ctx.fillRect(x, top, width, height);
     ctx.strokeStyle = "rgba(15, 23, 42, 0.18)";
     ctx.strokeRect(x + 0.5, top + 0.5, width - 1, height - 1);
-  }
-
-  const targets = state.scoreView.targets || [];
-  let nextTarget = null;
-  if (targets.length) {
-    let lo = 0;
-    let hi = targets.length;
-    while (lo < hi) {
-      const mid = (lo + hi) >> 1;
-      if (targets[mid].tick < nowTick) lo = mid + 1;
-      else hi = mid;
+    if (n.name && height >= 14) {
+      ctx.font = "9px Menlo, monospace";
+      if (ctx.measureText(n.name).width <= width - 2) {
+        ctx.fillStyle = "rgba(255, 255, 255, 0.9)";
+        ctx.fillText(n.name, x + 1, bottom - 3);
+      }
     }
-    nextTarget = targets[lo] || null;
   }
 
+  const nextTarget = state.upcomingTargets[0] || null;
+
   if (nextTarget) {
     const y = tickToY(nextTarget.tick);
     if (y >= 0 && y <= nowLineY) {
@@ -969,19 +1019,155 @@ if (!listen) {
     switch (type) {
       case "ScoreViewUpdated":
         state.scoreView.title = data.title || null;
+        state.scoreView.composer = data.composer || null;
         state.scoreView.ppq = data.ppq || 480;
+        state.scoreView.keySignatures = Array.isArray(data.key_signatures) ? data.key_signatures : [];
         state.scoreView.notes = Array.isArray(data.notes) ? data.notes : [];
+        state.missedNoteIds.clear();
         state.scoreView.targets = Array.isArray(data.targets) ? data.targets : [];
+        state.upcomingTargets = [];
         state.scoreView.pedal = Array.isArray(data.pedal) ? data.pedal : [];
         state.scoreView.pedal.sort((a, b) => (a.start_tick || 0) - (b.start_tick || 0));
         state.scoreView.noteStarts = state.scoreView.notes.map((n) => n.start_tick || 0);
         state.scoreView.pedalStarts = state.scoreView.pedal.map((p) => p.start_tick || 0);
-        document.getElementById("score-title").textContent =
-          state.scoreView.title || `PPQ ${state.scoreView.ppq}`;
+        state.scoreView.grid = Array.isArray(data.grid) ? data.grid : [];
+        state.scoreView.gridTicks = state.scoreView.grid.map((line) => line.tick);
+        document.getElementById("score-title").textContent = state.scoreView.title
+          ? [
+              state.scoreView.title,
+              state.scoreView.composer,
+              formatKeySignature(state.scoreView.keySignatures[0]),
+              data.origin_path ? `from ${data.origin_path.split(/[\\/]/).pop()}` : null,
+            ]
+              .filter(Boolean)
+              .join(" — ")
+          : `PPQ ${state.scoreView.ppq}`;
+        break;
+      case "AudioLevels":
+        {
+          const peak = (data.master && data.master.peak) || 0;
+          document.getElementById("master-meter").value = Math.min(peak, 1);
+          document.getElementById("master-meter-value").textContent =
+            peak > 0.00001 ? `${(20 * Math.log10(peak)).toFixed(1)} dB` : "-∞ dB";
+          document.getElementById("limiter-reduction-value").textContent =
+            `${(-(data.gain_reduction_db || 0)).toFixed(1)} dB`;
+        }
+        break;
+      case "AudioSelfTestResult":
+        {
+          const failed = (data.stages || []).filter((stage) => stage.status === "Failed");
+          document.getElementById("self-test-status").textContent = data.passed
+            ? "Audio path OK"
+            : failed
+                .map((stage) => `${stage.bus ? `${stage.bus} ` : ""}${stage.stage}: ${stage.detail}`)
+                .join("; ");
+        }
+        break;
+      case "MidiCaptureReport":
+        {
+          const report = data.report;
+          const jitter = report.jitter
+            ? `, jitter ${report.jitter.std_dev_ms.toFixed(2)} ms (max ${report.jitter.max_abs_ms.toFixed(2)} ms)`
+            : "";
+          const lost = report.queue_drops + report.overflowed;
+          document.getElementById("midi-capture-status").textContent =
+            `${report.captured} events, ${report.duplicates} duplicates, ${lost} dropped${jitter}` +
+            (data.path ? ` — saved ${data.path}` : "");
+        }
+        break;
+      case "DiagnosticsExported":
+        document.getElementById("diag-status").textContent = `Saved ${data.path}`;
+        revealPath(data.path);
+        break;
+      case "LateAudioEvents":
+        console.warn(
+          `Late audio events: ${data.late_events} (dropped ${data.dropped_note_ons} note-ons, worst ${(data.max_lateness_ms || 0).toFixed(1)} ms)`,
+        );
+        break;
+      case "AudioRecordingUpdated":
+        state.recording = Boolean(data.recording);
+        document.getElementById("btn-record-audio").textContent = state.recording
+          ? "Stop recording"
+          : "Record output";
+        document.getElementById("recording-duration-value").textContent =
+          `${(data.duration_secs || 0).toFixed(1)} s`;
+        if (!state.recording && data.dropped_frames > 0) {
+          showError(`Recording dropped ${data.dropped_frames} frames`);
+        }
         break;
       case "OmrProgress":
         setPdfConvertUi(true, data.stage);
         break;
+      case "CommandFailed":
+        // Failed invocations already show their error; this also covers background work.
+        console.warn(
+          `${data.command_kind} failed${data.recoverable ? "" : " (backend)"}: ${data.message}`,
+        );
+        break;
+      case "SetupRequired":
+        setupPending = data.missing.slice();
+        showView("settings");
+        showError("Welcome! Pick your keyboard and audio output, then open Practice to play the demo.");
+        completeSelectedDeviceSteps(state.settings);
+        break;
+      case "SetupCompleted":
+        setupPending = [];
+        break;
+      case "RecoveredSession": {
+        const { snapshot } = data;
+        const title = snapshot.score_title || "the last score";
+        const savedAt = new Date(snapshot.saved_at_unix_ms).toLocaleString();
+        if (window.confirm(`Practice of ${title} stopped unexpectedly (${savedAt}). Resume it?`)) {
+          sendCommand({ type: "ResumeRecoveredSession" });
+        }
+        break;
+      }
+      case "AudioOutputConfigured": {
+        const status = document.getElementById("audio-config-status");
+        if (status) {
+          const frames = data.effective.buffer_size_frames;
+          status.textContent = `${data.effective.sample_rate_hz} Hz, buffer ${frames ? `${frames} frames` : "default"}`;
+        }
+        break;
+      }
+      case "AudioStats": {
+        const stats = document.getElementById("audio-stats");
+        if (stats) {
+          stats.textContent = `Input jitter ${data.input_jitter_ms.toFixed(1)} ms, clock drift ${Math.round(data.clock_drift_ppm)} ppm, synth load ${Math.round(data.synth_load.total_percent)}%`;
+        }
+        break;
+      }
+      case "SampleRateCorrected":
+        console.warn(data.message);
+        break;
+      case "ClockDriftWarning":
+      case "SynthLoadWarning":
+        showError(data.message);
+        break;
+      case "QualityReduced":
+        if (data.automatic) showError(data.message);
+        break;
+      case "QualityRestored":
+        console.info("Synth back at full quality");
+        break;
+      case "ScoreFileSaved":
+        setMidiLoadUi(false, `Saved ${data.path}`);
+        break;
+      case "ScoreDirtyChanged":
+        document.getElementById("btn-save-project").textContent = data.dirty
+          ? "Save project*"
+          : "Save project";
+        break;
+      case "SilentAudioFallback":
+        showError(`No audio output (${data.reason}); practicing without sound.`);
+        break;
+      case "ImportWarnings":
+        if (Array.isArray(data.messages) && data.messages.length > 0) {
+          data.messages.forEach((message) => console.warn(`Import warning: ${message}`));
+          const more = data.messages.length > 1 ? ` (+${data.messages.length - 1} more)` : "";
+          showError(`Import warning: ${data.messages[0]}${more}`);
+        }
+        break;
       case "OmrDiagnostics":
         if (data.severity === "error") {
           showError(data.message);
@@ -1008,7 +1194,7 @@ if (!listen) {
           document.getElementById("midi-output-path").value = data.output_path;
           (async () => {
             const source = data.musicxml_path
-              ? { type: "MusicXmlFile", payload: data.musicxml_path }
+              ? { type: "MusicXmlFile", payload: { path: data.musicxml_path } }
               : { type: "MidiFile", payload: data.output_path };
             setPdfConvertUi(true, data.musicxml_path ? "Loading MusicXML..." : "Loading MIDI...");
             setMidiLoadUi(true, "Loading...");
@@ -1035,6 +1221,24 @@ if (!listen) {
         );
         ensureMidiSelected();
         break;
+      case "MidiInputLost":
+        showError("MIDI keyboard disconnected; it will reconnect when plugged back in.");
+        break;
+      case "MidiInputRestored":
+        showError("MIDI keyboard reconnected.");
+        break;
+      case "InternalDemosListed": {
+        const select = document.getElementById("demo-select");
+        select.replaceChildren(
+          ...data.demos.map((demo) => {
+            const option = document.createElement("option");
+            option.value = demo.id;
+            option.textContent = `${demo.title.replace(/^Demo: /, "")} (${demo.difficulty})`;
+            return option;
+          })
+        );
+        break;
+      }
       case "AudioOutputsUpdated":
         state.audioOutputs = data.devices;
         updateDeviceSelect(
@@ -1065,6 +1269,7 @@ if (!listen) {
         updateTransport();
         ensureAudioSelected();
         ensureMidiSelected();
+        completeSelectedDeviceSteps(data.settings);
         break;
       case "SoundFontStatus": {
         state.sf2Loaded = !!data.loaded;
@@ -1091,9 +1296,25 @@ if (!listen) {
         updateTransport();
         onTransportUpdate(data);
         break;
+      case "UpcomingTargets":
+        state.upcomingTargets = Array.isArray(data.targets) ? data.targets : [];
+        break;
       case "JudgeFeedback":
         document.getElementById("judge-grade").textContent = data.grade;
+        document.getElementById("judge-grade").title = (data.expected_names || []).join(" ");
+        if (data.grade === "Miss") {
+          for (const id of data.note_ids || []) state.missedNoteIds.add(id);
+        }
+        break;
+      case "ScoreAnalysis": {
+        const suggested = document.getElementById("btn-tempo-suggested");
+        const tempo = data.analysis.suggested_tempo_multiplier;
+        suggested.dataset.tempo = String(tempo);
+        suggested.textContent = `Start at ${Math.round(tempo * 100)}%`;
+        suggested.title = `Up to ${data.analysis.peak_notes_per_second} notes a second, chords of up to ${data.analysis.max_chord_size}`;
+        suggested.hidden = tempo >= 1;
         break;
+      }
       case "ScoreSummaryUpdated":
         document.getElementById("judge-combo").textContent = data.combo;
         document.getElementById("judge-score").textContent = data.score;
@@ -1125,13 +1346,19 @@ document.getElementById("btn-stop").addEventListener("click", () => {
   sendCommand({ type: "StopPractice" });
 });
 
+document.getElementById("btn-panic").addEventListener("click", () => {
+  sendCommand({ type: "PanicAllNotes" });
+});
+
 document.querySelectorAll(".tempo-btn").forEach((button) => {
   button.addEventListener("click", () => {
     const tempo = parseFloat(button.dataset.tempo);
     if (!Number.isFinite(tempo) || tempo <= 0) return;
     state.transport.tempo_multiplier = tempo;
     updateTransport();
-    sendCommand({ type: "SetTempoMultiplier", payload: { x: tempo } });
+    // Glide while playing so the change is not jarring.
+    const ramp_ms = state.transport.playing ? 1500 : null;
+    sendCommand({ type: "SetTempoMultiplier", payload: { x: tempo, ramp_ms } });
   });
 });
 
@@ -1157,20 +1384,36 @@ document.getElementById("btn-load-midi").addEventListener("click", () => {
   if (!path) return;
   (async () => {
     setMidiLoadUi(true, "Loading...");
-    const ok = await sendCommandAck({
-      type: "LoadScore",
-      payload: { source: { type: "MidiFile", payload: path } },
-    });
+    const source = path.toLowerCase().endsWith(".cadenza")
+      ? { type: "CadenzaFile", payload: path }
+      : { type: "MidiFile", payload: path };
+    const ok = await sendCommandAck({ type: "LoadScore", payload: { source } });
     setMidiLoadUi(false, ok ? "Loaded" : "Failed");
   })();
 });
 
+document.getElementById("btn-save-project").addEventListener("click", async () => {
+  const file = await pickSaveFile({
+    title: "Save project",
+    filters: [{ name: "Cadenza project", extensions: ["cadenza"] }],
+  });
+  if (file) {
+    const path = file.toLowerCase().endsWith(".cadenza") ? file : `${file}.cadenza`;
+    sendCommand({ type: "SaveScoreFile", payload: { path } });
+  }
+});
+
 document.getElementById("btn-load-demo").addEventListener("click", () => {
   (async () => {
     setMidiLoadUi(true, "Loading demo...");
     const ok = await sendCommandAck({
       type: "LoadScore",
-      payload: { source: { type: "InternalDemo", payload: "c_major_scale" } },
+      payload: {
+        source: {
+          type: "InternalDemo",
+          payload: document.getElementById("demo-select").value || "c_major_scale",
+        },
+      },
     });
     setMidiLoadUi(false, ok ? "Loaded demo" : "Failed");
   })();
@@ -1179,7 +1422,10 @@ document.getElementById("btn-load-demo").addEventListener("click", () => {
 document.getElementById("btn-browse-midi").addEventListener("click", async () => {
   const file = await pickFile({
     title: "Select MIDI file",
-    filters: [{ name: "MIDI", extensions: ["mid", "midi"] }],
+    filters: [
+      { name: "MIDI", extensions: ["mid", "midi"] },
+      { name: "Cadenza project", extensions: ["cadenza"] },
+    ],
   });
   if (file) {
     document.getElementById("midi-path").value = file;
@@ -1222,6 +1468,8 @@ document.getElementById("btn-convert-pdf").addEventListener("click", () => {
   const pdfPath = document.getElementById("pdf-path").value.trim();
   let outputPath = document.getElementById("midi-output-path").value.trim();
   const audiverisPath = document.getElementById("audiveris-path").value.trim();
+  const splitByHand = document.getElementById("pdf-split-hands").checked;
+  const omrCleanup = document.getElementById("pdf-omr-cleanup").checked;
   if (!pdfPath) return;
   if (outputPath) {
     const normalizedOutputPath = ensureMidiExtension(outputPath);
@@ -1238,6 +1486,8 @@ document.getElementById("btn-convert-pdf").addEventListener("click", () => {
         pdf_path: pdfPath,
         output_path: outputPath || "",
         audiveris_path: audiverisPath || null,
+        split_by_hand: splitByHand,
+        omr_cleanup: omrCleanup,
       },
     });
     if (!ok) {
@@ -1271,6 +1521,11 @@ document.getElementById("btn-refresh-audio").addEventListener("click", () => {
   sendCommand({ type: "ListAudioOutputs" });
 });
 
+document.getElementById("btn-self-test-audio").addEventListener("click", () => {
+  document.getElementById("self-test-status").textContent = "Checking…";
+  sendCommand({ type: "RunAudioSelfTest" });
+});
+
 document.getElementById("btn-test-audio").addEventListener("click", () => {
   sendCommand({ type: "TestAudio" });
 });
@@ -1279,6 +1534,10 @@ document.getElementById("btn-refresh-midi").addEventListener("click", () => {
   sendCommand({ type: "ListMidiInputs" });
 });
 
+document.getElementById("btn-reconnect-devices").addEventListener("click", () => {
+  sendCommand({ type: "RefreshDevices" });
+});
+
 document.getElementById("audio-output").addEventListener("change", (event) => {
   const id = event.target.value;
   if (!id) return;
@@ -1288,27 +1547,20 @@ document.getElementById("audio-output").addEventListener("change", (event) => {
   });
 });
 
+document.getElementById("volume-curve").addEventListener("change", (event) => {
+  sendCommand({ type: "SetVolumeCurve", payload: { curve: event.target.value } });
+});
+
+document.getElementById("synth-quality").addEventListener("change", (event) => {
+  const quality = event.target.value || null;
+  sendCommand({ type: "SetSynthQuality", payload: { quality } });
+});
+
 document.getElementById("audio-buffer").addEventListener("change", (event) => {
-  const id = document.getElementById("audio-output").value;
-  if (!id) return;
   const value = String(event.target.value || "").trim();
   const frames = value ? parseInt(value, 10) : 0;
   const desiredFrames = Number.isFinite(frames) && frames > 0 ? frames : null;
-
-  const device = state.audioOutputs.find((d) => d.id === id);
-  const base = device ? device.default_config : { sample_rate_hz: 48000, channels: 2 };
-
-  sendCommand({
-    type: "SelectAudioOutput",
-    payload: {
-      device_id: id,
-      config: {
-        sample_rate_hz: base.sample_rate_hz,
-        channels: base.channels,
-        buffer_size_frames: desiredFrames,
-      },
-    },
-  });
+  sendCommand({ type: "SetAudioBufferSize", payload: { frames: desiredFrames } });
 });
 
 document.getElementById("midi-input").addEventListener("change", (event) => {
@@ -1324,6 +1576,11 @@ document.getElementById("monitor-toggle").addEventListener("change", (event) =>
   sendCommand({ type: "SetMonitorEnabled", payload: { enabled: event.target.checked } });
 });
 
+document.getElementById("ducking-toggle").addEventListener("change", (event) => {
+  const current = (state.settings && state.settings.ducking) || { depth_db: 6, attack_ms: 20, release_ms: 500 };
+  sendCommand({ type: "SetDuckingParams", payload: { params: { ...current, enabled: event.target.checked } } });
+});
+
 document.getElementById("master-volume").addEventListener("input", (event) => {
   const volume = parseFloat(event.target.value);
   document.getElementById("master-volume-value").textContent = volume.toFixed(2);
@@ -1348,12 +1605,32 @@ document.getElementById("bus-metro").addEventListener("input", (event) => {
 });
 
 
+document.getElementById("btn-record-audio").addEventListener("click", async () => {
+  if (state.recording) {
+    sendCommand({ type: "StopAudioRecording" });
+    return;
+  }
+  const file = await pickSaveFile({
+    title: "Save recording",
+    filters: [{ name: "WAV", extensions: ["wav"] }],
+  });
+  if (file) {
+    const path = file.toLowerCase().endsWith(".wav") ? file : `${file}.wav`;
+    sendCommand({ type: "StartAudioRecording", payload: { path } });
+  }
+});
+
 document.getElementById("btn-export-diag").addEventListener("click", () => {
   const path = document.getElementById("diag-path").value.trim();
   if (!path) return;
   sendCommand({ type: "ExportDiagnostics", payload: { path } });
 });
 
+document.getElementById("btn-midi-capture").addEventListener("click", () => {
+  document.getElementById("midi-capture-status").textContent = "Capturing… play some notes.";
+  sendCommand({ type: "StartMidiCapture", payload: { seconds: 10 } });
+});
+
 document.getElementById("btn-browse-diag").addEventListener("click", async () => {
   const folder = await pickFile({
     title: "Select diagnostics folder",
@@ -1402,4 +1679,5 @@ document.getElementById("btn-load-sf2").addEventListener("click", () => {
 // Initialize
 sendCommand({ type: "ListAudioOutputs" });
 sendCommand({ type: "ListMidiInputs" });
+sendCommand({ type: "ListInternalDemos" });
 sendCommand({ type: "GetSessionState" });